@@ -1,22 +1,32 @@
 use std::borrow::BorrowMut;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
+use futures::TryStreamExt;
 use glob::PatternError;
 use rayon::prelude::*;
 
 use tokio::sync::mpsc::{Receiver, Sender};
-use tokio::sync::RwLock;
+use tokio::sync::{watch, RwLock};
 use unicase::UniCase;
 
-use bookworm_database::paginator::Selection;
-use bookworm_database::{AppDatabase, Book, BookView, DatabaseError};
+use bookworm_database::paginator::{QueryBuilder, Selection};
+use bookworm_database::search::{Search, SearchMode};
+use bookworm_database::{
+    AppDatabase, Book, BookView, DatabaseError, DelimitedFormat, DetectionStrategy, ExportFormat,
+    FtsReport, LibraryStats, MonthlyReading, VariantEditPolicy,
+};
 use bookworm_input::Edit;
 use bookworm_records::book::{BookID, ColumnIdentifier, RecordError};
-use bookworm_records::{BookError, BookVariant};
+use bookworm_records::series::Series;
+use bookworm_records::variant::{BookType, MetadataField};
+use bookworm_records::{BookError, BookVariant, Collation};
 
 use crate::columns::Columns;
+use crate::fetch::{self, FetchError};
+use crate::journal::{Change, Journal};
 use crate::parser::{ModifyColumn, Source, Target};
 
 macro_rules! async_write {
@@ -38,6 +48,7 @@ pub enum ApplicationError<DBError> {
     Book(BookError),
     Database(DatabaseError<DBError>),
     BadGlob(glob::PatternError),
+    Fetch(FetchError),
     Unknown(&'static str),
 }
 
@@ -74,24 +85,32 @@ impl<DBError> From<PatternError> for ApplicationError<DBError> {
     }
 }
 
+impl<DBError> From<FetchError> for ApplicationError<DBError> {
+    fn from(e: FetchError) -> Self {
+        ApplicationError::Fetch(e)
+    }
+}
+
+/// The number of files which are read before the books in them are passed on to be inserted.
+const SCAN_CHUNK_SIZE: usize = 512;
+
+/// The number of chunks of books which may be read ahead of the chunk being inserted.
+const SCAN_CHUNKS_AHEAD: usize = 2;
+
 // Benchmarks:
 // 5.3k books, Windows: 0.75s
 // 332 books, Linux: ~0.042s
-fn books_in_dir<P: AsRef<Path>>(dir: P, depth: u8) -> Result<Vec<BookVariant>, std::io::Error> {
-    // TODO: Handle reads erroring out due to filesystem issues somehow.
+fn paths_in_dir<P: AsRef<Path>>(dir: P, depth: u8) -> Result<Vec<PathBuf>, std::io::Error> {
     Ok(jwalk::WalkDir::new(std::fs::canonicalize(dir)?)
         .max_depth(depth as usize)
         .into_iter()
         .filter_map(Result::ok)
+        .filter(|e| !e.file_type().is_dir())
         .map(|e| e.path())
-        .collect::<Vec<_>>()
-        .par_iter()
-        .filter_map(|path| BookVariant::from_path(path).ok())
-        .collect::<Vec<_>>())
+        .collect())
 }
 
-fn books_globbed<S: AsRef<str>>(glob: S) -> Result<Vec<BookVariant>, glob::PatternError> {
-    // TODO: Handle reads erroring out due to filesystem issues somehow.
+fn paths_globbed<S: AsRef<str>>(glob: S) -> Result<Vec<PathBuf>, glob::PatternError> {
     // TODO: Measure how well this performs - solutions for std::fs::canonicalize?
     // TODO: Create a new, better glob that does stuff like take AsRef<str> and AsRef<OsStr>
     //  and does parallelism like jwalk.
@@ -101,20 +120,553 @@ fn books_globbed<S: AsRef<str>>(glob: S) -> Result<Vec<BookVariant>, glob::Patte
         .collect::<Vec<_>>()
         .par_iter()
         .filter_map(|path| std::fs::canonicalize(path).ok())
-        .filter_map(|path| BookVariant::from_path(path).ok())
-        .collect::<Vec<_>>())
+        .collect())
+}
+
+/// How far reading the files of a directory has progressed.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Progress {
+    /// The number of files which have been read.
+    pub processed: usize,
+    /// The number of files to read, which is zero until every file has been found.
+    pub total: usize,
+    /// The file which was read most recently.
+    pub current: Option<PathBuf>,
+}
+
+/// An update about a directory being added with `AppChannel::add_dir`.
+#[derive(Debug)]
+pub enum ImportEvent {
+    Progress(Progress),
+    /// Every book was read and inserted, with the given IDs.
+    Done(Vec<BookID>),
+}
+
+/// Reads the books in `source` on a blocking thread, and returns a channel which receives the
+/// books in chunks as they are read, so that earlier chunks can be inserted while later files
+/// are parsed. The files of each chunk are parsed in parallel on rayon's thread pool, which has
+/// a thread for each core, and reading stops once `SCAN_CHUNKS_AHEAD` chunks are waiting to be
+/// received, so that books are not held in memory faster than they are inserted.
+///
+/// If `progress` is provided, it is updated as each file is read. If `serial` is set, the files
+/// of each chunk are parsed one at a time, in order, on the blocking thread instead.
+fn scan_books(
+    source: Source,
+    progress: Option<watch::Sender<Progress>>,
+    serial: bool,
+) -> Receiver<Vec<BookVariant>> {
+    let (tx, rx) = tokio::sync::mpsc::channel(SCAN_CHUNKS_AHEAD);
+    tokio::task::spawn_blocking(move || {
+        // TODO: Handle reads erroring out due to filesystem issues somehow.
+        let paths = match source {
+            Source::File(path) => vec![path],
+            Source::Dir(dir, depth) => paths_in_dir(dir, depth).unwrap_or_default(),
+            Source::Glob(glob) => paths_globbed(glob).unwrap_or_default(),
+        };
+        let total = paths.len();
+        let processed = AtomicUsize::new(0);
+        let report = |read, current: Option<&PathBuf>| {
+            if let Some(progress) = &progress {
+                let _ = progress.send(Progress {
+                    processed: read,
+                    total,
+                    current: current.cloned(),
+                });
+            }
+        };
+        report(0, None);
+        let read = |path: &PathBuf| {
+            let book = BookVariant::from_path(path).ok();
+            report(processed.fetch_add(1, Ordering::Relaxed) + 1, Some(path));
+            book
+        };
+        for chunk in paths.chunks(SCAN_CHUNK_SIZE) {
+            let books: Vec<_> = if serial {
+                chunk.iter().filter_map(read).collect()
+            } else {
+                chunk.par_iter().filter_map(read).collect()
+            };
+            // The receiver is dropped if inserting the books fails.
+            if !books.is_empty() && tx.blocking_send(books).is_err() {
+                break;
+            }
+        }
+    });
+    rx
 }
 
-/// Returns the first available path amongst the variants of the book, or None if no such
-/// path exists.
+/// Returns the path of the variant at `index`, or None if no such variant exists. If no index
+/// is given, the variant is chosen by `format_order`.
 ///
 /// # Arguments
 ///
 /// * ` book ` - The book to find a path for.
-fn get_book_path(book: &Book, index: usize) -> Option<&Path> {
+/// * ` index ` - The index of the variant to find a path for.
+/// * ` format_order ` - The formats to choose first, if no index is given.
+fn get_book_path<'a>(
+    book: &'a Book,
+    index: Option<usize>,
+    format_order: &[BookType],
+) -> Option<&'a Path> {
+    let index = index.or_else(|| book.variant_by_format(format_order))?;
     Some(book.variants().get(index)?.path())
 }
 
+/// Returns the directory which contains the preferred variant of the book, or None if the
+/// book has no variants.
+fn get_book_folder(book: &Book) -> Option<&Path> {
+    book.preferred_variant()?
+        .path()
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+}
+
+/// Renames the file of `variant` to "Author - Title.ext" in its current directory, and
+/// updates the path of the variant. If another file already has the new name, a numeric
+/// suffix is appended to the name. Variants without a title are not renamed.
+///
+/// # Arguments
+///
+/// * ` variant ` - The variant to rename.
+///
+/// # Errors
+/// If renaming the file fails, an error will be returned, and the variant is not modified.
+fn rename_in_place(variant: &mut BookVariant) -> Result<(), std::io::Error> {
+    let title = match &variant.local_title {
+        Some(title) => title,
+        None => return Ok(()),
+    };
+    let stem = match variant.additional_authors.as_deref() {
+        Some([author, ..]) => format!("{} - {}", author, title),
+        _ => title.to_string(),
+    };
+    let stem: String = stem
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c => c,
+        })
+        .collect();
+    let ext = variant
+        .path
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+
+    let mut target = variant.path.with_file_name(format!("{}{}", stem, ext));
+    let mut suffix = 1;
+    while target != variant.path && target.exists() {
+        target = variant
+            .path
+            .with_file_name(format!("{} ({}){}", stem, suffix, ext));
+        suffix += 1;
+    }
+
+    if target != variant.path {
+        std::fs::rename(&variant.path, &target)?;
+        variant.path = target;
+    }
+    Ok(())
+}
+
+/// Renames the files of newly inserted variants to "Author - Title.ext" in their directories,
+/// as `rename_in_place` does, and updates the stored paths of the variants. Only the variants
+/// of the given books which were read from one of `paths` are renamed - duplicates which were
+/// skipped or attached to existing books keep their names.
+///
+/// # Arguments
+///
+/// * ` db ` - The database the variants were inserted into.
+/// * ` ids ` - The IDs of the books which were created for the variants.
+/// * ` paths ` - The paths the inserted variants were read from.
+///
+/// # Errors
+/// If reading the books or updating the paths fails, an error will be returned, and the files
+/// are moved back to their original paths.
+async fn rename_inserted<D: AppDatabase + Send + Sync>(
+    db: &RwLock<D>,
+    ids: &[BookID],
+    paths: &[PathBuf],
+) -> Result<(), DatabaseError<D::Error>> {
+    let paths: HashSet<_> = paths.iter().collect();
+    let books = db.read().await.get_books(ids).await?;
+    let mut moves = vec![];
+    for (id, book) in &books {
+        for variant in book.variants() {
+            if !paths.contains(&variant.path) {
+                continue;
+            }
+            let mut renamed = variant.clone();
+            if rename_in_place(&mut renamed).is_ok() && renamed.path != variant.path {
+                moves.push((*id, variant.path.clone(), renamed.path));
+            }
+        }
+    }
+    if moves.is_empty() {
+        return Ok(());
+    }
+
+    let moved = db.write().await.move_variants(&moves).await;
+    if moved.is_err() {
+        for (_, from, to) in &moves {
+            let _ = std::fs::rename(to, from);
+        }
+    }
+    moved
+}
+
+/// Matches `name` against `pattern`, where `pattern` consists of literal text and fields of
+/// the form `{field}`. Each field matches the shortest non-empty text which is followed by the
+/// literal text after the field, and the last field matches the remainder of `name`. Returns each
+/// field name with its trimmed value, or None if `name` does not match the pattern.
+///
+/// # Arguments
+///
+/// * ` pattern ` - The pattern to match, eg. "{series} - {index} - {title}".
+/// * ` name ` - The text to match against.
+fn match_filename_pattern<'p>(pattern: &'p str, name: &str) -> Option<Vec<(&'p str, String)>> {
+    let mut fields = vec![];
+    let mut pattern = pattern;
+    let mut rest = name;
+    loop {
+        let start = match pattern.find('{') {
+            Some(start) => start,
+            None => return if rest == pattern { Some(fields) } else { None },
+        };
+        rest = rest.strip_prefix(&pattern[..start])?;
+        let end = start + pattern[start..].find('}')?;
+        let field = &pattern[start + 1..end];
+        pattern = &pattern[end + 1..];
+
+        let literal = &pattern[..pattern.find('{').unwrap_or(pattern.len())];
+        let value_end = if literal.len() == pattern.len() {
+            rest.strip_suffix(literal)?.len()
+        } else if literal.is_empty() {
+            // Adjacent fields can not be separated.
+            return None;
+        } else {
+            rest.find(literal)?
+        };
+        let value = rest[..value_end].trim();
+        if value.is_empty() {
+            return None;
+        }
+        fields.push((field, value.to_string()));
+        rest = &rest[value_end..];
+    }
+}
+
+/// Returns the edits which set the series, series index and title of `book` from the file name
+/// of its preferred variant, as read using `pattern`. Fields other than "series", "index" and
+/// "title" are ignored. If the file name does not match the pattern, returns None.
+///
+/// # Arguments
+///
+/// * ` book ` - The book to edit.
+/// * ` pattern ` - The pattern to read the file name with - see `match_filename_pattern`.
+fn filename_edits(book: &Book, pattern: &str) -> Option<Box<[(ColumnIdentifier, Edit)]>> {
+    let name = book.preferred_variant()?.path().file_stem()?.to_str()?;
+    let mut series = None;
+    let mut index = None;
+    let mut edits = vec![];
+    for (field, value) in match_filename_pattern(pattern, name)? {
+        match field {
+            "series" => series = Some(value),
+            "index" => index = value.parse::<f32>().ok(),
+            "title" => edits.push((ColumnIdentifier::Title, Edit::Replace(value))),
+            _ => {}
+        }
+    }
+
+    // If only the index is provided, it applies to the current series of the book.
+    let name = series.or_else(|| index.and(book.series().map(|s| s.name.clone())));
+    if let Some(name) = name {
+        let series = Series { name, index };
+        edits.push((ColumnIdentifier::Series, Edit::Replace(series.to_string())));
+    }
+
+    if edits.is_empty() {
+        None
+    } else {
+        Some(edits.into_boxed_slice())
+    }
+}
+
+/// Returns the edits which update `book` with the metadata read from `scanned`, a fresh read
+/// of one of its files. Only the fields which are read from files of the scanned format are
+/// updated. If `overwrite` is false, only columns which are currently empty are filled in -
+/// otherwise, the existing values are replaced, discarding any manual edits.
+///
+/// # Arguments
+///
+/// * ` book ` - The book to edit.
+/// * ` scanned ` - The variant which was freshly read from the book's file.
+/// * ` overwrite ` - Whether to replace existing values.
+fn rescan_edits(
+    book: &Book,
+    scanned: &BookVariant,
+    overwrite: bool,
+) -> Option<Box<[(ColumnIdentifier, Edit)]>> {
+    let fields = scanned.book_type().metadata_fields();
+    let mut edits = vec![];
+
+    if fields.contains(&MetadataField::Title) {
+        if let Some(title) = &scanned.local_title {
+            if book
+                .title()
+                .map_or(true, |t| overwrite && t != title.as_str())
+            {
+                edits.push((ColumnIdentifier::Title, Edit::Replace(title.clone())));
+            }
+        }
+    }
+
+    if fields.contains(&MetadataField::Authors) {
+        if let Some(authors) = &scanned.additional_authors {
+            let current = book.authors().unwrap_or_default();
+            let replace = if current.is_empty() {
+                !authors.is_empty()
+            } else {
+                overwrite && current != authors.as_slice()
+            };
+            if replace {
+                edits.push((ColumnIdentifier::Author, Edit::Delete));
+                edits.extend(
+                    authors
+                        .iter()
+                        .map(|author| (ColumnIdentifier::Author, Edit::Append(author.clone()))),
+                );
+            }
+        }
+    }
+
+    if fields.contains(&MetadataField::Description) {
+        if let Some(description) = &scanned.description {
+            if book
+                .description()
+                .map_or(true, |d| overwrite && d != description)
+            {
+                edits.push((
+                    ColumnIdentifier::Description,
+                    Edit::Replace(description.clone()),
+                ));
+            }
+        }
+    }
+
+    if fields.contains(&MetadataField::Series) {
+        if let Some(series) = &scanned.series {
+            if book.series().map_or(true, |s| overwrite && s != series) {
+                edits.push((ColumnIdentifier::Series, Edit::Replace(series.to_string())));
+            }
+        }
+    }
+
+    if edits.is_empty() {
+        None
+    } else {
+        Some(edits.into_boxed_slice())
+    }
+}
+
+/// Returns the edits which bring the title and authors of `book` up to date with `scanned`, the
+/// preferred variant of the book as read again from its file. The title is only replaced if it
+/// changed in the file since `stored` was read, so that titles edited in bookworm are kept.
+/// Authors are not stored per variant, so they are replaced whenever they differ from the
+/// authors of the book. Tags are never edited. Returns None if neither column changed.
+///
+/// # Arguments
+///
+/// * ` book ` - The book to edit.
+/// * ` stored ` - The preferred variant of the book, as stored in the database.
+/// * ` scanned ` - The variant which was freshly read from the book's file.
+fn refresh_edits(
+    book: &Book,
+    stored: &BookVariant,
+    scanned: &BookVariant,
+) -> Option<Box<[(ColumnIdentifier, Edit)]>> {
+    let mut edits = vec![];
+
+    if let Some(title) = &scanned.local_title {
+        if stored.local_title.as_ref() != Some(title) && book.title() != Some(title.as_str()) {
+            edits.push((ColumnIdentifier::Title, Edit::Replace(title.clone())));
+        }
+    }
+
+    if let Some(authors) = &scanned.additional_authors {
+        if !authors.is_empty() && book.authors().unwrap_or_default() != authors.as_slice() {
+            edits.push((ColumnIdentifier::Author, Edit::Delete));
+            edits.extend(
+                authors
+                    .iter()
+                    .map(|author| (ColumnIdentifier::Author, Edit::Append(author.clone()))),
+            );
+        }
+    }
+
+    if edits.is_empty() {
+        None
+    } else {
+        Some(edits.into_boxed_slice())
+    }
+}
+
+/// Splits `s` into words as a POSIX shell would, without expanding anything - words are
+/// separated by whitespace, which is kept inside single or double quotes, and a backslash
+/// outside of single quotes escapes the following character.
+fn split_shell_words(s: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some('\''), '\'') | (Some('"'), '"') => quote = None,
+            (Some('"'), '\\') | (None, '\\') => {
+                if let Some(escaped) = chars.next() {
+                    word.get_or_insert_with(String::new).push(escaped);
+                }
+            }
+            (Some(_), c) => word.get_or_insert_with(String::new).push(c),
+            (None, '\'') | (None, '"') => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, c) => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    words
+}
+
+/// Returns a command which opens `path` with `app`. If `app` is the path of an existing file,
+/// it is the program to run, even if it contains whitespace. Otherwise, `app` is split into
+/// words as a shell would, with the first word being the program to run, and the remaining
+/// words being passed as arguments before the path. If `app` is empty, returns None.
+///
+/// # Arguments
+///
+/// * ` app ` - The application to open the path with, and any arguments it requires.
+/// * ` path ` - The path to open.
+fn open_with_command(app: &str, path: &Path) -> Option<std::process::Command> {
+    let app = app.trim();
+    if !app.is_empty() && Path::new(app).is_file() {
+        let mut command = std::process::Command::new(app);
+        command.arg(path);
+        return Some(command);
+    }
+    let mut words = split_shell_words(app).into_iter();
+    let mut command = std::process::Command::new(words.next()?);
+    command.args(words).arg(path);
+    Some(command)
+}
+
+/// Normalizes an author's name for comparison, ignoring case and whitespace - eg.
+/// "J.R.R. Tolkien" and "J. R. R. Tolkien" have the same normalized name.
+fn normalize_author(author: &str) -> String {
+    author
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Normalizes an author's name more loosely than `normalize_author`, also ignoring punctuation -
+/// eg. "J.R.R. Tolkien" and "JRR Tolkien" have the same loosely normalized name. As this can
+/// join distinct authors, these near-matches are only consolidated when confirmed.
+fn loosely_normalize_author(author: &str) -> String {
+    author
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Groups `authors` by their names as normalized by `normalize`, and returns the (from, to)
+/// renames which consolidate each group to its most common spelling. Ties are broken by
+/// choosing the lexicographically smallest spelling.
+///
+/// # Arguments
+///
+/// * ` authors ` - Each author, and the number of books they authored.
+/// * ` normalize ` - Returns the name which authors are grouped by.
+fn author_renames(
+    authors: Vec<(String, usize)>,
+    normalize: fn(&str) -> String,
+) -> Vec<(String, String)> {
+    let mut groups: HashMap<String, Vec<(String, usize)>> = HashMap::new();
+    for (author, count) in authors {
+        groups
+            .entry(normalize(&author))
+            .or_default()
+            .push((author, count));
+    }
+
+    let mut renames = vec![];
+    for (_, mut spellings) in groups {
+        if spellings.len() < 2 {
+            continue;
+        }
+        spellings.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+        let mut spellings = spellings.into_iter().map(|(author, _)| author);
+        let canonical = spellings.next().expect("groups are non-empty");
+        renames.extend(spellings.map(|author| (author, canonical.clone())));
+    }
+    renames.sort();
+    renames
+}
+
+/// Reads a tag mapping from the file at the provided path. Each non-empty line which does not
+/// start with `#` consists of a key and one or more tags, separated by the delimiter of `format`.
+/// Keys of the form `column=value` match books where the column is exactly equal to value -
+/// otherwise, the key is matched against the title.
+///
+/// # Arguments
+///
+/// * ` path ` - The path to the mapping file.
+/// * ` format ` - The delimiter and text encoding of the mapping file.
+///
+/// # Errors
+/// If reading the file fails, an error will be returned.
+fn read_tag_mapping<P: AsRef<Path>>(
+    path: P,
+    format: &DelimitedFormat,
+) -> Result<Vec<(Search, Vec<String>)>, std::io::Error> {
+    let bytes = std::fs::read(path)?;
+    let (contents, _, _) = format.encoding.decode(&bytes);
+    Ok(contents
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split(format.delimiter);
+            let key = fields.next()?.trim();
+            let tags: Vec<_> = fields
+                .map(str::trim)
+                .filter(|tag| !tag.is_empty())
+                .map(String::from)
+                .collect();
+            if tags.is_empty() {
+                return None;
+            }
+            let (column, search) = match key.split_once('=') {
+                Some((column, value)) if !column.contains(char::is_whitespace) => {
+                    (ColumnIdentifier::from(column), value)
+                }
+                _ => (ColumnIdentifier::Title, key),
+            };
+            Some((
+                Search {
+                    mode: SearchMode::ExactString,
+                    column,
+                    search: search.to_string(),
+                },
+                tags,
+            ))
+        })
+        .collect())
+}
+
 pub enum AppTask {
     Save,
     IsSaved,
@@ -126,10 +678,33 @@ pub enum AppTask {
     EditBooks(Box<[BookID]>, Box<[(ColumnIdentifier, Edit)]>),
     EditSelection(Selection, Box<[(ColumnIdentifier, Edit)]>),
     AddBooks(Box<[Source]>),
+    AddDir(PathBuf, u8),
     UpdateBooks(Box<[Source]>),
     TryMergeAllBooks,
+    FindDuplicates(DetectionStrategy),
+    ImportTags(PathBuf, DelimitedFormat),
+    ImportDb(PathBuf),
+    SetPreferredVariant(BookID, usize),
+    DedupeAuthors(bool),
+    ColumnStats(ColumnIdentifier),
+    ReadingStats,
+    LibraryStats,
+    Export(ExportFormat, PathBuf),
+    SeriesFromFilename(Selection, String),
+    ClearTags(Selection),
+    RenameTag(String, String),
+    DeleteTag(String),
+    Rescan(Selection, bool),
+    Refresh(Selection),
+    Fetch(Selection, bool),
+    GetBook(BookID),
+    RebuildFts,
+    Undo,
+    Redo,
+    #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
+    OpenBookIn(BookID, Option<usize>, Target),
     #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
-    OpenBookIn(BookID, usize, Target),
+    OpenFolder(BookID),
 }
 
 pub enum AppResponse<D: AppDatabase + 'static> {
@@ -139,23 +714,57 @@ pub enum AppResponse<D: AppDatabase + 'static> {
     BookView(BookView<D>),
     // DeleteMatching
     Deleted(HashSet<BookID>),
-    // AddBooks
+    // AddBooks, AddDir
     Created(Vec<BookID>),
+    // AddDir, before the books are created
+    Progress(Progress),
     // Delete these ids, and refresh ids from DB
     MergeRefresh(HashSet<BookID>),
+    // FindDuplicates
+    Duplicates(Vec<Vec<Arc<Book>>>),
+    // ColumnStats
+    ColumnStats(Vec<(String, usize)>),
+    // ReadingStats
+    ReadingStats(Vec<MonthlyReading>),
+    // LibraryStats
+    LibraryStats(Option<LibraryStats>),
+    // Export
+    Exported(Option<usize>),
+    // GetBook
+    Book(Option<Arc<Book>>),
+    // RebuildFts
+    FtsReport(Option<FtsReport>),
+    // Undo, Redo, DeleteSelected
+    Changed(bool),
+    // Refresh
+    Refreshed(usize),
+    // Fetch
+    Fetched(Option<usize>),
+    // RenameTag, DeleteTag
+    TagsChanged(Option<usize>),
+    // DedupeAuthors
+    NearMatches(Option<usize>),
     Empty,
 }
 
 pub struct App<D: AppDatabase + 'static> {
     db: Arc<RwLock<D>>,
     updated: bool,
+    variant_edits: VariantEditPolicy,
+    rename_in_place: bool,
+    delete_empty_books: bool,
+    format_order: Vec<BookType>,
+    safe_mode: bool,
+    journal: Journal,
     event_receiver: Receiver<AppTask>,
     result_sender: Sender<AppResponse<D>>,
+    cancel_import: watch::Receiver<bool>,
 }
 
 pub struct AppChannel<D: AppDatabase + 'static> {
     sender: Sender<AppTask>,
     receiver: Arc<RwLock<Receiver<AppResponse<D>>>>,
+    cancel_import: watch::Sender<bool>,
 }
 
 impl<D: AppDatabase + Send + Sync> AppChannel<D> {
@@ -167,11 +776,12 @@ impl<D: AppDatabase + Send + Sync> AppChannel<D> {
         self.receiver.as_ref().write().await.recv().await
     }
 
-    pub async fn delete_selected(&self, selected: Selection) {
+    /// Deletes the selected books, and returns whether the deletion succeeded.
+    pub async fn delete_selected(&self, selected: Selection) -> bool {
         self.send(AppTask::DeleteSelected(selected)).await;
         match self.receive().await.unwrap() {
-            AppResponse::Empty => {}
-            _ => panic!("Expected AppResponse::Empty response from application"),
+            AppResponse::Changed(deleted) => deleted,
+            _ => panic!("Expected AppResponse::Changed response from application"),
         }
     }
 
@@ -223,6 +833,16 @@ impl<D: AppDatabase + Send + Sync> AppChannel<D> {
         }
     }
 
+    /// Opens the directory which contains the preferred variant of the book in the file manager.
+    #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
+    pub async fn open_folder(&self, id: BookID) {
+        self.send(AppTask::OpenFolder(id)).await;
+        match self.receive().await.unwrap() {
+            AppResponse::Empty => {}
+            _ => panic!("Expected Empty response from application"),
+        }
+    }
+
     pub async fn edit_selected(
         &self,
         selection: Selection,
@@ -235,6 +855,29 @@ impl<D: AppDatabase + Send + Sync> AppChannel<D> {
         }
     }
 
+    /// Starts adding the books in `dir`, up to `depth` directories deep. Until `import_event`
+    /// returns `ImportEvent::Done`, no other tasks may be sent.
+    pub async fn add_dir(&self, dir: PathBuf, depth: u8) {
+        let _ = self.cancel_import.send(false);
+        self.send(AppTask::AddDir(dir, depth)).await;
+    }
+
+    /// Stops adding the books of the directory being added with `add_dir` once the books which
+    /// are being inserted have been inserted. `import_event` then returns `ImportEvent::Done`
+    /// with the IDs of the books which were added.
+    pub fn cancel_import(&self) {
+        let _ = self.cancel_import.send(true);
+    }
+
+    /// Returns the next update about the directory being added with `add_dir`.
+    pub async fn import_event(&self) -> ImportEvent {
+        match self.receive().await.unwrap() {
+            AppResponse::Progress(progress) => ImportEvent::Progress(progress),
+            AppResponse::Created(ids) => ImportEvent::Done(ids),
+            _ => panic!("Expected Progress or Created response from application"),
+        }
+    }
+
     pub async fn add_books(&self, sources: Box<[Source]>) -> Vec<BookID> {
         self.send(AppTask::AddBooks(sources)).await;
         match self.receive().await.unwrap() {
@@ -252,7 +895,7 @@ impl<D: AppDatabase + Send + Sync> AppChannel<D> {
     }
 
     #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
-    pub async fn open_book(&self, id: BookID, index: usize, target: Target) {
+    pub async fn open_book(&self, id: BookID, index: Option<usize>, target: Target) {
         self.send(AppTask::OpenBookIn(id, index, target)).await;
         match self.receive().await.unwrap() {
             AppResponse::Empty => {}
@@ -260,14 +903,205 @@ impl<D: AppDatabase + Send + Sync> AppChannel<D> {
         }
     }
 
-    pub async fn try_merge_all_books(&self) -> HashSet<BookID> {
+    /// Merges similar books, and returns the IDs of the books which were merged into others, or
+    /// None if merging failed.
+    pub async fn try_merge_all_books(&self) -> Option<HashSet<BookID>> {
         self.send(AppTask::TryMergeAllBooks).await;
         match self.receive().await.unwrap() {
-            AppResponse::MergeRefresh(book_ids) => book_ids,
+            AppResponse::MergeRefresh(book_ids) => Some(book_ids),
+            AppResponse::Empty => None,
+            _ => panic!("Expected MergeRefresh response from application"),
+        }
+    }
+
+    pub async fn import_tags(&self, path: PathBuf, format: DelimitedFormat) {
+        self.send(AppTask::ImportTags(path, format)).await;
+        match self.receive().await.unwrap() {
+            AppResponse::Empty => {}
+            _ => panic!("Expected Empty response from application"),
+        }
+    }
+
+    pub async fn import_db(&self, path: PathBuf) -> Vec<BookID> {
+        self.send(AppTask::ImportDb(path)).await;
+        match self.receive().await.unwrap() {
+            AppResponse::Created(result) => result,
+            _ => panic!("Expected Created response from application"),
+        }
+    }
+
+    pub async fn set_preferred_variant(&self, id: BookID, index: usize) {
+        self.send(AppTask::SetPreferredVariant(id, index)).await;
+        match self.receive().await.unwrap() {
+            AppResponse::Empty => {}
+            _ => panic!("Expected Empty response from application"),
+        }
+    }
+
+    /// Consolidates spelling variants of each author, including near-matches if `confirmed`.
+    /// Returns the number of near-matching spellings which were left unconsolidated, or None
+    /// if consolidating the authors failed.
+    pub async fn dedupe_authors(&self, confirmed: bool) -> Option<usize> {
+        self.send(AppTask::DedupeAuthors(confirmed)).await;
+        match self.receive().await.unwrap() {
+            AppResponse::NearMatches(near_matches) => near_matches,
+            _ => panic!("Expected NearMatches response from application"),
+        }
+    }
+
+    pub async fn series_from_filename(&self, selection: Selection, pattern: String) {
+        self.send(AppTask::SeriesFromFilename(selection, pattern))
+            .await;
+        match self.receive().await.unwrap() {
+            AppResponse::Empty => {}
+            _ => panic!("Expected Empty response from application"),
+        }
+    }
+
+    pub async fn clear_tags(&self, selection: Selection) {
+        self.send(AppTask::ClearTags(selection)).await;
+        match self.receive().await.unwrap() {
+            AppResponse::Empty => {}
+            _ => panic!("Expected Empty response from application"),
+        }
+    }
+
+    /// Renames the tag `old` to `new` in every book, and returns the number of tags which were
+    /// renamed, or None if renaming failed.
+    pub async fn rename_tag(&self, old: String, new: String) -> Option<usize> {
+        self.send(AppTask::RenameTag(old, new)).await;
+        match self.receive().await.unwrap() {
+            AppResponse::TagsChanged(renamed) => renamed,
+            _ => panic!("Expected TagsChanged response from application"),
+        }
+    }
+
+    /// Removes the tag `name` from every book, and returns the number of tags which were
+    /// removed, or None if removing the tag failed.
+    pub async fn delete_tag(&self, name: String) -> Option<usize> {
+        self.send(AppTask::DeleteTag(name)).await;
+        match self.receive().await.unwrap() {
+            AppResponse::TagsChanged(deleted) => deleted,
+            _ => panic!("Expected TagsChanged response from application"),
+        }
+    }
+
+    pub async fn rescan(&self, selection: Selection, overwrite: bool) {
+        self.send(AppTask::Rescan(selection, overwrite)).await;
+        match self.receive().await.unwrap() {
+            AppResponse::Empty => {}
             _ => panic!("Expected Empty response from application"),
         }
     }
 
+    /// Reads the files of the selected books again, updating books with files which changed
+    /// since they were added, and returns the number of books which were updated.
+    pub async fn refresh(&self, selection: Selection) -> usize {
+        self.send(AppTask::Refresh(selection)).await;
+        match self.receive().await.unwrap() {
+            AppResponse::Refreshed(refreshed) => refreshed,
+            _ => panic!("Expected Refreshed response from application"),
+        }
+    }
+
+    /// Fills in the metadata of the selected books which have an ISBN from Open Library, and
+    /// returns the number of books which were updated. If `overwrite` is false, only empty
+    /// columns are filled in. If no book could be looked up, None is returned.
+    pub async fn fetch(&self, selection: Selection, overwrite: bool) -> Option<usize> {
+        self.send(AppTask::Fetch(selection, overwrite)).await;
+        match self.receive().await.unwrap() {
+            AppResponse::Fetched(fetched) => fetched,
+            _ => panic!("Expected Fetched response from application"),
+        }
+    }
+
+    /// Returns each value of `column`, with the number of books which have that value, from
+    /// the most to the least common value.
+    pub async fn column_stats(&self, column: ColumnIdentifier) -> Vec<(String, usize)> {
+        self.send(AppTask::ColumnStats(column)).await;
+        match self.receive().await.unwrap() {
+            AppResponse::ColumnStats(stats) => stats,
+            _ => panic!("Expected ColumnStats response from application"),
+        }
+    }
+
+    /// Returns the number of books added and finished in each month, from the oldest to the
+    /// newest month.
+    pub async fn reading_stats(&self) -> Vec<MonthlyReading> {
+        self.send(AppTask::ReadingStats).await;
+        match self.receive().await.unwrap() {
+            AppResponse::ReadingStats(stats) => stats,
+            _ => panic!("Expected ReadingStats response from application"),
+        }
+    }
+
+    /// Returns a summary of the library, or None if it could not be computed.
+    pub async fn library_stats(&self) -> Option<LibraryStats> {
+        self.send(AppTask::LibraryStats).await;
+        match self.receive().await.unwrap() {
+            AppResponse::LibraryStats(stats) => stats,
+            _ => panic!("Expected LibraryStats response from application"),
+        }
+    }
+
+    /// Writes every book in the database to the file at `path`, in the given format, and
+    /// returns the number of books written. If the export fails, None is returned.
+    pub async fn export(&self, format: ExportFormat, path: PathBuf) -> Option<usize> {
+        self.send(AppTask::Export(format, path)).await;
+        match self.receive().await.unwrap() {
+            AppResponse::Exported(written) => written,
+            _ => panic!("Expected Exported response from application"),
+        }
+    }
+
+    /// Returns groups of books which are duplicates of each other, as determined by
+    /// `strategy`, without merging them.
+    pub async fn find_duplicates(&self, strategy: DetectionStrategy) -> Vec<Vec<Arc<Book>>> {
+        self.send(AppTask::FindDuplicates(strategy)).await;
+        match self.receive().await.unwrap() {
+            AppResponse::Duplicates(groups) => groups,
+            _ => panic!("Expected Duplicates response from application"),
+        }
+    }
+
+    /// Returns the book with the given ID, if it exists.
+    pub async fn get_book(&self, id: BookID) -> Option<Arc<Book>> {
+        self.send(AppTask::GetBook(id)).await;
+        match self.receive().await.unwrap() {
+            AppResponse::Book(book) => book,
+            _ => panic!("Expected Book response from application"),
+        }
+    }
+
+    /// Undoes the most recent edit or deletion which has not been undone, and returns whether
+    /// there was a change to undo.
+    pub async fn undo(&self) -> bool {
+        self.send(AppTask::Undo).await;
+        match self.receive().await.unwrap() {
+            AppResponse::Changed(changed) => changed,
+            _ => panic!("Expected Changed response from application"),
+        }
+    }
+
+    /// Redoes the most recently undone change, and returns whether there was a change to redo.
+    pub async fn redo(&self) -> bool {
+        self.send(AppTask::Redo).await;
+        match self.receive().await.unwrap() {
+            AppResponse::Changed(changed) => changed,
+            _ => panic!("Expected Changed response from application"),
+        }
+    }
+
+    /// Rebuilds the full-text index, and returns a report describing the rebuilt index. If
+    /// full-text search is not supported, or rebuilding the index fails, None is returned.
+    pub async fn rebuild_fts(&self) -> Option<FtsReport> {
+        self.send(AppTask::RebuildFts).await;
+        match self.receive().await.unwrap() {
+            AppResponse::FtsReport(report) => report,
+            _ => panic!("Expected FtsReport response from application"),
+        }
+    }
+
     pub async fn modify_columns(
         &self,
         columns: Box<[ModifyColumn]>,
@@ -296,33 +1130,75 @@ impl<D: AppDatabase + Send + Sync> App<D> {
     pub fn new(db: D) -> (Self, AppChannel<D>) {
         let (event_sender, event_receiver) = tokio::sync::mpsc::channel(100);
         let (result_sender, result_receiver) = tokio::sync::mpsc::channel(100);
+        let (cancel_sender, cancel_receiver) = watch::channel(false);
 
         (
             App {
                 db: Arc::new(RwLock::new(db)),
                 updated: true,
+                variant_edits: VariantEditPolicy::default(),
+                rename_in_place: false,
+                delete_empty_books: true,
+                format_order: Vec::new(),
+                safe_mode: false,
+                journal: Journal::default(),
                 event_receiver,
                 result_sender,
+                cancel_import: cancel_receiver,
             },
             AppChannel {
                 sender: event_sender,
                 receiver: Arc::new(RwLock::new(result_receiver)),
+                cancel_import: cancel_sender,
             },
         )
     }
 
-    pub async fn db_path(&self) -> std::path::PathBuf {
-        self.db.read().await.path().to_path_buf()
+    /// Sets which variants bulk edits of per-variant columns apply to.
+    pub fn set_variant_edit_policy(&mut self, policy: VariantEditPolicy) {
+        self.variant_edits = policy;
     }
 
-    /// Returns a BookView, allowing reads of all available books.
-    pub async fn new_book_view(&self) -> BookView<D> {
-        BookView::new(self.db.clone()).await
+    /// Sets whether added books are renamed to "Author - Title.ext" in their current directory.
+    pub fn set_rename_in_place(&mut self, rename_in_place: bool) {
+        self.rename_in_place = rename_in_place;
     }
 
-    /// Applies the specified edits to the provided book in the internal
-    /// database.
-    ///
+    /// Sets whether books which are left without variants after merging or updating books
+    /// are removed from the database.
+    pub fn set_delete_empty_books(&mut self, delete_empty_books: bool) {
+        self.delete_empty_books = delete_empty_books;
+    }
+
+    /// Sets the formats which are opened first when a book is opened without choosing a
+    /// variant, from the most to the least preferred.
+    pub fn set_format_order(&mut self, format_order: Vec<BookType>) {
+        self.format_order = format_order;
+    }
+
+    /// Sets whether background tasks are disabled - in safe mode, book views never prefetch
+    /// books in the background, so that all reads happen in response to user input, and the
+    /// files of added directories are parsed one at a time.
+    pub fn set_safe_mode(&mut self, safe_mode: bool) {
+        self.safe_mode = safe_mode;
+    }
+
+    pub async fn db_path(&self) -> std::path::PathBuf {
+        self.db.read().await.path().to_path_buf()
+    }
+
+    /// Returns a BookView, allowing reads of all available books.
+    pub async fn new_book_view(&self) -> BookView<D> {
+        let mut book_view = BookView::new(self.db.clone()).await;
+        if self.safe_mode {
+            book_view.set_prefetch(false);
+        }
+        book_view
+    }
+
+    /// Applies the specified edits to the provided book in the internal
+    /// database.
+    ///
     /// # Errors
     /// If no book is selected, or if editing the book fails, an error will be returned.
     async fn edit_book_with_id(
@@ -337,14 +1213,511 @@ impl<D: AppDatabase + Send + Sync> App<D> {
         ))
     }
 
+    /// Removes the books with the given IDs, keeping a copy of each so that the deletion can
+    /// be undone.
+    ///
+    /// # Errors
+    /// If reading or removing the books fails, an error will be returned.
     async fn remove_books(
         &mut self,
         ids: &HashSet<BookID>,
     ) -> Result<(), ApplicationError<D::Error>> {
+        let snapshot = {
+            let ids: Vec<_> = ids.iter().cloned().collect();
+            self.db.read().await.snapshot_books(&ids).await?
+        };
         async_write!(self, db, db.remove_books(ids).await)?;
+        self.journal.record(Change::Deleted(snapshot));
+        Ok(())
+    }
+
+    /// Removes the selected books, keeping a copy of each so that the deletion can be undone.
+    ///
+    /// # Errors
+    /// If reading or removing the selected books fails, an error will be returned.
+    async fn remove_selected(
+        &mut self,
+        selection: &Selection,
+    ) -> Result<(), ApplicationError<D::Error>> {
+        let snapshot = {
+            let ids: Vec<_> = self
+                .read_selection(selection)
+                .await?
+                .iter()
+                .map(|book| book.id())
+                .collect();
+            self.db.read().await.snapshot_books(&ids).await?
+        };
+        async_write!(self, db, db.remove_selected(selection).await)?;
+        self.journal.record(Change::Deleted(snapshot));
+        Ok(())
+    }
+
+    /// Applies the edits to each book with the given ID, keeping a copy of each book as it was
+    /// before the edits so that they can be undone.
+    ///
+    /// # Errors
+    /// If reading the books fails, an error will be returned. Books which can not be edited are
+    /// skipped.
+    async fn edit_books(
+        &mut self,
+        ids: &[BookID],
+        edits: Box<[(ColumnIdentifier, Edit)]>,
+    ) -> Result<(), ApplicationError<D::Error>> {
+        let books = self.db.read().await.get_books(ids).await?;
+        let mut edited = Vec::with_capacity(books.len());
+        for (id, book) in books {
+            if self.edit_book_with_id(id, &edits).await.is_ok() {
+                edited.push(book);
+            }
+        }
+        self.journal.record(Change::Edited {
+            books: edited,
+            edits,
+            policy: VariantEditPolicy::AllVariants,
+        });
+        Ok(())
+    }
+
+    /// Applies the edits to the selected books, keeping a copy of each book as it was before
+    /// the edits so that they can be undone.
+    ///
+    /// # Errors
+    /// If reading or editing the selected books fails, an error will be returned.
+    async fn edit_selected(
+        &mut self,
+        selection: &Selection,
+        edits: Box<[(ColumnIdentifier, Edit)]>,
+    ) -> Result<(), ApplicationError<D::Error>> {
+        let books = self.read_selection(selection).await?;
+        let policy = self.variant_edits;
+        async_write!(self, db, db.edit_selected(selection, &edits, policy).await)?;
+        self.journal.record(Change::Edited {
+            books,
+            edits,
+            policy,
+        });
         Ok(())
     }
 
+    /// Undoes the most recent edit or deletion which has not been undone, by writing back the
+    /// books it affected as they were before the change. Returns whether there was a change
+    /// to undo.
+    ///
+    /// # Errors
+    /// If restoring the books fails, an error will be returned, and the change can be undone
+    /// again.
+    pub async fn undo(&mut self) -> Result<bool, ApplicationError<D::Error>> {
+        let change = match self.journal.pop_undo() {
+            Some(change) => change,
+            None => return Ok(false),
+        };
+        let result = match &change {
+            Change::Deleted(snapshot) => {
+                async_write!(self, db, db.restore_snapshot(snapshot).await)
+            }
+            Change::Edited { books, .. } => async_write!(self, db, db.restore_books(books).await),
+        };
+        match result {
+            Ok(()) => {
+                self.journal.push_redo(change);
+                Ok(true)
+            }
+            Err(e) => {
+                self.journal.push_undo(change);
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Redoes the most recently undone change, by deleting or editing the same books again.
+    /// Returns whether there was a change to redo.
+    ///
+    /// # Errors
+    /// If deleting or editing the books fails, an error will be returned, and the change can
+    /// be redone again.
+    pub async fn redo(&mut self) -> Result<bool, ApplicationError<D::Error>> {
+        let change = match self.journal.pop_redo() {
+            Some(change) => change,
+            None => return Ok(false),
+        };
+        let result = match &change {
+            Change::Deleted(_) => {
+                let ids = change.ids().into_iter().collect();
+                async_write!(self, db, db.remove_books(&ids).await)
+            }
+            Change::Edited {
+                books,
+                edits,
+                policy,
+            } => {
+                // The copies match the current state of the books, as the change was undone.
+                let selection = Selection::Partial(
+                    books.iter().map(|book| (book.id(), book.clone())).collect(),
+                    Box::default(),
+                    Collation::CaseInsensitive,
+                );
+                async_write!(self, db, db.edit_selected(&selection, edits, *policy).await)
+            }
+        };
+        match result {
+            Ok(()) => {
+                self.journal.push_undo(change);
+                Ok(true)
+            }
+            Err(e) => {
+                self.journal.push_redo(change);
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Reads the tag mapping at the provided path, and appends the mapped tags to all books
+    /// matching each key. Tags are applied in a single batch, and tags which a book already has
+    /// are skipped.
+    ///
+    /// # Errors
+    /// If reading the mapping fails, or if editing the books fails, an error will be returned.
+    async fn import_tags(
+        &mut self,
+        path: &Path,
+        format: &DelimitedFormat,
+    ) -> Result<(), ApplicationError<D::Error>> {
+        let mapping = read_tag_mapping(path, format)?;
+        let mut tagged: HashMap<BookID, Vec<String>> = HashMap::new();
+        {
+            let db = self.db.read().await;
+            let builder = QueryBuilder::default().full_text_search(db.supports_full_text_search());
+            for (search, tags) in mapping {
+                let matcher = search
+                    .into_matcher()
+                    .map_err(|_| ApplicationError::Unknown("Invalid key in tag mapping"))?;
+                let (query, bound_variables) =
+                    builder.join_cols(None, &vec![matcher].into_boxed_slice());
+                for book in db.read_selected_books(&query, &bound_variables).await? {
+                    let book_tags = tagged.entry(book.id()).or_default();
+                    for tag in &tags {
+                        if !book.free_tags.contains(tag) && !book_tags.contains(tag) {
+                            book_tags.push(tag.clone());
+                        }
+                    }
+                }
+            }
+        }
+
+        let edits: Vec<_> = tagged
+            .into_iter()
+            .filter(|(_, tags)| !tags.is_empty())
+            .map(|(id, tags)| {
+                let edits: Box<[_]> = tags
+                    .into_iter()
+                    .map(|tag| (ColumnIdentifier::Tags, Edit::Append(tag)))
+                    .collect();
+                (id, edits)
+            })
+            .collect();
+
+        if !edits.is_empty() {
+            async_write!(self, db, db.edit_many(&edits).await)?;
+        }
+        Ok(())
+    }
+
+    /// Looks up each selected book with an ISBN on Open Library, filling in its title, authors,
+    /// description and cover. Returns the number of books which were updated. Books which can
+    /// not be looked up are skipped.
+    ///
+    /// # Errors
+    /// Will return an error if the database fails, or if books were looked up, but every lookup
+    /// failed.
+    async fn fetch(
+        &mut self,
+        selection: &Selection,
+        overwrite: bool,
+    ) -> Result<usize, ApplicationError<D::Error>> {
+        let books = self.read_selection(selection).await?;
+        let mut edits = vec![];
+        let mut covers = vec![];
+        let mut error = None;
+        for book in &books {
+            let isbn = match fetch::isbn(book) {
+                Some(isbn) => isbn,
+                None => continue,
+            };
+            let fetched = match fetch::lookup(&isbn).await {
+                Ok(Some(fetched)) => fetched,
+                Ok(None) => continue,
+                Err(e) => {
+                    error = Some(e);
+                    continue;
+                }
+            };
+            if let Some(book_edits) = fetch::fetch_edits(book, &fetched, overwrite) {
+                edits.push((book.id(), book_edits));
+            }
+            if let Some(url) = fetched.cover_url.filter(|_| overwrite || !book.has_cover) {
+                match fetch::cover(&url).await {
+                    Ok(image) => covers.push((book.id(), image)),
+                    Err(e) => error = Some(e),
+                }
+            }
+        }
+
+        // Individual lookups may fail, but if nothing could be fetched, the failure is reported.
+        if let Some(e) = error {
+            if edits.is_empty() && covers.is_empty() {
+                return Err(e.into());
+            }
+        }
+        let mut updated: HashSet<_> = edits.iter().map(|(id, _)| *id).collect();
+        if !edits.is_empty() {
+            async_write!(self, db, db.edit_many(&edits).await)?;
+        }
+        for (id, image) in covers {
+            async_write!(self, db, db.set_cover(id, &image).await)?;
+            updated.insert(id);
+        }
+        Ok(updated.len())
+    }
+
+    /// Copies every book in the database at the provided path into the current database,
+    /// including its variants, series and tags. Books are inserted according to the insert
+    /// policy, which decides what happens to books whose files are already in the current
+    /// database. The source database is copied and brought up to date before it is read, so
+    /// that databases created by older versions can be imported, and is never modified.
+    ///
+    /// # Errors
+    /// If copying or reading the source database fails, or if inserting the books fails, an
+    /// error will be returned.
+    async fn import_db(&mut self, path: &Path) -> Result<Vec<BookID>, ApplicationError<D::Error>> {
+        const BATCH_SIZE: usize = 1000;
+
+        // Changes which were not yet written back to the source are kept in its write-ahead log.
+        let dir = tempfile::tempdir()?;
+        let copy = dir.path().join("import.db");
+        std::fs::copy(path, &copy)?;
+        let mut wal = path.as_os_str().to_owned();
+        wal.push("-wal");
+        if Path::new(&wal).exists() {
+            std::fs::copy(&wal, dir.path().join("import.db-wal"))?;
+        }
+        let source = D::open(&copy).await?;
+
+        let mut books = source.stream_books();
+        let mut batch = Vec::with_capacity(BATCH_SIZE);
+        let mut ids = vec![];
+        while let Some(book) = books.try_next().await? {
+            batch.push(book);
+            if batch.len() == BATCH_SIZE {
+                ids.extend(async_write!(self, db, db.import_books(&batch).await)?);
+                batch.clear();
+            }
+        }
+        if !batch.is_empty() {
+            ids.extend(async_write!(self, db, db.import_books(&batch).await)?);
+        }
+        Ok(ids)
+    }
+
+    /// Consolidates spelling variants of each author (eg. "J.R.R. Tolkien" and
+    /// "J. R. R. Tolkien") to the most common spelling, across all books. Near-matches which
+    /// only differ in punctuation (eg. "JRR Tolkien") are also consolidated if `confirmed`, and
+    /// otherwise, the number of near-matching spellings which remain is returned.
+    ///
+    /// # Errors
+    /// If reading or renaming the authors fails, an error will be returned.
+    async fn dedupe_authors(
+        &mut self,
+        confirmed: bool,
+    ) -> Result<usize, ApplicationError<D::Error>> {
+        let normalize = if confirmed {
+            loosely_normalize_author
+        } else {
+            normalize_author
+        };
+        let authors = self.db.read().await.authors().await?;
+        let renames = author_renames(authors, normalize);
+        if !renames.is_empty() {
+            async_write!(self, db, db.rename_authors(&renames).await)?;
+        }
+        if confirmed {
+            return Ok(0);
+        }
+        let authors = self.db.read().await.authors().await?;
+        Ok(author_renames(authors, loosely_normalize_author).len())
+    }
+
+    /// Returns groups of books which are duplicates of each other, as determined by
+    /// `strategy`. Books are not modified.
+    ///
+    /// # Errors
+    /// If finding or reading the duplicate books fails, an error will be returned.
+    async fn find_duplicates(
+        &self,
+        strategy: DetectionStrategy,
+    ) -> Result<Vec<Vec<Arc<Book>>>, ApplicationError<D::Error>> {
+        let db = self.db.read().await;
+        let groups = db.find_duplicates(strategy).await?;
+        let ids: Vec<_> = groups.iter().flatten().cloned().collect();
+        let books = db.get_books(&ids).await?;
+        Ok(groups
+            .into_iter()
+            .map(|group| {
+                group
+                    .iter()
+                    .filter_map(|id| books.get(id).cloned())
+                    .collect()
+            })
+            .collect())
+    }
+
+    /// Returns all books contained in the provided selection, in no particular order.
+    ///
+    /// # Errors
+    /// If reading the selected books fails, an error will be returned.
+    async fn read_selection(
+        &self,
+        selection: &Selection,
+    ) -> Result<Vec<Arc<Book>>, ApplicationError<D::Error>> {
+        let db = self.db.read().await;
+        let builder = QueryBuilder::default().full_text_search(db.supports_full_text_search());
+        let (query, bound_variables) = match selection {
+            Selection::Partial(books, _, _) => return Ok(books.values().cloned().collect()),
+            Selection::All(match_rules) => builder.join_cols(None, match_rules),
+            Selection::Range(start, end, cmp_rules, _, match_rules, collation) => builder
+                .cmp_rules(cmp_rules)
+                .collation(*collation)
+                .include_id(true)
+                .between_books(start, end, match_rules),
+            Selection::Empty => return Ok(vec![]),
+        };
+        Ok(db.read_selected_books(&query, &bound_variables).await?)
+    }
+
+    /// Removes all tags other than authors from each selected book.
+    ///
+    /// # Errors
+    /// If reading the selected books or removing their tags fails, an error will be returned.
+    async fn clear_tags(
+        &mut self,
+        selection: &Selection,
+    ) -> Result<(), ApplicationError<D::Error>> {
+        let ids: Vec<_> = self
+            .read_selection(selection)
+            .await?
+            .iter()
+            .map(|book| book.id())
+            .collect();
+        if !ids.is_empty() {
+            async_write!(self, db, db.clear_tags(&ids).await)?;
+        }
+        Ok(())
+    }
+
+    /// Sets the series, series index and title of each selected book from the file name of its
+    /// preferred variant, as read using `pattern`. Books with file names which do not match
+    /// the pattern are not modified.
+    ///
+    /// # Errors
+    /// If reading or editing the selected books fails, an error will be returned.
+    async fn series_from_filename(
+        &mut self,
+        selection: &Selection,
+        pattern: &str,
+    ) -> Result<(), ApplicationError<D::Error>> {
+        let books = self.read_selection(selection).await?;
+        let edits: Vec<_> = books
+            .iter()
+            .filter_map(|book| Some((book.id(), filename_edits(book, pattern)?)))
+            .collect();
+        if !edits.is_empty() {
+            async_write!(self, db, db.edit_many(&edits).await)?;
+        }
+        Ok(())
+    }
+
+    /// Reads the preferred variant of each selected book from disk again, and updates the
+    /// title, authors and description of the book with the values read. If `overwrite` is
+    /// false, only empty columns are filled in. Books with files which can not be read are
+    /// not modified.
+    ///
+    /// # Errors
+    /// If reading or editing the selected books fails, an error will be returned.
+    async fn rescan(
+        &mut self,
+        selection: &Selection,
+        overwrite: bool,
+    ) -> Result<(), ApplicationError<D::Error>> {
+        let books = self.read_selection(selection).await?;
+        let edits: Vec<_> = books
+            .iter()
+            .filter_map(|book| {
+                let scanned = BookVariant::from_path(book.preferred_variant()?.path()).ok()?;
+                Some((book.id(), rescan_edits(book, &scanned, overwrite)?))
+            })
+            .collect();
+        if !edits.is_empty() {
+            async_write!(self, db, db.edit_many(&edits).await)?;
+        }
+        Ok(())
+    }
+
+    /// Reads the file of each variant of the selected books again, and picks up the changes to
+    /// files which were modified since they were added, as detected by their size and hash.
+    /// The stored metadata of each modified variant, including its description, is replaced
+    /// with the values read, and the title and authors of the book are updated from its
+    /// preferred variant using `refresh_edits`. Tags and unmodified files are left as they are,
+    /// as are files which can not be read. Returns the number of books which were updated.
+    ///
+    /// # Errors
+    /// If reading or editing the selected books fails, an error will be returned.
+    async fn refresh(
+        &mut self,
+        selection: &Selection,
+    ) -> Result<usize, ApplicationError<D::Error>> {
+        let books = self.read_selection(selection).await?;
+        let mut variants = vec![];
+        let mut edits = vec![];
+        let mut refreshed = HashSet::new();
+        for book in &books {
+            for (index, stored) in book.variants().iter().enumerate() {
+                let scanned = match BookVariant::from_path(stored.path()) {
+                    Ok(scanned) => scanned,
+                    Err(_) => continue,
+                };
+                if scanned.file_size == stored.file_size && scanned.hash == stored.hash {
+                    continue;
+                }
+                if index == 0 {
+                    if let Some(book_edits) = refresh_edits(book, stored, &scanned) {
+                        edits.push((book.id(), book_edits));
+                    }
+                }
+                refreshed.insert(book.id());
+                variants.push((book.id(), scanned));
+            }
+        }
+
+        if !variants.is_empty() {
+            async_write!(self, db, db.refresh_variants(&variants).await)?;
+        }
+        if !edits.is_empty() {
+            async_write!(self, db, db.edit_many(&edits).await)?;
+        }
+        Ok(refreshed.len())
+    }
+
+    /// Removes the books which have no variants, if enabled, and returns their IDs.
+    ///
+    /// # Errors
+    /// If removing the books fails, an error will be returned.
+    async fn remove_empty_books(&mut self) -> Result<HashSet<BookID>, DatabaseError<D::Error>> {
+        if !self.delete_empty_books {
+            return Ok(HashSet::new());
+        }
+        async_write!(self, db, db.remove_empty_books().await)
+    }
+
     /// Saves the internal database to disk. Note that with SQLite, all operations are saved
     /// immediately.
     ///
@@ -368,7 +1741,7 @@ impl<D: AppDatabase + Send + Sync> App<D> {
                 #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
                 AppTask::OpenBookIn(book, index, target) => {
                     if let Ok(book) = self.db.read().await.get_book(book).await {
-                        if let Some(path) = get_book_path(&book, index) {
+                        if let Some(path) = get_book_path(&book, index, &self.format_order) {
                             match target {
                                 Target::FileManager => {
                                     let _ = opener::open_in_file_manager(path);
@@ -376,36 +1749,44 @@ impl<D: AppDatabase + Send + Sync> App<D> {
                                 Target::DefaultApp => {
                                     let _ = opener::open(path);
                                 }
+                                Target::App(app) => {
+                                    if let Some(mut command) = open_with_command(&app, path) {
+                                        let _ = command.spawn();
+                                    }
+                                }
                             }
                         }
                     }
 
                     AppResponse::Empty
                 }
+                #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
+                AppTask::OpenFolder(book) => {
+                    if let Ok(book) = self.db.read().await.get_book(book).await {
+                        if let Some(dir) = get_book_folder(&book) {
+                            let _ = opener::open(dir);
+                        }
+                    }
+
+                    AppResponse::Empty
+                }
                 AppTask::DeleteIds(ids) => {
                     let _ = self.remove_books(&ids).await;
                     AppResponse::Empty
                 }
                 AppTask::DeleteSelected(selection) => {
-                    let _ = self.db.write().await.remove_selected(&selection).await;
-                    self.register_update();
-                    AppResponse::Empty
+                    AppResponse::Changed(self.remove_selected(&selection).await.is_ok())
                 }
                 AppTask::EditBooks(books, edits) => {
-                    for book in books.to_vec().into_iter() {
-                        let _ = self.edit_book_with_id(book, &edits).await;
-                    }
+                    let _ = self.edit_books(&books, edits).await;
                     AppResponse::Empty
                 }
                 AppTask::EditSelection(selection, edits) => {
-                    let _ = self
-                        .db
-                        .write()
-                        .await
-                        .edit_selected(&selection, &edits)
-                        .await;
+                    let _ = self.edit_selected(&selection, edits).await;
                     AppResponse::Empty
                 }
+                AppTask::Undo => AppResponse::Changed(self.undo().await.unwrap_or(false)),
+                AppTask::Redo => AppResponse::Changed(self.redo().await.unwrap_or(false)),
                 AppTask::AddBooks(sources) => {
                     // TODO: Handle failed reads.
                     // TODO: Provide feedback about duplicated books
@@ -417,40 +1798,44 @@ impl<D: AppDatabase + Send + Sync> App<D> {
                         match source {
                             Source::File(f) => {
                                 if let Ok(book) = BookVariant::from_path(&f) {
-                                    if let Ok(id) =
-                                        async_write!(self, db, db.insert_book(book).await)
-                                    {
-                                        ids.push(id);
-                                    }
-                                }
-                            }
-                            Source::Dir(dir, depth) => {
-                                if let Ok(books) = books_in_dir(&dir, depth) {
-                                    let db = self.db.clone();
-                                    futs.push(tokio::spawn(async move {
-                                        if let Ok(items) =
-                                            db.write().await.insert_books(books.into_iter()).await
-                                        {
-                                            items
-                                        } else {
-                                            vec![]
+                                    let path = book.path.clone();
+                                    if let Ok(items) = async_write!(
+                                        self,
+                                        db,
+                                        db.insert_books(std::iter::once(book)).await
+                                    ) {
+                                        if self.rename_in_place {
+                                            let _ =
+                                                rename_inserted(&self.db, &items, &[path]).await;
                                         }
-                                    }));
+                                        ids.extend(items);
+                                    }
                                 }
                             }
-                            Source::Glob(glob) => {
-                                if let Ok(books) = books_globbed(&glob) {
-                                    let db = self.db.clone();
-                                    futs.push(tokio::spawn(async move {
-                                        if let Ok(items) =
-                                            db.write().await.insert_books(books.into_iter()).await
-                                        {
-                                            items
-                                        } else {
-                                            vec![]
+                            source => {
+                                let mut chunks = scan_books(source, None, self.safe_mode);
+                                let rename = self.rename_in_place;
+                                let db = self.db.clone();
+                                futs.push(tokio::spawn(async move {
+                                    let mut ids = vec![];
+                                    while let Some(books) = chunks.recv().await {
+                                        let paths: Vec<_> =
+                                            books.iter().map(|book| book.path.clone()).collect();
+                                        let inserted =
+                                            db.write().await.insert_books(books.into_iter()).await;
+                                        match inserted {
+                                            Ok(items) => {
+                                                if rename {
+                                                    let _ =
+                                                        rename_inserted(&db, &items, &paths).await;
+                                                }
+                                                ids.extend(items)
+                                            }
+                                            Err(_) => break,
                                         }
-                                    }));
-                                }
+                                    }
+                                    ids
+                                }));
                             }
                         }
                     }
@@ -459,8 +1844,6 @@ impl<D: AppDatabase + Send + Sync> App<D> {
                     // in parallel when # of sources is large - very close to real read time when
                     // reading many 100s of thousands of books.
                     // (eg. if you're reading an entire file system).
-                    // We should provide a mechanism to stream books as they're read, and then
-                    // chunk these into a transaction-sized list
                     for fut in futs {
                         if let Ok(items) = fut.await {
                             ids.extend(items);
@@ -469,6 +1852,53 @@ impl<D: AppDatabase + Send + Sync> App<D> {
 
                     AppResponse::Created(ids)
                 }
+                AppTask::AddDir(dir, depth) => {
+                    let (progress_tx, mut progress) = watch::channel(Progress::default());
+                    let mut chunks =
+                        scan_books(Source::Dir(dir, depth), Some(progress_tx), self.safe_mode);
+                    let mut cancel = self.cancel_import.clone();
+                    let mut ids = vec![];
+                    loop {
+                        tokio::select! {
+                            // Dropping the chunks stops the scan once the current chunk is read.
+                            Ok(()) = cancel.changed() => {
+                                if *cancel.borrow() {
+                                    break;
+                                }
+                            }
+                            // While a chunk is inserted, only the latest progress is kept.
+                            Ok(()) = progress.changed() => {
+                                let current = progress.borrow().clone();
+                                self.result_sender
+                                    .send(AppResponse::Progress(current))
+                                    .await
+                                    .ok();
+                            }
+                            books = chunks.recv() => match books {
+                                Some(books) => {
+                                    let paths: Vec<_> =
+                                        books.iter().map(|book| book.path.clone()).collect();
+                                    match async_write!(
+                                        self,
+                                        db,
+                                        db.insert_books(books.into_iter()).await
+                                    ) {
+                                        Ok(items) => {
+                                            if self.rename_in_place {
+                                                let _ =
+                                                    rename_inserted(&self.db, &items, &paths).await;
+                                            }
+                                            ids.extend(items)
+                                        }
+                                        Err(_) => break,
+                                    }
+                                }
+                                None => break,
+                            },
+                        }
+                    }
+                    AppResponse::Created(ids)
+                }
                 AppTask::UpdateBooks(sources) => {
                     let mut futs = vec![];
                     for source in sources.into_vec() {
@@ -483,21 +1913,25 @@ impl<D: AppDatabase + Send + Sync> App<D> {
                                 }
                             }
                             Source::Dir(dir, depth) => {
-                                if let Ok(books) = books_in_dir(&dir, depth) {
-                                    let db = self.db.clone();
-                                    futs.push(tokio::spawn(async move {
+                                let mut chunks =
+                                    scan_books(Source::Dir(dir, depth), None, self.safe_mode);
+                                let db = self.db.clone();
+                                futs.push(tokio::spawn(async move {
+                                    while let Some(books) = chunks.recv().await {
                                         let _ = db.write().await.update(books.into_iter()).await;
-                                    }));
-                                }
+                                    }
+                                }));
                             }
                             Source::Glob(glob) => {
-                                if let Ok(books) = books_globbed(&glob) {
-                                    let db = self.db.clone();
-                                    futs.push(tokio::spawn(async move {
+                                let mut chunks =
+                                    scan_books(Source::Glob(glob), None, self.safe_mode);
+                                let db = self.db.clone();
+                                futs.push(tokio::spawn(async move {
+                                    while let Some(books) = chunks.recv().await {
                                         let _ =
                                             db.write().await.insert_books(books.into_iter()).await;
-                                    }));
-                                }
+                                    }
+                                }));
                             }
                         }
                     }
@@ -505,11 +1939,93 @@ impl<D: AppDatabase + Send + Sync> App<D> {
                     for fut in futs {
                         let _ = fut.await;
                     }
+                    let _ = self.remove_empty_books().await;
+                    AppResponse::Empty
+                }
+                AppTask::ImportTags(path, format) => {
+                    let _ = self.import_tags(&path, &format).await;
+                    AppResponse::Empty
+                }
+                AppTask::ImportDb(path) => {
+                    AppResponse::Created(self.import_db(&path).await.unwrap_or_default())
+                }
+                AppTask::DedupeAuthors(confirmed) => {
+                    AppResponse::NearMatches(self.dedupe_authors(confirmed).await.ok())
+                }
+                AppTask::SeriesFromFilename(selection, pattern) => {
+                    let _ = self.series_from_filename(&selection, &pattern).await;
+                    AppResponse::Empty
+                }
+                AppTask::ClearTags(selection) => {
+                    let _ = self.clear_tags(&selection).await;
+                    AppResponse::Empty
+                }
+                AppTask::RenameTag(old, new) => AppResponse::TagsChanged(
+                    async_write!(self, db, db.rename_tag(&old, &new).await).ok(),
+                ),
+                AppTask::DeleteTag(name) => AppResponse::TagsChanged(
+                    async_write!(self, db, db.delete_tag(&name).await).ok(),
+                ),
+                AppTask::Rescan(selection, overwrite) => {
+                    let _ = self.rescan(&selection, overwrite).await;
+                    AppResponse::Empty
+                }
+                AppTask::Refresh(selection) => {
+                    AppResponse::Refreshed(self.refresh(&selection).await.unwrap_or_default())
+                }
+                AppTask::Fetch(selection, overwrite) => {
+                    AppResponse::Fetched(self.fetch(&selection, overwrite).await.ok())
+                }
+                AppTask::FindDuplicates(strategy) => AppResponse::Duplicates(
+                    self.find_duplicates(strategy).await.unwrap_or_default(),
+                ),
+                AppTask::GetBook(id) => {
+                    AppResponse::Book(self.db.read().await.get_book(id).await.ok())
+                }
+                AppTask::RebuildFts => {
+                    AppResponse::FtsReport(self.db.write().await.rebuild_fts().await.ok().flatten())
+                }
+                AppTask::ColumnStats(column) => AppResponse::ColumnStats(
+                    self.db
+                        .read()
+                        .await
+                        .column_stats(&column)
+                        .await
+                        .unwrap_or_default(),
+                ),
+                AppTask::ReadingStats => AppResponse::ReadingStats(
+                    self.db
+                        .read()
+                        .await
+                        .reading_stats()
+                        .await
+                        .unwrap_or_default(),
+                ),
+                AppTask::LibraryStats => {
+                    AppResponse::LibraryStats(self.db.read().await.library_stats().await.ok())
+                }
+                AppTask::Export(format, path) => {
+                    AppResponse::Exported(match std::fs::File::create(&path) {
+                        Ok(file) => self
+                            .db
+                            .read()
+                            .await
+                            .export(format, std::io::BufWriter::new(file))
+                            .await
+                            .ok(),
+                        Err(_) => None,
+                    })
+                }
+                AppTask::SetPreferredVariant(id, index) => {
+                    let _ = async_write!(self, db, db.set_preferred_variant(id, index).await);
                     AppResponse::Empty
                 }
                 // Add details about strategies (eg. which types of books, what to do on conflict)
                 AppTask::TryMergeAllBooks => {
-                    if let Ok(ids) = async_write!(self, db, db.merge_similar().await) {
+                    if let Ok(mut ids) = async_write!(self, db, db.merge_similar().await) {
+                        if let Ok(removed) = self.remove_empty_books().await {
+                            ids.extend(removed);
+                        }
                         AppResponse::MergeRefresh(ids)
                     } else {
                         AppResponse::Empty
@@ -533,3 +2049,831 @@ impl<D: AppDatabase + Send + Sync> App<D> {
         self.db.read().await.saved().await
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::convert::TryFrom;
+
+    use bookworm_database::test_util::RecordingDatabase;
+    use bookworm_database::{InsertPolicy, SQLiteDatabase};
+    use bookworm_records::test_util::variant;
+    use bookworm_records::variant::BookType;
+    use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+
+    #[tokio::test]
+    async fn test_safe_mode_disables_prefetch() {
+        /// Scrolls through a view of the books, and returns the LIMIT of each read.
+        async fn scroll(safe_mode: bool) -> Vec<Option<i64>> {
+            let dir = tempfile::tempdir().unwrap();
+            let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+                .await
+                .unwrap();
+            let titles: Vec<_> = (0..40).map(|i| format!("book {:02}", i)).collect();
+            db.insert_books(
+                titles
+                    .iter()
+                    .enumerate()
+                    .map(|(i, title)| variant(title, i as u8)),
+            )
+            .await
+            .unwrap();
+
+            let (mut app, _channel) = App::new(RecordingDatabase::new(db));
+            app.set_safe_mode(safe_mode);
+            let mut book_view = app.new_book_view().await;
+            book_view.refresh_window_size(5).await.unwrap();
+            book_view.scroll_down(3).await.unwrap();
+            // Scopes created after scrolling must not prefetch either.
+            book_view
+                .push_scope(&[Search {
+                    mode: SearchMode::Default,
+                    column: ColumnIdentifier::Title,
+                    search: "book".to_string(),
+                }])
+                .await
+                .unwrap();
+            book_view.refresh_window_size(5).await.unwrap();
+            book_view.scroll_down(3).await.unwrap();
+            // Give prefetches spawned in the background a chance to run.
+            for _ in 0..10 {
+                tokio::task::yield_now().await;
+            }
+            let limits = app.db.read().await.limits();
+            limits
+        }
+
+        // Prefetching reads more books than fit in the window.
+        assert!(scroll(false).await.iter().any(|&limit| limit > Some(5)));
+
+        let limits = scroll(true).await;
+        assert!(!limits.is_empty());
+        assert!(limits.iter().all(|&limit| limit <= Some(5)));
+    }
+
+    #[tokio::test]
+    async fn test_import_tags() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        let titles = ["Dune", "Emma", "Ulysses"];
+        let ids = db
+            .insert_books(
+                titles
+                    .iter()
+                    .enumerate()
+                    .map(|(i, title)| variant(title, i as u8)),
+            )
+            .await
+            .unwrap();
+
+        let mapping = dir.path().join("tags.tsv");
+        std::fs::write(
+            &mapping,
+            "# key\ttags\nDune\tscifi\tclassic\ntitle=Emma\tclassic\nMissing\tnone\n",
+        )
+        .unwrap();
+
+        let (mut app, _channel) = App::new(db);
+        app.import_tags(&mapping, &DelimitedFormat::default())
+            .await
+            .unwrap();
+
+        let db = app.db.read().await;
+        let books = db.get_books(&ids).await.unwrap();
+        let tags = |id: &BookID| {
+            let mut tags: Vec<_> = books[id].free_tags.iter().cloned().collect();
+            tags.sort();
+            tags
+        };
+        assert_eq!(tags(&ids[0]), vec!["classic", "scifi"]);
+        assert_eq!(tags(&ids[1]), vec!["classic"]);
+        assert!(tags(&ids[2]).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_import_tags_delimited() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        let ids = db
+            .insert_books(vec![variant("Les Misérables", 0)].into_iter())
+            .await
+            .unwrap();
+
+        // "Les Misérables;littérature;classique", encoded as latin-1.
+        let mapping = dir.path().join("tags.csv");
+        std::fs::write(
+            &mapping,
+            b"Les Mis\xe9rables;litt\xe9rature;classique\n".as_ref(),
+        )
+        .unwrap();
+
+        let (mut app, _channel) = App::new(db);
+        let format = DelimitedFormat {
+            delimiter: ';',
+            encoding: encoding_rs::Encoding::for_label(b"latin1").unwrap(),
+        };
+        app.import_tags(&mapping, &format).await.unwrap();
+
+        let db = app.db.read().await;
+        let mut tags: Vec<_> = db
+            .get_book(ids[0])
+            .await
+            .unwrap()
+            .free_tags
+            .iter()
+            .cloned()
+            .collect();
+        tags.sort();
+        assert_eq!(tags, vec!["classique", "littérature"]);
+    }
+
+    #[tokio::test]
+    async fn test_import_db() {
+        let dir = tempfile::tempdir().unwrap();
+        let source_path = dir.path().join("source.db");
+        {
+            let mut source = SQLiteDatabase::open(&source_path).await.unwrap();
+            let ids = source
+                .insert_books(vec![variant("Dune", 0), variant("Emma", 1)].into_iter())
+                .await
+                .unwrap();
+            source
+                .edit_book_with_id(
+                    ids[0],
+                    &[
+                        (
+                            ColumnIdentifier::Series,
+                            Edit::Replace("Dune [1]".to_string()),
+                        ),
+                        (ColumnIdentifier::Tags, Edit::Append("scifi".to_string())),
+                        (
+                            ColumnIdentifier::NamedTag("genre".to_string()),
+                            Edit::Replace("space opera".to_string()),
+                        ),
+                    ],
+                )
+                .await
+                .unwrap();
+        }
+
+        let mut db = SQLiteDatabase::open(dir.path().join("target.db"))
+            .await
+            .unwrap();
+        db.set_insert_policy(InsertPolicy::SkipDuplicates);
+        let (mut app, _channel) = App::new(db);
+        let ids = app.import_db(&source_path).await.unwrap();
+        assert_eq!(ids.len(), 2);
+
+        // Books which already exist in the target are skipped by the insert policy.
+        assert!(app.import_db(&source_path).await.unwrap().is_empty());
+
+        let db = app.db.read().await;
+        assert_eq!(
+            db.read_selected_books("SELECT book_id FROM books", &[])
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+        let books = db.get_books(&ids).await.unwrap();
+        let dune = &books[&ids[0]];
+        assert_eq!(dune.title(), Some("Dune"));
+        let series = dune.series().unwrap();
+        assert_eq!(series.name, "Dune");
+        assert_eq!(series.index, Some(1.));
+        assert!(dune.free_tags.contains("scifi"));
+        assert_eq!(
+            dune.tags().get("genre").map(String::as_str),
+            Some("space opera")
+        );
+        assert_eq!(dune.variants()[0].hash, [0; 32]);
+
+        let emma = &books[&ids[1]];
+        assert_eq!(emma.title(), Some("Emma"));
+        assert!(emma.series().is_none());
+        assert!(emma.free_tags.is_empty());
+        assert!(db
+            .has_column(&UniCase::new("genre".to_string()))
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_import_db_before_migrations() {
+        let dir = tempfile::tempdir().unwrap();
+        let source_path = dir.path().join("source.db");
+        {
+            let mut source = SQLiteDatabase::open(&source_path).await.unwrap();
+            source
+                .insert_books(vec![variant("Dune", 0)].into_iter())
+                .await
+                .unwrap();
+        }
+        // Databases created by earlier versions have no dates, or positions for variants.
+        let has_date_added =
+            "SELECT EXISTS (SELECT 1 FROM pragma_table_info('books') WHERE name = 'date_added');";
+        let source = SqlitePoolOptions::new()
+            .connect_with(SqliteConnectOptions::new().filename(&source_path))
+            .await
+            .unwrap();
+        for statement in [
+            "ALTER TABLE books DROP COLUMN date_added;",
+            "ALTER TABLE variants DROP COLUMN position;",
+        ] {
+            sqlx::query(statement).execute(&source).await.unwrap();
+        }
+
+        let db = SQLiteDatabase::open(dir.path().join("target.db"))
+            .await
+            .unwrap();
+        let (mut app, _channel) = App::new(db);
+        let ids = app.import_db(&source_path).await.unwrap();
+        assert_eq!(ids.len(), 1);
+        let book = app.db.read().await.get_book(ids[0]).await.unwrap();
+        assert_eq!(book.title(), Some("Dune"));
+        assert_eq!(book.variants().len(), 1);
+
+        // The source is read from a copy, which is migrated instead of the source.
+        let migrated: bool = sqlx::query_scalar(has_date_added)
+            .fetch_one(&source)
+            .await
+            .unwrap();
+        assert!(!migrated);
+        source.close().await;
+    }
+
+    #[tokio::test]
+    async fn test_undo_redo() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        let ids = db
+            .insert_books(vec![variant("Dune", 0), variant("Emma", 1)].into_iter())
+            .await
+            .unwrap();
+        let (mut app, _channel) = App::new(db);
+        let title = |app: &App<SQLiteDatabase>, id: BookID| {
+            let db = app.db.clone();
+            async move {
+                db.read()
+                    .await
+                    .get_book(id)
+                    .await
+                    .ok()
+                    .and_then(|book| book.title().map(str::to_string))
+            }
+        };
+        assert!(!app.undo().await.unwrap());
+
+        let selection = Selection::Partial(
+            app.db.read().await.get_books(&ids).await.unwrap(),
+            Box::default(),
+            Collation::CaseInsensitive,
+        );
+        app.edit_selected(
+            &selection,
+            vec![(ColumnIdentifier::Title, Edit::Replace("edited".to_string()))].into_boxed_slice(),
+        )
+        .await
+        .unwrap();
+        app.remove_books(&std::iter::once(ids[0]).collect())
+            .await
+            .unwrap();
+        assert_eq!(title(&app, ids[0]).await, None);
+
+        // Deleted books are restored with their original IDs.
+        assert!(app.undo().await.unwrap());
+        assert_eq!(title(&app, ids[0]).await, Some("edited".to_string()));
+        assert!(app.undo().await.unwrap());
+        assert_eq!(title(&app, ids[0]).await, Some("Dune".to_string()));
+        assert_eq!(title(&app, ids[1]).await, Some("Emma".to_string()));
+        assert!(!app.undo().await.unwrap());
+
+        assert!(app.redo().await.unwrap());
+        assert_eq!(title(&app, ids[1]).await, Some("edited".to_string()));
+        assert!(app.redo().await.unwrap());
+        assert_eq!(title(&app, ids[0]).await, None);
+        assert!(!app.redo().await.unwrap());
+
+        // New changes can not be redone after undoing.
+        assert!(app.undo().await.unwrap());
+        app.edit_books(
+            &[ids[0]],
+            vec![(ColumnIdentifier::Title, Edit::Replace("Dune".to_string()))].into_boxed_slice(),
+        )
+        .await
+        .unwrap();
+        assert!(!app.redo().await.unwrap());
+        assert!(app.undo().await.unwrap());
+        assert_eq!(title(&app, ids[0]).await, Some("edited".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_undo_delete_restores_tags_and_cover() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        let id = db.insert_book(variant("Dune", 0)).await.unwrap();
+        let genre = ColumnIdentifier::MultiMap("genre".to_string());
+        db.edit_book_with_id(id, &[(genre.clone(), Edit::Append("scifi".to_string()))])
+            .await
+            .unwrap();
+        db.set_cover(id, &[1, 2, 3]).await.unwrap();
+        let (mut app, _channel) = App::new(db);
+
+        app.remove_books(&std::iter::once(id).collect())
+            .await
+            .unwrap();
+        assert!(app
+            .db
+            .read()
+            .await
+            .column_stats(&genre)
+            .await
+            .unwrap()
+            .is_empty());
+
+        assert!(app.undo().await.unwrap());
+        let db = app.db.read().await;
+        assert_eq!(db.get_book(id).await.unwrap().title(), Some("Dune"));
+        assert_eq!(
+            db.column_stats(&genre).await.unwrap(),
+            vec![("scifi".to_string(), 1)]
+        );
+        assert_eq!(db.get_cover(id).await.unwrap(), Some(vec![1, 2, 3]));
+    }
+
+    #[tokio::test]
+    async fn test_dedupe_authors() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        let book_authors: [&[&str]; 4] = [
+            &["J.R.R. Tolkien"],
+            &["J. R. R. Tolkien"],
+            &["J.R.R. Tolkien", "J. R. R. Tolkien"],
+            &["J.R.R. Tolkien", "Christopher Tolkien"],
+        ];
+        let ids = db
+            .insert_books(book_authors.iter().enumerate().map(|(i, authors)| {
+                let mut variant = variant(&format!("Book {}", i), i as u8);
+                variant.additional_authors =
+                    Some(authors.iter().map(|author| author.to_string()).collect());
+                variant
+            }))
+            .await
+            .unwrap();
+
+        let (mut app, _channel) = App::new(db);
+        assert_eq!(app.dedupe_authors(false).await.unwrap(), 0);
+
+        let db = app.db.read().await;
+        assert_eq!(
+            db.authors().await.unwrap(),
+            vec![
+                ("Christopher Tolkien".to_string(), 1),
+                ("J.R.R. Tolkien".to_string(), 4)
+            ]
+        );
+        let books = db.get_books(&ids).await.unwrap();
+        for id in &ids[..3] {
+            assert_eq!(
+                books[id].authors(),
+                Some(&["J.R.R. Tolkien".to_string()][..])
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dedupe_authors_near_matches() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        let authors = [
+            "J.R.R. Tolkien",
+            "J.R.R. Tolkien",
+            "JRR Tolkien",
+            "Ursula Le Guin",
+        ];
+        db.insert_books(authors.iter().enumerate().map(|(i, author)| {
+            let mut variant = variant(&format!("Book {}", i), i as u8);
+            variant.additional_authors = Some(vec![author.to_string()]);
+            variant
+        }))
+        .await
+        .unwrap();
+        let (mut app, _channel) = App::new(db);
+
+        // Near-matches are only reported until they are confirmed.
+        assert_eq!(app.dedupe_authors(false).await.unwrap(), 1);
+        assert_eq!(app.db.read().await.authors().await.unwrap().len(), 3);
+
+        assert_eq!(app.dedupe_authors(true).await.unwrap(), 0);
+        assert_eq!(
+            app.db.read().await.authors().await.unwrap(),
+            vec![
+                ("J.R.R. Tolkien".to_string(), 3),
+                ("Ursula Le Guin".to_string(), 1)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_scan_books() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("nested");
+        std::fs::create_dir(&nested).unwrap();
+        let count = SCAN_CHUNK_SIZE + 10;
+        for i in 0..count {
+            let dir = if i % 2 == 0 {
+                dir.path()
+            } else {
+                nested.as_path()
+            };
+            std::fs::write(dir.join(format!("{}.epub", i)), b"").unwrap();
+        }
+        std::fs::write(dir.path().join("notes.md"), b"").unwrap();
+
+        // Books are received in chunks, and files which are not books are skipped.
+        let mut chunks = scan_books(Source::Dir(dir.path().to_path_buf(), 2), None, false);
+        let mut sizes = vec![];
+        let mut titles = HashSet::new();
+        while let Some(books) = chunks.recv().await {
+            sizes.push(books.len());
+            titles.extend(books.into_iter().filter_map(|book| book.local_title));
+        }
+        assert_eq!(sizes.len(), 2);
+        assert!(sizes.iter().all(|&size| size <= SCAN_CHUNK_SIZE));
+        assert_eq!(titles.len(), count);
+        assert!(titles.contains("0.epub"));
+
+        // Only the top level of the directory is read with a depth of 1.
+        let mut chunks = scan_books(Source::Dir(dir.path().to_path_buf(), 1), None, false);
+        let mut read = 0;
+        while let Some(books) = chunks.recv().await {
+            read += books.len();
+        }
+        assert_eq!(read, count / 2);
+
+        // Parsing files serially reads the same books, in the order they were found.
+        let paths = paths_in_dir(dir.path(), 1).unwrap();
+        let mut chunks = scan_books(Source::Dir(dir.path().to_path_buf(), 1), None, true);
+        let mut read = vec![];
+        while let Some(books) = chunks.recv().await {
+            read.extend(books.into_iter().map(|book| book.path));
+        }
+        let books: Vec<_> = paths
+            .into_iter()
+            .filter(|path| path.extension().map_or(false, |ext| ext == "epub"))
+            .collect();
+        assert_eq!(read, books);
+    }
+
+    #[tokio::test]
+    async fn test_add_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let books = dir.path().join("books");
+        std::fs::create_dir(&books).unwrap();
+        for i in 0..5 {
+            std::fs::write(books.join(format!("{}.epub", i)), i.to_string()).unwrap();
+        }
+        let db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        let (mut app, channel) = App::new(db);
+        tokio::spawn(async move {
+            let _ = app.event_loop().await;
+        });
+
+        channel.add_dir(books, 1).await;
+        let ids = loop {
+            match channel.import_event().await {
+                // Progress is only reported once every file has been found.
+                ImportEvent::Progress(progress) => {
+                    assert_eq!(progress.total, 5);
+                    assert!(progress.processed <= progress.total);
+                }
+                ImportEvent::Done(ids) => break ids,
+            }
+        };
+        assert_eq!(ids.len(), 5);
+        assert!(channel.take_update().await);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_import() {
+        let dir = tempfile::tempdir().unwrap();
+        let books = dir.path().join("books");
+        std::fs::create_dir(&books).unwrap();
+        for i in 0..5 {
+            std::fs::write(books.join(format!("{}.epub", i)), i.to_string()).unwrap();
+        }
+        let db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        let (mut app, channel) = App::new(db);
+
+        // The import is cancelled before any of the files can be read.
+        channel.add_dir(books.clone(), 1).await;
+        channel.cancel_import();
+        tokio::spawn(async move {
+            let _ = app.event_loop().await;
+        });
+        let ids = loop {
+            if let ImportEvent::Done(ids) = channel.import_event().await {
+                break ids;
+            }
+        };
+        assert!(ids.is_empty());
+
+        // Later imports are not cancelled.
+        channel.add_dir(books, 1).await;
+        let ids = loop {
+            if let ImportEvent::Done(ids) = channel.import_event().await {
+                break ids;
+            }
+        };
+        assert_eq!(ids.len(), 5);
+    }
+
+    #[test]
+    fn test_rename_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut renamed = vec![];
+        for (i, name) in ["dune.epub", "dune (copy).epub"].iter().enumerate() {
+            let path = dir.path().join(name);
+            std::fs::write(&path, b"").unwrap();
+            let mut book = variant("Dune", i as u8);
+            book.path = path.clone();
+            book.additional_authors = Some(vec!["Frank Herbert".to_string()]);
+
+            rename_in_place(&mut book).unwrap();
+            assert!(!path.exists());
+            assert!(book.path.exists());
+            assert_eq!(book.hash, [i as u8; 32]);
+            renamed.push(book.path);
+        }
+
+        assert_eq!(
+            renamed,
+            vec![
+                dir.path().join("Frank Herbert - Dune.epub"),
+                dir.path().join("Frank Herbert - Dune (1).epub"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rename_inserted() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        db.set_insert_policy(InsertPolicy::SkipDuplicates);
+        let db = RwLock::new(db);
+
+        let mut books = vec![];
+        let mut paths = vec![];
+        for name in ["dune.epub", "dune (copy).epub"].iter() {
+            let path = dir.path().join(name);
+            std::fs::write(&path, b"").unwrap();
+            let mut book = variant("Dune", 0);
+            book.path = path.clone();
+            book.additional_authors = Some(vec!["Frank Herbert".to_string()]);
+            books.push(book);
+            paths.push(path);
+        }
+        // The copy has the same hash, so it is skipped rather than inserted.
+        let ids = db
+            .write()
+            .await
+            .insert_books(books.into_iter())
+            .await
+            .unwrap();
+        assert_eq!(ids.len(), 1);
+
+        rename_inserted(&db, &ids, &paths).await.unwrap();
+
+        let renamed = dir.path().join("Frank Herbert - Dune.epub");
+        assert!(!paths[0].exists());
+        assert!(renamed.exists());
+        // Skipped duplicates keep their name.
+        assert!(paths[1].exists());
+
+        let books = db.read().await.get_books(&ids[..1]).await.unwrap();
+        let variants = books[&ids[0]].variants();
+        assert_eq!(variants.len(), 1);
+        assert_eq!(variants[0].path, renamed);
+    }
+
+    #[test]
+    fn test_filename_edits() {
+        let pattern = "{series} - {index} - {title}";
+        assert_eq!(
+            match_filename_pattern(pattern, "The Expanse - 2 - Caliban's War"),
+            Some(vec![
+                ("series", "The Expanse".to_string()),
+                ("index", "2".to_string()),
+                ("title", "Caliban's War".to_string()),
+            ])
+        );
+        assert_eq!(match_filename_pattern(pattern, "Caliban's War"), None);
+        assert_eq!(
+            match_filename_pattern("[{series} {index}] {title}", "[Dune 1.5] Dune - Messiah"),
+            Some(vec![
+                ("series", "Dune".to_string()),
+                ("index", "1.5".to_string()),
+                ("title", "Dune - Messiah".to_string()),
+            ])
+        );
+
+        let book = |path: &str| {
+            let mut variant = variant("", 0);
+            variant.path = PathBuf::from(path);
+            Book {
+                variants: vec![variant],
+                ..Default::default()
+            }
+        };
+        assert_eq!(
+            filename_edits(&book("books/Dune - 1 - Dune.epub"), pattern),
+            Some(
+                vec![
+                    (ColumnIdentifier::Title, Edit::Replace("Dune".to_string())),
+                    (
+                        ColumnIdentifier::Series,
+                        Edit::Replace("Dune [1]".to_string())
+                    ),
+                ]
+                .into_boxed_slice()
+            )
+        );
+        assert_eq!(
+            filename_edits(&book("books/Dune - Messiah.epub"), "{series} - {title}"),
+            Some(
+                vec![
+                    (
+                        ColumnIdentifier::Title,
+                        Edit::Replace("Messiah".to_string())
+                    ),
+                    (ColumnIdentifier::Series, Edit::Replace("Dune".to_string())),
+                ]
+                .into_boxed_slice()
+            )
+        );
+        assert_eq!(filename_edits(&book("books/Dune.epub"), pattern), None);
+    }
+
+    #[test]
+    fn test_rescan_edits() {
+        let mut scanned = variant("Dune", 0);
+        scanned.additional_authors = Some(vec!["Frank Herbert".to_string()]);
+
+        let mut book = Book::from_variant(BookID::try_from(1).unwrap(), scanned.clone());
+        book.title = Some("My Dune".to_string());
+        assert_eq!(rescan_edits(&book, &scanned, false), None);
+        assert_eq!(
+            rescan_edits(&book, &scanned, true),
+            Some(
+                vec![(ColumnIdentifier::Title, Edit::Replace("Dune".to_string()))]
+                    .into_boxed_slice()
+            )
+        );
+
+        book.title = None;
+        book.authors = None;
+        assert_eq!(
+            rescan_edits(&book, &scanned, false),
+            Some(
+                vec![
+                    (ColumnIdentifier::Title, Edit::Replace("Dune".to_string())),
+                    (ColumnIdentifier::Author, Edit::Delete),
+                    (
+                        ColumnIdentifier::Author,
+                        Edit::Append("Frank Herbert".to_string())
+                    ),
+                ]
+                .into_boxed_slice()
+            )
+        );
+
+        // Titles and authors are also read from PDF files.
+        let expected = rescan_edits(&book, &scanned, false);
+        scanned.book_type = BookType::PDF;
+        assert_eq!(rescan_edits(&book, &scanned, false), expected);
+
+        // Series are only read from EPUB files.
+        book.title = Some("Dune".to_string());
+        book.authors = scanned.additional_authors.clone();
+        scanned.series = Some(Series {
+            name: "Dune Chronicles".to_string(),
+            index: Some(1.),
+        });
+        assert_eq!(rescan_edits(&book, &scanned, false), None);
+        scanned.book_type = BookType::EPUB;
+        assert_eq!(
+            rescan_edits(&book, &scanned, false),
+            Some(
+                vec![(
+                    ColumnIdentifier::Series,
+                    Edit::Replace("Dune Chronicles [1]".to_string())
+                )]
+                .into_boxed_slice()
+            )
+        );
+    }
+
+    #[test]
+    fn test_refresh_edits() {
+        let stored = variant("Dune", 0);
+        let mut book = Book::from_variant(BookID::try_from(1).unwrap(), stored.clone());
+        book.title = Some("My Dune".to_string());
+        book.authors = Some(vec!["Frank Herbert".to_string()]);
+
+        // Titles edited in bookworm are kept, unless the title in the file changed.
+        let mut scanned = variant("Dune", 1);
+        scanned.additional_authors = Some(vec!["Frank Herbert".to_string()]);
+        assert_eq!(refresh_edits(&book, &stored, &scanned), None);
+
+        scanned.local_title = Some("Dune Messiah".to_string());
+        scanned.additional_authors = Some(vec!["F. Herbert".to_string()]);
+        assert_eq!(
+            refresh_edits(&book, &stored, &scanned),
+            Some(
+                vec![
+                    (
+                        ColumnIdentifier::Title,
+                        Edit::Replace("Dune Messiah".to_string())
+                    ),
+                    (ColumnIdentifier::Author, Edit::Delete),
+                    (
+                        ColumnIdentifier::Author,
+                        Edit::Append("F. Herbert".to_string())
+                    ),
+                ]
+                .into_boxed_slice()
+            )
+        );
+    }
+
+    #[test]
+    fn test_open_with_command() {
+        let path = Path::new("books/Dune.epub");
+        let command = open_with_command("ebook-viewer --detach", path).unwrap();
+        assert_eq!(command.get_program(), "ebook-viewer");
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(args, vec!["--detach", "books/Dune.epub"]);
+
+        let command = open_with_command("zathura", path).unwrap();
+        assert_eq!(command.get_program(), "zathura");
+        assert_eq!(
+            command.get_args().collect::<Vec<_>>(),
+            vec!["books/Dune.epub"]
+        );
+
+        assert!(open_with_command("  ", path).is_none());
+
+        // Programs and arguments containing whitespace can be quoted or escaped.
+        let command = open_with_command(
+            r#""/opt/My Reader/reader" --title 'Dune Messiah' a\ b"#,
+            path,
+        )
+        .unwrap();
+        assert_eq!(command.get_program(), "/opt/My Reader/reader");
+        assert_eq!(
+            command.get_args().collect::<Vec<_>>(),
+            vec!["--title", "Dune Messiah", "a b", "books/Dune.epub"]
+        );
+
+        // Existing programs are run as they are, even if they are not quoted.
+        let dir = tempfile::tempdir().unwrap();
+        let program = dir.path().join("My Reader");
+        std::fs::write(&program, b"").unwrap();
+        let command = open_with_command(program.to_str().unwrap(), path).unwrap();
+        assert_eq!(command.get_program(), program.as_os_str());
+        assert_eq!(
+            command.get_args().collect::<Vec<_>>(),
+            vec!["books/Dune.epub"]
+        );
+    }
+
+    #[test]
+    fn test_split_shell_words() {
+        assert_eq!(split_shell_words("  a  b "), vec!["a", "b"]);
+        assert_eq!(split_shell_words(r#"a "" 'b c'd"#), vec!["a", "", "b cd"]);
+        assert_eq!(
+            split_shell_words(r#""a \"b\"" 'c\'"#),
+            vec![r#"a "b""#, r"c\"]
+        );
+        assert!(split_shell_words("").is_empty());
+    }
+}
@@ -1,21 +1,137 @@
 use std::borrow::Cow;
+use std::collections::HashMap;
 
 use unicase::UniCase;
 
-use bookworm_records::book::ColumnIdentifier;
+use bookworm_database::search::SizeUnits;
+use bookworm_records::book::{AuthorDisplay, ColumnIdentifier};
 use bookworm_records::Book;
 
+/// How the width of a column is chosen when the table is laid out.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Width {
+    /// The column shares the width which is not taken by other columns.
+    Auto,
+    /// The column is the given number of characters wide.
+    Fixed(u16),
+    /// The column takes the given percentage of the width of the table.
+    Percent(u8),
+}
+
+impl Default for Width {
+    fn default() -> Self {
+        Width::Auto
+    }
+}
+
+/// How a column is displayed in the table.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ColumnDisplay {
+    pub name: String,
+    pub width: Width,
+    /// Hidden columns keep their width, so that it is used again when they are shown.
+    pub visible: bool,
+}
+
+impl ColumnDisplay {
+    /// Returns a visible column with an automatic width.
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        ColumnDisplay {
+            name: name.into(),
+            width: Width::Auto,
+            visible: true,
+        }
+    }
+}
+
+/// The columns displayed in the table, and the columns which can be searched. The two are
+/// independent - hiding a column does not stop books from being filtered by it.
 #[derive(Default)]
 pub struct Columns {
     selected_cols: Vec<UniCase<String>>,
+    /// Columns which were displayed, or are configured, but are currently hidden.
+    hidden_cols: Vec<UniCase<String>>,
+    widths: HashMap<UniCase<String>, Width>,
+    unsearchable_cols: Vec<UniCase<String>>,
+    author_display: AuthorDisplay,
+    size_units: SizeUnits,
 }
 
 impl Columns {
+    /// Creates a table which displays `selected_cols`, where every column other than those in
+    /// `unsearchable_cols` can be searched.
+    pub fn new(selected_cols: Vec<String>, unsearchable_cols: Vec<String>) -> Self {
+        Columns {
+            selected_cols: selected_cols.into_iter().map(UniCase::new).collect(),
+            hidden_cols: vec![],
+            widths: HashMap::new(),
+            unsearchable_cols: unsearchable_cols.into_iter().map(UniCase::new).collect(),
+            author_display: AuthorDisplay::default(),
+            size_units: SizeUnits::default(),
+        }
+    }
+
+    /// Creates a table which displays the visible columns of `columns`, in order, where every
+    /// column other than those in `unsearchable_cols` can be searched.
+    pub fn from_display(columns: Vec<ColumnDisplay>, unsearchable_cols: Vec<String>) -> Self {
+        let mut table = Columns::new(vec![], unsearchable_cols);
+        for column in columns {
+            let name = UniCase::new(column.name);
+            if table.selected_cols.contains(&name) || table.hidden_cols.contains(&name) {
+                continue;
+            }
+            if column.width != Width::Auto {
+                table.widths.insert(name.clone(), column.width);
+            }
+            if column.visible {
+                table.selected_cols.push(name);
+            } else {
+                table.hidden_cols.push(name);
+            }
+        }
+        table
+    }
+
+    /// Returns how each column is displayed - the visible columns in order, followed by the
+    /// hidden columns.
+    pub fn column_display(&self) -> Vec<ColumnDisplay> {
+        let display = |visible: bool| {
+            move |column: &UniCase<String>| ColumnDisplay {
+                name: column.clone().into_inner(),
+                width: self.width(column),
+                visible,
+            }
+        };
+        self.selected_cols
+            .iter()
+            .map(display(true))
+            .chain(self.hidden_cols.iter().map(display(false)))
+            .collect()
+    }
+
+    /// Returns the width of `column`, which is automatic unless configured otherwise.
+    pub fn width(&self, column: &UniCase<String>) -> Width {
+        self.widths.get(column).copied().unwrap_or_default()
+    }
+
+    /// Returns the width of each displayed column, in order.
+    pub fn selected_widths(&self) -> Vec<Width> {
+        self.selected_cols
+            .iter()
+            .map(|column| self.width(column))
+            .collect()
+    }
+
+    /// Hides `column`. Its width is kept, and is used again if the column is shown.
     pub fn remove_column(&mut self, column: &UniCase<String>) {
-        self.selected_cols.retain(|x| x != column);
+        if self.selected_cols.contains(column) {
+            self.selected_cols.retain(|x| x != column);
+            self.hidden_cols.push(column.clone());
+        }
     }
 
     pub fn add_column(&mut self, column: UniCase<String>) {
+        self.hidden_cols.retain(|x| x != &column);
         if !self.selected_cols.contains(&column) {
             self.selected_cols.push(column);
         }
@@ -30,11 +146,12 @@ impl Columns {
                 .iter()
                 .map(|col| (col, ColumnIdentifier::from(col)))
                 .map(move |(col, col_id)| {
+                    let author_display = self.author_display.clone();
                     (
                         col,
                         books.iter().map(move |book| {
                             book.as_ref()
-                                .get_column(&col_id)
+                                .display_value(&col_id, &author_display)
                                 .unwrap_or(Cow::Borrowed(""))
                         }),
                     )
@@ -45,12 +162,55 @@ impl Columns {
     pub fn selected_cols(&self) -> &[UniCase<String>] {
         &self.selected_cols
     }
+
+    /// Returns the displayed columns, followed by the hidden columns.
+    pub fn known_cols(&self) -> impl Iterator<Item = &UniCase<String>> {
+        self.selected_cols.iter().chain(self.hidden_cols.iter())
+    }
+
+    /// Sets whether books can be filtered by `column`. This does not change whether the
+    /// column is displayed.
+    pub fn set_searchable(&mut self, column: UniCase<String>, searchable: bool) {
+        if searchable {
+            self.unsearchable_cols.retain(|x| x != &column);
+        } else if !self.unsearchable_cols.contains(&column) {
+            self.unsearchable_cols.push(column);
+        }
+    }
+
+    /// Returns whether books can be filtered by `column`.
+    pub fn is_searchable(&self, column: &ColumnIdentifier) -> bool {
+        !self
+            .unsearchable_cols
+            .iter()
+            .any(|col| ColumnIdentifier::from(col) == *column)
+    }
+
+    pub fn unsearchable_cols(&self) -> &[UniCase<String>] {
+        &self.unsearchable_cols
+    }
+
+    /// Sets how the authors of each book are joined when displayed.
+    pub fn set_author_display(&mut self, author_display: AuthorDisplay) {
+        self.author_display = author_display;
+    }
+
+    pub fn author_display(&self) -> &AuthorDisplay {
+        &self.author_display
+    }
+
+    /// Sets the meaning of the "KB", "MB" and "GB" units when filtering books by file size.
+    pub fn set_size_units(&mut self, size_units: SizeUnits) {
+        self.size_units = size_units;
+    }
+
+    pub fn size_units(&self) -> SizeUnits {
+        self.size_units
+    }
 }
 
 impl From<Vec<String>> for Columns {
     fn from(selected_cols: Vec<String>) -> Self {
-        Columns {
-            selected_cols: selected_cols.into_iter().map(UniCase::new).collect(),
-        }
+        Columns::new(selected_cols, vec![])
     }
 }
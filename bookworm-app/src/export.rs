@@ -0,0 +1,321 @@
+use std::io::Write;
+use std::sync::Arc;
+
+use bookworm_database::export::write_csv_row;
+use bookworm_database::{DelimitedFormat, MonthlyReading};
+use bookworm_records::Book;
+
+/// Returns whether exports of `count` books should be pretty-printed by default - single books
+/// are pretty-printed, while bulk exports are kept compact.
+pub fn pretty_by_default(count: usize) -> bool {
+    count <= 1
+}
+
+/// Writes the provided books to `writer` as a JSON array.
+///
+/// # Arguments
+///
+/// * ` writer ` - The destination of the exported books.
+/// * ` books ` - The books to export.
+/// * ` pretty ` - Whether to indent the output.
+///
+/// # Errors
+/// If serializing the books, or writing to `writer` fails, an error will be returned.
+pub fn write_json<W: Write>(
+    writer: W,
+    books: &[Arc<Book>],
+    pretty: bool,
+) -> Result<(), std::io::Error> {
+    let books: Vec<&Book> = books.iter().map(|book| book.as_ref()).collect();
+    if pretty {
+        serde_json::to_writer_pretty(writer, &books)?;
+    } else {
+        serde_json::to_writer(writer, &books)?;
+    }
+    Ok(())
+}
+
+/// Writes the provided book to `writer` as an OPF package document, starting with an XML
+/// declaration.
+///
+/// # Arguments
+///
+/// * ` writer ` - The destination of the exported book.
+/// * ` book ` - The book to export.
+/// * ` pretty ` - Whether to indent the output.
+///
+/// # Errors
+/// If writing to `writer` fails, an error will be returned.
+pub fn write_opf<W: Write>(mut writer: W, book: &Book, pretty: bool) -> Result<(), std::io::Error> {
+    let mut metadata = XmlElement::new("metadata")
+        .attr("xmlns:dc", "http://purl.org/dc/elements/1.1/")
+        .attr("xmlns:opf", "http://www.idpf.org/2007/opf");
+    if let Some(id) = book.id {
+        metadata.push(
+            XmlElement::new("dc:identifier")
+                .attr("id", "bookworm-id")
+                .text(id.to_string()),
+        );
+    }
+    if let Some(title) = book.title() {
+        metadata.push(XmlElement::new("dc:title").text(title));
+    }
+    for author in book.authors().unwrap_or_default() {
+        metadata.push(
+            XmlElement::new("dc:creator")
+                .attr("opf:role", "aut")
+                .text(author),
+        );
+    }
+    if let Some(description) = book.description() {
+        metadata.push(XmlElement::new("dc:description").text(description));
+    }
+    let mut free_tags: Vec<_> = book.free_tags().iter().collect();
+    free_tags.sort();
+    for tag in free_tags {
+        metadata.push(XmlElement::new("dc:subject").text(tag));
+    }
+    if let Some(series) = book.series() {
+        metadata.push(
+            XmlElement::new("meta")
+                .attr("name", "calibre:series")
+                .attr("content", &series.name),
+        );
+        if let Some(index) = series.index {
+            metadata.push(
+                XmlElement::new("meta")
+                    .attr("name", "calibre:series_index")
+                    .attr("content", index.to_string()),
+            );
+        }
+    }
+    let mut named_tags: Vec<_> = book.tags().iter().collect();
+    named_tags.sort();
+    for (name, value) in named_tags {
+        metadata.push(
+            XmlElement::new("meta")
+                .attr("name", name)
+                .attr("content", value),
+        );
+    }
+
+    let mut package = XmlElement::new("package")
+        .attr("xmlns", "http://www.idpf.org/2007/opf")
+        .attr("version", "2.0")
+        .attr("unique-identifier", "bookworm-id");
+    package.push(metadata);
+
+    let mut out = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    if pretty {
+        out.push('\n');
+    }
+    package.render(&mut out, 0, pretty);
+    writer.write_all(out.as_bytes())
+}
+
+/// An XML element, with its attributes and either text or child elements.
+pub(crate) struct XmlElement {
+    name: &'static str,
+    attrs: Vec<(&'static str, String)>,
+    text: Option<String>,
+    children: Vec<XmlElement>,
+}
+
+impl XmlElement {
+    pub(crate) fn new(name: &'static str) -> Self {
+        XmlElement {
+            name,
+            attrs: vec![],
+            text: None,
+            children: vec![],
+        }
+    }
+
+    pub(crate) fn attr<S: Into<String>>(mut self, name: &'static str, value: S) -> Self {
+        self.attrs.push((name, value.into()));
+        self
+    }
+
+    pub(crate) fn text<S: Into<String>>(mut self, text: S) -> Self {
+        self.text = Some(text.into());
+        self
+    }
+
+    pub(crate) fn push(&mut self, child: XmlElement) {
+        self.children.push(child);
+    }
+
+    /// Renders the element into `out`. If `pretty` is set, each element is placed on its own
+    /// line, and indented by two spaces per level of depth.
+    pub(crate) fn render(&self, out: &mut String, depth: usize, pretty: bool) {
+        if pretty {
+            out.push_str(&"  ".repeat(depth));
+        }
+        out.push('<');
+        out.push_str(self.name);
+        for (name, value) in &self.attrs {
+            out.push_str(&format!(r#" {}="{}""#, name, escape_xml(value)));
+        }
+
+        match (&self.text, self.children.is_empty()) {
+            (None, true) => out.push_str("/>"),
+            (Some(text), true) => {
+                out.push('>');
+                out.push_str(&escape_xml(text));
+                out.push_str(&format!("</{}>", self.name));
+            }
+            (_, false) => {
+                out.push('>');
+                if pretty {
+                    out.push('\n');
+                }
+                for child in &self.children {
+                    child.render(out, depth + 1, pretty);
+                }
+                if pretty {
+                    out.push_str(&"  ".repeat(depth));
+                }
+                out.push_str(&format!("</{}>", self.name));
+            }
+        }
+        if pretty {
+            out.push('\n');
+        }
+    }
+}
+
+/// Writes the number of books added and finished in each month to `writer` as delimited text,
+/// with a `month,added,finished` header.
+///
+/// # Arguments
+///
+/// * ` writer ` - The destination of the exported statistics.
+/// * ` stats ` - The monthly statistics to export.
+/// * ` format ` - The delimiter and encoding to write with.
+///
+/// # Errors
+/// If writing to `writer` fails, an error will be returned.
+pub fn write_reading_csv<W: Write>(
+    mut writer: W,
+    stats: &[MonthlyReading],
+    format: &DelimitedFormat,
+) -> Result<(), std::io::Error> {
+    write_csv_row(
+        &mut writer,
+        format,
+        ["month", "added", "finished"].iter().copied(),
+    )?;
+    for month in stats {
+        let (added, finished) = (month.added.to_string(), month.finished.to_string());
+        write_csv_row(
+            &mut writer,
+            format,
+            [month.month.as_str(), &added, &finished].iter().copied(),
+        )?;
+    }
+    Ok(())
+}
+
+fn escape_xml(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::convert::TryFrom;
+
+    use bookworm_records::book::BookID;
+    use bookworm_records::series::Series;
+
+    fn book() -> Book {
+        Book {
+            id: Some(BookID::try_from(1).unwrap()),
+            title: Some("Pride & Prejudice".to_string()),
+            authors: Some(vec!["Jane Austen".to_string()]),
+            series: Some(Series {
+                name: "Novels".to_string(),
+                index: Some(2.),
+            }),
+            free_tags: vec!["classic".to_string()].into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_pretty_json() {
+        let books = vec![Arc::new(book())];
+        let mut pretty = vec![];
+        let mut compact = vec![];
+        write_json(&mut pretty, &books, true).unwrap();
+        write_json(&mut compact, &books, false).unwrap();
+
+        assert!(pretty.len() > compact.len());
+        assert!(pretty.contains(&b'\n'));
+        assert!(!compact.contains(&b'\n'));
+        let pretty: serde_json::Value = serde_json::from_slice(&pretty).unwrap();
+        let compact: serde_json::Value = serde_json::from_slice(&compact).unwrap();
+        assert_eq!(pretty, compact);
+    }
+
+    #[test]
+    fn test_pretty_opf() {
+        let mut pretty = vec![];
+        let mut compact = vec![];
+        write_opf(&mut pretty, &book(), true).unwrap();
+        write_opf(&mut compact, &book(), false).unwrap();
+        let pretty = String::from_utf8(pretty).unwrap();
+        let compact = String::from_utf8(compact).unwrap();
+
+        let declaration = r#"<?xml version="1.0" encoding="UTF-8"?>"#;
+        assert!(pretty.starts_with(declaration));
+        assert!(compact.starts_with(declaration));
+        assert!(pretty.contains("\n  <metadata"));
+        assert!(pretty.contains("\n    <dc:title>Pride &amp; Prejudice</dc:title>\n"));
+        assert!(!compact.contains('\n'));
+
+        let stripped: String = pretty.lines().map(str::trim).collect();
+        assert_eq!(stripped, compact);
+    }
+
+    #[test]
+    fn test_reading_csv() {
+        let stats = vec![
+            MonthlyReading {
+                month: "2021-01".to_string(),
+                added: 2,
+                finished: 1,
+            },
+            MonthlyReading {
+                month: "2021-03".to_string(),
+                added: 0,
+                finished: 3,
+            },
+        ];
+        let mut csv = vec![];
+        write_reading_csv(&mut csv, &stats, &DelimitedFormat::csv()).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "month,added,finished\n2021-01,2,1\n2021-03,0,3\n"
+        );
+
+        let mut tsv = vec![];
+        write_reading_csv(&mut tsv, &stats, &DelimitedFormat::default()).unwrap();
+        assert_eq!(
+            String::from_utf8(tsv).unwrap(),
+            "month\tadded\tfinished\n2021-01\t2\t1\n2021-03\t0\t3\n"
+        );
+    }
+}
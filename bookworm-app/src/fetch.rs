@@ -0,0 +1,302 @@
+use serde_json::Value;
+
+use bookworm_input::Edit;
+use bookworm_records::book::ColumnIdentifier;
+use bookworm_records::variant::Identifier;
+use bookworm_records::Book;
+
+/// The metadata of a book, as found in an online metadata source.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FetchedMetadata {
+    pub title: Option<String>,
+    pub authors: Vec<String>,
+    pub description: Option<String>,
+    /// The URL of the largest available cover image.
+    pub cover_url: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum FetchError {
+    /// bookworm was built without the `fetch` feature, so metadata can not be fetched.
+    Disabled,
+    #[cfg(feature = "fetch")]
+    Request(reqwest::Error),
+}
+
+#[cfg(feature = "fetch")]
+impl From<reqwest::Error> for FetchError {
+    fn from(e: reqwest::Error) -> Self {
+        FetchError::Request(e)
+    }
+}
+
+/// Returns the ISBN of `book`, without hyphens, preferring the ISBN of its preferred variant.
+pub fn isbn(book: &Book) -> Option<String> {
+    book.preferred_variant()
+        .into_iter()
+        .chain(book.variants())
+        .find_map(|variant| match &variant.identifier {
+            Some(Identifier::ISBN(isbn)) => Some(isbn.to_string().replace('-', "")),
+            _ => None,
+        })
+}
+
+/// Returns the URL of the Open Library entry of the book with the given ISBN.
+pub fn open_library_url(isbn: &str) -> String {
+    format!(
+        "https://openlibrary.org/api/books?bibkeys=ISBN:{}&format=json&jscmd=details",
+        isbn
+    )
+}
+
+/// Reads the metadata of the book with the given ISBN from `body`, the response to a request for
+/// `open_library_url(isbn)`. Returns None if the response is malformed, or if Open Library does
+/// not know the book.
+///
+/// # Arguments
+/// * ` isbn ` - The ISBN which was looked up.
+/// * ` body ` - The JSON response from Open Library.
+pub fn parse_open_library(isbn: &str, body: &str) -> Option<FetchedMetadata> {
+    let response: Value = serde_json::from_str(body).ok()?;
+    let details = response.get(format!("ISBN:{}", isbn))?.get("details")?;
+    let text = |value: &Value| {
+        value
+            .as_str()
+            .map(str::trim)
+            .filter(|text| !text.is_empty())
+            .map(str::to_string)
+    };
+
+    // Descriptions are either plain strings, or text objects of the form {"type", "value"}.
+    let description = details
+        .get("description")
+        .and_then(|description| text(description.get("value").unwrap_or(description)));
+    let authors = details
+        .get("authors")
+        .and_then(Value::as_array)
+        .map(|authors| {
+            authors
+                .iter()
+                .filter_map(|author| text(author.get("name")?))
+                .collect()
+        })
+        .unwrap_or_default();
+    // Open Library marks missing covers with negative IDs.
+    let cover_url = details
+        .get("covers")
+        .and_then(Value::as_array)
+        .and_then(|covers| covers.iter().filter_map(Value::as_i64).find(|&id| id > 0))
+        .map(|id| format!("https://covers.openlibrary.org/b/id/{}-L.jpg", id));
+
+    Some(FetchedMetadata {
+        title: details.get("title").and_then(text),
+        authors,
+        description,
+        cover_url,
+    })
+}
+
+/// Returns the edits which fill in the columns of `book` from `fetched`. If `overwrite` is false,
+/// only columns which are currently empty are filled in - otherwise, existing values are
+/// replaced. Returns None if no column would change.
+///
+/// # Arguments
+///
+/// * ` book ` - The book to edit.
+/// * ` fetched ` - The metadata fetched for the book.
+/// * ` overwrite ` - Whether to replace existing values.
+pub fn fetch_edits(
+    book: &Book,
+    fetched: &FetchedMetadata,
+    overwrite: bool,
+) -> Option<Box<[(ColumnIdentifier, Edit)]>> {
+    let mut edits = vec![];
+    if let Some(title) = &fetched.title {
+        if book
+            .title()
+            .map_or(true, |t| overwrite && t != title.as_str())
+        {
+            edits.push((ColumnIdentifier::Title, Edit::Replace(title.clone())));
+        }
+    }
+
+    let current = book.authors().unwrap_or_default();
+    let replace = if current.is_empty() {
+        !fetched.authors.is_empty()
+    } else {
+        overwrite && !fetched.authors.is_empty() && current != fetched.authors.as_slice()
+    };
+    if replace {
+        edits.push((ColumnIdentifier::Author, Edit::Delete));
+        edits.extend(
+            fetched
+                .authors
+                .iter()
+                .map(|author| (ColumnIdentifier::Author, Edit::Append(author.clone()))),
+        );
+    }
+
+    if let Some(description) = &fetched.description {
+        if book
+            .description()
+            .map_or(true, |d| overwrite && d != description)
+        {
+            edits.push((
+                ColumnIdentifier::Description,
+                Edit::Replace(description.clone()),
+            ));
+        }
+    }
+
+    if edits.is_empty() {
+        None
+    } else {
+        Some(edits.into_boxed_slice())
+    }
+}
+
+/// Looks up the book with the given ISBN on Open Library. Returns None if Open Library does not
+/// know the book.
+///
+/// # Errors
+/// Will return an error if the request fails.
+#[cfg(feature = "fetch")]
+pub async fn lookup(isbn: &str) -> Result<Option<FetchedMetadata>, FetchError> {
+    let body = reqwest::get(open_library_url(isbn))
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    Ok(parse_open_library(isbn, &body))
+}
+
+#[cfg(not(feature = "fetch"))]
+pub async fn lookup(_isbn: &str) -> Result<Option<FetchedMetadata>, FetchError> {
+    Err(FetchError::Disabled)
+}
+
+/// Downloads the cover image at `url`.
+///
+/// # Errors
+/// Will return an error if the request fails.
+#[cfg(feature = "fetch")]
+pub async fn cover(url: &str) -> Result<Vec<u8>, FetchError> {
+    Ok(reqwest::get(url)
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?
+        .to_vec())
+}
+
+#[cfg(not(feature = "fetch"))]
+pub async fn cover(_url: &str) -> Result<Vec<u8>, FetchError> {
+    Err(FetchError::Disabled)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::convert::TryFrom;
+    use std::path::PathBuf;
+
+    use bookworm_records::book::BookID;
+    use bookworm_records::variant::BookType;
+    use bookworm_records::BookVariant;
+
+    const DUNE: &str = r#"{"ISBN:9780441013593": {
+        "bib_key": "ISBN:9780441013593",
+        "details": {
+            "title": "Dune",
+            "authors": [{"key": "/authors/OL79034A", "name": "Frank Herbert"}],
+            "description": {"type": "/type/text", "value": "Set on the desert planet Arrakis."},
+            "covers": [-1, 8228719]
+        }
+    }}"#;
+
+    fn book(title: Option<&str>, authors: Option<Vec<String>>) -> Book {
+        let mut book = Book::from_variant(
+            BookID::try_from(1).unwrap(),
+            BookVariant {
+                book_type: BookType::EPUB,
+                path: PathBuf::from("dune.epub"),
+                file_size: 0,
+                local_title: None,
+                identifier: Some(Identifier::new("isbn", "9780441013593".to_string())),
+                language: None,
+                additional_authors: None,
+                translators: None,
+                description: None,
+                series: None,
+                id: None,
+                hash: [0; 32],
+                free_tags: Default::default(),
+                named_tags: Default::default(),
+            },
+        );
+        book.title = title.map(str::to_string);
+        book.authors = authors;
+        book
+    }
+
+    #[test]
+    fn test_parse_open_library() {
+        assert_eq!(
+            parse_open_library("9780441013593", DUNE),
+            Some(FetchedMetadata {
+                title: Some("Dune".to_string()),
+                authors: vec!["Frank Herbert".to_string()],
+                description: Some("Set on the desert planet Arrakis.".to_string()),
+                cover_url: Some("https://covers.openlibrary.org/b/id/8228719-L.jpg".to_string()),
+            })
+        );
+        let plain = r#"{"ISBN:1": {"details": {"description": "Plain text", "covers": [-1]}}}"#;
+        assert_eq!(
+            parse_open_library("1", plain),
+            Some(FetchedMetadata {
+                description: Some("Plain text".to_string()),
+                ..Default::default()
+            })
+        );
+        assert_eq!(parse_open_library("9780441013593", "{}"), None);
+        assert_eq!(parse_open_library("9780441013593", "not json"), None);
+    }
+
+    #[test]
+    fn test_fetch_edits() {
+        let fetched = parse_open_library("9780441013593", DUNE).unwrap();
+        let book = book(None, None);
+        assert_eq!(isbn(&book), Some("9780441013593".to_string()));
+        assert_eq!(
+            fetch_edits(&book, &fetched, false),
+            Some(
+                vec![
+                    (ColumnIdentifier::Title, Edit::Replace("Dune".to_string())),
+                    (ColumnIdentifier::Author, Edit::Delete),
+                    (
+                        ColumnIdentifier::Author,
+                        Edit::Append("Frank Herbert".to_string())
+                    ),
+                    (
+                        ColumnIdentifier::Description,
+                        Edit::Replace("Set on the desert planet Arrakis.".to_string())
+                    ),
+                ]
+                .into_boxed_slice()
+            )
+        );
+
+        let mut book = self::book(Some("My Dune"), Some(vec!["F. Herbert".to_string()]));
+        book.description = Some("Spice".to_string());
+        assert_eq!(fetch_edits(&book, &fetched, false), None);
+        assert_eq!(
+            fetch_edits(&book, &fetched, true).map(|edits| edits.len()),
+            Some(4)
+        );
+
+        // Books without an ISBN can not be looked up.
+        book.variants[0].identifier = Some(Identifier::new("amazon", "B00B7NPRY8".to_string()));
+        assert_eq!(isbn(&book), None);
+    }
+}
@@ -0,0 +1,158 @@
+use std::sync::Arc;
+
+use bookworm_database::{Book, BookSnapshot, VariantEditPolicy};
+use bookworm_input::Edit;
+use bookworm_records::book::{BookID, ColumnIdentifier};
+
+/// The maximum number of changes which can be undone.
+const MAX_CHANGES: usize = 100;
+
+/// The maximum number of books kept across all changes which can be undone. Once exceeded, the
+/// oldest changes are discarded, although the most recent change is always kept.
+const MAX_BOOKS: usize = 10_000;
+
+/// A mutation of the database which can be undone. Each change keeps a copy of every book it
+/// affected, as the books were before the change, so that undoing the change writes the copies
+/// back to the database under their original IDs.
+pub(crate) enum Change {
+    /// The books were removed from the database. The snapshot keeps their covers and tags.
+    Deleted(BookSnapshot),
+    /// The edits were applied to the books, with the given variant edit policy.
+    Edited {
+        books: Vec<Arc<Book>>,
+        edits: Box<[(ColumnIdentifier, Edit)]>,
+        policy: VariantEditPolicy,
+    },
+}
+
+impl Change {
+    /// Returns the number of books affected by this change.
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            Change::Deleted(snapshot) => snapshot.len(),
+            Change::Edited { books, .. } => books.len(),
+        }
+    }
+
+    /// Returns the IDs of the books affected by this change.
+    pub(crate) fn ids(&self) -> Vec<BookID> {
+        match self {
+            Change::Deleted(snapshot) => snapshot.ids(),
+            Change::Edited { books, .. } => books.iter().map(|book| book.id()).collect(),
+        }
+    }
+}
+
+/// Records changes to the database, so that they can be undone and redone in order.
+#[derive(Default)]
+pub(crate) struct Journal {
+    undo: Vec<Change>,
+    redo: Vec<Change>,
+}
+
+impl Journal {
+    /// Records a new change. Changes which affected no books are not recorded. Since the
+    /// changes which were undone may no longer apply, they can not be redone afterwards. If
+    /// more than `MAX_CHANGES` changes or `MAX_BOOKS` books are kept, the oldest changes are
+    /// discarded.
+    pub(crate) fn record(&mut self, change: Change) {
+        if change.len() == 0 {
+            return;
+        }
+        self.undo.push(change);
+        self.redo.clear();
+
+        let mut books: usize = self.undo.iter().map(Change::len).sum();
+        let mut discard = 0;
+        while self.undo.len() - discard > MAX_CHANGES
+            || (books > MAX_BOOKS && self.undo.len() - discard > 1)
+        {
+            books -= self.undo[discard].len();
+            discard += 1;
+        }
+        self.undo.drain(..discard);
+    }
+
+    /// Removes and returns the most recent change which has not been undone.
+    pub(crate) fn pop_undo(&mut self) -> Option<Change> {
+        self.undo.pop()
+    }
+
+    /// Removes and returns the most recently undone change.
+    pub(crate) fn pop_redo(&mut self) -> Option<Change> {
+        self.redo.pop()
+    }
+
+    /// Marks `change` as applied, so that it can be undone, without discarding undone changes.
+    pub(crate) fn push_undo(&mut self, change: Change) {
+        self.undo.push(change);
+    }
+
+    /// Marks `change` as undone, so that it can be redone.
+    pub(crate) fn push_redo(&mut self, change: Change) {
+        self.redo.push(change);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::convert::TryFrom;
+
+    fn edited(ids: std::ops::Range<u64>) -> Change {
+        Change::Edited {
+            books: ids
+                .map(|id| {
+                    Arc::new(Book {
+                        id: Some(BookID::try_from(id).unwrap()),
+                        ..Default::default()
+                    })
+                })
+                .collect(),
+            edits: Box::default(),
+            policy: VariantEditPolicy::AllVariants,
+        }
+    }
+
+    fn deleted(id: u64) -> Change {
+        edited(id..id + 1)
+    }
+
+    #[test]
+    fn test_record_clears_redo() {
+        let mut journal = Journal::default();
+        journal.record(Change::Deleted(BookSnapshot::default()));
+        assert!(journal.pop_undo().is_none());
+
+        journal.record(deleted(1));
+        journal.record(deleted(2));
+        let change = journal.pop_undo().unwrap();
+        assert_eq!(change.ids(), vec![BookID::try_from(2).unwrap()]);
+        journal.push_redo(change);
+
+        journal.record(deleted(3));
+        assert!(journal.pop_redo().is_none());
+        assert_eq!(
+            journal.pop_undo().unwrap().ids(),
+            vec![BookID::try_from(3).unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_record_discards_oldest() {
+        let mut journal = Journal::default();
+        for id in 1..=MAX_CHANGES as u64 + 1 {
+            journal.record(deleted(id));
+        }
+        assert_eq!(journal.undo.len(), MAX_CHANGES);
+        assert_eq!(journal.undo[0].ids(), vec![BookID::try_from(2).unwrap()]);
+
+        // The most recent change is kept, even if it has more than `MAX_BOOKS` books.
+        journal.record(edited(1..MAX_BOOKS as u64 / 2));
+        assert_eq!(journal.undo.len(), MAX_CHANGES);
+        journal.record(edited(1..MAX_BOOKS as u64 + 2));
+        assert_eq!(journal.undo.len(), 1);
+        assert_eq!(journal.undo[0].len(), MAX_BOOKS + 1);
+    }
+}
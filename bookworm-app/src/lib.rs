@@ -11,5 +11,11 @@ pub use settings::Settings;
 #[allow(clippy::module_inception)]
 pub mod app;
 pub mod columns;
+pub mod export;
+pub mod fetch;
+mod journal;
+pub mod maintenance;
+#[cfg(feature = "opds")]
+pub mod opds;
 pub mod parser;
 pub mod settings;
@@ -0,0 +1,74 @@
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+/// Removes each file directly inside `dir` which was last modified more than `retention` ago,
+/// other than `active`, and returns the number of files removed. If the directory does not
+/// exist, nothing happens.
+///
+/// # Arguments
+///
+/// * ` dir ` - The directory containing the log files.
+/// * ` active ` - The log file which is currently being written to, which is never removed.
+/// * ` retention ` - How long log files are kept for.
+///
+/// # Errors
+/// If reading the directory or removing a file fails, an error will be returned.
+pub fn prune_logs<P: AsRef<Path>>(
+    dir: P,
+    active: &Path,
+    retention: Duration,
+) -> Result<usize, std::io::Error> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+
+    let now = SystemTime::now();
+    let mut removed = 0;
+    for entry in entries {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() || entry.path() == active {
+            continue;
+        }
+        let age = now.duration_since(metadata.modified()?).unwrap_or_default();
+        if age > retention {
+            std::fs::remove_file(entry.path())?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_prune_logs() {
+        let dir = tempfile::tempdir().unwrap();
+        let active = dir.path().join("log");
+        let rotated = dir.path().join("log.1");
+        std::fs::write(&active, "").unwrap();
+        std::fs::write(&rotated, "").unwrap();
+
+        assert_eq!(
+            prune_logs(dir.path(), &active, Duration::from_secs(60)).unwrap(),
+            0
+        );
+        assert!(rotated.exists());
+
+        assert_eq!(
+            prune_logs(dir.path(), &active, Duration::from_secs(0)).unwrap(),
+            1
+        );
+        assert!(active.exists());
+        assert!(!rotated.exists());
+
+        assert_eq!(
+            prune_logs(dir.path().join("missing"), &active, Duration::from_secs(0)).unwrap(),
+            0
+        );
+    }
+}
@@ -0,0 +1,714 @@
+use std::convert::TryFrom;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::RwLock;
+
+use bookworm_database::matchers::column;
+use bookworm_database::paginator::Paginator;
+use bookworm_database::search::{Matcher, Normalization};
+use bookworm_database::{AppDatabase, DatabaseError};
+use bookworm_records::book::{BookID, ColumnIdentifier};
+use bookworm_records::variant::BookType;
+use bookworm_records::{Book, ColumnOrder};
+
+use crate::export::XmlElement;
+
+/// The number of entries in each page of a feed, unless set otherwise.
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+
+/// The longest request head which is read, in bytes. Longer requests are rejected.
+const MAX_REQUEST_LEN: usize = 8192;
+
+const NAVIGATION: &str = "application/atom+xml;profile=opds-catalog;kind=navigation";
+const ACQUISITION: &str = "application/atom+xml;profile=opds-catalog;kind=acquisition";
+const OPENSEARCH: &str = "application/opensearchdescription+xml";
+/// The update time of feeds which have no dated books.
+const EPOCH: &str = "1970-01-01T00:00:00Z";
+
+const OK: &str = "200 OK";
+const BAD_REQUEST: &str = "400 Bad Request";
+const NOT_FOUND: &str = "404 Not Found";
+const METHOD_NOT_ALLOWED: &str = "405 Method Not Allowed";
+const INTERNAL_ERROR: &str = "500 Internal Server Error";
+
+/// The body of a response - either a document, or a file which is streamed from disk.
+enum Body {
+    Text(String),
+    File {
+        file: tokio::fs::File,
+        name: String,
+        len: u64,
+    },
+}
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: Body,
+}
+
+impl Response {
+    /// Returns a response holding `root` as an XML document.
+    fn xml(content_type: &'static str, root: &XmlElement) -> Self {
+        let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        root.render(&mut out, 0, true);
+        Response {
+            status: OK,
+            content_type,
+            body: Body::Text(out),
+        }
+    }
+
+    fn error(status: &'static str) -> Self {
+        Response {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: Body::Text(status.to_string()),
+        }
+    }
+}
+
+/// Returns the MIME type of files of `book_type`.
+fn mime_type(book_type: &BookType) -> &'static str {
+    match book_type {
+        BookType::EPUB => "application/epub+zip",
+        BookType::MOBI => "application/x-mobipocket-ebook",
+        BookType::PDF => "application/pdf",
+        BookType::FB2 => "application/x-fictionbook+xml",
+    }
+}
+
+/// Percent-encodes `s`, so that it can be used as a segment of a URL path.
+fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                char::from(b).to_string()
+            }
+            b => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Decodes the percent-encoded bytes in `s`. Malformed escapes are kept as they are.
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(b) => {
+                decoded.push(b);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Returns the decoded value of the parameter `name` in the query string `query`.
+fn query_param(query: &str, name: &str) -> Option<String> {
+    query.split('&').find_map(|pair| {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        if percent_decode(key) == name {
+            Some(percent_decode(&value.replace('+', " ")))
+        } else {
+            None
+        }
+    })
+}
+
+/// Returns `href` with the page number `page` appended to its query string.
+fn page_href(href: &str, page: usize) -> String {
+    let separator = if href.contains('?') { '&' } else { '?' };
+    format!("{}{}page={}", href, separator, page)
+}
+
+/// Returns the time at which `book` was added as an Atom date, if it is known.
+fn date_updated(book: &Book) -> Option<String> {
+    book.date_added
+        .as_ref()
+        .map(|date| format!("{}Z", date.replacen(' ', "T", 1)))
+}
+
+fn link<S: Into<String>>(rel: &'static str, href: S, kind: &'static str) -> XmlElement {
+    XmlElement::new("link")
+        .attr("rel", rel)
+        .attr("href", href)
+        .attr("type", kind)
+}
+
+/// Returns a feed with the given ID, title and update time, which links to itself at `href`,
+/// to the root of the catalog, and to the search.
+fn feed(id: &str, title: &str, updated: &str, href: &str, kind: &'static str) -> XmlElement {
+    let mut feed = XmlElement::new("feed")
+        .attr("xmlns", "http://www.w3.org/2005/Atom")
+        .attr("xmlns:dc", "http://purl.org/dc/terms/")
+        .attr("xmlns:opds", "http://opds-spec.org/2010/catalog");
+    feed.push(XmlElement::new("id").text(format!("urn:bookworm:{}", id)));
+    feed.push(XmlElement::new("title").text(title));
+    feed.push(XmlElement::new("updated").text(updated));
+    feed.push(link("self", href, kind));
+    feed.push(link("start", "/opds", NAVIGATION));
+    feed.push(link("search", "/opds/opensearch.xml", OPENSEARCH));
+    feed.push(link("search", "/opds/search?q={searchTerms}", ACQUISITION));
+    feed
+}
+
+/// Links `feed`, which is page `page` of the feed at `href`, to the pages around it.
+fn push_page_links(
+    feed: &mut XmlElement,
+    href: &str,
+    page: usize,
+    has_next: bool,
+    kind: &'static str,
+) {
+    if page > 0 {
+        feed.push(link("first", page_href(href, 0), kind));
+        feed.push(link("previous", page_href(href, page - 1), kind));
+    }
+    if has_next {
+        feed.push(link("next", page_href(href, page + 1), kind));
+    }
+}
+
+/// Returns an entry of a navigation feed, which links to the feed at `href`.
+fn navigation_entry(
+    id: &str,
+    title: &str,
+    content: &str,
+    href: String,
+    kind: &'static str,
+) -> XmlElement {
+    let mut entry = XmlElement::new("entry");
+    entry.push(XmlElement::new("title").text(title));
+    entry.push(XmlElement::new("id").text(format!("urn:bookworm:{}", id)));
+    entry.push(XmlElement::new("updated").text(EPOCH));
+    entry.push(
+        XmlElement::new("content")
+            .attr("type", "text")
+            .text(content),
+    );
+    entry.push(link("subsection", href, kind));
+    entry
+}
+
+/// Returns an entry of an acquisition feed, which links to each variant of `book`.
+fn book_entry(book: &Book) -> XmlElement {
+    let id = book.id();
+    let mut entry = XmlElement::new("entry");
+    entry.push(XmlElement::new("title").text(book.title().unwrap_or("Untitled")));
+    entry.push(XmlElement::new("id").text(format!("urn:bookworm:book:{}", id)));
+    entry.push(
+        XmlElement::new("updated").text(date_updated(book).unwrap_or_else(|| EPOCH.to_string())),
+    );
+    for author in book.authors().unwrap_or_default() {
+        let mut element = XmlElement::new("author");
+        element.push(XmlElement::new("name").text(author));
+        entry.push(element);
+    }
+    if let Some(language) = book
+        .preferred_variant()
+        .and_then(|variant| variant.language.as_ref())
+    {
+        entry.push(XmlElement::new("dc:language").text(language));
+    }
+    if let Some(series) = book.series() {
+        entry.push(
+            XmlElement::new("category")
+                .attr("scheme", "urn:bookworm:series")
+                .attr("term", series.name.clone())
+                .attr("label", series.to_string()),
+        );
+    }
+    if let Some(description) = book.description() {
+        entry.push(
+            XmlElement::new("summary")
+                .attr("type", "text")
+                .text(description),
+        );
+    }
+    for (index, variant) in book.variants().iter().enumerate() {
+        entry.push(link(
+            "http://opds-spec.org/acquisition",
+            format!("/opds/books/{}/{}", id, index),
+            mime_type(&variant.book_type),
+        ));
+    }
+    entry
+}
+
+/// Serves a read-only OPDS 1.2 catalog of the books in a database over HTTP, so that the
+/// library can be browsed and downloaded from e-readers. The catalog is rooted at `/opds`, and
+/// provides feeds of all books, of books by each author and in each series, and of books whose
+/// titles contain a search term. Each variant of a book is downloaded from
+/// `/opds/books/[id]/[variant]`.
+pub struct OpdsServer<D: AppDatabase + 'static> {
+    db: Arc<RwLock<D>>,
+    page_size: usize,
+}
+
+impl<D: AppDatabase + Send + Sync + 'static> OpdsServer<D> {
+    pub fn new(db: D) -> Self {
+        OpdsServer {
+            db: Arc::new(RwLock::new(db)),
+            page_size: DEFAULT_PAGE_SIZE,
+        }
+    }
+
+    /// Sets the number of entries in each page of a feed, which is at least 1.
+    pub fn set_page_size(&mut self, page_size: usize) {
+        self.page_size = page_size.max(1);
+    }
+
+    /// Listens for requests at `addr`, and responds to each connection in a separate task.
+    ///
+    /// # Errors
+    /// Will return an error if `addr` can not be bound, or if accepting a connection fails.
+    pub async fn serve(self, addr: SocketAddr) -> Result<(), std::io::Error> {
+        let listener = TcpListener::bind(addr).await?;
+        let server = Arc::new(self);
+        loop {
+            let (mut stream, peer) = listener.accept().await?;
+            let server = server.clone();
+            tokio::spawn(async move {
+                if let Err(e) = server.handle(&mut stream).await {
+                    tracing::info!("Failed to respond to {}: {:?}", peer, e);
+                }
+            });
+        }
+    }
+
+    /// Reads a single request from `stream`, and writes the response. The connection is not
+    /// kept alive.
+    async fn handle<S: AsyncRead + AsyncWrite + Unpin>(
+        &self,
+        stream: &mut S,
+    ) -> Result<(), std::io::Error> {
+        let mut head = vec![];
+        let mut buf = [0; 1024];
+        while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+            if head.len() > MAX_REQUEST_LEN {
+                return write_response(stream, Response::error(BAD_REQUEST)).await;
+            }
+            let read = stream.read(&mut buf).await?;
+            if read == 0 {
+                return Ok(());
+            }
+            head.extend_from_slice(&buf[..read]);
+        }
+
+        let head = String::from_utf8_lossy(&head);
+        let mut request_line = head.lines().next().unwrap_or_default().split_whitespace();
+        let response = match (request_line.next(), request_line.next()) {
+            (Some("GET"), Some(target)) => self.respond(target).await,
+            (Some(_), Some(_)) => Response::error(METHOD_NOT_ALLOWED),
+            _ => Response::error(BAD_REQUEST),
+        };
+        write_response(stream, response).await
+    }
+
+    /// Returns the response to a GET request for `target`.
+    async fn respond(&self, target: &str) -> Response {
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let page = query_param(query, "page")
+            .and_then(|page| page.parse().ok())
+            .unwrap_or(0);
+        let segments: Vec<_> = path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(percent_decode)
+            .collect();
+        let segments: Vec<_> = segments.iter().map(String::as_str).collect();
+
+        let title = |title: String| {
+            column(ColumnIdentifier::Title)
+                .normalized(Normalization::CaseInsensitive)
+                .contains(title)
+        };
+        let response = match segments.as_slice() {
+            [] | ["opds"] => Ok(self.root()),
+            ["opds", "opensearch.xml"] => Ok(opensearch()),
+            ["opds", "books"] => {
+                self.acquisition_feed("books", "All books", "/opds/books", vec![], vec![], page)
+                    .await
+            }
+            ["opds", "books", id, variant] => self.variant_file(id, variant).await,
+            ["opds", "authors"] => self.authors_feed(page).await,
+            ["opds", "authors", author] => match column(ColumnIdentifier::Author).equals(*author) {
+                Ok(matcher) => {
+                    self.acquisition_feed(
+                        &format!("author:{}", author),
+                        author,
+                        &format!("/opds/authors/{}", percent_encode(author)),
+                        vec![(ColumnIdentifier::Series, ColumnOrder::Ascending)],
+                        vec![matcher],
+                        page,
+                    )
+                    .await
+                }
+                Err(_) => Ok(Response::error(BAD_REQUEST)),
+            },
+            ["opds", "series"] => self.series_feed(page).await,
+            ["opds", "series", series] => match column(ColumnIdentifier::Series).equals(*series) {
+                Ok(matcher) => {
+                    self.acquisition_feed(
+                        &format!("series:{}", series),
+                        series,
+                        &format!("/opds/series/{}", percent_encode(series)),
+                        vec![(ColumnIdentifier::Series, ColumnOrder::Ascending)],
+                        vec![matcher],
+                        page,
+                    )
+                    .await
+                }
+                Err(_) => Ok(Response::error(BAD_REQUEST)),
+            },
+            ["opds", "search"] => {
+                match query_param(query, "q").map(|terms| (title(terms.clone()), terms)) {
+                    Some((Ok(matcher), terms)) => {
+                        self.acquisition_feed(
+                            &format!("search:{}", terms),
+                            &format!("Search for {}", terms),
+                            &format!("/opds/search?q={}", percent_encode(&terms)),
+                            vec![],
+                            vec![matcher],
+                            page,
+                        )
+                        .await
+                    }
+                    _ => Ok(Response::error(BAD_REQUEST)),
+                }
+            }
+            _ => Ok(Response::error(NOT_FOUND)),
+        };
+        response.unwrap_or_else(|e| match e {
+            DatabaseError::BookNotFound(_) => Response::error(NOT_FOUND),
+            e => {
+                tracing::error!("Failed to respond to {}: {:?}", target, e);
+                Response::error(INTERNAL_ERROR)
+            }
+        })
+    }
+
+    /// Returns the navigation feed at the root of the catalog.
+    fn root(&self) -> Response {
+        let mut root = feed("root", "bookworm", EPOCH, "/opds", NAVIGATION);
+        root.push(navigation_entry(
+            "books",
+            "All books",
+            "Every book, by title",
+            String::from("/opds/books"),
+            ACQUISITION,
+        ));
+        root.push(navigation_entry(
+            "authors",
+            "Authors",
+            "Books by author",
+            String::from("/opds/authors"),
+            NAVIGATION,
+        ));
+        root.push(navigation_entry(
+            "series",
+            "Series",
+            "Books by series",
+            String::from("/opds/series"),
+            NAVIGATION,
+        ));
+        Response::xml(NAVIGATION, &root)
+    }
+
+    /// Returns page `page` of the acquisition feed of the books which match `matchers`, sorted
+    /// by `sort` and then by title. The feed is read with a `Paginator`, so that only the books
+    /// on the page are read.
+    async fn acquisition_feed(
+        &self,
+        id: &str,
+        title: &str,
+        href: &str,
+        mut sort: Vec<(ColumnIdentifier, ColumnOrder)>,
+        matchers: Vec<Box<dyn Matcher + Send + Sync>>,
+        page: usize,
+    ) -> Result<Response, DatabaseError<D::Error>> {
+        sort.push((ColumnIdentifier::Title, ColumnOrder::Ascending));
+        let paginator = Paginator::new(self.db.clone(), self.page_size, sort.into_boxed_slice())
+            .bind_match(matchers.into_boxed_slice());
+        // Read one more book than fits on the page, to find out if there is a next page.
+        let mut books = paginator
+            .read_page(page.saturating_mul(self.page_size), self.page_size + 1)
+            .await?;
+        let has_next = books.len() > self.page_size;
+        books.truncate(self.page_size);
+
+        let updated = books
+            .iter()
+            .filter_map(|book| date_updated(book))
+            .max()
+            .unwrap_or_else(|| EPOCH.to_string());
+        let mut feed = feed(id, title, &updated, &page_href(href, page), ACQUISITION);
+        push_page_links(&mut feed, href, page, has_next, ACQUISITION);
+        for book in &books {
+            feed.push(book_entry(book));
+        }
+        Ok(Response::xml(ACQUISITION, &feed))
+    }
+
+    /// Returns page `page` of a navigation feed, which links to the acquisition feed of each
+    /// value in `values`, sorted by name.
+    fn values_feed(
+        &self,
+        id: &'static str,
+        title: &str,
+        mut values: Vec<(String, usize)>,
+        page: usize,
+    ) -> Response {
+        values.sort_by_cached_key(|(name, _)| name.to_lowercase());
+        let href = format!("/opds/{}", id);
+        let start = page.saturating_mul(self.page_size).min(values.len());
+        let end = (start + self.page_size).min(values.len());
+
+        let mut feed = feed(id, title, EPOCH, &page_href(&href, page), NAVIGATION);
+        push_page_links(&mut feed, &href, page, end < values.len(), NAVIGATION);
+        for (name, count) in &values[start..end] {
+            feed.push(navigation_entry(
+                &format!("{}:{}", id, name),
+                name,
+                &format!("{} books", count),
+                format!("{}/{}", href, percent_encode(name)),
+                ACQUISITION,
+            ));
+        }
+        Response::xml(NAVIGATION, &feed)
+    }
+
+    async fn authors_feed(&self, page: usize) -> Result<Response, DatabaseError<D::Error>> {
+        let authors = self.db.read().await.authors().await?;
+        Ok(self.values_feed("authors", "Authors", authors, page))
+    }
+
+    async fn series_feed(&self, page: usize) -> Result<Response, DatabaseError<D::Error>> {
+        let series = self
+            .db
+            .read()
+            .await
+            .column_stats(&ColumnIdentifier::Series)
+            .await?;
+        Ok(self.values_feed("series", "Series", series, page))
+    }
+
+    /// Returns the file of the variant at `index` of the book with the given ID.
+    async fn variant_file(
+        &self,
+        id: &str,
+        index: &str,
+    ) -> Result<Response, DatabaseError<D::Error>> {
+        let id = id.parse().ok().and_then(|id| BookID::try_from(id).ok());
+        let (id, index) = match (id, index.parse::<usize>().ok()) {
+            (Some(id), Some(index)) => (id, index),
+            _ => return Ok(Response::error(NOT_FOUND)),
+        };
+        let book = self.db.read().await.get_book(id).await?;
+        let variant = match book.variants().get(index) {
+            Some(variant) => variant,
+            None => return Ok(Response::error(NOT_FOUND)),
+        };
+        let file = match tokio::fs::File::open(&variant.path).await {
+            Ok(file) => file,
+            Err(e) => {
+                tracing::info!("Failed to open {}: {:?}", variant.path.display(), e);
+                return Ok(Response::error(NOT_FOUND));
+            }
+        };
+        let len = file.metadata().await?.len();
+        Ok(Response {
+            status: OK,
+            content_type: mime_type(&variant.book_type),
+            body: Body::File {
+                file,
+                name: variant
+                    .path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().replace('"', "'"))
+                    .unwrap_or_default(),
+                len,
+            },
+        })
+    }
+}
+
+/// Returns the OpenSearch description of the catalog's search.
+fn opensearch() -> Response {
+    let mut description = XmlElement::new("OpenSearchDescription")
+        .attr("xmlns", "http://a9.com/-/spec/opensearch/1.1/");
+    description.push(XmlElement::new("ShortName").text("bookworm"));
+    description.push(XmlElement::new("Description").text("Search books by title"));
+    description.push(
+        XmlElement::new("Url")
+            .attr("type", ACQUISITION)
+            .attr("template", "/opds/search?q={searchTerms}"),
+    );
+    Response::xml(OPENSEARCH, &description)
+}
+
+/// Writes `response` to `stream`, streaming files rather than reading them into memory.
+async fn write_response<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    response: Response,
+) -> Result<(), std::io::Error> {
+    let Response {
+        status,
+        content_type,
+        body,
+    } = response;
+    let head = |len: u64, extra: &str| {
+        format!(
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n",
+            status, content_type, len, extra
+        )
+    };
+    match body {
+        Body::Text(text) => {
+            stream
+                .write_all(head(text.len() as u64, "").as_bytes())
+                .await?;
+            stream.write_all(text.as_bytes()).await?;
+        }
+        Body::File {
+            mut file,
+            name,
+            len,
+        } => {
+            let disposition = format!("Content-Disposition: attachment; filename=\"{}\"\r\n", name);
+            stream.write_all(head(len, &disposition).as_bytes()).await?;
+            tokio::io::copy(&mut file, stream).await?;
+        }
+    }
+    stream.flush().await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use bookworm_database::SQLiteDatabase;
+    use bookworm_records::test_util::variant;
+
+    fn text(response: &Response) -> &str {
+        match &response.body {
+            Body::Text(text) => text,
+            Body::File { .. } => panic!("expected a document"),
+        }
+    }
+
+    #[test]
+    fn test_percent_encoding() {
+        assert_eq!(percent_encode("Le Guin, Ursula"), "Le%20Guin%2C%20Ursula");
+        assert_eq!(percent_decode("Le%20Guin%2C%20Ursula"), "Le Guin, Ursula");
+        assert_eq!(percent_decode(&percent_encode("Толстой")), "Толстой");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(
+            query_param("page=2&q=dune+messiah", "q").unwrap(),
+            "dune messiah"
+        );
+        assert_eq!(query_param("q=a%2Bb", "q").unwrap(), "a+b");
+        assert_eq!(query_param("page=2", "q"), None);
+        assert_eq!(
+            page_href("/opds/search?q=dune", 1),
+            "/opds/search?q=dune&page=1"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_catalog() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        let file = dir.path().join("dune.epub");
+        std::fs::write(&file, b"epub contents").unwrap();
+        let books = [
+            ("Dune", "Frank Herbert", file),
+            ("Emma", "Jane Austen", dir.path().join("emma.epub")),
+            (
+                "Persuasion",
+                "Jane Austen",
+                dir.path().join("persuasion.epub"),
+            ),
+        ];
+        let ids = db
+            .insert_books(books.iter().enumerate().map(|(i, (title, author, path))| {
+                let mut variant = variant(title, i as u8);
+                variant.path = path.clone();
+                variant.additional_authors = Some(vec![author.to_string()]);
+                variant
+            }))
+            .await
+            .unwrap();
+        let mut server = OpdsServer::new(db);
+        server.set_page_size(2);
+
+        let root = server.respond("/opds").await;
+        assert_eq!(root.status, OK);
+        assert_eq!(root.content_type, NAVIGATION);
+        assert!(text(&root).contains("href=\"/opds/authors\""));
+
+        let first = server.respond("/opds/books").await;
+        assert_eq!(first.content_type, ACQUISITION);
+        assert!(text(&first).contains("<title>Dune</title>"));
+        assert!(text(&first).contains("<title>Emma</title>"));
+        assert!(!text(&first).contains("<title>Persuasion</title>"));
+        assert!(text(&first).contains("href=\"/opds/books?page=1\""));
+        assert!(text(&first).contains(&format!("href=\"/opds/books/{}/0\"", ids[0])));
+
+        let second = server.respond("/opds/books?page=1").await;
+        assert!(text(&second).contains("<title>Persuasion</title>"));
+        assert!(text(&second).contains("rel=\"previous\""));
+        assert!(!text(&second).contains("rel=\"next\""));
+
+        let authors = server.respond("/opds/authors").await;
+        assert!(text(&authors).contains("href=\"/opds/authors/Jane%20Austen\""));
+        let austen = server.respond("/opds/authors/Jane%20Austen").await;
+        assert!(text(&austen).contains("<title>Emma</title>"));
+        assert!(!text(&austen).contains("<title>Dune</title>"));
+
+        let search = server.respond("/opds/search?q=DUNE").await;
+        assert!(text(&search).contains("<title>Dune</title>"));
+        assert!(!text(&search).contains("<title>Emma</title>"));
+        assert_eq!(server.respond("/opds/search").await.status, BAD_REQUEST);
+
+        match server
+            .respond(&format!("/opds/books/{}/0", ids[0]))
+            .await
+            .body
+        {
+            Body::File { name, len, .. } => {
+                assert_eq!(name, "dune.epub");
+                assert_eq!(len, 13);
+            }
+            Body::Text(_) => panic!("expected a file"),
+        }
+        // The file of the second book does not exist.
+        let missing = [
+            format!("/opds/books/{}/0", ids[1]),
+            format!("/opds/books/{}/1", ids[0]),
+            String::from("/opds/books/100/0"),
+            String::from("/opds/books/0/0"),
+            String::from("/opds/unknown"),
+        ];
+        for target in &missing {
+            assert_eq!(server.respond(target).await.status, NOT_FOUND);
+        }
+    }
+}
@@ -2,9 +2,11 @@ use std::collections::HashMap;
 use std::path::PathBuf;
 use std::str::FromStr;
 
+use encoding_rs::Encoding;
 use itertools::Itertools;
 
-use bookworm_database::search::{Search, SearchMode};
+use bookworm_database::search::{self, Search, SearchMode, SizeUnits};
+use bookworm_database::{DelimitedFormat, DetectionStrategy, ExportFormat};
 use bookworm_input::Edit;
 use bookworm_records::book::{BookID, ColumnIdentifier};
 use bookworm_records::ColumnOrder;
@@ -22,8 +24,57 @@ pub enum CommandError {
     UnknownFlag,
     UnexpectedArguments,
     ConflictingArguments,
+    /// A search expression could not be parsed.
+    InvalidSearch(search::Error),
 }
 
+/// The name of each command, as typed at the start of the command line.
+pub const COMMAND_NAMES: [&str; 40] = [
+    ":d",
+    ":e",
+    ":a",
+    ":c",
+    ":s",
+    ":o",
+    ":open",
+    ":folder",
+    ":read",
+    ":unread",
+    ":m",
+    ":dups",
+    ":q",
+    ":w",
+    ":wq",
+    ":f",
+    ":tf",
+    ":j",
+    ":hl",
+    ":n",
+    ":N",
+    ":h",
+    ":update",
+    ":import",
+    ":variant",
+    ":authors",
+    ":export",
+    ":stats",
+    ":series",
+    ":tags",
+    ":tag",
+    ":diff",
+    ":fts",
+    ":rescan",
+    ":variants",
+    ":find",
+    ":u",
+    ":r",
+    ":refresh",
+    ":fetch",
+];
+
+/// The commands which take column names as arguments.
+pub const COLUMN_COMMANDS: [&str; 3] = [":c", ":s", ":stats"];
+
 enum CommandRoot {
     Delete,
     Edit,
@@ -31,14 +82,38 @@ enum CommandRoot {
     ModifyColumns,
     SortColumns,
     OpenBook,
+    OpenFolder,
+    Read,
+    Unread,
     MergeBooks,
+    Duplicates,
     Quit,
     Write,
     WriteQuit,
     FindMatches,
+    TempFindMatches,
     JumpTo,
+    Highlight,
+    NextMatch,
+    PrevMatch,
     Help,
     Update,
+    Import,
+    Variant,
+    Authors,
+    Export,
+    Stats,
+    Series,
+    Tags,
+    Diff,
+    Fts,
+    Rescan,
+    Variants,
+    FindText,
+    Undo,
+    Redo,
+    Refresh,
+    Fetch,
 }
 
 impl FromStr for CommandRoot {
@@ -51,15 +126,39 @@ impl FromStr for CommandRoot {
             ":a" => CommandRoot::AddBooks,
             ":c" => CommandRoot::ModifyColumns,
             ":s" => CommandRoot::SortColumns,
-            ":o" => CommandRoot::OpenBook,
+            ":o" | ":open" => CommandRoot::OpenBook,
+            ":folder" => CommandRoot::OpenFolder,
+            ":read" => CommandRoot::Read,
+            ":unread" => CommandRoot::Unread,
             ":m" => CommandRoot::MergeBooks,
+            ":dups" => CommandRoot::Duplicates,
             ":q" => CommandRoot::Quit,
             ":w" => CommandRoot::Write,
             ":wq" => CommandRoot::WriteQuit,
             ":f" => CommandRoot::FindMatches,
+            ":tf" => CommandRoot::TempFindMatches,
             ":j" => CommandRoot::JumpTo,
+            ":hl" => CommandRoot::Highlight,
+            ":n" => CommandRoot::NextMatch,
+            ":N" => CommandRoot::PrevMatch,
             ":h" => CommandRoot::Help,
             ":update" => CommandRoot::Update,
+            ":import" => CommandRoot::Import,
+            ":variant" => CommandRoot::Variant,
+            ":authors" => CommandRoot::Authors,
+            ":export" => CommandRoot::Export,
+            ":stats" => CommandRoot::Stats,
+            ":series" => CommandRoot::Series,
+            ":tags" | ":tag" => CommandRoot::Tags,
+            ":diff" => CommandRoot::Diff,
+            ":fts" => CommandRoot::Fts,
+            ":rescan" => CommandRoot::Rescan,
+            ":variants" => CommandRoot::Variants,
+            ":find" => CommandRoot::FindText,
+            ":u" => CommandRoot::Undo,
+            ":r" => CommandRoot::Redo,
+            ":refresh" => CommandRoot::Refresh,
+            ":fetch" => CommandRoot::Fetch,
             _ => return Err(CommandError::UnknownCommand),
         })
     }
@@ -74,17 +173,50 @@ pub enum Command {
     DeleteAll,
     EditBook(BookIndex, Box<[(ColumnIdentifier, Edit)]>),
     AddBooks(Box<[Source]>),
+    /// Add the books in a directory, up to the given depth, reporting progress as files are read.
+    AddDir(PathBuf, u8),
     ModifyColumns(Box<[ModifyColumn]>),
     SortColumns(Box<[(ColumnIdentifier, ColumnOrder)]>),
-    OpenBookIn(BookIndex, usize, Target),
+    OpenBookIn(BookIndex, Option<usize>, Target),
+    /// Open the directory which contains the book's preferred variant.
+    OpenFolder(BookIndex),
     TryMergeAllBooks,
+    FindDuplicates(DetectionStrategy),
     Quit,
     Write,
     WriteAndQuit,
     FilterMatches(Box<[Search]>),
+    TempFilterMatches(Box<[Search]>),
     JumpTo(Box<[Search]>),
+    HighlightMatches(Box<[Search]>),
+    NextMatch,
+    PrevMatch,
     Help(String),
     UpdateBooks(Box<[Source]>),
+    ImportTags(PathBuf, DelimitedFormat),
+    ImportDb(PathBuf),
+    SetPreferredVariant(BookIndex, usize),
+    /// Consolidate spelling variants of authors, including near-matches if confirmed.
+    DedupeAuthors(bool),
+    ExportView(ExportFormat, PathBuf),
+    ExportDatabase(ExportFormat, PathBuf),
+    Stats,
+    ColumnStats(ColumnIdentifier),
+    ReadingStats(Option<(PathBuf, DelimitedFormat)>),
+    SeriesFromFilename(String),
+    ClearTags(bool),
+    /// Rename a tag in every book.
+    RenameTag(String, String),
+    /// Remove a tag from every book, if confirmed.
+    DeleteTag(String, bool),
+    DiffBook(BookID),
+    RebuildFts,
+    Rescan(bool, bool),
+    ListVariants,
+    Undo,
+    Redo,
+    Refresh,
+    Fetch(bool),
     GeneralHelp,
     // TODO:
     //  eg. :m 1 2 -> merge 2 into 1
@@ -97,8 +229,15 @@ impl Command {
     pub fn requires_ui(&self) -> bool {
         use Command::*;
         match self {
-            EditBook(b, _) | OpenBookIn(b, _, _) => b == &BookIndex::Selected,
-            DeleteSelected | ModifyColumns(_) | SortColumns(_) | FilterMatches(_) => true,
+            EditBook(b, _) | OpenBookIn(b, _, _) | SetPreferredVariant(b, _) => {
+                b == &BookIndex::Selected
+            }
+            OpenFolder(b) => b == &BookIndex::Selected,
+            DeleteSelected | ModifyColumns(_) | SortColumns(_) => true,
+            FilterMatches(_) | TempFilterMatches(_) | ExportView(_, _) => true,
+            SeriesFromFilename(_) | ClearTags(_) | DiffBook(_) => true,
+            Rescan(_, _) | Refresh | Fetch(_) | ListVariants => true,
+            HighlightMatches(_) | NextMatch | PrevMatch => true,
             _ => false,
         }
     }
@@ -185,14 +324,47 @@ impl CommandRoot {
             }
             CommandRoot::SortColumns => SortColumns::from_args(start_args, trailing_args)?.into(),
             CommandRoot::OpenBook => OpenBook::from_args(start_args, trailing_args)?.into(),
+            CommandRoot::OpenFolder => OpenFolder::from_args(start_args, trailing_args)?.into(),
+            CommandRoot::Read => MarkRead::from_args(start_args, trailing_args)?.into(),
+            CommandRoot::Unread => MarkRead {
+                read: false,
+                ..MarkRead::from_args(start_args, trailing_args)?
+            }
+            .into(),
             CommandRoot::MergeBooks => Merge::from_args(start_args, trailing_args)?.into(),
+            CommandRoot::Duplicates => FindDuplicates::from_args(start_args, trailing_args)?.into(),
             CommandRoot::Quit => Quit::from_args(start_args, trailing_args)?.into(),
             CommandRoot::Write => Write::from_args(start_args, trailing_args)?.into(),
             CommandRoot::WriteQuit => WriteQuit::from_args(start_args, trailing_args)?.into(),
             CommandRoot::FindMatches => Filter::from_args(start_args, trailing_args)?.into(),
+            CommandRoot::TempFindMatches => {
+                TempFilter::from_args(start_args, trailing_args)?.into()
+            }
             CommandRoot::JumpTo => Jump::from_args(start_args, trailing_args)?.into(),
+            CommandRoot::Highlight => Highlight::from_args(start_args, trailing_args)?.into(),
+            CommandRoot::NextMatch => NextMatch::from_args(start_args, trailing_args)?.into(),
+            CommandRoot::PrevMatch => PrevMatch::from_args(start_args, trailing_args)?.into(),
             CommandRoot::Help => Help::from_args(start_args, trailing_args)?.into(),
             CommandRoot::Update => UpdateBooks::from_args(start_args, trailing_args)?.into(),
+            CommandRoot::Import => match start_args.first().map(String::as_str) {
+                Some("db") => ImportDb::from_args(start_args, trailing_args)?.into(),
+                _ => ImportTags::from_args(start_args, trailing_args)?.into(),
+            },
+            CommandRoot::Variant => PreferredVariant::from_args(start_args, trailing_args)?.into(),
+            CommandRoot::Authors => DedupeAuthors::from_args(start_args, trailing_args)?.into(),
+            CommandRoot::Export => Export::from_args(start_args, trailing_args)?.into(),
+            CommandRoot::Stats => Stats::from_args(start_args, trailing_args)?.into(),
+            CommandRoot::Series => SeriesFromFilename::from_args(start_args, trailing_args)?.into(),
+            CommandRoot::Tags => Tags::from_args(start_args, trailing_args)?.into(),
+            CommandRoot::Diff => DiffBook::from_args(start_args, trailing_args)?.into(),
+            CommandRoot::Fts => RebuildFts::from_args(start_args, trailing_args)?.into(),
+            CommandRoot::Rescan => Rescan::from_args(start_args, trailing_args)?.into(),
+            CommandRoot::Variants => ListVariants::from_args(start_args, trailing_args)?.into(),
+            CommandRoot::FindText => FindText::from_args(start_args, trailing_args)?.into(),
+            CommandRoot::Undo => Undo::from_args(start_args, trailing_args)?.into(),
+            CommandRoot::Redo => Redo::from_args(start_args, trailing_args)?.into(),
+            CommandRoot::Refresh => Refresh::from_args(start_args, trailing_args)?.into(),
+            CommandRoot::Fetch => Fetch::from_args(start_args, trailing_args)?.into(),
         })
     }
 }
@@ -333,7 +505,14 @@ struct AddBooks {
 
 impl From<AddBooks> for Command {
     fn from(ab: AddBooks) -> Self {
-        Command::AddBooks(ab.sources)
+        // A lone directory may hold many books, so its progress is reported.
+        let mut sources = ab.sources.into_vec();
+        if let [Source::Dir(_, _)] = sources.as_slice() {
+            if let Some(Source::Dir(dir, depth)) = sources.pop() {
+                return Command::AddDir(dir, depth);
+            }
+        }
+        Command::AddBooks(sources.into_boxed_slice())
     }
 }
 
@@ -497,322 +676,1119 @@ impl CommandParser for UpdateBooks {
     }
 }
 
-struct EditBook {
-    index: BookIndex,
-    edits: Box<[(ColumnIdentifier, Edit)]>,
+/// Parses a delimiter, which is either a single character, or "tab" for tabs.
+fn parse_delimiter(delimiter: &str) -> Result<char, CommandError> {
+    if delimiter.eq_ignore_ascii_case("tab") || delimiter == "\\t" {
+        return Ok('\t');
+    }
+    let mut chars = delimiter.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => Err(CommandError::UnexpectedArguments),
+    }
 }
 
-impl From<EditBook> for Command {
-    fn from(eb: EditBook) -> Self {
-        Command::EditBook(eb.index, eb.edits)
+/// Applies the delimiter (-d) and encoding (-e) flags to `format`.
+fn parse_delimited_flags(
+    trailing_args: Vec<(String, Vec<String>)>,
+    mut format: DelimitedFormat,
+) -> Result<DelimitedFormat, CommandError> {
+    for (flag, args) in trailing_args {
+        let arg = match args.as_slice() {
+            [arg] => remove_string_quotes(arg.clone()),
+            [] => return Err(insuf()),
+            _ => return Err(CommandError::UnexpectedArguments),
+        };
+        match flag.as_str() {
+            "-d" => format.delimiter = parse_delimiter(&arg)?,
+            "-e" => {
+                format.encoding =
+                    Encoding::for_label(arg.as_bytes()).ok_or(CommandError::UnexpectedArguments)?
+            }
+            _ => return Err(CommandError::UnknownFlag),
+        }
     }
+    Ok(format)
 }
 
-impl CommandParser for EditBook {
+struct ImportTags {
+    path: PathBuf,
+    format: DelimitedFormat,
+}
+
+impl From<ImportTags> for Command {
+    fn from(it: ImportTags) -> Self {
+        Command::ImportTags(it.path, it.format)
+    }
+}
+
+impl CommandParser for ImportTags {
     fn from_args(
         start_args: Vec<String>,
         trailing_args: Vec<(String, Vec<String>)>,
     ) -> Result<Self, CommandError> {
-        let (id, mut start_args) = if start_args.len() % 2 == 1 {
-            let mut args = start_args.into_iter();
-            let id = Some(
-                BookID::from_str(&args.next().ok_or_else(insuf)?)
-                    .map_err(|_| CommandError::UnexpectedArguments)?,
-            );
-            (id, args)
-        } else {
-            (None, start_args.into_iter())
-        };
+        let format = parse_delimited_flags(trailing_args, DelimitedFormat::default())?;
 
-        let mut edits = Vec::new();
-        while let Some(col) = start_args.next() {
-            edits.push((
-                ColumnIdentifier::from(col),
-                Edit::Replace(start_args.next().ok_or_else(insuf)?),
-            ));
+        let mut args = start_args.into_iter();
+        match args.next().as_deref() {
+            Some("tags") => {}
+            Some(_) => return Err(CommandError::UnexpectedArguments),
+            None => return Err(insuf()),
         }
 
-        for (flag, args) in trailing_args.into_iter() {
-            let mut args = args.into_iter();
-            let edit = match flag.as_str() {
-                "-d" => match ColumnIdentifier::from(args.next().ok_or_else(insuf)?) {
-                    ColumnIdentifier::Tags => match args.next() {
-                        None => (ColumnIdentifier::Tags, Edit::Delete),
-                        Some(tag) => (ColumnIdentifier::ExactTag(tag), Edit::Delete),
-                    },
-                    column => (column, Edit::Delete),
-                },
-                "-a" => match ColumnIdentifier::from(args.next().ok_or_else(insuf)?) {
-                    ColumnIdentifier::Tags => match (args.next(), args.next()) {
-                        (Some(value), None) => (ColumnIdentifier::Tags, Edit::Append(value)),
-                        (Some(tag), Some(value)) => {
-                            (ColumnIdentifier::ExactTag(tag), Edit::Append(value))
-                        }
-                        _ => return Err(CommandError::InsufficientArguments),
-                    },
-                    column => (column, Edit::Append(args.next().ok_or_else(insuf)?)),
-                },
-                "-r" => match ColumnIdentifier::from(args.next().ok_or_else(insuf)?) {
-                    ColumnIdentifier::Tags => match (args.next(), args.next()) {
-                        (Some(value), None) => (ColumnIdentifier::Tags, Edit::Replace(value)),
-                        (Some(tag), Some(value)) => {
-                            (ColumnIdentifier::ExactTag(tag), Edit::Replace(value))
-                        }
-                        _ => return Err(CommandError::InsufficientArguments),
-                    },
-                    column => (column, Edit::Replace(args.next().ok_or_else(insuf)?)),
-                },
-                _ => return Err(CommandError::UnknownFlag),
-            };
-
-            edits.push(edit);
-
-            while let Some(col) = args.next() {
-                edits.push((
-                    ColumnIdentifier::from(col),
-                    Edit::Replace(args.next().ok_or_else(insuf)?),
-                ));
-            }
+        let path = args.next().map(remove_string_quotes).ok_or_else(insuf)?;
+        if args.next().is_some() {
+            return Err(CommandError::UnexpectedArguments);
         }
 
-        if edits.is_empty() {
-            Err(CommandError::InsufficientArguments)
-        } else {
-            Ok(EditBook {
-                index: id.map(BookIndex::ID).unwrap_or(BookIndex::Selected),
-                edits: edits.into_boxed_slice(),
-            })
-        }
+        Ok(ImportTags {
+            path: PathBuf::from(path),
+            format,
+        })
     }
 }
 
-struct SortColumns {
-    sorts: Box<[(ColumnIdentifier, ColumnOrder)]>,
-}
+struct ImportDb(PathBuf);
 
-impl From<SortColumns> for Command {
-    fn from(sc: SortColumns) -> Self {
-        Command::SortColumns(sc.sorts)
+impl From<ImportDb> for Command {
+    fn from(id: ImportDb) -> Self {
+        Command::ImportDb(id.0)
     }
 }
 
-impl CommandParser for SortColumns {
+impl CommandParser for ImportDb {
     fn from_args(
         start_args: Vec<String>,
         trailing_args: Vec<(String, Vec<String>)>,
     ) -> Result<Self, CommandError> {
-        let mut sort_cols: Vec<_> = start_args
-            .into_iter()
-            .map(|s| (ColumnIdentifier::from(s), ColumnOrder::Ascending))
-            .collect();
-
-        for (flag, args) in trailing_args.into_iter() {
-            if flag != "-d" {
-                return Err(CommandError::UnknownFlag);
-            }
-            let mut args = args.into_iter();
-            sort_cols.push((
-                ColumnIdentifier::from(args.next().ok_or_else(insuf)?),
-                ColumnOrder::Descending,
-            ));
-            sort_cols.extend(args.map(|s| (ColumnIdentifier::from(s), ColumnOrder::Ascending)));
+        if !trailing_args.is_empty() {
+            return Err(CommandError::UnknownFlag);
         }
 
-        if sort_cols.is_empty() {
-            Err(CommandError::InsufficientArguments)
-        } else {
-            Ok(SortColumns {
-                sorts: sort_cols.into_boxed_slice(),
-            })
+        match start_args.as_slice() {
+            [action, path] if action == "db" => {
+                Ok(ImportDb(PathBuf::from(remove_string_quotes(path.clone()))))
+            }
+            [_] => Err(insuf()),
+            _ => Err(CommandError::UnexpectedArguments),
         }
     }
 }
 
-#[cfg_attr(test, derive(PartialEq))]
-#[derive(Debug)]
-pub enum ModifyColumn {
-    Remove(String),
-    Add(String),
-}
-
-struct ModifyColumns {
-    columns: Box<[ModifyColumn]>,
+struct DedupeAuthors {
+    confirmed: bool,
 }
 
-impl From<ModifyColumns> for Command {
-    fn from(mc: ModifyColumns) -> Self {
-        Command::ModifyColumns(mc.columns)
+impl From<DedupeAuthors> for Command {
+    fn from(d: DedupeAuthors) -> Self {
+        Command::DedupeAuthors(d.confirmed)
     }
 }
 
-impl CommandParser for ModifyColumns {
+impl CommandParser for DedupeAuthors {
     fn from_args(
         start_args: Vec<String>,
         trailing_args: Vec<(String, Vec<String>)>,
     ) -> Result<Self, CommandError> {
-        let mut columns: Vec<_> = start_args.into_iter().map(ModifyColumn::Add).collect();
+        let mut confirmed = false;
+        for (flag, args) in trailing_args {
+            match flag.as_str() {
+                "-y" if args.is_empty() => confirmed = true,
+                "-y" => return Err(CommandError::UnexpectedArguments),
+                _ => return Err(CommandError::UnknownFlag),
+            }
+        }
 
-        for (remove_col, add_cols) in trailing_args.into_iter() {
-            columns.push(ModifyColumn::Remove(
-                remove_col
-                    .strip_prefix('-')
-                    .expect("col starts with '-' if it ends up in trailing_args")
-                    .to_string(),
-            ));
-            columns.extend(add_cols.into_iter().map(ModifyColumn::Add));
+        match start_args.as_slice() {
+            [action] if action == "dedupe" => Ok(DedupeAuthors { confirmed }),
+            [] => Err(insuf()),
+            _ => Err(CommandError::UnexpectedArguments),
         }
+    }
+}
 
-        Ok(ModifyColumns {
-            columns: columns.into_boxed_slice(),
-        })
+enum Export {
+    // The books in the current view.
+    View(ExportFormat, PathBuf),
+    // Every book in the database.
+    Database(ExportFormat, PathBuf),
+}
+
+impl From<Export> for Command {
+    fn from(export: Export) -> Self {
+        match export {
+            Export::View(format, path) => Command::ExportView(format, path),
+            Export::Database(format, path) => Command::ExportDatabase(format, path),
+        }
     }
 }
 
-struct Matches {
-    matches: Box<[Search]>,
+fn export_format(name: &str) -> Result<ExportFormat, CommandError> {
+    ExportFormat::from_name(name).ok_or(CommandError::UnexpectedArguments)
 }
 
-impl Matches {
+impl CommandParser for Export {
     fn from_args(
         start_args: Vec<String>,
         trailing_args: Vec<(String, Vec<String>)>,
     ) -> Result<Self, CommandError> {
-        let mut matches = vec![];
-        let mut arg_iter = start_args.into_iter();
-
-        while let Some(col) = arg_iter.next() {
-            let search = arg_iter.next().ok_or_else(insuf)?;
-            matches.push(Search {
-                mode: SearchMode::Default,
-                column: ColumnIdentifier::from(col),
-                search: remove_string_quotes(search),
-            });
-        }
-
-        for (flag, args) in trailing_args {
-            let mode = match flag.as_str() {
-                "-r" => Ok(SearchMode::Regex),
-                "-e" => Ok(SearchMode::ExactSubstring),
-                "-x" => Ok(SearchMode::ExactString),
-                _ => Err(CommandError::UnknownFlag),
-            }?;
-
-            let mut args = args.into_iter();
-            matches.push(Search {
-                mode,
-                column: ColumnIdentifier::from(args.next().ok_or_else(insuf)?),
-                search: remove_string_quotes(args.next().ok_or_else(insuf)?),
-            });
-            while let Some(col) = args.next() {
-                let search = args.next().ok_or_else(insuf)?;
-                matches.push(Search {
-                    mode: SearchMode::Default,
-                    column: ColumnIdentifier::from(col),
-                    search: remove_string_quotes(search),
-                });
+        let has_flags = !trailing_args.is_empty();
+        let csv = parse_delimited_flags(trailing_args, DelimitedFormat::csv())?;
+        let export = match start_args.as_slice() {
+            [view, format, path] if view == "view" => Export::View(
+                export_format(format)?,
+                PathBuf::from(remove_string_quotes(path.clone())),
+            ),
+            [view, ..] if view == "view" => return Err(insuf()),
+            // Without a format, the format is chosen by the file extension, defaulting to JSON.
+            [path] => {
+                let path = PathBuf::from(remove_string_quotes(path.clone()));
+                let format = ExportFormat::from_extension(&path).unwrap_or(ExportFormat::Json);
+                Export::Database(format, path)
             }
-        }
+            [format, path] => Export::Database(
+                export_format(format)?,
+                PathBuf::from(remove_string_quotes(path.clone())),
+            ),
+            [] => return Err(insuf()),
+            _ => return Err(CommandError::UnexpectedArguments),
+        };
 
-        if matches.is_empty() {
-            Err(CommandError::InsufficientArguments)
-        } else {
-            Ok(Matches {
-                matches: matches.into_boxed_slice(),
-            })
-        }
+        // The delimiter and encoding only apply to CSV files.
+        let with_csv = |format: ExportFormat| match format {
+            ExportFormat::Csv(_) => Ok(ExportFormat::Csv(csv)),
+            ExportFormat::Json if has_flags => Err(CommandError::UnexpectedArguments),
+            ExportFormat::Json => Ok(ExportFormat::Json),
+        };
+        Ok(match export {
+            Export::View(format, path) => Export::View(with_csv(format)?, path),
+            Export::Database(format, path) => Export::Database(with_csv(format)?, path),
+        })
     }
 }
 
-struct Jump {
-    matches: Matches,
+enum Stats {
+    // A summary of the whole library.
+    Library,
+    Column(ColumnIdentifier),
+    // Books added and finished per month, optionally written to a CSV file in the given format.
+    Reading(Option<(PathBuf, DelimitedFormat)>),
 }
 
-impl From<Jump> for Command {
-    fn from(j: Jump) -> Self {
-        Command::JumpTo(j.matches.matches)
+impl From<Stats> for Command {
+    fn from(stats: Stats) -> Self {
+        match stats {
+            Stats::Library => Command::Stats,
+            Stats::Column(column) => Command::ColumnStats(column),
+            Stats::Reading(path) => Command::ReadingStats(path),
+        }
     }
 }
 
-impl CommandParser for Jump {
+impl CommandParser for Stats {
     fn from_args(
         start_args: Vec<String>,
         trailing_args: Vec<(String, Vec<String>)>,
     ) -> Result<Self, CommandError> {
-        Ok(Jump {
-            matches: Matches::from_args(start_args, trailing_args)?,
-        })
+        // Only CSV files take a delimiter and encoding.
+        if let [reading, path] = start_args.as_slice() {
+            if reading == "reading" {
+                let format = parse_delimited_flags(trailing_args, DelimitedFormat::csv())?;
+                let path = PathBuf::from(remove_string_quotes(path.clone()));
+                return Ok(Stats::Reading(Some((path, format))));
+            }
+        }
+        if !trailing_args.is_empty() {
+            return Err(CommandError::UnknownFlag);
+        }
+
+        match start_args.as_slice() {
+            [reading] if reading == "reading" => Ok(Stats::Reading(None)),
+            [column] => Ok(Stats::Column(ColumnIdentifier::from(column))),
+            [] => Ok(Stats::Library),
+            _ => Err(CommandError::UnexpectedArguments),
+        }
     }
 }
 
-struct Filter {
-    matches: Matches,
+struct SeriesFromFilename {
+    pattern: String,
 }
 
-impl From<Filter> for Command {
-    fn from(f: Filter) -> Self {
-        Command::FilterMatches(f.matches.matches)
+impl From<SeriesFromFilename> for Command {
+    fn from(sf: SeriesFromFilename) -> Self {
+        Command::SeriesFromFilename(sf.pattern)
     }
 }
 
-impl CommandParser for Filter {
+impl CommandParser for SeriesFromFilename {
     fn from_args(
         start_args: Vec<String>,
         trailing_args: Vec<(String, Vec<String>)>,
     ) -> Result<Self, CommandError> {
-        Ok(Filter {
-            matches: Matches::from_args(start_args, trailing_args)?,
-        })
+        if !trailing_args.is_empty() {
+            return Err(CommandError::UnknownFlag);
+        }
+
+        let mut args = start_args.into_iter();
+        match args.next().as_deref() {
+            Some("from-filename") => {}
+            Some(_) => return Err(CommandError::UnexpectedArguments),
+            None => return Err(insuf()),
+        }
+
+        let pattern = args.next().map(remove_string_quotes).ok_or_else(insuf)?;
+        if args.next().is_some() {
+            return Err(CommandError::UnexpectedArguments);
+        }
+
+        Ok(SeriesFromFilename { pattern })
     }
 }
 
-struct Help {
-    term: Option<String>,
+enum Tags {
+    Clear(bool),
+    Rename(String, String),
+    Delete(String, bool),
 }
 
-impl From<Help> for Command {
-    fn from(h: Help) -> Self {
-        match h.term {
-            None => Command::GeneralHelp,
-            Some(term) => Command::Help(term),
+impl From<Tags> for Command {
+    fn from(t: Tags) -> Self {
+        match t {
+            Tags::Clear(confirmed) => Command::ClearTags(confirmed),
+            Tags::Rename(old, new) => Command::RenameTag(old, new),
+            Tags::Delete(name, confirmed) => Command::DeleteTag(name, confirmed),
         }
     }
 }
 
-impl CommandParser for Help {
+impl CommandParser for Tags {
     fn from_args(
         start_args: Vec<String>,
-        _ta: Vec<(String, Vec<String>)>,
+        trailing_args: Vec<(String, Vec<String>)>,
     ) -> Result<Self, CommandError> {
-        Ok(Help {
-            term: start_args.into_iter().next(),
-        })
-    }
-}
+        let mut confirmed = false;
+        for (flag, args) in trailing_args {
+            match flag.as_str() {
+                "-y" if args.is_empty() => confirmed = true,
+                "-y" => return Err(CommandError::UnexpectedArguments),
+                _ => return Err(CommandError::UnknownFlag),
+            }
+        }
 
-#[derive(Debug, Eq, PartialEq)]
-pub enum Target {
-    DefaultApp,
-    FileManager,
+        let mut args = start_args.into_iter().map(remove_string_quotes);
+        let action = args.next().ok_or_else(insuf)?;
+        let tags = match action.as_str() {
+            "clear-all" => Tags::Clear(confirmed),
+            "rename" => Tags::Rename(
+                args.next().ok_or_else(insuf)?,
+                args.next().ok_or_else(insuf)?,
+            ),
+            "delete" => Tags::Delete(args.next().ok_or_else(insuf)?, confirmed),
+            _ => return Err(CommandError::UnexpectedArguments),
+        };
+        if args.next().is_some() {
+            return Err(CommandError::UnexpectedArguments);
+        }
+        Ok(tags)
+    }
 }
 
-struct OpenBook {
-    target: Target,
-    book_index: BookIndex,
-    variant_index: usize,
+struct DiffBook {
+    id: BookID,
 }
 
-impl From<OpenBook> for Command {
-    fn from(ob: OpenBook) -> Self {
-        Command::OpenBookIn(ob.book_index, ob.variant_index, ob.target)
+impl From<DiffBook> for Command {
+    fn from(db: DiffBook) -> Self {
+        Command::DiffBook(db.id)
     }
 }
 
-impl CommandParser for OpenBook {
+impl CommandParser for DiffBook {
     fn from_args(
         start_args: Vec<String>,
         trailing_args: Vec<(String, Vec<String>)>,
     ) -> Result<Self, CommandError> {
-        let mut args = start_args.into_iter();
+        if !trailing_args.is_empty() {
+            return Err(CommandError::UnknownFlag);
+        }
+
+        match start_args.as_slice() {
+            [id] => Ok(DiffBook {
+                id: BookID::from_str(id).map_err(|_| CommandError::UnexpectedArguments)?,
+            }),
+            [] => Err(insuf()),
+            _ => Err(CommandError::UnexpectedArguments),
+        }
+    }
+}
+
+struct RebuildFts;
+
+impl From<RebuildFts> for Command {
+    fn from(_r: RebuildFts) -> Self {
+        Command::RebuildFts
+    }
+}
+
+impl CommandParser for RebuildFts {
+    fn from_args(
+        start_args: Vec<String>,
+        trailing_args: Vec<(String, Vec<String>)>,
+    ) -> Result<Self, CommandError> {
+        if !trailing_args.is_empty() {
+            return Err(CommandError::UnknownFlag);
+        }
+
+        match start_args.as_slice() {
+            [action] if action == "rebuild" => Ok(RebuildFts),
+            [] => Err(insuf()),
+            _ => Err(CommandError::UnexpectedArguments),
+        }
+    }
+}
+
+struct Rescan {
+    overwrite: bool,
+    confirmed: bool,
+}
+
+impl From<Rescan> for Command {
+    fn from(r: Rescan) -> Self {
+        Command::Rescan(r.overwrite, r.confirmed)
+    }
+}
+
+impl CommandParser for Rescan {
+    fn from_args(
+        start_args: Vec<String>,
+        trailing_args: Vec<(String, Vec<String>)>,
+    ) -> Result<Self, CommandError> {
+        if !start_args.is_empty() {
+            return Err(CommandError::UnexpectedArguments);
+        }
+
+        let mut overwrite = false;
+        let mut confirmed = false;
+        for (flag, args) in trailing_args {
+            if !args.is_empty() {
+                return Err(CommandError::UnexpectedArguments);
+            }
+            match flag.as_str() {
+                "--overwrite" => overwrite = true,
+                "-y" => confirmed = true,
+                _ => return Err(CommandError::UnknownFlag),
+            }
+        }
+
+        Ok(Rescan {
+            overwrite,
+            confirmed,
+        })
+    }
+}
+
+struct FindDuplicates {
+    strategy: DetectionStrategy,
+}
+
+impl From<FindDuplicates> for Command {
+    fn from(fd: FindDuplicates) -> Self {
+        Command::FindDuplicates(fd.strategy)
+    }
+}
+
+impl CommandParser for FindDuplicates {
+    fn from_args(
+        start_args: Vec<String>,
+        trailing_args: Vec<(String, Vec<String>)>,
+    ) -> Result<Self, CommandError> {
+        if !trailing_args.is_empty() {
+            return Err(CommandError::UnknownFlag);
+        }
+
+        // Identical files are the safest sign of duplicates, and are found by default.
+        let strategy = match start_args.as_slice() {
+            [] => DetectionStrategy::VariantHash,
+            [strategy] => match strategy.as_str() {
+                "hash" => DetectionStrategy::VariantHash,
+                "author" => DetectionStrategy::AuthorOverlap,
+                "title" => DetectionStrategy::Title,
+                _ => return Err(CommandError::UnexpectedArguments),
+            },
+            _ => return Err(CommandError::UnexpectedArguments),
+        };
+        Ok(FindDuplicates { strategy })
+    }
+}
+
+struct ListVariants;
+
+impl From<ListVariants> for Command {
+    fn from(_l: ListVariants) -> Self {
+        Command::ListVariants
+    }
+}
+
+impl CommandParser for ListVariants {
+    fn from_args(
+        start_args: Vec<String>,
+        trailing_args: Vec<(String, Vec<String>)>,
+    ) -> Result<Self, CommandError> {
+        if !trailing_args.is_empty() {
+            return Err(CommandError::UnknownFlag);
+        }
+        if !start_args.is_empty() {
+            return Err(CommandError::UnexpectedArguments);
+        }
+        Ok(ListVariants)
+    }
+}
+
+/// Checks that a command was given no arguments or flags.
+fn no_arguments(
+    start_args: Vec<String>,
+    trailing_args: Vec<(String, Vec<String>)>,
+) -> Result<(), CommandError> {
+    if !trailing_args.is_empty() {
+        return Err(CommandError::UnknownFlag);
+    }
+    if !start_args.is_empty() {
+        return Err(CommandError::UnexpectedArguments);
+    }
+    Ok(())
+}
+
+struct Undo;
+
+impl From<Undo> for Command {
+    fn from(_u: Undo) -> Self {
+        Command::Undo
+    }
+}
+
+impl CommandParser for Undo {
+    fn from_args(
+        start_args: Vec<String>,
+        trailing_args: Vec<(String, Vec<String>)>,
+    ) -> Result<Self, CommandError> {
+        no_arguments(start_args, trailing_args)?;
+        Ok(Undo)
+    }
+}
+
+struct Redo;
+
+impl From<Redo> for Command {
+    fn from(_r: Redo) -> Self {
+        Command::Redo
+    }
+}
+
+impl CommandParser for Redo {
+    fn from_args(
+        start_args: Vec<String>,
+        trailing_args: Vec<(String, Vec<String>)>,
+    ) -> Result<Self, CommandError> {
+        no_arguments(start_args, trailing_args)?;
+        Ok(Redo)
+    }
+}
+
+struct Refresh;
+
+impl From<Refresh> for Command {
+    fn from(_r: Refresh) -> Self {
+        Command::Refresh
+    }
+}
+
+impl CommandParser for Refresh {
+    fn from_args(
+        start_args: Vec<String>,
+        trailing_args: Vec<(String, Vec<String>)>,
+    ) -> Result<Self, CommandError> {
+        no_arguments(start_args, trailing_args)?;
+        Ok(Refresh)
+    }
+}
+
+struct Fetch {
+    overwrite: bool,
+}
+
+impl From<Fetch> for Command {
+    fn from(f: Fetch) -> Self {
+        Command::Fetch(f.overwrite)
+    }
+}
+
+impl CommandParser for Fetch {
+    fn from_args(
+        start_args: Vec<String>,
+        trailing_args: Vec<(String, Vec<String>)>,
+    ) -> Result<Self, CommandError> {
+        if !start_args.is_empty() {
+            return Err(CommandError::UnexpectedArguments);
+        }
+
+        let mut overwrite = false;
+        for (flag, args) in trailing_args {
+            if !args.is_empty() {
+                return Err(CommandError::UnexpectedArguments);
+            }
+            match flag.as_str() {
+                "--overwrite" => overwrite = true,
+                _ => return Err(CommandError::UnknownFlag),
+            }
+        }
+
+        Ok(Fetch { overwrite })
+    }
+}
+
+struct PreferredVariant {
+    book_index: BookIndex,
+    variant_index: usize,
+}
+
+impl From<PreferredVariant> for Command {
+    fn from(pv: PreferredVariant) -> Self {
+        Command::SetPreferredVariant(pv.book_index, pv.variant_index)
+    }
+}
+
+impl CommandParser for PreferredVariant {
+    fn from_args(
+        start_args: Vec<String>,
+        trailing_args: Vec<(String, Vec<String>)>,
+    ) -> Result<Self, CommandError> {
+        if !trailing_args.is_empty() {
+            return Err(CommandError::UnknownFlag);
+        }
+
+        let mut args = start_args.into_iter();
+        match args.next().as_deref() {
+            Some("primary") => {}
+            Some(_) => return Err(CommandError::UnexpectedArguments),
+            None => return Err(insuf()),
+        }
+
+        let variant_index = args
+            .next()
+            .ok_or_else(insuf)
+            .and_then(|i| usize::from_str(&i).map_err(|_| CommandError::UnexpectedArguments))?;
+
+        let book_index = match args.next() {
+            None => BookIndex::Selected,
+            Some(id) => BookID::from_str(&id)
+                .map(BookIndex::ID)
+                .map_err(|_| CommandError::UnexpectedArguments)?,
+        };
+
+        if args.next().is_some() {
+            return Err(CommandError::UnexpectedArguments);
+        }
+
+        Ok(PreferredVariant {
+            book_index,
+            variant_index,
+        })
+    }
+}
+
+struct EditBook {
+    index: BookIndex,
+    edits: Box<[(ColumnIdentifier, Edit)]>,
+}
+
+impl From<EditBook> for Command {
+    fn from(eb: EditBook) -> Self {
+        Command::EditBook(eb.index, eb.edits)
+    }
+}
+
+impl CommandParser for EditBook {
+    fn from_args(
+        start_args: Vec<String>,
+        trailing_args: Vec<(String, Vec<String>)>,
+    ) -> Result<Self, CommandError> {
+        let (id, mut start_args) = if start_args.len() % 2 == 1 {
+            let mut args = start_args.into_iter();
+            let id = Some(
+                BookID::from_str(&args.next().ok_or_else(insuf)?)
+                    .map_err(|_| CommandError::UnexpectedArguments)?,
+            );
+            (id, args)
+        } else {
+            (None, start_args.into_iter())
+        };
+
+        let mut edits = Vec::new();
+        while let Some(col) = start_args.next() {
+            edits.push((
+                parse_column(col),
+                Edit::Replace(start_args.next().ok_or_else(insuf)?),
+            ));
+        }
+
+        for (flag, args) in trailing_args.into_iter() {
+            let mut args = args.into_iter();
+            let edit = match flag.as_str() {
+                "-d" => match parse_column(args.next().ok_or_else(insuf)?) {
+                    ColumnIdentifier::Tags => match args.next() {
+                        None => (ColumnIdentifier::Tags, Edit::Delete),
+                        Some(tag) => (ColumnIdentifier::ExactTag(tag), Edit::Delete),
+                    },
+                    ColumnIdentifier::MultiMap(name) => match args.next() {
+                        None => (ColumnIdentifier::MultiMap(name), Edit::Delete),
+                        Some(value) => (ColumnIdentifier::MultiMapExact(name, value), Edit::Delete),
+                    },
+                    column => (column, Edit::Delete),
+                },
+                "-a" => match parse_column(args.next().ok_or_else(insuf)?) {
+                    ColumnIdentifier::Tags => match (args.next(), args.next()) {
+                        (Some(value), None) => (ColumnIdentifier::Tags, Edit::Append(value)),
+                        (Some(tag), Some(value)) => {
+                            (ColumnIdentifier::ExactTag(tag), Edit::Append(value))
+                        }
+                        _ => return Err(CommandError::InsufficientArguments),
+                    },
+                    ColumnIdentifier::MultiMap(name) => match (args.next(), args.next()) {
+                        (Some(value), None) => {
+                            (ColumnIdentifier::MultiMap(name), Edit::Append(value))
+                        }
+                        (Some(old), Some(value)) => (
+                            ColumnIdentifier::MultiMapExact(name, old),
+                            Edit::Append(value),
+                        ),
+                        _ => return Err(CommandError::InsufficientArguments),
+                    },
+                    column => (column, Edit::Append(args.next().ok_or_else(insuf)?)),
+                },
+                "-r" => match parse_column(args.next().ok_or_else(insuf)?) {
+                    ColumnIdentifier::Tags => match (args.next(), args.next()) {
+                        (Some(value), None) => (ColumnIdentifier::Tags, Edit::Replace(value)),
+                        (Some(tag), Some(value)) => {
+                            (ColumnIdentifier::ExactTag(tag), Edit::Replace(value))
+                        }
+                        _ => return Err(CommandError::InsufficientArguments),
+                    },
+                    ColumnIdentifier::MultiMap(name) => match (args.next(), args.next()) {
+                        (Some(value), None) => {
+                            (ColumnIdentifier::MultiMap(name), Edit::Replace(value))
+                        }
+                        (Some(old), Some(value)) => (
+                            ColumnIdentifier::MultiMapExact(name, old),
+                            Edit::Replace(value),
+                        ),
+                        _ => return Err(CommandError::InsufficientArguments),
+                    },
+                    column => (column, Edit::Replace(args.next().ok_or_else(insuf)?)),
+                },
+                _ => return Err(CommandError::UnknownFlag),
+            };
+
+            edits.push(edit);
+
+            while let Some(col) = args.next() {
+                edits.push((
+                    parse_column(col),
+                    Edit::Replace(args.next().ok_or_else(insuf)?),
+                ));
+            }
+        }
+
+        if edits.is_empty() {
+            Err(CommandError::InsufficientArguments)
+        } else {
+            Ok(EditBook {
+                index: id.map(BookIndex::ID).unwrap_or(BookIndex::Selected),
+                edits: edits.into_boxed_slice(),
+            })
+        }
+    }
+}
+
+struct SortColumns {
+    sorts: Box<[(ColumnIdentifier, ColumnOrder)]>,
+}
+
+/// Returns the column which `name` refers to. Names starting with `#` refer to multimap tags,
+/// which may have several values for each book.
+fn parse_column(name: String) -> ColumnIdentifier {
+    match name.strip_prefix('#') {
+        Some(tag) => ColumnIdentifier::MultiMap(tag.to_lowercase()),
+        None => ColumnIdentifier::from(name),
+    }
+}
+
+impl From<SortColumns> for Command {
+    fn from(sc: SortColumns) -> Self {
+        Command::SortColumns(sc.sorts)
+    }
+}
+
+impl CommandParser for SortColumns {
+    fn from_args(
+        start_args: Vec<String>,
+        trailing_args: Vec<(String, Vec<String>)>,
+    ) -> Result<Self, CommandError> {
+        let mut sort_cols: Vec<_> = start_args
+            .into_iter()
+            .map(|s| (parse_column(s), ColumnOrder::Ascending))
+            .collect();
+
+        for (flag, args) in trailing_args.into_iter() {
+            if flag != "-d" {
+                return Err(CommandError::UnknownFlag);
+            }
+            let mut args = args.into_iter();
+            sort_cols.push((
+                parse_column(args.next().ok_or_else(insuf)?),
+                ColumnOrder::Descending,
+            ));
+            sort_cols.extend(args.map(|s| (parse_column(s), ColumnOrder::Ascending)));
+        }
+
+        if sort_cols.is_empty() {
+            Err(CommandError::InsufficientArguments)
+        } else {
+            Ok(SortColumns {
+                sorts: sort_cols.into_boxed_slice(),
+            })
+        }
+    }
+}
+
+#[cfg_attr(test, derive(PartialEq))]
+#[derive(Debug)]
+pub enum ModifyColumn {
+    Remove(String),
+    Add(String),
+}
+
+struct ModifyColumns {
+    columns: Box<[ModifyColumn]>,
+}
+
+impl From<ModifyColumns> for Command {
+    fn from(mc: ModifyColumns) -> Self {
+        Command::ModifyColumns(mc.columns)
+    }
+}
+
+impl CommandParser for ModifyColumns {
+    fn from_args(
+        start_args: Vec<String>,
+        trailing_args: Vec<(String, Vec<String>)>,
+    ) -> Result<Self, CommandError> {
+        let mut columns: Vec<_> = start_args.into_iter().map(ModifyColumn::Add).collect();
+
+        for (remove_col, add_cols) in trailing_args.into_iter() {
+            columns.push(ModifyColumn::Remove(
+                remove_col
+                    .strip_prefix('-')
+                    .expect("col starts with '-' if it ends up in trailing_args")
+                    .to_string(),
+            ));
+            columns.extend(add_cols.into_iter().map(ModifyColumn::Add));
+        }
+
+        Ok(ModifyColumns {
+            columns: columns.into_boxed_slice(),
+        })
+    }
+}
+
+struct Matches {
+    matches: Box<[Search]>,
+}
+
+/// Returns a default search over `col`, as described by `Search::for_column`.
+fn default_search(col: String, search: String) -> Search {
+    Search::for_column(&col, remove_string_quotes(search))
+}
+
+impl Matches {
+    fn from_args(
+        start_args: Vec<String>,
+        trailing_args: Vec<(String, Vec<String>)>,
+    ) -> Result<Self, CommandError> {
+        let mut matches = vec![];
+        // Searches of the form column:value are parsed as a single search expression.
+        if start_args.first().map_or(false, |arg| arg.contains(':')) {
+            let expression = search::parse_expression(&start_args.join(" "))
+                .map_err(CommandError::InvalidSearch)?;
+            matches.extend(expression.into_vec());
+        } else {
+            let mut arg_iter = start_args.into_iter();
+            while let Some(col) = arg_iter.next() {
+                let search = arg_iter.next().ok_or_else(insuf)?;
+                matches.push(default_search(col, search));
+            }
+        }
+
+        for (flag, args) in trailing_args {
+            if flag == "-empty" {
+                if args.is_empty() {
+                    return Err(CommandError::InsufficientArguments);
+                }
+                matches.extend(args.into_iter().map(|col| Search {
+                    mode: SearchMode::Empty,
+                    column: ColumnIdentifier::from(col),
+                    search: String::new(),
+                }));
+                continue;
+            }
+
+            let mut args = args.into_iter();
+            if flag == "-series" {
+                matches.push(Search {
+                    mode: SearchMode::InSeries,
+                    column: ColumnIdentifier::Series,
+                    search: String::new(),
+                });
+                while let Some(col) = args.next() {
+                    let search = args.next().ok_or_else(insuf)?;
+                    matches.push(default_search(col, search));
+                }
+                continue;
+            }
+
+            let mode = match flag.as_str() {
+                "-r" | "--regex" => Ok(SearchMode::Regex),
+                "-e" => Ok(SearchMode::ExactSubstring),
+                "-x" => Ok(SearchMode::ExactString),
+                _ => Err(CommandError::UnknownFlag),
+            }?;
+
+            matches.push(Search {
+                mode,
+                column: ColumnIdentifier::from(args.next().ok_or_else(insuf)?),
+                search: remove_string_quotes(args.next().ok_or_else(insuf)?),
+            });
+            while let Some(col) = args.next() {
+                let search = args.next().ok_or_else(insuf)?;
+                matches.push(default_search(col, search));
+            }
+        }
+
+        if matches.is_empty() {
+            Err(CommandError::InsufficientArguments)
+        } else {
+            Ok(Matches {
+                matches: matches.into_boxed_slice(),
+            })
+        }
+    }
+}
+
+struct Jump {
+    matches: Matches,
+}
+
+impl From<Jump> for Command {
+    fn from(j: Jump) -> Self {
+        Command::JumpTo(j.matches.matches)
+    }
+}
+
+impl CommandParser for Jump {
+    fn from_args(
+        start_args: Vec<String>,
+        trailing_args: Vec<(String, Vec<String>)>,
+    ) -> Result<Self, CommandError> {
+        Ok(Jump {
+            matches: Matches::from_args(start_args, trailing_args)?,
+        })
+    }
+}
+
+struct Filter {
+    matches: Matches,
+}
+
+impl From<Filter> for Command {
+    fn from(f: Filter) -> Self {
+        Command::FilterMatches(f.matches.matches)
+    }
+}
+
+impl CommandParser for Filter {
+    fn from_args(
+        start_args: Vec<String>,
+        trailing_args: Vec<(String, Vec<String>)>,
+    ) -> Result<Self, CommandError> {
+        Ok(Filter {
+            matches: Matches::from_args(start_args, trailing_args)?,
+        })
+    }
+}
+
+/// A filter on the full-text index of titles, authors and descriptions.
+struct FindText {
+    text: String,
+}
+
+impl From<FindText> for Command {
+    fn from(f: FindText) -> Self {
+        Command::FilterMatches(
+            vec![Search {
+                mode: SearchMode::FullText,
+                column: ColumnIdentifier::ID,
+                search: f.text,
+            }]
+            .into_boxed_slice(),
+        )
+    }
+}
+
+impl CommandParser for FindText {
+    fn from_args(
+        start_args: Vec<String>,
+        trailing_args: Vec<(String, Vec<String>)>,
+    ) -> Result<Self, CommandError> {
+        if !trailing_args.is_empty() {
+            return Err(CommandError::UnknownFlag);
+        }
+        if start_args.is_empty() {
+            return Err(insuf());
+        }
+        Ok(FindText {
+            text: start_args.join(" "),
+        })
+    }
+}
+
+/// A filter which is excluded from the persisted session state.
+struct TempFilter {
+    matches: Matches,
+}
+
+impl From<TempFilter> for Command {
+    fn from(f: TempFilter) -> Self {
+        Command::TempFilterMatches(f.matches.matches)
+    }
+}
+
+impl CommandParser for TempFilter {
+    fn from_args(
+        start_args: Vec<String>,
+        trailing_args: Vec<(String, Vec<String>)>,
+    ) -> Result<Self, CommandError> {
+        Ok(TempFilter {
+            matches: Matches::from_args(start_args, trailing_args)?,
+        })
+    }
+}
+
+struct Highlight {
+    matches: Matches,
+}
+
+impl From<Highlight> for Command {
+    fn from(h: Highlight) -> Self {
+        Command::HighlightMatches(h.matches.matches)
+    }
+}
+
+impl CommandParser for Highlight {
+    fn from_args(
+        start_args: Vec<String>,
+        trailing_args: Vec<(String, Vec<String>)>,
+    ) -> Result<Self, CommandError> {
+        Ok(Highlight {
+            matches: Matches::from_args(start_args, trailing_args)?,
+        })
+    }
+}
+
+struct NextMatch;
+
+impl From<NextMatch> for Command {
+    fn from(_n: NextMatch) -> Self {
+        Command::NextMatch
+    }
+}
+
+impl CommandParser for NextMatch {
+    fn from_args(_sa: Vec<String>, _ta: Vec<(String, Vec<String>)>) -> Result<Self, CommandError> {
+        Ok(NextMatch)
+    }
+}
+
+struct PrevMatch;
+
+impl From<PrevMatch> for Command {
+    fn from(_p: PrevMatch) -> Self {
+        Command::PrevMatch
+    }
+}
+
+impl CommandParser for PrevMatch {
+    fn from_args(_sa: Vec<String>, _ta: Vec<(String, Vec<String>)>) -> Result<Self, CommandError> {
+        Ok(PrevMatch)
+    }
+}
+
+struct Help {
+    term: Option<String>,
+}
+
+impl From<Help> for Command {
+    fn from(h: Help) -> Self {
+        match h.term {
+            None => Command::GeneralHelp,
+            Some(term) => Command::Help(term),
+        }
+    }
+}
+
+impl CommandParser for Help {
+    fn from_args(
+        start_args: Vec<String>,
+        _ta: Vec<(String, Vec<String>)>,
+    ) -> Result<Self, CommandError> {
+        Ok(Help {
+            term: start_args.into_iter().next(),
+        })
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum Target {
+    DefaultApp,
+    FileManager,
+    /// Open the book with the given application, which may include arguments.
+    App(String),
+}
+
+struct OpenBook {
+    target: Target,
+    book_index: BookIndex,
+    /// The variant to open, or None if the variant is chosen by format.
+    variant_index: Option<usize>,
+}
+
+impl From<OpenBook> for Command {
+    fn from(ob: OpenBook) -> Self {
+        Command::OpenBookIn(ob.book_index, ob.variant_index, ob.target)
+    }
+}
+
+impl CommandParser for OpenBook {
+    fn from_args(
+        start_args: Vec<String>,
+        trailing_args: Vec<(String, Vec<String>)>,
+    ) -> Result<Self, CommandError> {
+        let mut args = start_args.into_iter().peekable();
+
+        let mut target = Target::DefaultApp;
+        if args.peek().map(String::as_str) == Some("with") {
+            args.next();
+            target = Target::App(args.next().ok_or(CommandError::InsufficientArguments)?);
+        }
 
         let mut book_index = args
             .next()
@@ -823,15 +1799,13 @@ impl CommandParser for OpenBook {
             })
             .unwrap_or(BookIndex::Selected);
 
-        let mut variant_index = args
-            .next()
-            .map(|i| usize::from_str(&i).unwrap_or(0))
-            .unwrap_or(0);
-
-        let mut target = Target::DefaultApp;
+        let mut variant_index = args.next().map(|i| usize::from_str(&i).unwrap_or(0));
 
         for (flag, args) in trailing_args {
             if flag == "-f" {
+                if target != Target::DefaultApp {
+                    return Err(CommandError::ConflictingArguments);
+                }
                 target = Target::FileManager;
             } else {
                 return Err(CommandError::UnknownFlag);
@@ -846,8 +1820,7 @@ impl CommandParser for OpenBook {
                     let vi = args
                         .next()
                         .as_deref()
-                        .map_or(Ok(0), usize::from_str)
-                        .unwrap_or(0);
+                        .map(|i| usize::from_str(i).unwrap_or(0));
                     book_index = BookIndex::ID(bi);
                     variant_index = vi;
                 }
@@ -862,6 +1835,81 @@ impl CommandParser for OpenBook {
     }
 }
 
+struct OpenFolder {
+    book_index: BookIndex,
+}
+
+impl From<OpenFolder> for Command {
+    fn from(of: OpenFolder) -> Self {
+        Command::OpenFolder(of.book_index)
+    }
+}
+
+impl CommandParser for OpenFolder {
+    fn from_args(
+        start_args: Vec<String>,
+        trailing_args: Vec<(String, Vec<String>)>,
+    ) -> Result<Self, CommandError> {
+        if !trailing_args.is_empty() {
+            return Err(CommandError::UnknownFlag);
+        }
+
+        let book_index = match start_args.as_slice() {
+            [] => BookIndex::Selected,
+            [id] => BookID::from_str(id)
+                .map(BookIndex::ID)
+                .map_err(|_| CommandError::UnexpectedArguments)?,
+            _ => return Err(CommandError::UnexpectedArguments),
+        };
+
+        Ok(OpenFolder { book_index })
+    }
+}
+
+/// Marks a book as read, or as unread.
+struct MarkRead {
+    book_index: BookIndex,
+    read: bool,
+}
+
+impl From<MarkRead> for Command {
+    fn from(mr: MarkRead) -> Self {
+        let status = if mr.read { "yes" } else { "no" };
+        Command::EditBook(
+            mr.book_index,
+            vec![(
+                ColumnIdentifier::ReadStatus,
+                Edit::Replace(status.to_string()),
+            )]
+            .into_boxed_slice(),
+        )
+    }
+}
+
+impl CommandParser for MarkRead {
+    fn from_args(
+        start_args: Vec<String>,
+        trailing_args: Vec<(String, Vec<String>)>,
+    ) -> Result<Self, CommandError> {
+        if !trailing_args.is_empty() {
+            return Err(CommandError::UnknownFlag);
+        }
+
+        let book_index = match start_args.as_slice() {
+            [] => BookIndex::Selected,
+            [id] => BookID::from_str(id)
+                .map(BookIndex::ID)
+                .map_err(|_| CommandError::UnexpectedArguments)?,
+            _ => return Err(CommandError::UnexpectedArguments),
+        };
+
+        Ok(MarkRead {
+            book_index,
+            read: true,
+        })
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -878,32 +1926,32 @@ mod test {
             ),
             (
                 vec![":a", "-d", "hello world"],
-                Command::AddBooks(
-                    vec![Source::Dir(PathBuf::from("hello world"), 1)].into_boxed_slice(),
-                ),
+                Command::AddDir(PathBuf::from("hello world"), 1),
             ),
             (
                 vec![":a", "-d", "hello world", "-r", "1"],
-                Command::AddBooks(
-                    vec![Source::Dir(PathBuf::from("hello world"), 1)].into_boxed_slice(),
-                ),
+                Command::AddDir(PathBuf::from("hello world"), 1),
             ),
             (
                 vec![":a", "-r", "22", "-d", "hello world"],
-                Command::AddBooks(
-                    vec![Source::Dir(PathBuf::from("hello world"), 22)].into_boxed_slice(),
-                ),
+                Command::AddDir(PathBuf::from("hello world"), 22),
             ),
             (
                 vec![":a", "-d", "hello world", "-r"],
-                Command::AddBooks(
-                    vec![Source::Dir(PathBuf::from("hello world"), 255)].into_boxed_slice(),
-                ),
+                Command::AddDir(PathBuf::from("hello world"), 255),
             ),
             (
                 vec![":a", "-r", "-d", "hello world"],
+                Command::AddDir(PathBuf::from("hello world"), 255),
+            ),
+            (
+                vec![":a", "-d", "hello", "world"],
                 Command::AddBooks(
-                    vec![Source::Dir(PathBuf::from("hello world"), 255)].into_boxed_slice(),
+                    vec![
+                        Source::Dir(PathBuf::from("hello"), 1),
+                        Source::Dir(PathBuf::from("world"), 1),
+                    ]
+                    .into_boxed_slice(),
                 ),
             ),
             (
@@ -920,4 +1968,638 @@ mod test {
             assert_eq!(res, command, "from {:?} expected {:?}", args, command);
         }
     }
+    #[test]
+    fn test_column_case_insensitive() {
+        let parse = |args: &[&str]| {
+            parse_args(args.iter().map(|s| s.to_string()).collect())
+                .expect("Parsing provided args should not fail")
+        };
+
+        for (lower, mixed) in [("author", "AuThOr"), ("publisher", "Publisher")] {
+            assert_eq!(parse(&[":s", lower]), parse(&[":s", mixed]));
+            assert_eq!(
+                parse(&[":f", lower, "value"]),
+                parse(&[":f", mixed, "value"])
+            );
+        }
+
+        assert_eq!(
+            parse(&[":s", "PUBLISHER"]),
+            Command::SortColumns(
+                vec![(
+                    ColumnIdentifier::NamedTag("publisher".to_string()),
+                    ColumnOrder::Ascending
+                )]
+                .into_boxed_slice()
+            )
+        );
+    }
+
+    #[test]
+    fn test_sort_multimap() {
+        let parse = |args: &[&str]| {
+            parse_args(args.iter().map(|s| s.to_string()).collect())
+                .expect("Parsing provided args should not fail")
+        };
+
+        assert_eq!(
+            parse(&[":s", "#Genre", "-d", "title"]),
+            Command::SortColumns(
+                vec![
+                    (
+                        ColumnIdentifier::MultiMap("genre".to_string()),
+                        ColumnOrder::Ascending
+                    ),
+                    (ColumnIdentifier::Title, ColumnOrder::Descending),
+                ]
+                .into_boxed_slice()
+            )
+        );
+    }
+
+    #[test]
+    fn test_edit_multimap() {
+        let parse = |args: &[&str]| {
+            parse_args(args.iter().map(|s| s.to_string()).collect())
+                .expect("Parsing provided args should not fail")
+        };
+        let author =
+            |value: &str| ColumnIdentifier::MultiMapExact("author".to_string(), value.to_string());
+
+        assert_eq!(
+            parse(&[":e", "-r", "#Author", "Frank Herbrt", "Frank Herbert"]),
+            Command::EditBook(
+                BookIndex::Selected,
+                vec![(
+                    author("Frank Herbrt"),
+                    Edit::Replace("Frank Herbert".to_string())
+                )]
+                .into_boxed_slice()
+            )
+        );
+        assert_eq!(
+            parse(&[
+                ":e",
+                "-d",
+                "#author",
+                "Frank Herbert",
+                "-a",
+                "#genre",
+                "scifi"
+            ]),
+            Command::EditBook(
+                BookIndex::Selected,
+                vec![
+                    (author("Frank Herbert"), Edit::Delete),
+                    (
+                        ColumnIdentifier::MultiMap("genre".to_string()),
+                        Edit::Append("scifi".to_string())
+                    ),
+                ]
+                .into_boxed_slice()
+            )
+        );
+    }
+
+    #[test]
+    fn test_series_filter() {
+        let parse = |args: &[&str]| {
+            parse_args(args.iter().map(|s| s.to_string()).collect())
+                .expect("Parsing provided args should not fail")
+        };
+        let in_series = |name: &str| Search {
+            mode: SearchMode::InSeries,
+            column: ColumnIdentifier::Series,
+            search: name.to_string(),
+        };
+
+        assert_eq!(
+            parse(&[":f", "-series"]),
+            Command::FilterMatches(vec![in_series("")].into_boxed_slice())
+        );
+        assert_eq!(
+            parse(&[":f", "series", "\"The Expanse\""]),
+            Command::FilterMatches(vec![in_series("The Expanse")].into_boxed_slice())
+        );
+    }
+
+    #[test]
+    fn test_import_tags_format() {
+        let parse = |args: &[&str]| parse_args(args.iter().map(|s| s.to_string()).collect());
+
+        assert_eq!(
+            parse(&[":import", "tags", "tags.csv", "-d", ";", "-e", "latin1"]).unwrap(),
+            Command::ImportTags(
+                PathBuf::from("tags.csv"),
+                DelimitedFormat {
+                    delimiter: ';',
+                    encoding: encoding_rs::WINDOWS_1252,
+                }
+            )
+        );
+        assert_eq!(
+            parse(&[":import", "tags", "tags.tsv"]).unwrap(),
+            Command::ImportTags(PathBuf::from("tags.tsv"), DelimitedFormat::default())
+        );
+        assert!(matches!(
+            parse(&[":import", "tags", "tags.csv", "-e", "not-an-encoding"]),
+            Err(CommandError::UnexpectedArguments)
+        ));
+    }
+
+    #[test]
+    fn test_find_duplicates() {
+        let parse = |args: &[&str]| parse_args(args.iter().map(|s| s.to_string()).collect());
+
+        assert_eq!(
+            parse(&[":dups"]).unwrap(),
+            Command::FindDuplicates(DetectionStrategy::VariantHash)
+        );
+        assert_eq!(
+            parse(&[":dups", "author"]).unwrap(),
+            Command::FindDuplicates(DetectionStrategy::AuthorOverlap)
+        );
+        assert_eq!(
+            parse(&[":dups", "title"]).unwrap(),
+            Command::FindDuplicates(DetectionStrategy::Title)
+        );
+        assert!(matches!(
+            parse(&[":dups", "isbn"]),
+            Err(CommandError::UnexpectedArguments)
+        ));
+        assert!(!parse(&[":dups"]).unwrap().requires_ui());
+    }
+
+    #[test]
+    fn test_import_db() {
+        let parse = |args: &[&str]| parse_args(args.iter().map(|s| s.to_string()).collect());
+
+        assert_eq!(
+            parse(&[":import", "db", "\"other library.db\""]).unwrap(),
+            Command::ImportDb(PathBuf::from("other library.db"))
+        );
+        assert!(matches!(
+            parse(&[":import", "db"]),
+            Err(CommandError::InsufficientArguments)
+        ));
+        assert!(matches!(
+            parse(&[":import", "db", "a.db", "-d", ";"]),
+            Err(CommandError::UnknownFlag)
+        ));
+    }
+
+    #[test]
+    fn test_rescan() {
+        let parse = |args: &[&str]| parse_args(args.iter().map(|s| s.to_string()).collect());
+
+        assert_eq!(parse(&[":rescan"]).unwrap(), Command::Rescan(false, false));
+        assert_eq!(
+            parse(&[":rescan", "--overwrite", "-y"]).unwrap(),
+            Command::Rescan(true, true)
+        );
+        assert!(matches!(
+            parse(&[":rescan", "--force"]),
+            Err(CommandError::UnknownFlag)
+        ));
+    }
+
+    #[test]
+    fn test_command_names() {
+        for name in COMMAND_NAMES.iter().chain(COLUMN_COMMANDS.iter()) {
+            assert!(
+                CommandRoot::from_str(name).is_ok(),
+                "{} is not a command",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_fetch() {
+        let parse = |args: &[&str]| parse_args(args.iter().map(|s| s.to_string()).collect());
+
+        assert_eq!(parse(&[":fetch"]).unwrap(), Command::Fetch(false));
+        assert_eq!(
+            parse(&[":fetch", "--overwrite"]).unwrap(),
+            Command::Fetch(true)
+        );
+        assert!(matches!(
+            parse(&[":fetch", "-y"]),
+            Err(CommandError::UnknownFlag)
+        ));
+        assert!(matches!(
+            parse(&[":fetch", "1"]),
+            Err(CommandError::UnexpectedArguments)
+        ));
+    }
+
+    #[test]
+    fn test_list_variants() {
+        let parse = |args: &[&str]| parse_args(args.iter().map(|s| s.to_string()).collect());
+
+        assert_eq!(parse(&[":variants"]).unwrap(), Command::ListVariants);
+        assert!(matches!(
+            parse(&[":variants", "1"]),
+            Err(CommandError::UnexpectedArguments)
+        ));
+    }
+
+    #[test]
+    fn test_reading_stats() {
+        let parse = |args: &[&str]| parse_args(args.iter().map(|s| s.to_string()).collect());
+
+        assert_eq!(parse(&[":stats"]).unwrap(), Command::Stats);
+        assert_eq!(
+            parse(&[":stats", "reading"]).unwrap(),
+            Command::ReadingStats(None)
+        );
+        assert_eq!(
+            parse(&[":stats", "reading", "reading.csv"]).unwrap(),
+            Command::ReadingStats(Some((PathBuf::from("reading.csv"), DelimitedFormat::csv())))
+        );
+        assert_eq!(
+            parse(&[
+                ":stats",
+                "reading",
+                "reading.csv",
+                "-d",
+                "tab",
+                "-e",
+                "latin1"
+            ])
+            .unwrap(),
+            Command::ReadingStats(Some((
+                PathBuf::from("reading.csv"),
+                DelimitedFormat {
+                    delimiter: '\t',
+                    encoding: Encoding::for_label(b"latin1").unwrap(),
+                }
+            )))
+        );
+        assert!(matches!(
+            parse(&[":stats", "reading", "-d", ";"]),
+            Err(CommandError::UnknownFlag)
+        ));
+        assert_eq!(
+            parse(&[":stats", "genre"]).unwrap(),
+            Command::ColumnStats(ColumnIdentifier::from("genre"))
+        );
+        assert!(matches!(
+            parse(&[":stats", "genre", "genre.csv"]),
+            Err(CommandError::UnexpectedArguments)
+        ));
+    }
+
+    #[test]
+    fn test_export() {
+        let parse = |args: &[&str]| parse_args(args.iter().map(|s| s.to_string()).collect());
+
+        assert_eq!(
+            parse(&[":export", "view", "csv", "view.csv"]).unwrap(),
+            Command::ExportView(
+                ExportFormat::Csv(DelimitedFormat::csv()),
+                PathBuf::from("view.csv")
+            )
+        );
+        assert_eq!(
+            parse(&[":export", "books.csv"]).unwrap(),
+            Command::ExportDatabase(
+                ExportFormat::Csv(DelimitedFormat::csv()),
+                PathBuf::from("books.csv")
+            )
+        );
+        assert_eq!(
+            parse(&[":export", "books.csv", "-d", ";", "-e", "latin1"]).unwrap(),
+            Command::ExportDatabase(
+                ExportFormat::Csv(DelimitedFormat {
+                    delimiter: ';',
+                    encoding: Encoding::for_label(b"latin1").unwrap(),
+                }),
+                PathBuf::from("books.csv")
+            )
+        );
+        assert!(matches!(
+            parse(&[":export", "books.json", "-d", ";"]),
+            Err(CommandError::UnexpectedArguments)
+        ));
+        assert_eq!(
+            parse(&[":export", "books"]).unwrap(),
+            Command::ExportDatabase(ExportFormat::Json, PathBuf::from("books"))
+        );
+        assert_eq!(
+            parse(&[":export", "json", "books.csv"]).unwrap(),
+            Command::ExportDatabase(ExportFormat::Json, PathBuf::from("books.csv"))
+        );
+        assert!(matches!(
+            parse(&[":export", "xml", "books.xml"]),
+            Err(CommandError::UnexpectedArguments)
+        ));
+        assert!(!parse(&[":export", "books.csv"]).unwrap().requires_ui());
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let parse = |args: &[&str]| parse_args(args.iter().map(|s| s.to_string()).collect());
+
+        assert_eq!(parse(&[":u"]).unwrap(), Command::Undo);
+        assert_eq!(parse(&[":r"]).unwrap(), Command::Redo);
+        assert!(matches!(
+            parse(&[":u", "2"]),
+            Err(CommandError::UnexpectedArguments)
+        ));
+        assert!(matches!(
+            parse(&[":r", "-a"]),
+            Err(CommandError::UnknownFlag)
+        ));
+        assert!(!parse(&[":u"]).unwrap().requires_ui());
+    }
+
+    #[test]
+    fn test_refresh() {
+        let parse = |args: &[&str]| parse_args(args.iter().map(|s| s.to_string()).collect());
+
+        assert_eq!(parse(&[":refresh"]).unwrap(), Command::Refresh);
+        assert!(parse(&[":refresh"]).unwrap().requires_ui());
+        assert!(matches!(
+            parse(&[":refresh", "all"]),
+            Err(CommandError::UnexpectedArguments)
+        ));
+    }
+
+    #[test]
+    fn test_find_text() {
+        let parse = |args: &[&str]| parse_args(args.iter().map(|s| s.to_string()).collect());
+
+        assert_eq!(
+            parse(&[":find", "frank", "herbert"]).unwrap(),
+            Command::FilterMatches(
+                vec![Search {
+                    mode: SearchMode::FullText,
+                    column: ColumnIdentifier::ID,
+                    search: "frank herbert".to_string(),
+                }]
+                .into_boxed_slice()
+            )
+        );
+        assert!(matches!(
+            parse(&[":find"]),
+            Err(CommandError::InsufficientArguments)
+        ));
+    }
+
+    #[test]
+    fn test_regex_filter() {
+        let parse = |args: &[&str]| parse_args(args.iter().map(|s| s.to_string()).collect());
+        let regex = Command::FilterMatches(
+            vec![Search {
+                mode: SearchMode::Regex,
+                column: ColumnIdentifier::Title,
+                search: "^Dune".to_string(),
+            }]
+            .into_boxed_slice(),
+        );
+
+        assert_eq!(parse(&[":f", "-r", "title", "^Dune"]).unwrap(), regex);
+        assert_eq!(parse(&[":f", "--regex", "title", "^Dune"]).unwrap(), regex);
+    }
+
+    #[test]
+    fn test_size_filter() {
+        let args = vec![
+            ":f".to_string(),
+            "size".to_string(),
+            "1MB..20MB".to_string(),
+        ];
+        assert_eq!(
+            parse_args(args).expect("Parsing provided args should not fail"),
+            Command::FilterMatches(
+                vec![Search {
+                    mode: SearchMode::FileSize(SizeUnits::Decimal),
+                    column: ColumnIdentifier::Variants,
+                    search: "1MB..20MB".to_string(),
+                }]
+                .into_boxed_slice()
+            )
+        );
+    }
+
+    #[test]
+    fn test_search_expression() {
+        let parse = |args: &[&str]| parse_args(args.iter().map(|s| s.to_string()).collect());
+        let expected = Command::FilterMatches(
+            vec![
+                Search {
+                    mode: SearchMode::Default,
+                    column: ColumnIdentifier::Author,
+                    search: "Tolkien".to_string(),
+                },
+                Search {
+                    mode: SearchMode::InSeries,
+                    column: ColumnIdentifier::Series,
+                    search: "Lord of the Rings".to_string(),
+                },
+            ]
+            .into_boxed_slice(),
+        );
+
+        // Quoted values are split into several arguments by the command line.
+        assert_eq!(
+            parse(&[
+                ":f",
+                "author:Tolkien",
+                "AND",
+                "series:\"Lord",
+                "of",
+                "the",
+                "Rings\""
+            ])
+            .unwrap(),
+            expected
+        );
+        assert_eq!(
+            parse(&[":f", "author:Tolkien series:'Lord of the Rings'"]).unwrap(),
+            expected
+        );
+        assert_eq!(
+            parse(&[":f", "ext:epub", "-x", "title", "Dune"]).unwrap(),
+            Command::FilterMatches(
+                vec![
+                    Search {
+                        mode: SearchMode::Extension,
+                        column: ColumnIdentifier::Variants,
+                        search: "epub".to_string(),
+                    },
+                    Search {
+                        mode: SearchMode::ExactString,
+                        column: ColumnIdentifier::Title,
+                        search: "Dune".to_string(),
+                    },
+                ]
+                .into_boxed_slice()
+            )
+        );
+
+        for invalid in [
+            "author:Tolkien AND",
+            "AND author:Tolkien",
+            "author:Tolkien OR author:Lewis",
+            "author:Tolkien NOT series:Hobbit",
+            "author:Tolkien Lewis",
+            "author:\"Tolkien",
+            "author: Tolkien",
+        ] {
+            assert!(
+                matches!(parse(&[":f", invalid]), Err(CommandError::InvalidSearch(_))),
+                "{} should not be a valid expression",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_extension_filter() {
+        let args = vec![":f".to_string(), "EXT".to_string(), "pdf".to_string()];
+        assert_eq!(
+            parse_args(args).expect("Parsing provided args should not fail"),
+            Command::FilterMatches(
+                vec![Search {
+                    mode: SearchMode::Extension,
+                    column: ColumnIdentifier::Variants,
+                    search: "pdf".to_string(),
+                }]
+                .into_boxed_slice()
+            )
+        );
+    }
+
+    #[test]
+    fn test_open_book() {
+        let parse = |args: &[&str]| parse_args(args.iter().map(|s| s.to_string()).collect());
+
+        // Without a variant index, the variant is chosen by format.
+        assert_eq!(
+            parse(&[":o"]).unwrap(),
+            Command::OpenBookIn(BookIndex::Selected, None, Target::DefaultApp)
+        );
+        assert_eq!(
+            parse(&[":o", "3", "1"]).unwrap(),
+            Command::OpenBookIn(
+                BookIndex::ID(BookID::from_str("3").unwrap()),
+                Some(1),
+                Target::DefaultApp
+            )
+        );
+        assert_eq!(
+            parse(&[":o", "-f", "3"]).unwrap(),
+            Command::OpenBookIn(
+                BookIndex::ID(BookID::from_str("3").unwrap()),
+                None,
+                Target::FileManager
+            )
+        );
+    }
+
+    #[test]
+    fn test_open_folder() {
+        let parse = |args: &[&str]| parse_args(args.iter().map(|s| s.to_string()).collect());
+
+        assert_eq!(
+            parse(&[":folder"]).unwrap(),
+            Command::OpenFolder(BookIndex::Selected)
+        );
+        assert!(parse(&[":folder"]).unwrap().requires_ui());
+        let command = parse(&[":folder", "3"]).unwrap();
+        assert!(!command.requires_ui());
+        assert_eq!(
+            command,
+            Command::OpenFolder(BookIndex::ID(BookID::from_str("3").unwrap()))
+        );
+        assert!(matches!(
+            parse(&[":folder", "three"]),
+            Err(CommandError::UnexpectedArguments)
+        ));
+        assert!(matches!(
+            parse(&[":folder", "-f"]),
+            Err(CommandError::UnknownFlag)
+        ));
+    }
+
+    #[test]
+    fn test_read_commands() {
+        let parse = |args: &[&str]| parse_args(args.iter().map(|s| s.to_string()).collect());
+        let read = |index, status: &str| {
+            Command::EditBook(
+                index,
+                vec![(
+                    ColumnIdentifier::ReadStatus,
+                    Edit::Replace(status.to_string()),
+                )]
+                .into_boxed_slice(),
+            )
+        };
+
+        assert_eq!(parse(&[":read"]).unwrap(), read(BookIndex::Selected, "yes"));
+        assert!(parse(&[":read"]).unwrap().requires_ui());
+        assert_eq!(
+            parse(&[":unread", "3"]).unwrap(),
+            read(BookIndex::ID(BookID::from_str("3").unwrap()), "no")
+        );
+        assert!(!parse(&[":unread", "3"]).unwrap().requires_ui());
+        assert!(matches!(
+            parse(&[":read", "3", "4"]),
+            Err(CommandError::UnexpectedArguments)
+        ));
+    }
+
+    #[test]
+    fn test_dedupe_authors() {
+        let parse = |args: &[&str]| parse_args(args.iter().map(|s| s.to_string()).collect());
+
+        assert_eq!(
+            parse(&[":authors", "dedupe"]).unwrap(),
+            Command::DedupeAuthors(false)
+        );
+        assert_eq!(
+            parse(&[":authors", "dedupe", "-y"]).unwrap(),
+            Command::DedupeAuthors(true)
+        );
+        assert!(matches!(
+            parse(&[":authors", "dedupe", "-y", "now"]),
+            Err(CommandError::UnexpectedArguments)
+        ));
+    }
+
+    #[test]
+    fn test_tag_commands() {
+        let parse = |args: &[&str]| parse_args(args.iter().map(|s| s.to_string()).collect());
+
+        assert_eq!(
+            parse(&[":tag", "rename", "scfi", "\"science fiction\""]).unwrap(),
+            Command::RenameTag("scfi".to_string(), "science fiction".to_string())
+        );
+        assert_eq!(
+            parse(&[":tag", "delete", "scifi"]).unwrap(),
+            Command::DeleteTag("scifi".to_string(), false)
+        );
+        assert_eq!(
+            parse(&[":tags", "delete", "scifi", "-y"]).unwrap(),
+            Command::DeleteTag("scifi".to_string(), true)
+        );
+        assert_eq!(
+            parse(&[":tags", "clear-all", "-y"]).unwrap(),
+            Command::ClearTags(true)
+        );
+        // Tags are changed in every book, so no books need to be selected.
+        assert!(!parse(&[":tag", "rename", "scfi", "scifi"])
+            .unwrap()
+            .requires_ui());
+        assert!(matches!(
+            parse(&[":tag", "rename", "scfi"]),
+            Err(CommandError::InsufficientArguments)
+        ));
+        assert!(matches!(
+            parse(&[":tag", "delete", "scifi", "fantasy"]),
+            Err(CommandError::UnexpectedArguments)
+        ));
+    }
 }
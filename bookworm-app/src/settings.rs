@@ -1,9 +1,19 @@
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
 
-use bookworm_records::book::ColumnIdentifier;
-use bookworm_records::ColumnOrder;
+use bookworm_database::search::{Normalization, Search, SearchMode, SizeUnits};
+use bookworm_database::{
+    InsertPolicy, TagAppendPolicy, VariantEditPolicy, DEFAULT_INSERT_BATCH_SIZE,
+};
+use bookworm_records::book::{AuthorDisplay, AuthorNameOrder, AuthorOrder, ColumnIdentifier};
+use bookworm_records::variant::BookType;
+use bookworm_records::{Collation, ColumnOrder};
+
+use crate::columns::{ColumnDisplay, Width};
 
 #[derive(Copy, Clone)]
 pub enum Color {
@@ -28,20 +38,43 @@ pub enum Color {
 /// Provides terminal UI settings.
 pub struct Settings {
     pub interface_style: InterfaceStyle,
-    pub columns: Vec<String>,
+    /// The columns of the table, with their widths and whether they are displayed.
+    pub columns: Vec<ColumnDisplay>,
+    /// Columns which can not be used to filter books, whether or not they are displayed.
+    pub unsearchable_columns: Vec<String>,
+    /// The indicator appended to cells which are too long to fit in their column.
+    pub truncation: String,
+    /// How the authors of each book are joined when displayed.
+    pub author_display: AuthorDisplay,
+    /// The meaning of the "KB", "MB" and "GB" units when filtering books by file size.
+    pub size_units: SizeUnits,
+    /// How text is normalized before it is compared when filtering books.
+    pub search_normalization: Normalization,
+    /// Whether the selected book, with its cover, is shown beside the table.
+    pub show_covers: bool,
     pub sort_settings: SortSettings,
     pub navigation_settings: NavigationSettings,
     pub database_settings: DatabaseSettings,
+    pub maintenance_settings: MaintenanceSettings,
+    pub session_settings: SessionSettings,
 }
 
 impl Default for Settings {
     fn default() -> Self {
         Settings {
             interface_style: InterfaceStyle::default(),
-            columns: vec![String::from("Title"), String::from("Authors")],
+            columns: vec![ColumnDisplay::new("Title"), ColumnDisplay::new("Authors")],
+            unsearchable_columns: vec![],
+            truncation: String::from("..."),
+            author_display: AuthorDisplay::default(),
+            size_units: SizeUnits::default(),
+            search_normalization: Normalization::default(),
+            show_covers: false,
             sort_settings: SortSettings::default(),
             navigation_settings: NavigationSettings::default(),
             database_settings: Default::default(),
+            maintenance_settings: Default::default(),
+            session_settings: Default::default(),
         }
     }
 }
@@ -72,12 +105,15 @@ impl Default for InterfaceStyle {
 #[derive(Debug, Clone)]
 pub struct SortSettings {
     pub columns: Box<[(ColumnIdentifier, ColumnOrder)]>,
+    /// How text columns are compared when sorting.
+    pub collation: Collation,
 }
 
 impl Default for SortSettings {
     fn default() -> Self {
         SortSettings {
             columns: vec![].into_boxed_slice(),
+            collation: Collation::default(),
         }
     }
 }
@@ -86,6 +122,13 @@ impl Default for SortSettings {
 pub struct NavigationSettings {
     pub scroll: usize,
     pub inverted: bool,
+    /// The key which, pressed with CTRL, opens the quick add prompt.
+    pub quick_add: char,
+    /// Whether to only load the visible rows, reading more only when scrolling, instead of
+    /// prefetching books ahead of time.
+    pub load_more: bool,
+    /// Whether the first book is selected on startup, so that commands apply to it immediately.
+    pub select_first: bool,
 }
 
 impl Default for NavigationSettings {
@@ -93,6 +136,9 @@ impl Default for NavigationSettings {
         NavigationSettings {
             scroll: 5,
             inverted: false,
+            quick_add: 'n',
+            load_more: false,
+            select_first: false,
         }
     }
 }
@@ -100,6 +146,27 @@ impl Default for NavigationSettings {
 #[derive(Debug, Clone)]
 pub struct DatabaseSettings {
     pub path: PathBuf,
+    /// The maximum number of books to keep in memory.
+    pub cache_size: usize,
+    /// Which variants bulk edits of per-variant columns apply to.
+    pub variant_edits: VariantEditPolicy,
+    /// Whether added books are renamed to "Author - Title.ext" in their current directory.
+    pub rename_in_place: bool,
+    /// Whether books left without variants after merging or updating books are removed.
+    pub delete_empty_books: bool,
+    /// The maximum number of characters in the title of an added book, or 0 for no limit.
+    pub max_title_length: usize,
+    /// The maximum number of characters in the description of an added book, or 0 for no limit.
+    pub max_description_length: usize,
+    /// What happens when an added file is already in the database.
+    pub duplicates: InsertPolicy,
+    /// How values appended to named tags are combined with existing values.
+    pub tag_append: TagAppendPolicy,
+    /// The number of books added in each transaction when adding many books.
+    pub insert_batch_size: usize,
+    /// The formats which are opened first when a book is opened without choosing a variant,
+    /// from the most to the least preferred.
+    pub format_order: Vec<BookType>,
 }
 
 impl Default for DatabaseSettings {
@@ -112,10 +179,41 @@ impl Default for DatabaseSettings {
                     p
                 },
             ),
+            cache_size: 32768,
+            variant_edits: VariantEditPolicy::default(),
+            rename_in_place: false,
+            delete_empty_books: true,
+            max_title_length: 1024,
+            max_description_length: 65536,
+            duplicates: InsertPolicy::default(),
+            tag_append: TagAppendPolicy::default(),
+            insert_batch_size: DEFAULT_INSERT_BATCH_SIZE,
+            format_order: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct MaintenanceSettings {
+    /// The number of days log files are kept for before being removed at startup.
+    pub log_retention_days: u64,
+}
+
+impl Default for MaintenanceSettings {
+    fn default() -> Self {
+        MaintenanceSettings {
+            log_retention_days: 7,
         }
     }
 }
 
+/// The state of the interface which is restored when the application is next opened.
+#[derive(Debug, Clone, Default)]
+pub struct SessionSettings {
+    /// The searches of each persisted filter, from the first to the last applied.
+    pub filters: Vec<Box<[Search]>>,
+}
+
 fn str_to_color_or<S: AsRef<str>>(s: S, default: Color) -> Color {
     match s.as_ref().to_ascii_lowercase().as_str() {
         "black" => Color::Black,
@@ -160,6 +258,182 @@ fn color_to_string(c: Color) -> String {
     .to_string()
 }
 
+fn str_to_variant_policy_or<S: AsRef<str>>(s: S, default: VariantEditPolicy) -> VariantEditPolicy {
+    match s.as_ref().to_ascii_lowercase().as_str() {
+        "all" => VariantEditPolicy::AllVariants,
+        "primary" => VariantEditPolicy::PrimaryOnly,
+        _ => default,
+    }
+}
+
+fn str_to_insert_policy_or<S: AsRef<str>>(s: S, default: InsertPolicy) -> InsertPolicy {
+    match s.as_ref().to_ascii_lowercase().as_str() {
+        "new" => InsertPolicy::AlwaysNew,
+        "skip" => InsertPolicy::SkipDuplicates,
+        "attach" => InsertPolicy::AttachVariant,
+        _ => default,
+    }
+}
+
+fn insert_policy_to_string(policy: InsertPolicy) -> String {
+    match policy {
+        InsertPolicy::AlwaysNew => "new",
+        InsertPolicy::SkipDuplicates => "skip",
+        InsertPolicy::AttachVariant => "attach",
+    }
+    .to_string()
+}
+
+fn str_to_size_units_or<S: AsRef<str>>(s: S, default: SizeUnits) -> SizeUnits {
+    match s.as_ref().to_ascii_lowercase().as_str() {
+        "decimal" => SizeUnits::Decimal,
+        "binary" => SizeUnits::Binary,
+        _ => default,
+    }
+}
+
+fn str_to_normalization_or<S: AsRef<str>>(s: S, default: Normalization) -> Normalization {
+    match s.as_ref().to_ascii_lowercase().as_str() {
+        "exact" => Normalization::Exact,
+        "case-insensitive" => Normalization::CaseInsensitive,
+        "unaccented" => Normalization::Unaccented,
+        _ => default,
+    }
+}
+
+fn normalization_to_string(normalization: Normalization) -> String {
+    match normalization {
+        Normalization::Exact => "exact",
+        Normalization::CaseInsensitive => "case-insensitive",
+        Normalization::Unaccented => "unaccented",
+    }
+    .to_string()
+}
+
+fn size_units_to_string(units: SizeUnits) -> String {
+    match units {
+        SizeUnits::Decimal => "decimal",
+        SizeUnits::Binary => "binary",
+    }
+    .to_string()
+}
+
+fn str_to_author_order_or<S: AsRef<str>>(s: S, default: AuthorOrder) -> AuthorOrder {
+    match s.as_ref().to_ascii_lowercase().as_str() {
+        "entered" => AuthorOrder::AsEntered,
+        "alphabetical" => AuthorOrder::Alphabetical,
+        "role" => AuthorOrder::RolePriority,
+        _ => default,
+    }
+}
+
+fn author_order_to_string(order: AuthorOrder) -> String {
+    match order {
+        AuthorOrder::AsEntered => "entered",
+        AuthorOrder::Alphabetical => "alphabetical",
+        AuthorOrder::RolePriority => "role",
+    }
+    .to_string()
+}
+
+fn str_to_author_names_or<S: AsRef<str>>(s: S, default: AuthorNameOrder) -> AuthorNameOrder {
+    match s.as_ref().to_ascii_lowercase().as_str() {
+        "first_last" => AuthorNameOrder::FirstLast,
+        "last_first" => AuthorNameOrder::LastFirst,
+        _ => default,
+    }
+}
+
+fn author_names_to_string(names: AuthorNameOrder) -> String {
+    match names {
+        AuthorNameOrder::FirstLast => "first_last",
+        AuthorNameOrder::LastFirst => "last_first",
+    }
+    .to_string()
+}
+
+fn str_to_search_mode<S: AsRef<str>>(s: S) -> Option<SearchMode> {
+    Some(match s.as_ref().to_ascii_lowercase().as_str() {
+        "regex" => SearchMode::Regex,
+        "substring" => SearchMode::ExactSubstring,
+        "default" => SearchMode::Default,
+        "exact" => SearchMode::ExactString,
+        "empty" => SearchMode::Empty,
+        "extension" => SearchMode::Extension,
+        "series" => SearchMode::InSeries,
+        "size" => SearchMode::FileSize(SizeUnits::Decimal),
+        "binary_size" => SearchMode::FileSize(SizeUnits::Binary),
+        "text" => SearchMode::FullText,
+        _ => return None,
+    })
+}
+
+fn search_mode_to_string(mode: SearchMode) -> String {
+    match mode {
+        SearchMode::Regex => "regex",
+        SearchMode::ExactSubstring => "substring",
+        SearchMode::Default => "default",
+        SearchMode::ExactString => "exact",
+        SearchMode::Empty => "empty",
+        SearchMode::Extension => "extension",
+        SearchMode::InSeries => "series",
+        SearchMode::FileSize(SizeUnits::Decimal) => "size",
+        SearchMode::FileSize(SizeUnits::Binary) => "binary_size",
+        SearchMode::FullText => "text",
+    }
+    .to_string()
+}
+
+/// Reads the column of a persisted search, where multimap tags are prefixed with `#`, as they are
+/// in commands.
+fn str_to_search_column(s: String) -> ColumnIdentifier {
+    match s.strip_prefix('#') {
+        Some(tag) => ColumnIdentifier::MultiMap(tag.to_lowercase()),
+        None => ColumnIdentifier::from(s),
+    }
+}
+
+fn search_column_to_string(column: ColumnIdentifier) -> String {
+    match column {
+        ColumnIdentifier::MultiMap(tag) => format!("#{}", tag),
+        column => column.into_string(),
+    }
+}
+
+/// Reads a column width, which is "auto", a number of characters (eg. "20"), or a percentage
+/// of the table width (eg. "25%").
+fn str_to_width_or<S: AsRef<str>>(s: S, default: Width) -> Width {
+    let s = s.as_ref().trim();
+    if s.eq_ignore_ascii_case("auto") {
+        return Width::Auto;
+    }
+    match s.strip_suffix('%') {
+        Some(percent) => percent
+            .trim()
+            .parse()
+            .ok()
+            .filter(|&percent| percent <= 100)
+            .map_or(default, Width::Percent),
+        None => s.parse().map_or(default, Width::Fixed),
+    }
+}
+
+fn width_to_string(width: Width) -> String {
+    match width {
+        Width::Auto => String::from("auto"),
+        Width::Fixed(width) => width.to_string(),
+        Width::Percent(percent) => format!("{}%", percent),
+    }
+}
+
+fn variant_policy_to_string(policy: VariantEditPolicy) -> String {
+    match policy {
+        VariantEditPolicy::AllVariants => "all",
+        VariantEditPolicy::PrimaryOnly => "primary",
+    }
+    .to_string()
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct TomlSettings {
     colors: Option<TomlColors>,
@@ -167,6 +441,8 @@ struct TomlSettings {
     sorting: Option<TomlSort>,
     navigation: Option<TomlNavigation>,
     database: Option<TomlDatabase>,
+    maintenance: Option<TomlMaintenance>,
+    session: Option<TomlSession>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -249,38 +525,123 @@ impl TomlColors {
 #[derive(Debug, Serialize, Deserialize)]
 struct TomlColumns {
     columns: Option<Vec<String>>,
+    unsearchable: Option<Vec<String>>,
+    truncation: Option<String>,
+    author_separator: Option<String>,
+    author_limit: Option<usize>,
+    author_order: Option<String>,
+    author_names: Option<String>,
+    size_units: Option<String>,
+    search_normalization: Option<String>,
+    show_covers: Option<bool>,
+    hidden: Option<Vec<String>>,
+    // Tables must follow values, so this is last.
+    widths: Option<BTreeMap<String, String>>,
 }
 
 impl Default for TomlColumns {
     fn default() -> Self {
-        TomlColumns { columns: None }
+        TomlColumns {
+            columns: None,
+            unsearchable: None,
+            truncation: None,
+            author_separator: None,
+            author_limit: None,
+            author_order: None,
+            author_names: None,
+            size_units: None,
+            search_normalization: None,
+            show_covers: None,
+            hidden: None,
+            widths: None,
+        }
     }
 }
 
-impl From<TomlColumns> for Vec<String> {
-    fn from(t: TomlColumns) -> Self {
-        if let Some(s) = t.columns {
-            s
-        } else {
-            vec![String::from("Title"), String::from("Authors")]
+impl TomlColumns {
+    /// Returns the displayed columns, followed by the hidden columns, with their widths.
+    fn columns(&self) -> Vec<ColumnDisplay> {
+        let visible = self
+            .columns
+            .clone()
+            .unwrap_or_else(|| vec![String::from("Title"), String::from("Authors")]);
+        let hidden = self.hidden.clone().unwrap_or_default();
+        let widths = self.widths.clone().unwrap_or_default();
+        let width = |name: &str| {
+            widths
+                .iter()
+                .find(|(column, _)| column.to_lowercase() == name.to_lowercase())
+                .map_or(Width::Auto, |(_, width)| {
+                    str_to_width_or(width, Width::Auto)
+                })
+        };
+
+        visible
+            .into_iter()
+            .map(|name| (name, true))
+            .chain(hidden.into_iter().map(|name| (name, false)))
+            .map(|(name, visible)| ColumnDisplay {
+                width: width(&name),
+                name,
+                visible,
+            })
+            .collect()
+    }
+
+    fn unsearchable(&self) -> Vec<String> {
+        self.unsearchable.clone().unwrap_or_default()
+    }
+
+    fn truncation(&self) -> String {
+        self.truncation
+            .clone()
+            .unwrap_or_else(|| String::from("..."))
+    }
+
+    fn author_display(&self) -> AuthorDisplay {
+        let default = AuthorDisplay::default();
+        AuthorDisplay {
+            separator: self.author_separator.clone().unwrap_or(default.separator),
+            // A limit of 0 would hide every author.
+            limit: self.author_limit.filter(|&limit| limit > 0),
+            order: self
+                .author_order
+                .as_ref()
+                .map_or(default.order, |s| str_to_author_order_or(s, default.order)),
+            names: self
+                .author_names
+                .as_ref()
+                .map_or(default.names, |s| str_to_author_names_or(s, default.names)),
         }
     }
-}
 
-impl From<Vec<String>> for TomlColumns {
-    fn from(vs: Vec<String>) -> Self {
-        TomlColumns { columns: Some(vs) }
+    fn size_units(&self) -> SizeUnits {
+        self.size_units.as_ref().map_or(SizeUnits::default(), |s| {
+            str_to_size_units_or(s, SizeUnits::default())
+        })
+    }
+
+    fn search_normalization(&self) -> Normalization {
+        self.search_normalization
+            .as_ref()
+            .map_or(Normalization::default(), |s| {
+                str_to_normalization_or(s, Normalization::default())
+            })
     }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 struct TomlSort {
     columns: Option<Vec<(String, Option<bool>)>>,
+    case_sensitive: Option<bool>,
 }
 
 impl Default for TomlSort {
     fn default() -> Self {
-        TomlSort { columns: None }
+        TomlSort {
+            columns: None,
+            case_sensitive: Some(false),
+        }
     }
 }
 
@@ -298,6 +659,11 @@ impl From<TomlSort> for SortSettings {
             .collect();
         SortSettings {
             columns: columns.into_boxed_slice(),
+            collation: if t.case_sensitive.unwrap_or(false) {
+                Collation::CaseSensitive
+            } else {
+                Collation::CaseInsensitive
+            },
         }
     }
 }
@@ -312,6 +678,7 @@ impl From<SortSettings> for TomlSort {
                     .map(|(c, r)| (c.into_string(), Some(r.as_bool())))
                     .collect(),
             ),
+            case_sensitive: Some(s.collation == Collation::CaseSensitive),
         }
     }
 }
@@ -320,6 +687,9 @@ impl From<SortSettings> for TomlSort {
 struct TomlNavigation {
     scroll: Option<usize>,
     inverted: Option<bool>,
+    quick_add: Option<char>,
+    load_more: Option<bool>,
+    select_first: Option<bool>,
 }
 
 impl Default for TomlNavigation {
@@ -327,6 +697,9 @@ impl Default for TomlNavigation {
         TomlNavigation {
             scroll: Some(5),
             inverted: Some(cfg!(macos)),
+            quick_add: Some('n'),
+            load_more: Some(false),
+            select_first: Some(false),
         }
     }
 }
@@ -336,6 +709,9 @@ impl From<TomlNavigation> for NavigationSettings {
         NavigationSettings {
             scroll: t.scroll.unwrap_or(5),
             inverted: t.inverted.unwrap_or(cfg!(macos)),
+            quick_add: t.quick_add.unwrap_or('n'),
+            load_more: t.load_more.unwrap_or(false),
+            select_first: t.select_first.unwrap_or(false),
         }
     }
 }
@@ -345,6 +721,9 @@ impl From<NavigationSettings> for TomlNavigation {
         TomlNavigation {
             scroll: Some(n.scroll),
             inverted: Some(n.inverted),
+            quick_add: Some(n.quick_add),
+            load_more: Some(n.load_more),
+            select_first: Some(n.select_first),
         }
     }
 }
@@ -352,37 +731,204 @@ impl From<NavigationSettings> for TomlNavigation {
 #[derive(Debug, Serialize, Deserialize)]
 struct TomlDatabase {
     file: Option<PathBuf>,
+    cache_size: Option<usize>,
+    variant_edits: Option<String>,
+    rename_in_place: Option<bool>,
+    delete_empty_books: Option<bool>,
+    max_title_length: Option<usize>,
+    max_description_length: Option<usize>,
+    duplicates: Option<String>,
+    tag_separator: Option<String>,
+    insert_batch_size: Option<usize>,
+    format_order: Option<Vec<String>>,
 }
 
 impl Default for TomlDatabase {
     fn default() -> Self {
-        TomlDatabase { file: None }
+        TomlDatabase {
+            file: None,
+            cache_size: None,
+            variant_edits: None,
+            rename_in_place: None,
+            delete_empty_books: None,
+            max_title_length: None,
+            max_description_length: None,
+            duplicates: None,
+            tag_separator: None,
+            insert_batch_size: None,
+            format_order: None,
+        }
     }
 }
 
 impl From<TomlDatabase> for DatabaseSettings {
     fn from(t: TomlDatabase) -> Self {
+        let default = Self::default();
         DatabaseSettings {
-            path: t.file.unwrap_or_else(|| Self::default().path),
+            path: t.file.unwrap_or(default.path),
+            cache_size: t.cache_size.unwrap_or(default.cache_size),
+            variant_edits: t.variant_edits.map_or(default.variant_edits, |s| {
+                str_to_variant_policy_or(s, default.variant_edits)
+            }),
+            rename_in_place: t.rename_in_place.unwrap_or(default.rename_in_place),
+            delete_empty_books: t.delete_empty_books.unwrap_or(default.delete_empty_books),
+            max_title_length: t.max_title_length.unwrap_or(default.max_title_length),
+            max_description_length: t
+                .max_description_length
+                .unwrap_or(default.max_description_length),
+            duplicates: t.duplicates.map_or(default.duplicates, |s| {
+                str_to_insert_policy_or(s, default.duplicates)
+            }),
+            tag_append: t
+                .tag_separator
+                .map_or(default.tag_append, TagAppendPolicy::Idempotent),
+            insert_batch_size: t.insert_batch_size.unwrap_or(default.insert_batch_size),
+            format_order: t.format_order.map_or(default.format_order, |formats| {
+                formats
+                    .iter()
+                    .filter_map(|format| BookType::try_from(OsStr::new(format)).ok())
+                    .collect()
+            }),
         }
     }
 }
 
 impl From<DatabaseSettings> for TomlDatabase {
     fn from(n: DatabaseSettings) -> Self {
-        TomlDatabase { file: Some(n.path) }
+        TomlDatabase {
+            file: Some(n.path),
+            cache_size: Some(n.cache_size),
+            variant_edits: Some(variant_policy_to_string(n.variant_edits)),
+            rename_in_place: Some(n.rename_in_place),
+            delete_empty_books: Some(n.delete_empty_books),
+            max_title_length: Some(n.max_title_length),
+            max_description_length: Some(n.max_description_length),
+            duplicates: Some(insert_policy_to_string(n.duplicates)),
+            tag_separator: match n.tag_append {
+                TagAppendPolicy::Concatenate => None,
+                TagAppendPolicy::Idempotent(separator) => Some(separator),
+            },
+            insert_batch_size: Some(n.insert_batch_size),
+            format_order: Some(
+                n.format_order
+                    .iter()
+                    .map(|book_type| book_type.extensions()[0].to_string())
+                    .collect(),
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TomlMaintenance {
+    log_retention_days: Option<u64>,
+}
+
+impl Default for TomlMaintenance {
+    fn default() -> Self {
+        TomlMaintenance {
+            log_retention_days: None,
+        }
+    }
+}
+
+impl From<TomlMaintenance> for MaintenanceSettings {
+    fn from(t: TomlMaintenance) -> Self {
+        let default = Self::default();
+        MaintenanceSettings {
+            log_retention_days: t.log_retention_days.unwrap_or(default.log_retention_days),
+        }
+    }
+}
+
+impl From<MaintenanceSettings> for TomlMaintenance {
+    fn from(m: MaintenanceSettings) -> Self {
+        TomlMaintenance {
+            log_retention_days: Some(m.log_retention_days),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TomlSession {
+    /// The searches of each filter, as (mode, column, search) triples.
+    filters: Option<Vec<Vec<(String, String, String)>>>,
+}
+
+impl Default for TomlSession {
+    fn default() -> Self {
+        TomlSession { filters: None }
+    }
+}
+
+impl From<TomlSession> for SessionSettings {
+    fn from(t: TomlSession) -> Self {
+        let filters = t.filters.unwrap_or_default();
+        SessionSettings {
+            filters: filters
+                .into_iter()
+                .map(|searches| {
+                    // Searches with unknown modes are dropped, as in other settings.
+                    searches
+                        .into_iter()
+                        .filter_map(|(mode, column, search)| {
+                            Some(Search {
+                                mode: str_to_search_mode(mode)?,
+                                column: str_to_search_column(column),
+                                search,
+                            })
+                        })
+                        .collect::<Vec<_>>()
+                        .into_boxed_slice()
+                })
+                .filter(|searches| !searches.is_empty())
+                .collect(),
+        }
+    }
+}
+
+impl From<SessionSettings> for TomlSession {
+    fn from(s: SessionSettings) -> Self {
+        TomlSession {
+            filters: Some(
+                s.filters
+                    .into_iter()
+                    .map(|searches| {
+                        searches
+                            .into_vec()
+                            .into_iter()
+                            .map(|search| {
+                                (
+                                    search_mode_to_string(search.mode),
+                                    search_column_to_string(search.column),
+                                    search.search,
+                                )
+                            })
+                            .collect()
+                    })
+                    .collect(),
+            ),
+        }
     }
 }
 
 pub struct InterfaceSettings {
     pub interface_style: InterfaceStyle,
-    pub columns: Vec<String>,
+    pub columns: Vec<ColumnDisplay>,
+    pub unsearchable_columns: Vec<String>,
+    pub truncation: String,
+    pub author_display: AuthorDisplay,
+    pub size_units: SizeUnits,
+    pub search_normalization: Normalization,
+    pub show_covers: bool,
     pub navigation_settings: NavigationSettings,
 }
 
 pub struct AppSettings {
     pub sort_settings: SortSettings,
     pub database_settings: DatabaseSettings,
+    pub maintenance_settings: MaintenanceSettings,
+    pub session_settings: SessionSettings,
 }
 
 impl Settings {
@@ -398,22 +944,94 @@ impl Settings {
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, std::io::Error> {
         let f = std::fs::read_to_string(path)?;
         let value: TomlSettings = toml::from_str(f.as_str())?;
+        let layout = value.layout.unwrap_or_default();
         Ok(Settings {
             interface_style: value.colors.unwrap_or_default().into(),
-            columns: value.layout.unwrap_or_default().into(),
+            columns: layout.columns(),
+            unsearchable_columns: layout.unsearchable(),
+            truncation: layout.truncation(),
+            author_display: layout.author_display(),
+            size_units: layout.size_units(),
+            search_normalization: layout.search_normalization(),
+            show_covers: layout.show_covers.unwrap_or(false),
             navigation_settings: value.navigation.unwrap_or_default().into(),
             sort_settings: value.sorting.unwrap_or_default().into(),
             database_settings: value.database.unwrap_or_default().into(),
+            maintenance_settings: value.maintenance.unwrap_or_default().into(),
+            session_settings: value.session.unwrap_or_default().into(),
         })
     }
 
+    /// Creates the settings for a new installation, and writes them to the provided location.
+    /// The settings are the defaults, with the database at `database` if it is provided. The
+    /// directories which will contain the settings and the database are created if they do not
+    /// exist.
+    ///
+    /// # Arguments
+    ///
+    /// * ` path ` - The path to write the settings to.
+    /// * ` database ` - The path of the database, if the default should not be used.
+    ///
+    /// # Error
+    /// Errors if creating the directories or writing the settings fails.
+    pub fn first_run<P: AsRef<Path>>(
+        path: P,
+        database: Option<PathBuf>,
+    ) -> Result<Self, std::io::Error> {
+        let mut settings = Settings::default();
+        if let Some(database) = database {
+            settings.database_settings.path = database;
+        }
+        if let Some(dir) = settings.database_settings.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        if let Some(dir) = path.as_ref().parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        settings.write(path)?;
+        Ok(settings)
+    }
+
     pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), std::io::Error> {
         let value = TomlSettings {
             colors: Some(self.interface_style.clone().into()),
-            layout: Some(self.columns.clone().into()),
+            layout: Some(TomlColumns {
+                columns: Some(
+                    self.columns
+                        .iter()
+                        .filter(|column| column.visible)
+                        .map(|column| column.name.clone())
+                        .collect(),
+                ),
+                unsearchable: Some(self.unsearchable_columns.clone()),
+                truncation: Some(self.truncation.clone()),
+                author_separator: Some(self.author_display.separator.clone()),
+                author_limit: self.author_display.limit,
+                author_order: Some(author_order_to_string(self.author_display.order)),
+                author_names: Some(author_names_to_string(self.author_display.names)),
+                size_units: Some(size_units_to_string(self.size_units)),
+                search_normalization: Some(normalization_to_string(self.search_normalization)),
+                show_covers: Some(self.show_covers),
+                hidden: Some(
+                    self.columns
+                        .iter()
+                        .filter(|column| !column.visible)
+                        .map(|column| column.name.clone())
+                        .collect(),
+                ),
+                widths: Some(
+                    self.columns
+                        .iter()
+                        .filter(|column| column.width != Width::Auto)
+                        .map(|column| (column.name.clone(), width_to_string(column.width)))
+                        .collect(),
+                ),
+            }),
             sorting: Some(self.sort_settings.clone().into()),
             navigation: Some(self.navigation_settings.clone().into()),
             database: Some(self.database_settings.clone().into()),
+            maintenance: Some(self.maintenance_settings.into()),
+            session: Some(self.session_settings.clone().into()),
         };
         std::fs::write(
             path,
@@ -427,13 +1045,170 @@ impl Settings {
         let interface_settings = InterfaceSettings {
             interface_style: self.interface_style,
             columns: self.columns,
+            unsearchable_columns: self.unsearchable_columns,
+            truncation: self.truncation,
+            author_display: self.author_display,
+            size_units: self.size_units,
+            search_normalization: self.search_normalization,
+            show_covers: self.show_covers,
             navigation_settings: self.navigation_settings,
         };
         let app_settings = AppSettings {
             sort_settings: self.sort_settings,
             database_settings: self.database_settings,
+            maintenance_settings: self.maintenance_settings,
+            session_settings: self.session_settings,
         };
 
         (interface_settings, app_settings)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_first_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config/bookworm/settings.toml");
+        let database = dir.path().join("library/bookworm.db");
+
+        let settings = Settings::first_run(&path, Some(database.clone())).unwrap();
+        assert_eq!(settings.database_settings.path, database);
+        assert!(path.is_file());
+        assert!(dir.path().join("library").is_dir());
+
+        let written = Settings::open(&path).unwrap();
+        assert_eq!(written.database_settings.path, database);
+        assert_eq!(written.columns, settings.columns);
+    }
+
+    #[test]
+    fn test_sort_settings_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.toml");
+
+        let mut settings = Settings::default();
+        settings.sort_settings.columns = vec![
+            (ColumnIdentifier::Author, ColumnOrder::Ascending),
+            (ColumnIdentifier::DateAdded, ColumnOrder::Descending),
+        ]
+        .into_boxed_slice();
+        settings.write(&path).unwrap();
+
+        let written = Settings::open(&path).unwrap();
+        assert_eq!(
+            written.sort_settings.columns,
+            settings.sort_settings.columns
+        );
+        assert_eq!(written.sort_settings.collation, Collation::CaseInsensitive);
+    }
+
+    #[test]
+    fn test_session_settings_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.toml");
+
+        let search = |mode: SearchMode, column: ColumnIdentifier, search: &str| Search {
+            mode,
+            column,
+            search: search.to_string(),
+        };
+        let mut settings = Settings::default();
+        settings.session_settings.filters = vec![
+            vec![search(SearchMode::Default, ColumnIdentifier::Title, "dune")].into_boxed_slice(),
+            vec![
+                search(SearchMode::Regex, ColumnIdentifier::Author, "^Frank"),
+                search(
+                    SearchMode::FileSize(SizeUnits::Binary),
+                    ColumnIdentifier::Variants,
+                    ">1MB",
+                ),
+                search(
+                    SearchMode::Empty,
+                    ColumnIdentifier::NamedTag("genre".to_string()),
+                    "",
+                ),
+                search(
+                    SearchMode::ExactString,
+                    ColumnIdentifier::MultiMap("genre".to_string()),
+                    "fantasy",
+                ),
+            ]
+            .into_boxed_slice(),
+        ];
+        settings.write(&path).unwrap();
+
+        let written = Settings::open(&path).unwrap();
+        assert_eq!(
+            written.session_settings.filters,
+            settings.session_settings.filters
+        );
+
+        // Settings written before filters were persisted have no filters.
+        std::fs::write(&path, "[navigation]\nscroll = 3\n").unwrap();
+        let written = Settings::open(&path).unwrap();
+        assert!(written.session_settings.filters.is_empty());
+    }
+
+    #[test]
+    fn test_column_display_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.toml");
+
+        let mut settings = Settings::default();
+        settings.columns = vec![
+            ColumnDisplay::new("Title"),
+            ColumnDisplay {
+                name: String::from("Description"),
+                width: Width::Fixed(20),
+                visible: true,
+            },
+            ColumnDisplay {
+                name: String::from("Series"),
+                width: Width::Percent(25),
+                visible: false,
+            },
+        ];
+        settings.show_covers = true;
+        settings.write(&path).unwrap();
+        let written = Settings::open(&path).unwrap();
+        assert_eq!(written.columns, settings.columns);
+        assert!(written.show_covers);
+
+        assert_eq!(str_to_width_or("Auto", Width::Fixed(1)), Width::Auto);
+        assert_eq!(str_to_width_or(" 30% ", Width::Auto), Width::Percent(30));
+        assert_eq!(str_to_width_or("150%", Width::Auto), Width::Auto);
+        assert_eq!(str_to_width_or("wide", Width::Auto), Width::Auto);
+    }
+
+    #[test]
+    fn test_format_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.toml");
+
+        let mut settings = Settings::default();
+        settings.database_settings.format_order = vec![BookType::EPUB, BookType::MOBI];
+        settings.write(&path).unwrap();
+        let written = Settings::open(&path).unwrap();
+        assert_eq!(
+            written.database_settings.format_order,
+            settings.database_settings.format_order
+        );
+
+        // Formats are named by their extensions, and unknown formats are ignored.
+        let toml = TomlDatabase {
+            format_order: Some(vec![
+                "AZW3".to_string(),
+                "djvu".to_string(),
+                "pdf".to_string(),
+            ]),
+            ..Default::default()
+        };
+        assert_eq!(
+            DatabaseSettings::from(toml).format_order,
+            vec![BookType::MOBI, BookType::PDF]
+        );
+    }
+}
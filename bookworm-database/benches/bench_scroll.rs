@@ -0,0 +1,111 @@
+#![feature(test)]
+extern crate test;
+use test::Bencher;
+
+use std::convert::TryFrom;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use rand::distributions::Alphanumeric;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use tokio::runtime::Runtime;
+use tokio::sync::RwLock;
+
+use bookworm_database::{AppDatabase, BookView, SQLiteDatabase};
+use bookworm_records::book::BookID;
+use bookworm_records::variant::BookType;
+use bookworm_records::BookVariant;
+
+const NUM_BOOKS: usize = 10_000;
+const WINDOW_SIZE: usize = 50;
+
+fn generate_random_string(rng: &mut StdRng, len: usize) -> String {
+    rng.sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+fn generate_random_variant(rng: &mut StdRng) -> BookVariant {
+    let title_len = rng.gen_range(3..40);
+    let title = generate_random_string(rng, title_len);
+    let authors = (0..rng.gen_range(1..4))
+        .map(|_| {
+            let len = rng.gen_range(5..20);
+            generate_random_string(rng, len)
+        })
+        .collect();
+    let description_len = rng.gen_range(0..500);
+    BookVariant {
+        book_type: BookType::EPUB,
+        path: PathBuf::from(format!("{}.epub", title)),
+        file_size: rng.gen(),
+        local_title: Some(title),
+        identifier: None,
+        language: None,
+        additional_authors: Some(authors),
+        translators: None,
+        description: Some(generate_random_string(rng, description_len)),
+        series: None,
+        id: None,
+        hash: rng.gen(),
+        free_tags: Default::default(),
+        named_tags: Default::default(),
+    }
+}
+
+/// Creates a database in `dir` containing `num_books` randomly generated books. The generator
+/// is seeded, so that each run benchmarks the same books. No books are kept in memory, so that
+/// all reads go to SQLite.
+fn generate_database(rt: &Runtime, dir: &tempfile::TempDir, num_books: usize) -> SQLiteDatabase {
+    let mut rng = StdRng::seed_from_u64(0);
+    let variants: Vec<_> = (0..num_books)
+        .map(|_| generate_random_variant(&mut rng))
+        .collect();
+    rt.block_on(async {
+        let mut db = SQLiteDatabase::open(dir.path().join("bench.db"))
+            .await
+            .unwrap();
+        db.insert_books(variants.into_iter()).await.unwrap();
+        db.set_cache_capacity(Some(0)).await;
+        db
+    })
+}
+
+#[bench]
+fn bench_read_books_from_sql(b: &mut Bencher) {
+    let rt = Runtime::new().unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let db = generate_database(&rt, &dir, NUM_BOOKS);
+    let ids: Vec<_> = (1..=WINDOW_SIZE as u64)
+        .filter_map(|id| BookID::try_from(id).ok())
+        .collect();
+
+    b.iter(|| {
+        rt.block_on(async { test::black_box(db.get_books(&ids).await.unwrap()) });
+    });
+}
+
+#[bench]
+fn bench_scroll(b: &mut Bencher) {
+    let rt = Runtime::new().unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let db = generate_database(&rt, &dir, NUM_BOOKS);
+    let mut view = rt.block_on(async {
+        let mut view = BookView::new(Arc::new(RwLock::new(db))).await;
+        view.refresh_window_size(WINDOW_SIZE).await.unwrap();
+        view
+    });
+
+    // Scrolls through the whole database, a page at a time.
+    b.iter(|| {
+        rt.block_on(async {
+            view.home().await.unwrap();
+            for _ in 0..NUM_BOOKS / WINDOW_SIZE {
+                view.page_down().await.unwrap();
+            }
+            test::black_box(view.window().len())
+        })
+    });
+}
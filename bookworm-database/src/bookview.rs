@@ -6,10 +6,10 @@ use tokio::sync::RwLock;
 use unicase::UniCase;
 
 use bookworm_records::book::{BookID, ColumnIdentifier, RecordError};
-use bookworm_records::{Book, ColumnOrder};
+use bookworm_records::{Book, Collation, ColumnOrder};
 
-use crate::paginator::{Paginator, Selection};
-use crate::search::{Error as SearchError, Search};
+use crate::paginator::{Direction, Paginator, Selection};
+use crate::search::{Error as SearchError, Matcher, Normalization, Search};
 use crate::{AppDatabase, DatabaseError};
 
 #[derive(Debug)]
@@ -45,8 +45,14 @@ impl<DBError> From<RecordError> for BookViewError<DBError> {
 
 pub struct BookView<D: AppDatabase + 'static> {
     scopes: Vec<Paginator<D>>,
+    // The searches which created each scope, and whether the scope is temporary.
+    filters: Vec<(Box<[Search]>, bool)>,
     // The "root" scope.
     root_cursor: Paginator<D>,
+    // Matches which are highlighted, rather than filtered.
+    highlight: Box<[Box<dyn Matcher + Send + Sync>]>,
+    // How text is normalized by searches.
+    normalization: Normalization,
     db: Arc<RwLock<D>>,
 }
 
@@ -61,9 +67,14 @@ impl<D: AppDatabase> BookView<D> {
 
 impl<D: AppDatabase + Send + Sync + 'static> BookView<D> {
     pub async fn new(db: Arc<RwLock<D>>) -> Self {
+        let mut root_cursor = Paginator::new(db.clone(), 0, vec![].into_boxed_slice());
+        root_cursor.set_full_text_search(db.read().await.supports_full_text_search());
         Self {
             scopes: vec![],
-            root_cursor: Paginator::new(db.clone(), 0, vec![].into_boxed_slice()),
+            filters: vec![],
+            root_cursor,
+            highlight: vec![].into_boxed_slice(),
+            normalization: Normalization::default(),
             db,
         }
     }
@@ -86,6 +97,14 @@ impl<D: AppDatabase + Send + Sync + 'static> BookView<D> {
         Ok(())
     }
 
+    /// Reads every book in the current scope, in the current sort order.
+    pub async fn read_all(&self) -> Result<Vec<Arc<Book>>, DatabaseError<D::Error>> {
+        match self.scopes.last() {
+            None => self.root_cursor.read_all().await,
+            Some(cursor) => cursor.read_all().await,
+        }
+    }
+
     pub fn selected_books(&self) -> &Selection {
         match self.scopes.last() {
             None => self.root_cursor.selected(),
@@ -107,6 +126,37 @@ impl<D: AppDatabase + Send + Sync + 'static> BookView<D> {
         self.root_cursor.window_size()
     }
 
+    /// Sets whether all scopes prefetch books beyond those needed to fill the window.
+    pub fn set_prefetch(&mut self, prefetch: bool) {
+        for cursor in std::iter::once(&mut self.root_cursor).chain(self.scopes.iter_mut()) {
+            cursor.set_prefetch(prefetch);
+        }
+    }
+
+    /// Returns whether the active scope prefetches books beyond those needed to fill the window.
+    pub fn prefetch(&self) -> bool {
+        self.scopes.last().unwrap_or(&self.root_cursor).prefetch()
+    }
+
+    /// Sets how all scopes compare text columns when sorting. Takes effect on the next sort
+    /// or refresh.
+    pub fn set_collation(&mut self, collation: Collation) {
+        for cursor in std::iter::once(&mut self.root_cursor).chain(self.scopes.iter_mut()) {
+            cursor.set_collation(collation);
+        }
+    }
+
+    /// Sets how text is normalized by searches. Takes effect on the next search, or the next
+    /// highlight.
+    pub fn set_normalization(&mut self, normalization: Normalization) {
+        self.normalization = normalization;
+    }
+
+    /// Returns how text is normalized by searches.
+    pub fn normalization(&self) -> Normalization {
+        self.normalization
+    }
+
     /// Returns the books in the selection with their index, relative to the top
     /// and the book itself
     pub fn relative_selections(&self) -> Vec<(usize, &Arc<Book>)> {
@@ -134,18 +184,48 @@ impl<D: AppDatabase + Send + Sync + 'static> BookView<D> {
     pub async fn has_column(&self, col: &UniCase<String>) -> Result<bool, DatabaseError<D::Error>> {
         self.db.read().await.has_column(col).await
     }
+
+    /// Returns the encoded cover image of the book with the given ID, if it has one.
+    pub async fn cover(&self, id: BookID) -> Result<Option<Vec<u8>>, DatabaseError<D::Error>> {
+        self.db.read().await.get_cover(id).await
+    }
 }
 
 impl<D: AppDatabase + Send + Sync> BookView<D> {
     pub async fn push_scope(&mut self, searches: &[Search]) -> Result<(), BookViewError<D::Error>> {
         self.scopes.push(self.create_paginator(searches));
+        self.filters
+            .push((searches.to_vec().into_boxed_slice(), false));
+        Ok(())
+    }
+
+    /// Pushes a scope like `push_scope`, but marks it as temporary, so that its searches
+    /// are excluded from `persisted_filters`.
+    pub async fn push_temp_scope(
+        &mut self,
+        searches: &[Search],
+    ) -> Result<(), BookViewError<D::Error>> {
+        self.scopes.push(self.create_paginator(searches));
+        self.filters
+            .push((searches.to_vec().into_boxed_slice(), true));
         Ok(())
     }
 
     pub fn pop_scope(&mut self) -> bool {
+        self.filters.pop();
         self.scopes.pop().is_some()
     }
 
+    /// Returns the searches of each non-temporary scope, in the order they were applied.
+    /// This is the filter state which should be written when persisting the session.
+    pub fn persisted_filters(&self) -> Vec<Box<[Search]>> {
+        self.filters
+            .iter()
+            .filter(|(_, temporary)| !temporary)
+            .map(|(searches, _)| searches.clone())
+            .collect()
+    }
+
     fn create_paginator(&self, searches: &[Search]) -> Paginator<D> {
         let mut matchers = vec![];
         for scope in &self.scopes {
@@ -154,17 +234,21 @@ impl<D: AppDatabase + Send + Sync> BookView<D> {
         for item in searches
             .iter()
             .cloned()
-            .map(Search::into_matcher)
+            .map(|search| search.into_normalized_matcher(self.normalization))
             .filter_map(Result::ok)
         {
             matchers.push(item);
         }
-        Paginator::new(
+        let mut paginator = Paginator::new(
             self.db.clone(),
             self.root_cursor.window_size(),
             self.root_cursor.sort_rules().to_vec().into_boxed_slice(),
         )
-        .bind_match(matchers.into_boxed_slice())
+        .bind_match(matchers.into_boxed_slice());
+        paginator.set_prefetch(self.root_cursor.prefetch());
+        paginator.set_collation(self.root_cursor.collation());
+        paginator.set_full_text_search(self.root_cursor.full_text_search());
+        paginator
     }
 
     #[tracing::instrument(name = "Jumping to match target", skip(self, searches))]
@@ -189,6 +273,52 @@ impl<D: AppDatabase + Send + Sync> BookView<D> {
         Ok(true)
     }
 
+    /// Highlights all books matching `searches`, without hiding books which do not match.
+    /// Highlighted books can be visited using `next_match` and `prev_match`.
+    pub fn highlight(&mut self, searches: &[Search]) -> Result<(), BookViewError<D::Error>> {
+        let mut matchers = vec![];
+        for search in searches.iter().cloned() {
+            matchers.push(search.into_normalized_matcher(self.normalization)?);
+        }
+        self.highlight = matchers.into_boxed_slice();
+        Ok(())
+    }
+
+    /// Removes the active highlight, and returns whether a highlight was active.
+    pub fn clear_highlight(&mut self) -> bool {
+        !std::mem::take(&mut self.highlight).is_empty()
+    }
+
+    /// Returns true if a highlight is active and the book matches it.
+    pub fn is_highlighted(&self, book: &Book) -> bool {
+        !self.highlight.is_empty() && self.highlight.iter().all(|m| m.is_match(book))
+    }
+
+    /// Selects the next highlighted book after the current selection, wrapping around
+    /// to the start if necessary. Returns whether a highlighted book was found.
+    pub async fn next_match(&mut self) -> Result<bool, DatabaseError<D::Error>> {
+        self.step_match(Direction::Down).await
+    }
+
+    /// Selects the previous highlighted book before the current selection, wrapping around
+    /// to the end if necessary. Returns whether a highlighted book was found.
+    pub async fn prev_match(&mut self) -> Result<bool, DatabaseError<D::Error>> {
+        self.step_match(Direction::Up).await
+    }
+
+    async fn step_match(&mut self, direction: Direction) -> Result<bool, DatabaseError<D::Error>> {
+        if self.highlight.is_empty() {
+            return Ok(false);
+        }
+        let highlight = std::mem::take(&mut self.highlight);
+        let found = self
+            .active_cursor_mut()
+            .select_next_match(&highlight, direction)
+            .await;
+        self.highlight = highlight;
+        found
+    }
+
     pub async fn scroll_up(&mut self, scroll: usize) -> Result<(), DatabaseError<D::Error>> {
         self.active_cursor_mut().scroll_up(scroll).await
     }
@@ -241,6 +371,11 @@ impl<D: AppDatabase + Send + Sync> BookView<D> {
         self.active_cursor_mut().select_page_down().await
     }
 
+    /// Selects the first book, in sort order, if no books are selected.
+    pub async fn select_first(&mut self) -> Result<(), DatabaseError<D::Error>> {
+        self.active_cursor_mut().select_first().await
+    }
+
     pub async fn select_to_start(&mut self) -> Result<(), DatabaseError<D::Error>> {
         self.active_cursor_mut().select_to_start().await
     }
@@ -249,3 +384,133 @@ impl<D: AppDatabase + Send + Sync> BookView<D> {
         self.active_cursor_mut().select_to_end().await
     }
 }
+
+#[cfg(all(test, feature = "sqlite"))]
+mod test {
+    use super::*;
+
+    use bookworm_records::test_util::variant;
+
+    use crate::search::SearchMode;
+    use crate::SQLiteDatabase;
+
+    #[tokio::test]
+    async fn test_select_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        let titles = ["delta", "alpha", "charlie"];
+        db.insert_books(
+            titles
+                .iter()
+                .enumerate()
+                .map(|(i, title)| variant(title, i as u8)),
+        )
+        .await
+        .unwrap();
+
+        let mut view = BookView::new(Arc::new(RwLock::new(db))).await;
+        view.sort_by_columns(&[(ColumnIdentifier::Title, ColumnOrder::Ascending)])
+            .await
+            .unwrap();
+        assert!(view.selected_books().first().is_none());
+
+        // Selecting does not require the window to be loaded.
+        view.select_first().await.unwrap();
+        let selected_title = |view: &BookView<SQLiteDatabase>| {
+            view.selected_books()
+                .first()
+                .and_then(|book| book.title.clone())
+        };
+        assert_eq!(selected_title(&view).as_deref(), Some("alpha"));
+
+        // An existing selection is kept.
+        view.refresh_window_size(titles.len()).await.unwrap();
+        view.down().await.unwrap();
+        view.select_first().await.unwrap();
+        assert_eq!(selected_title(&view).as_deref(), Some("charlie"));
+    }
+
+    #[tokio::test]
+    async fn test_next_match_advances_selection() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        let titles = ["alpha", "beta match", "gamma", "delta match", "epsilon"];
+        db.insert_books(
+            titles
+                .iter()
+                .enumerate()
+                .map(|(i, title)| variant(title, i as u8)),
+        )
+        .await
+        .unwrap();
+
+        let mut view = BookView::new(Arc::new(RwLock::new(db))).await;
+        view.refresh_window_size(titles.len()).await.unwrap();
+        view.highlight(&[Search {
+            mode: SearchMode::Default,
+            column: ColumnIdentifier::Title,
+            search: "match".to_string(),
+        }])
+        .unwrap();
+
+        let selected_title = |view: &BookView<SQLiteDatabase>| {
+            view.selected_books()
+                .first()
+                .and_then(|book| book.title.clone())
+        };
+
+        assert!(view.next_match().await.unwrap());
+        assert_eq!(selected_title(&view).as_deref(), Some("beta match"));
+        assert!(view.next_match().await.unwrap());
+        assert_eq!(selected_title(&view).as_deref(), Some("delta match"));
+        // Wraps around to the first match.
+        assert!(view.next_match().await.unwrap());
+        assert_eq!(selected_title(&view).as_deref(), Some("beta match"));
+        assert!(view.prev_match().await.unwrap());
+        assert_eq!(selected_title(&view).as_deref(), Some("delta match"));
+
+        assert!(view.window().iter().all(|book| {
+            view.is_highlighted(book) == book.title().map_or(false, |t| t.contains("match"))
+        }));
+        assert!(view.clear_highlight());
+        assert!(!view.next_match().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_temp_filter_is_not_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        let mut view = BookView::new(Arc::new(RwLock::new(db))).await;
+        let search = |title: &str| Search {
+            mode: SearchMode::Default,
+            column: ColumnIdentifier::Title,
+            search: title.to_string(),
+        };
+
+        view.push_scope(&[search("saved")]).await.unwrap();
+        view.push_temp_scope(&[search("scratch")]).await.unwrap();
+        assert_eq!(
+            view.persisted_filters(),
+            vec![vec![search("saved")].into_boxed_slice()]
+        );
+
+        view.push_scope(&[search("later")]).await.unwrap();
+        assert_eq!(
+            view.persisted_filters(),
+            vec![
+                vec![search("saved")].into_boxed_slice(),
+                vec![search("later")].into_boxed_slice()
+            ]
+        );
+
+        assert!(view.pop_scope());
+        assert!(view.pop_scope());
+        assert_eq!(view.persisted_filters().len(), 1);
+    }
+}
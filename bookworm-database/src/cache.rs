@@ -1,4 +1,4 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::Arc;
 
 #[cfg(feature = "serde")]
@@ -12,67 +12,162 @@ use bookworm_records::{Book, Edit as BEdit};
 /// `BookCache` acts as an intermediate caching layer between the backend database
 /// and the front-end UI - allowing books that are already in memory to be provided
 /// without going through the database.
+///
+/// When the cache is full, the least recently used book is evicted. Books which are referenced
+/// outside of the cache (eg. books in a paginator's window) are never evicted.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug)]
 pub(crate) struct BookCache {
-    books: HashMap<BookID, Arc<Book>>,
+    /// Maps each book's ID to the book and the time at which it was last used.
+    books: HashMap<BookID, (Arc<Book>, u64)>,
+    /// Maps each use time to the ID of the book used at that time.
+    recency: BTreeMap<u64, BookID>,
+    clock: u64,
     cols: HashSet<UniCase<String>>,
     capacity: Option<usize>,
 }
 
+/// Returns the columns which every book has.
+fn default_columns() -> impl Iterator<Item = UniCase<String>> {
+    ["title", "authors", "series", "id", "description"]
+        .iter()
+        .map(|s| s.to_string())
+        .map(UniCase::new)
+}
+
 impl Default for BookCache {
     fn default() -> Self {
         BookCache {
             books: HashMap::default(),
-            cols: ["title", "authors", "series", "id", "description"]
-                .iter()
-                .map(|s| s.to_string())
-                .map(UniCase::new)
-                .collect(),
+            recency: BTreeMap::default(),
+            clock: 0,
+            cols: default_columns().collect(),
             capacity: Some(32768),
         }
     }
 }
 
 impl BookCache {
+    /// Creates a cache containing the provided books and columns. `capacity` is not enforced
+    /// until the next insertion.
     pub(crate) fn from_values_unchecked(
         books: HashMap<BookID, Arc<Book>>,
         cols: HashSet<UniCase<String>>,
+        capacity: Option<usize>,
     ) -> Self {
-        BookCache {
-            books,
+        let mut cache = BookCache {
+            books: HashMap::with_capacity(books.len()),
+            recency: BTreeMap::new(),
+            clock: 0,
             cols,
-            capacity: None,
+            capacity,
+        };
+        for (id, book) in books {
+            cache.clock += 1;
+            cache.recency.insert(cache.clock, id);
+            cache.books.insert(id, (book, cache.clock));
+        }
+        cache
+    }
+
+    pub(crate) fn capacity(&self) -> Option<usize> {
+        self.capacity
+    }
+
+    /// Sets the maximum number of books in the cache, evicting the least recently used books
+    /// if necessary.
+    pub(crate) fn set_capacity(&mut self, capacity: Option<usize>) {
+        self.capacity = capacity;
+        self.make_space(0);
+    }
+
+    /// Marks the book with the given ID as the most recently used book, and returns it.
+    fn touch(&mut self, id: BookID) -> Option<&Arc<Book>> {
+        let (book, last_used) = self.books.get_mut(&id)?;
+        self.recency.remove(last_used);
+        self.clock += 1;
+        *last_used = self.clock;
+        self.recency.insert(self.clock, id);
+        Some(book)
+    }
+
+    /// Evicts the least recently used book which is not referenced outside of the cache.
+    /// Returns false if no such book exists.
+    fn evict_lru(&mut self) -> bool {
+        let books = &self.books;
+        let lru = self
+            .recency
+            .iter()
+            .find(|(_, id)| {
+                books
+                    .get(id)
+                    .map_or(true, |(book, _)| Arc::strong_count(book) == 1)
+            })
+            .map(|(&last_used, &id)| (last_used, id));
+
+        match lru {
+            None => false,
+            Some((last_used, id)) => {
+                self.recency.remove(&last_used);
+                self.books.remove(&id);
+                true
+            }
+        }
+    }
+
+    /// Evicts books until `additional` books can be inserted without exceeding the capacity,
+    /// or until no more books can be evicted.
+    fn make_space(&mut self, additional: usize) {
+        if let Some(capacity) = self.capacity {
+            while self.books.len() + additional > capacity {
+                if !self.evict_lru() {
+                    break;
+                }
+            }
         }
     }
 
     /// Inserts `book` into `self`, making space in the internal cache if necessary.
     pub(crate) fn insert_book(&mut self, book: Arc<Book>) {
-        if Some(self.books.len()) >= self.capacity {
-            if let Some(key) = self.books.keys().next().cloned() {
-                self.books.remove(&key);
-            }
+        let id = book.id();
+        if let Some((cached, _)) = self.books.get_mut(&id) {
+            *cached = book;
+            self.touch(id);
+            return;
         }
-        self.books.insert(book.id(), book);
+
+        self.make_space(1);
+        self.clock += 1;
+        self.recency.insert(self.clock, id);
+        self.books.insert(id, (book, self.clock));
     }
 
     pub(crate) fn insert_columns<I: IntoIterator<Item = UniCase<String>>>(&mut self, columns: I) {
         self.cols.extend(columns.into_iter());
     }
 
+    /// Replaces the known columns with the default columns and the provided columns.
+    pub(crate) fn set_columns<I: IntoIterator<Item = UniCase<String>>>(&mut self, columns: I) {
+        self.cols = default_columns().chain(columns).collect();
+    }
+
     pub fn remove_books(&mut self, ids: &HashSet<BookID>) {
         for id in ids {
-            self.books.remove(id);
+            if let Some((_, last_used)) = self.books.remove(id) {
+                self.recency.remove(&last_used);
+            }
         }
     }
 
     pub fn clear(&mut self) {
         self.books.clear();
+        self.recency.clear();
     }
 
+    /// Returns the books with the given IDs, marking each as recently used.
     #[must_use]
-    pub fn get_books(&self, ids: &[BookID]) -> Vec<Option<&Arc<Book>>> {
-        ids.iter().map(|id| self.books.get(id)).collect()
+    pub fn get_books(&mut self, ids: &[BookID]) -> Vec<Option<Arc<Book>>> {
+        ids.iter().map(|id| self.touch(*id).cloned()).collect()
     }
 
     pub fn edit_book_with_id(
@@ -82,7 +177,7 @@ impl BookCache {
     ) -> Result<bool, RecordError> {
         match self.books.get_mut(&id) {
             None => Ok(false),
-            Some(book) => {
+            Some((book, _)) => {
                 for (column, edit) in edits {
                     let edit = match edit {
                         Edit::Delete => BEdit::Delete,
@@ -101,6 +196,10 @@ impl BookCache {
                         ColumnIdentifier::NamedTag(x) => {
                             self.cols.insert(UniCase::new(x.to_owned()));
                         }
+                        ColumnIdentifier::ReadStatus => {
+                            self.cols
+                                .insert(UniCase::new(ColumnIdentifier::ReadStatus.into_string()));
+                        }
                         _ => {}
                     }
                 }
@@ -116,7 +215,7 @@ impl BookCache {
     pub fn merge_similar_books(&mut self) -> Vec<(BookID, BookID)> {
         let mut ref_map: HashMap<(String, String), BookID> = HashMap::new();
         let mut merges = vec![];
-        for book in self.books.values() {
+        for (book, _) in self.books.values() {
             if let Some(title) = book.title() {
                 if let Some(authors) = book.authors() {
                     let a: String = authors.join(", ").to_ascii_lowercase();
@@ -131,10 +230,13 @@ impl BookCache {
         }
 
         for (b1, b2_id) in merges.iter() {
-            let b2 = self.books.remove(b2_id);
+            let b2 = self.books.remove(b2_id).map(|(b2, last_used)| {
+                self.recency.remove(&last_used);
+                b2
+            });
             // b1, b2 always exist: ref_map only stores b1. Any given b2 will only ever merge
             // into an instance of b1, and a b1 will never merge into a b2
-            if let Some(b1) = self.books.get_mut(b1) {
+            if let Some((b1, _)) = self.books.get_mut(b1) {
                 if let Some(b2) = b2 {
                     Arc::make_mut(b1).merge_mut(&b2);
                 }
@@ -148,3 +250,48 @@ impl BookCache {
         self.cols.contains(col)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::convert::TryFrom;
+
+    fn book(id: u64) -> Arc<Book> {
+        Arc::new(Book {
+            id: Some(BookID::try_from(id).unwrap()),
+            ..Default::default()
+        })
+    }
+
+    fn cached(cache: &mut BookCache, id: u64) -> bool {
+        cache.get_books(&[BookID::try_from(id).unwrap()])[0].is_some()
+    }
+
+    #[test]
+    fn test_insert_evicts_lru() {
+        let mut cache = BookCache::from_values_unchecked(HashMap::new(), HashSet::new(), Some(2));
+        cache.insert_book(book(1));
+        cache.insert_book(book(2));
+        // Book 2 becomes the least recently used book.
+        assert!(cached(&mut cache, 1));
+        cache.insert_book(book(3));
+
+        assert!(cached(&mut cache, 1));
+        assert!(!cached(&mut cache, 2));
+        assert!(cached(&mut cache, 3));
+    }
+
+    #[test]
+    fn test_insert_does_not_evict_referenced_books() {
+        let mut cache = BookCache::from_values_unchecked(HashMap::new(), HashSet::new(), Some(2));
+        let window = vec![book(1)];
+        cache.insert_book(window[0].clone());
+        cache.insert_book(book(2));
+        cache.insert_book(book(3));
+
+        assert!(cached(&mut cache, 1));
+        assert!(!cached(&mut cache, 2));
+        assert!(cached(&mut cache, 3));
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::ffi::OsStr;
+use std::path::Path;
+
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::SqlitePool;
+
+use bookworm_records::series::Series;
+use bookworm_records::variant::{hash_file, BookType, Identifier};
+use bookworm_records::BookVariant;
+
+/// The name of the database file at the root of a Calibre library.
+pub const CALIBRE_METADATA: &str = "metadata.db";
+
+/// Groups the rows of a link table by the ID of the book they belong to, keeping the order in
+/// which the rows were read.
+fn group_by_book<T>(rows: Vec<(i64, T)>) -> HashMap<i64, Vec<T>> {
+    let mut groups: HashMap<i64, Vec<T>> = HashMap::new();
+    for (book, value) in rows {
+        groups.entry(book).or_default().push(value);
+    }
+    groups
+}
+
+/// Reads every book from the Calibre metadata database at `path`, which is opened read-only,
+/// as the variants of each book. Each format in the `data` table becomes a variant of its book,
+/// with files resolved relative to the directory containing the database. Calibre links authors
+/// to books through the `books_authors_link` table, and stores series indices as floats - these
+/// become the authors and `Series.index` of each variant.
+///
+/// Formats which bookworm does not support are skipped with a message, and formats whose files
+/// can not be read are skipped with a warning, as they can not be hashed. Books with no
+/// remaining formats are skipped.
+///
+/// # Errors
+/// Will return an error if the database can not be opened, or if it does not have Calibre's
+/// tables.
+pub(crate) async fn read_books(path: &Path) -> Result<Vec<Vec<BookVariant>>, sqlx::Error> {
+    let connection = SqlitePoolOptions::new()
+        .connect_with(SqliteConnectOptions::new().filename(path).read_only(true))
+        .await?;
+    let books = read_books_from(&connection, path.parent().unwrap_or_else(|| Path::new(""))).await;
+    connection.close().await;
+    books
+}
+
+async fn read_books_from(
+    connection: &SqlitePool,
+    library: &Path,
+) -> Result<Vec<Vec<BookVariant>>, sqlx::Error> {
+    let rows: Vec<(i64, String, String, f64)> =
+        sqlx::query_as("SELECT id, title, path, series_index FROM books ORDER BY id;")
+            .fetch_all(connection)
+            .await?;
+
+    let mut authors = group_by_book(
+        sqlx::query_as::<_, (i64, String)>(
+            "SELECT books_authors_link.book, authors.name FROM books_authors_link
+            JOIN authors ON authors.id = books_authors_link.author
+            ORDER BY books_authors_link.id;",
+        )
+        .fetch_all(connection)
+        .await?,
+    );
+    let mut series = group_by_book(
+        sqlx::query_as::<_, (i64, String)>(
+            "SELECT books_series_link.book, series.name FROM books_series_link
+            JOIN series ON series.id = books_series_link.series;",
+        )
+        .fetch_all(connection)
+        .await?,
+    );
+    let mut identifiers = group_by_book(
+        sqlx::query_as::<_, (i64, String, String)>(
+            "SELECT book, type, val FROM identifiers ORDER BY id;",
+        )
+        .fetch_all(connection)
+        .await?
+        .into_iter()
+        .map(|(book, scheme, value)| (book, (scheme, value)))
+        .collect(),
+    );
+    let mut formats = group_by_book(
+        sqlx::query_as::<_, (i64, String, String)>(
+            "SELECT book, format, name FROM data ORDER BY id;",
+        )
+        .fetch_all(connection)
+        .await?
+        .into_iter()
+        .map(|(book, format, name)| (book, (format, name)))
+        .collect(),
+    );
+
+    let mut books = Vec::with_capacity(rows.len());
+    for (id, title, book_path, series_index) in rows {
+        let authors = authors.remove(&id).filter(|authors| !authors.is_empty());
+        // ISBNs identify a book better than store-specific identifiers.
+        let identifiers = identifiers.remove(&id).unwrap_or_default();
+        let identifier = identifiers
+            .iter()
+            .find(|(scheme, _)| scheme == "isbn")
+            .or_else(|| identifiers.first())
+            .map(|(scheme, value)| Identifier::new(scheme, value.clone()));
+
+        let series = series
+            .remove(&id)
+            .and_then(|names| names.into_iter().next())
+            .map(|name| Series {
+                name,
+                index: Some(series_index as f32),
+            });
+        let mut variants = vec![];
+        for (format, name) in formats.remove(&id).unwrap_or_default() {
+            let extension = format.to_lowercase();
+            let book_type = match BookType::try_from(OsStr::new(&extension)) {
+                Ok(book_type) => book_type,
+                Err(_) => {
+                    tracing::info!("Skipping unsupported {} format of {}", format, title);
+                    continue;
+                }
+            };
+            let path = library
+                .join(&book_path)
+                .join(format!("{}.{}", name, extension));
+            let (file_size, hash) = match hash_file(&path) {
+                Ok(file_info) => file_info,
+                Err(e) => {
+                    tracing::warn!(
+                        "Skipping {}, which could not be read: {}",
+                        path.display(),
+                        e
+                    );
+                    continue;
+                }
+            };
+            variants.push(BookVariant {
+                book_type,
+                path,
+                file_size,
+                local_title: Some(title.clone()),
+                identifier: identifier.clone(),
+                language: None,
+                additional_authors: authors.clone(),
+                translators: None,
+                description: None,
+                series: series.clone(),
+                id: Some(variants.len() as u32),
+                hash,
+                free_tags: Default::default(),
+                named_tags: Default::default(),
+            });
+        }
+
+        if variants.is_empty() {
+            tracing::info!("Skipping {}, which has no readable formats", title);
+        } else {
+            books.push(variants);
+        }
+    }
+    Ok(books)
+}
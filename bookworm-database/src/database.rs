@@ -1,9 +1,12 @@
 use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
+use std::io::Write;
 use std::path;
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
+use futures::stream::BoxStream;
 use unicase::UniCase;
 
 use bookworm_input::Edit;
@@ -11,6 +14,7 @@ use bookworm_records::book::{BookID, ColumnIdentifier, RecordError};
 use bookworm_records::{Book, BookVariant};
 
 use crate::paginator::{Selection, Variable};
+use crate::ExportFormat;
 
 #[derive(Debug)]
 pub enum DatabaseError<DBError> {
@@ -32,6 +36,269 @@ impl<DBError> From<RecordError> for DatabaseError<DBError> {
     }
 }
 
+/// Determines which variants of each book are modified when bulk-editing columns which are
+/// stored per variant, such as the description.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum VariantEditPolicy {
+    /// Edit every variant of each book.
+    AllVariants,
+    /// Only edit the preferred variant of each book.
+    PrimaryOnly,
+}
+
+impl Default for VariantEditPolicy {
+    fn default() -> Self {
+        VariantEditPolicy::PrimaryOnly
+    }
+}
+
+/// Determines what happens when an inserted variant has the same file size and hash as a
+/// variant which is already in the database.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum InsertPolicy {
+    /// Always create a new book, even if the file is already in the database.
+    AlwaysNew,
+    /// Do not insert the variant, and return the ID of the book which has the file.
+    SkipDuplicates,
+    /// Add the variant to the book which has the file, and return the ID of that book.
+    AttachVariant,
+}
+
+impl Default for InsertPolicy {
+    fn default() -> Self {
+        InsertPolicy::AlwaysNew
+    }
+}
+
+/// Determines how a value appended to a named tag is combined with the tag's existing value.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TagAppendPolicy {
+    /// The appended text is concatenated to the existing value.
+    Concatenate,
+    /// Named tags are lists of values, separated by the provided separator. Appending a value
+    /// which is already in the list does nothing, and other values are added to the end of
+    /// the list.
+    Idempotent(String),
+}
+
+impl Default for TagAppendPolicy {
+    fn default() -> Self {
+        TagAppendPolicy::Concatenate
+    }
+}
+
+impl TagAppendPolicy {
+    /// Returns the value of a named tag which has the value `current` after `value` is
+    /// appended to it, or None if appending does not change the tag.
+    ///
+    /// # Arguments
+    /// * ` current ` - The existing value of the tag, if any.
+    /// * ` value ` - The value to append.
+    pub fn append(&self, current: Option<&str>, value: &str) -> Option<String> {
+        match self {
+            TagAppendPolicy::Concatenate => {
+                Some(format!("{}{}", current.unwrap_or_default(), value))
+            }
+            TagAppendPolicy::Idempotent(separator) => {
+                // Whitespace around separators is ignored, so that ", " and "," are equivalent.
+                let delimiter = match separator.trim() {
+                    "" => separator.as_str(),
+                    delimiter => delimiter,
+                };
+                let item = value.trim();
+                let item = item.strip_prefix(delimiter).unwrap_or(item).trim();
+                match current.filter(|current| !current.trim().is_empty()) {
+                    _ if item.is_empty() => None,
+                    None => Some(item.to_string()),
+                    Some(current) if current.split(delimiter).any(|x| x.trim() == item) => None,
+                    Some(current) => Some(format!("{}{}{}", current, separator, item)),
+                }
+            }
+        }
+    }
+}
+
+/// A row of the `books` table: the ID, title, series name, series index and date added.
+pub(crate) type BookRow = (
+    i64,
+    Option<String>,
+    Option<String>,
+    Option<f64>,
+    Option<String>,
+);
+
+/// A row of the `variants` table, in column order.
+pub(crate) type VariantRow = (
+    String,
+    Vec<u8>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<i64>,
+    Vec<u8>,
+    i64,
+    i64,
+    i64,
+);
+
+/// A copy of every row which stores some books, taken by `AppDatabase::snapshot_books`.
+/// Unlike `Book`, a snapshot keeps the cover and all multimap tags of each book, so that
+/// `AppDatabase::restore_snapshot` can write the books back exactly as they were.
+#[derive(Clone, Debug, Default)]
+pub struct BookSnapshot {
+    pub(crate) books: Vec<BookRow>,
+    pub(crate) variants: Vec<VariantRow>,
+    pub(crate) named_tags: Vec<(String, String, i64)>,
+    pub(crate) free_tags: Vec<(String, i64)>,
+    pub(crate) multimap_tags: Vec<(String, String, i64)>,
+    pub(crate) covers: Vec<(Vec<u8>, i64)>,
+}
+
+impl BookSnapshot {
+    /// Returns the IDs of the books in the snapshot.
+    pub fn ids(&self) -> Vec<BookID> {
+        self.books
+            .iter()
+            .filter_map(|book| BookID::new(book.0 as u64))
+            .collect()
+    }
+
+    /// Returns the number of books in the snapshot.
+    pub fn len(&self) -> usize {
+        self.books.len()
+    }
+
+    /// Returns true if the snapshot contains no books.
+    pub fn is_empty(&self) -> bool {
+        self.books.is_empty()
+    }
+}
+
+/// Determines which books are considered to be duplicates of each other.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum DetectionStrategy {
+    /// Books which have a variant with the same file size and hash.
+    VariantHash,
+    /// Books with the same title, ignoring case, which share at least one author.
+    AuthorOverlap,
+    /// Books with the same title, ignoring case.
+    Title,
+}
+
+/// Determines what happens when duplicate books have different values for the same column.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ConflictResolution {
+    /// Do not merge the books.
+    Skip,
+    /// Merge the books, keeping the values of the book with the lower ID.
+    KeepOriginal,
+    /// Do not merge the books, and return the conflict so that it can be resolved by the user.
+    Return,
+}
+
+/// Two duplicate books which were not merged, as they have different values for some columns.
+#[derive(Clone, Debug)]
+pub struct MergeConflict {
+    /// The book which would be kept.
+    pub book1: Arc<Book>,
+    /// The book which would be merged into `book1`.
+    pub book2: Arc<Book>,
+    /// The columns which both books have, with different values.
+    pub conflicts: Vec<ColumnIdentifier>,
+}
+
+/// Describes the full-text index after it has been rebuilt.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct FtsReport {
+    /// How long rebuilding the index took.
+    pub elapsed: Duration,
+    /// The number of books in the database.
+    pub books: usize,
+    /// The number of books in the full-text index.
+    pub indexed: usize,
+}
+
+impl FtsReport {
+    /// Returns whether every book, and nothing else, is in the full-text index.
+    pub fn is_consistent(&self) -> bool {
+        self.books == self.indexed
+    }
+}
+
+/// The named tag which records when a book was finished, as an ISO 8601 date.
+pub const FINISHED_TAG: &str = "finished";
+
+/// The number of books added and finished in a particular month.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MonthlyReading {
+    /// The month, as `YYYY-MM`.
+    pub month: String,
+    /// The number of books which were added to the database in the month.
+    pub added: usize,
+    /// The number of books whose `finished` tag falls in the month.
+    pub finished: usize,
+}
+
+/// Determines what happens to rows which belong to a book which does not exist, such as the
+/// variants and tags left behind by an interrupted deletion.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum RepairPolicy {
+    /// Delete the rows.
+    Delete,
+    /// Create an empty book for each missing book, so that the rows belong to a book again.
+    Recreate,
+}
+
+/// The rows which were found to belong to a book which does not exist.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RepairReport {
+    /// The number of orphaned variants.
+    pub variants: usize,
+    /// The number of orphaned named tags.
+    pub named_tags: usize,
+    /// The number of orphaned free tags.
+    pub free_tags: usize,
+    /// The number of orphaned multimap tags, such as authors.
+    pub multimap_tags: usize,
+    /// The number of orphaned covers.
+    pub covers: usize,
+    /// The books which were created for the orphaned rows, with `RepairPolicy::Recreate`.
+    pub recreated: Vec<BookID>,
+}
+
+impl RepairReport {
+    /// Returns the total number of orphaned rows.
+    pub fn rows(&self) -> usize {
+        self.variants + self.named_tags + self.free_tags + self.multimap_tags + self.covers
+    }
+}
+
+/// A summary of the library, as a whole.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct LibraryStats {
+    /// The number of books.
+    pub books: usize,
+    /// The number of variants, across all books.
+    pub variants: usize,
+    /// The total size of all variants, in bytes.
+    pub total_size: u64,
+    /// Each author, with the number of books they wrote, from the most to the least prolific.
+    pub authors: Vec<(String, usize)>,
+    /// Each series, with the number of books in it, from the longest to the shortest series.
+    pub series: Vec<(String, usize)>,
+    /// Each language, with the number of books which have a variant in it.
+    pub languages: Vec<(String, usize)>,
+    /// The number of books without a cover.
+    pub missing_cover: usize,
+    /// The number of books without a title.
+    pub missing_title: usize,
+    /// The number of books without any authors.
+    pub missing_authors: usize,
+    /// The number of books without a description in any variant.
+    pub missing_description: usize,
+}
+
 #[async_trait]
 pub trait AppDatabase {
     type Error: Send + Debug;
@@ -48,8 +315,26 @@ pub trait AppDatabase {
         P: AsRef<path::Path> + Send + Sync,
         Self: Sized;
 
+    /// Opens the existing database at the path, without allowing any writes to it.
+    ///
+    /// # Arguments
+    ///
+    /// * ` file_path ` - A path to a database.
+    ///
+    /// # Errors
+    /// This function will return an error if the file does not exist, or points to an invalid
+    /// database.
+    async fn open_read_only<P>(file_path: P) -> Result<Self, DatabaseError<Self::Error>>
+    where
+        P: AsRef<path::Path> + Send + Sync,
+        Self: Sized;
+
     fn path(&self) -> &path::Path;
 
+    /// Returns whether full-text search is available. If it is not, searches fall back to
+    /// slower queries which do not use the full-text index.
+    fn supports_full_text_search(&self) -> bool;
+
     /// Saves the database to its original location.
     ///
     /// # Errors
@@ -58,7 +343,9 @@ pub trait AppDatabase {
 
     /// Inserts the given book into the database, setting the ID automatically. The ID set
     /// will be returned, and calling other `AppDatabase` methods which take `BookID` with the
-    /// given ID will perform functions on, or return the same book.
+    /// given ID will perform functions on, or return the same book. If the insert policy skips
+    /// the book or attaches it to a book which has the same file, the ID of that book is
+    /// returned instead.
     ///
     /// # Arguments
     /// * ` book ` - A book to be stored.
@@ -70,7 +357,9 @@ pub trait AppDatabase {
         book: BookVariant,
     ) -> Result<BookID, DatabaseError<Self::Error>>;
 
-    /// Stores each book into the database, and returns a Vec of corresponding IDs.
+    /// Stores each book into the database, and returns the IDs of the books which were created.
+    /// Depending on the insert policy, variants which are already in the database may be
+    /// skipped or attached to an existing book - the IDs of those books are not returned.
     ///
     /// # Arguments
     /// * ` books ` - Some number of books to be stored.
@@ -82,6 +371,64 @@ pub trait AppDatabase {
         books: I,
     ) -> Result<Vec<BookID>, DatabaseError<Self::Error>>;
 
+    /// Inserts a copy of each book, including its variants, series and tags, as determined by
+    /// the insert policy, in the same way as `insert_books`. Books keep the date at which they
+    /// were added, and books with no variants are skipped. Returns the IDs of the new books, in
+    /// the same order as `books`.
+    ///
+    /// # Arguments
+    /// * ` books ` - The books to copy into the database.
+    ///
+    /// # Errors
+    /// This function will return an error if the database fails.
+    async fn import_books(
+        &mut self,
+        books: &[Arc<Book>],
+    ) -> Result<Vec<BookID>, DatabaseError<Self::Error>>;
+
+    /// Writes each book back to the database exactly as provided, under its ID. Books which no
+    /// longer exist are inserted again, while the title, series, variants, tags and authors of
+    /// existing books are replaced. All books are written in a single transaction. This is used
+    /// to undo edits, from copies of the books taken beforehand.
+    ///
+    /// # Arguments
+    /// * ` books ` - The books to restore, each of which must have an ID.
+    ///
+    /// # Errors
+    /// This function will return an error if the database fails.
+    async fn restore_books(
+        &mut self,
+        books: &[Arc<Book>],
+    ) -> Result<(), DatabaseError<Self::Error>>;
+
+    /// Copies every row which stores the books with the given IDs, including their covers and
+    /// tags, so that the books can later be written back by `restore_snapshot`. IDs of books
+    /// which do not exist are ignored.
+    ///
+    /// # Arguments
+    /// * ` ids ` - The IDs of the books to copy.
+    ///
+    /// # Errors
+    /// This function will return an error if the database fails.
+    async fn snapshot_books(
+        &self,
+        ids: &[BookID],
+    ) -> Result<BookSnapshot, DatabaseError<Self::Error>>;
+
+    /// Writes the books in the snapshot back to the database under their original IDs,
+    /// replacing any books which currently have those IDs. All books are written in a single
+    /// transaction. This is used to undo deletions.
+    ///
+    /// # Arguments
+    /// * ` snapshot ` - The books to restore, as returned by `snapshot_books`.
+    ///
+    /// # Errors
+    /// This function will return an error if the database fails.
+    async fn restore_snapshot(
+        &mut self,
+        snapshot: &BookSnapshot,
+    ) -> Result<(), DatabaseError<Self::Error>>;
+
     /// Removes all books with the given IDs. If a book with a given ID does not exist, or an ID
     /// is repeated, no changes will occur for that particular ID.
     ///
@@ -161,6 +508,8 @@ pub trait AppDatabase {
     ///
     /// # Arguments
     /// * ` selected ` - a Selection over target items..
+    /// * ` edits ` - A set of <field, value> pairs to set in each book.
+    /// * ` policy ` - Which variants of each book to edit, for columns stored per variant.
     ///
     /// # Errors
     /// This function will return an error if the database fails, or if any of the provided
@@ -169,6 +518,21 @@ pub trait AppDatabase {
         &mut self,
         selected: &Selection,
         edits: &[(ColumnIdentifier, Edit)],
+        policy: VariantEditPolicy,
+    ) -> Result<(), DatabaseError<Self::Error>>;
+
+    /// For each pair of (id, edits), applies the edits to the book with the given ID.
+    /// All edits are applied as a single batch - if any edit fails, no book is modified.
+    ///
+    /// # Arguments
+    /// * ` edits ` - The IDs of the books to edit, paired with the edits to apply to each.
+    ///
+    /// # Errors
+    /// This function will return an error if the database fails, if any book is not found,
+    /// or if any of the provided edits try to mutate an immutable column
+    async fn edit_many(
+        &mut self,
+        edits: &[(BookID, Box<[(ColumnIdentifier, Edit)]>)],
     ) -> Result<(), DatabaseError<Self::Error>>;
 
     /// Merges all books with matching titles and authors (case insensitive), in no
@@ -179,11 +543,237 @@ pub trait AppDatabase {
     /// This function will return an error if updating the database fails.
     async fn merge_similar(&mut self) -> Result<HashSet<BookID>, DatabaseError<Self::Error>>;
 
+    /// Returns groups of books which are duplicates of each other, as determined by `strategy`,
+    /// without modifying any books. Each group holds at least two books, in order of ID, and
+    /// groups are ordered by the ID of their first book.
+    ///
+    /// # Arguments
+    /// * ` strategy ` - How duplicate books are found.
+    ///
+    /// # Errors
+    /// This function will return an error if the database fails.
+    async fn find_duplicates(
+        &self,
+        strategy: DetectionStrategy,
+    ) -> Result<Vec<Vec<BookID>>, DatabaseError<Self::Error>>;
+
+    /// Removes all books which have no variants, and returns their IDs. Such books have no
+    /// files associated with them.
+    ///
+    /// # Errors
+    /// This function will return an error if the database fails.
+    async fn remove_empty_books(&mut self) -> Result<HashSet<BookID>, DatabaseError<Self::Error>>;
+
+    /// Writes every book in the database to `writer` in the given format, in order of ID, and
+    /// returns the number of books written. Books are read with `stream_books`, so that the
+    /// whole database is not held in memory.
+    ///
+    /// # Arguments
+    /// * ` format ` - The format to write books in.
+    /// * ` writer ` - The destination of the exported books.
+    ///
+    /// # Errors
+    /// This function will return an error if the database fails, or if writing fails.
+    async fn export<W: Write + Send>(
+        &self,
+        format: ExportFormat,
+        writer: W,
+    ) -> Result<usize, DatabaseError<Self::Error>>;
+
+    /// Returns every book in the database, in order of ID. Books are read in batches as the
+    /// stream is polled, and are not added to the cache, so that the whole database is never
+    /// held in memory.
+    ///
+    /// # Errors
+    /// Each item will be an error if reading the batch it belongs to fails.
+    fn stream_books(&self) -> BoxStream<'_, Result<Arc<Book>, DatabaseError<Self::Error>>>;
+
+    /// Updates the paths of stored variants to those of the provided variants with the same
+    /// file size and hash, so that moved files are found again. Variants which do not match a
+    /// stored variant are ignored.
+    ///
+    /// # Arguments
+    /// * ` books ` - The variants read from the files on disk.
+    ///
+    /// # Errors
+    /// This function will return an error if the database fails.
     async fn update<I: Iterator<Item = BookVariant> + Send>(
         &mut self,
         books: I,
     ) -> Result<(), DatabaseError<Self::Error>>;
 
+    /// Replaces the file size, hash, title, identifier, language and description of stored
+    /// variants with those of the provided variants, which were read again from their files.
+    /// Each variant is matched to the variant of the book with the given ID which has the same
+    /// path, and variants which do not match a stored variant are ignored.
+    ///
+    /// # Arguments
+    /// * ` variants ` - The variants read from the files on disk, with the IDs of their books.
+    ///
+    /// # Errors
+    /// This function will return an error if the database fails.
+    async fn refresh_variants(
+        &mut self,
+        variants: &[(BookID, BookVariant)],
+    ) -> Result<(), DatabaseError<Self::Error>>;
+
+    /// Replaces the path of each stored variant whose file was moved. Each move is a tuple of
+    /// the ID of the variant's book, the path the variant is stored with, and its new path -
+    /// moves which do not match a stored variant are ignored.
+    ///
+    /// # Arguments
+    /// * ` moves ` - The book IDs, old paths and new paths of the moved variants.
+    ///
+    /// # Errors
+    /// This function will return an error if the database fails.
+    async fn move_variants(
+        &mut self,
+        moves: &[(BookID, path::PathBuf, path::PathBuf)],
+    ) -> Result<(), DatabaseError<Self::Error>>;
+
+    /// Makes the variant at `index` the preferred variant of the book with the given ID, by
+    /// moving it to the front of the book's variants.
+    ///
+    /// # Arguments
+    /// * ` id ` - The ID of the book to modify.
+    /// * ` index ` - The index of the variant to prefer.
+    ///
+    /// # Errors
+    /// This function will return an error if the database fails, if the book is not found,
+    /// or if the book has no variant at `index`.
+    async fn set_preferred_variant(
+        &mut self,
+        id: BookID,
+        index: usize,
+    ) -> Result<(), DatabaseError<Self::Error>>;
+
+    /// Stores `image` as the cover of the book with the given ID, replacing any existing cover.
+    ///
+    /// # Arguments
+    /// * ` id ` - The ID of the book to modify.
+    /// * ` image ` - The encoded cover image.
+    ///
+    /// # Errors
+    /// This function will return an error if the database fails, or if the book is not found.
+    async fn set_cover(
+        &mut self,
+        id: BookID,
+        image: &[u8],
+    ) -> Result<(), DatabaseError<Self::Error>>;
+
+    /// Returns the encoded cover image of the book with the given ID, or None if it has no
+    /// cover.
+    ///
+    /// # Arguments
+    /// * ` id ` - The ID of the book.
+    ///
+    /// # Errors
+    /// This function will return an error if the database fails.
+    async fn get_cover(&self, id: BookID) -> Result<Option<Vec<u8>>, DatabaseError<Self::Error>>;
+
+    /// Rebuilds the set of known columns from the columns used by the books in the database.
+    ///
+    /// # Errors
+    /// This function will return an error if the database fails.
+    async fn refresh_columns(&self) -> Result<(), DatabaseError<Self::Error>>;
+
+    /// Returns each distinct author in the database, with the number of books they authored.
+    ///
+    /// # Errors
+    /// This function will return an error if the database fails.
+    async fn authors(&self) -> Result<Vec<(String, usize)>, DatabaseError<Self::Error>>;
+
+    /// For each pair of (from, to), replaces the author `from` with `to` in all books. If a
+    /// book already lists `to` as an author, `from` is removed from the book instead.
+    ///
+    /// # Arguments
+    /// * ` renames ` - The authors to rename, paired with their new names.
+    ///
+    /// # Errors
+    /// This function will return an error if the database fails.
+    async fn rename_authors(
+        &mut self,
+        renames: &[(String, String)],
+    ) -> Result<(), DatabaseError<Self::Error>>;
+
+    /// Removes all tags from the books with the given IDs, in a single transaction. This includes
+    /// free tags, named tags, and all multimap tags other than authors.
+    ///
+    /// # Arguments
+    /// * ` ids ` - The IDs of the books to remove tags from.
+    ///
+    /// # Errors
+    /// This function will return an error if the database fails.
+    async fn clear_tags(&mut self, ids: &[BookID]) -> Result<(), DatabaseError<Self::Error>>;
+
+    /// Renames the tag `old` to `new` in every book, in a single transaction - free tags with
+    /// the value `old`, and named tags and multimap tags with the name `old`. If a book already
+    /// has the tag `new`, the tag `old` is removed from the book instead. Authors are not
+    /// renamed. Returns the number of tags which were renamed.
+    ///
+    /// # Arguments
+    /// * ` old ` - The tag to rename.
+    /// * ` new ` - The new name of the tag.
+    ///
+    /// # Errors
+    /// This function will return an error if the database fails.
+    async fn rename_tag(
+        &mut self,
+        old: &str,
+        new: &str,
+    ) -> Result<usize, DatabaseError<Self::Error>>;
+
+    /// Removes the tag `name` from every book, in a single transaction - free tags with the
+    /// value `name`, and named tags and multimap tags with the name `name`. Authors are not
+    /// removed. Returns the number of tags which were removed.
+    ///
+    /// # Arguments
+    /// * ` name ` - The tag to remove.
+    ///
+    /// # Errors
+    /// This function will return an error if the database fails.
+    async fn delete_tag(&mut self, name: &str) -> Result<usize, DatabaseError<Self::Error>>;
+
+    /// Rebuilds the full-text index from the stored books, and then compares the number of
+    /// indexed books against the number of stored books. If full-text search is not supported,
+    /// nothing is rebuilt, and None is returned.
+    ///
+    /// # Errors
+    /// This function will return an error if the database fails.
+    async fn rebuild_fts(&mut self) -> Result<Option<FtsReport>, DatabaseError<Self::Error>>;
+
+    /// Returns each distinct value of `column`, with the number of books which have that value,
+    /// ordered from the most to the least common value. Books without a value for the column
+    /// are not counted. Columns which can not be grouped, such as IDs and descriptions, have
+    /// no values.
+    ///
+    /// # Arguments
+    /// * ` column ` - The column to group books by.
+    ///
+    /// # Errors
+    /// This function will return an error if the database fails.
+    async fn column_stats(
+        &self,
+        column: &ColumnIdentifier,
+    ) -> Result<Vec<(String, usize)>, DatabaseError<Self::Error>>;
+
+    /// Returns the number of books added and finished in each month, from the oldest to the
+    /// newest month, using the `date_added` and `finished` named tags. Tags which do not start
+    /// with a `YYYY-MM` date are ignored, and months in which no books were added or finished
+    /// are omitted.
+    ///
+    /// # Errors
+    /// This function will return an error if the database fails.
+    async fn reading_stats(&self) -> Result<Vec<MonthlyReading>, DatabaseError<Self::Error>>;
+
+    /// Returns a summary of the library - the number of books and variants, their total size,
+    /// the number of books by each author, in each series and in each language, and the number
+    /// of books which are missing a cover, title, authors or description.
+    ///
+    /// # Errors
+    /// This function will return an error if the database fails.
+    async fn library_stats(&self) -> Result<LibraryStats, DatabaseError<Self::Error>>;
+
     #[must_use]
     /// Returns whether the provided column exists in at least one book in the database.
     ///
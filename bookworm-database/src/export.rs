@@ -0,0 +1,296 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+
+use encoding_rs::Encoding;
+use itertools::Itertools;
+use serde_json::json;
+
+use bookworm_records::Book;
+
+/// Describes how the fields of a delimited file are separated, and how its text is encoded.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct DelimitedFormat {
+    pub delimiter: char,
+    pub encoding: &'static Encoding,
+}
+
+impl Default for DelimitedFormat {
+    fn default() -> Self {
+        DelimitedFormat {
+            delimiter: '\t',
+            encoding: encoding_rs::UTF_8,
+        }
+    }
+}
+
+impl DelimitedFormat {
+    /// Returns the format of CSV files - comma-separated, and encoded as UTF-8.
+    pub fn csv() -> Self {
+        DelimitedFormat {
+            delimiter: ',',
+            encoding: encoding_rs::UTF_8,
+        }
+    }
+}
+
+/// The formats which books can be exported to.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ExportFormat {
+    /// One row per book, with the columns in `CSV_HEADER`, written in the given format.
+    Csv(DelimitedFormat),
+    /// A JSON array, with one object per book.
+    Json,
+}
+
+impl ExportFormat {
+    /// Returns the format with the given name, ignoring case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "csv" => Some(ExportFormat::Csv(DelimitedFormat::csv())),
+            "json" => Some(ExportFormat::Json),
+            _ => None,
+        }
+    }
+
+    /// Returns the format named by the extension of `path`, if it has one.
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        Self::from_name(path.extension()?.to_str()?)
+    }
+}
+
+/// The columns of exported CSV files, in order.
+pub const CSV_HEADER: [&str; 7] = [
+    "id",
+    "title",
+    "authors",
+    "series",
+    "description",
+    "tags",
+    "paths",
+];
+
+/// The separator placed between the values of list columns, such as authors, in CSV files.
+const CSV_LIST_SEPARATOR: &str = "; ";
+
+/// Writes books to a writer one at a time, so that exports do not need to keep every book in
+/// memory. CSV rows contain the columns in `CSV_HEADER`, while JSON objects also contain the
+/// named tags of each book.
+pub struct BookWriter<W: Write> {
+    format: ExportFormat,
+    writer: W,
+    written: usize,
+}
+
+impl<W: Write> BookWriter<W> {
+    /// Creates a `BookWriter`, and writes the start of the export - the CSV header, or the start
+    /// of the JSON array.
+    ///
+    /// # Errors
+    /// If writing to `writer` fails, an error will be returned.
+    pub fn new(format: ExportFormat, mut writer: W) -> Result<Self, std::io::Error> {
+        match format {
+            ExportFormat::Csv(csv) => write_csv_row(&mut writer, &csv, CSV_HEADER.iter().copied())?,
+            ExportFormat::Json => writer.write_all(b"[")?,
+        }
+        Ok(BookWriter {
+            format,
+            writer,
+            written: 0,
+        })
+    }
+
+    /// Writes `book` to the export.
+    ///
+    /// # Errors
+    /// If writing to the underlying writer fails, an error will be returned.
+    pub fn write_book(&mut self, book: &Book) -> Result<(), std::io::Error> {
+        let mut tags: Vec<_> = book.free_tags().iter().collect();
+        tags.sort();
+        let paths: Vec<_> = book
+            .variants()
+            .iter()
+            .map(|variant| variant.path().display().to_string())
+            .collect();
+        let id = book.id.map(u64::from);
+
+        match self.format {
+            ExportFormat::Csv(csv) => {
+                let row = [
+                    id.map(|id| id.to_string()).unwrap_or_default(),
+                    book.title().unwrap_or_default().to_string(),
+                    book.authors().unwrap_or_default().join(CSV_LIST_SEPARATOR),
+                    book.series()
+                        .map(|series| series.to_string())
+                        .unwrap_or_default(),
+                    book.description().cloned().unwrap_or_default(),
+                    tags.iter().join(CSV_LIST_SEPARATOR),
+                    paths.join(CSV_LIST_SEPARATOR),
+                ];
+                write_csv_row(&mut self.writer, &csv, row.iter().map(String::as_str))?;
+            }
+            ExportFormat::Json => {
+                if self.written != 0 {
+                    self.writer.write_all(b",")?;
+                }
+                let named_tags: BTreeMap<_, _> = book.tags().iter().collect();
+                let object = json!({
+                    "id": id,
+                    "title": book.title(),
+                    "authors": book.authors().unwrap_or_default(),
+                    "series": book.series().map(|series| series.to_string()),
+                    "description": book.description(),
+                    "tags": tags,
+                    "named_tags": named_tags,
+                    "paths": paths,
+                });
+                serde_json::to_writer(&mut self.writer, &object)?;
+            }
+        }
+        self.written += 1;
+        Ok(())
+    }
+
+    /// Writes the end of the export, and returns the number of books which were written.
+    ///
+    /// # Errors
+    /// If writing to or flushing the underlying writer fails, an error will be returned.
+    pub fn finish(mut self) -> Result<usize, std::io::Error> {
+        if self.format == ExportFormat::Json {
+            self.writer.write_all(b"]\n")?;
+        }
+        self.writer.flush()?;
+        Ok(self.written)
+    }
+}
+
+/// Quotes `field` if it contains the delimiter, a quote or a line break, doubling any quotes
+/// inside it.
+pub fn csv_field(field: &str, delimiter: char) -> String {
+    if field.contains(&[delimiter, '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Writes `fields` as a line of a delimited file in the given format. Characters which the
+/// encoding can not represent are written as HTML numeric character references.
+///
+/// # Errors
+/// If writing to `writer` fails, an error will be returned.
+pub fn write_csv_row<'a, W: Write, I: Iterator<Item = &'a str>>(
+    writer: &mut W,
+    format: &DelimitedFormat,
+    fields: I,
+) -> Result<(), std::io::Error> {
+    let mut line = fields
+        .map(|field| csv_field(field, format.delimiter))
+        .join(&format.delimiter.to_string());
+    line.push('\n');
+    writer.write_all(&format.encoding.encode(&line).0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::convert::TryFrom;
+
+    use bookworm_records::book::BookID;
+    use bookworm_records::series::Series;
+
+    fn book() -> Book {
+        Book {
+            id: Some(BookID::try_from(3).unwrap()),
+            title: Some("Dune, Part \"One\"".to_string()),
+            authors: Some(vec![
+                "Frank Herbert".to_string(),
+                "Brian Herbert".to_string(),
+            ]),
+            series: Some(Series {
+                name: "Dune".to_string(),
+                index: Some(1.),
+            }),
+            free_tags: vec!["scifi".to_string(), "classic".to_string()]
+                .into_iter()
+                .collect(),
+            named_tags: vec![("publisher".to_string(), "Chilton".to_string())]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_from_extension() {
+        assert_eq!(
+            ExportFormat::from_extension(Path::new("books.CSV")),
+            Some(ExportFormat::Csv(DelimitedFormat::csv()))
+        );
+        assert_eq!(
+            ExportFormat::from_extension(Path::new("books.json")),
+            Some(ExportFormat::Json)
+        );
+        assert_eq!(ExportFormat::from_extension(Path::new("books")), None);
+    }
+
+    #[test]
+    fn test_write_csv() {
+        let mut out = vec![];
+        let mut writer =
+            BookWriter::new(ExportFormat::Csv(DelimitedFormat::csv()), &mut out).unwrap();
+        writer.write_book(&book()).unwrap();
+        assert_eq!(writer.finish().unwrap(), 1);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "id,title,authors,series,description,tags,paths\n\
+            3,\"Dune, Part \"\"One\"\"\",Frank Herbert; Brian Herbert,Dune [1],,classic; scifi,\n"
+        );
+    }
+
+    #[test]
+    fn test_write_delimited_csv() {
+        let format = DelimitedFormat {
+            delimiter: ';',
+            encoding: Encoding::for_label(b"latin1").unwrap(),
+        };
+        let mut book = book();
+        book.title = Some("Les Misérables".to_string());
+        book.free_tags.clear();
+        let mut out = vec![];
+        let mut writer = BookWriter::new(ExportFormat::Csv(format), &mut out).unwrap();
+        writer.write_book(&book).unwrap();
+        writer.finish().unwrap();
+        // Fields which contain the delimiter are quoted, while commas no longer need to be.
+        assert_eq!(
+            out,
+            b"id;title;authors;series;description;tags;paths\n\
+            3;Les Mis\xe9rables;\"Frank Herbert; Brian Herbert\";Dune [1];;;\n"
+                .to_vec()
+        );
+    }
+
+    #[test]
+    fn test_write_json() {
+        let mut out = vec![];
+        let mut writer = BookWriter::new(ExportFormat::Json, &mut out).unwrap();
+        writer.write_book(&book()).unwrap();
+        writer.write_book(&Book::placeholder()).unwrap();
+        assert_eq!(writer.finish().unwrap(), 2);
+
+        let exported: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        let exported = exported.as_array().unwrap();
+        assert_eq!(exported.len(), 2);
+        assert_eq!(exported[0]["id"], 3);
+        assert_eq!(exported[0]["title"], "Dune, Part \"One\"");
+        assert_eq!(
+            exported[0]["authors"],
+            json!(["Frank Herbert", "Brian Herbert"])
+        );
+        assert_eq!(exported[0]["tags"], json!(["classic", "scifi"]));
+        assert_eq!(exported[0]["named_tags"]["publisher"], "Chilton");
+        assert_eq!(exported[1]["authors"], json!([]));
+        assert!(exported[1]["title"].is_null());
+    }
+}
@@ -0,0 +1,172 @@
+use std::borrow::Cow;
+use std::ffi::CString;
+use std::os::raw::{c_int, c_void};
+
+use libsqlite3_sys as ffi;
+use regex::Regex;
+use sqlx::sqlite::SqliteConnection;
+
+use bookworm_records::author;
+
+use crate::search::Normalization;
+
+/// Returns the text of `value`, or None if `value` is NULL.
+///
+/// # Safety
+/// `value` must be a valid argument of the function which is currently being called.
+unsafe fn value_text<'a>(value: *mut ffi::sqlite3_value) -> Option<Cow<'a, str>> {
+    let text = ffi::sqlite3_value_text(value);
+    if text.is_null() {
+        return None;
+    }
+    let len = ffi::sqlite3_value_bytes(value) as usize;
+    Some(String::from_utf8_lossy(std::slice::from_raw_parts(
+        text, len,
+    )))
+}
+
+unsafe extern "C" fn drop_regex(regex: *mut c_void) {
+    drop(Box::from_raw(regex as *mut Regex));
+}
+
+/// Implements `value REGEXP pattern`, which SQLite calls as `regexp(pattern, value)`.
+/// Compiled patterns are cached by SQLite for the duration of each statement. As with
+/// `RegexMatcher::is_match`, NULL values are matched as if they were empty.
+unsafe extern "C" fn regexp(
+    ctx: *mut ffi::sqlite3_context,
+    argc: c_int,
+    argv: *mut *mut ffi::sqlite3_value,
+) {
+    let args = std::slice::from_raw_parts(argv, argc as usize);
+    let value = value_text(args[1]).unwrap_or_default();
+
+    let cached = ffi::sqlite3_get_auxdata(ctx, 0) as *const Regex;
+    let is_match = if cached.is_null() {
+        let pattern = match value_text(args[0]) {
+            Some(pattern) => pattern,
+            None => return ffi::sqlite3_result_null(ctx),
+        };
+        let regex = match Regex::new(&pattern) {
+            Ok(regex) => regex,
+            Err(e) => {
+                let message = e.to_string();
+                return ffi::sqlite3_result_error(
+                    ctx,
+                    message.as_ptr() as *const _,
+                    message.len() as c_int,
+                );
+            }
+        };
+        let is_match = regex.is_match(&value);
+        // SQLite takes ownership of the regex, and drops it once the statement is done with it.
+        ffi::sqlite3_set_auxdata(
+            ctx,
+            0,
+            Box::into_raw(Box::new(regex)) as *mut c_void,
+            Some(drop_regex),
+        );
+        is_match
+    } else {
+        (*cached).is_match(&value)
+    };
+    ffi::sqlite3_result_int(ctx, is_match as c_int);
+}
+
+/// Returns the text of the single argument in `argv`, transformed with `map`.
+///
+/// # Safety
+/// `ctx` and `argv` must be the arguments of the function which is currently being called.
+unsafe fn map_text<F>(ctx: *mut ffi::sqlite3_context, argv: *mut *mut ffi::sqlite3_value, map: F)
+where
+    F: for<'a> FnOnce(&'a str) -> Cow<'a, str>,
+{
+    let text = match value_text(*argv) {
+        Some(text) => text,
+        None => return ffi::sqlite3_result_null(ctx),
+    };
+    let text = map(&text);
+    // SQLITE_TRANSIENT makes SQLite copy the text before it is dropped.
+    ffi::sqlite3_result_text(
+        ctx,
+        text.as_ptr() as *const _,
+        text.len() as c_int,
+        ffi::SQLITE_TRANSIENT(),
+    );
+}
+
+/// Implements `casefold(value)`, which normalizes text like `Normalization::CaseInsensitive`.
+unsafe extern "C" fn casefold(
+    ctx: *mut ffi::sqlite3_context,
+    _argc: c_int,
+    argv: *mut *mut ffi::sqlite3_value,
+) {
+    map_text(ctx, argv, |text| Normalization::CaseInsensitive.apply(text))
+}
+
+/// Implements `unaccent(value)`, which normalizes text like `Normalization::Unaccented`.
+unsafe extern "C" fn unaccent(
+    ctx: *mut ffi::sqlite3_context,
+    _argc: c_int,
+    argv: *mut *mut ffi::sqlite3_value,
+) {
+    map_text(ctx, argv, |text| Normalization::Unaccented.apply(text))
+}
+
+/// Implements `author_key(value)`, which returns the name of an author with the surname first,
+/// so that authors are sorted by surname like `Book::cmp_column`.
+unsafe extern "C" fn author_key(
+    ctx: *mut ffi::sqlite3_context,
+    _argc: c_int,
+    argv: *mut *mut ffi::sqlite3_value,
+) {
+    map_text(ctx, argv, |text| Cow::Owned(author::surname_first(text)))
+}
+
+type ScalarFunction =
+    unsafe extern "C" fn(*mut ffi::sqlite3_context, c_int, *mut *mut ffi::sqlite3_value);
+
+/// Registers the deterministic scalar function `function` on `connection` under `name`.
+fn create_function(
+    connection: &mut SqliteConnection,
+    name: &str,
+    args: c_int,
+    function: ScalarFunction,
+) -> Result<(), sqlx::Error> {
+    let c_name = CString::new(name).expect("Function name should not contain NUL bytes.");
+    let result = unsafe {
+        ffi::sqlite3_create_function_v2(
+            connection.as_raw_handle(),
+            c_name.as_ptr(),
+            args,
+            ffi::SQLITE_UTF8 | ffi::SQLITE_DETERMINISTIC,
+            std::ptr::null_mut(),
+            Some(function),
+            None,
+            None,
+            None,
+        )
+    };
+    if result == ffi::SQLITE_OK {
+        Ok(())
+    } else {
+        Err(sqlx::Error::Protocol(format!(
+            "Failed to register the {} function (error code {})",
+            name, result
+        )))
+    }
+}
+
+/// Registers the functions used by matchers on `connection`:
+/// * `regexp`, which allows `REGEXP` to be used in queries. Patterns use the syntax of the
+///   `regex` crate, so that they match the same books as `RegexMatcher::is_match`.
+/// * `casefold` and `unaccent`, which normalize text like `Normalization::apply`.
+/// * `author_key`, which is used to sort authors by surname.
+///
+/// # Errors
+/// Will return an error if SQLite fails to register a function.
+pub(crate) fn register_functions(connection: &mut SqliteConnection) -> Result<(), sqlx::Error> {
+    create_function(connection, "regexp", 2, regexp)?;
+    create_function(connection, "casefold", 1, casefold)?;
+    create_function(connection, "unaccent", 1, unaccent)?;
+    create_function(connection, "author_key", 1, author_key)
+}
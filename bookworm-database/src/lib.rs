@@ -5,14 +5,27 @@
 
 pub use bookview::BookView;
 pub use bookworm_records::book::Book;
-pub use database::{AppDatabase, DatabaseError};
+pub use database::{
+    AppDatabase, BookSnapshot, ConflictResolution, DatabaseError, DetectionStrategy, FtsReport,
+    InsertPolicy, LibraryStats, MergeConflict, MonthlyReading, RepairPolicy, RepairReport,
+    TagAppendPolicy, VariantEditPolicy,
+};
+pub use export::{DelimitedFormat, ExportFormat};
 #[cfg(feature = "sqlite")]
-pub use sqlite_database::SQLiteDatabase;
+pub use sqlite_database::{FieldLimits, SQLiteDatabase, DEFAULT_INSERT_BATCH_SIZE};
 
 pub mod bookview;
 mod cache;
+#[cfg(feature = "sqlite")]
+mod calibre;
 pub mod database;
+pub mod export;
+#[cfg(feature = "sqlite")]
+mod functions;
+pub mod matchers;
 pub mod paginator;
 pub mod search;
 #[cfg(feature = "sqlite")]
 pub mod sqlite_database;
+#[cfg(all(feature = "sqlite", any(test, feature = "test-util")))]
+pub mod test_util;
@@ -0,0 +1,76 @@
+use bookworm_records::book::ColumnIdentifier;
+
+use crate::search::{
+    Error, ExactStringMatcher, ExactSubstringMatcher, Matcher, Normalization, RegexMatcher,
+};
+
+/// Builds matchers over a single column.
+#[derive(Debug, Clone)]
+pub struct ColumnMatchers {
+    column: ColumnIdentifier,
+    normalization: Normalization,
+}
+
+/// Returns a builder for matchers over `column`, which is either a `ColumnIdentifier` or the
+/// name of a column.
+pub fn column<C: Into<ColumnIdentifier>>(column: C) -> ColumnMatchers {
+    ColumnMatchers {
+        column: column.into(),
+        normalization: Normalization::Exact,
+    }
+}
+
+impl ColumnMatchers {
+    /// Normalizes text with `normalization` before comparing it. By default, text is compared
+    /// exactly.
+    pub fn normalized(mut self, normalization: Normalization) -> Self {
+        self.normalization = normalization;
+        self
+    }
+
+    /// Matches books where the column contains `text`. Case is significant, unless the text
+    /// is normalized.
+    ///
+    /// # Errors
+    /// Will return an error if `text` is too long to be matched.
+    pub fn contains<S: Into<String>>(
+        self,
+        text: S,
+    ) -> Result<Box<dyn Matcher + Send + Sync>, Error> {
+        Ok(Box::new(ExactSubstringMatcher::with_normalization(
+            self.column,
+            text.into(),
+            self.normalization,
+        )?))
+    }
+
+    /// Matches books where the column is exactly `text`. If `text` is empty, books without
+    /// a value in the column are also matched.
+    ///
+    /// # Errors
+    /// This function does not currently return errors.
+    pub fn equals<S: Into<String>>(self, text: S) -> Result<Box<dyn Matcher + Send + Sync>, Error> {
+        Ok(Box::new(ExactStringMatcher::with_normalization(
+            self.column,
+            text.into(),
+            self.normalization,
+        )?))
+    }
+
+    /// Matches books where the column matches the regular expression `regex`, using the syntax
+    /// of the `regex` crate. Case is significant, unless the pattern starts with `(?i)` or the
+    /// text is normalized.
+    ///
+    /// # Errors
+    /// Will return an error if `regex` is not a valid regular expression.
+    pub fn matches_regex<S: Into<String>>(
+        self,
+        regex: S,
+    ) -> Result<Box<dyn Matcher + Send + Sync>, Error> {
+        Ok(Box::new(RegexMatcher::with_normalization(
+            self.column,
+            regex.into(),
+            self.normalization,
+        )?))
+    }
+}
@@ -4,8 +4,8 @@ use std::sync::Arc;
 
 use tokio::sync::RwLock;
 
-use bookworm_records::book::{BookID, ColumnIdentifier};
-use bookworm_records::{Book, ColumnOrder};
+use bookworm_records::book::{BookID, ColumnIdentifier, READ_STATUS_TAG};
+use bookworm_records::{Book, Collation, ColumnOrder};
 
 use crate::search::Matcher;
 use crate::{AppDatabase, DatabaseError};
@@ -38,6 +38,7 @@ pub enum Selection {
     Partial(
         HashMap<BookID, Arc<Book>>,
         Box<[(ColumnIdentifier, ColumnOrder)]>,
+        Collation,
     ),
     Range(
         Arc<Book>,
@@ -45,6 +46,7 @@ pub enum Selection {
         Box<[(ColumnIdentifier, ColumnOrder)]>,
         Direction,
         Box<[Box<dyn Matcher + Send + Sync>]>,
+        Collation,
     ),
     Empty,
 }
@@ -53,8 +55,8 @@ impl Selection {
     pub fn is_empty(&self) -> bool {
         match self {
             Selection::All(_) => false,
-            Selection::Partial(books, _) => books.is_empty(),
-            Selection::Range(_, _, _, _, _) => false,
+            Selection::Partial(books, _, _) => books.is_empty(),
+            Selection::Range(_, _, _, _, _, _) => false,
             Selection::Empty => true,
         }
     }
@@ -62,11 +64,11 @@ impl Selection {
     fn contains(&self, book: &Book) -> bool {
         match self {
             Selection::All(matcher) => matcher.iter().all(|x| x.is_match(book)),
-            Selection::Partial(books, _) => books.contains_key(&book.id()),
-            Selection::Range(start, stop, cols, _, match_rules) => {
+            Selection::Partial(books, _, _) => books.contains_key(&book.id()),
+            Selection::Range(start, stop, cols, _, match_rules, collation) => {
                 match_rules.iter().all(|m| m.is_match(book))
-                    && start.cmp_columns(book, cols).is_le()
-                    && stop.cmp_columns(book, cols).is_ge()
+                    && start.cmp_columns(book, cols, *collation).is_le()
+                    && stop.cmp_columns(book, cols, *collation).is_ge()
             }
             Selection::Empty => false,
         }
@@ -79,11 +81,11 @@ impl Selection {
     pub fn front(&self) -> Option<&Arc<Book>> {
         match self {
             Selection::All(_) => None,
-            Selection::Partial(books, sorting_rules) => books
+            Selection::Partial(books, sorting_rules, collation) => books
                 .values()
-                .min_by(|a, b| a.cmp_columns(b, sorting_rules)),
-            Selection::Range(start, _, _, Direction::Up, _) => Some(start),
-            Selection::Range(_, end, _, Direction::Down, _) => Some(end),
+                .min_by(|a, b| a.cmp_columns(b, sorting_rules, *collation)),
+            Selection::Range(start, _, _, Direction::Up, _, _) => Some(start),
+            Selection::Range(_, end, _, Direction::Down, _, _) => Some(end),
             Selection::Empty => None,
         }
     }
@@ -91,10 +93,10 @@ impl Selection {
     pub fn first(&self) -> Option<&Arc<Book>> {
         match self {
             Selection::All(_) => None,
-            Selection::Partial(books, sorting_rules) => books
+            Selection::Partial(books, sorting_rules, collation) => books
                 .values()
-                .min_by(|a, b| a.cmp_columns(b, sorting_rules)),
-            Selection::Range(start, _, _, _, _) => Some(start),
+                .min_by(|a, b| a.cmp_columns(b, sorting_rules, *collation)),
+            Selection::Range(start, _, _, _, _, _) => Some(start),
             Selection::Empty => None,
         }
     }
@@ -102,10 +104,10 @@ impl Selection {
     fn last(&self) -> Option<&Arc<Book>> {
         match self {
             Selection::All(_) => None,
-            Selection::Partial(books, sorting_rules) => books
+            Selection::Partial(books, sorting_rules, collation) => books
                 .values()
-                .max_by(|a, b| a.cmp_columns(b, sorting_rules)),
-            Selection::Range(_, end, _, _, _) => Some(end),
+                .max_by(|a, b| a.cmp_columns(b, sorting_rules, *collation)),
+            Selection::Range(_, end, _, _, _, _) => Some(end),
             Selection::Empty => None,
         }
     }
@@ -113,8 +115,8 @@ impl Selection {
     fn is_single(&self) -> bool {
         match self {
             Selection::All(_) => false,
-            Selection::Partial(books, _) => books.len() == 1,
-            Selection::Range(start, end, _, _, _) => start.id() == end.id(),
+            Selection::Partial(books, _, _) => books.len() == 1,
+            Selection::Range(start, end, _, _, _, _) => start.id() == end.id(),
             Selection::Empty => false,
         }
     }
@@ -124,13 +126,16 @@ impl Clone for Selection {
     fn clone(&self) -> Self {
         match self {
             Selection::All(matches) => Selection::All(clone_match_box(matches)),
-            Selection::Partial(books, sort) => Selection::Partial(books.clone(), sort.clone()),
-            Selection::Range(start, end, sort, dir, matches) => Selection::Range(
+            Selection::Partial(books, sort, collation) => {
+                Selection::Partial(books.clone(), sort.clone(), *collation)
+            }
+            Selection::Range(start, end, sort, dir, matches, collation) => Selection::Range(
                 start.clone(),
                 end.clone(),
                 sort.clone(),
                 dir.clone(),
                 clone_match_box(matches),
+                *collation,
             ),
             Selection::Empty => Selection::Empty,
         }
@@ -180,17 +185,19 @@ impl SqlFrom {
 }
 
 struct RowCmp {
-    lhs: Vec<(String, Option<Variable>)>,
-    rhs: Vec<(String, Option<Variable>)>,
+    lhs: Vec<(String, Vec<Variable>)>,
+    rhs: Vec<(String, Vec<Variable>)>,
     cmp: String,
+    collation: Collation,
 }
 
 impl RowCmp {
-    fn new(cmp: String) -> Self {
+    fn new(cmp: String, collation: Collation) -> Self {
         Self {
             lhs: vec![],
             rhs: vec![],
             cmp: cmp,
+            collation,
         }
     }
     fn cmp_column(
@@ -201,26 +208,79 @@ impl RowCmp {
         table_alias: &str,
         col_alias: &str,
     ) {
-        let cmp_key = if matches!(column, ColumnIdentifier::ID) {
-            Some(Variable::Int(u64::from(book.id()) as i64))
-        } else {
-            if let Some(cmp_key) = book.get_column(column) {
-                Some(Variable::Str(cmp_key.to_string()))
-            } else {
-                None
+        let book_id = u64::from(book.id()) as i64;
+        // Multimap tags are sorted by their smallest value, which is read from the database,
+        // as books do not keep the values of each tag in a sortable form. Authors are sorted
+        // by surname, as in `read_column`.
+        let multimap = match column {
+            ColumnIdentifier::Author => Some(("author", "author_key(value)")),
+            ColumnIdentifier::MultiMap(name) | ColumnIdentifier::MultiMapExact(name, _) => {
+                Some((name.as_str(), "value"))
             }
+            _ => None,
         };
+        if let Some((name, value)) = multimap {
+            self.push_cmp(
+                cmp,
+                table_alias,
+                &sort_keys(column, col_alias)[0],
+                format!(
+                    "(SELECT MIN({}) FROM multimap_tags WHERE name = ? AND book_id = ?)",
+                    value
+                ),
+                vec![Variable::Str(name.to_string()), Variable::Int(book_id)],
+            );
+            return;
+        }
 
-        if let Some(cmp_key) = cmp_key {
-            if cmp == ">" {
-                self.lhs
-                    .push((format!("{}.{}", table_alias, col_alias), None));
-                self.rhs.push(("?".to_string(), Some(cmp_key)));
-            } else {
-                self.lhs.push(("?".to_string(), Some(cmp_key)));
-                self.rhs
-                    .push((format!("{}.{}", table_alias, col_alias), None));
+        let cmp_keys = match column {
+            ColumnIdentifier::ID => vec![Variable::Int(book_id)],
+            // Series are compared by name, and then by index.
+            ColumnIdentifier::Series => match book.series() {
+                Some(series) => std::iter::once(Variable::Str(series.name.clone()))
+                    .chain(series.index.map(|index| Variable::Float(index as f64)))
+                    .collect(),
+                None => vec![],
+            },
+            // Missing dates are read as empty strings by `read_column`.
+            ColumnIdentifier::DateAdded => {
+                vec![Variable::Str(book.date_added.clone().unwrap_or_default())]
             }
+            column => book
+                .get_column(column)
+                .map(|cmp_key| Variable::Str(cmp_key.to_string()))
+                .into_iter()
+                .collect(),
+        };
+
+        for (cmp_key, col_alias) in cmp_keys.into_iter().zip(sort_keys(column, col_alias)) {
+            self.push_cmp(cmp, table_alias, &col_alias, "?".to_string(), vec![cmp_key]);
+        }
+    }
+
+    /// Compares the column `col_alias` of `table_alias` to `cmp_key`, which is an SQL
+    /// expression using the variables in `bound`.
+    fn push_cmp(
+        &mut self,
+        cmp: &str,
+        table_alias: &str,
+        col_alias: &str,
+        cmp_key: String,
+        bound: Vec<Variable>,
+    ) {
+        // The collation must match the one used to sort, or pages will skip or repeat books.
+        let column = format!(
+            "{}.{} COLLATE {}",
+            table_alias,
+            col_alias,
+            sql_collation(self.collation)
+        );
+        if cmp == ">" {
+            self.lhs.push((column, vec![]));
+            self.rhs.push((cmp_key, bound));
+        } else {
+            self.lhs.push((cmp_key, bound));
+            self.rhs.push((column, vec![]));
         }
     }
 
@@ -230,20 +290,16 @@ impl RowCmp {
         }
 
         let mut lhs = String::new();
-        for (s, key) in self.lhs {
+        for (s, keys) in self.lhs {
             lhs += &s;
             lhs += ", ";
-            if let Some(val) = key {
-                bind_vars.push(val);
-            }
+            bind_vars.extend(keys);
         }
         let mut rhs = String::new();
-        for (s, key) in self.rhs {
+        for (s, keys) in self.rhs {
             rhs += &s;
             rhs += ", ";
-            if let Some(val) = key {
-                bind_vars.push(val);
-            }
+            bind_vars.extend(keys);
         }
 
         let lhs = lhs.strip_suffix(", ")?;
@@ -263,12 +319,33 @@ impl Display for SqlFrom {
     }
 }
 
+/// Returns the names of the columns selected by `read_column` for `column`, in the order
+/// in which they are sorted.
+fn sort_keys(column: &ColumnIdentifier, col_alias: &str) -> Vec<String> {
+    match column {
+        ColumnIdentifier::Series => vec![col_alias.to_string(), format!("{}_index", col_alias)],
+        ColumnIdentifier::Author => vec![format!("{}_key", col_alias)],
+        _ => vec![col_alias.to_string()],
+    }
+}
+
+/// Returns the name of the SQLite collation which orders text in the same way as `collation`.
+fn sql_collation(collation: Collation) -> &'static str {
+    match collation {
+        Collation::CaseInsensitive => "NOCASE",
+        Collation::CaseSensitive => "BINARY",
+    }
+}
+
 pub struct QueryBuilder {
     order: ColumnOrder,
     cmp_rules: Vec<(ColumnIdentifier, ColumnOrder)>,
     sort: bool,
     id_inclusive: bool,
     limit: Option<i64>,
+    offset: Option<i64>,
+    collation: Collation,
+    full_text_search: bool,
 }
 
 impl Default for QueryBuilder {
@@ -279,6 +356,9 @@ impl Default for QueryBuilder {
             sort: false,
             id_inclusive: false,
             limit: None,
+            offset: None,
+            collation: Collation::default(),
+            full_text_search: true,
         }
     }
 }
@@ -307,6 +387,13 @@ impl QueryBuilder {
         self
     }
 
+    /// Skips the first `offset` books which would otherwise be returned. Only applies to
+    /// queries with a limit.
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = Some(offset as i64);
+        self
+    }
+
     pub fn order(mut self, order: ColumnOrder) -> Self {
         self.order = order;
         self
@@ -317,14 +404,31 @@ impl QueryBuilder {
         self
     }
 
+    pub fn collation(mut self, collation: Collation) -> Self {
+        self.collation = collation;
+        self
+    }
+
+    /// Sets whether the database has a full-text index. If not, matchers which use the index
+    /// use their fallback queries instead.
+    pub fn full_text_search(mut self, full_text_search: bool) -> Self {
+        self.full_text_search = full_text_search;
+        self
+    }
+
     fn add_match_rules(
+        &self,
         from: &mut SqlFrom,
         where_str: &mut String,
         bind_vars: &mut Vec<Variable>,
         match_rules: &Box<[Box<dyn Matcher + Send + Sync>]>,
     ) {
         for match_rule in match_rules.iter() {
-            let (col_id, query_str, var) = match_rule.sql_query();
+            let (col_id, query_str, var) = if self.full_text_search {
+                match_rule.sql_query()
+            } else {
+                match_rule.fallback_sql_query()
+            };
             match from.select_column(col_id) {
                 None => {}
                 Some((table_alias, col_alias, bound)) => {
@@ -334,7 +438,11 @@ impl QueryBuilder {
                         where_str.push_str(" AND ");
                     }
 
-                    where_str.push_str(&format!(" {}.{} {}", table_alias, col_alias, query_str));
+                    let column = match match_rule.normalization().sql_function() {
+                        None => format!("{}.{}", table_alias, col_alias),
+                        Some(function) => format!("{}({}.{})", function, table_alias, col_alias),
+                    };
+                    where_str.push_str(&format!(" {} {}", column, query_str));
                     bind_vars.extend(var.into_iter());
                 }
             }
@@ -379,7 +487,10 @@ impl QueryBuilder {
         // WHERE (books.book_id > 291 AND mvalue >= "Alastair")
         // ORDER BY mvalue ASC, ..., books.book_id ASC
         // LIMIT 5;
-        let mut row_cmp = RowCmp::new(if self.id_inclusive { ">=" } else { ">" }.to_string());
+        let mut row_cmp = RowCmp::new(
+            if self.id_inclusive { ">=" } else { ">" }.to_string(),
+            self.collation,
+        );
         // Add the id comparison to ensure pagination doesn't repeat items
 
         for (col_id, col_ord) in self.cmp_rules.iter() {
@@ -393,18 +504,21 @@ impl QueryBuilder {
                     }
 
                     if self.sort {
-                        order_str.push_str(&format!(
-                            "{} {}, ",
-                            col_alias,
-                            order_repr(col_ord.clone(), self.order)
-                        ));
+                        for sort_key in sort_keys(col_id, &col_alias) {
+                            order_str.push_str(&format!(
+                                "{} COLLATE {} {}, ",
+                                sort_key,
+                                sql_collation(self.collation),
+                                order_repr(col_ord.clone(), self.order)
+                            ));
+                        }
                     }
                 }
             }
         }
 
         let mut where_str = row_cmp.to_where(&mut bind_vars).unwrap_or_default();
-        Self::add_match_rules(&mut from, &mut where_str, &mut bind_vars, match_rules);
+        self.add_match_rules(&mut from, &mut where_str, &mut bind_vars, match_rules);
         // Clean up string.
         if !where_str.is_empty() {
             where_str = format!("WHERE ({})", where_str);
@@ -416,11 +530,14 @@ impl QueryBuilder {
         }
         let mut query = format!("SELECT ATABLE.book_id {} {} {}", from, where_str, order_str);
         if let Some(limit) = self.limit {
-            query += " LIMIT ?;";
+            query += " LIMIT ?";
             bind_vars.push(Variable::Int(limit));
-        } else {
-            query += ";";
+            if let Some(offset) = self.offset {
+                query += " OFFSET ?";
+                bind_vars.push(Variable::Int(offset));
+            }
         }
+        query += ";";
 
         (query, bind_vars)
     }
@@ -436,8 +553,14 @@ impl QueryBuilder {
         let mut order_str = String::new();
         let mut bind_vars = vec![];
 
-        let mut row_cmp_start = RowCmp::new(if self.id_inclusive { ">=" } else { ">" }.to_string());
-        let mut row_cmp_end = RowCmp::new(if self.id_inclusive { ">=" } else { ">" }.to_string());
+        let mut row_cmp_start = RowCmp::new(
+            if self.id_inclusive { ">=" } else { ">" }.to_string(),
+            self.collation,
+        );
+        let mut row_cmp_end = RowCmp::new(
+            if self.id_inclusive { ">=" } else { ">" }.to_string(),
+            self.collation,
+        );
 
         // Add the id comparison to ensure pagination doesn't repeat items
 
@@ -456,11 +579,14 @@ impl QueryBuilder {
                     row_cmp_end.cmp_column(end_cmp, end, col_id, &table_alias, &col_alias);
 
                     if self.sort {
-                        order_str.push_str(&format!(
-                            "{} {}, ",
-                            col_alias,
-                            order_repr(col_ord.clone(), self.order)
-                        ));
+                        for sort_key in sort_keys(col_id, &col_alias) {
+                            order_str.push_str(&format!(
+                                "{} COLLATE {} {}, ",
+                                sort_key,
+                                sql_collation(self.collation),
+                                order_repr(col_ord.clone(), self.order)
+                            ));
+                        }
                     }
                 }
             }
@@ -476,7 +602,7 @@ impl QueryBuilder {
             _ => String::new(),
         };
 
-        Self::add_match_rules(&mut from, &mut where_str, &mut bind_vars, match_rules);
+        self.add_match_rules(&mut from, &mut where_str, &mut bind_vars, match_rules);
 
         if !where_str.is_empty() {
             where_str = format!("WHERE ({})", where_str);
@@ -576,12 +702,19 @@ pub struct Paginator<D: AppDatabase + 'static> {
     // Store selected values (no relative indices).
     // When up/down etc is called, find first selected value based on ordering scheme & scroll from there.
     selected: Selection,
+    // Whether to speculatively read further books when loading, to warm the database cache.
+    prefetch: bool,
+    // How text columns are compared when sorting.
+    collation: Collation,
+    // Whether searches may use the full-text index.
+    full_text_search: bool,
     db: Arc<RwLock<D>>,
 }
 
 #[derive(Debug)]
 pub enum Variable {
     Int(i64),
+    Float(f64),
     Str(String),
 }
 
@@ -595,19 +728,39 @@ fn read_column(column: &ColumnIdentifier, id: &str) -> Option<(String, Option<St
             format!("(SELECT book_id, book_id as {} from books)", id),
             None,
         )),
-        ColumnIdentifier::Series => None,
+        ColumnIdentifier::Series => Some((
+            format!(
+                "(SELECT book_id, series_name as {}, series_id as {}_index from books)",
+                id, id
+            ),
+            None,
+        )),
+        // Authors are matched by name, but sorted by surname, however their names are stored.
         ColumnIdentifier::Author => Some((
             format!(
                 r#"(
-    SELECT book_id, MIN(value) as {}
+    SELECT book_id, MIN(value) as {}, MIN(author_key(value)) as {}_key
     FROM multimap_tags
     WHERE name="author"
     GROUP BY book_id
 )"#,
-                id
+                id, id
             ),
             None,
         )),
+        // Like authors, books are sorted by the smallest value of the tag.
+        ColumnIdentifier::MultiMap(name) | ColumnIdentifier::MultiMapExact(name, _) => Some((
+            format!(
+                r#"(
+    SELECT book_id, MIN(value) as {}
+    FROM multimap_tags
+    WHERE name=?
+    GROUP BY book_id
+)"#,
+                id
+            ),
+            Some(name.clone()),
+        )),
         ColumnIdentifier::NamedTag(tag_name) => Some((
             format!(
                 r#"(
@@ -620,9 +773,45 @@ fn read_column(column: &ColumnIdentifier, id: &str) -> Option<(String, Option<St
             ),
             Some(tag_name.clone()),
         )), // named_tags / name, "value"
-        ColumnIdentifier::Description => None, // variants / description
-        ColumnIdentifier::MultiMap(_) => None, // unimplemented
-        ColumnIdentifier::MultiMapExact(_, _) => None, // unimplemented
+        ColumnIdentifier::ReadStatus => {
+            read_column(&ColumnIdentifier::NamedTag(READ_STATUS_TAG.to_string()), id)
+        }
+        // Books take their description from the first variant which has one.
+        ColumnIdentifier::Description => Some((
+            format!(
+                r#"(
+    SELECT book_id, (
+        SELECT description
+        FROM variants
+        WHERE variants.book_id = books.book_id AND description IS NOT NULL
+        ORDER BY position
+        LIMIT 1
+    ) as {}
+    FROM books
+)"#,
+                id
+            ),
+            None,
+        )),
+        ColumnIdentifier::Cover => Some((
+            format!(
+                r#"(
+    SELECT book_id, EXISTS (SELECT 1 FROM covers WHERE covers.book_id = books.book_id) as {}
+    FROM books
+)"#,
+                id
+            ),
+            None,
+        )),
+        // Books added before dates were recorded have an empty date, so that they sort last
+        // when sorting by newest first.
+        ColumnIdentifier::DateAdded => Some((
+            format!(
+                "(SELECT book_id, COALESCE(date_added, '') as {} from books)",
+                id
+            ),
+            None,
+        )),
         ColumnIdentifier::Variants => None,    // unsortable
         ColumnIdentifier::Tags => None,        // unsortable
         ColumnIdentifier::ExactTag(_) => None, // unsortable
@@ -711,10 +900,49 @@ impl<D: AppDatabase + Send + Sync> Paginator<D> {
             sorting_rules: add_id(sorting_rules),
             matching_rules: vec![].into_boxed_slice(),
             selected: Selection::Empty,
+            prefetch: true,
+            collation: Collation::default(),
+            full_text_search: true,
             db,
         }
     }
 
+    /// Sets whether loading books also prefetches the books which follow them. If disabled,
+    /// only the books needed to fill the window are read, and further books are only read
+    /// when scrolled to.
+    pub fn set_prefetch(&mut self, prefetch: bool) {
+        self.prefetch = prefetch;
+    }
+
+    pub fn prefetch(&self) -> bool {
+        self.prefetch
+    }
+
+    /// Sets how text columns are compared when sorting. The window is not reloaded until the
+    /// next sort or refresh.
+    pub fn set_collation(&mut self, collation: Collation) {
+        self.collation = collation;
+    }
+
+    pub fn collation(&self) -> Collation {
+        self.collation
+    }
+
+    /// Sets whether the database has a full-text index, which searches use if it is available.
+    pub fn set_full_text_search(&mut self, full_text_search: bool) {
+        self.full_text_search = full_text_search;
+    }
+
+    pub fn full_text_search(&self) -> bool {
+        self.full_text_search
+    }
+
+    fn query_builder(&self) -> QueryBuilder {
+        QueryBuilder::default()
+            .collation(self.collation)
+            .full_text_search(self.full_text_search)
+    }
+
     pub fn selected(&self) -> &Selection {
         &self.selected
     }
@@ -739,6 +967,52 @@ impl<D: AppDatabase + Send + Sync> Paginator<D> {
         self.make_book_visible(target).await
     }
 
+    /// Reads every book which matches the paginator's matching rules, in sorted order.
+    ///
+    /// # Errors
+    /// This function will return an error if the database fails.
+    pub async fn read_all(&self) -> Result<Vec<Arc<Book>>, DatabaseError<D::Error>> {
+        let (query, bindings) = self
+            .query_builder()
+            .cmp_rules(&self.sorting_rules)
+            .sort(true)
+            .order(ColumnOrder::Descending)
+            .join_cols(None, &self.matching_rules);
+        self.db
+            .read()
+            .await
+            .read_selected_books(&query, &bindings)
+            .await
+    }
+
+    /// Reads at most `len` books which match the paginator's matching rules, in sorted order,
+    /// skipping the first `offset` books. This does not change the loaded books or the window.
+    ///
+    /// # Errors
+    /// This function will return an error if the database fails.
+    pub async fn read_page(
+        &self,
+        offset: usize,
+        len: usize,
+    ) -> Result<Vec<Arc<Book>>, DatabaseError<D::Error>> {
+        if len == 0 {
+            return Ok(vec![]);
+        }
+        let (query, bindings) = self
+            .query_builder()
+            .cmp_rules(&self.sorting_rules)
+            .sort(true)
+            .order(ColumnOrder::Descending)
+            .limit(len)
+            .offset(offset)
+            .join_cols(None, &self.matching_rules);
+        self.db
+            .read()
+            .await
+            .read_selected_books(&query, &bindings)
+            .await
+    }
+
     pub fn window(&self) -> &[Arc<Book>] {
         &self
             .books
@@ -753,7 +1027,8 @@ impl<D: AppDatabase + Send + Sync> Paginator<D> {
         if num_books == 0 {
             return Ok(());
         }
-        let query_builder = QueryBuilder::default()
+        let query_builder = self
+            .query_builder()
             .cmp_rules(&self.sorting_rules)
             .sort(true)
             .order(ColumnOrder::Descending)
@@ -768,18 +1043,19 @@ impl<D: AppDatabase + Send + Sync> Paginator<D> {
             .read_selected_books(&query, &bindings)
             .await?;
 
-        let (query, bindings) = query_builder
-            .limit(num_books * 5)
-            .join_cols(self.books.last().map(|x| x.as_ref()), &self.matching_rules);
-
-        let db = self.db.clone();
-        tokio::spawn(async move {
-            let _ = db
-                .write()
-                .await
-                .read_selected_books(&query, &bindings)
-                .await;
-        });
+        if self.prefetch {
+            let (query, bindings) = query_builder
+                .limit(num_books * 5)
+                .join_cols(self.books.last().map(|x| x.as_ref()), &self.matching_rules);
+            let db = self.db.clone();
+            tokio::spawn(async move {
+                let _ = db
+                    .write()
+                    .await
+                    .read_selected_books(&query, &bindings)
+                    .await;
+            });
+        }
 
         self.books.extend(books);
         Ok(())
@@ -794,7 +1070,8 @@ impl<D: AppDatabase + Send + Sync> Paginator<D> {
             return Ok(());
         }
 
-        let query_builder = QueryBuilder::default()
+        let query_builder = self
+            .query_builder()
             .cmp_rules(&self.sorting_rules)
             .sort(true)
             .order(ColumnOrder::Ascending)
@@ -803,9 +1080,6 @@ impl<D: AppDatabase + Send + Sync> Paginator<D> {
         let (query, bindings) =
             query_builder.join_cols(self.books.first().map(|x| x.as_ref()), &self.matching_rules);
         // Read only the number of items needed to fill top.
-        // TODO: If len is a large jump, we should do an OFFSET instead of loading
-        //  everything in-between (check if len > some multiple of window size),
-        //  and drop all ensuing books.
         let mut books = self
             .db
             .write()
@@ -813,18 +1087,19 @@ impl<D: AppDatabase + Send + Sync> Paginator<D> {
             .read_selected_books(&query, &bindings)
             .await?;
 
-        let (query, bindings) = query_builder
-            .limit(num_books * 5)
-            .join_cols(self.books.last().map(|x| x.as_ref()), &self.matching_rules);
-
-        let db = self.db.clone();
-        tokio::spawn(async move {
-            let _ = db
-                .write()
-                .await
-                .read_selected_books(&query, &bindings)
-                .await;
-        });
+        if self.prefetch {
+            let (query, bindings) = query_builder
+                .limit(num_books * 5)
+                .join_cols(self.books.last().map(|x| x.as_ref()), &self.matching_rules);
+            let db = self.db.clone();
+            tokio::spawn(async move {
+                let _ = db
+                    .write()
+                    .await
+                    .read_selected_books(&query, &bindings)
+                    .await;
+            });
+        }
 
         if !books.is_empty() {
             books.reverse();
@@ -834,13 +1109,62 @@ impl<D: AppDatabase + Send + Sync> Paginator<D> {
         Ok(())
     }
 
+    /// Replaces the loaded books with the window of books which starts `offset` books after
+    /// the last loaded book, without reading the books in between. If the jump passes the last
+    /// book, the window is filled with the books before it.
+    async fn jump_after_end(&mut self, offset: usize) -> Result<(), DatabaseError<D::Error>> {
+        let (query, bindings) = self
+            .query_builder()
+            .cmp_rules(&self.sorting_rules)
+            .sort(true)
+            .order(ColumnOrder::Descending)
+            .limit(self.window_size)
+            .offset(offset)
+            .join_cols(self.books.last().map(|x| x.as_ref()), &self.matching_rules);
+        self.books = self
+            .db
+            .write()
+            .await
+            .read_selected_books(&query, &bindings)
+            .await?;
+        self.window_top = 0;
+        self.load_books_before_start(self.window_size.saturating_sub(self.books.len()))
+            .await
+    }
+
+    /// Replaces the loaded books with the window of books which ends `offset` books before
+    /// the first loaded book, without reading the books in between. If the jump passes the
+    /// first book, the window is filled with the books after it, so that it starts at the
+    /// first book.
+    async fn jump_before_start(&mut self, offset: usize) -> Result<(), DatabaseError<D::Error>> {
+        let (query, bindings) = self
+            .query_builder()
+            .cmp_rules(&self.sorting_rules)
+            .sort(true)
+            .order(ColumnOrder::Ascending)
+            .limit(self.window_size)
+            .offset(offset)
+            .join_cols(self.books.first().map(|x| x.as_ref()), &self.matching_rules);
+        self.books = self
+            .db
+            .write()
+            .await
+            .read_selected_books(&query, &bindings)
+            .await?;
+        self.books.reverse();
+        self.window_top = 0;
+        self.load_books_after_end(self.window_size.saturating_sub(self.books.len()))
+            .await
+    }
+
     #[tracing::instrument(name = "Making book visible", skip(self, book))]
     pub async fn make_book_visible<B: AsRef<Book>>(
         &mut self,
         book: Option<B>,
     ) -> Result<(), DatabaseError<D::Error>> {
         tracing::info!("Making book visible");
-        let builder = QueryBuilder::default()
+        let builder = self
+            .query_builder()
             .cmp_rules(&self.sorting_rules)
             .sort(true)
             .order(ColumnOrder::Descending)
@@ -896,15 +1220,83 @@ impl<D: AppDatabase + Send + Sync> Paginator<D> {
             self.sorting_rules.clone(),
             Direction::Down,
             clone_match_box(&self.matching_rules),
+            self.collation,
         );
 
         Ok(())
     }
 
+    /// Selects the first book after (or before, if `direction` is `Direction::Up`) the current
+    /// selection which satisfies both the internal matching rules and `match_rules`, and makes
+    /// it visible. If no such book exists past the selection, the search wraps around.
+    /// Returns whether a matching book was found.
+    ///
+    /// # Arguments
+    /// * ` match_rules ` - Additional rules which the target book must match.
+    /// * ` direction ` - The direction to search in.
+    ///
+    /// # Errors
+    /// This function will return an error if reading from the database fails.
+    pub async fn select_next_match(
+        &mut self,
+        match_rules: &[Box<dyn Matcher + Send + Sync>],
+        direction: Direction,
+    ) -> Result<bool, DatabaseError<D::Error>> {
+        let matchers: Box<[_]> = self
+            .matching_rules
+            .iter()
+            .chain(match_rules.iter())
+            .map(|m| m.box_clone())
+            .collect();
+
+        let (anchor, order) = match direction {
+            Direction::Down => (self.selected.last().cloned(), ColumnOrder::Descending),
+            Direction::Up => (self.selected.first().cloned(), ColumnOrder::Ascending),
+        };
+
+        let query_builder = self
+            .query_builder()
+            .cmp_rules(&self.sorting_rules)
+            .sort(true)
+            .order(order)
+            .limit(1);
+
+        let mut target = {
+            let (query, bindings) =
+                query_builder.join_cols(anchor.as_ref().map(|x| x.as_ref()), &matchers);
+            self.db
+                .write()
+                .await
+                .read_selected_books(&query, &bindings)
+                .await?
+                .pop()
+        };
+
+        if target.is_none() && anchor.is_some() {
+            let (query, bindings) = query_builder.join_cols(None, &matchers);
+            target = self
+                .db
+                .write()
+                .await
+                .read_selected_books(&query, &bindings)
+                .await?
+                .pop();
+        }
+
+        match target {
+            None => Ok(false),
+            Some(target) => {
+                self.select_and_make_visible(target).await?;
+                Ok(true)
+            }
+        }
+    }
+
     async fn scroll_up_move_select(&mut self, len: usize) -> Result<(), DatabaseError<D::Error>> {
         if let Some(target) = self.selected.first().cloned() {
             return if self.selected.is_single() {
-                let (query, bindings) = QueryBuilder::default()
+                let (query, bindings) = self
+                    .query_builder()
                     .cmp_rules(&self.sorting_rules)
                     .sort(true)
                     .order(ColumnOrder::Ascending)
@@ -928,6 +1320,7 @@ impl<D: AppDatabase + Send + Sync> Paginator<D> {
                     self.sorting_rules.clone(),
                     Direction::Down,
                     clone_match_box(&self.matching_rules),
+                    self.collation,
                 );
                 Ok(())
             } else {
@@ -945,7 +1338,8 @@ impl<D: AppDatabase + Send + Sync> Paginator<D> {
     async fn scroll_down_move_select(&mut self, len: usize) -> Result<(), DatabaseError<D::Error>> {
         if let Some(target) = self.selected.last().cloned() {
             return if self.selected.is_single() {
-                let (query, bindings) = QueryBuilder::default()
+                let (query, bindings) = self
+                    .query_builder()
                     .cmp_rules(&self.sorting_rules)
                     .sort(true)
                     .order(ColumnOrder::Descending)
@@ -969,6 +1363,7 @@ impl<D: AppDatabase + Send + Sync> Paginator<D> {
                     self.sorting_rules.clone(),
                     Direction::Down,
                     clone_match_box(&self.matching_rules),
+                    self.collation,
                 );
                 Ok(())
             } else {
@@ -990,10 +1385,13 @@ impl<D: AppDatabase + Send + Sync> Paginator<D> {
                 Ok(())
             }
             Some(limit) => {
-                // TODO If len is a large jump, we should do an OFFSET instead of loading
-                //  everything in-between (check if len > some multiple of window size),
-                //  and drop all current books.
-                self.load_books_after_end(limit).await?;
+                // If the new window does not overlap the loaded books, the books in between
+                // are skipped, and only the new window is kept, so that paging through the
+                // library does not read or hold every book it passes.
+                match (self.window_top + len).checked_sub(self.books.len()) {
+                    Some(skipped) if !self.books.is_empty() => self.jump_after_end(skipped).await?,
+                    _ => self.load_books_after_end(limit).await?,
+                }
                 match self.books.len().checked_sub(self.window_size) {
                     None => {
                         self.load_books_before_start(self.window_size - self.books.len())
@@ -1010,7 +1408,14 @@ impl<D: AppDatabase + Send + Sync> Paginator<D> {
     pub async fn scroll_up(&mut self, len: usize) -> Result<(), DatabaseError<D::Error>> {
         match self.window_top.checked_sub(len) {
             None => {
-                self.load_books_before_start(len - self.window_top).await?;
+                // As when scrolling down, windows which do not overlap the loaded books are
+                // read on their own.
+                match len.checked_sub(self.window_top + self.window_size) {
+                    Some(skipped) if !self.books.is_empty() => {
+                        self.jump_before_start(skipped).await?
+                    }
+                    _ => self.load_books_before_start(len - self.window_top).await?,
+                }
                 self.window_top = 0;
             }
             Some(window_top) => {
@@ -1047,6 +1452,7 @@ impl<D: AppDatabase + Send + Sync> Paginator<D> {
                             self.sorting_rules.clone(),
                             Direction::Down,
                             clone_match_box(&self.matching_rules),
+                            self.collation,
                         );
                     }
                 }
@@ -1060,7 +1466,8 @@ impl<D: AppDatabase + Send + Sync> Paginator<D> {
         match (self.selected.last().cloned(), self.selected.is_single()) {
             (None, _) | (Some(_), true) => {
                 self.window_top = 0;
-                let (query, bindings) = QueryBuilder::default()
+                let (query, bindings) = self
+                    .query_builder()
                     .cmp_rules(&self.sorting_rules)
                     .sort(true)
                     .order(ColumnOrder::Ascending)
@@ -1083,6 +1490,7 @@ impl<D: AppDatabase + Send + Sync> Paginator<D> {
                             self.sorting_rules.clone(),
                             Direction::Down,
                             clone_match_box(&self.matching_rules),
+                            self.collation,
                         );
                     }
                 }
@@ -1101,31 +1509,64 @@ impl<D: AppDatabase + Send + Sync> Paginator<D> {
         self.scroll_down(0).await
     }
 
+    /// Reads the loaded books and the selection from the database again. The window is moved
+    /// so that the selected book keeps its row, at its new position in sort order, since
+    /// editing the book may have changed where it is sorted.
     #[tracing::instrument(name = "Refreshing paginator", skip(self))]
     pub async fn refresh(&mut self) -> Result<(), DatabaseError<D::Error>> {
-        // Find first selected which is visible, otherwise first item in window
-        let target = self
-            .window()
+        // The selected book which the cursor is on if it is visible, otherwise the first
+        // selected book which is visible, otherwise the first book in the window.
+        let window = self.window();
+        let front = self.selected.front().map(|book| book.id());
+        let (row, target) = window
             .iter()
-            .find(|x| self.selected.contains(&x))
-            .or_else(|| self.window().first())
-            .cloned();
+            .position(|book| Some(book.id()) == front)
+            .or_else(|| window.iter().position(|book| self.selected.contains(book)))
+            .or_else(|| window.first().map(|_| 0))
+            .map_or((0, None), |row| (row, Some(window[row].id())));
+
+        self.refresh_selection().await?;
+        let target = match target {
+            Some(id) => self.db.read().await.get_book(id).await.ok(),
+            None => None,
+        };
+
         self.books.clear();
         tracing::info!("Cleared internal books");
-        self.make_book_visible(target).await?;
+        self.make_book_visible(target.as_ref()).await?;
+        if let Some(target) = target {
+            if let Some(pos) = self.books.iter().position(|book| book.id() == target.id()) {
+                self.load_books_before_start(row.saturating_sub(pos))
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Replaces the selected books with their current values. If a range of books was
+    /// selected, and its start is now sorted after its end, the start and end are swapped and
+    /// the direction is reversed, so that the cursor stays on the same book.
+    async fn refresh_selection(&mut self) -> Result<(), DatabaseError<D::Error>> {
         match &mut self.selected {
             Selection::All(_) => {}
-            Selection::Partial(books, _) => {
+            Selection::Partial(books, _, _) => {
                 let ids: Vec<_> = books.keys().cloned().collect();
                 *books = self.db.read().await.get_books(&ids).await?;
             }
-            Selection::Range(start, end, _, _, _) => {
+            Selection::Range(start, end, cols, direction, _, collation) => {
                 if let Ok(new_start) = self.db.read().await.get_book(start.id()).await {
                     *start = new_start;
                 }
                 if let Ok(new_end) = self.db.read().await.get_book(end.id()).await {
                     *end = new_end;
                 }
+                if start.cmp_columns(end, cols, *collation).is_gt() {
+                    std::mem::swap(start, end);
+                    *direction = match direction {
+                        Direction::Up => Direction::Down,
+                        Direction::Down => Direction::Up,
+                    };
+                }
             }
             Selection::Empty => {}
         }
@@ -1182,10 +1623,13 @@ impl<D: AppDatabase + Send + Sync> Paginator<D> {
                 self.scroll_up(len).await?;
                 Ok(Selection::All(rules))
             }
-            Selection::Partial(books, rules) => Ok(Selection::Partial(books, rules)),
-            Selection::Range(start, end, sorting_rules, Direction::Down, matches) => {
+            Selection::Partial(books, rules, collation) => {
+                Ok(Selection::Partial(books, rules, collation))
+            }
+            Selection::Range(start, end, sorting_rules, Direction::Down, matches, collation) => {
                 // Need to get end + len'th book.
-                let (query, bound_variables) = QueryBuilder::default()
+                let (query, bound_variables) = self
+                    .query_builder()
                     .order(ColumnOrder::Ascending)
                     .sort(true)
                     .cmp_rules(&self.sorting_rules)
@@ -1204,13 +1648,14 @@ impl<D: AppDatabase + Send + Sync> Paginator<D> {
                         self.scroll_up(len).await?;
                     }
 
-                    if book.cmp_columns(&start, &sorting_rules).is_lt() {
+                    if book.cmp_columns(&start, &sorting_rules, collation).is_lt() {
                         Ok(Selection::Range(
                             book,
                             start,
                             sorting_rules,
                             Direction::Up,
                             matches,
+                            collation,
                         ))
                     } else {
                         Ok(Selection::Range(
@@ -1219,6 +1664,7 @@ impl<D: AppDatabase + Send + Sync> Paginator<D> {
                             sorting_rules,
                             Direction::Down,
                             matches,
+                            collation,
                         ))
                     }
                 } else {
@@ -1228,12 +1674,14 @@ impl<D: AppDatabase + Send + Sync> Paginator<D> {
                         sorting_rules,
                         Direction::Down,
                         matches,
+                        collation,
                     ))
                 }
             }
-            Selection::Range(start, end, sorting_rules, Direction::Up, matches) => {
+            Selection::Range(start, end, sorting_rules, Direction::Up, matches, collation) => {
                 // Need to get end + len'th book.
-                let (query, bound_variables) = QueryBuilder::default()
+                let (query, bound_variables) = self
+                    .query_builder()
                     .order(ColumnOrder::Ascending)
                     .sort(true)
                     .cmp_rules(&self.sorting_rules)
@@ -1257,6 +1705,7 @@ impl<D: AppDatabase + Send + Sync> Paginator<D> {
                         sorting_rules,
                         Direction::Up,
                         matches,
+                        collation,
                     ))
                 } else {
                     Ok(Selection::Range(
@@ -1265,11 +1714,13 @@ impl<D: AppDatabase + Send + Sync> Paginator<D> {
                         sorting_rules,
                         Direction::Up,
                         matches,
+                        collation,
                     ))
                 }
             }
             Selection::Empty => {
-                let (query, bound_variables) = QueryBuilder::default()
+                let (query, bound_variables) = self
+                    .query_builder()
                     .order(ColumnOrder::Ascending)
                     .sort(true)
                     .cmp_rules(&self.sorting_rules)
@@ -1301,6 +1752,7 @@ impl<D: AppDatabase + Send + Sync> Paginator<D> {
                         self.sorting_rules.clone(),
                         Direction::Down,
                         clone_match_box(&self.matching_rules),
+                        self.collation,
                     ))
                 } else {
                     Ok(Selection::Empty)
@@ -1331,10 +1783,13 @@ impl<D: AppDatabase + Send + Sync> Paginator<D> {
                 self.scroll_down(len).await?;
                 Ok(Selection::All(rules))
             }
-            Selection::Partial(books, rules) => Ok(Selection::Partial(books, rules)),
-            Selection::Range(start, end, sorting_rules, Direction::Down, matches) => {
+            Selection::Partial(books, rules, collation) => {
+                Ok(Selection::Partial(books, rules, collation))
+            }
+            Selection::Range(start, end, sorting_rules, Direction::Down, matches, collation) => {
                 // Need to get end + len'th book.
-                let (query, bound_variables) = QueryBuilder::default()
+                let (query, bound_variables) = self
+                    .query_builder()
                     .order(ColumnOrder::Descending)
                     .sort(true)
                     .cmp_rules(&self.sorting_rules)
@@ -1358,6 +1813,7 @@ impl<D: AppDatabase + Send + Sync> Paginator<D> {
                         sorting_rules,
                         Direction::Down,
                         matches,
+                        collation,
                     ))
                 } else {
                     Ok(Selection::Range(
@@ -1366,12 +1822,14 @@ impl<D: AppDatabase + Send + Sync> Paginator<D> {
                         sorting_rules,
                         Direction::Down,
                         matches,
+                        collation,
                     ))
                 }
             }
-            Selection::Range(start, end, sorting_rules, Direction::Up, matches) => {
+            Selection::Range(start, end, sorting_rules, Direction::Up, matches, collation) => {
                 // Need to get end + len'th book.
-                let (query, bound_variables) = QueryBuilder::default()
+                let (query, bound_variables) = self
+                    .query_builder()
                     .order(ColumnOrder::Descending)
                     .sort(true)
                     .cmp_rules(&self.sorting_rules)
@@ -1390,13 +1848,14 @@ impl<D: AppDatabase + Send + Sync> Paginator<D> {
                         self.scroll_down(len).await?;
                     }
 
-                    if book.cmp_columns(&end, &sorting_rules).is_gt() {
+                    if book.cmp_columns(&end, &sorting_rules, collation).is_gt() {
                         Ok(Selection::Range(
                             end,
                             book,
                             sorting_rules,
                             Direction::Down,
                             matches,
+                            collation,
                         ))
                     } else {
                         Ok(Selection::Range(
@@ -1405,6 +1864,7 @@ impl<D: AppDatabase + Send + Sync> Paginator<D> {
                             sorting_rules,
                             Direction::Up,
                             matches,
+                            collation,
                         ))
                     }
                 } else {
@@ -1414,11 +1874,13 @@ impl<D: AppDatabase + Send + Sync> Paginator<D> {
                         sorting_rules,
                         Direction::Up,
                         matches,
+                        collation,
                     ))
                 }
             }
             Selection::Empty => {
-                let (query, bound_variables) = QueryBuilder::default()
+                let (query, bound_variables) = self
+                    .query_builder()
                     .order(ColumnOrder::Descending)
                     .sort(true)
                     .cmp_rules(&self.sorting_rules)
@@ -1450,6 +1912,7 @@ impl<D: AppDatabase + Send + Sync> Paginator<D> {
                         self.sorting_rules.clone(),
                         Direction::Down,
                         clone_match_box(&self.matching_rules),
+                        self.collation,
                     ))
                 } else {
                     Ok(Selection::Empty)
@@ -1489,6 +1952,40 @@ impl<D: AppDatabase + Send + Sync> Paginator<D> {
         self.select_down(self.window_size).await
     }
 
+    /// Selects the first book, in sort order, if no books are selected. The window is not
+    /// moved, so that this can be used before the window size is known.
+    pub async fn select_first(&mut self) -> PaginatorResult<D::Error> {
+        if !matches!(self.selected, Selection::Empty) {
+            return Ok(());
+        }
+
+        let (query, bindings) = self
+            .query_builder()
+            .cmp_rules(&self.sorting_rules)
+            .sort(true)
+            .order(ColumnOrder::Descending)
+            .limit(1)
+            .join_cols(None, &self.matching_rules);
+        let book = self
+            .db
+            .write()
+            .await
+            .read_selected_books(&query, &bindings)
+            .await?
+            .pop();
+        if let Some(book) = book {
+            self.selected = Selection::Range(
+                book.clone(),
+                book,
+                self.sorting_rules.clone(),
+                Direction::Down,
+                clone_match_box(&self.matching_rules),
+                self.collation,
+            );
+        }
+        Ok(())
+    }
+
     pub async fn select_to_start(&mut self) -> PaginatorResult<D::Error> {
         match self.selected.last().cloned() {
             None => Ok(()),
@@ -1501,6 +1998,7 @@ impl<D: AppDatabase + Send + Sync> Paginator<D> {
                     self.sorting_rules.clone(),
                     Direction::Up,
                     clone_match_box(&self.matching_rules),
+                    self.collation,
                 );
                 Ok(())
             }
@@ -1522,9 +2020,397 @@ impl<D: AppDatabase + Send + Sync> Paginator<D> {
                     self.sorting_rules.clone(),
                     Direction::Down,
                     clone_match_box(&self.matching_rules),
+                    self.collation,
                 );
                 Ok(())
             }
         }
     }
 }
+
+#[cfg(all(test, feature = "sqlite"))]
+mod test {
+    use super::*;
+
+    use bookworm_input::Edit;
+    use bookworm_records::test_util::variant;
+    use bookworm_records::BookVariant;
+
+    use crate::test_util::RecordingDatabase;
+    use crate::SQLiteDatabase;
+
+    #[tokio::test]
+    async fn test_load_more_only_reads_visible_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        let titles: Vec<_> = (0..20).map(|i| format!("book {:02}", i)).collect();
+        db.insert_books(
+            titles
+                .iter()
+                .enumerate()
+                .map(|(i, title)| variant(title, i as u8)),
+        )
+        .await
+        .unwrap();
+
+        let db = Arc::new(RwLock::new(RecordingDatabase::new(db)));
+        let mut paginator = Paginator::new(db.clone(), 0, vec![].into_boxed_slice());
+        paginator.set_prefetch(false);
+
+        paginator.update_window_size(5).await.unwrap();
+        paginator.scroll_down(2).await.unwrap();
+        tokio::task::yield_now().await;
+
+        let window: Vec<_> = paginator
+            .window()
+            .iter()
+            .map(|book| book.title().unwrap().to_string())
+            .collect();
+        assert_eq!(window, titles[2..7].to_vec());
+        assert_eq!(db.read().await.limits(), vec![Some(5), Some(2)]);
+    }
+
+    #[tokio::test]
+    async fn test_large_scrolls_skip_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        let titles: Vec<_> = (0..40).map(|i| format!("book {:02}", i)).collect();
+        db.insert_books(
+            titles
+                .iter()
+                .enumerate()
+                .map(|(i, title)| variant(title, i as u8)),
+        )
+        .await
+        .unwrap();
+
+        let db = Arc::new(RwLock::new(RecordingDatabase::new(db)));
+        let mut paginator = Paginator::new(db.clone(), 0, vec![].into_boxed_slice());
+        paginator.set_prefetch(false);
+        paginator.update_window_size(2).await.unwrap();
+
+        let window = |paginator: &Paginator<RecordingDatabase>| -> Vec<String> {
+            paginator
+                .window()
+                .iter()
+                .map(|book| book.title().unwrap().to_string())
+                .collect()
+        };
+        paginator.scroll_down(20).await.unwrap();
+        assert_eq!(window(&paginator), titles[20..22].to_vec());
+        paginator.scroll_up(15).await.unwrap();
+        assert_eq!(window(&paginator), titles[5..7].to_vec());
+        paginator.scroll_down(100).await.unwrap();
+        assert_eq!(window(&paginator), titles[38..40].to_vec());
+
+        // Only a window of books is read for each jump.
+        assert_eq!(db.read().await.limits(), vec![Some(2); 5]);
+    }
+
+    #[tokio::test]
+    async fn test_large_scrolls_stop_at_ends() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        let titles: Vec<_> = (0..40).map(|i| format!("book {:02}", i)).collect();
+        db.insert_books(
+            titles
+                .iter()
+                .enumerate()
+                .map(|(i, title)| variant(title, i as u8)),
+        )
+        .await
+        .unwrap();
+
+        let db = Arc::new(RwLock::new(RecordingDatabase::new(db)));
+        let mut paginator = Paginator::new(db.clone(), 0, vec![].into_boxed_slice());
+        paginator.set_prefetch(false);
+        paginator.update_window_size(2).await.unwrap();
+
+        let window = |paginator: &Paginator<RecordingDatabase>| -> Vec<String> {
+            paginator
+                .window()
+                .iter()
+                .map(|book| book.title().unwrap().to_string())
+                .collect()
+        };
+        paginator.scroll_down(100).await.unwrap();
+        assert_eq!(window(&paginator), titles[38..40].to_vec());
+        paginator.scroll_up(100).await.unwrap();
+        assert_eq!(window(&paginator), titles[0..2].to_vec());
+        // Jumps which pass the first book by less than a window are filled from the first book.
+        paginator.scroll_down(10).await.unwrap();
+        paginator.scroll_up(11).await.unwrap();
+        assert_eq!(window(&paginator), titles[0..2].to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_page_scrolls_keep_one_window() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        let titles: Vec<_> = (0..10).map(|i| format!("book {:02}", i)).collect();
+        db.insert_books(
+            titles
+                .iter()
+                .enumerate()
+                .map(|(i, title)| variant(title, i as u8)),
+        )
+        .await
+        .unwrap();
+
+        let db = Arc::new(RwLock::new(RecordingDatabase::new(db)));
+        let mut paginator = Paginator::new(db.clone(), 0, vec![].into_boxed_slice());
+        paginator.set_prefetch(false);
+        paginator.update_window_size(2).await.unwrap();
+
+        // Paging down and back up reads one window at a time, and only keeps that window.
+        for start in [2, 4, 6] {
+            paginator.scroll_down(2).await.unwrap();
+            assert_eq!(paginator.window()[0].title(), Some(titles[start].as_str()));
+            assert_eq!(paginator.books.len(), 2);
+        }
+        for start in [4, 2] {
+            paginator.scroll_up(2).await.unwrap();
+            assert_eq!(paginator.window()[0].title(), Some(titles[start].as_str()));
+            assert_eq!(paginator.books.len(), 2);
+        }
+        assert_eq!(db.read().await.limits(), vec![Some(2); 6]);
+    }
+
+    #[tokio::test]
+    async fn test_read_page() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        let titles: Vec<_> = (0..10).map(|i| format!("book {:02}", i)).collect();
+        db.insert_books(
+            titles
+                .iter()
+                .enumerate()
+                .map(|(i, title)| variant(title, i as u8)),
+        )
+        .await
+        .unwrap();
+        let db = Arc::new(RwLock::new(db));
+        let sort = vec![(ColumnIdentifier::Title, ColumnOrder::Descending)].into_boxed_slice();
+        let paginator = Paginator::new(db, 0, sort);
+
+        let page = |books: Vec<Arc<Book>>| -> Vec<String> {
+            books
+                .iter()
+                .map(|book| book.title().unwrap().to_string())
+                .collect()
+        };
+        let mut expected = titles.clone();
+        expected.reverse();
+        assert_eq!(
+            page(paginator.read_page(0, 4).await.unwrap()),
+            expected[..4].to_vec()
+        );
+        assert_eq!(
+            page(paginator.read_page(8, 4).await.unwrap()),
+            expected[8..].to_vec()
+        );
+        assert!(paginator.read_page(12, 4).await.unwrap().is_empty());
+        assert!(paginator.window().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_collation() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        db.insert_books(
+            ["Banana", "cherry", "apple"]
+                .iter()
+                .enumerate()
+                .map(|(i, title)| variant(title, i as u8)),
+        )
+        .await
+        .unwrap();
+        let db = Arc::new(RwLock::new(db));
+        let sort = vec![(ColumnIdentifier::Title, ColumnOrder::Ascending)].into_boxed_slice();
+
+        for (collation, expected) in [
+            (Collation::CaseInsensitive, ["apple", "Banana", "cherry"]),
+            (Collation::CaseSensitive, ["Banana", "apple", "cherry"]),
+        ]
+        .iter()
+        {
+            let mut paginator = Paginator::new(db.clone(), 0, sort.clone());
+            paginator.set_prefetch(false);
+            paginator.set_collation(*collation);
+
+            // Scroll one book at a time, so that each page is read relative to the last book.
+            paginator.update_window_size(1).await.unwrap();
+            let mut titles = vec![];
+            for _ in 0..expected.len() {
+                titles.push(paginator.window()[0].title().unwrap().to_string());
+                paginator.scroll_down(1).await.unwrap();
+            }
+            assert_eq!(titles, expected);
+
+            let books = paginator.read_all().await.unwrap();
+            let titles: Vec<_> = books.iter().map(|book| book.title().unwrap()).collect();
+            assert_eq!(titles, expected);
+            assert!(books[0].cmp_columns(&books[1], &sort, *collation).is_lt());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sort_by_series_and_description() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        let books = [
+            ("Dune Messiah", "Dune [2]", "b"),
+            ("Heretics of Dune", "Dune [10]", "c"),
+            ("Foundation", "Foundation [1]", "a"),
+        ];
+        let ids = db
+            .insert_books(
+                books
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (title, _, description))| BookVariant {
+                        description: Some(description.to_string()),
+                        ..variant(title, i as u8)
+                    }),
+            )
+            .await
+            .unwrap();
+        for (id, (_, series, _)) in ids.iter().zip(books.iter()) {
+            db.edit_book_with_id(
+                *id,
+                &[(ColumnIdentifier::Series, Edit::Replace(series.to_string()))],
+            )
+            .await
+            .unwrap();
+        }
+        let db = Arc::new(RwLock::new(db));
+
+        for (column, expected) in [
+            (
+                ColumnIdentifier::Series,
+                ["Dune Messiah", "Heretics of Dune", "Foundation"],
+            ),
+            (
+                ColumnIdentifier::Description,
+                ["Foundation", "Dune Messiah", "Heretics of Dune"],
+            ),
+        ]
+        .iter()
+        {
+            let sort = vec![(column.clone(), ColumnOrder::Ascending)].into_boxed_slice();
+            let mut paginator = Paginator::new(db.clone(), 0, sort);
+            paginator.set_prefetch(false);
+
+            // Scroll one book at a time, so that each page is read relative to the last book.
+            paginator.update_window_size(1).await.unwrap();
+            let mut titles = vec![];
+            for _ in 0..expected.len() {
+                titles.push(paginator.window()[0].title().unwrap().to_string());
+                paginator.scroll_down(1).await.unwrap();
+            }
+            assert_eq!(titles, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_refresh_keeps_selection() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        let titles: Vec<_> = (0..10).map(|i| format!("book {:02}", i)).collect();
+        let ids = db
+            .insert_books(
+                titles
+                    .iter()
+                    .enumerate()
+                    .map(|(i, title)| variant(title, i as u8)),
+            )
+            .await
+            .unwrap();
+        let db = Arc::new(RwLock::new(db));
+        let sort = vec![(ColumnIdentifier::Title, ColumnOrder::Ascending)].into_boxed_slice();
+        let mut paginator = Paginator::new(db.clone(), 0, sort);
+        paginator.set_prefetch(false);
+        paginator.update_window_size(5).await.unwrap();
+        paginator.select_first().await.unwrap();
+        paginator.down().await.unwrap();
+        paginator.down().await.unwrap();
+
+        let window = |paginator: &Paginator<SQLiteDatabase>| -> Vec<String> {
+            paginator
+                .window()
+                .iter()
+                .map(|book| book.title().unwrap().to_string())
+                .collect()
+        };
+        let front = |paginator: &Paginator<SQLiteDatabase>| -> String {
+            let book = paginator.selected().front().unwrap();
+            book.title().unwrap().to_string()
+        };
+        let rows = |paginator: &Paginator<SQLiteDatabase>| -> Vec<usize> {
+            paginator
+                .relative_selections()
+                .iter()
+                .map(|(row, _)| *row)
+                .collect()
+        };
+        assert_eq!(front(&paginator), "book 02");
+        assert_eq!(rows(&paginator), vec![2]);
+
+        // Editing the sort key moves the book, but it stays selected, on the same row.
+        let edit = |title: &str| [(ColumnIdentifier::Title, Edit::Replace(title.to_string()))];
+        db.write()
+            .await
+            .edit_book_with_id(ids[2], &edit("book 07b"))
+            .await
+            .unwrap();
+        paginator.refresh().await.unwrap();
+        assert_eq!(front(&paginator), "book 07b");
+        assert_eq!(
+            window(&paginator),
+            vec!["book 06", "book 07", "book 07b", "book 08", "book 09"]
+        );
+        assert_eq!(rows(&paginator), vec![2]);
+
+        // If the cursor moves past the other end of a range, the direction is reversed.
+        let start = db.read().await.get_book(ids[1]).await.unwrap();
+        let end = db.read().await.get_book(ids[3]).await.unwrap();
+        paginator.selected = Selection::Range(
+            start,
+            end,
+            paginator.sorting_rules.clone(),
+            Direction::Up,
+            clone_match_box(&paginator.matching_rules),
+            paginator.collation,
+        );
+        db.write()
+            .await
+            .edit_book_with_id(ids[1], &edit("book 99"))
+            .await
+            .unwrap();
+        paginator.refresh().await.unwrap();
+        assert_eq!(front(&paginator), "book 99");
+        match paginator.selected() {
+            Selection::Range(start, _, _, direction, _, _) => {
+                assert_eq!(start.title(), Some("book 03"));
+                assert_eq!(*direction, Direction::Down);
+            }
+            _ => panic!("Expected a range to be selected"),
+        }
+    }
+}
@@ -2,8 +2,10 @@ use std::borrow::Cow;
 
 use regex::{Error as RegexError, Regex};
 use sublime_fuzzy::best_match;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
 
-use bookworm_records::book::ColumnIdentifier;
+use bookworm_records::book::{ColumnIdentifier, AUTHOR_MULTIMAP, READ_STATUS_TAG};
 use bookworm_records::Book;
 
 use crate::paginator::Variable;
@@ -16,11 +18,101 @@ pub enum SearchMode {
     ExactSubstring,
     Default,
     ExactString,
+    Empty,
+    Extension,
+    InSeries,
+    FileSize(SizeUnits),
+    FullText,
+}
+
+/// The meaning of the "KB", "MB" and "GB" units in file size searches - "KiB", "MiB" and "GiB"
+/// are always binary.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SizeUnits {
+    /// 1KB is 1000 bytes.
+    Decimal,
+    /// 1KB is 1024 bytes.
+    Binary,
+}
+
+impl Default for SizeUnits {
+    fn default() -> Self {
+        SizeUnits::Decimal
+    }
+}
+
+/// How text is normalized before it is compared by a matcher.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Normalization {
+    /// Text is compared as is.
+    Exact,
+    /// Text is compared after folding case, so that "bronte" matches "BRONTE".
+    CaseInsensitive,
+    /// Text is compared after folding case and removing accents and other combining marks,
+    /// so that "bronte" matches "Brontë".
+    Unaccented,
+}
+
+impl Default for Normalization {
+    fn default() -> Self {
+        Normalization::Exact
+    }
+}
+
+/// Removes combining marks from `text`, after decomposing accented characters into their
+/// base character and combining marks.
+fn strip_marks(text: &str) -> String {
+    text.nfd().filter(|&c| !is_combining_mark(c)).collect()
+}
+
+impl Normalization {
+    /// Returns `text`, normalized for comparison.
+    pub fn apply<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        match self {
+            Normalization::Exact => Cow::Borrowed(text),
+            Normalization::CaseInsensitive => Cow::Owned(text.to_lowercase()),
+            Normalization::Unaccented => Cow::Owned(strip_marks(text).to_lowercase()),
+        }
+    }
+
+    /// Returns the SQL function which normalizes text in the same way as `apply`, if any.
+    /// These functions are registered on each SQLite connection.
+    pub fn sql_function(&self) -> Option<&'static str> {
+        match self {
+            Normalization::Exact => None,
+            Normalization::CaseInsensitive => Some("casefold"),
+            Normalization::Unaccented => Some("unaccent"),
+        }
+    }
+
+    /// Returns `pattern`, changed so that it matches text normalized by `apply`. Regex syntax
+    /// is preserved, so case is ignored with a flag rather than by lowercasing the pattern.
+    fn regex_pattern(&self, pattern: &str) -> String {
+        match self {
+            Normalization::Exact => pattern.to_string(),
+            Normalization::CaseInsensitive => format!("(?i){}", pattern),
+            Normalization::Unaccented => format!("(?i){}", strip_marks(pattern)),
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum Error {
     Regex(RegexError),
+    /// The search is not a valid range of file sizes.
+    FileSize(String),
+    /// The search expression can not be parsed, for the given reason.
+    Expression(String),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Regex(e) => write!(f, "Invalid regular expression: {}", e),
+            Error::FileSize(search) => write!(f, "Invalid file size range: {}", search),
+            Error::Expression(reason) => write!(f, "Invalid search expression: {}", reason),
+        }
+    }
 }
 
 impl From<RegexError> for Error {
@@ -37,18 +129,161 @@ pub struct Search {
 }
 
 impl Search {
+    /// Returns the default search of `column` for `search` - if `column` is "ext" or
+    /// "extension", books are instead matched by the file extensions of their variants, if
+    /// `column` is "size", books are matched by the file sizes of their variants, and if `column`
+    /// is "series", books are matched by the exact name of their series.
+    pub fn for_column(column: &str, search: String) -> Self {
+        match column.to_lowercase().as_str() {
+            "ext" | "extension" => Search {
+                mode: SearchMode::Extension,
+                column: ColumnIdentifier::Variants,
+                search,
+            },
+            "series" => Search {
+                mode: SearchMode::InSeries,
+                column: ColumnIdentifier::Series,
+                search,
+            },
+            "size" => Search {
+                mode: SearchMode::FileSize(SizeUnits::default()),
+                column: ColumnIdentifier::Variants,
+                search,
+            },
+            _ => Search {
+                mode: SearchMode::Default,
+                column: ColumnIdentifier::from(column),
+                search,
+            },
+        }
+    }
+
     pub fn into_matcher(self) -> Result<Box<dyn Matcher + Send + Sync>, Error> {
+        self.into_normalized_matcher(Normalization::Exact)
+    }
+
+    /// Creates a matcher for this search, which compares text after applying `normalization`.
+    /// Searches which do not compare text, such as file size searches, ignore `normalization`.
+    pub fn into_normalized_matcher(
+        self,
+        normalization: Normalization,
+    ) -> Result<Box<dyn Matcher + Send + Sync>, Error> {
+        let (column, search) = (self.column, self.search);
         Ok(match self.mode {
-            SearchMode::Regex => Box::new(RegexMatcher::new(self.column, self.search)?),
-            SearchMode::ExactSubstring => {
-                Box::new(ExactSubstringMatcher::new(self.column, self.search)?)
-            }
-            SearchMode::ExactString => Box::new(ExactStringMatcher::new(self.column, self.search)?),
-            SearchMode::Default => Box::new(DefaultMatcher::new(self.column, self.search)?),
+            SearchMode::Regex => Box::new(RegexMatcher::with_normalization(
+                column,
+                search,
+                normalization,
+            )?),
+            SearchMode::ExactSubstring => Box::new(ExactSubstringMatcher::with_normalization(
+                column,
+                search,
+                normalization,
+            )?),
+            SearchMode::ExactString => Box::new(ExactStringMatcher::with_normalization(
+                column,
+                search,
+                normalization,
+            )?),
+            SearchMode::Default => Box::new(DefaultMatcher::with_normalization(
+                column,
+                search,
+                normalization,
+            )?),
+            SearchMode::Empty => match column {
+                ColumnIdentifier::Cover => Box::new(HasCoverMatcher::new(column, String::new())?),
+                column => Box::new(EmptyMatcher::new(column, String::new())?),
+            },
+            SearchMode::Extension => Box::new(ExtensionMatcher::new(column, search)?),
+            SearchMode::InSeries => Box::new(InSeriesMatcher::new(column, search)?),
+            SearchMode::FileSize(units) => Box::new(FileSizeMatcher::with_units(search, units)?),
+            SearchMode::FullText => Box::new(FullTextMatcher::new(column, search)?),
         })
     }
 }
 
+/// Parses a search expression, such as `author:Tolkien AND series:"Lord of the Rings"`, into
+/// the searches which a book must match to match the expression. Each term of the expression is
+/// a `column:value` pair, where values containing spaces are quoted, and is searched for as in
+/// `Search::for_column`. Terms are joined by AND, which may be left out.
+///
+/// # Errors
+/// Will return an error if the expression is empty, if a term is not a `column:value` pair, if a
+/// quoted value is not closed, or if the expression uses OR or NOT, which are not supported yet.
+pub fn parse_expression(expression: &str) -> Result<Box<[Search]>, Error> {
+    let error = |reason: String| Err(Error::Expression(reason));
+    let mut searches = vec![];
+    // Whether the previous token was AND, or the expression has just started.
+    let mut expect_term = true;
+    let mut chars = expression.chars().peekable();
+    loop {
+        while chars.peek().map_or(false, |c| c.is_whitespace()) {
+            chars.next();
+        }
+        if chars.peek().is_none() {
+            break;
+        }
+
+        let mut word = String::new();
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() || c == ':' {
+                break;
+            }
+            word.push(c);
+            chars.next();
+        }
+        if chars.peek() != Some(&':') {
+            match word.as_str() {
+                "AND" if !expect_term => expect_term = true,
+                "AND" => return error("expected a term before AND".to_string()),
+                "OR" | "NOT" => return error(format!("{} is not supported yet", word)),
+                _ => return error(format!("expected column:value, found {}", word)),
+            }
+            continue;
+        }
+        chars.next();
+        if word.is_empty() {
+            return error("expected a column before :".to_string());
+        }
+
+        let mut value = String::new();
+        match chars.peek().copied() {
+            Some(quote) if quote == '"' || quote == '\'' => {
+                chars.next();
+                loop {
+                    match chars.next() {
+                        Some(c) if c == quote => break,
+                        Some(c) => value.push(c),
+                        None => return error(format!("unclosed quote in {}:", word)),
+                    }
+                }
+            }
+            _ => {
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() {
+                        break;
+                    }
+                    value.push(c);
+                    chars.next();
+                }
+                if value.is_empty() {
+                    return error(format!("expected a value after {}:", word));
+                }
+            }
+        }
+        searches.push(Search::for_column(&word, value));
+        expect_term = false;
+    }
+
+    if searches.is_empty() {
+        error("expected at least one column:value term".to_string())
+    } else if expect_term {
+        error("expected a term after AND".to_string())
+    } else {
+        Ok(searches.into_boxed_slice())
+    }
+}
+
 /// Provides a mechanism to determine if a particular book matches a particular search string,
 /// using some internally defined comparison method.
 pub trait Matcher: Send + Sync {
@@ -57,44 +292,104 @@ pub trait Matcher: Send + Sync {
     where
         Self: Sized;
 
+    /// Creates a new Matcher like `new`, which compares text after applying `normalization`.
+    /// Matchers which do not compare text ignore `normalization`.
+    fn with_normalization(
+        column: ColumnIdentifier,
+        search: String,
+        _normalization: Normalization,
+    ) -> Result<Self, Error>
+    where
+        Self: Sized,
+    {
+        Self::new(column, search)
+    }
+
     /// Determines if the book matches the internal match rules.
     fn is_match(&self, book: &Book) -> bool;
 
     fn sql_query(&self) -> (&ColumnIdentifier, String, Option<Variable>);
 
+    /// Returns the query used in place of `sql_query` if the database has no full-text index.
+    /// Only matchers which use the full-text index need to override this.
+    fn fallback_sql_query(&self) -> (&ColumnIdentifier, String, Option<Variable>) {
+        self.sql_query()
+    }
+
+    /// Returns the normalization which is applied to column values before they are compared
+    /// using the query from `sql_query`.
+    fn normalization(&self) -> Normalization {
+        Normalization::Exact
+    }
+
     fn box_clone(&self) -> Box<dyn Matcher + Send + Sync>;
 }
 
+/// Returns whether `is_match` accepts the value of `column` in `book`, after applying
+/// `normalization`. Books without a value are matched as if the value was empty, and each free
+/// tag is matched separately.
+fn column_matches<F: Fn(&str) -> bool>(
+    book: &Book,
+    column: &ColumnIdentifier,
+    normalization: Normalization,
+    is_match: F,
+) -> bool {
+    if *column == ColumnIdentifier::Tags {
+        return book
+            .free_tags
+            .iter()
+            .any(|value| is_match(&normalization.apply(value)))
+            || is_match("");
+    }
+
+    match book.get_column(column) {
+        None => is_match(""),
+        Some(value) => is_match(&normalization.apply(&value)),
+    }
+}
+
 #[derive(Clone)]
 pub struct RegexMatcher {
     column: ColumnIdentifier,
     regex: Regex,
+    normalization: Normalization,
 }
 
 impl Matcher for RegexMatcher {
     fn new(column: ColumnIdentifier, search: String) -> Result<Self, Error> {
+        Self::with_normalization(column, search, Normalization::Exact)
+    }
+
+    fn with_normalization(
+        column: ColumnIdentifier,
+        search: String,
+        normalization: Normalization,
+    ) -> Result<Self, Error> {
         Ok(RegexMatcher {
             column,
-            regex: Regex::new(&search)?,
+            regex: Regex::new(&normalization.regex_pattern(&search))?,
+            normalization,
         })
     }
 
     #[inline(always)]
     fn is_match(&self, book: &Book) -> bool {
-        if self.column == ColumnIdentifier::Tags {
-            return book.free_tags.iter().any(|v| self.regex.is_match(v))
-                || self.regex.is_match("");
-        }
-
-        match book.get_column(&self.column) {
-            None => self.regex.is_match(""),
-            Some(Cow::Borrowed(value)) => self.regex.is_match(value),
-            Some(Cow::Owned(value)) => self.regex.is_match(&value),
-        }
+        column_matches(book, &self.column, self.normalization, |value| {
+            self.regex.is_match(value)
+        })
     }
 
     fn sql_query(&self) -> (&ColumnIdentifier, String, Option<Variable>) {
-        unimplemented!()
+        // REGEXP is provided by the regexp function, which is registered on each connection.
+        (
+            &self.column,
+            "REGEXP ?".to_string(),
+            Some(Variable::Str(self.regex.as_str().to_string())),
+        )
+    }
+
+    fn normalization(&self) -> Normalization {
+        self.normalization
     }
 
     fn box_clone(&self) -> Box<dyn Matcher + Send + Sync> {
@@ -106,32 +401,60 @@ impl Matcher for RegexMatcher {
 pub struct ExactSubstringMatcher {
     column: ColumnIdentifier,
     regex: Regex,
+    search: String,
+    normalization: Normalization,
 }
 
 impl Matcher for ExactSubstringMatcher {
     fn new(column: ColumnIdentifier, search: String) -> Result<Self, Error> {
+        Self::with_normalization(column, search, Normalization::Exact)
+    }
+
+    fn with_normalization(
+        column: ColumnIdentifier,
+        search: String,
+        normalization: Normalization,
+    ) -> Result<Self, Error> {
+        let search = normalization.apply(&search).into_owned();
         Ok(ExactSubstringMatcher {
             column,
             regex: Regex::new(&regex::escape(&search))?,
+            search,
+            normalization,
         })
     }
 
     #[inline(always)]
     fn is_match(&self, book: &Book) -> bool {
-        if self.column == ColumnIdentifier::Tags {
-            return book.free_tags.iter().any(|v| self.regex.is_match(v))
-                || self.regex.is_match("");
-        }
+        column_matches(book, &self.column, self.normalization, |value| {
+            self.regex.is_match(value)
+        })
+    }
 
-        match book.get_column(&self.column) {
-            None => self.regex.is_match(""),
-            Some(Cow::Borrowed(value)) => self.regex.is_match(value),
-            Some(Cow::Owned(value)) => self.regex.is_match(&value),
+    fn sql_query(&self) -> (&ColumnIdentifier, String, Option<Variable>) {
+        // GLOB is case-sensitive, unlike LIKE. Wildcards in the search are escaped by
+        // placing them in a character class.
+        let mut pattern = String::from("*");
+        for c in self.search.chars() {
+            match c {
+                '*' | '?' | '[' => {
+                    pattern.push('[');
+                    pattern.push(c);
+                    pattern.push(']');
+                }
+                c => pattern.push(c),
+            }
         }
+        pattern.push('*');
+        (
+            &self.column,
+            "GLOB ?".to_string(),
+            Some(Variable::Str(pattern)),
+        )
     }
 
-    fn sql_query(&self) -> (&ColumnIdentifier, String, Option<Variable>) {
-        unimplemented!()
+    fn normalization(&self) -> Normalization {
+        self.normalization
     }
 
     fn box_clone(&self) -> Box<dyn Matcher + Send + Sync> {
@@ -142,37 +465,45 @@ impl Matcher for ExactSubstringMatcher {
 pub struct ExactStringMatcher {
     column: ColumnIdentifier,
     string: String,
+    normalization: Normalization,
 }
 
 impl Matcher for ExactStringMatcher {
     fn new(column: ColumnIdentifier, search: String) -> Result<Self, Error> {
+        Self::with_normalization(column, search, Normalization::Exact)
+    }
+
+    fn with_normalization(
+        column: ColumnIdentifier,
+        search: String,
+        normalization: Normalization,
+    ) -> Result<Self, Error> {
         Ok(ExactStringMatcher {
             column,
-            string: search,
+            string: normalization.apply(&search).into_owned(),
+            normalization,
         })
     }
 
     #[inline(always)]
     fn is_match(&self, book: &Book) -> bool {
-        if self.column == ColumnIdentifier::Tags {
-            return book.free_tags.contains(&self.string) || self.string.is_empty();
-        }
-
-        match book.get_column(&self.column) {
-            None => self.string.is_empty(),
-            Some(Cow::Borrowed(value)) => self.string == value,
-            Some(Cow::Owned(value)) => self.string == value,
-        }
+        column_matches(book, &self.column, self.normalization, |value| {
+            self.string == value
+        })
     }
 
     fn sql_query(&self) -> (&ColumnIdentifier, String, Option<Variable>) {
         (
             &self.column,
-            format!("= ?"),
+            "= ?".to_string(),
             Some(Variable::Str(self.string.clone())),
         )
     }
 
+    fn normalization(&self) -> Normalization {
+        self.normalization
+    }
+
     fn box_clone(&self) -> Box<dyn Matcher + Send + Sync> {
         Box::new(self.clone())
     }
@@ -182,39 +513,438 @@ impl Matcher for ExactStringMatcher {
 pub struct DefaultMatcher {
     column: ColumnIdentifier,
     string: String,
+    normalization: Normalization,
 }
 
 impl Matcher for DefaultMatcher {
     fn new(column: ColumnIdentifier, search: String) -> Result<Self, Error> {
+        Self::with_normalization(column, search, Normalization::Exact)
+    }
+
+    fn with_normalization(
+        column: ColumnIdentifier,
+        search: String,
+        normalization: Normalization,
+    ) -> Result<Self, Error> {
         Ok(DefaultMatcher {
             column,
-            string: search,
+            string: normalization.apply(&search).into_owned(),
+            normalization,
+        })
+    }
+
+    #[inline(always)]
+    fn is_match(&self, book: &Book) -> bool {
+        column_matches(book, &self.column, self.normalization, |value| {
+            best_match(&self.string, value).is_some()
+        })
+    }
+
+    fn sql_query(&self) -> (&ColumnIdentifier, String, Option<Variable>) {
+        (
+            &self.column,
+            "LIKE '%' || ? || '%'".to_string(),
+            Some(Variable::Str(self.string.clone())),
+        )
+    }
+
+    fn normalization(&self) -> Normalization {
+        self.normalization
+    }
+
+    fn box_clone(&self) -> Box<dyn Matcher + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
+
+/// Matches books based on whether a cover is stored for them. Books with a cover are
+/// matched if the search string is non-empty, and books without a cover otherwise.
+#[derive(Clone)]
+pub struct HasCoverMatcher {
+    has_cover: bool,
+}
+
+impl Matcher for HasCoverMatcher {
+    fn new(_column: ColumnIdentifier, search: String) -> Result<Self, Error> {
+        Ok(HasCoverMatcher {
+            has_cover: !search.is_empty(),
         })
     }
 
     #[inline(always)]
     fn is_match(&self, book: &Book) -> bool {
-        if self.column == ColumnIdentifier::Tags {
-            return book
-                .free_tags
-                .iter()
-                .any(|value| best_match(&self.string, value).is_some())
-                || best_match(&self.string, "").is_some();
+        book.has_cover == self.has_cover
+    }
+
+    fn sql_query(&self) -> (&ColumnIdentifier, String, Option<Variable>) {
+        (
+            &ColumnIdentifier::Cover,
+            format!("= {}", self.has_cover as u8),
+            None,
+        )
+    }
+
+    fn box_clone(&self) -> Box<dyn Matcher + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
+
+/// Matches books which have no value for a column, including books which have no row for the
+/// column in its table, such as books without a particular named tag.
+#[derive(Clone)]
+pub struct EmptyMatcher {
+    column: ColumnIdentifier,
+}
+
+impl Matcher for EmptyMatcher {
+    fn new(column: ColumnIdentifier, _search: String) -> Result<Self, Error> {
+        Ok(EmptyMatcher { column })
+    }
+
+    #[inline(always)]
+    fn is_match(&self, book: &Book) -> bool {
+        match &self.column {
+            ColumnIdentifier::Tags => book.free_tags.is_empty(),
+            ColumnIdentifier::ExactTag(tag) => !book.free_tags.contains(tag),
+            ColumnIdentifier::Variants => book.variants().is_empty(),
+            column => book
+                .get_column(column)
+                .map_or(true, |value| value.is_empty()),
         }
+    }
+
+    fn sql_query(&self) -> (&ColumnIdentifier, String, Option<Variable>) {
+        let name = |name: &str| Some(Variable::Str(name.to_string()));
+        // Books are selected by ID, as books without a value may have no row to join with.
+        let (query, bound) = match &self.column {
+            ColumnIdentifier::ID => ("IS NULL", None),
+            ColumnIdentifier::Title => (
+                "IN (SELECT book_id FROM books WHERE title IS NULL OR title = '')",
+                None,
+            ),
+            ColumnIdentifier::Series => (
+                "IN (SELECT book_id FROM books WHERE series_name IS NULL OR series_name = '')",
+                None,
+            ),
+            ColumnIdentifier::DateAdded => (
+                "IN (SELECT book_id FROM books WHERE date_added IS NULL OR date_added = '')",
+                None,
+            ),
+            ColumnIdentifier::Author => (
+                "NOT IN (SELECT book_id FROM multimap_tags WHERE name = ?)",
+                name(AUTHOR_MULTIMAP),
+            ),
+            ColumnIdentifier::MultiMap(tag) | ColumnIdentifier::MultiMapExact(tag, _) => (
+                "NOT IN (SELECT book_id FROM multimap_tags WHERE name = ?)",
+                name(tag),
+            ),
+            ColumnIdentifier::NamedTag(tag) => (
+                "NOT IN (SELECT book_id FROM named_tags WHERE name = ? AND value != '')",
+                name(tag),
+            ),
+            ColumnIdentifier::ReadStatus => (
+                "NOT IN (SELECT book_id FROM named_tags WHERE name = ? AND value != '')",
+                name(READ_STATUS_TAG),
+            ),
+            ColumnIdentifier::Description => (
+                "NOT IN (SELECT book_id FROM variants WHERE description IS NOT NULL AND description != '')",
+                None,
+            ),
+            ColumnIdentifier::Variants => ("NOT IN (SELECT book_id FROM variants)", None),
+            ColumnIdentifier::Tags => ("NOT IN (SELECT book_id FROM free_tags)", None),
+            ColumnIdentifier::ExactTag(tag) => (
+                "NOT IN (SELECT book_id FROM free_tags WHERE value = ?)",
+                name(tag),
+            ),
+            ColumnIdentifier::Cover => ("NOT IN (SELECT book_id FROM covers)", None),
+        };
+        (&ColumnIdentifier::ID, query.to_string(), bound)
+    }
 
-        match book.get_column(&self.column) {
-            None => best_match(&self.string, ""),
-            Some(Cow::Borrowed(value)) => best_match(&self.string, value),
-            Some(Cow::Owned(value)) => best_match(&self.string, &value),
+    fn box_clone(&self) -> Box<dyn Matcher + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
+
+/// Matches books which have at least one variant whose file extension is equal to the search
+/// string, ignoring case. A leading '.' in the search string is ignored.
+#[derive(Clone)]
+pub struct ExtensionMatcher {
+    extension: String,
+}
+
+impl Matcher for ExtensionMatcher {
+    fn new(_column: ColumnIdentifier, search: String) -> Result<Self, Error> {
+        Ok(ExtensionMatcher {
+            extension: search.trim_start_matches('.').to_string(),
+        })
+    }
+
+    #[inline(always)]
+    fn is_match(&self, book: &Book) -> bool {
+        book.variants().iter().any(|variant| {
+            variant
+                .path()
+                .extension()
+                .map(|ext| ext.to_string_lossy().eq_ignore_ascii_case(&self.extension))
+                .unwrap_or(false)
+        })
+    }
+
+    fn sql_query(&self) -> (&ColumnIdentifier, String, Option<Variable>) {
+        (
+            &ColumnIdentifier::ID,
+            "IN (SELECT book_id FROM variants WHERE CAST(path AS TEXT) LIKE '%.' || ?)".to_string(),
+            Some(Variable::Str(self.extension.clone())),
+        )
+    }
+
+    fn box_clone(&self) -> Box<dyn Matcher + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
+
+/// Matches books which belong to a series. If a series name is provided, only books in the
+/// series with that exact name are matched.
+#[derive(Clone)]
+pub struct InSeriesMatcher(Option<String>);
+
+impl Matcher for InSeriesMatcher {
+    fn new(_column: ColumnIdentifier, search: String) -> Result<Self, Error> {
+        Ok(InSeriesMatcher(if search.is_empty() {
+            None
+        } else {
+            Some(search)
+        }))
+    }
+
+    #[inline(always)]
+    fn is_match(&self, book: &Book) -> bool {
+        match (book.series(), &self.0) {
+            (None, _) => false,
+            (Some(_), None) => true,
+            (Some(series), Some(name)) => &series.name == name,
         }
-        .is_some()
+    }
+
+    fn sql_query(&self) -> (&ColumnIdentifier, String, Option<Variable>) {
+        match &self.0 {
+            None => (
+                &ColumnIdentifier::ID,
+                "IN (SELECT book_id FROM books WHERE series_name IS NOT NULL)".to_string(),
+                None,
+            ),
+            Some(name) => (
+                &ColumnIdentifier::ID,
+                "IN (SELECT book_id FROM books WHERE series_name = ?)".to_string(),
+                Some(Variable::Str(name.clone())),
+            ),
+        }
+    }
+
+    fn box_clone(&self) -> Box<dyn Matcher + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
+
+/// Parses a file size such as "512", "20MB" or "1.5 GiB", returning the size in bytes.
+///
+/// # Arguments
+/// * ` size ` - The file size, with an optional unit.
+/// * ` units ` - The meaning of the "KB", "MB" and "GB" units.
+pub fn parse_file_size(size: &str, units: SizeUnits) -> Option<u64> {
+    let size = size.trim();
+    let split = size
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or_else(|| size.len());
+    let (value, unit) = size.split_at(split);
+    let value: f64 = value.parse().ok()?;
+
+    let kilo: f64 = match units {
+        SizeUnits::Decimal => 1000.,
+        SizeUnits::Binary => 1024.,
+    };
+    let multiplier = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1.,
+        "k" | "kb" => kilo,
+        "m" | "mb" => kilo.powi(2),
+        "g" | "gb" => kilo.powi(3),
+        "kib" => 1024.,
+        "mib" => 1024f64.powi(2),
+        "gib" => 1024f64.powi(3),
+        _ => return None,
+    };
+    Some((value * multiplier).round() as u64)
+}
+
+/// Parses an inclusive range of file sizes of the form "min..max", where either bound may be
+/// omitted, returning the bounds in bytes.
+///
+/// # Arguments
+/// * ` range ` - The range of file sizes, eg. "1MB..20MB" or "..500KB".
+/// * ` units ` - The meaning of the "KB", "MB" and "GB" units.
+pub fn parse_file_size_range(range: &str, units: SizeUnits) -> Option<(u64, u64)> {
+    let (min, max) = range.split_once("..")?;
+    let bound = |size: &str, default: u64| {
+        if size.trim().is_empty() {
+            Some(default)
+        } else {
+            parse_file_size(size, units)
+        }
+    };
+    // SQLite integers are signed, so sizes are capped at i64::MAX.
+    let min = bound(min, 0)?;
+    let max = bound(max, i64::MAX as u64)?.min(i64::MAX as u64);
+    if min > max {
+        None
+    } else {
+        Some((min, max))
+    }
+}
+
+/// Matches books which have at least one variant whose file size is within an inclusive range
+/// of bytes. The search string is a range such as "1MB..20MB" - see `parse_file_size_range`.
+#[derive(Clone)]
+pub struct FileSizeMatcher {
+    min: u64,
+    max: u64,
+}
+
+impl FileSizeMatcher {
+    /// Creates a matcher over the range in `search`, where "KB", "MB" and "GB" are interpreted
+    /// according to `units`.
+    pub fn with_units(search: String, units: SizeUnits) -> Result<Self, Error> {
+        match parse_file_size_range(&search, units) {
+            Some((min, max)) => Ok(FileSizeMatcher { min, max }),
+            None => Err(Error::FileSize(search)),
+        }
+    }
+}
+
+impl Matcher for FileSizeMatcher {
+    fn new(_column: ColumnIdentifier, search: String) -> Result<Self, Error> {
+        Self::with_units(search, SizeUnits::default())
+    }
+
+    #[inline(always)]
+    fn is_match(&self, book: &Book) -> bool {
+        book.variants()
+            .iter()
+            .any(|variant| (self.min..=self.max).contains(&variant.file_size))
     }
 
     fn sql_query(&self) -> (&ColumnIdentifier, String, Option<Variable>) {
         (
-            &self.column,
-            format!("LIKE '%' || ? || '%'"),
-            Some(Variable::Str(self.string.clone())),
+            &ColumnIdentifier::ID,
+            format!(
+                "IN (SELECT book_id FROM variants WHERE file_size BETWEEN {} AND {})",
+                self.min, self.max
+            ),
+            None,
+        )
+    }
+
+    fn box_clone(&self) -> Box<dyn Matcher + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
+
+/// Matches books whose title, authors or descriptions contain a word starting with each word
+/// of the search string, ignoring case and accents, as the `unicode61` tokenizer of the FTS5
+/// full-text index does. Words are separated by characters which are not alphanumeric. If the
+/// database does not support full-text search, the fallback query matches each word with
+/// `REGEXP` instead.
+#[derive(Clone)]
+pub struct FullTextMatcher {
+    terms: Vec<String>,
+}
+
+impl FullTextMatcher {
+    /// Returns the FTS5 query which matches each term as a prefix. The terms are quoted, so that
+    /// words such as "AND" are not read as FTS5 operators.
+    fn fts_query(&self) -> String {
+        self.terms
+            .iter()
+            .map(|term| format!("\"{}\"*", term))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl Matcher for FullTextMatcher {
+    fn new(_column: ColumnIdentifier, search: String) -> Result<Self, Error> {
+        Ok(FullTextMatcher {
+            terms: search
+                .split(|c: char| !c.is_alphanumeric())
+                .filter(|term| !term.is_empty())
+                .map(|term| Normalization::Unaccented.apply(term).into_owned())
+                .collect(),
+        })
+    }
+
+    fn is_match(&self, book: &Book) -> bool {
+        let mut text = String::new();
+        for value in book
+            .title()
+            .into_iter()
+            .chain(
+                book.authors()
+                    .unwrap_or_default()
+                    .iter()
+                    .map(String::as_str),
+            )
+            .chain(
+                book.variants()
+                    .iter()
+                    .filter_map(|v| v.description.as_deref()),
+            )
+        {
+            text.push_str(&Normalization::Unaccented.apply(value));
+            text.push(' ');
+        }
+        let words: Vec<_> = text.split(|c: char| !c.is_alphanumeric()).collect();
+        self.terms
+            .iter()
+            .all(|term| words.iter().any(|word| word.starts_with(term.as_str())))
+    }
+
+    fn sql_query(&self) -> (&ColumnIdentifier, String, Option<Variable>) {
+        // FTS5 rejects empty queries, and no terms match every book.
+        if self.terms.is_empty() {
+            return (&ColumnIdentifier::ID, "IS NOT NULL".to_string(), None);
+        }
+        (
+            &ColumnIdentifier::ID,
+            "IN (SELECT rowid FROM books_fts WHERE books_fts MATCH ?)".to_string(),
+            Some(Variable::Str(self.fts_query())),
+        )
+    }
+
+    fn fallback_sql_query(&self) -> (&ColumnIdentifier, String, Option<Variable>) {
+        if self.terms.is_empty() {
+            return (&ColumnIdentifier::ID, "IS NOT NULL".to_string(), None);
+        }
+        // Terms only contain alphanumeric characters, so they can be written into the
+        // patterns as they are.
+        let conditions = self
+            .terms
+            .iter()
+            .map(|term| format!("text REGEXP '(?:^|[^\\p{{Alphabetic}}\\p{{N}}]){}'", term))
+            .collect::<Vec<_>>()
+            .join(" AND ");
+        (
+            &ColumnIdentifier::ID,
+            format!(
+                r#"IN (SELECT book_id FROM (SELECT book_id, unaccent(
+    ifnull(title, '') || ' ' ||
+    ifnull((SELECT group_concat(value, ' ') FROM multimap_tags
+        WHERE multimap_tags.book_id = books.book_id AND name = 'author'), '') || ' ' ||
+    ifnull((SELECT group_concat(description, ' ') FROM variants
+        WHERE variants.book_id = books.book_id), '')
+) AS text FROM books) WHERE {})"#,
+                conditions
+            ),
+            None,
         )
     }
 
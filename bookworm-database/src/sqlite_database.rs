@@ -1,7 +1,8 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::convert::{TryFrom, TryInto};
 use std::ffi::OsString;
 use std::fmt::Formatter;
+use std::io::Write;
 use std::num::NonZeroU64;
 #[cfg(unix)]
 use std::os::unix::ffi::{OsStrExt, OsStringExt};
@@ -10,8 +11,10 @@ use std::os::windows::ffi::{OsStrExt, OsStringExt};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Instant;
 
 use async_trait::async_trait;
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
 use itertools::Itertools;
 use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
 use sqlx::{Sqlite, SqlitePool, Transaction};
@@ -19,44 +22,43 @@ use tokio::sync::RwLock;
 use unicase::UniCase;
 
 use bookworm_input::Edit;
-use bookworm_records::book::{BookID, ColumnIdentifier};
+use bookworm_records::author;
+use bookworm_records::book::{
+    read_status, BookID, ColumnIdentifier, RecordError, AUTHOR_MULTIMAP, READ_STATUS_TAG,
+};
 use bookworm_records::series::Series;
 use bookworm_records::{Book, BookVariant, Edit as BEdit};
 
 use crate::cache::BookCache;
+use crate::calibre::{self, CALIBRE_METADATA};
+use crate::database::FINISHED_TAG;
+use crate::export::BookWriter;
+use crate::functions::register_functions;
 use crate::paginator::{QueryBuilder, Selection, Variable};
-use crate::{AppDatabase, DatabaseError};
-
-// CREATE VIRTUAL TABLE table_fts USING FTS5 (
-//     fields,
-//     content="items"
-// );
-// INSERT INTO table_fts (fields)
-//     SELECT fields FROM table;
-//
-// SELECT rowid, * FROM table_fts WHERE table_fts MATCH ?;
-// CREATE TRIGGER notes_fts_before_update BEFORE UPDATE ON notes BEGIN
-//     DELETE FROM notes_fts WHERE docid=old.rowid;
-// END
-//
-// CREATE TRIGGER notes_fts_before_delete BEFORE DELETE ON notes BEGIN
-//     DELETE FROM notes_fts WHERE docid=old.rowid;
-// END
-//
-// CREATE TRIGGER notes_after_update AFTER UPDATE ON notes BEGIN
-//     INSERT INTO notes_fts(docid, id, title, body) SELECT rowid, id, title, body FROM notes WHERE is_conflict = 0 AND encryption_applied = 0 AND new.rowid = notes.rowid;
-// END
-//
-// CREATE TRIGGER notes_after_insert AFTER INSERT ON notes BEGIN
-//     INSERT INTO notes_fts(docid, id, title, body) SELECT rowid, id, title, body FROM notes WHERE is_conflict = 0 AND encryption_applied = 0 AND new.rowid = notes.rowid;
-// END
+use crate::{
+    AppDatabase, BookSnapshot, ConflictResolution, DatabaseError, DetectionStrategy, ExportFormat,
+    FtsReport, InsertPolicy, LibraryStats, MergeConflict, MonthlyReading, RepairPolicy,
+    RepairReport, TagAppendPolicy, VariantEditPolicy,
+};
+
+/// The number of books read from the database at a time when streaming books.
+const STREAM_BATCH_SIZE: i64 = 512;
+
+/// The number of books inserted in each transaction by `insert_books`, unless set otherwise.
+pub const DEFAULT_INSERT_BATCH_SIZE: usize = 5000;
+
+/// The fraction of all books which must be removed at once for the space they used to be
+/// returned to the file system.
+const VACUUM_THRESHOLD: f64 = 0.2;
+
 // TODO: Index for title, named_tags, min of multimap_tag
 /// Top level book metadata
 const CREATE_BOOKS: &str = r#"CREATE TABLE IF NOT EXISTS `books` (
 `book_id` INTEGER NOT NULL PRIMARY KEY,
 `title` TEXT DEFAULT NULL,
 `series_name` TEXT DEFAULT NULL,
-`series_id` REAL DEFAULT NULL
+`series_id` REAL DEFAULT NULL,
+`date_added` TEXT DEFAULT CURRENT_TIMESTAMP
 );"#;
 
 /// Tags for books with a particular name and single value
@@ -103,17 +105,133 @@ const CREATE_VARIANTS: &str = r#"CREATE TABLE IF NOT EXISTS `variants` (
 `hash` BLOB NOT NULL,
 `file_size` INTEGER NOT NULL,
 `book_id` INTEGER NOT NULL,
+`position` INTEGER NOT NULL DEFAULT 0,
+FOREIGN KEY(book_id) REFERENCES books(book_id)
+    ON UPDATE CASCADE
+    ON DELETE CASCADE
+);"#;
+
+const CREATE_COVERS: &str = r#"CREATE TABLE IF NOT EXISTS `covers` (
+`image` BLOB NOT NULL,
+`book_id` INTEGER NOT NULL UNIQUE,
 FOREIGN KEY(book_id) REFERENCES books(book_id)
     ON UPDATE CASCADE
     ON DELETE CASCADE
 );"#;
 
+/// The tables whose rows belong to a book, which are checked for orphaned rows by
+/// `SQLiteDatabase::repair`.
+const ORPHAN_TABLES: [&str; 5] = [
+    "variants",
+    "named_tags",
+    "free_tags",
+    "multimap_tags",
+    "covers",
+];
+
+// The full-text index over titles, authors and descriptions is only maintained if FTS5 is
+// available - see `SQLiteDatabase::enable_full_text_search`. Authors and descriptions are not
+// stored in the books table, so the index stores its own copy of the text of each book, as
+// given by `books_search`.
+const CREATE_BOOKS_FTS: &str = r#"CREATE VIRTUAL TABLE IF NOT EXISTS `books_fts` USING fts5(
+`title`,
+`authors`,
+`description`
+);"#;
+
+const CREATE_BOOKS_SEARCH: &str = r#"CREATE VIEW IF NOT EXISTS `books_search` AS
+SELECT book_id, title,
+    (SELECT group_concat(value, ' ') FROM multimap_tags
+        WHERE multimap_tags.book_id = books.book_id AND name = 'author') AS authors,
+    (SELECT group_concat(description, ' ') FROM variants
+        WHERE variants.book_id = books.book_id) AS description
+FROM books;"#;
+
+/// Returns the statements which replace the full-text index entry of the book with the ID given
+/// by the `id` expression with the current text of the book.
+fn fts_refresh(id: &str) -> String {
+    format!(
+        "DELETE FROM books_fts WHERE rowid = {id};
+    INSERT INTO books_fts(rowid, title, authors, description)
+        SELECT book_id, title, authors, description FROM books_search WHERE book_id = {id};",
+        id = id
+    )
+}
+
+/// The names of the triggers which keep the full-text index up to date.
+const BOOKS_FTS_TRIGGERS: [&str; 9] = [
+    "books_fts_insert",
+    "books_fts_delete",
+    "books_fts_update",
+    "books_fts_authors_insert",
+    "books_fts_authors_delete",
+    "books_fts_authors_update",
+    "books_fts_variants_insert",
+    "books_fts_variants_delete",
+    "books_fts_variants_update",
+];
+
+/// Returns the statements which create the triggers in `BOOKS_FTS_TRIGGERS`.
+fn create_books_fts_triggers() -> Vec<String> {
+    let events = [
+        "AFTER INSERT ON `books`",
+        "AFTER DELETE ON `books`",
+        "AFTER UPDATE OF title ON `books`",
+        "AFTER INSERT ON `multimap_tags` WHEN new.name = 'author'",
+        "AFTER DELETE ON `multimap_tags` WHEN old.name = 'author'",
+        "AFTER UPDATE ON `multimap_tags` WHEN old.name = 'author' OR new.name = 'author'",
+        "AFTER INSERT ON `variants`",
+        "AFTER DELETE ON `variants`",
+        "AFTER UPDATE OF description, book_id ON `variants`",
+    ];
+    let bodies = [
+        fts_refresh("new.book_id"),
+        "DELETE FROM books_fts WHERE rowid = old.book_id;".to_string(),
+        fts_refresh("new.book_id"),
+        fts_refresh("new.book_id"),
+        fts_refresh("old.book_id"),
+        fts_refresh("old.book_id") + &fts_refresh("new.book_id"),
+        fts_refresh("new.book_id"),
+        fts_refresh("old.book_id"),
+        fts_refresh("old.book_id") + &fts_refresh("new.book_id"),
+    ];
+    BOOKS_FTS_TRIGGERS
+        .iter()
+        .zip(events.iter())
+        .zip(bodies.iter())
+        .map(|((name, event), body)| {
+            format!(
+                "CREATE TRIGGER IF NOT EXISTS `{}` {} BEGIN\n    {}\nEND;",
+                name, event, body
+            )
+        })
+        .collect()
+}
+
+/// Replaces the contents of the full-text index with the current text of every book.
+const REBUILD_BOOKS_FTS: [&str; 2] = [
+    "DELETE FROM books_fts;",
+    "INSERT INTO books_fts(rowid, title, authors, description)
+        SELECT book_id, title, authors, description FROM books_search;",
+];
+
+/// Returns the query used to find books with titles containing the bound search term.
+/// If FTS5 is not available, a LIKE based query is used instead.
+fn title_search_query(fts5: bool) -> &'static str {
+    if fts5 {
+        "SELECT rowid FROM books_fts WHERE books_fts MATCH 'title : ' || ? ORDER BY rowid"
+    } else {
+        "SELECT book_id FROM books WHERE title LIKE '%' || ? || '%' ORDER BY book_id"
+    }
+}
+
 #[derive(sqlx::FromRow)]
 struct BookData {
     book_id: i64,
     title: Option<String>,
     series_name: Option<String>,
     series_id: Option<f32>,
+    date_added: Option<String>,
 }
 
 #[derive(sqlx::FromRow)]
@@ -195,6 +313,7 @@ impl TryFrom<BookData> for Book {
                     index: series_id,
                 })
             },
+            date_added: bd.date_added,
             variants: Vec::with_capacity(2),
             ..Default::default()
         })
@@ -223,6 +342,7 @@ impl TryFrom<VariantData> for BookVariant {
             additional_authors: None,
             translators: None,
             description: vd.description,
+            series: None,
             id: vd.id.map(|id| u32::try_from(id).unwrap()),
             hash: {
                 let len = vd.hash.len();
@@ -237,14 +357,295 @@ impl TryFrom<VariantData> for BookVariant {
     }
 }
 
+/// The maximum lengths, in characters, of text fields of inserted books. Longer fields are
+/// truncated, so that malformed metadata (eg. a "title" containing the whole book) does not
+/// bloat the database.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FieldLimits {
+    /// The maximum length of titles, or None if titles are not truncated.
+    pub title: Option<usize>,
+    /// The maximum length of descriptions, or None if descriptions are not truncated.
+    pub description: Option<usize>,
+}
+
+impl Default for FieldLimits {
+    fn default() -> Self {
+        FieldLimits {
+            title: Some(1024),
+            description: Some(65536),
+        }
+    }
+}
+
+/// Appended to truncated fields, to indicate that text was removed.
+const TRUNCATION_INDICATOR: char = '…';
+
+/// Truncates `value` to at most `limit` characters, ending in the truncation indicator,
+/// and logs that `field` of the book at `path` exceeded the limit.
+fn truncate_field(value: &mut String, limit: usize, field: &str, path: &Path) {
+    if value.chars().count() > limit {
+        tracing::warn!(
+            "Truncating {} of {} to {} characters",
+            field,
+            path.display(),
+            limit
+        );
+        let keep = limit.saturating_sub(1);
+        let end = value
+            .char_indices()
+            .nth(keep)
+            .map_or(value.len(), |(i, _)| i);
+        value.truncate(end);
+        if limit > 0 {
+            value.push(TRUNCATION_INDICATOR);
+        }
+    }
+}
+
+impl FieldLimits {
+    /// Truncates the fields of `variant` which exceed their limits.
+    fn apply(&self, variant: &mut BookVariant) {
+        if let (Some(limit), Some(title)) = (self.title, variant.local_title.as_mut()) {
+            truncate_field(title, limit, "title", &variant.path);
+        }
+        if let (Some(limit), Some(description)) = (self.description, variant.description.as_mut()) {
+            truncate_field(description, limit, "description", &variant.path);
+        }
+    }
+}
+
+/// Returns the columns which `book1` and `book2` both have, with different values. Authors and
+/// tags are combined when merging, and do not conflict.
+fn merge_conflicts(book1: &Book, book2: &Book) -> Vec<ColumnIdentifier> {
+    book1
+        .diff(book2)
+        .into_iter()
+        .filter(|(column, left, right)| {
+            left.is_some()
+                && right.is_some()
+                && !matches!(
+                    column,
+                    ColumnIdentifier::Author | ColumnIdentifier::Tags | ColumnIdentifier::Cover
+                )
+        })
+        .map(|(column, _, _)| column)
+        .collect()
+}
+
 pub struct SQLiteDatabase {
     connection: SqlitePool,
     cache: Arc<RwLock<BookCache>>,
     path: PathBuf,
+    // Whether the SQLite library supports FTS5, and the full-text index is maintained.
+    fts5: bool,
+    field_limits: FieldLimits,
+    insert_policy: InsertPolicy,
+    tag_append: TagAppendPolicy,
+    insert_batch_size: usize,
 }
 
 // TODO: Measure performance of deletion with current changes, and check if issues are still present.
 impl SQLiteDatabase {
+    /// Sets the maximum number of books which are kept in memory. If `capacity` is None,
+    /// the number of books in memory is unbounded.
+    ///
+    /// # Arguments
+    /// * ` capacity ` - The maximum number of books to keep in memory.
+    pub async fn set_cache_capacity(&self, capacity: Option<usize>) {
+        self.cache.write().await.set_capacity(capacity);
+    }
+
+    /// Sets the maximum lengths of the text fields of books inserted after this call.
+    ///
+    /// # Arguments
+    /// * ` limits ` - The maximum lengths of each field.
+    pub fn set_field_limits(&mut self, limits: FieldLimits) {
+        self.field_limits = limits;
+    }
+
+    /// Sets how inserted variants whose files are already in the database are handled.
+    ///
+    /// # Arguments
+    /// * ` policy ` - What to do with duplicate files.
+    pub fn set_insert_policy(&mut self, policy: InsertPolicy) {
+        self.insert_policy = policy;
+    }
+
+    /// Sets how values appended to named tags are combined with the existing values.
+    ///
+    /// # Arguments
+    /// * ` policy ` - How appended values are combined with existing values.
+    pub fn set_tag_append_policy(&mut self, policy: TagAppendPolicy) {
+        self.tag_append = policy;
+    }
+
+    /// Sets the number of books inserted in each transaction by `insert_books`. Larger batches
+    /// are faster, but hold the write lock for longer.
+    ///
+    /// # Arguments
+    /// * ` size ` - The number of books in each transaction. Batches hold at least one book.
+    pub fn set_insert_batch_size(&mut self, size: usize) {
+        self.insert_batch_size = size.max(1);
+    }
+
+    /// Returns the IDs of all books with titles matching `text`, in ascending order.
+    ///
+    /// # Arguments
+    /// * ` text ` - The text to search for.
+    ///
+    /// # Errors
+    /// This function will return an error if the database fails.
+    pub async fn search_titles(
+        &self,
+        text: &str,
+    ) -> Result<Vec<BookID>, DatabaseError<<Self as AppDatabase>::Error>> {
+        let text = if self.fts5 {
+            // Quote the text, so that it is matched as a phrase rather than as FTS5 syntax.
+            format!("\"{}\"", text.replace('"', "\"\""))
+        } else {
+            text.to_string()
+        };
+        let ids: Vec<i64> = sqlx::query_scalar(title_search_query(self.fts5))
+            .bind(text)
+            .fetch_all(&self.connection)
+            .await
+            .map_err(DatabaseError::Backend)?;
+        Ok(ids
+            .into_iter()
+            .filter_map(|id| BookID::try_from(id as u64).ok())
+            .collect())
+    }
+
+    /// Copies every book from a Calibre library into this database, including its authors,
+    /// series, identifiers and formats, and returns the IDs of the new books. Books are inserted
+    /// like variants from `insert_books`, so the insert policy decides what happens to files
+    /// which are already in the database. The Calibre database is opened read-only, and is
+    /// never modified.
+    ///
+    /// # Arguments
+    /// * ` path ` - The Calibre library, or the `metadata.db` file inside of it.
+    ///
+    /// # Errors
+    /// This function will return an error if the Calibre database can not be read, or if
+    /// inserting the books fails.
+    pub async fn import_calibre<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+    ) -> Result<Vec<BookID>, DatabaseError<<Self as AppDatabase>::Error>> {
+        let path = path.as_ref();
+        let path = if path.is_dir() {
+            path.join(CALIBRE_METADATA)
+        } else {
+            path.to_path_buf()
+        };
+        let books = calibre::read_books(&path)
+            .await
+            .map_err(DatabaseError::Backend)?;
+        let ids = self
+            .insert_books_async(
+                books.into_iter(),
+                self.insert_batch_size,
+                self.insert_policy,
+            )
+            .await
+            .map_err(DatabaseError::Backend)?;
+        Ok(ids
+            .into_iter()
+            .filter_map(|(id, new)| if new { Some(id) } else { None })
+            .collect())
+    }
+
+    /// Checks whether FTS5 is available by creating a temporary FTS5 table. If it is, the
+    /// full-text index is created and kept up to date using triggers. Otherwise, the triggers
+    /// are removed so that writes do not fail, and full-text search is disabled with a warning.
+    async fn enable_full_text_search(
+        &mut self,
+    ) -> Result<(), DatabaseError<<Self as AppDatabase>::Error>> {
+        let probe =
+            sqlx::query("CREATE VIRTUAL TABLE IF NOT EXISTS temp.fts5_probe USING fts5(x);")
+                .execute(&self.connection)
+                .await;
+        if let Err(e) = probe {
+            tracing::warn!("FTS5 is not available, falling back to LIKE queries: {}", e);
+            return self.disable_full_text_search().await;
+        }
+        sqlx::query("DROP TABLE IF EXISTS temp.fts5_probe;")
+            .execute(&self.connection)
+            .await
+            .map_err(DatabaseError::Backend)?;
+
+        // Earlier versions indexed only titles, reading them from the books table.
+        let title_only: bool = sqlx::query_scalar(
+            r#"SELECT EXISTS (
+    SELECT 1 FROM sqlite_master WHERE name = 'books_fts' AND sql LIKE '%content=%'
+);"#,
+        )
+        .fetch_one(&self.connection)
+        .await
+        .map_err(DatabaseError::Backend)?;
+        if title_only {
+            tracing::info!("Replacing the full-text index of titles");
+            self.disable_full_text_search().await?;
+            sqlx::query("DROP TABLE books_fts;")
+                .execute(&self.connection)
+                .await
+                .map_err(DatabaseError::Backend)?;
+        }
+
+        let indexed: bool = sqlx::query_scalar(
+            r#"SELECT EXISTS (
+    SELECT 1 FROM sqlite_master WHERE type = 'trigger' AND name = 'books_fts_variants_update'
+);"#,
+        )
+        .fetch_one(&self.connection)
+        .await
+        .map_err(DatabaseError::Backend)?;
+
+        let mut tx = self
+            .connection
+            .begin()
+            .await
+            .map_err(DatabaseError::Backend)?;
+        for query in [CREATE_BOOKS_FTS, CREATE_BOOKS_SEARCH] {
+            sqlx::query(query)
+                .execute(&mut tx)
+                .await
+                .map_err(DatabaseError::Backend)?;
+        }
+        for trigger in create_books_fts_triggers() {
+            sqlx::query(&trigger)
+                .execute(&mut tx)
+                .await
+                .map_err(DatabaseError::Backend)?;
+        }
+        // Books may have been written while the index was not maintained.
+        if !indexed {
+            for query in REBUILD_BOOKS_FTS {
+                sqlx::query(query)
+                    .execute(&mut tx)
+                    .await
+                    .map_err(DatabaseError::Backend)?;
+            }
+        }
+        tx.commit().await.map_err(DatabaseError::Backend)?;
+        self.fts5 = true;
+        Ok(())
+    }
+
+    /// Stops maintaining the full-text index, and routes searches to LIKE based queries.
+    async fn disable_full_text_search(
+        &mut self,
+    ) -> Result<(), DatabaseError<<Self as AppDatabase>::Error>> {
+        for trigger in BOOKS_FTS_TRIGGERS {
+            sqlx::query(&format!("DROP TRIGGER IF EXISTS {};", trigger))
+                .execute(&self.connection)
+                .await
+                .map_err(DatabaseError::Backend)?;
+        }
+        self.fts5 = false;
+        Ok(())
+    }
+
     #[tracing::instrument(name = "Reading the ids of books matching the query", skip(self))]
     async fn read_book_ids(
         &self,
@@ -260,6 +661,7 @@ impl SQLiteDatabase {
         for value in bound_variables {
             query = match value {
                 Variable::Int(i) => query.bind(i),
+                Variable::Float(f) => query.bind(f),
                 Variable::Str(s) => query.bind(s),
             };
         }
@@ -279,49 +681,204 @@ impl SQLiteDatabase {
         Ok(ids)
     }
 
-    async fn deduplicate(
+    /// Merges books which are duplicates of each other, as determined by `detection`. Each
+    /// group of duplicates is merged into the book with the lowest ID. Multi-valued columns,
+    /// such as authors and tags, are combined. If both books have different values for another
+    /// column, `resolution` determines whether the books are merged.
+    ///
+    /// Returns the IDs of the books which were merged into other books, and thus removed, and
+    /// the conflicts which were not resolved, if `resolution` is `ConflictResolution::Return`.
+    ///
+    /// # Arguments
+    /// * ` detection ` - How duplicate books are found.
+    /// * ` resolution ` - What to do with duplicates which have conflicting values.
+    ///
+    /// # Errors
+    /// This function will return an error if the database fails.
+    pub async fn deduplicate(
         &mut self,
-        merges: &[(BookID, BookID)],
-    ) -> Result<(), DatabaseError<<Self as AppDatabase>::Error>> {
-        enum ConflictResolution {
-            Skip,
-            KeepOriginal,
-            Return,
+        detection: DetectionStrategy,
+        resolution: ConflictResolution,
+    ) -> Result<(HashSet<BookID>, Vec<MergeConflict>), DatabaseError<<Self as AppDatabase>::Error>>
+    {
+        let candidates = self.duplicate_pairs(detection).await?;
+
+        let mut merges = vec![];
+        let mut conflicts = vec![];
+        for (into, from) in candidates {
+            if resolution != ConflictResolution::KeepOriginal {
+                let book1 = self.get_book(into).await?;
+                let book2 = self.get_book(from).await?;
+                let conflicting = merge_conflicts(&book1, &book2);
+                if !conflicting.is_empty() {
+                    if resolution == ConflictResolution::Return {
+                        conflicts.push(MergeConflict {
+                            book1,
+                            book2,
+                            conflicts: conflicting,
+                        });
+                    }
+                    continue;
+                }
+            }
+            merges.push((into, from));
         }
 
-        enum DetectionStrategy {
-            VariantHash,
-            AuthorOverlap,
-            Title,
+        self.merge_by_ids(&merges)
+            .await
+            .map_err(DatabaseError::Backend)?;
+        // The books which were merged into must be read again, to include the new variants.
+        let kept: HashSet<_> = merges.iter().map(|(into, _)| *into).collect();
+        self.cache.write().await.remove_books(&kept);
+        let removed: HashSet<_> = merges.into_iter().map(|(_, from)| from).collect();
+        if !removed.is_empty() {
+            self.remove_books(&removed).await?;
         }
+        Ok((removed, conflicts))
+    }
+
+    /// Returns pairs of duplicate books, as determined by `detection`, in the order in which
+    /// `deduplicate` merges them. Each pair holds the book with the lowest ID of its group of
+    /// duplicates, followed by another book in the group. Each book is in at most one group.
+    ///
+    /// # Arguments
+    /// * ` detection ` - How duplicate books are found.
+    ///
+    /// # Errors
+    /// This function will return an error if the database fails.
+    async fn duplicate_pairs(
+        &self,
+        detection: DetectionStrategy,
+    ) -> Result<Vec<(BookID, BookID)>, DatabaseError<<Self as AppDatabase>::Error>> {
+        let query = match detection {
+            DetectionStrategy::VariantHash => {
+                "SELECT DISTINCT a.book_id, b.book_id FROM variants a
+                JOIN variants b ON a.file_size = b.file_size AND a.hash = b.hash
+                    AND a.book_id < b.book_id
+                ORDER BY a.book_id, b.book_id"
+            }
+            DetectionStrategy::AuthorOverlap => {
+                "SELECT DISTINCT a.book_id, b.book_id FROM books a
+                JOIN books b ON LOWER(a.title) = LOWER(b.title) AND a.book_id < b.book_id
+                JOIN multimap_tags ma ON ma.book_id = a.book_id AND ma.name = 'author'
+                JOIN multimap_tags mb ON mb.book_id = b.book_id AND mb.name = 'author'
+                    AND LOWER(ma.value) = LOWER(mb.value)
+                ORDER BY a.book_id, b.book_id"
+            }
+            DetectionStrategy::Title => {
+                "SELECT a.book_id, b.book_id FROM books a
+                JOIN books b ON LOWER(a.title) = LOWER(b.title) AND a.book_id < b.book_id
+                ORDER BY a.book_id, b.book_id"
+            }
+        };
+        let pairs: Vec<(i64, i64)> = sqlx::query_as(query)
+            .fetch_all(&self.connection)
+            .await
+            .map_err(DatabaseError::Backend)?;
 
-        struct MergeConflict {
-            book1: Arc<Book>,
-            book2: Arc<Book>,
-            conflicts: Vec<ColumnIdentifier>,
+        // Each book is merged into the lowest ID of its group, and only merged once.
+        let mut merged_into: HashMap<BookID, BookID> = HashMap::new();
+        let mut candidates = vec![];
+        for (a, b) in pairs {
+            let (a, b) = match (BookID::try_from(a as u64), BookID::try_from(b as u64)) {
+                (Ok(a), Ok(b)) => (a, b),
+                _ => continue,
+            };
+            let into = *merged_into.get(&a).unwrap_or(&a);
+            if merged_into.contains_key(&b) {
+                continue;
+            }
+            merged_into.insert(b, into);
+            candidates.push((into, b));
         }
-        let conflict = ConflictResolution::KeepOriginal;
-        let detection = DetectionStrategy::VariantHash;
+        Ok(candidates)
+    }
 
-        // let conflicts = vec![];
+    /// Finds rows of the variants, tags and covers tables which belong to a book which does not
+    /// exist, and deletes them or recreates their books, as determined by `policy`. Recreated
+    /// books are empty, other than the orphaned rows.
+    ///
+    /// # Arguments
+    /// * ` policy ` - What to do with the orphaned rows.
+    ///
+    /// # Errors
+    /// This function will return an error if the database fails.
+    #[tracing::instrument(name = "Repairing orphaned rows", skip(self))]
+    pub async fn repair(
+        &mut self,
+        policy: RepairPolicy,
+    ) -> Result<RepairReport, DatabaseError<<Self as AppDatabase>::Error>> {
+        let mut tx = self
+            .connection
+            .begin()
+            .await
+            .map_err(DatabaseError::Backend)?;
+        let mut counts = [0; ORPHAN_TABLES.len()];
+        for (table, count) in ORPHAN_TABLES.iter().zip(counts.iter_mut()) {
+            let orphans: i64 = sqlx::query_scalar(&format!(
+                "SELECT COUNT(*) FROM {} WHERE book_id NOT IN (SELECT book_id FROM books);",
+                table
+            ))
+            .fetch_one(&mut tx)
+            .await
+            .map_err(DatabaseError::Backend)?;
+            *count = orphans as usize;
+        }
 
-        let books = self
-            .read_selected_books(
-                "SELECT book_id
-            FROM `variants`
-            GROUP BY hash
-            HAVING COUNT(*) > 1",
-                &[],
-            )
-            .await?;
-        // titles, authors
-        // variant by variant: identical hashmaps
-        // SELECT hash
-        // FROM variants
-        // GROUP BY hash
-        // HAVING COUNT(*) > 1
-        // ORDER BY hash DESC;
-        Ok(())
+        let mut recreated = vec![];
+        match policy {
+            RepairPolicy::Delete => {
+                for table in ORPHAN_TABLES.iter() {
+                    sqlx::query(&format!(
+                        "DELETE FROM {} WHERE book_id NOT IN (SELECT book_id FROM books);",
+                        table
+                    ))
+                    .execute(&mut tx)
+                    .await
+                    .map_err(DatabaseError::Backend)?;
+                }
+            }
+            RepairPolicy::Recreate => {
+                let orphaned = ORPHAN_TABLES
+                    .iter()
+                    .map(|table| format!("SELECT book_id FROM {}", table))
+                    .join(" UNION ");
+                let book_ids: Vec<i64> = sqlx::query_scalar(&format!(
+                    "SELECT book_id FROM ({}) WHERE book_id NOT IN (SELECT book_id FROM books) ORDER BY book_id;",
+                    orphaned
+                ))
+                .fetch_all(&mut tx)
+                .await
+                .map_err(DatabaseError::Backend)?;
+                for book_id in book_ids {
+                    sqlx::query("INSERT INTO books (book_id) VALUES (?);")
+                        .bind(book_id)
+                        .execute(&mut tx)
+                        .await
+                        .map_err(DatabaseError::Backend)?;
+                    recreated.extend(BookID::try_from(book_id as u64).ok());
+                }
+            }
+        }
+        tx.commit().await.map_err(DatabaseError::Backend)?;
+
+        if !recreated.is_empty() {
+            self.cache
+                .write()
+                .await
+                .remove_books(&recreated.iter().copied().collect());
+            // Tags of the recreated books may add new columns.
+            self.refresh_columns().await?;
+        }
+        let [variants, named_tags, free_tags, multimap_tags, covers] = counts;
+        Ok(RepairReport {
+            variants,
+            named_tags,
+            free_tags,
+            multimap_tags,
+            covers,
+            recreated,
+        })
     }
 
     #[tracing::instrument(
@@ -331,7 +888,8 @@ impl SQLiteDatabase {
             raw_variants,
             raw_named_tags,
             raw_free_tags,
-            raw_multimap_tags
+            raw_multimap_tags,
+            raw_covers
         )
     )]
     fn books_from_sql(
@@ -340,6 +898,7 @@ impl SQLiteDatabase {
         raw_named_tags: Vec<NamedTagData>,
         raw_free_tags: Vec<FreeTagData>,
         raw_multimap_tags: Vec<NamedTagData>,
+        raw_covers: Vec<i64>,
     ) -> (Vec<(BookID, Arc<Book>)>, HashSet<UniCase<String>>) {
         let mut books: HashMap<_, _> = raw_books
             .into_iter()
@@ -415,20 +974,27 @@ impl SQLiteDatabase {
                     "author" => book
                         .extend_column(&ColumnIdentifier::Author, tag.value)
                         .expect("Extending author is infallible."),
+                    // Books do not store the values of other multimap tags, which are read
+                    // from the database when sorting.
                     name => {
                         if !prime_cols.contains(name) {
                             prime_cols.insert(name.to_string());
                         }
-                        book.extend_column(
-                            &ColumnIdentifier::MultiMap(name.to_string()),
-                            tag.value,
-                        )
-                        .expect("Extending multimap is infallible");
                     }
                 },
             }
         }
 
+        for book_id in raw_covers.into_iter() {
+            let id = BookID::try_from(book_id as u64).expect("book_id is non-null");
+            match books.get_mut(&id) {
+                None => {
+                    tracing::error!("Found orphan cover for ID {} while loading books.", id);
+                }
+                Some(book) => book.has_cover = true,
+            }
+        }
+
         (
             books.into_iter().map(|(a, b)| (a, Arc::new(b))).collect(),
             prime_cols.into_iter().map(UniCase::new).collect(),
@@ -462,7 +1028,11 @@ impl SQLiteDatabase {
             ", ?".repeat(ids.len().max(128).saturating_sub(1))
         );
         let raw_book_query = format!("SELECT * FROM books WHERE books.book_id {}", where_);
-        let raw_variant_query = format!("SELECT * FROM variants WHERE variants.book_id {}", where_);
+        // Variants are ordered by position, which determines the preferred variant.
+        let raw_variant_query = format!(
+            "SELECT * FROM variants WHERE variants.book_id {} ORDER BY position",
+            where_
+        );
         let raw_named_tag_query = format!(
             "SELECT * FROM named_tags WHERE named_tags.book_id {}",
             where_
@@ -473,12 +1043,14 @@ impl SQLiteDatabase {
             "SELECT * FROM multimap_tags WHERE multimap_tags.book_id {}",
             where_
         );
+        let raw_cover_query = format!("SELECT book_id FROM covers WHERE covers.book_id {}", where_);
 
         let mut raw_books = sqlx::query_as(&raw_book_query);
         let mut raw_variants = sqlx::query_as(&raw_variant_query);
         let mut raw_named_tags = sqlx::query_as(&raw_named_tag_query);
         let mut raw_free_tags = sqlx::query_as(&raw_free_tag_query);
         let mut raw_multimap_tags = sqlx::query_as(&raw_multimap_tag_query);
+        let mut raw_covers = sqlx::query_scalar(&raw_cover_query);
 
         for id in ids
             .iter()
@@ -491,32 +1063,36 @@ impl SQLiteDatabase {
             raw_named_tags = raw_named_tags.bind(id);
             raw_free_tags = raw_free_tags.bind(id);
             raw_multimap_tags = raw_multimap_tags.bind(id);
+            raw_covers = raw_covers.bind(id);
         }
         let raw_books = raw_books.fetch_all(&self.connection);
         let raw_variants = raw_variants.fetch_all(&self.connection);
         let raw_named_tags = raw_named_tags.fetch_all(&self.connection);
         let raw_free_tags = raw_free_tags.fetch_all(&self.connection);
         let raw_multimap_tags = raw_multimap_tags.fetch_all(&self.connection);
+        let raw_covers = raw_covers.fetch_all(&self.connection);
         tracing::info!("Reading books from database");
 
         let start = std::time::Instant::now();
 
-        let (raw_books, raw_variants, raw_named_tags, raw_free_tags, raw_multimap_tags) = tokio::join!(
+        let (raw_books, raw_variants, raw_named_tags, raw_free_tags, raw_multimap_tags, raw_covers) = tokio::join!(
             raw_books,
             raw_variants,
             raw_named_tags,
             raw_free_tags,
-            raw_multimap_tags
+            raw_multimap_tags,
+            raw_covers
         );
 
         tracing::info!("Received results");
 
-        let (raw_books, raw_variants, raw_named_tags, raw_free_tags, raw_multimap_tags) = (
+        let (raw_books, raw_variants, raw_named_tags, raw_free_tags, raw_multimap_tags, raw_covers) = (
             raw_books.map_err(DatabaseError::Backend)?,
             raw_variants.map_err(DatabaseError::Backend)?,
             raw_named_tags.map_err(DatabaseError::Backend)?,
             raw_free_tags.map_err(DatabaseError::Backend)?,
             raw_multimap_tags.map_err(DatabaseError::Backend)?,
+            raw_covers.map_err(DatabaseError::Backend)?,
         );
 
         let end = std::time::Instant::now();
@@ -533,6 +1109,7 @@ impl SQLiteDatabase {
             raw_named_tags,
             raw_free_tags,
             raw_multimap_tags,
+            raw_covers,
         );
         let end = std::time::Instant::now();
         tracing::info!("Took {}s to convert books", (end - start).as_secs_f32());
@@ -564,7 +1141,11 @@ impl SQLiteDatabase {
         };
         let where_ = format!("IN ({})", ids.iter().map(|id| id.to_string()).join(", "));
         let raw_book_query = format!("SELECT * FROM books WHERE books.book_id {}", where_);
-        let raw_variant_query = format!("SELECT * FROM variants WHERE variants.book_id {}", where_);
+        // Variants are ordered by position, which determines the preferred variant.
+        let raw_variant_query = format!(
+            "SELECT * FROM variants WHERE variants.book_id {} ORDER BY position",
+            where_
+        );
         let raw_named_tag_query = format!(
             "SELECT * FROM named_tags WHERE named_tags.book_id {}",
             where_
@@ -575,6 +1156,7 @@ impl SQLiteDatabase {
             "SELECT * FROM multimap_tags WHERE multimap_tags.book_id {}",
             where_
         );
+        let raw_cover_query = format!("SELECT book_id FROM covers WHERE covers.book_id {}", where_);
 
         let raw_books = sqlx::query_as(&raw_book_query)
             .persistent(false)
@@ -591,27 +1173,32 @@ impl SQLiteDatabase {
         let raw_multimap_tags = sqlx::query_as(&raw_multimap_tag_query)
             .persistent(false)
             .fetch_all(&self.connection);
+        let raw_covers = sqlx::query_scalar(&raw_cover_query)
+            .persistent(false)
+            .fetch_all(&self.connection);
 
         tracing::info!("Reading books from database");
 
         let start = std::time::Instant::now();
 
-        let (raw_books, raw_variants, raw_named_tags, raw_free_tags, raw_multimap_tags) = tokio::join!(
+        let (raw_books, raw_variants, raw_named_tags, raw_free_tags, raw_multimap_tags, raw_covers) = tokio::join!(
             raw_books,
             raw_variants,
             raw_named_tags,
             raw_free_tags,
-            raw_multimap_tags
+            raw_multimap_tags,
+            raw_covers
         );
 
         tracing::info!("Received results");
 
-        let (raw_books, raw_variants, raw_named_tags, raw_free_tags, raw_multimap_tags) = (
+        let (raw_books, raw_variants, raw_named_tags, raw_free_tags, raw_multimap_tags, raw_covers) = (
             raw_books.map_err(DatabaseError::Backend)?,
             raw_variants.map_err(DatabaseError::Backend)?,
             raw_named_tags.map_err(DatabaseError::Backend)?,
             raw_free_tags.map_err(DatabaseError::Backend)?,
             raw_multimap_tags.map_err(DatabaseError::Backend)?,
+            raw_covers.map_err(DatabaseError::Backend)?,
         );
 
         let end = std::time::Instant::now();
@@ -628,6 +1215,7 @@ impl SQLiteDatabase {
             raw_named_tags,
             raw_free_tags,
             raw_multimap_tags,
+            raw_covers,
         );
         let end = std::time::Instant::now();
         tracing::info!("Took {}s to convert books", (end - start).as_secs_f32());
@@ -635,34 +1223,68 @@ impl SQLiteDatabase {
         Ok((new_books, columns))
     }
 
+    /// Reads the next `STREAM_BATCH_SIZE` books with IDs greater than `last_id`, in order of ID,
+    /// and returns them with the ID of the last book read. Books are read around the cache, so
+    /// that reading every book does not evict the books which are in use.
+    ///
+    /// # Errors
+    /// This function will return an error if the database fails.
+    async fn read_books_after(
+        &self,
+        last_id: i64,
+    ) -> Result<Option<(Vec<Arc<Book>>, i64)>, DatabaseError<<SQLiteDatabase as AppDatabase>::Error>>
+    {
+        let ids = self
+            .read_book_ids(
+                "SELECT book_id FROM books WHERE book_id > ? ORDER BY book_id LIMIT ?;",
+                &[Variable::Int(last_id), Variable::Int(STREAM_BATCH_SIZE)],
+            )
+            .await?;
+        let last_id = match ids.last() {
+            None => return Ok(None),
+            Some(&id) => u64::from(id) as i64,
+        };
+        let (mut books, _) = self.read_books_from_sql(&ids).await?;
+        books.sort_unstable_by_key(|(id, _)| *id);
+        Ok(Some((
+            books.into_iter().map(|(_, book)| book).collect(),
+            last_id,
+        )))
+    }
+
     async fn load_books(
         &mut self,
     ) -> Result<(), DatabaseError<<SQLiteDatabase as AppDatabase>::Error>> {
         let raw_books =
-            sqlx::query_as!(BookData, "SELECT * FROM books").fetch_all(&self.connection);
+            sqlx::query_as::<_, BookData>("SELECT * FROM books").fetch_all(&self.connection);
         let raw_variants =
-            sqlx::query_as!(VariantData, "SELECT * FROM variants").fetch_all(&self.connection);
+            sqlx::query_as::<_, VariantData>("SELECT * FROM variants ORDER BY position")
+                .fetch_all(&self.connection);
         let raw_named_tags =
             sqlx::query_as!(NamedTagData, "SELECT * FROM named_tags").fetch_all(&self.connection);
         let raw_free_tags =
             sqlx::query_as!(FreeTagData, "SELECT * FROM free_tags").fetch_all(&self.connection);
         let raw_multimap_tags = sqlx::query_as!(NamedTagData, "SELECT * FROM multimap_tags")
             .fetch_all(&self.connection);
+        let raw_covers =
+            sqlx::query_scalar("SELECT book_id FROM covers").fetch_all(&self.connection);
 
-        let (raw_books, raw_variants, raw_named_tags, raw_free_tags, raw_multimap_tags) = tokio::join!(
+        let (raw_books, raw_variants, raw_named_tags, raw_free_tags, raw_multimap_tags, raw_covers) = tokio::join!(
             raw_books,
             raw_variants,
             raw_named_tags,
             raw_free_tags,
-            raw_multimap_tags
+            raw_multimap_tags,
+            raw_covers
         );
 
-        let (raw_books, raw_variants, raw_named_tags, raw_free_tags, raw_multimap_tags) = (
+        let (raw_books, raw_variants, raw_named_tags, raw_free_tags, raw_multimap_tags, raw_covers) = (
             raw_books.map_err(DatabaseError::Backend)?,
             raw_variants.map_err(DatabaseError::Backend)?,
             raw_named_tags.map_err(DatabaseError::Backend)?,
             raw_free_tags.map_err(DatabaseError::Backend)?,
             raw_multimap_tags.map_err(DatabaseError::Backend)?,
+            raw_covers.map_err(DatabaseError::Backend)?,
         );
 
         let (books, columns) = SQLiteDatabase::books_from_sql(
@@ -671,27 +1293,64 @@ impl SQLiteDatabase {
             raw_named_tags,
             raw_free_tags,
             raw_multimap_tags,
+            raw_covers,
         );
 
+        let capacity = self.cache.read().await.capacity();
         self.cache = Arc::new(RwLock::new(BookCache::from_values_unchecked(
             books.into_iter().collect(),
             columns,
+            capacity,
         )));
         Ok(())
     }
 }
 
+/// Returns true if any of the edits may add or remove a named column.
+fn edits_named_columns(edits: &[(ColumnIdentifier, Edit)]) -> bool {
+    edits.iter().any(|(column, _)| {
+        matches!(
+            column,
+            ColumnIdentifier::NamedTag(_)
+                | ColumnIdentifier::ReadStatus
+                | ColumnIdentifier::MultiMap(_)
+                | ColumnIdentifier::MultiMapExact(_, _)
+        )
+    })
+}
+
+/// Returns the condition which selects the variants edited under `policy`, for the book
+/// whose ID is bound to the condition.
+fn variant_condition(policy: VariantEditPolicy) -> &'static str {
+    match policy {
+        VariantEditPolicy::AllVariants => "book_id = ?",
+        VariantEditPolicy::PrimaryOnly => {
+            "rowid = (SELECT rowid FROM variants WHERE book_id = ? ORDER BY position LIMIT 1)"
+        }
+    }
+}
+
 #[tracing::instrument(name="Modifying book", skip(tx, book, edits), fields(id = %book.id()))]
 async fn edit_book<'a>(
     mut tx: Transaction<'a, Sqlite>,
     book: &mut Book,
     edits: &[(ColumnIdentifier, Edit)],
+    policy: VariantEditPolicy,
+    tag_append: &TagAppendPolicy,
 ) -> Result<Transaction<'a, Sqlite>, DatabaseError<<SQLiteDatabase as AppDatabase>::Error>> {
     let id = book.id();
     let book_id = u64::from(id) as i64;
     for (column, edit) in edits {
-        if matches!(column, ColumnIdentifier::ID | ColumnIdentifier::Variants) {
-            tracing::error!("Attempted to edit immutable field (one of ID, Variants)");
+        if matches!(
+            column,
+            ColumnIdentifier::ID
+                | ColumnIdentifier::Variants
+                | ColumnIdentifier::Cover
+                | ColumnIdentifier::DateAdded
+        ) {
+            tracing::error!(
+                "Attempted to edit immutable field (one of ID, Variants, Cover, Added)"
+            );
             continue;
         }
 
@@ -708,32 +1367,125 @@ async fn edit_book<'a>(
             }
         };
 
-        match &edit {
-            BEdit::Delete => {
-                match column {
-                    ColumnIdentifier::Title => {
-                        sqlx::query!("UPDATE books SET title = null WHERE book_id = ?;", book_id)
-                    }
-                    ColumnIdentifier::Author => sqlx::query!(
-                        "DELETE FROM multimap_tags where book_id = ? AND name = 'author';",
-                        book_id
-                    ),
-                    ColumnIdentifier::ID => unreachable!(),
-                    ColumnIdentifier::Series => sqlx::query!(
-                        "UPDATE books SET series_name = null, series_id = null WHERE book_id = ?;",
-                        book_id
-                    ),
-                    ColumnIdentifier::Variants => unreachable!(),
-                    ColumnIdentifier::Description => sqlx::query!(
-                        "UPDATE variants SET description = null WHERE book_id = ?;",
-                        book_id
-                    ),
-                    ColumnIdentifier::NamedTag(column) => {
-                        sqlx::query!(
-                            "DELETE FROM named_tags where book_id = ? and name = ?;",
-                            book_id,
-                            column
-                        )
+        let edit = match (edit, column) {
+            (BEdit::Append(value), ColumnIdentifier::NamedTag(name))
+                if matches!(tag_append, TagAppendPolicy::Idempotent(_)) =>
+            {
+                let current = book.named_tags.get(name).map(String::as_str);
+                match tag_append.append(current, &value) {
+                    Some(value) => BEdit::Replace(value),
+                    None => continue,
+                }
+            }
+            (edit, _) => edit,
+        };
+
+        // The read status is stored as a named tag, which only holds the known statuses.
+        if matches!(column, ColumnIdentifier::ReadStatus) {
+            let edit = match edit {
+                BEdit::Delete => {
+                    sqlx::query("DELETE FROM named_tags WHERE book_id = ? AND name = ?;")
+                        .bind(book_id)
+                        .bind(READ_STATUS_TAG)
+                        .execute(&mut tx)
+                        .await
+                        .map_err(DatabaseError::Backend)?;
+                    BEdit::Delete
+                }
+                BEdit::Replace(value) => {
+                    let status = read_status(&value)
+                        .ok_or(DatabaseError::Record(RecordError::InvalidValue(value)))?;
+                    sqlx::query(
+                        "INSERT OR REPLACE INTO named_tags (name, value, book_id) VALUES(?, ?, ?);",
+                    )
+                    .bind(READ_STATUS_TAG)
+                    .bind(status)
+                    .bind(book_id)
+                    .execute(&mut tx)
+                    .await
+                    .map_err(DatabaseError::Backend)?;
+                    BEdit::Replace(status.to_string())
+                }
+                BEdit::Append(_) => {
+                    return Err(DatabaseError::Record(RecordError::InextensibleColumn))
+                }
+            };
+            book.edit_column(column, edit)
+                .map_err(DatabaseError::Record)?;
+            continue;
+        }
+
+        // Authors are stored as a multimap tag.
+        let multimap = match column {
+            ColumnIdentifier::Author => Some((AUTHOR_MULTIMAP, None)),
+            ColumnIdentifier::MultiMap(name) => Some((name.as_str(), None)),
+            ColumnIdentifier::MultiMapExact(name, value) => {
+                Some((name.as_str(), Some(value.as_str())))
+            }
+            _ => None,
+        };
+        if let Some((name, value)) = multimap {
+            let edit = if name == AUTHOR_MULTIMAP {
+                normalize_author_edit(value, edit)
+            } else {
+                edit
+            };
+            edit_multimap(&mut tx, book_id, name, value, &edit)
+                .await
+                .map_err(DatabaseError::Backend)?;
+            book.edit_column(column, edit)
+                .map_err(DatabaseError::Record)?;
+            continue;
+        }
+
+        // Descriptions are stored per variant, so the policy decides which are edited.
+        if matches!(column, ColumnIdentifier::Description) {
+            let update = match &edit {
+                BEdit::Delete => "description = null",
+                BEdit::Replace(_) => "description = ?",
+                BEdit::Append(_) => "description = description || ?",
+            };
+            let sql = format!(
+                "UPDATE variants SET {} WHERE {};",
+                update,
+                variant_condition(policy)
+            );
+            let mut query = sqlx::query(&sql);
+            if let BEdit::Replace(value) | BEdit::Append(value) = &edit {
+                query = query.bind(value.as_str());
+            }
+            query
+                .bind(book_id)
+                .execute(&mut tx)
+                .await
+                .map_err(DatabaseError::Backend)?;
+            book.edit_column(column, edit)
+                .map_err(DatabaseError::Record)?;
+            continue;
+        }
+
+        match &edit {
+            BEdit::Delete => {
+                match column {
+                    ColumnIdentifier::Title => {
+                        sqlx::query!("UPDATE books SET title = null WHERE book_id = ?;", book_id)
+                    }
+                    ColumnIdentifier::ID => unreachable!(),
+                    ColumnIdentifier::Series => sqlx::query!(
+                        "UPDATE books SET series_name = null, series_id = null WHERE book_id = ?;",
+                        book_id
+                    ),
+                    ColumnIdentifier::Variants
+                    | ColumnIdentifier::Cover
+                    | ColumnIdentifier::DateAdded
+                    | ColumnIdentifier::ReadStatus => unreachable!(),
+                    ColumnIdentifier::Description => unreachable!(),
+                    ColumnIdentifier::NamedTag(column) => {
+                        sqlx::query!(
+                            "DELETE FROM named_tags where book_id = ? and name = ?;",
+                            book_id,
+                            column
+                        )
                         .execute(&mut tx)
                         .await
                         .map_err(DatabaseError::Backend)?;
@@ -750,9 +1502,9 @@ async fn edit_book<'a>(
                         .map_err(DatabaseError::Backend)?;
                         continue;
                     }
-                    ColumnIdentifier::MultiMap(_) | ColumnIdentifier::MultiMapExact(_, _) => {
-                        unimplemented!("Deleting multimap tags not supported.")
-                    }
+                    ColumnIdentifier::Author
+                    | ColumnIdentifier::MultiMap(_)
+                    | ColumnIdentifier::MultiMapExact(_, _) => unreachable!(),
                     ColumnIdentifier::Tags => {
                         sqlx::query!("DELETE FROM free_tags where book_id = ?;", book_id,)
                     }
@@ -772,16 +1524,6 @@ async fn edit_book<'a>(
                     .await
                     .map_err(DatabaseError::Backend)?;
                 }
-                ColumnIdentifier::Author => {
-                    sqlx::query!(
-                        "INSERT INTO multimap_tags (name, value, book_id) VALUES('author', ?, ?);",
-                        value,
-                        book_id
-                    )
-                    .execute(&mut tx)
-                    .await
-                    .map_err(DatabaseError::Backend)?;
-                }
                 ColumnIdentifier::Series => {
                     let series = Series::from_str(value).ok();
                     let (series, series_index) = match series {
@@ -799,17 +1541,11 @@ async fn edit_book<'a>(
                     .map_err(DatabaseError::Backend)?;
                 }
                 ColumnIdentifier::ID => unreachable!(),
-                ColumnIdentifier::Variants => unreachable!(),
-                ColumnIdentifier::Description => {
-                    sqlx::query!(
-                        "UPDATE variants SET description = ? WHERE book_id = ?",
-                        value,
-                        book_id
-                    )
-                    .execute(&mut tx)
-                    .await
-                    .map_err(DatabaseError::Backend)?;
-                }
+                ColumnIdentifier::Variants
+                | ColumnIdentifier::Cover
+                | ColumnIdentifier::DateAdded
+                | ColumnIdentifier::ReadStatus => unreachable!(),
+                ColumnIdentifier::Description => unreachable!(),
                 ColumnIdentifier::Tags => {
                     sqlx::query!(
                         "INSERT into free_tags (value, book_id) VALUES(?, ?)",
@@ -843,9 +1579,9 @@ async fn edit_book<'a>(
                             .await
                             .map_err(DatabaseError::Backend)?;
                 }
-                ColumnIdentifier::MultiMap(_) | ColumnIdentifier::MultiMapExact(_, _) => {
-                    unimplemented!("Replacing multimap tags not supported.")
-                }
+                ColumnIdentifier::Author
+                | ColumnIdentifier::MultiMap(_)
+                | ColumnIdentifier::MultiMapExact(_, _) => unreachable!(),
             },
             BEdit::Append(value) => match column {
                 ColumnIdentifier::Title => {
@@ -858,31 +1594,15 @@ async fn edit_book<'a>(
                     .await
                     .map_err(DatabaseError::Backend)?;
                 }
-                ColumnIdentifier::Author => {
-                    sqlx::query!(
-                        "INSERT INTO multimap_tags (name, value, book_id) VALUES('author', ?, ?);",
-                        value,
-                        book_id
-                    )
-                    .execute(&mut tx)
-                    .await
-                    .map_err(DatabaseError::Backend)?;
-                }
                 ColumnIdentifier::Series => {
                     unreachable!("book should reject concatenating to series");
                 }
                 ColumnIdentifier::ID => unreachable!(),
-                ColumnIdentifier::Variants => unreachable!(),
-                ColumnIdentifier::Description => {
-                    sqlx::query!(
-                        "UPDATE variants SET description = description || ? WHERE book_id = ?",
-                        value,
-                        book_id
-                    )
-                    .execute(&mut tx)
-                    .await
-                    .map_err(DatabaseError::Backend)?;
-                }
+                ColumnIdentifier::Variants
+                | ColumnIdentifier::Cover
+                | ColumnIdentifier::DateAdded
+                | ColumnIdentifier::ReadStatus => unreachable!(),
+                ColumnIdentifier::Description => unreachable!(),
                 ColumnIdentifier::Tags => {
                     sqlx::query!(
                         "INSERT into free_tags (value, book_id) VALUES(?, ?)",
@@ -920,9 +1640,9 @@ async fn edit_book<'a>(
                             .await
                             .map_err(DatabaseError::Backend)?;
                 }
-                ColumnIdentifier::MultiMap(_) | ColumnIdentifier::MultiMapExact(_, _) => {
-                    unimplemented!("Appending to multimap tags not supported.")
-                }
+                ColumnIdentifier::Author
+                | ColumnIdentifier::MultiMap(_)
+                | ColumnIdentifier::MultiMapExact(_, _) => unreachable!(),
             },
         }
 
@@ -932,12 +1652,262 @@ async fn edit_book<'a>(
     Ok(tx)
 }
 
+/// Rewrites `authors` in the canonical "First Last" form, so that the same author is not stored
+/// under two names, and removes authors which are then repeated.
+fn normalize_authors(authors: &mut Vec<String>) {
+    let mut seen = HashSet::new();
+    *authors = std::mem::take(authors)
+        .into_iter()
+        .map(|name| author::normalize(&name))
+        .filter(|name| seen.insert(name.clone()))
+        .collect();
+}
+
+/// Returns `edit` with the author it sets in the canonical "First Last" form, so that editing
+/// authors does not store the same author under two names. Appending to the author `value`
+/// replaces it with the normalized result.
+fn normalize_author_edit(value: Option<&str>, edit: BEdit) -> BEdit {
+    match (value, edit) {
+        (_, BEdit::Delete) => BEdit::Delete,
+        (_, BEdit::Replace(name)) => BEdit::Replace(author::normalize(&name)),
+        (None, BEdit::Append(name)) => BEdit::Append(author::normalize(&name)),
+        (Some(value), BEdit::Append(suffix)) => {
+            BEdit::Replace(author::normalize(&format!("{}{}", value, suffix)))
+        }
+    }
+}
+
+/// Applies `edit` to the values of the multimap tag `name` of the book with the given ID. If
+/// `value` is provided, only that value is edited - it is removed by deletes, and replaced by
+/// the new value, or by itself followed by the appended text, keeping its position among the
+/// other values. Otherwise, deletes remove every value, replacing sets the only value, and
+/// appending adds a value. Values which the book already has are not added twice.
+async fn edit_multimap(
+    tx: &mut Transaction<'_, Sqlite>,
+    book_id: i64,
+    name: &str,
+    value: Option<&str>,
+    edit: &BEdit,
+) -> Result<(), sqlx::Error> {
+    let (value, new_value) = match (value, edit) {
+        (None, BEdit::Delete) | (None, BEdit::Replace(_)) => {
+            sqlx::query("DELETE FROM multimap_tags WHERE book_id = ? AND name = ?;")
+                .bind(book_id)
+                .bind(name)
+                .execute(&mut *tx)
+                .await?;
+            match edit {
+                BEdit::Replace(new_value) => (None, new_value.clone()),
+                _ => return Ok(()),
+            }
+        }
+        (None, BEdit::Append(new_value)) => (None, new_value.clone()),
+        (Some(value), BEdit::Delete) => {
+            sqlx::query("DELETE FROM multimap_tags WHERE book_id = ? AND name = ? AND value = ?;")
+                .bind(book_id)
+                .bind(name)
+                .bind(value)
+                .execute(&mut *tx)
+                .await?;
+            return Ok(());
+        }
+        (Some(value), BEdit::Replace(new_value)) => (Some(value), new_value.clone()),
+        (Some(value), BEdit::Append(suffix)) => (Some(value), format!("{}{}", value, suffix)),
+    };
+
+    if let Some(value) = value {
+        if value == new_value {
+            return Ok(());
+        }
+        // Updating the row in place keeps the order of the values - if the book already has
+        // the new value, the update is ignored and the old value is removed instead.
+        sqlx::query(
+            "UPDATE OR IGNORE multimap_tags SET value = ? WHERE book_id = ? AND name = ? AND value = ?;",
+        )
+        .bind(&new_value)
+        .bind(book_id)
+        .bind(name)
+        .bind(value)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("DELETE FROM multimap_tags WHERE book_id = ? AND name = ? AND value = ?;")
+            .bind(book_id)
+            .bind(name)
+            .bind(value)
+            .execute(&mut *tx)
+            .await?;
+    }
+    sqlx::query("INSERT OR IGNORE INTO multimap_tags (name, value, book_id) VALUES(?, ?, ?);")
+        .bind(name)
+        .bind(&new_value)
+        .bind(book_id)
+        .execute(&mut *tx)
+        .await?;
+    Ok(())
+}
+
+/// Inserts the variants, tags and authors of `book` under the book with the given ID.
+async fn insert_book_contents(
+    tx: &mut Transaction<'_, Sqlite>,
+    book_id: i64,
+    book: &Book,
+) -> Result<(), sqlx::Error> {
+    for variant in book.variants() {
+        insert_variant(tx, book_id, variant).await?;
+    }
+    for value in book.free_tags() {
+        sqlx::query("INSERT INTO free_tags (value, book_id) VALUES(?, ?);")
+            .bind(value)
+            .bind(book_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    for (name, value) in book.tags() {
+        sqlx::query("INSERT INTO named_tags (name, value, book_id) VALUES(?, ?, ?);")
+            .bind(name.to_lowercase())
+            .bind(value)
+            .bind(book_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    for name in book.authors().unwrap_or_default() {
+        // Authors are stored as "First Last", as they are when books are inserted.
+        sqlx::query(
+            "INSERT OR IGNORE INTO multimap_tags (name, value, book_id) VALUES('author', ?, ?);",
+        )
+        .bind(author::normalize(name))
+        .bind(book_id)
+        .execute(&mut *tx)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Returns the variants of `book`, with the title, authors, series, description and tags of
+/// the book moved onto the first variant, which they are taken from when the book is inserted.
+fn book_variants(book: &Book) -> Vec<BookVariant> {
+    let mut variants = book.variants().to_vec();
+    if let Some(first) = variants.first_mut() {
+        first.local_title = book.title.clone();
+        first.additional_authors = book.authors.clone();
+        first.series = book.series.clone();
+        if first.description.is_none() {
+            first.description = book.description.clone();
+        }
+        first.free_tags = book.free_tags.clone();
+        first.named_tags = book.named_tags.clone();
+    }
+    variants
+}
+
+/// Returns the ID of a book with a variant which has the same file size and hash as `variant`.
+async fn find_variant_book(
+    tx: &mut Transaction<'_, Sqlite>,
+    variant: &BookVariant,
+) -> Result<Option<BookID>, sqlx::Error> {
+    let existing: Option<i64> =
+        sqlx::query_scalar("SELECT book_id FROM variants WHERE file_size = ? AND hash = ? LIMIT 1")
+            .bind(variant.file_size as i64)
+            .bind(variant.hash.to_vec())
+            .fetch_optional(&mut *tx)
+            .await?;
+    Ok(existing.map(|id| {
+        BookID::try_from(id as u64)
+            .expect("SQLite database should never return NULL ID from primary key.")
+    }))
+}
+
+/// Inserts `variant` into the variants table, as the last variant of the book with the given ID.
+async fn insert_variant(
+    tx: &mut Transaction<'_, Sqlite>,
+    book_id: i64,
+    variant: &BookVariant,
+) -> Result<(), sqlx::Error> {
+    let book_type =
+        ron::to_string(variant.book_type()).expect("Serialization of value should never fail.");
+    #[cfg(unix)]
+    let path = variant.path().as_os_str().as_bytes();
+    #[cfg(windows)]
+    let path = v16_to_v8(variant.path().as_os_str().encode_wide().collect());
+    let local_title = &variant.local_title;
+    let identifier = variant
+        .identifier
+        .as_ref()
+        .map(|i| ron::to_string(i).expect("Serialization of value should never fail."));
+    let language = &variant.language;
+    let description = &variant.description;
+    let sub_id = &variant.id;
+    let hash = variant.hash.to_vec();
+    let file_size = variant.file_size as i64;
+    sqlx::query(
+        "INSERT into variants (book_type, path, local_title, identifier, language, description, id, hash, file_size, book_id, position) \
+        VALUES(?, ?, ?, ?, ?, ?, ?, ?, ?, ?, (SELECT COALESCE(MAX(position) + 1, 0) FROM variants WHERE book_id = ?))",
+    )
+    .bind(book_type)
+    .bind(path)
+    .bind(local_title)
+    .bind(identifier)
+    .bind(language)
+    .bind(description)
+    .bind(sub_id)
+    .bind(hash)
+    .bind(file_size)
+    .bind(book_id)
+    .bind(book_id)
+    .execute(&mut *tx)
+    .await?;
+    Ok(())
+}
+
 impl SQLiteDatabase {
-    async fn insert_books_async<I: Iterator<Item = BookVariant> + Send>(
+    /// Prepares `variant` to be written, by truncating its fields, normalizing its authors and
+    /// lowercasing its tag names, as they are when parsing columns.
+    fn prepare_variant(&self, mut variant: BookVariant) -> BookVariant {
+        self.field_limits.apply(&mut variant);
+        if let Some(authors) = variant.additional_authors.as_mut() {
+            normalize_authors(authors);
+        }
+        if variant
+            .named_tags
+            .keys()
+            .any(|name| name.to_lowercase() != *name)
+        {
+            variant.named_tags = std::mem::take(&mut variant.named_tags)
+                .into_iter()
+                .map(|(name, value)| (name.to_lowercase(), value))
+                .collect();
+        }
+        variant
+    }
+
+    /// Adds `variant` to the book with the given ID, and to the cached copy of the book.
+    async fn attach_variant(
+        &self,
+        tx: &mut Transaction<'_, Sqlite>,
+        id: BookID,
+        variant: BookVariant,
+    ) -> Result<(), sqlx::Error> {
+        insert_variant(tx, u64::from(id) as i64, &variant).await?;
+        let mut cache = self.cache.write().await;
+        if let Some(Some(book)) = cache.get_books(&[id]).pop() {
+            let mut book = Book::clone(&book);
+            book.push_variant(variant);
+            cache.insert_book(Arc::new(book));
+        }
+        Ok(())
+    }
+
+    /// Inserts the books as determined by `policy`, committing every `transaction_size`
+    /// books. Each book is given as its variants - the first variant decides which book
+    /// holds them, and provides the title, authors and tags of new books. Returns the ID of
+    /// the book which holds each group of variants, in order, and whether the book was
+    /// created for it.
+    async fn insert_books_async<I: Iterator<Item = Vec<BookVariant>> + Send>(
         &mut self,
         books: I,
         transaction_size: usize,
-    ) -> Result<Vec<BookID>, <Self as AppDatabase>::Error> {
+        policy: InsertPolicy,
+    ) -> Result<Vec<(BookID, bool)>, <Self as AppDatabase>::Error> {
         let mut book_iter = books.into_iter().peekable();
         let mut ids = Vec::with_capacity({
             let (low, high) = book_iter.size_hint();
@@ -946,101 +1916,269 @@ impl SQLiteDatabase {
 
         while book_iter.peek().is_some() {
             let mut tx = self.connection.begin().await?;
-            for variant in book_iter.by_ref().take(transaction_size) {
-                let variant: BookVariant = variant;
-                let title = variant.local_title.as_ref();
-                // let (series, series_index) = (None, None);
-                // match book.get_series() {
-                //     None => (None, None),
-                //     Some(series) => (Some(&series.name), series.index.clone()),
-                // };
-
-                // let id = sqlx::query!(
-                //     "INSERT into books (title, series_name, series_id) VALUES(?, ?, ?)",
-                //     title,
-                //     series,
-                //     series_index
-                // )
-                // .execute(&mut tx)
-                // .await?
-                // .last_insert_rowid();
-
-                let id = sqlx::query!("INSERT into books (title) VALUES(?)", title)
+            // Books inserted together share a date, which is also kept in the cache.
+            let date_added: String = sqlx::query_scalar("SELECT CURRENT_TIMESTAMP;")
+                .fetch_one(&mut tx)
+                .await?;
+            for variants in book_iter.by_ref().take(transaction_size) {
+                let mut variants = variants.into_iter();
+                let variant = match variants.next() {
+                    Some(variant) => self.prepare_variant(variant),
+                    None => continue,
+                };
+
+                let existing = if policy != InsertPolicy::AlwaysNew {
+                    find_variant_book(&mut tx, &variant).await?
+                } else {
+                    None
+                };
+                let (id, new) = if let Some(id) = existing {
+                    if policy == InsertPolicy::AttachVariant {
+                        tracing::info!(
+                            "Attaching {} to existing book {}",
+                            variant.path.display(),
+                            id
+                        );
+                        self.attach_variant(&mut tx, id, variant).await?;
+                    } else {
+                        tracing::info!(
+                            "Skipping {}, which is already in book {}",
+                            variant.path.display(),
+                            id
+                        );
+                    }
+                    (id, false)
+                } else {
+                    let (series_name, series_index) = match &variant.series {
+                        Some(series) => (Some(series.name.as_str()), series.index),
+                        None => (None, None),
+                    };
+                    let id = sqlx::query(
+                        "INSERT INTO books (title, series_name, series_id, date_added) VALUES(?, ?, ?, ?);",
+                    )
+                    .bind(variant.local_title.as_deref())
+                    .bind(series_name)
+                    .bind(series_index)
+                    .bind(date_added.as_str())
                     .execute(&mut tx)
                     .await?
                     .last_insert_rowid();
 
-                let book_type = ron::to_string(variant.book_type())
-                    .expect("Serialization of value should never fail.");
-                #[cfg(unix)]
-                let path = variant.path().as_os_str().as_bytes();
-                #[cfg(windows)]
-                let path = v16_to_v8(variant.path().as_os_str().encode_wide().collect());
-                let local_title = &variant.local_title;
-                let identifier = variant
-                    .identifier
-                    .as_ref()
-                    .map(|i| ron::to_string(i).expect("Serialization of value should never fail."));
-                let language = &variant.language;
-                let description = &variant.description;
-                let sub_id = &variant.id;
-                let hash = variant.hash.to_vec();
-                let file_size = variant.file_size as i64;
-                sqlx::query!(
-                    "INSERT into variants (book_type, path, local_title, identifier, language, description, id, hash, file_size, book_id) VALUES(?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
-                    book_type,
-                    path,
-                    local_title,
-                    identifier,
-                    language,
-                    description,
-                    sub_id,
-                    hash,
-                    file_size,
-                    id,
-                ).execute(&mut tx).await?;
+                    insert_variant(&mut tx, id, &variant).await?;
 
-                for value in variant.free_tags.iter() {
-                    sqlx::query!(
-                        "INSERT INTO free_tags (value, book_id) VALUES(?, ?);",
-                        value,
-                        id
-                    )
-                    .execute(&mut tx)
-                    .await?;
-                }
+                    for value in variant.free_tags.iter() {
+                        sqlx::query!(
+                            "INSERT INTO free_tags (value, book_id) VALUES(?, ?);",
+                            value,
+                            id
+                        )
+                        .execute(&mut tx)
+                        .await?;
+                    }
 
-                for (name, value) in variant.named_tags.iter() {
-                    sqlx::query!(
-                        "INSERT INTO named_tags (name, value, book_id) VALUES(?, ?, ?);",
-                        name,
-                        value,
-                        id
-                    )
-                    .execute(&mut tx)
-                    .await?;
-                }
+                    for (name, value) in variant.named_tags.iter() {
+                        sqlx::query!(
+                            "INSERT INTO named_tags (name, value, book_id) VALUES(?, ?, ?);",
+                            name,
+                            value,
+                            id
+                        )
+                        .execute(&mut tx)
+                        .await?;
+                    }
 
-                if let Some(authors) = &variant.additional_authors {
-                    for author in authors {
-                        sqlx::query!("INSERT INTO multimap_tags (name, value, book_id) VALUES(\"author\", ?, ?);", author, id).execute(&mut tx).await?;
+                    if let Some(authors) = &variant.additional_authors {
+                        for author in authors {
+                            sqlx::query!("INSERT INTO multimap_tags (name, value, book_id) VALUES(\"author\", ?, ?);", author, id).execute(&mut tx).await?;
+                        }
                     }
-                }
 
-                let id = BookID::try_from(id as u64)
-                    .expect("SQLite database should never return NULL ID from primary key.");
-                self.cache
-                    .write()
-                    .await
-                    .insert_book(Arc::new(Book::from_variant(id, variant)));
+                    let id = BookID::try_from(id as u64)
+                        .expect("SQLite database should never return NULL ID from primary key.");
+                    let mut book = Book::from_variant(id, variant);
+                    book.date_added = Some(date_added.clone());
+                    self.cache.write().await.insert_book(Arc::new(book));
+                    (id, true)
+                };
 
-                ids.push(id);
+                // The remaining variants are added to the same book, unless they are duplicates
+                // which should be skipped.
+                for variant in variants {
+                    let variant = self.prepare_variant(variant);
+                    if policy == InsertPolicy::SkipDuplicates {
+                        if let Some(existing) = find_variant_book(&mut tx, &variant).await? {
+                            tracing::info!(
+                                "Skipping {}, which is already in book {}",
+                                variant.path.display(),
+                                existing
+                            );
+                            continue;
+                        }
+                    }
+                    self.attach_variant(&mut tx, id, variant).await?;
+                }
+                ids.push((id, new));
             }
             tx.commit().await?;
         }
         Ok(ids)
     }
 
+    /// Adds the `date_added` column to databases created before it existed. SQLite does not
+    /// allow columns to be added with a default of `CURRENT_TIMESTAMP`, so existing books
+    /// have no date, and new books are given their date explicitly.
+    async fn add_date_added(&self) -> Result<(), sqlx::Error> {
+        let exists: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM pragma_table_info('books') WHERE name = 'date_added');",
+        )
+        .fetch_one(&self.connection)
+        .await?;
+        if !exists {
+            tracing::info!("Adding date_added column to books");
+            sqlx::query("ALTER TABLE books ADD COLUMN date_added TEXT DEFAULT NULL;")
+                .execute(&self.connection)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Adds the `position` column, which orders the variants of each book, to databases created
+    /// before it existed. Variants were previously ordered by rowid, which is kept as their order.
+    async fn add_variant_positions(&self) -> Result<(), sqlx::Error> {
+        let exists: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM pragma_table_info('variants') WHERE name = 'position');",
+        )
+        .fetch_one(&self.connection)
+        .await?;
+        if !exists {
+            tracing::info!("Adding position column to variants");
+            let mut tx = self.connection.begin().await?;
+            sqlx::query("ALTER TABLE variants ADD COLUMN position INTEGER NOT NULL DEFAULT 0;")
+                .execute(&mut tx)
+                .await?;
+            sqlx::query(
+                "UPDATE variants SET position = (SELECT COUNT(*) FROM variants AS earlier \
+                WHERE earlier.book_id = variants.book_id AND earlier.rowid < variants.rowid);",
+            )
+            .execute(&mut tx)
+            .await?;
+            tx.commit().await?;
+        }
+        Ok(())
+    }
+
+    /// Warns if books have a named tag called `read`, which can not be reached by name, as the
+    /// `read` column refers to the read status of books. The read status is stored under
+    /// `READ_STATUS_TAG` instead, so such tags are left as they are.
+    async fn warn_hidden_read_tags(&self) -> Result<(), sqlx::Error> {
+        let hidden: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM named_tags WHERE name = 'read';")
+                .fetch_one(&self.connection)
+                .await?;
+        if hidden > 0 {
+            tracing::warn!(
+                "{} books have a \"read\" tag, which is hidden by the read status column",
+                hidden
+            );
+        }
+        Ok(())
+    }
+
+    /// Lowercases the names of named and multimap tags, which earlier versions stored as they
+    /// were typed. Column names are lowercased when they are parsed, so tags with other names
+    /// could not be reached. Named tags which a book already has under the lowercase name are
+    /// kept as they are, with a warning, while multimap values are only duplicates and are
+    /// removed.
+    async fn lowercase_tag_names(&self) -> Result<(), sqlx::Error> {
+        let mut tx = self.connection.begin().await?;
+        for table in ["named_tags", "multimap_tags"] {
+            let names: Vec<String> =
+                sqlx::query_scalar(&format!("SELECT DISTINCT name FROM {};", table))
+                    .fetch_all(&mut tx)
+                    .await?;
+            for name in names {
+                let lowercase = name.to_lowercase();
+                if lowercase == name {
+                    continue;
+                }
+                tracing::info!("Renaming {} {:?} to {:?}", table, name, lowercase);
+                sqlx::query(&format!(
+                    "UPDATE OR IGNORE {} SET name = ? WHERE name = ?;",
+                    table
+                ))
+                .bind(&lowercase)
+                .bind(&name)
+                .execute(&mut tx)
+                .await?;
+                if table == "multimap_tags" {
+                    sqlx::query("DELETE FROM multimap_tags WHERE name = ?;")
+                        .bind(&name)
+                        .execute(&mut tx)
+                        .await?;
+                    continue;
+                }
+                let conflicts: i64 =
+                    sqlx::query_scalar("SELECT COUNT(*) FROM named_tags WHERE name = ?;")
+                        .bind(&name)
+                        .fetch_one(&mut tx)
+                        .await?;
+                if conflicts > 0 {
+                    tracing::warn!(
+                        "{} books have both {:?} and {:?} tags - {:?} was not renamed",
+                        conflicts,
+                        name,
+                        lowercase,
+                        name
+                    );
+                }
+            }
+        }
+        tx.commit().await
+    }
+
+    /// Returns whether the space used by removed books is returned to the file system, which
+    /// is the case for databases created by `open`.
+    async fn incremental_vacuum_enabled(&self) -> Result<bool, sqlx::Error> {
+        let auto_vacuum: i64 = sqlx::query_scalar("PRAGMA auto_vacuum;")
+            .fetch_one(&self.connection)
+            .await?;
+        // 2 is INCREMENTAL.
+        Ok(auto_vacuum == 2)
+    }
+
+    /// Allows the space used by removed books to be returned to the file system, without
+    /// rebuilding the whole database. Databases created before this was the default are
+    /// rebuilt once, which may take a while for large databases. Returns whether the database
+    /// was rebuilt.
+    ///
+    /// # Errors
+    /// This function will return an error if the database fails.
+    #[tracing::instrument(name = "Enabling incremental vacuum", skip(self))]
+    pub async fn enable_incremental_vacuum(
+        &self,
+    ) -> Result<bool, DatabaseError<<Self as AppDatabase>::Error>> {
+        if self
+            .incremental_vacuum_enabled()
+            .await
+            .map_err(DatabaseError::Backend)?
+        {
+            return Ok(false);
+        }
+        // Both statements must run on the same connection for the setting to apply.
+        let mut connection = self
+            .connection
+            .acquire()
+            .await
+            .map_err(DatabaseError::Backend)?;
+        for statement in ["PRAGMA auto_vacuum = INCREMENTAL;", "VACUUM;"] {
+            sqlx::query(statement)
+                .execute(&mut connection)
+                .await
+                .map_err(DatabaseError::Backend)?;
+        }
+        Ok(true)
+    }
+
     #[tracing::instrument(name = "Clearing database", skip(self))]
     async fn clear_db_async(&mut self) -> Result<(), sqlx::Error> {
         let mut tx = self.connection.begin().await?;
@@ -1056,6 +2194,7 @@ impl SQLiteDatabase {
         sqlx::query!("DELETE FROM variants")
             .execute(&mut tx)
             .await?;
+        sqlx::query("DELETE FROM covers").execute(&mut tx).await?;
         sqlx::query!("DELETE FROM books").execute(&mut tx).await?;
         // When deleting all books, 100% should do a vacuum
         tx.commit().await?;
@@ -1077,14 +2216,31 @@ impl SQLiteDatabase {
         sqlx::query(&format!("PRAGMA cache_size = {}", size))
             .execute(&mut tx)
             .await?;
+        let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM books;")
+            .fetch_one(&mut tx)
+            .await?;
         let s = merges.map(|id| id.to_string()).join(", ");
-        sqlx::query(&format!("DELETE FROM books WHERE book_id IN ({})", s))
+        let removed = sqlx::query(&format!("DELETE FROM books WHERE book_id IN ({})", s))
             .execute(&mut tx)
-            .await?;
+            .await?
+            .rows_affected();
         sqlx::query!("PRAGMA cache_size = 4096")
             .execute(&mut tx)
             .await?;
-        tx.commit().await
+        tx.commit().await?;
+
+        // Deleted pages are otherwise kept in the file, to be reused by later inserts.
+        if total > 0 && removed as f64 / total as f64 >= VACUUM_THRESHOLD {
+            tracing::info!(
+                "Reclaiming space after removing {} of {} books",
+                removed,
+                total
+            );
+            sqlx::query("PRAGMA incremental_vacuum;")
+                .execute(&self.connection)
+                .await?;
+        }
+        Ok(())
     }
 
     async fn merge_by_ids(&mut self, merges: &[(BookID, BookID)]) -> Result<(), sqlx::Error> {
@@ -1124,13 +2280,28 @@ impl SQLiteDatabase {
             .execute(&mut tx)
             .await?;
 
-            sqlx::query!(
-                "UPDATE variants SET book_id = ? WHERE book_id = ?",
-                merged_into,
-                merged_from
+            // The merged variants are placed after the existing variants, in the same order.
+            let offset: i64 = sqlx::query_scalar(
+                "SELECT COALESCE(MAX(position) + 1, 0) FROM variants WHERE book_id = ?",
+            )
+            .bind(merged_into)
+            .fetch_one(&mut tx)
+            .await?;
+            sqlx::query(
+                "UPDATE variants SET book_id = ?, position = position + ? WHERE book_id = ?",
             )
+            .bind(merged_into)
+            .bind(offset)
+            .bind(merged_from)
             .execute(&mut tx)
             .await?;
+
+            // Keeps the existing cover of the merged book, if any.
+            sqlx::query("UPDATE OR IGNORE covers SET book_id = ? WHERE book_id = ?")
+                .bind(merged_into)
+                .bind(merged_from)
+                .execute(&mut tx)
+                .await?;
         }
         tx.commit().await
     }
@@ -1139,6 +2310,7 @@ impl SQLiteDatabase {
         &mut self,
         books: &HashMap<BookID, Arc<Book>>,
         edits: &[(ColumnIdentifier, Edit)],
+        policy: VariantEditPolicy,
         transaction_size: usize,
     ) -> Result<(), DatabaseError<<Self as AppDatabase>::Error>> {
         let mut tx = self
@@ -1149,7 +2321,14 @@ impl SQLiteDatabase {
 
         let mut num_transactions = 0;
         for mut book in books.values().cloned() {
-            tx = edit_book(tx, Arc::make_mut(&mut book), edits).await?;
+            tx = edit_book(
+                tx,
+                Arc::make_mut(&mut book),
+                edits,
+                policy,
+                &self.tag_append,
+            )
+            .await?;
             self.cache.write().await.insert_book(book);
             // Commits transaction after a certain number of steps
             // to avoid data loss.
@@ -1192,6 +2371,7 @@ impl AppDatabase for SQLiteDatabase {
             }
         }
         let database = SqlitePoolOptions::new()
+            .after_connect(|connection| Box::pin(async move { register_functions(connection) }))
             .connect_with(
                 SqliteConnectOptions::new()
                     .filename(&file_path)
@@ -1200,24 +2380,40 @@ impl AppDatabase for SQLiteDatabase {
             .await
             .map_err(DatabaseError::Backend)?;
 
-        let db = Self {
+        let mut db = Self {
             connection: database,
             cache: Default::default(),
             path: file_path.as_ref().to_path_buf(),
+            fts5: false,
+            field_limits: FieldLimits::default(),
+            insert_policy: InsertPolicy::default(),
+            tag_append: TagAppendPolicy::default(),
+            insert_batch_size: DEFAULT_INSERT_BATCH_SIZE,
         };
 
         tracing::info!("Creating core tables if they do not exist");
-        for query in [
-            CREATE_BOOKS,
-            CREATE_FREE_TAGS,
-            CREATE_NAMED_TAGS,
-            CREATE_MULTIMAP_TAGS,
-            CREATE_VARIANTS,
-        ] {
-            sqlx::query(query)
-                .execute(&db.connection)
+        {
+            // Incremental vacuum can only be enabled before any tables are created, by the
+            // connection which creates them. Existing databases are not affected.
+            let mut connection = db
+                .connection
+                .acquire()
                 .await
                 .map_err(DatabaseError::Backend)?;
+            for query in [
+                "PRAGMA auto_vacuum = INCREMENTAL;",
+                CREATE_BOOKS,
+                CREATE_FREE_TAGS,
+                CREATE_NAMED_TAGS,
+                CREATE_MULTIMAP_TAGS,
+                CREATE_VARIANTS,
+                CREATE_COVERS,
+            ] {
+                sqlx::query(query)
+                    .execute(&mut connection)
+                    .await
+                    .map_err(DatabaseError::Backend)?;
+            }
         }
 
         // TODO: Disable this when doing large writes.
@@ -1229,6 +2425,7 @@ impl AppDatabase for SQLiteDatabase {
             "named_tags",
             "free_tags",
             "multimap_tags",
+            "covers",
         ] {
             sqlx::query(&format!(
                 "CREATE INDEX IF NOT EXISTS {}_ids on {}(book_id);",
@@ -1238,7 +2435,62 @@ impl AppDatabase for SQLiteDatabase {
             .await
             .map_err(DatabaseError::Backend)?;
         }
+        // Used to find files which are already in the database when inserting books.
+        sqlx::query("CREATE INDEX IF NOT EXISTS variant_files on variants(file_size, hash);")
+            .execute(&db.connection)
+            .await
+            .map_err(DatabaseError::Backend)?;
+
+        db.add_date_added().await.map_err(DatabaseError::Backend)?;
+        db.add_variant_positions()
+            .await
+            .map_err(DatabaseError::Backend)?;
+        db.lowercase_tag_names()
+            .await
+            .map_err(DatabaseError::Backend)?;
+        db.warn_hidden_read_tags()
+            .await
+            .map_err(DatabaseError::Backend)?;
+        if !db
+            .incremental_vacuum_enabled()
+            .await
+            .map_err(DatabaseError::Backend)?
+        {
+            tracing::info!(
+                "Space used by removed books is not returned to the file system until the database is rebuilt with --vacuum"
+            );
+        }
+        db.enable_full_text_search().await?;
+        db.refresh_columns().await?;
+        Ok(db)
+    }
+
+    async fn open_read_only<P>(file_path: P) -> Result<Self, DatabaseError<Self::Error>>
+    where
+        P: AsRef<Path> + Send + Sync,
+        Self: Sized,
+    {
+        let database = SqlitePoolOptions::new()
+            .after_connect(|connection| Box::pin(async move { register_functions(connection) }))
+            .connect_with(
+                SqliteConnectOptions::new()
+                    .filename(&file_path)
+                    .read_only(true),
+            )
+            .await
+            .map_err(DatabaseError::Backend)?;
 
+        let db = Self {
+            connection: database,
+            cache: Default::default(),
+            path: file_path.as_ref().to_path_buf(),
+            fts5: false,
+            field_limits: FieldLimits::default(),
+            insert_policy: InsertPolicy::default(),
+            tag_append: TagAppendPolicy::default(),
+            insert_batch_size: DEFAULT_INSERT_BATCH_SIZE,
+        };
+        db.refresh_columns().await?;
         Ok(db)
     }
 
@@ -1246,6 +2498,10 @@ impl AppDatabase for SQLiteDatabase {
         self.path.as_path()
     }
 
+    fn supports_full_text_search(&self) -> bool {
+        self.fts5
+    }
+
     async fn save(&mut self) -> Result<(), DatabaseError<Self::Error>> {
         Ok(())
     }
@@ -1255,24 +2511,305 @@ impl AppDatabase for SQLiteDatabase {
         book: BookVariant,
     ) -> Result<BookID, DatabaseError<Self::Error>> {
         let ids = self
-            .insert_books_async(std::iter::once(book), 1)
+            .insert_books_async(std::iter::once(vec![book]), 1, self.insert_policy)
             .await
             .map_err(DatabaseError::Backend)?;
-        Ok(ids[0])
+        Ok(ids[0].0)
     }
 
     async fn insert_books<I: Iterator<Item = BookVariant> + Send>(
         &mut self,
         books: I,
     ) -> Result<Vec<BookID>, DatabaseError<Self::Error>> {
-        self.insert_books_async(books, 5000)
+        let ids = self
+            .insert_books_async(
+                books.map(|book| vec![book]),
+                self.insert_batch_size,
+                self.insert_policy,
+            )
+            .await
+            .map_err(DatabaseError::Backend)?;
+        Ok(ids
+            .into_iter()
+            .filter_map(|(id, new)| if new { Some(id) } else { None })
+            .collect())
+    }
+
+    #[tracing::instrument(name = "Importing books", skip(self, books))]
+    async fn import_books(
+        &mut self,
+        books: &[Arc<Book>],
+    ) -> Result<Vec<BookID>, DatabaseError<Self::Error>> {
+        let books: Vec<_> = books
+            .iter()
+            .filter(|book| {
+                if book.variants().is_empty() {
+                    tracing::info!("Skipping {:?}, which has no variants", book.title());
+                }
+                !book.variants().is_empty()
+            })
+            .collect();
+        let placed = self
+            .insert_books_async(
+                books.iter().map(|book| book_variants(book)),
+                self.insert_batch_size,
+                self.insert_policy,
+            )
             .await
-            .map_err(DatabaseError::Backend)
+            .map_err(DatabaseError::Backend)?;
+
+        // New books keep the date at which they were added to the other database, if any.
+        let mut tx = self
+            .connection
+            .begin()
+            .await
+            .map_err(DatabaseError::Backend)?;
+        let mut dated = HashSet::new();
+        for (book, (id, new)) in books.iter().zip(placed.iter()) {
+            if let (true, Some(date_added)) = (*new, book.date_added.as_deref()) {
+                sqlx::query("UPDATE books SET date_added = ? WHERE book_id = ?;")
+                    .bind(date_added)
+                    .bind(u64::from(*id) as i64)
+                    .execute(&mut tx)
+                    .await
+                    .map_err(DatabaseError::Backend)?;
+                dated.insert(*id);
+            }
+        }
+        tx.commit().await.map_err(DatabaseError::Backend)?;
+        self.cache.write().await.remove_books(&dated);
+        self.refresh_columns().await?;
+        Ok(placed
+            .into_iter()
+            .filter_map(|(id, new)| if new { Some(id) } else { None })
+            .collect())
+    }
+
+    async fn restore_books(
+        &mut self,
+        books: &[Arc<Book>],
+    ) -> Result<(), DatabaseError<Self::Error>> {
+        let mut tx = self
+            .connection
+            .begin()
+            .await
+            .map_err(DatabaseError::Backend)?;
+        for book in books {
+            let id = u64::from(book.id()) as i64;
+            let (series_name, series_index) = match book.series() {
+                Some(series) => (Some(series.name.as_str()), series.index),
+                None => (None, None),
+            };
+            // Updating the existing row, rather than replacing it, keeps the cover of the book.
+            sqlx::query(
+                "INSERT INTO books (book_id, title, series_name, series_id, date_added)
+                VALUES(?, ?, ?, ?, ?)
+                ON CONFLICT(book_id) DO UPDATE SET
+                title = excluded.title, series_name = excluded.series_name, series_id = excluded.series_id;",
+            )
+            .bind(id)
+            .bind(book.title())
+            .bind(series_name)
+            .bind(series_index)
+            .bind(book.date_added.as_deref())
+            .execute(&mut tx)
+            .await
+            .map_err(DatabaseError::Backend)?;
+
+            for table in &["variants", "free_tags", "named_tags"] {
+                sqlx::query(&format!("DELETE FROM {} WHERE book_id = ?;", table))
+                    .bind(id)
+                    .execute(&mut tx)
+                    .await
+                    .map_err(DatabaseError::Backend)?;
+            }
+            sqlx::query("DELETE FROM multimap_tags WHERE book_id = ? AND name = 'author';")
+                .bind(id)
+                .execute(&mut tx)
+                .await
+                .map_err(DatabaseError::Backend)?;
+            insert_book_contents(&mut tx, id, book)
+                .await
+                .map_err(DatabaseError::Backend)?;
+        }
+        tx.commit().await.map_err(DatabaseError::Backend)?;
+
+        {
+            let mut cache = self.cache.write().await;
+            for book in books {
+                cache.insert_book(book.clone());
+            }
+        }
+        self.refresh_columns().await?;
+        Ok(())
+    }
+
+    async fn snapshot_books(
+        &self,
+        ids: &[BookID],
+    ) -> Result<BookSnapshot, DatabaseError<Self::Error>> {
+        if ids.is_empty() {
+            return Ok(BookSnapshot::default());
+        }
+        let ids = ids.iter().join(", ");
+        // The rows are read in a single transaction, so that they are consistent with each other.
+        let mut tx = self
+            .connection
+            .begin()
+            .await
+            .map_err(DatabaseError::Backend)?;
+        let select = |columns: &str, table: &str| {
+            format!(
+                "SELECT {} FROM {} WHERE book_id IN ({});",
+                columns, table, ids
+            )
+        };
+        let snapshot = BookSnapshot {
+            books: sqlx::query_as(&select(
+                "book_id, title, series_name, series_id, date_added",
+                "books",
+            ))
+            .fetch_all(&mut tx)
+            .await
+            .map_err(DatabaseError::Backend)?,
+            variants: sqlx::query_as(&select(
+                "book_type, path, local_title, identifier, language, description, id, hash, file_size, book_id, position",
+                "variants",
+            ))
+            .fetch_all(&mut tx)
+            .await
+            .map_err(DatabaseError::Backend)?,
+            named_tags: sqlx::query_as(&select("name, value, book_id", "named_tags"))
+                .fetch_all(&mut tx)
+                .await
+                .map_err(DatabaseError::Backend)?,
+            free_tags: sqlx::query_as(&select("value, book_id", "free_tags"))
+                .fetch_all(&mut tx)
+                .await
+                .map_err(DatabaseError::Backend)?,
+            multimap_tags: sqlx::query_as(&select("name, value, book_id", "multimap_tags"))
+                .fetch_all(&mut tx)
+                .await
+                .map_err(DatabaseError::Backend)?,
+            covers: sqlx::query_as(&select("image, book_id", "covers"))
+                .fetch_all(&mut tx)
+                .await
+                .map_err(DatabaseError::Backend)?,
+        };
+        tx.commit().await.map_err(DatabaseError::Backend)?;
+        Ok(snapshot)
+    }
+
+    async fn restore_snapshot(
+        &mut self,
+        snapshot: &BookSnapshot,
+    ) -> Result<(), DatabaseError<Self::Error>> {
+        if snapshot.is_empty() {
+            return Ok(());
+        }
+        let ids: HashSet<_> = snapshot.ids().into_iter().collect();
+        let mut tx = self
+            .connection
+            .begin()
+            .await
+            .map_err(DatabaseError::Backend)?;
+        // Removing the books first also removes any rows which were added since the snapshot.
+        sqlx::query(&format!(
+            "DELETE FROM books WHERE book_id IN ({});",
+            ids.iter().join(", ")
+        ))
+        .execute(&mut tx)
+        .await
+        .map_err(DatabaseError::Backend)?;
+        for (id, title, series_name, series_id, date_added) in &snapshot.books {
+            sqlx::query(
+                "INSERT INTO books (book_id, title, series_name, series_id, date_added)
+                VALUES(?, ?, ?, ?, ?);",
+            )
+            .bind(id)
+            .bind(title)
+            .bind(series_name)
+            .bind(series_id)
+            .bind(date_added)
+            .execute(&mut tx)
+            .await
+            .map_err(DatabaseError::Backend)?;
+        }
+        for (
+            book_type,
+            path,
+            local_title,
+            identifier,
+            language,
+            description,
+            variant_id,
+            hash,
+            file_size,
+            id,
+            position,
+        ) in &snapshot.variants
+        {
+            sqlx::query(
+                "INSERT INTO variants (book_type, path, local_title, identifier, language, description, id, hash, file_size, book_id, position)
+                VALUES(?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?);",
+            )
+            .bind(book_type)
+            .bind(path)
+            .bind(local_title)
+            .bind(identifier)
+            .bind(language)
+            .bind(description)
+            .bind(variant_id)
+            .bind(hash)
+            .bind(file_size)
+            .bind(id)
+            .bind(position)
+            .execute(&mut tx)
+            .await
+            .map_err(DatabaseError::Backend)?;
+        }
+        for (name, value, id) in &snapshot.named_tags {
+            sqlx::query("INSERT INTO named_tags (name, value, book_id) VALUES(?, ?, ?);")
+                .bind(name)
+                .bind(value)
+                .bind(id)
+                .execute(&mut tx)
+                .await
+                .map_err(DatabaseError::Backend)?;
+        }
+        for (value, id) in &snapshot.free_tags {
+            sqlx::query("INSERT INTO free_tags (value, book_id) VALUES(?, ?);")
+                .bind(value)
+                .bind(id)
+                .execute(&mut tx)
+                .await
+                .map_err(DatabaseError::Backend)?;
+        }
+        for (name, value, id) in &snapshot.multimap_tags {
+            sqlx::query("INSERT INTO multimap_tags (name, value, book_id) VALUES(?, ?, ?);")
+                .bind(name)
+                .bind(value)
+                .bind(id)
+                .execute(&mut tx)
+                .await
+                .map_err(DatabaseError::Backend)?;
+        }
+        for (image, id) in &snapshot.covers {
+            sqlx::query("INSERT INTO covers (image, book_id) VALUES(?, ?);")
+                .bind(image)
+                .bind(id)
+                .execute(&mut tx)
+                .await
+                .map_err(DatabaseError::Backend)?;
+        }
+        tx.commit().await.map_err(DatabaseError::Backend)?;
+
+        // The books are read from the database again when they are next needed.
+        self.cache.write().await.remove_books(&ids);
+        self.refresh_columns().await?;
+        Ok(())
     }
 
-    // TODO: In both remove_books and remove_selected,
-    //  we should ensure that large deletes don't
-    //  cause the DB to remain large.
     async fn remove_books(
         &mut self,
         ids: &HashSet<BookID>,
@@ -1292,18 +2829,24 @@ impl AppDatabase for SQLiteDatabase {
                 if matchers.is_empty() {
                     return self.clear().await;
                 } else {
-                    QueryBuilder::default().join_cols(None, matchers)
+                    QueryBuilder::default()
+                        .full_text_search(self.fts5)
+                        .join_cols(None, matchers)
                 }
             }
-            Selection::Partial(books, _) => {
+            Selection::Partial(books, _, _) => {
                 return self
                     .remove_books(&books.keys().cloned().collect::<HashSet<_>>())
                     .await;
             }
-            Selection::Range(start, end, cmp_rules, _, match_rules) => QueryBuilder::default()
-                .cmp_rules(cmp_rules)
-                .include_id(true)
-                .between_books(start, end, match_rules),
+            Selection::Range(start, end, cmp_rules, _, match_rules, collation) => {
+                QueryBuilder::default()
+                    .cmp_rules(cmp_rules)
+                    .collation(*collation)
+                    .full_text_search(self.fts5)
+                    .include_id(true)
+                    .between_books(start, end, match_rules)
+            }
             Selection::Empty => {
                 return Ok(());
             }
@@ -1344,12 +2887,12 @@ impl AppDatabase for SQLiteDatabase {
     {
         let mut books: HashMap<BookID, Option<Arc<Book>>> = self
             .cache
-            .read()
+            .write()
             .await
             .get_books(ids)
             .into_iter()
             .zip(ids.iter().cloned())
-            .map(|(book, id)| (id, book.cloned()))
+            .map(|(book, id)| (id, book))
             .collect();
         tracing::info!("Finishing loading all cached books");
         let ids: Vec<_> = books
@@ -1413,9 +2956,19 @@ impl AppDatabase for SQLiteDatabase {
             .remove(&id)
             .ok_or(DatabaseError::BookNotFound(id))?;
         let book_ = Arc::make_mut(&mut book);
-        let tx = edit_book(tx, book_, edits).await?;
+        let tx = edit_book(
+            tx,
+            book_,
+            edits,
+            VariantEditPolicy::AllVariants,
+            &self.tag_append,
+        )
+        .await?;
         tx.commit().await.map_err(DatabaseError::Backend)?;
         self.cache.write().await.insert_book(book);
+        if edits_named_columns(edits) {
+            self.refresh_columns().await?;
+        }
         Ok(())
     }
 
@@ -1423,17 +2976,27 @@ impl AppDatabase for SQLiteDatabase {
         &mut self,
         selected: &Selection,
         edits: &[(ColumnIdentifier, Edit)],
+        policy: VariantEditPolicy,
     ) -> Result<(), DatabaseError<Self::Error>> {
         let (query, bound_variables) = match selected {
-            Selection::All(matchers) => QueryBuilder::default().join_cols(None, matchers),
-            Selection::Partial(books, _) => {
-                self.edit_unique(books, edits, 5000).await?;
+            Selection::All(matchers) => QueryBuilder::default()
+                .full_text_search(self.fts5)
+                .join_cols(None, matchers),
+            Selection::Partial(books, _, _) => {
+                self.edit_unique(books, edits, policy, 5000).await?;
+                if edits_named_columns(edits) {
+                    self.refresh_columns().await?;
+                }
                 return Ok(());
             }
-            Selection::Range(start, end, cmp_rules, _, match_rules) => QueryBuilder::default()
-                .cmp_rules(cmp_rules)
-                .include_id(true)
-                .between_books(start, end, match_rules),
+            Selection::Range(start, end, cmp_rules, _, match_rules, collation) => {
+                QueryBuilder::default()
+                    .cmp_rules(cmp_rules)
+                    .collation(*collation)
+                    .full_text_search(self.fts5)
+                    .include_id(true)
+                    .between_books(start, end, match_rules)
+            }
             Selection::Empty => {
                 return Ok(());
             }
@@ -1445,7 +3008,51 @@ impl AppDatabase for SQLiteDatabase {
             .map(|x| (x.id(), x))
             .collect::<HashMap<_, _>>();
 
-        self.edit_unique(&books, edits, 5000).await
+        self.edit_unique(&books, edits, policy, 5000).await?;
+        if edits_named_columns(edits) {
+            self.refresh_columns().await?;
+        }
+        Ok(())
+    }
+
+    async fn edit_many(
+        &mut self,
+        edits: &[(BookID, Box<[(ColumnIdentifier, Edit)]>)],
+    ) -> Result<(), DatabaseError<Self::Error>> {
+        let ids: Vec<_> = edits.iter().map(|(id, _)| *id).collect();
+        let mut books = self.get_books(&ids).await?;
+
+        let mut tx = self
+            .connection
+            .begin()
+            .await
+            .map_err(DatabaseError::Backend)?;
+        for (id, book_edits) in edits {
+            let book = books.get_mut(id).ok_or(DatabaseError::BookNotFound(*id))?;
+            tx = edit_book(
+                tx,
+                Arc::make_mut(book),
+                book_edits,
+                VariantEditPolicy::AllVariants,
+                &self.tag_append,
+            )
+            .await?;
+        }
+        tx.commit().await.map_err(DatabaseError::Backend)?;
+
+        {
+            let mut cache = self.cache.write().await;
+            for book in books.into_values() {
+                cache.insert_book(book);
+            }
+        }
+        if edits
+            .iter()
+            .any(|(_, book_edits)| edits_named_columns(book_edits))
+        {
+            self.refresh_columns().await?;
+        }
+        Ok(())
     }
 
     async fn merge_similar(&mut self) -> Result<HashSet<BookID>, DatabaseError<Self::Error>> {
@@ -1464,59 +3071,566 @@ impl AppDatabase for SQLiteDatabase {
         Ok(to_remove)
     }
 
-    // TODO: has_column needs to check DB
-    async fn has_column(&self, col: &UniCase<String>) -> Result<bool, DatabaseError<Self::Error>> {
-        Ok(self.cache.read().await.has_column(col))
-    }
-
-    async fn saved(&self) -> bool {
-        true
-    }
-
-    #[tracing::instrument(name = "Updating books from sources", skip(self, books))]
-    async fn update<I: Iterator<Item = BookVariant> + Send>(
-        &mut self,
-        books: I,
-    ) -> Result<(), DatabaseError<Self::Error>> {
-        #[derive(sqlx::FromRow)]
-        struct VariantMetadata {
-            book_id: i64,
-            id: Option<i64>,
-            file_size: i64,
-            hash: Vec<u8>,
+    async fn find_duplicates(
+        &self,
+        strategy: DetectionStrategy,
+    ) -> Result<Vec<Vec<BookID>>, DatabaseError<Self::Error>> {
+        // Groups are the same as those which `deduplicate` would merge.
+        let mut groups: BTreeMap<BookID, Vec<BookID>> = BTreeMap::new();
+        for (into, from) in self.duplicate_pairs(strategy).await? {
+            groups.entry(into).or_insert_with(|| vec![into]).push(from);
         }
+        Ok(groups
+            .into_values()
+            .map(|mut group| {
+                group.sort_unstable();
+                group
+            })
+            .collect())
+    }
 
-        let raw_variants: Vec<VariantMetadata> = sqlx::query_as!(
-            VariantMetadata,
-            "SELECT book_id, id, file_size, hash FROM variants"
+    async fn remove_empty_books(&mut self) -> Result<HashSet<BookID>, DatabaseError<Self::Error>> {
+        let book_ids: Vec<i64> = sqlx::query_scalar(
+            "SELECT book_id FROM books WHERE book_id NOT IN (SELECT book_id FROM variants)",
         )
         .fetch_all(&self.connection)
         .await
         .map_err(DatabaseError::Backend)?;
-
-        tracing::info!("Read {} hashes from disk", raw_variants.len());
-        let target_hashmap: HashMap<(u64, [u8; 32]), (Option<u32>, BookID)> = raw_variants
+        let ids: HashSet<_> = book_ids
             .into_iter()
-            .map(|vm| {
-                (
-                    (vm.file_size as u64, {
-                        let len = vm.hash.len();
-                        vm.hash
-                            .try_into()
-                            .map_err(|_| {
-                                DataError::Serialize(format!(
-                                    "Got {} byte hash, expected 32 byte hash",
-                                    len
-                                ))
-                            })
-                            .unwrap()
-                    }),
-                    (
-                        vm.id.map(|id| id as u32),
-                        BookID::try_from(vm.book_id as u64).unwrap(),
-                    ),
-                )
-            })
+            .filter_map(|id| BookID::try_from(id as u64).ok())
+            .collect();
+        if !ids.is_empty() {
+            self.remove_books(&ids).await?;
+        }
+        Ok(ids)
+    }
+
+    async fn set_preferred_variant(
+        &mut self,
+        id: BookID,
+        index: usize,
+    ) -> Result<(), DatabaseError<Self::Error>> {
+        let mut book = self
+            .get_books(&[id])
+            .await?
+            .remove(&id)
+            .ok_or(DatabaseError::BookNotFound(id))?;
+        Arc::make_mut(&mut book).set_preferred_variant(index)?;
+
+        let book_id = u64::from(id) as i64;
+        let mut tx = self
+            .connection
+            .begin()
+            .await
+            .map_err(DatabaseError::Backend)?;
+        let mut rowids: Vec<i64> =
+            sqlx::query_scalar("SELECT rowid FROM variants WHERE book_id = ? ORDER BY position;")
+                .bind(book_id)
+                .fetch_all(&mut tx)
+                .await
+                .map_err(DatabaseError::Backend)?;
+        // The preferred variant is moved to the front, preserving the order of the remaining
+        // variants.
+        if index < rowids.len() {
+            let preferred = rowids.remove(index);
+            rowids.insert(0, preferred);
+        }
+        for (position, rowid) in rowids.into_iter().enumerate() {
+            sqlx::query("UPDATE variants SET position = ? WHERE rowid = ?;")
+                .bind(position as i64)
+                .bind(rowid)
+                .execute(&mut tx)
+                .await
+                .map_err(DatabaseError::Backend)?;
+        }
+        tx.commit().await.map_err(DatabaseError::Backend)?;
+        self.cache.write().await.insert_book(book);
+        Ok(())
+    }
+
+    async fn set_cover(
+        &mut self,
+        id: BookID,
+        image: &[u8],
+    ) -> Result<(), DatabaseError<Self::Error>> {
+        let mut book = self
+            .get_books(&[id])
+            .await?
+            .remove(&id)
+            .ok_or(DatabaseError::BookNotFound(id))?;
+        sqlx::query("INSERT OR REPLACE INTO covers (image, book_id) VALUES (?, ?);")
+            .bind(image)
+            .bind(u64::from(id) as i64)
+            .execute(&self.connection)
+            .await
+            .map_err(DatabaseError::Backend)?;
+        Arc::make_mut(&mut book).has_cover = true;
+        self.cache.write().await.insert_book(book);
+        Ok(())
+    }
+
+    async fn get_cover(&self, id: BookID) -> Result<Option<Vec<u8>>, DatabaseError<Self::Error>> {
+        sqlx::query_scalar("SELECT image FROM covers WHERE book_id = ?;")
+            .bind(u64::from(id) as i64)
+            .fetch_optional(&self.connection)
+            .await
+            .map_err(DatabaseError::Backend)
+    }
+
+    async fn refresh_columns(&self) -> Result<(), DatabaseError<Self::Error>> {
+        let columns: Vec<String> = sqlx::query_scalar(
+            "SELECT name FROM named_tags UNION SELECT name FROM multimap_tags WHERE name != 'author';",
+        )
+        .fetch_all(&self.connection)
+        .await
+        .map_err(DatabaseError::Backend)?;
+        // The read status is listed under the name of its column, rather than its tag.
+        self.cache
+            .write()
+            .await
+            .set_columns(columns.into_iter().map(|name| {
+                if name == READ_STATUS_TAG {
+                    UniCase::new(ColumnIdentifier::ReadStatus.into_string())
+                } else {
+                    UniCase::new(name)
+                }
+            }));
+        Ok(())
+    }
+
+    async fn authors(&self) -> Result<Vec<(String, usize)>, DatabaseError<Self::Error>> {
+        let authors: Vec<(String, i64)> = sqlx::query_as(
+            "SELECT value, COUNT(*) FROM multimap_tags WHERE name = 'author' GROUP BY value ORDER BY value;",
+        )
+        .fetch_all(&self.connection)
+        .await
+        .map_err(DatabaseError::Backend)?;
+        Ok(authors
+            .into_iter()
+            .map(|(author, count)| (author, count as usize))
+            .collect())
+    }
+
+    async fn column_stats(
+        &self,
+        column: &ColumnIdentifier,
+    ) -> Result<Vec<(String, usize)>, DatabaseError<Self::Error>> {
+        let (select, name) = match column {
+            ColumnIdentifier::Title => ("SELECT title, COUNT(*) FROM books WHERE title IS NOT NULL", None),
+            ColumnIdentifier::Series => (
+                "SELECT series_name, COUNT(*) FROM books WHERE series_name IS NOT NULL",
+                None,
+            ),
+            ColumnIdentifier::Author => (
+                "SELECT value, COUNT(DISTINCT book_id) FROM multimap_tags WHERE name = 'author'",
+                None,
+            ),
+            ColumnIdentifier::MultiMap(name) => (
+                "SELECT value, COUNT(DISTINCT book_id) FROM multimap_tags WHERE name = ?",
+                Some(name.as_str()),
+            ),
+            ColumnIdentifier::NamedTag(name) => (
+                "SELECT value, COUNT(*) FROM named_tags WHERE name = ?",
+                Some(name.as_str()),
+            ),
+            ColumnIdentifier::ReadStatus => (
+                "SELECT value, COUNT(*) FROM named_tags WHERE name = ?",
+                Some(READ_STATUS_TAG),
+            ),
+            ColumnIdentifier::Tags => ("SELECT value, COUNT(*) FROM free_tags", None),
+            ColumnIdentifier::Variants => (
+                "SELECT book_type, COUNT(DISTINCT book_id) FROM variants",
+                None,
+            ),
+            ColumnIdentifier::Cover => (
+                "SELECT CASE WHEN EXISTS (SELECT 1 FROM covers WHERE covers.book_id = books.book_id) \
+                THEN 'true' ELSE 'false' END, COUNT(*) FROM books",
+                None,
+            ),
+            ColumnIdentifier::ID
+            | ColumnIdentifier::Description
+            | ColumnIdentifier::DateAdded
+            | ColumnIdentifier::ExactTag(_)
+            | ColumnIdentifier::MultiMapExact(_, _) => return Ok(vec![]),
+        };
+
+        let sql = format!("{} GROUP BY 1 ORDER BY 2 DESC, 1;", select);
+        let mut query = sqlx::query_as::<_, (String, i64)>(&sql);
+        if let Some(name) = name {
+            query = query.bind(name);
+        }
+        let stats = query
+            .fetch_all(&self.connection)
+            .await
+            .map_err(DatabaseError::Backend)?;
+        Ok(stats
+            .into_iter()
+            .map(|(value, count)| (value, count as usize))
+            .collect())
+    }
+
+    async fn reading_stats(&self) -> Result<Vec<MonthlyReading>, DatabaseError<Self::Error>> {
+        let stats = sqlx::query_as::<_, (String, i64, i64)>(
+            r#"SELECT month, SUM(added), SUM(finished) FROM (
+    SELECT substr(date_added, 1, 7) AS month, 1 AS added, 0 AS finished
+    FROM books WHERE date_added IS NOT NULL
+    UNION ALL
+    SELECT substr(value, 1, 7), 0, 1
+    FROM named_tags WHERE name = ?
+)
+WHERE month GLOB '[0-9][0-9][0-9][0-9]-[0-9][0-9]'
+GROUP BY month
+ORDER BY month;"#,
+        )
+        .bind(FINISHED_TAG)
+        .fetch_all(&self.connection)
+        .await
+        .map_err(DatabaseError::Backend)?;
+        Ok(stats
+            .into_iter()
+            .map(|(month, added, finished)| MonthlyReading {
+                month,
+                added: added as usize,
+                finished: finished as usize,
+            })
+            .collect())
+    }
+
+    async fn library_stats(&self) -> Result<LibraryStats, DatabaseError<Self::Error>> {
+        let (books, missing_title, missing_cover, missing_authors, missing_description) =
+            sqlx::query_as::<_, (i64, i64, i64, i64, i64)>(
+                r#"SELECT
+    COUNT(*),
+    COUNT(*) - COUNT(title),
+    IFNULL(SUM(NOT EXISTS (SELECT 1 FROM covers WHERE covers.book_id = books.book_id)), 0),
+    IFNULL(SUM(NOT EXISTS (SELECT 1 FROM multimap_tags
+        WHERE multimap_tags.book_id = books.book_id AND name = 'author')), 0),
+    IFNULL(SUM(NOT EXISTS (SELECT 1 FROM variants
+        WHERE variants.book_id = books.book_id AND description IS NOT NULL)), 0)
+FROM books;"#,
+            )
+            .fetch_one(&self.connection)
+            .await
+            .map_err(DatabaseError::Backend)?;
+        let (variants, total_size) = sqlx::query_as::<_, (i64, i64)>(
+            "SELECT COUNT(*), IFNULL(SUM(file_size), 0) FROM variants;",
+        )
+        .fetch_one(&self.connection)
+        .await
+        .map_err(DatabaseError::Backend)?;
+        let languages = sqlx::query_as::<_, (String, i64)>(
+            "SELECT language, COUNT(DISTINCT book_id) FROM variants WHERE language IS NOT NULL \
+            GROUP BY 1 ORDER BY 2 DESC, 1;",
+        )
+        .fetch_all(&self.connection)
+        .await
+        .map_err(DatabaseError::Backend)?
+        .into_iter()
+        .map(|(language, count)| (language, count as usize))
+        .collect();
+
+        Ok(LibraryStats {
+            books: books as usize,
+            variants: variants as usize,
+            total_size: total_size as u64,
+            authors: self.column_stats(&ColumnIdentifier::Author).await?,
+            series: self.column_stats(&ColumnIdentifier::Series).await?,
+            languages,
+            missing_cover: missing_cover as usize,
+            missing_title: missing_title as usize,
+            missing_authors: missing_authors as usize,
+            missing_description: missing_description as usize,
+        })
+    }
+
+    async fn rename_authors(
+        &mut self,
+        renames: &[(String, String)],
+    ) -> Result<(), DatabaseError<Self::Error>> {
+        let mut tx = self
+            .connection
+            .begin()
+            .await
+            .map_err(DatabaseError::Backend)?;
+        for (from, to) in renames {
+            // Renaming fails for books which already list `to`, due to the UNIQUE constraint -
+            // the remaining rows are duplicates, and are deleted.
+            sqlx::query(
+                "UPDATE OR IGNORE multimap_tags SET value = ? WHERE name = 'author' AND value = ?;",
+            )
+            .bind(to)
+            .bind(from)
+            .execute(&mut tx)
+            .await
+            .map_err(DatabaseError::Backend)?;
+            sqlx::query("DELETE FROM multimap_tags WHERE name = 'author' AND value = ?;")
+                .bind(from)
+                .execute(&mut tx)
+                .await
+                .map_err(DatabaseError::Backend)?;
+        }
+        tx.commit().await.map_err(DatabaseError::Backend)?;
+        // Any cached book may have been affected.
+        self.cache.write().await.clear();
+        Ok(())
+    }
+
+    async fn clear_tags(&mut self, ids: &[BookID]) -> Result<(), DatabaseError<Self::Error>> {
+        let mut tx = self
+            .connection
+            .begin()
+            .await
+            .map_err(DatabaseError::Backend)?;
+        for &id in ids {
+            let id = u64::from(id) as i64;
+            for sql in [
+                "DELETE FROM free_tags WHERE book_id = ?;",
+                "DELETE FROM named_tags WHERE book_id = ?;",
+                "DELETE FROM multimap_tags WHERE book_id = ? AND name != 'author';",
+            ] {
+                sqlx::query(sql)
+                    .bind(id)
+                    .execute(&mut tx)
+                    .await
+                    .map_err(DatabaseError::Backend)?;
+            }
+        }
+        tx.commit().await.map_err(DatabaseError::Backend)?;
+        self.cache.write().await.clear();
+        self.refresh_columns().await
+    }
+
+    async fn rename_tag(
+        &mut self,
+        old: &str,
+        new: &str,
+    ) -> Result<usize, DatabaseError<Self::Error>> {
+        // Free tags are values, which keep their case, while the names of other tags are
+        // stored in lowercase.
+        let (old_name, new_name) = (old.to_lowercase(), new.to_lowercase());
+        let mut tables = vec![
+            (
+                "UPDATE OR IGNORE free_tags SET value = ? WHERE value = ?;",
+                "DELETE FROM free_tags WHERE value = ?;",
+                old,
+                new,
+            ),
+            (
+                "UPDATE OR IGNORE named_tags SET name = ? WHERE name = ?;",
+                "DELETE FROM named_tags WHERE name = ?;",
+                old_name.as_str(),
+                new_name.as_str(),
+            ),
+        ];
+        // Renaming a tag to or from "author" would change the authors of books.
+        if old_name != "author" && new_name != "author" {
+            tables.push((
+                "UPDATE OR IGNORE multimap_tags SET name = ? WHERE name = ?;",
+                "DELETE FROM multimap_tags WHERE name = ?;",
+                old_name.as_str(),
+                new_name.as_str(),
+            ));
+        }
+        tables.retain(|(_, _, old, new)| old != new);
+        if tables.is_empty() {
+            return Ok(0);
+        }
+
+        let mut tx = self
+            .connection
+            .begin()
+            .await
+            .map_err(DatabaseError::Backend)?;
+        let mut renamed = 0;
+        for (update, delete, old, new) in tables {
+            renamed += sqlx::query(update)
+                .bind(new)
+                .bind(old)
+                .execute(&mut tx)
+                .await
+                .map_err(DatabaseError::Backend)?
+                .rows_affected();
+            // Renaming fails for books which already have `new`, due to the UNIQUE
+            // constraints - the remaining tags are duplicates, and are deleted.
+            renamed += sqlx::query(delete)
+                .bind(old)
+                .execute(&mut tx)
+                .await
+                .map_err(DatabaseError::Backend)?
+                .rows_affected();
+        }
+        tx.commit().await.map_err(DatabaseError::Backend)?;
+        self.cache.write().await.clear();
+        self.refresh_columns().await?;
+        Ok(renamed as usize)
+    }
+
+    async fn delete_tag(&mut self, name: &str) -> Result<usize, DatabaseError<Self::Error>> {
+        let mut tx = self
+            .connection
+            .begin()
+            .await
+            .map_err(DatabaseError::Backend)?;
+        let mut deleted = 0;
+        let lowercase = name.to_lowercase();
+        for (sql, name) in [
+            ("DELETE FROM free_tags WHERE value = ?;", name),
+            ("DELETE FROM named_tags WHERE name = ?;", lowercase.as_str()),
+            (
+                "DELETE FROM multimap_tags WHERE name = ? AND name != 'author';",
+                lowercase.as_str(),
+            ),
+        ] {
+            deleted += sqlx::query(sql)
+                .bind(name)
+                .execute(&mut tx)
+                .await
+                .map_err(DatabaseError::Backend)?
+                .rows_affected();
+        }
+        tx.commit().await.map_err(DatabaseError::Backend)?;
+        self.cache.write().await.clear();
+        self.refresh_columns().await?;
+        Ok(deleted as usize)
+    }
+
+    #[tracing::instrument(name = "Rebuilding the full-text index", skip(self))]
+    async fn rebuild_fts(&mut self) -> Result<Option<FtsReport>, DatabaseError<Self::Error>> {
+        if !self.fts5 {
+            return Ok(None);
+        }
+
+        let start = Instant::now();
+        let mut tx = self
+            .connection
+            .begin()
+            .await
+            .map_err(DatabaseError::Backend)?;
+        for query in REBUILD_BOOKS_FTS {
+            sqlx::query(query)
+                .execute(&mut tx)
+                .await
+                .map_err(DatabaseError::Backend)?;
+        }
+        tx.commit().await.map_err(DatabaseError::Backend)?;
+        let elapsed = start.elapsed();
+
+        let books: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM books;")
+            .fetch_one(&self.connection)
+            .await
+            .map_err(DatabaseError::Backend)?;
+        // The shadow table holding one row per indexed book is counted, as it is smaller than
+        // the table holding the indexed text.
+        let indexed: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM books_fts_docsize;")
+            .fetch_one(&self.connection)
+            .await
+            .map_err(DatabaseError::Backend)?;
+
+        let report = FtsReport {
+            elapsed,
+            books: books as usize,
+            indexed: indexed as usize,
+        };
+        tracing::info!("{:?}", report);
+        Ok(Some(report))
+    }
+
+    async fn has_column(&self, col: &UniCase<String>) -> Result<bool, DatabaseError<Self::Error>> {
+        if self.cache.read().await.has_column(col) {
+            return Ok(true);
+        }
+
+        // The cache may not know of columns which were added outside of this database,
+        // so the tags are checked directly. Tag names are stored in lowercase.
+        let name = col.to_lowercase();
+        let exists: Option<i64> = sqlx::query_scalar(
+            "SELECT 1 FROM named_tags WHERE name = ?
+            UNION ALL SELECT 1 FROM multimap_tags WHERE name = ? LIMIT 1;",
+        )
+        .bind(&name)
+        .bind(&name)
+        .fetch_optional(&self.connection)
+        .await
+        .map_err(DatabaseError::Backend)?;
+        if exists.is_some() {
+            self.cache
+                .write()
+                .await
+                .insert_columns(std::iter::once(col.clone()));
+        }
+        Ok(exists.is_some())
+    }
+
+    async fn saved(&self) -> bool {
+        true
+    }
+
+    #[tracing::instrument(name = "Exporting books", skip(self, writer))]
+    async fn export<W: Write + Send>(
+        &self,
+        format: ExportFormat,
+        writer: W,
+    ) -> Result<usize, DatabaseError<Self::Error>> {
+        let mut writer = BookWriter::new(format, writer)?;
+        let mut books = self.stream_books();
+        while let Some(book) = books.try_next().await? {
+            writer.write_book(&book)?;
+        }
+        Ok(writer.finish()?)
+    }
+
+    fn stream_books(&self) -> BoxStream<'_, Result<Arc<Book>, DatabaseError<Self::Error>>> {
+        stream::try_unfold(0, move |last_id| self.read_books_after(last_id))
+            .map_ok(|books| stream::iter(books.into_iter().map(Ok)))
+            .try_flatten()
+            .boxed()
+    }
+
+    #[tracing::instrument(name = "Updating books from sources", skip(self, books))]
+    async fn update<I: Iterator<Item = BookVariant> + Send>(
+        &mut self,
+        books: I,
+    ) -> Result<(), DatabaseError<Self::Error>> {
+        #[derive(sqlx::FromRow)]
+        struct VariantMetadata {
+            book_id: i64,
+            id: Option<i64>,
+            file_size: i64,
+            hash: Vec<u8>,
+        }
+
+        let raw_variants: Vec<VariantMetadata> = sqlx::query_as!(
+            VariantMetadata,
+            "SELECT book_id, id, file_size, hash FROM variants"
+        )
+        .fetch_all(&self.connection)
+        .await
+        .map_err(DatabaseError::Backend)?;
+
+        tracing::info!("Read {} hashes from disk", raw_variants.len());
+        let target_hashmap: HashMap<(u64, [u8; 32]), (Option<u32>, BookID)> = raw_variants
+            .into_iter()
+            .map(|vm| {
+                (
+                    (vm.file_size as u64, {
+                        let len = vm.hash.len();
+                        vm.hash
+                            .try_into()
+                            .map_err(|_| {
+                                DataError::Serialize(format!(
+                                    "Got {} byte hash, expected 32 byte hash",
+                                    len
+                                ))
+                            })
+                            .unwrap()
+                    }),
+                    (
+                        vm.id.map(|id| id as u32),
+                        BookID::try_from(vm.book_id as u64).unwrap(),
+                    ),
+                )
+            })
             .collect();
 
         sqlx::query!(
@@ -1587,11 +3701,86 @@ impl AppDatabase for SQLiteDatabase {
         tx.commit().await.map_err(DatabaseError::Backend)
     }
 
-    // async fn perform_query(
-    //     &mut self,
-    //     mut query: Select,
-    //     limit: usize,
-    // ) -> Result<Vec<Arc<Book>>, DatabaseError<Self::Error>> {
+    async fn refresh_variants(
+        &mut self,
+        variants: &[(BookID, BookVariant)],
+    ) -> Result<(), DatabaseError<Self::Error>> {
+        let mut tx = self
+            .connection
+            .begin()
+            .await
+            .map_err(DatabaseError::Backend)?;
+        for (book_id, variant) in variants {
+            let mut variant = variant.clone();
+            self.field_limits.apply(&mut variant);
+            #[cfg(unix)]
+            let path = variant.path().as_os_str().as_bytes();
+            #[cfg(windows)]
+            let path = v16_to_v8(variant.path().as_os_str().encode_wide().collect());
+            let identifier = variant
+                .identifier
+                .as_ref()
+                .map(|i| ron::to_string(i).expect("Serialization of value should never fail."));
+            sqlx::query(
+                "UPDATE variants SET local_title = ?, identifier = ?, language = ?, description = ?,
+                hash = ?, file_size = ? WHERE book_id = ? AND path = ?;",
+            )
+            .bind(&variant.local_title)
+            .bind(identifier)
+            .bind(&variant.language)
+            .bind(&variant.description)
+            .bind(variant.hash.to_vec())
+            .bind(variant.file_size as i64)
+            .bind(u64::from(*book_id) as i64)
+            .bind(path)
+            .execute(&mut tx)
+            .await
+            .map_err(DatabaseError::Backend)?;
+        }
+        tx.commit().await.map_err(DatabaseError::Backend)?;
+
+        let ids: HashSet<_> = variants.iter().map(|(id, _)| *id).collect();
+        self.cache.write().await.remove_books(&ids);
+        Ok(())
+    }
+
+    async fn move_variants(
+        &mut self,
+        moves: &[(BookID, PathBuf, PathBuf)],
+    ) -> Result<(), DatabaseError<Self::Error>> {
+        let mut tx = self
+            .connection
+            .begin()
+            .await
+            .map_err(DatabaseError::Backend)?;
+        for (book_id, from, to) in moves {
+            #[cfg(unix)]
+            let (from, to) = (from.as_os_str().as_bytes(), to.as_os_str().as_bytes());
+            #[cfg(windows)]
+            let (from, to) = (
+                v16_to_v8(from.as_os_str().encode_wide().collect()),
+                v16_to_v8(to.as_os_str().encode_wide().collect()),
+            );
+            sqlx::query("UPDATE variants SET path = ? WHERE book_id = ? AND path = ?;")
+                .bind(to)
+                .bind(u64::from(*book_id) as i64)
+                .bind(from)
+                .execute(&mut tx)
+                .await
+                .map_err(DatabaseError::Backend)?;
+        }
+        tx.commit().await.map_err(DatabaseError::Backend)?;
+
+        let ids: HashSet<_> = moves.iter().map(|(id, _, _)| *id).collect();
+        self.cache.write().await.remove_books(&ids);
+        Ok(())
+    }
+
+    // async fn perform_query(
+    //     &mut self,
+    //     mut query: Select,
+    //     limit: usize,
+    // ) -> Result<Vec<Arc<Book>>, DatabaseError<Self::Error>> {
     //     #[derive(sqlx::FromRow, Debug)]
     //     struct SqlxBookId {
     //         book_id: i64,
@@ -1629,3 +3818,2204 @@ impl AppDatabase for SQLiteDatabase {
     //         .collect())
     // }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use bookworm_records::test_util::variant;
+    use bookworm_records::variant::{BookType, Identifier};
+    use bookworm_records::ColumnOrder;
+
+    use crate::matchers;
+    use crate::paginator::Paginator;
+    use crate::search::{
+        parse_file_size_range, Matcher, Normalization, Search, SearchMode, SizeUnits,
+    };
+    use crate::DelimitedFormat;
+
+    #[tokio::test]
+    async fn test_set_preferred_variant() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let mut db = SQLiteDatabase::open(&path).await.unwrap();
+        let ids = db
+            .insert_books((0..3).map(|i| variant(&format!("Dune {}", i), i)))
+            .await
+            .unwrap();
+        db.merge_by_ids(&[(ids[0], ids[1]), (ids[0], ids[2])])
+            .await
+            .unwrap();
+
+        let mut db = SQLiteDatabase::open(&path).await.unwrap();
+        let preferred_title = |book: Arc<Book>| {
+            book.preferred_variant()
+                .and_then(|variant| variant.local_title.clone())
+        };
+        let book = db.get_book(ids[0]).await.unwrap();
+        assert_eq!(book.variants().len(), 3);
+        assert_eq!(preferred_title(book), Some("Dune 0".to_string()));
+
+        db.set_preferred_variant(ids[0], 2).await.unwrap();
+        let book = db.get_book(ids[0]).await.unwrap();
+        assert_eq!(preferred_title(book), Some("Dune 2".to_string()));
+
+        // The new order must be persisted, even if the database is rebuilt.
+        sqlx::query("VACUUM;")
+            .execute(&db.connection)
+            .await
+            .unwrap();
+        let db = SQLiteDatabase::open(&path).await.unwrap();
+        let book = db.get_book(ids[0]).await.unwrap();
+        let titles: Vec<_> = book
+            .variants()
+            .iter()
+            .map(|variant| variant.local_title.clone().unwrap())
+            .collect();
+        assert_eq!(titles, vec!["Dune 2", "Dune 0", "Dune 1"]);
+    }
+
+    #[tokio::test]
+    async fn test_add_variant_positions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let mut db = SQLiteDatabase::open(&path).await.unwrap();
+        let ids = db
+            .insert_books((0..3).map(|i| variant(&format!("Dune {}", i), i)))
+            .await
+            .unwrap();
+        db.merge_by_ids(&[(ids[0], ids[2]), (ids[0], ids[1])])
+            .await
+            .unwrap();
+        // Databases created before positions existed ordered variants by rowid.
+        sqlx::query("ALTER TABLE variants DROP COLUMN position;")
+            .execute(&db.connection)
+            .await
+            .unwrap();
+
+        let db = SQLiteDatabase::open(&path).await.unwrap();
+        let book = db.get_book(ids[0]).await.unwrap();
+        let titles: Vec<_> = book
+            .variants()
+            .iter()
+            .map(|variant| variant.local_title.clone().unwrap())
+            .collect();
+        assert_eq!(titles, vec!["Dune 0", "Dune 1", "Dune 2"]);
+    }
+
+    #[tokio::test]
+    async fn test_lowercase_tag_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let mut db = SQLiteDatabase::open(&path).await.unwrap();
+        let ids = db
+            .insert_books((0..2).map(|i| variant(&format!("Dune {}", i), i)))
+            .await
+            .unwrap();
+        // Earlier versions stored tag names as they were typed.
+        for (table, name, value, id) in [
+            ("named_tags", "Genre", "space opera", ids[0]),
+            ("named_tags", "Genre", "fantasy", ids[1]),
+            ("named_tags", "genre", "science fiction", ids[1]),
+            ("multimap_tags", "Mood", "tense", ids[0]),
+            ("multimap_tags", "mood", "tense", ids[0]),
+        ] {
+            sqlx::query(&format!(
+                "INSERT INTO {} (name, value, book_id) VALUES(?, ?, ?);",
+                table
+            ))
+            .bind(name)
+            .bind(value)
+            .bind(u64::from(id) as i64)
+            .execute(&db.connection)
+            .await
+            .unwrap();
+        }
+
+        let mut db = SQLiteDatabase::open(&path).await.unwrap();
+        let genre = ColumnIdentifier::NamedTag("genre".to_string());
+        let book = db.get_book(ids[0]).await.unwrap();
+        assert_eq!(book.get_column(&genre).as_deref(), Some("space opera"));
+        // Tags which conflict with the lowercase tag are left alone.
+        let book = db.get_book(ids[1]).await.unwrap();
+        assert_eq!(book.get_column(&genre).as_deref(), Some("science fiction"));
+        assert_eq!(
+            book.named_tags.get("Genre").map(String::as_str),
+            Some("fantasy")
+        );
+        assert_eq!(
+            db.column_stats(&ColumnIdentifier::MultiMap("mood".to_string()))
+                .await
+                .unwrap(),
+            vec![("tense".to_string(), 1)]
+        );
+
+        db.edit_book_with_id(ids[0], &[(genre.clone(), Edit::Delete)])
+            .await
+            .unwrap();
+        let db = SQLiteDatabase::open(&path).await.unwrap();
+        assert_eq!(db.get_book(ids[0]).await.unwrap().get_column(&genre), None);
+
+        // Names of inserted tags are lowercased.
+        let mut db = db;
+        let mut tagged = variant("Dune 2", 2);
+        tagged
+            .named_tags
+            .insert("Publisher".to_string(), "Chilton".to_string());
+        let id = db.insert_book(tagged).await.unwrap();
+        let db = SQLiteDatabase::open(&path).await.unwrap();
+        assert_eq!(
+            db.get_book(id)
+                .await
+                .unwrap()
+                .get_column(&ColumnIdentifier::from("Publisher"))
+                .as_deref(),
+            Some("Chilton")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_new_named_tag_is_column() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let mut db = SQLiteDatabase::open(&path).await.unwrap();
+        let id = db.insert_book(variant("Dune", 0)).await.unwrap();
+
+        let column = UniCase::new("publisher".to_string());
+        assert!(!db.has_column(&column).await.unwrap());
+
+        db.edit_book_with_id(
+            id,
+            &[(
+                ColumnIdentifier::NamedTag("publisher".to_string()),
+                Edit::Replace("Chilton".to_string()),
+            )],
+        )
+        .await
+        .unwrap();
+        assert!(db.has_column(&column).await.unwrap());
+
+        let db = SQLiteDatabase::open(&path).await.unwrap();
+        assert!(db.has_column(&column).await.unwrap());
+
+        // Columns added behind the cache's back must still be found.
+        for (table, name) in [("named_tags", "genre"), ("multimap_tags", "translator")] {
+            let column = UniCase::new(name.to_string());
+            assert!(!db.has_column(&column).await.unwrap());
+            sqlx::query(&format!(
+                "INSERT INTO {} (name, value, book_id) VALUES(?, 'value', ?);",
+                table
+            ))
+            .bind(name)
+            .bind(u64::from(id) as i64)
+            .execute(&db.connection)
+            .await
+            .unwrap();
+            assert!(db.has_column(&column).await.unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reclaim_space() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let mut db = SQLiteDatabase::open(&path).await.unwrap();
+        let connection = db.connection.clone();
+        let pragma = |name: &'static str| {
+            let connection = connection.clone();
+            async move {
+                sqlx::query_scalar::<_, i64>(&format!("PRAGMA {};", name))
+                    .fetch_one(&connection)
+                    .await
+                    .unwrap()
+            }
+        };
+        assert_eq!(pragma("auto_vacuum").await, 2);
+
+        let ids = db
+            .insert_books((0..100).map(|i| BookVariant {
+                description: Some("sand".repeat(1024)),
+                ..variant(&format!("Dune {}", i), i)
+            }))
+            .await
+            .unwrap();
+        let pages = pragma("page_count").await;
+
+        // Removing a few books leaves their pages free for later inserts.
+        db.remove_books(&ids[..5].iter().cloned().collect())
+            .await
+            .unwrap();
+        assert!(pragma("freelist_count").await > 0);
+
+        db.remove_books(&ids[5..90].iter().cloned().collect())
+            .await
+            .unwrap();
+        assert_eq!(pragma("freelist_count").await, 0);
+        assert!(pragma("page_count").await < pages);
+    }
+
+    #[tokio::test]
+    async fn test_enable_incremental_vacuum() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let old = SqlitePoolOptions::new()
+            .connect_with(
+                SqliteConnectOptions::new()
+                    .filename(&path)
+                    .create_if_missing(true),
+            )
+            .await
+            .unwrap();
+        sqlx::query(CREATE_BOOKS).execute(&old).await.unwrap();
+        old.close().await;
+
+        // Existing databases are only rebuilt when asked to.
+        let db = SQLiteDatabase::open(&path).await.unwrap();
+        assert!(!db.incremental_vacuum_enabled().await.unwrap());
+        assert!(db.enable_incremental_vacuum().await.unwrap());
+        assert!(db.incremental_vacuum_enabled().await.unwrap());
+        assert!(!db.enable_incremental_vacuum().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_stream_books() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let mut db = SQLiteDatabase::open(&path).await.unwrap();
+        let count = STREAM_BATCH_SIZE as usize + 10;
+        let ids = db
+            .insert_books((0..count).map(|i| BookVariant {
+                hash: [0; 32],
+                file_size: i as u64,
+                ..variant(&format!("Dune {}", i), 0)
+            }))
+            .await
+            .unwrap();
+
+        let db = SQLiteDatabase::open(&path).await.unwrap();
+        let books: Vec<_> = db.stream_books().try_collect().await.unwrap();
+        assert_eq!(books.iter().map(|book| book.id()).collect::<Vec<_>>(), ids);
+        assert_eq!(
+            books[count - 1].title.as_deref(),
+            Some(format!("Dune {}", count - 1).as_str())
+        );
+        // Streamed books must not be kept in memory.
+        assert!(db
+            .cache
+            .write()
+            .await
+            .get_books(&ids)
+            .iter()
+            .all(Option::is_none));
+    }
+
+    #[tokio::test]
+    async fn test_sort_by_surname() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let mut db = SQLiteDatabase::open(&path).await.unwrap();
+        let authored = |title: &str, hash: u8, author: &str| BookVariant {
+            additional_authors: Some(vec![author.to_string()]),
+            ..variant(title, hash)
+        };
+        let ids = db
+            .insert_books(
+                vec![
+                    authored("The Hobbit", 0, "Tolkien,  J.R.R."),
+                    authored("Dune", 1, "Frank Herbert"),
+                    authored("The Dispossessed", 2, "Ursula K. Le Guin"),
+                ]
+                .into_iter(),
+            )
+            .await
+            .unwrap();
+
+        let db = SQLiteDatabase::open(&path).await.unwrap();
+        let book = db.get_book(ids[0]).await.unwrap();
+        assert_eq!(book.authors(), Some(&["J.R.R. Tolkien".to_string()][..]));
+
+        let sort = vec![(ColumnIdentifier::Author, ColumnOrder::Ascending)].into_boxed_slice();
+        let mut paginator = Paginator::new(Arc::new(RwLock::new(db)), 0, sort);
+        paginator.set_prefetch(false);
+
+        // Scroll one book at a time, so that each page is read relative to the last book.
+        paginator.update_window_size(1).await.unwrap();
+        let mut titles = vec![];
+        for _ in 0..ids.len() {
+            titles.push(paginator.window()[0].title().unwrap().to_string());
+            paginator.scroll_down(1).await.unwrap();
+        }
+        assert_eq!(titles, vec!["Dune", "The Dispossessed", "The Hobbit"]);
+    }
+
+    #[tokio::test]
+    async fn test_normalize_written_authors() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let mut db = SQLiteDatabase::open(&path).await.unwrap();
+        let ids = db
+            .insert_books(vec![variant("Dune", 0)].into_iter())
+            .await
+            .unwrap();
+        let edit = |column: ColumnIdentifier, edit: Edit| [(column, edit)];
+        db.edit_book_with_id(
+            ids[0],
+            &edit(
+                ColumnIdentifier::Author,
+                Edit::Replace("Herbert,  Frank".to_string()),
+            ),
+        )
+        .await
+        .unwrap();
+        db.edit_book_with_id(
+            ids[0],
+            &edit(
+                ColumnIdentifier::Author,
+                Edit::Append("Herbert, Brian".to_string()),
+            ),
+        )
+        .await
+        .unwrap();
+        // Appending to an author which is then normalized to another is the same as replacing it.
+        db.edit_book_with_id(
+            ids[0],
+            &edit(
+                ColumnIdentifier::MultiMapExact("author".to_string(), "Brian Herbert".to_string()),
+                Edit::Append(",".to_string()),
+            ),
+        )
+        .await
+        .unwrap();
+        let expected = Some(vec![
+            "Frank Herbert".to_string(),
+            "Brian Herbert".to_string(),
+        ]);
+        assert_eq!(
+            db.get_book(ids[0]).await.unwrap().authors(),
+            expected.as_deref()
+        );
+
+        // Books which are imported are written in the same way.
+        let mut book = Book::from_variant(ids[0], variant("The Hobbit", 1));
+        book.authors = Some(vec![
+            "Tolkien, J.R.R.".to_string(),
+            "J.R.R. Tolkien".to_string(),
+        ]);
+        let imported = db.import_books(&[Arc::new(book)]).await.unwrap();
+
+        let db = SQLiteDatabase::open(&path).await.unwrap();
+        assert_eq!(
+            db.get_book(ids[0]).await.unwrap().authors(),
+            expected.as_deref()
+        );
+        assert_eq!(
+            db.get_book(imported[0]).await.unwrap().authors(),
+            Some(&["J.R.R. Tolkien".to_string()][..])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sort_by_date_added() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+
+        // A database created before dates were recorded.
+        let old = SqlitePoolOptions::new()
+            .connect_with(
+                SqliteConnectOptions::new()
+                    .filename(&path)
+                    .create_if_missing(true),
+            )
+            .await
+            .unwrap();
+        for statement in [
+            "CREATE TABLE books (book_id INTEGER NOT NULL PRIMARY KEY, title TEXT DEFAULT NULL, series_name TEXT DEFAULT NULL, series_id REAL DEFAULT NULL);",
+            "INSERT INTO books (title) VALUES ('Legacy');",
+        ] {
+            sqlx::query(statement).execute(&old).await.unwrap();
+        }
+        old.close().await;
+
+        let mut db = SQLiteDatabase::open(&path).await.unwrap();
+        let ids = db
+            .insert_books((0..2).map(|i| variant(&format!("Dune {}", i), i)))
+            .await
+            .unwrap();
+        let date_added = db.get_book(ids[0]).await.unwrap().date_added.clone();
+        assert!(date_added.is_some());
+        sqlx::query("UPDATE books SET date_added = '2020-01-01 00:00:00' WHERE book_id = ?;")
+            .bind(u64::from(ids[1]) as i64)
+            .execute(&db.connection)
+            .await
+            .unwrap();
+
+        let db = SQLiteDatabase::open(&path).await.unwrap();
+        assert_eq!(db.get_book(ids[0]).await.unwrap().date_added, date_added);
+
+        let sort = vec![(ColumnIdentifier::DateAdded, ColumnOrder::Descending)].into_boxed_slice();
+        let mut paginator = Paginator::new(Arc::new(RwLock::new(db)), 0, sort);
+        paginator.set_prefetch(false);
+
+        // Scroll one book at a time, so that each page is read relative to the last book.
+        paginator.update_window_size(1).await.unwrap();
+        let mut titles = vec![];
+        for _ in 0..3 {
+            titles.push(paginator.window()[0].title().unwrap().to_string());
+            paginator.scroll_down(1).await.unwrap();
+        }
+        assert_eq!(titles, vec!["Dune 0", "Dune 1", "Legacy"]);
+    }
+
+    #[tokio::test]
+    async fn test_remove_empty_books() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let mut db = SQLiteDatabase::open(&path).await.unwrap();
+        let ids = db
+            .insert_books((0..2).map(|i| variant(&format!("Dune {}", i), i)))
+            .await
+            .unwrap();
+        assert!(db.remove_empty_books().await.unwrap().is_empty());
+
+        // Removes the only variant of the first book.
+        sqlx::query("DELETE FROM variants WHERE book_id = ?")
+            .bind(u64::from(ids[0]) as i64)
+            .execute(&db.connection)
+            .await
+            .unwrap();
+
+        let removed = db.remove_empty_books().await.unwrap();
+        assert_eq!(removed, std::iter::once(ids[0]).collect());
+        assert!(db.get_book(ids[0]).await.is_err());
+        assert!(db.get_book(ids[1]).await.is_ok());
+    }
+
+    /// Deletes the row of the book with the given ID from the books table, leaving its other rows
+    /// behind, as if a deletion was interrupted.
+    async fn orphan_book(db: &SQLiteDatabase, id: BookID) {
+        let mut connection = db.connection.acquire().await.unwrap();
+        for statement in [
+            "PRAGMA foreign_keys = OFF;",
+            "DELETE FROM books WHERE book_id = ?;",
+            "PRAGMA foreign_keys = ON;",
+        ] {
+            sqlx::query(statement)
+                .bind(u64::from(id) as i64)
+                .execute(&mut connection)
+                .await
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_repair() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let mut db = SQLiteDatabase::open(&path).await.unwrap();
+        let ids = db
+            .insert_books((0..3).map(|i| variant(&format!("Dune {}", i), i)))
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO free_tags (value, book_id) VALUES ('unread', ?);")
+            .bind(u64::from(ids[0]) as i64)
+            .execute(&db.connection)
+            .await
+            .unwrap();
+        assert_eq!(
+            db.repair(RepairPolicy::Delete).await.unwrap(),
+            RepairReport::default()
+        );
+
+        orphan_book(&db, ids[0]).await;
+        orphan_book(&db, ids[1]).await;
+        let report = db.repair(RepairPolicy::Delete).await.unwrap();
+        assert_eq!((report.variants, report.free_tags), (2, 1));
+        assert_eq!(report.rows(), 3);
+        assert!(report.recreated.is_empty());
+        assert_eq!(db.repair(RepairPolicy::Delete).await.unwrap().rows(), 0);
+
+        orphan_book(&db, ids[2]).await;
+        let report = db.repair(RepairPolicy::Recreate).await.unwrap();
+        assert_eq!(report.rows(), 1);
+        assert_eq!(report.recreated, vec![ids[2]]);
+        assert_eq!(db.get_book(ids[2]).await.unwrap().variants().len(), 1);
+        assert_eq!(db.repair(RepairPolicy::Recreate).await.unwrap().rows(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_restore_books() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let mut db = SQLiteDatabase::open(&path).await.unwrap();
+        let ids = db
+            .insert_books((0..2).map(|i| variant(&format!("Dune {}", i), i)))
+            .await
+            .unwrap();
+        let snapshot: Vec<_> = db.get_books(&ids).await.unwrap().into_values().collect();
+
+        db.edit_book_with_id(
+            ids[0],
+            &[
+                (ColumnIdentifier::Title, Edit::Replace("edited".to_string())),
+                (ColumnIdentifier::Tags, Edit::Append("scifi".to_string())),
+            ],
+        )
+        .await
+        .unwrap();
+        db.remove_books(&std::iter::once(ids[1]).collect())
+            .await
+            .unwrap();
+
+        db.restore_books(&snapshot).await.unwrap();
+        let db = SQLiteDatabase::open(&path).await.unwrap();
+        for (i, id) in ids.iter().enumerate() {
+            let book = db.get_book(*id).await.unwrap();
+            assert_eq!(book.title(), Some(format!("Dune {}", i).as_str()));
+            assert!(book.free_tags().is_empty());
+            assert_eq!(book.variants().len(), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_refresh_variants() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let mut db = SQLiteDatabase::open(&path).await.unwrap();
+        let ids = db
+            .insert_books((0..2).map(|i| variant(&format!("Dune {}", i), i)))
+            .await
+            .unwrap();
+
+        let mut refreshed = variant("Dune 0", 0);
+        refreshed.description = Some("A novel by Frank Herbert.".to_string());
+        refreshed.hash = [5; 32];
+        refreshed.file_size = 10;
+        // Variants are matched by path, so the variant of the second book is not modified.
+        db.refresh_variants(&[(ids[0], refreshed.clone()), (ids[1], refreshed)])
+            .await
+            .unwrap();
+
+        let db = SQLiteDatabase::open(&path).await.unwrap();
+        let book = db.get_book(ids[0]).await.unwrap();
+        let variant = &book.variants()[0];
+        assert_eq!(variant.hash, [5; 32]);
+        assert_eq!(variant.file_size, 10);
+        assert_eq!(
+            book.description().map(String::as_str),
+            Some("A novel by Frank Herbert.")
+        );
+        assert_eq!(
+            db.get_book(ids[1]).await.unwrap().variants()[0].hash,
+            [1; 32]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_edit_multimap() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let mut db = SQLiteDatabase::open(&path).await.unwrap();
+        let mut book = variant("Dune", 0);
+        book.additional_authors = Some(vec![
+            "Frank Herbrt".to_string(),
+            "Brian Herbert".to_string(),
+        ]);
+        let id = db.insert_book(book).await.unwrap();
+
+        let author =
+            |value: &str| ColumnIdentifier::MultiMapExact("author".to_string(), value.to_string());
+        let genre = ColumnIdentifier::MultiMap("genre".to_string());
+        db.edit_book_with_id(
+            id,
+            &[
+                (
+                    author("Frank Herbrt"),
+                    Edit::Replace("Frank Herbert".to_string()),
+                ),
+                (genre.clone(), Edit::Append("scifi".to_string())),
+                (genre.clone(), Edit::Append("classic".to_string())),
+                (genre.clone(), Edit::Append("scifi".to_string())),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let db = SQLiteDatabase::open(&path).await.unwrap();
+        assert_eq!(
+            db.get_book(id).await.unwrap().authors(),
+            Some(&["Frank Herbert".to_string(), "Brian Herbert".to_string()][..])
+        );
+        assert_eq!(
+            db.column_stats(&genre).await.unwrap(),
+            vec![("classic".to_string(), 1), ("scifi".to_string(), 1)]
+        );
+
+        let mut db = db;
+        db.edit_book_with_id(
+            id,
+            &[
+                (author("Brian Herbert"), Edit::Delete),
+                (
+                    ColumnIdentifier::MultiMapExact("genre".to_string(), "scifi".to_string()),
+                    Edit::Append(" fiction".to_string()),
+                ),
+                (
+                    ColumnIdentifier::MultiMapExact("genre".to_string(), "classic".to_string()),
+                    Edit::Delete,
+                ),
+            ],
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            db.get_book(id).await.unwrap().authors(),
+            Some(&["Frank Herbert".to_string()][..])
+        );
+        assert_eq!(
+            db.column_stats(&genre).await.unwrap(),
+            vec![("scifi fiction".to_string(), 1)]
+        );
+
+        // Replacing authors removes the existing authors.
+        db.edit_book_with_id(
+            id,
+            &[
+                (
+                    ColumnIdentifier::Author,
+                    Edit::Replace("Kevin J. Anderson".to_string()),
+                ),
+                (genre.clone(), Edit::Delete),
+            ],
+        )
+        .await
+        .unwrap();
+        let db = SQLiteDatabase::open(&path).await.unwrap();
+        assert_eq!(
+            db.get_book(id).await.unwrap().authors(),
+            Some(&["Kevin J. Anderson".to_string()][..])
+        );
+        assert!(db.column_stats(&genre).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_field_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let mut db = SQLiteDatabase::open(&path).await.unwrap();
+        db.set_field_limits(FieldLimits {
+            title: Some(8),
+            description: None,
+        });
+        let mut book = variant(&"Dune ".repeat(1000), 0);
+        book.description = Some("a".repeat(100));
+        let id = db.insert_book(book).await.unwrap();
+
+        let db = SQLiteDatabase::open(&path).await.unwrap();
+        let book = db.get_book(id).await.unwrap();
+        assert_eq!(book.title(), Some("Dune Du…"));
+        assert_eq!(book.description().map(String::len), Some(100));
+
+        let mut short = "Dune".to_string();
+        truncate_field(&mut short, 4, "title", Path::new("Dune.epub"));
+        assert_eq!(short, "Dune");
+    }
+
+    #[tokio::test]
+    async fn test_insert_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let mut db = SQLiteDatabase::open(&path).await.unwrap();
+        let first = db.insert_book(variant("Dune", 0)).await.unwrap();
+
+        let copy = || BookVariant {
+            path: PathBuf::from("copy/Dune.epub"),
+            ..variant("Dune", 0)
+        };
+        let new = db.insert_book(copy()).await.unwrap();
+        assert_ne!(new, first);
+        db.remove_books(&std::iter::once(new).collect())
+            .await
+            .unwrap();
+
+        // Only the IDs of new books are returned.
+        db.set_insert_policy(InsertPolicy::SkipDuplicates);
+        let ids = db
+            .insert_books(vec![copy(), variant("Emma", 1)].into_iter())
+            .await
+            .unwrap();
+        assert_eq!(ids.len(), 1);
+        assert_ne!(ids[0], first);
+        assert_eq!(db.get_book(first).await.unwrap().variants().len(), 1);
+
+        db.set_insert_policy(InsertPolicy::AttachVariant);
+        assert!(db
+            .insert_books(std::iter::once(copy()))
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(db.get_book(first).await.unwrap().variants().len(), 2);
+        assert_eq!(db.insert_book(copy()).await.unwrap(), first);
+        assert_eq!(db.get_book(first).await.unwrap().variants().len(), 3);
+        let db = SQLiteDatabase::open(&path).await.unwrap();
+        assert_eq!(db.get_book(first).await.unwrap().variants().len(), 3);
+        let books: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM books")
+            .fetch_one(&db.connection)
+            .await
+            .unwrap();
+        assert_eq!(books, 2);
+    }
+
+    #[tokio::test]
+    async fn test_insert_batch_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let mut db = SQLiteDatabase::open(&path).await.unwrap();
+        db.set_insert_batch_size(0);
+        assert_eq!(db.insert_batch_size, 1);
+
+        // Files in later batches are found in the books committed by earlier batches.
+        db.set_insert_batch_size(2);
+        db.set_insert_policy(InsertPolicy::SkipDuplicates);
+        let ids = db
+            .insert_books(
+                vec![
+                    variant("Dune 0", 0),
+                    variant("Dune 1", 1),
+                    variant("Dune 2", 2),
+                    variant("Dune 0", 0),
+                    variant("Dune 3", 3),
+                ]
+                .into_iter(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(ids.len(), 4);
+
+        let db = SQLiteDatabase::open(&path).await.unwrap();
+        let books: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM books")
+            .fetch_one(&db.connection)
+            .await
+            .unwrap();
+        assert_eq!(books, 4);
+    }
+
+    /// Creates a database with three books titled Dune, two of which share an author, and a
+    /// copy of the first book's file titled Emma.
+    async fn duplicates_db(dir: &tempfile::TempDir) -> (SQLiteDatabase, Vec<BookID>) {
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        let authored = |title: &str, hash: u8, author: &str| BookVariant {
+            additional_authors: Some(vec![author.to_string()]),
+            ..variant(title, hash)
+        };
+        let ids = db
+            .insert_books(
+                vec![
+                    authored("Dune", 0, "Frank Herbert"),
+                    authored("Dune", 1, "frank herbert"),
+                    authored("Dune", 2, "Brian Herbert"),
+                    authored("Emma", 0, "Jane Austen"),
+                ]
+                .into_iter(),
+            )
+            .await
+            .unwrap();
+        (db, ids)
+    }
+
+    #[tokio::test]
+    async fn test_insert_series() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let mut db = SQLiteDatabase::open(&path).await.unwrap();
+        let in_series = |title: &str, hash: u8, index: Option<f32>| BookVariant {
+            series: Some(Series {
+                name: "Dune Chronicles".to_string(),
+                index,
+            }),
+            ..variant(title, hash)
+        };
+        let ids = db
+            .insert_books(
+                vec![
+                    in_series("Dune Messiah", 0, Some(2.)),
+                    in_series("Children of Dune", 1, None),
+                    variant("Hyperion", 2),
+                ]
+                .into_iter(),
+            )
+            .await
+            .unwrap();
+
+        let series: Vec<(Option<String>, Option<f32>)> =
+            sqlx::query_as("SELECT series_name, series_id FROM books ORDER BY book_id;")
+                .fetch_all(&db.connection)
+                .await
+                .unwrap();
+        assert_eq!(
+            series,
+            vec![
+                (Some("Dune Chronicles".to_string()), Some(2.)),
+                (Some("Dune Chronicles".to_string()), None),
+                (None, None),
+            ]
+        );
+
+        let db = SQLiteDatabase::open(&path).await.unwrap();
+        let book = db.get_book(ids[0]).await.unwrap();
+        assert_eq!(
+            book.get_column(&ColumnIdentifier::Series).as_deref(),
+            Some("Dune Chronicles [2]")
+        );
+        assert!(book.variants()[0].series.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_find_duplicates() {
+        let dir = tempfile::tempdir().unwrap();
+        let (db, ids) = duplicates_db(&dir).await;
+        assert_eq!(
+            db.find_duplicates(DetectionStrategy::Title).await.unwrap(),
+            vec![ids[0..3].to_vec()]
+        );
+        assert_eq!(
+            db.find_duplicates(DetectionStrategy::AuthorOverlap)
+                .await
+                .unwrap(),
+            vec![ids[0..2].to_vec()]
+        );
+        assert_eq!(
+            db.find_duplicates(DetectionStrategy::VariantHash)
+                .await
+                .unwrap(),
+            vec![vec![ids[0], ids[3]]]
+        );
+
+        // No books are merged.
+        let books: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM books")
+            .fetch_one(&db.connection)
+            .await
+            .unwrap();
+        assert_eq!(books, 4);
+    }
+
+    #[tokio::test]
+    async fn test_deduplicate() {
+        let set = |ids: &[BookID]| ids.iter().cloned().collect::<HashSet<_>>();
+
+        let dir = tempfile::tempdir().unwrap();
+        let (mut db, ids) = duplicates_db(&dir).await;
+        let (removed, conflicts) = db
+            .deduplicate(DetectionStrategy::Title, ConflictResolution::Skip)
+            .await
+            .unwrap();
+        assert_eq!(removed, set(&ids[1..3]));
+        assert!(conflicts.is_empty());
+        assert_eq!(db.get_book(ids[0]).await.unwrap().variants().len(), 3);
+
+        let dir = tempfile::tempdir().unwrap();
+        let (mut db, ids) = duplicates_db(&dir).await;
+        let (removed, _) = db
+            .deduplicate(DetectionStrategy::AuthorOverlap, ConflictResolution::Skip)
+            .await
+            .unwrap();
+        assert_eq!(removed, set(&ids[1..2]));
+
+        // Dune and Emma share a file, but have different titles.
+        let dir = tempfile::tempdir().unwrap();
+        let (mut db, ids) = duplicates_db(&dir).await;
+        let (removed, conflicts) = db
+            .deduplicate(DetectionStrategy::VariantHash, ConflictResolution::Skip)
+            .await
+            .unwrap();
+        assert!(removed.is_empty());
+        assert!(conflicts.is_empty());
+
+        let (removed, conflicts) = db
+            .deduplicate(DetectionStrategy::VariantHash, ConflictResolution::Return)
+            .await
+            .unwrap();
+        assert!(removed.is_empty());
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].book1.id(), ids[0]);
+        assert_eq!(conflicts[0].book2.id(), ids[3]);
+        assert_eq!(conflicts[0].conflicts, vec![ColumnIdentifier::Title]);
+
+        let (removed, _) = db
+            .deduplicate(
+                DetectionStrategy::VariantHash,
+                ConflictResolution::KeepOriginal,
+            )
+            .await
+            .unwrap();
+        assert_eq!(removed, set(&ids[3..]));
+        let book = db.get_book(ids[0]).await.unwrap();
+        assert_eq!(book.title(), Some("Dune"));
+        assert_eq!(book.variants().len(), 2);
+        assert!(db.get_book(ids[3]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_has_cover_matcher() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let mut db = SQLiteDatabase::open(&path).await.unwrap();
+        let ids = db
+            .insert_books((0..3).map(|i| variant(&format!("Dune {}", i), i)))
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO covers (image, book_id) VALUES (?, ?)")
+            .bind(vec![0u8; 4])
+            .bind(u64::from(ids[1]) as i64)
+            .execute(&db.connection)
+            .await
+            .unwrap();
+
+        let db = SQLiteDatabase::open(&path).await.unwrap();
+        let matcher = Search {
+            mode: SearchMode::Empty,
+            column: ColumnIdentifier::Cover,
+            search: String::new(),
+        }
+        .into_matcher()
+        .unwrap();
+        let (query, bound_variables) =
+            QueryBuilder::default().join_cols(None, &vec![matcher.box_clone()].into_boxed_slice());
+        let books = db
+            .read_selected_books(&query, &bound_variables)
+            .await
+            .unwrap();
+
+        let mut found: Vec<_> = books.iter().map(|book| book.id()).collect();
+        found.sort();
+        assert_eq!(found, vec![ids[0], ids[2]]);
+        assert!(books.iter().all(|book| matcher.is_match(book)));
+        assert!(db.get_book(ids[1]).await.unwrap().has_cover);
+    }
+
+    #[tokio::test]
+    async fn test_empty_matcher() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let mut db = SQLiteDatabase::open(&path).await.unwrap();
+        let ids = db
+            .insert_books((0..3).map(|i| variant(&format!("Dune {}", i), i)))
+            .await
+            .unwrap();
+        let replace =
+            |column: ColumnIdentifier, value: &str| [(column, Edit::Replace(value.to_string()))];
+        db.edit_book_with_id(
+            ids[0],
+            &replace(ColumnIdentifier::NamedTag("genre".to_string()), "fantasy"),
+        )
+        .await
+        .unwrap();
+        db.edit_book_with_id(ids[0], &replace(ColumnIdentifier::Series, "Dune [1]"))
+            .await
+            .unwrap();
+        db.edit_book_with_id(
+            ids[1],
+            &replace(ColumnIdentifier::MultiMap("mood".to_string()), "tense"),
+        )
+        .await
+        .unwrap();
+        db.edit_book_with_id(ids[1], &replace(ColumnIdentifier::Author, "Frank Herbert"))
+            .await
+            .unwrap();
+
+        // Books which have no tag row at all are empty, as well as books with an empty value.
+        let db = SQLiteDatabase::open(&path).await.unwrap();
+        for (column, expected) in [
+            (
+                ColumnIdentifier::NamedTag("genre".to_string()),
+                vec![ids[1], ids[2]],
+            ),
+            (
+                ColumnIdentifier::MultiMap("mood".to_string()),
+                vec![ids[0], ids[2]],
+            ),
+            (ColumnIdentifier::Series, vec![ids[1], ids[2]]),
+            (ColumnIdentifier::Author, vec![ids[0], ids[2]]),
+            (ColumnIdentifier::Tags, ids.clone()),
+            (ColumnIdentifier::Title, vec![]),
+        ] {
+            let matcher = Search {
+                mode: SearchMode::Empty,
+                column: column.clone(),
+                search: String::new(),
+            }
+            .into_matcher()
+            .unwrap();
+            let (query, bound_variables) = QueryBuilder::default()
+                .join_cols(None, &vec![matcher.box_clone()].into_boxed_slice());
+            let books = db
+                .read_selected_books(&query, &bound_variables)
+                .await
+                .unwrap();
+            let mut found: Vec<_> = books.iter().map(|book| book.id()).collect();
+            found.sort();
+            assert_eq!(found, expected, "{:?}", column);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_set_cover() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        let ids = db
+            .insert_books(vec![variant("Dune", 0)].into_iter())
+            .await
+            .unwrap();
+        assert!(!db.get_book(ids[0]).await.unwrap().has_cover);
+        assert_eq!(db.get_cover(ids[0]).await.unwrap(), None);
+
+        db.set_cover(ids[0], &[1, 2, 3]).await.unwrap();
+        db.set_cover(ids[0], &[4, 5]).await.unwrap();
+        assert!(db.get_book(ids[0]).await.unwrap().has_cover);
+        assert_eq!(db.get_cover(ids[0]).await.unwrap(), Some(vec![4, 5]));
+        let images: Vec<Vec<u8>> = sqlx::query_scalar("SELECT image FROM covers")
+            .fetch_all(&db.connection)
+            .await
+            .unwrap();
+        assert_eq!(images, vec![vec![4, 5]]);
+
+        let missing = BookID::try_from(100).unwrap();
+        assert!(matches!(
+            db.set_cover(missing, &[1]).await,
+            Err(DatabaseError::BookNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_column_matchers() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        let ids = db
+            .insert_books(
+                vec![
+                    variant("Dune", 0),
+                    variant("Dune Messiah", 1),
+                    variant("dune*", 2),
+                    variant("Emma", 3),
+                ]
+                .into_iter(),
+            )
+            .await
+            .unwrap();
+        let db = Arc::new(RwLock::new(db));
+
+        let read = |matcher: Box<dyn Matcher + Send + Sync>| {
+            let paginator = Paginator::new(db.clone(), 0, vec![].into_boxed_slice())
+                .bind_match(vec![matcher].into_boxed_slice());
+            async move {
+                let books = paginator.read_all().await.unwrap();
+                assert!(books
+                    .iter()
+                    .all(|book| paginator.matchers()[0].is_match(book)));
+                books.iter().map(|book| book.id()).collect::<Vec<_>>()
+            }
+        };
+
+        let title = || matchers::column(ColumnIdentifier::Title);
+        assert_eq!(
+            read(title().contains("Dune").unwrap()).await,
+            vec![ids[0], ids[1]]
+        );
+        assert_eq!(
+            read(matchers::column("TITLE").contains("e*").unwrap()).await,
+            vec![ids[2]]
+        );
+        assert_eq!(read(title().equals("Dune").unwrap()).await, vec![ids[0]]);
+
+        assert_eq!(
+            read(title().matches_regex("^E").unwrap()).await,
+            vec![ids[3]]
+        );
+        assert_eq!(
+            read(title().matches_regex("(?i)^dune$").unwrap()).await,
+            vec![ids[0]]
+        );
+        assert_eq!(
+            read(title().matches_regex(r"\*$").unwrap()).await,
+            vec![ids[2]]
+        );
+        assert!(title().matches_regex("(").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_normalized_matchers() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        let author = |title: &str, author: &str, hash: u8| BookVariant {
+            additional_authors: Some(vec![author.to_string()]),
+            ..variant(title, hash)
+        };
+        let ids = db
+            .insert_books(
+                vec![
+                    author("Jane Eyre", "Charlotte Brontë", 0),
+                    author("Wuthering Heights", "EMILY BRONTE", 1),
+                    author("Emma", "Jane Austen", 2),
+                ]
+                .into_iter(),
+            )
+            .await
+            .unwrap();
+        let db = Arc::new(RwLock::new(db));
+
+        let read = |matcher: Box<dyn Matcher + Send + Sync>| {
+            let paginator = Paginator::new(db.clone(), 0, vec![].into_boxed_slice())
+                .bind_match(vec![matcher].into_boxed_slice());
+            async move {
+                let books = paginator.read_all().await.unwrap();
+                assert!(books
+                    .iter()
+                    .all(|book| paginator.matchers()[0].is_match(book)));
+                books.iter().map(|book| book.id()).collect::<Vec<_>>()
+            }
+        };
+
+        let authors =
+            |normalization| matchers::column(ColumnIdentifier::Author).normalized(normalization);
+        assert_eq!(
+            read(authors(Normalization::Exact).contains("bronte").unwrap()).await,
+            vec![]
+        );
+        assert_eq!(
+            read(
+                authors(Normalization::CaseInsensitive)
+                    .contains("bronte")
+                    .unwrap()
+            )
+            .await,
+            vec![ids[1]]
+        );
+        assert_eq!(
+            read(
+                authors(Normalization::Unaccented)
+                    .contains("bronte")
+                    .unwrap()
+            )
+            .await,
+            vec![ids[0], ids[1]]
+        );
+        assert_eq!(
+            read(
+                authors(Normalization::Unaccented)
+                    .equals("Charlotte Bronte")
+                    .unwrap()
+            )
+            .await,
+            vec![ids[0]]
+        );
+        assert_eq!(
+            read(
+                authors(Normalization::Unaccented)
+                    .matches_regex("^(charlotte|emily) brontë$")
+                    .unwrap()
+            )
+            .await,
+            vec![ids[0], ids[1]]
+        );
+
+        let search = Search {
+            mode: SearchMode::Default,
+            column: ColumnIdentifier::Author,
+            search: "BRONTË".to_string(),
+        };
+        assert_eq!(
+            read(
+                search
+                    .into_normalized_matcher(Normalization::Unaccented)
+                    .unwrap()
+            )
+            .await,
+            vec![ids[0], ids[1]]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_extension_matcher() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let mut db = SQLiteDatabase::open(&path).await.unwrap();
+        let pdf = |title: &str, hash: u8| BookVariant {
+            book_type: BookType::PDF,
+            path: PathBuf::from(format!("{}.PDF", title)),
+            ..variant(title, hash)
+        };
+        let ids = db
+            .insert_books(
+                vec![variant("Dune", 0), pdf("Emma", 1), pdf("Dune Messiah", 2)].into_iter(),
+            )
+            .await
+            .unwrap();
+
+        let db = SQLiteDatabase::open(&path).await.unwrap();
+        let matcher = Search {
+            mode: SearchMode::Extension,
+            column: ColumnIdentifier::Variants,
+            search: ".pdf".to_string(),
+        }
+        .into_matcher()
+        .unwrap();
+        let (query, bound_variables) =
+            QueryBuilder::default().join_cols(None, &vec![matcher.box_clone()].into_boxed_slice());
+        let books = db
+            .read_selected_books(&query, &bound_variables)
+            .await
+            .unwrap();
+
+        let mut found: Vec<_> = books.iter().map(|book| book.id()).collect();
+        found.sort();
+        assert_eq!(found, vec![ids[1], ids[2]]);
+        assert!(books.iter().all(|book| matcher.is_match(book)));
+        assert!(!matcher.is_match(&db.get_book(ids[0]).await.unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_full_text_matcher() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let mut db = SQLiteDatabase::open(&path).await.unwrap();
+        let ids = db
+            .insert_books(
+                vec![
+                    BookVariant {
+                        additional_authors: Some(vec!["Frank Herbert".to_string()]),
+                        ..variant("Dune", 0)
+                    },
+                    BookVariant {
+                        description: Some("A novel by Frank Herbert's son.".to_string()),
+                        ..variant("Dune: House Atreides", 1)
+                    },
+                    variant("Emma", 2),
+                    variant("Les Misérables", 3),
+                ]
+                .into_iter(),
+            )
+            .await
+            .unwrap();
+        // Edits reach the index through the triggers.
+        db.edit_book_with_id(
+            ids[2],
+            &[(
+                ColumnIdentifier::Author,
+                Edit::Append("Jane Austen".to_string()),
+            )],
+        )
+        .await
+        .unwrap();
+
+        let db = SQLiteDatabase::open(&path).await.unwrap();
+        let find = |search: &str| {
+            Search {
+                mode: SearchMode::FullText,
+                column: ColumnIdentifier::ID,
+                search: search.to_string(),
+            }
+            .into_matcher()
+            .unwrap()
+        };
+        for (search, expected) in [
+            ("herb", vec![ids[0], ids[1]]),
+            ("HERBERT dune", vec![ids[0], ids[1]]),
+            ("atreides son", vec![ids[1]]),
+            ("austen", vec![ids[2]]),
+            // Punctuation separates words, rather than being read as FTS5 syntax.
+            ("\"emma*", vec![ids[2]]),
+            // Accents are ignored, as they are by the unicode61 tokenizer.
+            ("miserables", vec![ids[3]]),
+            ("MISÉR", vec![ids[3]]),
+            // Words must start with the search terms.
+            ("une", vec![]),
+        ] {
+            let matcher = find(search);
+            // Databases without FTS5 use the fallback query, which must find the same books.
+            for full_text_search in [db.supports_full_text_search(), false] {
+                let (query, bound_variables) = QueryBuilder::default()
+                    .full_text_search(full_text_search)
+                    .join_cols(None, &vec![matcher.box_clone()].into_boxed_slice());
+                let books = db
+                    .read_selected_books(&query, &bound_variables)
+                    .await
+                    .unwrap();
+                let mut found: Vec<_> = books.iter().map(|book| book.id()).collect();
+                found.sort();
+                assert_eq!(found, expected, "{} ({})", search, full_text_search);
+            }
+            for id in &ids {
+                let book = db.get_book(*id).await.unwrap();
+                assert_eq!(matcher.is_match(&book), expected.contains(id), "{}", search);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_file_size_matcher() {
+        const MB: u64 = 1000 * 1000;
+        const MIB: u64 = 1024 * 1024;
+        assert_eq!(
+            parse_file_size_range("1MB..20MB", SizeUnits::Decimal),
+            Some((MB, 20 * MB))
+        );
+        assert_eq!(
+            parse_file_size_range("1mb..20MiB", SizeUnits::Binary),
+            Some((MIB, 20 * MIB))
+        );
+        assert_eq!(
+            parse_file_size_range("1.5KB..", SizeUnits::Decimal),
+            Some((1500, i64::MAX as u64))
+        );
+        assert_eq!(
+            parse_file_size_range("..512", SizeUnits::Decimal),
+            Some((0, 512))
+        );
+        assert_eq!(parse_file_size_range("20MB..1MB", SizeUnits::Decimal), None);
+        assert_eq!(parse_file_size_range("1TB..2TB", SizeUnits::Decimal), None);
+        assert_eq!(parse_file_size_range("20MB", SizeUnits::Decimal), None);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let mut db = SQLiteDatabase::open(&path).await.unwrap();
+        let sized = |title: &str, hash: u8, file_size: u64| BookVariant {
+            file_size,
+            ..variant(title, hash)
+        };
+        let ids = db
+            .insert_books(
+                vec![
+                    sized("Small", 0, 500 * 1000),
+                    sized("Medium", 1, 5 * MB),
+                    sized("Edge", 2, 20 * MB),
+                    sized("Large", 3, 50 * MB),
+                ]
+                .into_iter(),
+            )
+            .await
+            .unwrap();
+
+        let db = SQLiteDatabase::open(&path).await.unwrap();
+        let matcher = Search {
+            mode: SearchMode::FileSize(SizeUnits::Decimal),
+            column: ColumnIdentifier::Variants,
+            search: "1MB..20MB".to_string(),
+        }
+        .into_matcher()
+        .unwrap();
+        let (query, bound_variables) =
+            QueryBuilder::default().join_cols(None, &vec![matcher.box_clone()].into_boxed_slice());
+        let books = db
+            .read_selected_books(&query, &bound_variables)
+            .await
+            .unwrap();
+
+        let mut found: Vec<_> = books.iter().map(|book| book.id()).collect();
+        found.sort();
+        assert_eq!(found, vec![ids[1], ids[2]]);
+        assert!(books.iter().all(|book| matcher.is_match(book)));
+        assert!(!matcher.is_match(&db.get_book(ids[3]).await.unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_in_series_matcher() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let mut db = SQLiteDatabase::open(&path).await.unwrap();
+        let ids = db
+            .insert_books((0..4).map(|i| variant(&format!("Book {}", i), i)))
+            .await
+            .unwrap();
+        for (id, series) in [
+            (ids[0], "Dune [1]"),
+            (ids[1], "The Expanse [2]"),
+            (ids[3], "Dune"),
+        ] {
+            db.edit_book_with_id(
+                id,
+                &[(ColumnIdentifier::Series, Edit::Replace(series.to_string()))],
+            )
+            .await
+            .unwrap();
+        }
+
+        let db = SQLiteDatabase::open(&path).await.unwrap();
+        for (series, expected) in [
+            ("", vec![ids[0], ids[1], ids[3]]),
+            ("Dune", vec![ids[0], ids[3]]),
+        ] {
+            let matcher = Search {
+                mode: SearchMode::InSeries,
+                column: ColumnIdentifier::Series,
+                search: series.to_string(),
+            }
+            .into_matcher()
+            .unwrap();
+            let (query, bound_variables) = QueryBuilder::default()
+                .join_cols(None, &vec![matcher.box_clone()].into_boxed_slice());
+            let books = db
+                .read_selected_books(&query, &bound_variables)
+                .await
+                .unwrap();
+
+            let mut found: Vec<_> = books.iter().map(|book| book.id()).collect();
+            found.sort();
+            assert_eq!(found, expected);
+            assert!(books.iter().all(|book| matcher.is_match(book)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_title_search_without_fts5() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        db.disable_full_text_search().await.unwrap();
+        assert!(!db.supports_full_text_search());
+        assert!(title_search_query(db.fts5).contains("LIKE"));
+
+        // Writes must not touch the full-text index once it is disabled.
+        let ids = db
+            .insert_books(
+                ["Dune", "Emma", "Dune Messiah"]
+                    .iter()
+                    .enumerate()
+                    .map(|(i, title)| variant(title, i as u8)),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            db.search_titles("dune").await.unwrap(),
+            vec![ids[0], ids[2]]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_fts() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        if !db.supports_full_text_search() {
+            assert_eq!(db.rebuild_fts().await.unwrap(), None);
+            return;
+        }
+
+        let ids = db
+            .insert_books(
+                ["Dune", "Emma", "Dune Messiah"]
+                    .iter()
+                    .enumerate()
+                    .map(|(i, title)| variant(title, i as u8)),
+            )
+            .await
+            .unwrap();
+
+        // Desynchronize the index from the books table.
+        sqlx::query("DELETE FROM books_fts;")
+            .execute(&db.connection)
+            .await
+            .unwrap();
+        assert!(db.search_titles("dune").await.unwrap().is_empty());
+
+        let report = db.rebuild_fts().await.unwrap().unwrap();
+        assert_eq!((report.books, report.indexed), (3, 3));
+        assert!(report.is_consistent());
+        assert_eq!(
+            db.search_titles("dune").await.unwrap(),
+            vec![ids[0], ids[2]]
+        );
+    }
+
+    async fn descriptions(db: &SQLiteDatabase, id: BookID) -> Vec<Option<String>> {
+        sqlx::query_scalar("SELECT description FROM variants WHERE book_id = ? ORDER BY position;")
+            .bind(u64::from(id) as i64)
+            .fetch_all(&db.connection)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_variant_edit_policy() {
+        let edits = [(
+            ColumnIdentifier::Description,
+            Edit::Replace("edited".to_string()),
+        )];
+        let edited = Some("edited".to_string());
+
+        for (policy, expected) in [
+            (VariantEditPolicy::AllVariants, edited.clone()),
+            (VariantEditPolicy::PrimaryOnly, None),
+        ] {
+            let dir = tempfile::tempdir().unwrap();
+            let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+                .await
+                .unwrap();
+            let ids = db
+                .insert_books((0..3).map(|i| variant(&format!("Dune {}", i), i)))
+                .await
+                .unwrap();
+            db.merge_by_ids(&[(ids[0], ids[1])]).await.unwrap();
+            db.remove_books(&vec![ids[1]].into_iter().collect())
+                .await
+                .unwrap();
+
+            db.edit_selected(&Selection::All(vec![].into_boxed_slice()), &edits, policy)
+                .await
+                .unwrap();
+
+            assert_eq!(
+                descriptions(&db, ids[0]).await,
+                vec![edited.clone(), expected],
+                "{:?}",
+                policy
+            );
+            assert_eq!(descriptions(&db, ids[2]).await, vec![edited.clone()]);
+            assert_eq!(
+                db.get_book(ids[0]).await.unwrap().description(),
+                edited.as_ref()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_clear_tags() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let mut db = SQLiteDatabase::open(&path).await.unwrap();
+        let ids = db
+            .insert_books((0..2).map(|i| variant(&format!("Book {}", i), i)))
+            .await
+            .unwrap();
+        let edits = [
+            (
+                ColumnIdentifier::Author,
+                Edit::Replace("Frank Herbert".to_string()),
+            ),
+            (ColumnIdentifier::Tags, Edit::Append("scifi".to_string())),
+            (
+                ColumnIdentifier::NamedTag("genre".to_string()),
+                Edit::Replace("scifi".to_string()),
+            ),
+        ];
+        for &id in &ids {
+            db.edit_book_with_id(id, &edits).await.unwrap();
+        }
+
+        db.clear_tags(&ids[..1]).await.unwrap();
+
+        let db = SQLiteDatabase::open(&path).await.unwrap();
+        let cleared = db.get_book(ids[0]).await.unwrap();
+        assert!(cleared.free_tags.is_empty());
+        assert!(cleared.named_tags.is_empty());
+        assert_eq!(cleared.title(), Some("Book 0"));
+        assert_eq!(cleared.authors(), Some(&["Frank Herbert".to_string()][..]));
+
+        let kept = db.get_book(ids[1]).await.unwrap();
+        assert!(kept.free_tags.contains("scifi"));
+        assert_eq!(kept.named_tags.get("genre"), Some(&"scifi".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_rename_and_delete_tags() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let mut db = SQLiteDatabase::open(&path).await.unwrap();
+        let ids = db
+            .insert_books((0..3).map(|i| variant(&format!("Book {}", i), i)))
+            .await
+            .unwrap();
+        let append =
+            |column: ColumnIdentifier, value: &str| (column, Edit::Append(value.to_string()));
+        let replace =
+            |column: ColumnIdentifier, value: &str| (column, Edit::Replace(value.to_string()));
+        let named = |name: &str| ColumnIdentifier::NamedTag(name.to_string());
+        let multimap = |name: &str| ColumnIdentifier::MultiMap(name.to_string());
+        db.edit_book_with_id(
+            ids[0],
+            &[
+                append(ColumnIdentifier::Tags, "scfi"),
+                replace(named("gnere"), "space opera"),
+            ],
+        )
+        .await
+        .unwrap();
+        db.edit_book_with_id(
+            ids[1],
+            &[
+                append(ColumnIdentifier::Tags, "scfi"),
+                append(ColumnIdentifier::Tags, "scifi"),
+                replace(named("gnere"), "space opera"),
+                replace(named("genre"), "fantasy"),
+            ],
+        )
+        .await
+        .unwrap();
+        db.edit_book_with_id(
+            ids[2],
+            &[
+                replace(ColumnIdentifier::Author, "Frank Herbert"),
+                append(multimap("mood"), "tense"),
+            ],
+        )
+        .await
+        .unwrap();
+
+        // Books which already have the new tag keep their value.
+        assert_eq!(db.rename_tag("scfi", "scifi").await.unwrap(), 2);
+        assert_eq!(db.rename_tag("gnere", "genre").await.unwrap(), 2);
+        assert_eq!(db.rename_tag("mood", "tone").await.unwrap(), 1);
+        assert_eq!(db.rename_tag("author", "writer").await.unwrap(), 0);
+        assert_eq!(db.rename_tag("missing", "tag").await.unwrap(), 0);
+
+        let db = SQLiteDatabase::open(&path).await.unwrap();
+        let books = db.get_books(&ids).await.unwrap();
+        let tag = |id: BookID, name: &str| books[&id].named_tags.get(name).cloned();
+        for id in &ids[..2] {
+            assert!(books[id].free_tags.iter().eq(&["scifi".to_string()]));
+            assert_eq!(tag(*id, "gnere"), None);
+        }
+        assert_eq!(tag(ids[0], "genre").as_deref(), Some("space opera"));
+        assert_eq!(tag(ids[1], "genre").as_deref(), Some("fantasy"));
+        assert_eq!(
+            books[&ids[2]].authors(),
+            Some(&["Frank Herbert".to_string()][..])
+        );
+        assert_eq!(
+            db.column_stats(&multimap("tone")).await.unwrap(),
+            vec![("tense".to_string(), 1)]
+        );
+
+        let mut db = db;
+        assert_eq!(db.delete_tag("scifi").await.unwrap(), 2);
+        assert_eq!(db.delete_tag("genre").await.unwrap(), 2);
+        assert_eq!(db.delete_tag("author").await.unwrap(), 0);
+        let books = db.get_books(&ids).await.unwrap();
+        assert!(books.values().all(|book| book.free_tags.is_empty()));
+        assert!(books.values().all(|book| book.named_tags.is_empty()));
+        assert!(!db
+            .has_column(&UniCase::new("genre".to_string()))
+            .await
+            .unwrap());
+        assert_eq!(
+            books[&ids[2]].authors(),
+            Some(&["Frank Herbert".to_string()][..])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_column_stats() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        let ids = db
+            .insert_books((0..4).map(|i| variant(&format!("Book {}", i), i)))
+            .await
+            .unwrap();
+        let genres = ["scifi", "fantasy", "scifi"];
+        for (id, genre) in ids.iter().zip(genres.iter()) {
+            db.edit_book_with_id(
+                *id,
+                &[(
+                    ColumnIdentifier::NamedTag("genre".to_string()),
+                    Edit::Replace(genre.to_string()),
+                )],
+            )
+            .await
+            .unwrap();
+        }
+
+        let stats = db
+            .column_stats(&ColumnIdentifier::from("genre"))
+            .await
+            .unwrap();
+        assert_eq!(
+            stats,
+            vec![("scifi".to_string(), 2), ("fantasy".to_string(), 1)]
+        );
+
+        let stats = db.column_stats(&ColumnIdentifier::Variants).await.unwrap();
+        assert_eq!(stats, vec![("EPUB".to_string(), 4)]);
+        assert!(db
+            .column_stats(&ColumnIdentifier::ID)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_update_moved_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let mut db = SQLiteDatabase::open(&path).await.unwrap();
+        let ids = db
+            .insert_books(vec![variant("Dune", 0), variant("Emma", 1)].into_iter())
+            .await
+            .unwrap();
+
+        let moved = |title: &str, hash: u8| BookVariant {
+            path: PathBuf::from(format!("library/{}.epub", title)),
+            ..variant(title, hash)
+        };
+        db.update(vec![moved("Dune", 0), moved("Unknown", 2)].into_iter())
+            .await
+            .unwrap();
+
+        let db = SQLiteDatabase::open(&path).await.unwrap();
+        let path = |id| {
+            let db = &db;
+            async move { db.get_book(id).await.unwrap().variants()[0].path.clone() }
+        };
+        assert_eq!(path(ids[0]).await, PathBuf::from("library/Dune.epub"));
+        assert_eq!(path(ids[1]).await, PathBuf::from("Emma.epub"));
+        assert_eq!(
+            db.read_selected_books("SELECT book_id FROM books;", &[])
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    /// Creates a Calibre library in `dir` with two books - "Dune", which has an EPUB and a
+    /// missing PDF, and "Notes", which only has a TXT file.
+    async fn calibre_library(dir: &Path) -> PathBuf {
+        let library = dir.join("Calibre Library");
+        std::fs::create_dir_all(library.join("Frank Herbert/Dune (1)")).unwrap();
+        std::fs::write(
+            library.join("Frank Herbert/Dune (1)/Dune - Frank Herbert.epub"),
+            b"PK",
+        )
+        .unwrap();
+
+        let calibre = SqlitePoolOptions::new()
+            .connect_with(
+                SqliteConnectOptions::new()
+                    .filename(library.join("metadata.db"))
+                    .create_if_missing(true),
+            )
+            .await
+            .unwrap();
+        for statement in [
+            "CREATE TABLE books (id INTEGER PRIMARY KEY, title TEXT NOT NULL, path TEXT NOT NULL, series_index REAL NOT NULL DEFAULT 1.0);",
+            "CREATE TABLE authors (id INTEGER PRIMARY KEY, name TEXT NOT NULL);",
+            "CREATE TABLE books_authors_link (id INTEGER PRIMARY KEY, book INTEGER NOT NULL, author INTEGER NOT NULL);",
+            "CREATE TABLE series (id INTEGER PRIMARY KEY, name TEXT NOT NULL);",
+            "CREATE TABLE books_series_link (id INTEGER PRIMARY KEY, book INTEGER NOT NULL, series INTEGER NOT NULL);",
+            "CREATE TABLE identifiers (id INTEGER PRIMARY KEY, book INTEGER NOT NULL, type TEXT NOT NULL, val TEXT NOT NULL);",
+            "CREATE TABLE data (id INTEGER PRIMARY KEY, book INTEGER NOT NULL, format TEXT NOT NULL, uncompressed_size INTEGER NOT NULL, name TEXT NOT NULL);",
+            "INSERT INTO books VALUES (1, 'Dune', 'Frank Herbert/Dune (1)', 1.5), (2, 'Notes', 'Unknown/Notes (2)', 1.0);",
+            "INSERT INTO authors VALUES (1, 'Frank Herbert'), (2, 'Brian Herbert');",
+            "INSERT INTO books_authors_link VALUES (1, 1, 1), (2, 1, 2);",
+            "INSERT INTO series VALUES (1, 'Dune Chronicles');",
+            "INSERT INTO books_series_link VALUES (1, 1, 1);",
+            "INSERT INTO identifiers VALUES (1, 1, 'goodreads', '234225'), (2, 1, 'isbn', '9780441013593');",
+            "INSERT INTO data VALUES (1, 1, 'EPUB', 2, 'Dune - Frank Herbert'), (2, 1, 'PDF', 1024, 'Dune - Frank Herbert'), (3, 2, 'TXT', 10, 'Notes');",
+        ] {
+            sqlx::query(statement).execute(&calibre).await.unwrap();
+        }
+        calibre.close().await;
+        library
+    }
+
+    #[tokio::test]
+    async fn test_import_calibre() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = calibre_library(dir.path()).await;
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        // Books with no supported formats are skipped.
+        let ids = db.import_calibre(&library).await.unwrap();
+        assert_eq!(ids.len(), 1);
+
+        let book = db.get_book(ids[0]).await.unwrap();
+        assert_eq!(book.title(), Some("Dune"));
+        assert_eq!(
+            book.authors().unwrap(),
+            &["Frank Herbert".to_string(), "Brian Herbert".to_string()]
+        );
+        assert_eq!(
+            book.series(),
+            Some(&Series {
+                name: "Dune Chronicles".to_string(),
+                index: Some(1.5),
+            })
+        );
+
+        // The PDF is missing, so it can not be hashed, and is skipped.
+        let variants = book.variants();
+        assert_eq!(variants.len(), 1);
+        assert_eq!(variants[0].book_type, BookType::EPUB);
+        assert_eq!(
+            variants[0].path,
+            library.join("Frank Herbert/Dune (1)/Dune - Frank Herbert.epub")
+        );
+        assert_eq!(variants[0].file_size, 2);
+        assert_ne!(variants[0].hash, [0; 32]);
+        assert!(matches!(variants[0].identifier, Some(Identifier::ISBN(_))));
+    }
+
+    #[tokio::test]
+    async fn test_reimport_calibre() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = calibre_library(dir.path()).await;
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        db.set_insert_policy(InsertPolicy::SkipDuplicates);
+        let ids = db.import_calibre(&library).await.unwrap();
+        assert_eq!(ids.len(), 1);
+
+        // Importing the library again finds every file already in the database.
+        assert!(db.import_calibre(&library).await.unwrap().is_empty());
+        let books: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM books")
+            .fetch_one(&db.connection)
+            .await
+            .unwrap();
+        assert_eq!(books, 1);
+        assert_eq!(db.get_book(ids[0]).await.unwrap().variants().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_export() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        db.insert_books(
+            vec![
+                BookVariant {
+                    additional_authors: Some(vec!["Frank Herbert".to_string()]),
+                    ..variant("Dune, Part One", 0)
+                },
+                variant("Emma", 1),
+            ]
+            .into_iter(),
+        )
+        .await
+        .unwrap();
+
+        let mut csv = vec![];
+        assert_eq!(
+            db.export(ExportFormat::Csv(DelimitedFormat::csv()), &mut csv)
+                .await
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "id,title,authors,series,description,tags,paths\n\
+            1,\"Dune, Part One\",Frank Herbert,,,,\"Dune, Part One.epub\"\n\
+            2,Emma,,,,,Emma.epub\n"
+        );
+
+        let mut json = vec![];
+        assert_eq!(db.export(ExportFormat::Json, &mut json).await.unwrap(), 2);
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        let titles: Vec<_> = json
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|book| book["title"].as_str().unwrap())
+            .collect();
+        assert_eq!(titles, vec!["Dune, Part One", "Emma"]);
+    }
+
+    #[tokio::test]
+    async fn test_tag_append_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        let ids = db
+            .insert_books((0..2).map(|i| variant(&format!("Book {}", i), i)))
+            .await
+            .unwrap();
+        let genre = ColumnIdentifier::NamedTag("genre".to_string());
+        let append = |value: &str| [(genre.clone(), Edit::Append(value.to_string()))];
+        let stored = |db: &SQLiteDatabase, id: BookID| {
+            let pool = db.connection.clone();
+            async move {
+                sqlx::query_scalar::<_, String>(
+                    "SELECT value FROM named_tags WHERE name = 'genre' AND book_id = ?;",
+                )
+                .bind(u64::from(id) as i64)
+                .fetch_one(&pool)
+                .await
+                .unwrap()
+            }
+        };
+
+        // By default, appended values are concatenated.
+        db.edit_book_with_id(ids[0], &append("scifi"))
+            .await
+            .unwrap();
+        db.edit_book_with_id(ids[0], &append("scifi"))
+            .await
+            .unwrap();
+        assert_eq!(stored(&db, ids[0]).await, "scifiscifi");
+
+        db.set_tag_append_policy(TagAppendPolicy::Idempotent(", ".to_string()));
+        db.edit_book_with_id(ids[1], &append("scifi"))
+            .await
+            .unwrap();
+        db.edit_book_with_id(ids[1], &append("scifi"))
+            .await
+            .unwrap();
+        assert_eq!(stored(&db, ids[1]).await, "scifi");
+        db.edit_book_with_id(ids[1], &append(", fantasy"))
+            .await
+            .unwrap();
+        db.edit_book_with_id(ids[1], &append("fantasy"))
+            .await
+            .unwrap();
+        assert_eq!(stored(&db, ids[1]).await, "scifi, fantasy");
+        assert_eq!(
+            db.get_book(ids[1]).await.unwrap().tags().get("genre"),
+            Some(&"scifi, fantasy".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_status() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        let ids = db
+            .insert_books((0..3).map(|i| variant(&format!("Book {}", i), i)))
+            .await
+            .unwrap();
+        // A tag which the user named "read" is kept apart from the read status.
+        let tag = ColumnIdentifier::NamedTag("read".to_string());
+        db.edit_book_with_id(ids[0], &[(tag.clone(), Edit::Replace("twice".to_string()))])
+            .await
+            .unwrap();
+        let read = |edit: Edit| [(ColumnIdentifier::ReadStatus, edit)];
+        let replace = |value: &str| read(Edit::Replace(value.to_string()));
+        for (id, status) in ids.iter().zip(["no", "Reading", "no"].iter()) {
+            db.edit_book_with_id(*id, &replace(*status)).await.unwrap();
+        }
+        // Existing statuses are replaced, and only known statuses are accepted.
+        db.edit_book_with_id(ids[0], &replace("yes")).await.unwrap();
+        assert!(matches!(
+            db.edit_book_with_id(ids[0], &replace("finished")).await,
+            Err(DatabaseError::Record(RecordError::InvalidValue(_)))
+        ));
+        assert!(matches!(
+            db.edit_book_with_id(ids[0], &read(Edit::Append("!".to_string())))
+                .await,
+            Err(DatabaseError::Record(RecordError::InextensibleColumn))
+        ));
+        let book = db.get_book(ids[0]).await.unwrap();
+        assert_eq!(
+            book.get_column(&ColumnIdentifier::ReadStatus).as_deref(),
+            Some("yes")
+        );
+        assert_eq!(book.get_column(&tag).as_deref(), Some("twice"));
+        assert_eq!(
+            db.column_stats(&ColumnIdentifier::ReadStatus)
+                .await
+                .unwrap(),
+            vec![
+                ("no".to_string(), 1),
+                ("reading".to_string(), 1),
+                ("yes".to_string(), 1)
+            ]
+        );
+        assert_eq!(
+            db.get_book(ids[1])
+                .await
+                .unwrap()
+                .tags()
+                .get(READ_STATUS_TAG),
+            Some(&"reading".to_string())
+        );
+        let db = Arc::new(RwLock::new(db));
+
+        let paginator = Paginator::new(db.clone(), 0, vec![].into_boxed_slice())
+            .bind_match(vec![matchers::column("read").equals("yes").unwrap()].into_boxed_slice());
+        let books = paginator.read_all().await.unwrap();
+        assert_eq!(
+            books.iter().map(|book| book.id()).collect::<Vec<_>>(),
+            vec![ids[0]]
+        );
+
+        let sort = vec![(ColumnIdentifier::ReadStatus, ColumnOrder::Ascending)].into_boxed_slice();
+        let mut paginator = Paginator::new(db, 0, sort);
+        paginator.set_prefetch(false);
+
+        // Scroll one book at a time, so that each page is read relative to the last book.
+        paginator.update_window_size(1).await.unwrap();
+        let mut titles = vec![];
+        for _ in 0..ids.len() {
+            titles.push(paginator.window()[0].title().unwrap().to_string());
+            paginator.scroll_down(1).await.unwrap();
+        }
+        assert_eq!(titles, vec!["Book 2", "Book 1", "Book 0"]);
+    }
+
+    #[tokio::test]
+    async fn test_reading_stats() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        let ids = db
+            .insert_books((0..4).map(|i| variant(&format!("Book {}", i), i)))
+            .await
+            .unwrap();
+        // Books are counted in the month they were added, unless they were added before dates
+        // were recorded.
+        sqlx::query("UPDATE books SET date_added = NULL WHERE book_id = ?;")
+            .bind(u64::from(ids[3]) as i64)
+            .execute(&db.connection)
+            .await
+            .unwrap();
+        let finished = [
+            Some("2021-03-20"),
+            Some("2021-01-30"),
+            None,
+            Some("2021-03-01"),
+        ];
+        for (id, finished) in ids.iter().zip(finished.iter()) {
+            if let Some(finished) = finished {
+                db.edit_book_with_id(
+                    *id,
+                    &[(
+                        ColumnIdentifier::NamedTag(FINISHED_TAG.to_string()),
+                        Edit::Replace(finished.to_string()),
+                    )],
+                )
+                .await
+                .unwrap();
+            }
+        }
+
+        let added = db
+            .get_book(ids[0])
+            .await
+            .unwrap()
+            .date_added
+            .clone()
+            .unwrap();
+        let month = |month: &str, added, finished| MonthlyReading {
+            month: month.to_string(),
+            added,
+            finished,
+        };
+        assert_eq!(
+            db.reading_stats().await.unwrap(),
+            vec![
+                month("2021-01", 0, 1),
+                month("2021-03", 0, 2),
+                month(&added[..7], 3, 0)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_library_stats() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        assert_eq!(db.library_stats().await.unwrap(), LibraryStats::default());
+
+        let mut dune = variant("Dune", 0);
+        dune.file_size = 100;
+        dune.language = Some("en".to_string());
+        dune.description = Some("Spice".to_string());
+        dune.additional_authors = Some(vec!["Frank Herbert".to_string()]);
+        dune.series = Some(Series {
+            name: "Dune".to_string(),
+            index: Some(1.),
+        });
+        let mut messiah = variant("Dune Messiah", 1);
+        messiah.file_size = 50;
+        messiah.language = Some("en".to_string());
+        messiah.additional_authors = Some(vec!["Frank Herbert".to_string()]);
+        messiah.series = Some(Series {
+            name: "Dune".to_string(),
+            index: Some(2.),
+        });
+        let mut untitled = variant("Untitled", 2);
+        untitled.local_title = None;
+        untitled.file_size = 7;
+        untitled.language = Some("fr".to_string());
+        let ids = db
+            .insert_books(vec![dune, messiah, untitled].into_iter())
+            .await
+            .unwrap();
+        db.set_cover(ids[0], &[1, 2, 3]).await.unwrap();
+
+        assert_eq!(
+            db.library_stats().await.unwrap(),
+            LibraryStats {
+                books: 3,
+                variants: 3,
+                total_size: 157,
+                authors: vec![("Frank Herbert".to_string(), 2)],
+                series: vec![("Dune".to_string(), 2)],
+                languages: vec![("en".to_string(), 2), ("fr".to_string(), 1)],
+                missing_cover: 2,
+                missing_title: 1,
+                missing_authors: 1,
+                missing_description: 2,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sort_by_multimap_tag() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        let ids = db
+            .insert_books((0..3).map(|i| variant(&format!("Book {}", i), i)))
+            .await
+            .unwrap();
+        let genres = [&["scifi", "classic"][..], &["fantasy"], &["Drama"]];
+        for (id, genres) in ids.iter().zip(genres.iter()) {
+            for genre in genres.iter() {
+                sqlx::query(
+                    "INSERT INTO multimap_tags (name, value, book_id) VALUES('genre', ?, ?);",
+                )
+                .bind(genre)
+                .bind(u64::from(*id) as i64)
+                .execute(&db.connection)
+                .await
+                .unwrap();
+            }
+        }
+        let db = Arc::new(RwLock::new(db));
+
+        let sort = vec![(
+            ColumnIdentifier::MultiMap("genre".to_string()),
+            ColumnOrder::Ascending,
+        )]
+        .into_boxed_slice();
+        let mut paginator = Paginator::new(db, 0, sort);
+        paginator.set_prefetch(false);
+
+        // Scroll one book at a time, so that each page is read relative to the last book.
+        paginator.update_window_size(1).await.unwrap();
+        let mut titles = vec![];
+        for _ in 0..ids.len() {
+            titles.push(paginator.window()[0].title().unwrap().to_string());
+            paginator.scroll_down(1).await.unwrap();
+        }
+        assert_eq!(titles, vec!["Book 0", "Book 2", "Book 1"]);
+    }
+}
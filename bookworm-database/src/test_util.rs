@@ -0,0 +1,314 @@
+//! Test doubles shared by the tests of the bookworm crates. Enabled by the `test-util` feature.
+
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+use unicase::UniCase;
+
+use bookworm_input::Edit;
+use bookworm_records::book::{BookID, ColumnIdentifier};
+use bookworm_records::{Book, BookVariant};
+
+use crate::paginator::{Selection, Variable};
+use crate::{
+    AppDatabase, BookSnapshot, DatabaseError, DetectionStrategy, ExportFormat, FtsReport,
+    LibraryStats, MonthlyReading, SQLiteDatabase, VariantEditPolicy,
+};
+
+/// Wraps a database, and records the LIMIT of each query used to read books. Every other
+/// method is delegated to the wrapped database.
+pub struct RecordingDatabase {
+    db: SQLiteDatabase,
+    limits: Mutex<Vec<Option<i64>>>,
+}
+
+impl RecordingDatabase {
+    pub fn new(db: SQLiteDatabase) -> Self {
+        RecordingDatabase {
+            db,
+            limits: Mutex::new(vec![]),
+        }
+    }
+
+    /// Returns the LIMIT of each query used to read books so far, in the order they were run.
+    pub fn limits(&self) -> Vec<Option<i64>> {
+        self.limits.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl AppDatabase for RecordingDatabase {
+    type Error = <SQLiteDatabase as AppDatabase>::Error;
+
+    async fn open<P>(file_path: P) -> Result<Self, DatabaseError<Self::Error>>
+    where
+        P: AsRef<Path> + Send + Sync,
+    {
+        Ok(RecordingDatabase::new(
+            SQLiteDatabase::open(file_path).await?,
+        ))
+    }
+
+    async fn open_read_only<P>(file_path: P) -> Result<Self, DatabaseError<Self::Error>>
+    where
+        P: AsRef<Path> + Send + Sync,
+    {
+        Ok(RecordingDatabase::new(
+            SQLiteDatabase::open_read_only(file_path).await?,
+        ))
+    }
+
+    fn path(&self) -> &Path {
+        self.db.path()
+    }
+
+    fn supports_full_text_search(&self) -> bool {
+        self.db.supports_full_text_search()
+    }
+
+    async fn save(&mut self) -> Result<(), DatabaseError<Self::Error>> {
+        self.db.save().await
+    }
+
+    async fn insert_book(
+        &mut self,
+        book: BookVariant,
+    ) -> Result<BookID, DatabaseError<Self::Error>> {
+        self.db.insert_book(book).await
+    }
+
+    async fn insert_books<I: Iterator<Item = BookVariant> + Send>(
+        &mut self,
+        books: I,
+    ) -> Result<Vec<BookID>, DatabaseError<Self::Error>> {
+        self.db.insert_books(books).await
+    }
+
+    async fn import_books(
+        &mut self,
+        books: &[Arc<Book>],
+    ) -> Result<Vec<BookID>, DatabaseError<Self::Error>> {
+        self.db.import_books(books).await
+    }
+
+    async fn restore_books(
+        &mut self,
+        books: &[Arc<Book>],
+    ) -> Result<(), DatabaseError<Self::Error>> {
+        self.db.restore_books(books).await
+    }
+
+    async fn snapshot_books(
+        &self,
+        ids: &[BookID],
+    ) -> Result<BookSnapshot, DatabaseError<Self::Error>> {
+        self.db.snapshot_books(ids).await
+    }
+
+    async fn restore_snapshot(
+        &mut self,
+        snapshot: &BookSnapshot,
+    ) -> Result<(), DatabaseError<Self::Error>> {
+        self.db.restore_snapshot(snapshot).await
+    }
+
+    async fn remove_books(
+        &mut self,
+        ids: &HashSet<BookID>,
+    ) -> Result<(), DatabaseError<Self::Error>> {
+        self.db.remove_books(ids).await
+    }
+
+    async fn remove_selected(
+        &mut self,
+        selection: &Selection,
+    ) -> Result<(), DatabaseError<Self::Error>> {
+        self.db.remove_selected(selection).await
+    }
+
+    async fn clear(&mut self) -> Result<(), DatabaseError<Self::Error>> {
+        self.db.clear().await
+    }
+
+    async fn get_book(&self, id: BookID) -> Result<Arc<Book>, DatabaseError<Self::Error>> {
+        self.db.get_book(id).await
+    }
+
+    async fn get_books(
+        &self,
+        ids: &[BookID],
+    ) -> Result<HashMap<BookID, Arc<Book>>, DatabaseError<Self::Error>> {
+        self.db.get_books(ids).await
+    }
+
+    async fn read_selected_books(
+        &self,
+        query: &str,
+        bound_variables: &[Variable],
+    ) -> Result<Vec<Arc<Book>>, DatabaseError<Self::Error>> {
+        let mut variables = bound_variables.iter().rev();
+        if query.contains(" OFFSET ") {
+            variables.next();
+        }
+        let limit = match variables.next() {
+            Some(Variable::Int(limit)) => Some(*limit),
+            _ => None,
+        };
+        self.limits.lock().unwrap().push(limit);
+        self.db.read_selected_books(query, bound_variables).await
+    }
+
+    async fn edit_book_with_id(
+        &mut self,
+        id: BookID,
+        edits: &[(ColumnIdentifier, Edit)],
+    ) -> Result<(), DatabaseError<Self::Error>> {
+        self.db.edit_book_with_id(id, edits).await
+    }
+
+    async fn edit_selected(
+        &mut self,
+        selected: &Selection,
+        edits: &[(ColumnIdentifier, Edit)],
+        policy: VariantEditPolicy,
+    ) -> Result<(), DatabaseError<Self::Error>> {
+        self.db.edit_selected(selected, edits, policy).await
+    }
+
+    async fn edit_many(
+        &mut self,
+        edits: &[(BookID, Box<[(ColumnIdentifier, Edit)]>)],
+    ) -> Result<(), DatabaseError<Self::Error>> {
+        self.db.edit_many(edits).await
+    }
+
+    async fn merge_similar(&mut self) -> Result<HashSet<BookID>, DatabaseError<Self::Error>> {
+        self.db.merge_similar().await
+    }
+
+    async fn find_duplicates(
+        &self,
+        strategy: DetectionStrategy,
+    ) -> Result<Vec<Vec<BookID>>, DatabaseError<Self::Error>> {
+        self.db.find_duplicates(strategy).await
+    }
+
+    async fn remove_empty_books(&mut self) -> Result<HashSet<BookID>, DatabaseError<Self::Error>> {
+        self.db.remove_empty_books().await
+    }
+
+    async fn update<I: Iterator<Item = BookVariant> + Send>(
+        &mut self,
+        books: I,
+    ) -> Result<(), DatabaseError<Self::Error>> {
+        self.db.update(books).await
+    }
+
+    async fn refresh_variants(
+        &mut self,
+        variants: &[(BookID, BookVariant)],
+    ) -> Result<(), DatabaseError<Self::Error>> {
+        self.db.refresh_variants(variants).await
+    }
+
+    async fn move_variants(
+        &mut self,
+        moves: &[(BookID, PathBuf, PathBuf)],
+    ) -> Result<(), DatabaseError<Self::Error>> {
+        self.db.move_variants(moves).await
+    }
+
+    async fn set_preferred_variant(
+        &mut self,
+        id: BookID,
+        index: usize,
+    ) -> Result<(), DatabaseError<Self::Error>> {
+        self.db.set_preferred_variant(id, index).await
+    }
+
+    async fn set_cover(
+        &mut self,
+        id: BookID,
+        image: &[u8],
+    ) -> Result<(), DatabaseError<Self::Error>> {
+        self.db.set_cover(id, image).await
+    }
+
+    async fn get_cover(&self, id: BookID) -> Result<Option<Vec<u8>>, DatabaseError<Self::Error>> {
+        self.db.get_cover(id).await
+    }
+
+    async fn refresh_columns(&self) -> Result<(), DatabaseError<Self::Error>> {
+        self.db.refresh_columns().await
+    }
+
+    async fn authors(&self) -> Result<Vec<(String, usize)>, DatabaseError<Self::Error>> {
+        self.db.authors().await
+    }
+
+    async fn rename_authors(
+        &mut self,
+        renames: &[(String, String)],
+    ) -> Result<(), DatabaseError<Self::Error>> {
+        self.db.rename_authors(renames).await
+    }
+
+    async fn clear_tags(&mut self, ids: &[BookID]) -> Result<(), DatabaseError<Self::Error>> {
+        self.db.clear_tags(ids).await
+    }
+
+    async fn rename_tag(
+        &mut self,
+        old: &str,
+        new: &str,
+    ) -> Result<usize, DatabaseError<Self::Error>> {
+        self.db.rename_tag(old, new).await
+    }
+
+    async fn delete_tag(&mut self, name: &str) -> Result<usize, DatabaseError<Self::Error>> {
+        self.db.delete_tag(name).await
+    }
+
+    async fn rebuild_fts(&mut self) -> Result<Option<FtsReport>, DatabaseError<Self::Error>> {
+        self.db.rebuild_fts().await
+    }
+
+    async fn column_stats(
+        &self,
+        column: &ColumnIdentifier,
+    ) -> Result<Vec<(String, usize)>, DatabaseError<Self::Error>> {
+        self.db.column_stats(column).await
+    }
+
+    async fn reading_stats(&self) -> Result<Vec<MonthlyReading>, DatabaseError<Self::Error>> {
+        self.db.reading_stats().await
+    }
+
+    async fn library_stats(&self) -> Result<LibraryStats, DatabaseError<Self::Error>> {
+        self.db.library_stats().await
+    }
+
+    async fn export<W: Write + Send>(
+        &self,
+        format: ExportFormat,
+        writer: W,
+    ) -> Result<usize, DatabaseError<Self::Error>> {
+        self.db.export(format, writer).await
+    }
+
+    fn stream_books(&self) -> BoxStream<'_, Result<Arc<Book>, DatabaseError<Self::Error>>> {
+        self.db.stream_books()
+    }
+
+    async fn has_column(&self, col: &UniCase<String>) -> Result<bool, DatabaseError<Self::Error>> {
+        self.db.has_column(col).await
+    }
+
+    async fn saved(&self) -> bool {
+        self.db.saved().await
+    }
+}
@@ -71,6 +71,92 @@ impl AutoCompleter<PathBuf> {
     }
 }
 
+impl AutoCompleter<String> {
+    /// Returns a new `AutoCompleter`, which will fill in the words starting with `word`, ignoring
+    /// case, in alphabetical order.
+    ///
+    /// # Arguments
+    /// * ` word ` - The word to provide autofills for.
+    /// * ` words ` - The words which may be filled in.
+    pub fn from_words<S: AsRef<str>, I: IntoIterator<Item = String>>(word: S, words: I) -> Self {
+        let word = word.as_ref().to_lowercase();
+        let mut candidates: Vec<_> = words
+            .into_iter()
+            .filter(|candidate| candidate.to_lowercase().starts_with(&word))
+            .collect();
+        candidates.sort();
+        candidates.dedup();
+        AutoCompleter {
+            word_len: word.len(),
+            candidates: RingFilter::new(candidates),
+        }
+    }
+
+    /// Returns a new `AutoCompleter`, which will fill in the words containing the characters of
+    /// `word` in order, ignoring case - for instance, "athr" fills in "author". Words are filled
+    /// in from the best to the worst match, as ranked by `fuzzy_score`.
+    ///
+    /// # Arguments
+    /// * ` word ` - The word to provide autofills for.
+    /// * ` words ` - The words which may be filled in.
+    pub fn new_fuzzy<S: AsRef<str>, I: IntoIterator<Item = String>>(word: S, words: I) -> Self {
+        let word = word.as_ref();
+        let mut candidates: Vec<_> = words
+            .into_iter()
+            .filter_map(|candidate| Some((fuzzy_score(word, &candidate)?, candidate)))
+            .collect();
+        candidates.sort_by(|(score_a, a), (score_b, b)| {
+            score_b
+                .cmp(score_a)
+                .then_with(|| a.len().cmp(&b.len()))
+                .then_with(|| a.cmp(b))
+        });
+        candidates.dedup_by(|(_, a), (_, b)| a == b);
+        AutoCompleter {
+            word_len: word.len(),
+            candidates: RingFilter::new(candidates.into_iter().map(|(_, word)| word).collect()),
+        }
+    }
+
+    /// Returns the next word, or None if no word matches the original. Once every word has
+    /// been returned, the words are returned again from the start.
+    pub fn next_word(&mut self) -> Option<&String> {
+        self.candidates.next_item_by(|_| true)
+    }
+}
+
+/// Returns how well `pattern` matches `candidate`, if the characters of `pattern` appear in
+/// `candidate` in order, ignoring case. Each matched character scores a point, with a bonus for
+/// characters which start a word of `candidate` or directly follow the previous match, and a
+/// penalty for each character skipped between matches. An empty pattern matches every candidate
+/// with a score of 0.
+///
+/// # Arguments
+/// * ` pattern ` - The characters to find.
+/// * ` candidate ` - The word to find the characters in.
+pub fn fuzzy_score(pattern: &str, candidate: &str) -> Option<i64> {
+    const WORD_START_BONUS: i64 = 8;
+    const CONSECUTIVE_BONUS: i64 = 4;
+
+    let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut next = 0;
+    for c in pattern.to_lowercase().chars() {
+        let found = next + candidate[next..].iter().position(|&x| x == c)?;
+        score += 1;
+        if found == 0 || !candidate[found - 1].is_alphanumeric() {
+            score += WORD_START_BONUS;
+        }
+        if found == next && next > 0 {
+            score += CONSECUTIVE_BONUS;
+        } else if next > 0 {
+            score -= (found - next) as i64;
+        }
+        next = found + 1;
+    }
+    Some(score)
+}
+
 struct RingFilter<S> {
     items: Vec<S>,
     index: usize,
@@ -115,6 +201,73 @@ impl<S> RingFilter<S> {
 mod test {
     use super::*;
 
+    fn completions(mut completer: AutoCompleter<String>) -> Vec<String> {
+        let first = match completer.next_word() {
+            Some(word) => word.clone(),
+            None => return vec![],
+        };
+        let mut words = vec![first.clone()];
+        while let Some(word) = completer.next_word().filter(|&word| word != &first) {
+            words.push(word.clone());
+        }
+        words
+    }
+
+    fn columns() -> Vec<String> {
+        [
+            "title",
+            "author",
+            "series",
+            "added",
+            "description",
+            "cover",
+            "tag",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+    }
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert!(fuzzy_score("athr", "author").is_some());
+        assert_eq!(fuzzy_score("atr", "title"), None);
+        assert_eq!(fuzzy_score("", "title"), Some(0));
+        assert!(fuzzy_score("AUTH", "author") > fuzzy_score("athr", "author"));
+        // Matches at the start of words rank above matches in the middle of words.
+        assert!(fuzzy_score("ds", "date_series") > fuzzy_score("ds", "descriptions"));
+        assert!(fuzzy_score("tit", "title") > fuzzy_score("tit", "subtitle"));
+    }
+
+    #[test]
+    fn test_fuzzy_completion() {
+        assert_eq!(
+            completions(AutoCompleter::new_fuzzy("athr", columns())),
+            vec!["author"]
+        );
+        assert_eq!(
+            completions(AutoCompleter::new_fuzzy("de", columns())),
+            vec!["description", "added"]
+        );
+        assert!(completions(AutoCompleter::new_fuzzy("xyz", columns())).is_empty());
+
+        let commands = vec![
+            ":s".to_string(),
+            ":series".to_string(),
+            ":stats".to_string(),
+        ];
+        assert_eq!(
+            completions(AutoCompleter::new_fuzzy(":ss", commands.clone())),
+            vec![":stats", ":series"]
+        );
+        // Prefix completion remains the default.
+        assert_eq!(
+            completions(AutoCompleter::from_words(":S", commands)),
+            vec![":s", ":series", ":stats"]
+        );
+        assert!(completions(AutoCompleter::from_words("athr", columns())).is_empty());
+    }
+
     #[test]
     fn test_empty_ring_works_ok() {
         let mut a: RingFilter<u8> = RingFilter::new(vec![]);
@@ -520,6 +520,7 @@ impl<K: Hash + PartialEq + Eq> InputRecorder<K> {
 pub struct CommandString {
     cursored_text: CursoredText,
     auto_fill: Option<AutoCompleter<PathBuf>>,
+    word_fill: Option<AutoCompleter<String>>,
     autofilled: Option<String>,
     open_end: bool,
     keep_last: bool,
@@ -559,6 +560,7 @@ impl CommandString {
         CommandString {
             cursored_text: CursoredText::default(),
             auto_fill: None,
+            word_fill: None,
             autofilled: None,
             open_end: true,
             keep_last: false,
@@ -570,6 +572,7 @@ impl CommandString {
     pub fn push(&mut self, c: char) {
         self.write_back();
         self.auto_fill = None;
+        self.word_fill = None;
         self.cursored_text.push(c)
     }
 
@@ -577,6 +580,7 @@ impl CommandString {
     pub fn backspace(&mut self) {
         self.write_back();
         self.auto_fill = None;
+        self.word_fill = None;
         self.open_end = true;
         self.cursored_text.backspace();
     }
@@ -585,6 +589,7 @@ impl CommandString {
     pub fn del(&mut self) {
         self.write_back();
         self.auto_fill = None;
+        self.word_fill = None;
         self.open_end = true;
         self.cursored_text.del();
     }
@@ -635,6 +640,7 @@ impl CommandString {
     /// Clears all internal state, including autofills.
     pub fn clear(&mut self) {
         self.auto_fill = None;
+        self.word_fill = None;
         self.autofilled = None;
         self.cursored_text.clear();
     }
@@ -692,6 +698,32 @@ impl CommandString {
         }
     }
 
+    /// Autofills the last word of the input with one of `words`, ranked by how well they
+    /// fuzzily match the last word. If the input ends with a space, a new word is filled in
+    /// instead. Repeated calls cycle through the matching words, until the input is edited.
+    ///
+    /// # Arguments
+    /// * ` words ` - The words which may be filled in, such as command or column names.
+    pub fn auto_fill_words<I: IntoIterator<Item = String>>(&mut self, words: I) {
+        if !self.cursored_text.can_autocomplete() {
+            return;
+        }
+
+        self.open_end = false;
+        if self.word_fill.is_none() {
+            self.keep_last = self.cursored_text.text.last() == Some(&' ');
+            let word = match self.get_values().last() {
+                Some((_, word)) if !self.keep_last => word,
+                _ => String::new(),
+            };
+            self.word_fill = Some(AutoCompleter::new_fuzzy(word, words));
+        }
+
+        if let Some(word) = self.word_fill.as_mut().and_then(AutoCompleter::next_word) {
+            self.autofilled = Some(word.clone());
+        }
+    }
+
     pub fn select_all(&mut self) {
         self.cursored_text.select_all()
     }
@@ -930,4 +962,35 @@ mod test {
             assert_eq!(results, expected);
         }
     }
+
+    #[test]
+    fn test_auto_fill_words() {
+        let columns = || {
+            vec![
+                "author".to_string(),
+                "title".to_string(),
+                "added".to_string(),
+            ]
+        };
+        let mut cs = CommandString::new();
+        for c in ":s athr".chars() {
+            cs.push(c);
+        }
+        cs.auto_fill_words(columns());
+        assert_eq!(cs.to_string(), ":s author");
+        // There is no other match, so the same word is filled in again.
+        cs.auto_fill_words(columns());
+        assert_eq!(cs.to_string(), ":s author");
+
+        cs.push(' ');
+        cs.auto_fill_words(columns());
+        assert_eq!(cs.to_string(), ":s author added");
+        cs.auto_fill_words(columns());
+        assert_eq!(cs.to_string(), ":s author title");
+
+        cs.clear();
+        cs.push('x');
+        cs.auto_fill_words(columns());
+        assert_eq!(cs.to_string(), "x");
+    }
 }
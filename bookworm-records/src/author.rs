@@ -0,0 +1,90 @@
+/// Words which follow a surname, rather than being part of it.
+const SUFFIXES: [&str; 7] = ["jr", "jr.", "sr", "sr.", "ii", "iii", "iv"];
+
+/// Words which are part of the surname that follows them, as in "Ursula K. Le Guin".
+const PARTICLES: [&str; 10] = [
+    "da", "de", "del", "der", "di", "du", "la", "le", "van", "von",
+];
+
+/// Returns `author` in the canonical "First Last" form, with surrounding and repeated
+/// whitespace removed. Names of the form "Last, First" are reordered, keeping any suffix
+/// at the end - "King, Martin Luther, Jr." becomes "Martin Luther King Jr.".
+///
+/// # Arguments
+/// * ` author ` - The name of the author, in either form.
+pub fn normalize(author: &str) -> String {
+    let author = author.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut parts = author.splitn(3, ',').map(str::trim);
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(last), Some(first), suffix) if !last.is_empty() && !first.is_empty() => {
+            match suffix.filter(|suffix| !suffix.is_empty()) {
+                Some(suffix) => format!("{} {} {}", first, last, suffix),
+                None => format!("{} {}", first, last),
+            }
+        }
+        _ => author
+            .trim_matches(|c: char| c == ',' || c.is_whitespace())
+            .to_string(),
+    }
+}
+
+/// Returns `author` in the "Last, First" form, so that authors are sorted by surname no
+/// matter how their names are stored. Names with a single word are returned unchanged.
+///
+/// # Arguments
+/// * ` author ` - The name of the author, in either form.
+pub fn surname_first(author: &str) -> String {
+    let author = normalize(author);
+    let words: Vec<_> = author.split(' ').collect();
+    let mut end = words.len();
+    if end > 2 && SUFFIXES.contains(&words[end - 1].to_lowercase().as_str()) {
+        end -= 1;
+    }
+    if end < 2 {
+        return author;
+    }
+
+    let mut start = end - 1;
+    while start > 1 && PARTICLES.contains(&words[start - 1].to_lowercase().as_str()) {
+        start -= 1;
+    }
+    let mut name = format!(
+        "{}, {}",
+        words[start..end].join(" "),
+        words[..start].join(" ")
+    );
+    if end < words.len() {
+        name.push_str(", ");
+        name.push_str(words[end]);
+    }
+    name
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("Tolkien, J.R.R."), "J.R.R. Tolkien");
+        assert_eq!(normalize("  J.R.R.   Tolkien "), "J.R.R. Tolkien");
+        assert_eq!(
+            normalize("King, Martin Luther, Jr."),
+            "Martin Luther King Jr."
+        );
+        assert_eq!(normalize("Homer"), "Homer");
+        assert_eq!(normalize("Homer,"), "Homer");
+    }
+
+    #[test]
+    fn test_surname_first() {
+        assert_eq!(surname_first("J.R.R. Tolkien"), "Tolkien, J.R.R.");
+        assert_eq!(surname_first("Tolkien, J.R.R."), "Tolkien, J.R.R.");
+        assert_eq!(surname_first("Ursula K. Le Guin"), "Le Guin, Ursula K.");
+        assert_eq!(
+            surname_first("Martin Luther King Jr."),
+            "King, Martin Luther, Jr."
+        );
+        assert_eq!(surname_first("Homer"), "Homer");
+    }
+}
@@ -7,16 +7,43 @@ use std::str::FromStr;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+use crate::author;
 use crate::series::Series;
-use crate::ColumnOrder;
+use crate::variant::BookType;
 use crate::{BookVariant, Edit};
+use crate::{Collation, ColumnOrder};
 
 pub type BookID = std::num::NonZeroU64;
 
+/// The name of the multimap tag which holds the authors of books. Books do not hold the values
+/// of other multimap tags.
+pub const AUTHOR_MULTIMAP: &str = "author";
+
+/// The name of the named tag which holds the read status of books. The name is reserved, so that
+/// the read status does not take over a tag which the user named `read`.
+pub const READ_STATUS_TAG: &str = "bookworm:read";
+
+/// The values which the read status of a book may take.
+pub const READ_STATUSES: [&str; 3] = ["yes", "no", "reading"];
+
+/// Returns the read status which `value` names, ignoring case and surrounding whitespace, or
+/// None if `value` is not a read status.
+pub fn read_status(value: &str) -> Option<&'static str> {
+    let value = value.trim();
+    READ_STATUSES
+        .iter()
+        .find(|status| status.eq_ignore_ascii_case(value))
+        .copied()
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum RecordError {
     ImmutableColumn,
     InextensibleColumn,
+    /// The book has no variant at the given index.
+    MissingVariant(usize),
+    /// The column does not accept the given value.
+    InvalidValue(String),
 }
 
 /// Identifies the columns a Book provides. Intended to provide a way to access arbitrary columns,
@@ -29,6 +56,12 @@ pub enum ColumnIdentifier {
     ID,
     Variants,
     Description,
+    Cover,
+    /// The time at which the book was added to the database.
+    DateAdded,
+    /// Whether the book has been read, which is one of `READ_STATUSES`. Stored as the named
+    /// tag `READ_STATUS_TAG`.
+    ReadStatus,
     Tags,
     ExactTag(String),
     MultiMap(String),
@@ -36,17 +69,37 @@ pub enum ColumnIdentifier {
     NamedTag(String),
 }
 
+/// The names of the columns which every book provides, as they are typed in commands.
+pub const COLUMN_NAMES: [&str; 10] = [
+    "title",
+    "author",
+    "series",
+    "id",
+    "variants",
+    "description",
+    "cover",
+    "added",
+    "read",
+    "tag",
+];
+
+/// Column names are case-insensitive - user-defined column names are lowercased, so that
+/// any casing of a name refers to the same column.
 impl<S: AsRef<str>> From<S> for ColumnIdentifier {
     fn from(val: S) -> Self {
-        match val.as_ref().to_ascii_lowercase().as_str() {
+        let val = val.as_ref().to_lowercase();
+        match val.as_str() {
             "author" | "authors" => Self::Author,
             "title" => Self::Title,
             "series" => Self::Series,
             "id" => Self::ID,
             "variant" | "variants" => Self::Variants,
             "description" => Self::Description,
+            "cover" | "covers" => Self::Cover,
+            "added" => Self::DateAdded,
+            "read" => Self::ReadStatus,
             "tag" => Self::Tags,
-            _ => Self::NamedTag(val.as_ref().to_owned()),
+            _ => Self::NamedTag(val),
         }
     }
 }
@@ -60,6 +113,9 @@ impl ColumnIdentifier {
             ColumnIdentifier::ID => "ID",
             ColumnIdentifier::Variants => "Variants",
             ColumnIdentifier::Description => "Description",
+            ColumnIdentifier::Cover => "Cover",
+            ColumnIdentifier::DateAdded => "Added",
+            ColumnIdentifier::ReadStatus => "Read",
             ColumnIdentifier::Tags | ColumnIdentifier::ExactTag(_) => "Tag",
             ColumnIdentifier::NamedTag(t) => return t,
             ColumnIdentifier::MultiMap(t) | ColumnIdentifier::MultiMapExact(t, _) => return t,
@@ -68,6 +124,108 @@ impl ColumnIdentifier {
     }
 }
 
+/// The order in which the authors of a book are displayed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AuthorOrder {
+    /// The order in which the authors were added to the book.
+    AsEntered,
+    /// Alphabetical order, ignoring case.
+    Alphabetical,
+    /// Authors first, followed by the translators of the book's variants, each in the order in
+    /// which they were added to the book.
+    RolePriority,
+}
+
+impl Default for AuthorOrder {
+    fn default() -> Self {
+        AuthorOrder::AsEntered
+    }
+}
+
+/// The form in which the name of each author is displayed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AuthorNameOrder {
+    /// Names are displayed as "First Last".
+    FirstLast,
+    /// Names are displayed as "Last, First".
+    LastFirst,
+}
+
+impl Default for AuthorNameOrder {
+    fn default() -> Self {
+        AuthorNameOrder::FirstLast
+    }
+}
+
+/// Describes how the authors of a book are joined when displayed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuthorDisplay {
+    /// The separator placed between authors.
+    pub separator: String,
+    /// The maximum number of authors to display - if a book has more authors, the first
+    /// `limit` authors are displayed, followed by "et al.".
+    pub limit: Option<usize>,
+    /// The order in which authors are displayed.
+    pub order: AuthorOrder,
+    /// The form in which the name of each author is displayed.
+    pub names: AuthorNameOrder,
+}
+
+impl Default for AuthorDisplay {
+    fn default() -> Self {
+        AuthorDisplay {
+            separator: String::from(", "),
+            limit: None,
+            order: AuthorOrder::default(),
+            names: AuthorNameOrder::default(),
+        }
+    }
+}
+
+impl AuthorDisplay {
+    /// Joins `authors` with the separator, collapsing the authors past the limit into "et al.".
+    pub fn join<S: AsRef<str>>(&self, authors: &[S]) -> String {
+        let authors: Vec<&str> = authors.iter().map(AsRef::as_ref).collect();
+        match self.limit {
+            Some(limit) if authors.len() > limit => {
+                format!("{} et al.", authors[..limit].join(&self.separator))
+            }
+            _ => authors.join(&self.separator),
+        }
+    }
+
+    /// Returns the authors of `book` in the configured order, joined as described by `join`.
+    /// Authors are sorted alphabetically by surname.
+    pub fn display(&self, book: &Book) -> Option<String> {
+        let mut authors: Vec<&str> = book.authors()?.iter().map(String::as_str).collect();
+        match self.order {
+            AuthorOrder::AsEntered => {}
+            AuthorOrder::Alphabetical => {
+                authors.sort_by_cached_key(|a| author::surname_first(a).to_lowercase())
+            }
+            AuthorOrder::RolePriority => {
+                let is_translator = |author: &str| {
+                    book.variants.iter().any(|variant| {
+                        variant
+                            .translators
+                            .as_ref()
+                            .map_or(false, |t| t.iter().any(|t| t == author))
+                    })
+                };
+                // Sorting is stable, so that authors with the same role keep their order.
+                authors.sort_by_key(|author| is_translator(author));
+            }
+        }
+        match self.names {
+            AuthorNameOrder::FirstLast => Some(self.join(&authors)),
+            AuthorNameOrder::LastFirst => {
+                let authors: Vec<_> = authors.into_iter().map(author::surname_first).collect();
+                Some(self.join(&authors))
+            }
+        }
+    }
+}
+
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq, Default)]
 /// Stores the metadata for a specific book, with an associated ID, variants, and fields shared
@@ -85,6 +243,11 @@ pub struct Book {
     pub variants: Vec<BookVariant>,
     pub free_tags: HashSet<String>,
     pub named_tags: HashMap<String, String>,
+    /// Whether a cover image is stored for this book.
+    pub has_cover: bool,
+    /// The time at which the book was added, as "YYYY-MM-DD HH:MM:SS" in UTC. Books added
+    /// before this was recorded have no date.
+    pub date_added: Option<String>,
 }
 
 impl Book {
@@ -124,10 +287,26 @@ impl Book {
             ColumnIdentifier::Series => Cow::Owned(self.series()?.to_string()),
             ColumnIdentifier::Description => Cow::Borrowed(self.description()?),
             ColumnIdentifier::NamedTag(x) => Cow::Borrowed(self.named_tags.get(x)?),
+            ColumnIdentifier::Cover => Cow::Borrowed(if self.has_cover { "true" } else { "false" }),
+            ColumnIdentifier::DateAdded => Cow::Borrowed(self.date_added.as_deref()?),
+            ColumnIdentifier::ReadStatus => Cow::Borrowed(self.named_tags.get(READ_STATUS_TAG)?),
             _ => return None,
         })
     }
 
+    /// Returns the value of the column as it should be displayed to the user. This is the same
+    /// as `get_column`, except that authors are ordered and joined as described by `authors`.
+    pub fn display_value(
+        &self,
+        column: &ColumnIdentifier,
+        authors: &AuthorDisplay,
+    ) -> Option<Cow<str>> {
+        match column {
+            ColumnIdentifier::Author => Some(Cow::Owned(authors.display(self)?)),
+            column => self.get_column(column),
+        }
+    }
+
     pub fn push_variant(&mut self, mut variant: BookVariant) {
         if self.title.is_none() {
             self.title = std::mem::take(&mut variant.local_title);
@@ -145,6 +324,39 @@ impl Book {
         self.variants.push(variant);
     }
 
+    /// Returns the variant which represents this book, if any.
+    pub fn preferred_variant(&self) -> Option<&BookVariant> {
+        self.variants.first()
+    }
+
+    /// Returns the index of the variant which should be opened when formats are preferred in
+    /// the given order - the first variant of the earliest format in `order` which the book has.
+    /// If the book has none of the formats in `order`, the preferred variant is chosen.
+    pub fn variant_by_format(&self, order: &[BookType]) -> Option<usize> {
+        order
+            .iter()
+            .find_map(|book_type| {
+                self.variants
+                    .iter()
+                    .position(|variant| variant.book_type() == book_type)
+            })
+            .or_else(|| self.preferred_variant().map(|_| 0))
+    }
+
+    /// Moves the variant at `index` to the front of the variants, making it the preferred
+    /// variant. The order of the remaining variants is preserved.
+    ///
+    /// # Errors
+    /// If no variant exists at `index`, an error will be returned.
+    pub fn set_preferred_variant(&mut self, index: usize) -> Result<(), RecordError> {
+        if index >= self.variants.len() {
+            return Err(RecordError::MissingVariant(index));
+        }
+        let variant = self.variants.remove(index);
+        self.variants.insert(0, variant);
+        Ok(())
+    }
+
     /// Merges self with other, assuming that other is in fact a variant of self.
     /// Missing metadata will be utilized from other, and `self` variants will be extended
     /// to include `other` variants.
@@ -164,6 +376,7 @@ impl Book {
 
         self.variants.extend_from_slice(&other.variants);
         self.named_tags.extend(other.named_tags.clone());
+        self.has_cover |= other.has_cover;
     }
 }
 
@@ -187,11 +400,13 @@ impl Book {
             id: Some(id),
             title: std::mem::take(&mut variant.local_title),
             authors: std::mem::take(&mut variant.additional_authors),
-            series: None,
+            series: std::mem::take(&mut variant.series),
             description: std::mem::take(&mut variant.description),
             named_tags: std::mem::take(&mut variant.named_tags),
             free_tags: std::mem::take(&mut variant.free_tags),
             variants: vec![variant],
+            has_cover: false,
+            date_added: None,
         }
     }
 
@@ -232,12 +447,21 @@ impl Book {
             ColumnIdentifier::Author => {
                 self.authors = Some(vec![value.to_owned()]);
             }
-            ColumnIdentifier::ID | ColumnIdentifier::Variants => {
+            ColumnIdentifier::ID
+            | ColumnIdentifier::Variants
+            | ColumnIdentifier::Cover
+            | ColumnIdentifier::DateAdded => {
                 return Err(RecordError::ImmutableColumn);
             }
             ColumnIdentifier::Series => {
                 self.series = Series::from_str(&value).ok();
             }
+            ColumnIdentifier::ReadStatus => {
+                let status = read_status(value)
+                    .ok_or_else(|| RecordError::InvalidValue(value.to_owned()))?;
+                self.named_tags
+                    .insert(READ_STATUS_TAG.to_owned(), status.to_owned());
+            }
             ColumnIdentifier::NamedTag(column) => {
                 self.named_tags.insert(column.to_owned(), value.to_owned());
             }
@@ -248,13 +472,36 @@ impl Book {
                 self.free_tags.remove(tag);
                 self.free_tags.insert(value.to_owned());
             }
-            ColumnIdentifier::MultiMap(_mm) | ColumnIdentifier::MultiMapExact(_mm, _) => {
-                unimplemented!("Can not set multimap columns.")
+            ColumnIdentifier::MultiMap(name) => {
+                if name == AUTHOR_MULTIMAP {
+                    self.authors = Some(vec![value.to_owned()]);
+                }
+            }
+            ColumnIdentifier::MultiMapExact(name, old) => {
+                if name == AUTHOR_MULTIMAP {
+                    self.replace_author(old, value);
+                }
             }
         }
         Ok(())
     }
 
+    /// Replaces the author `old` with `new`, keeping its position. If the book already has `new`
+    /// as an author, `old` is removed instead, and if the book does not have `old` as an author,
+    /// `new` is added.
+    fn replace_author(&mut self, old: &str, new: &str) {
+        let authors = self.authors.get_or_insert_with(Vec::new);
+        if authors.iter().any(|author| author == new) {
+            if old != new {
+                authors.retain(|author| author != old);
+            }
+        } else if let Some(author) = authors.iter_mut().find(|author| *author == old) {
+            *author = new.to_owned();
+        } else {
+            authors.push(new.to_owned());
+        }
+    }
+
     pub fn edit_column<E: AsRef<Edit>>(
         &mut self,
         column: &ColumnIdentifier,
@@ -284,12 +531,19 @@ impl Book {
             },
             ColumnIdentifier::Author => match &mut self.authors {
                 x @ None => *x = Some(vec![value.to_string()]),
-                Some(authors) => authors.push(value.to_owned()),
+                Some(authors) => {
+                    if !authors.iter().any(|author| author == value) {
+                        authors.push(value.to_owned());
+                    }
+                }
             },
-            ColumnIdentifier::ID | ColumnIdentifier::Variants => {
+            ColumnIdentifier::ID
+            | ColumnIdentifier::Variants
+            | ColumnIdentifier::Cover
+            | ColumnIdentifier::DateAdded => {
                 return Err(RecordError::ImmutableColumn);
             }
-            ColumnIdentifier::Series => {
+            ColumnIdentifier::Series | ColumnIdentifier::ReadStatus => {
                 return Err(RecordError::InextensibleColumn);
             }
             ColumnIdentifier::NamedTag(column) => {
@@ -308,7 +562,16 @@ impl Book {
                     self.free_tags.insert(tag.to_owned() + value);
                 }
             }
-            _ => unimplemented!("Can not extend multimap columns."),
+            ColumnIdentifier::MultiMap(name) => {
+                if name == AUTHOR_MULTIMAP {
+                    self.extend_column(&ColumnIdentifier::Author, value)?;
+                }
+            }
+            ColumnIdentifier::MultiMapExact(name, old) => {
+                if name == AUTHOR_MULTIMAP {
+                    self.replace_author(old, &format!("{}{}", old, value));
+                }
+            }
         }
         Ok(())
     }
@@ -318,10 +581,16 @@ impl Book {
             ColumnIdentifier::Title => self.title = None,
             ColumnIdentifier::Description => self.description = None,
             ColumnIdentifier::Author => self.authors = None,
-            ColumnIdentifier::ID | ColumnIdentifier::Variants => {
+            ColumnIdentifier::ID
+            | ColumnIdentifier::Variants
+            | ColumnIdentifier::Cover
+            | ColumnIdentifier::DateAdded => {
                 return Err(RecordError::ImmutableColumn);
             }
             ColumnIdentifier::Series => self.series = None,
+            ColumnIdentifier::ReadStatus => {
+                self.named_tags.remove(READ_STATUS_TAG);
+            }
             ColumnIdentifier::NamedTag(column) => {
                 self.named_tags.remove(column);
             }
@@ -331,7 +600,21 @@ impl Book {
             ColumnIdentifier::ExactTag(t) => {
                 self.free_tags.remove(t);
             }
-            _ => unimplemented!("Can not delete multimap columns."),
+            ColumnIdentifier::MultiMap(name) => {
+                if name == AUTHOR_MULTIMAP {
+                    self.authors = None;
+                }
+            }
+            ColumnIdentifier::MultiMapExact(name, old) => {
+                if name == AUTHOR_MULTIMAP {
+                    if let Some(authors) = &mut self.authors {
+                        authors.retain(|author| author != old);
+                        if authors.is_empty() {
+                            self.authors = None;
+                        }
+                    }
+                }
+            }
         }
         Ok(())
     }
@@ -345,25 +628,40 @@ impl Book {
     /// # Arguments
     /// * ` other ` - The other book with column to compare.
     /// * ` column ` - the column of interest.
-    pub fn cmp_column(&self, other: &Self, column: &ColumnIdentifier) -> Ordering {
+    pub fn cmp_column(
+        &self,
+        other: &Self,
+        column: &ColumnIdentifier,
+        collation: Collation,
+    ) -> Ordering {
         match column {
             ColumnIdentifier::ID => self.id.cmp(&other.id),
-            ColumnIdentifier::Series => self.series().cmp(&other.series()),
-            ColumnIdentifier::Title => self.title().cmp(&other.title()),
-            ColumnIdentifier::Description => self.description.as_ref().cmp(&other.description()),
+            ColumnIdentifier::Series => match (self.series(), other.series()) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Less,
+                (Some(_), None) => Ordering::Greater,
+                (Some(a), Some(b)) => collation
+                    .cmp_str(&a.name, &b.name)
+                    .then_with(|| a.cmp_index(b)),
+            },
+            ColumnIdentifier::Title => collation.cmp_opt(self.title(), other.title()),
+            ColumnIdentifier::Description => {
+                collation.cmp_opt(self.description(), other.description())
+            }
             ColumnIdentifier::Author => match (&self.authors, &other.authors) {
                 // TODO: Better comparison algorithm.
                 (None, None) => Ordering::Equal,
                 (None, Some(_)) => Ordering::Less,
                 (Some(_), None) => Ordering::Greater,
                 (Some(self_authors), Some(other_authors)) => {
-                    let mut self_iter = self_authors.iter();
-                    let mut other_iter = other_authors.iter();
+                    // Authors are compared by surname, however their names are stored.
+                    let mut self_iter = self_authors.iter().map(|a| author::surname_first(a));
+                    let mut other_iter = other_authors.iter().map(|a| author::surname_first(a));
                     let mut res = Ordering::Equal;
                     while res == Ordering::Equal {
                         let auth_a = self_iter.next();
                         let auth_b = other_iter.next();
-                        res = auth_a.cmp(&auth_b);
+                        res = collation.cmp_opt(auth_a, auth_b);
 
                         // Only need to check one - if equal and one is none, both are none
                         if auth_a.is_none() {
@@ -373,7 +671,7 @@ impl Book {
                     res
                 }
             },
-            c => self.get_column(c).cmp(&other.get_column(c)),
+            c => collation.cmp_opt(self.get_column(c), other.get_column(c)),
         }
     }
 
@@ -381,10 +679,11 @@ impl Book {
         &self,
         other: &Self,
         columns: &[(ColumnIdentifier, ColumnOrder)],
+        collation: Collation,
     ) -> Ordering {
         let mut ordering = Ordering::Equal;
         for (column, column_order) in columns {
-            ordering = self.cmp_column(other, column);
+            ordering = self.cmp_column(other, column, collation);
 
             if *column_order == ColumnOrder::Descending {
                 ordering = ordering.reverse();
@@ -396,6 +695,67 @@ impl Book {
         }
         ordering
     }
+
+    /// Returns the columns which are compared between `self` and `other`: the core columns,
+    /// followed by every named tag of either book, in alphabetical order.
+    pub fn compared_columns(&self, other: &Self) -> Vec<ColumnIdentifier> {
+        let mut named_tags: Vec<_> = self
+            .named_tags
+            .keys()
+            .chain(other.named_tags.keys())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .cloned()
+            .collect();
+        named_tags.sort();
+
+        let mut columns = vec![
+            ColumnIdentifier::Title,
+            ColumnIdentifier::Author,
+            ColumnIdentifier::Series,
+            ColumnIdentifier::Description,
+            ColumnIdentifier::Tags,
+            ColumnIdentifier::Cover,
+        ];
+        columns.extend(named_tags.into_iter().map(ColumnIdentifier::NamedTag));
+        columns
+    }
+
+    /// Returns the value of `column` as shown when comparing books. Unlike `get_column`,
+    /// free tags are included, sorted and joined by commas.
+    pub fn compared_value(&self, column: &ColumnIdentifier) -> Option<String> {
+        match column {
+            ColumnIdentifier::Tags => {
+                if self.free_tags.is_empty() {
+                    return None;
+                }
+                let mut tags: Vec<_> = self.free_tags.iter().map(String::as_str).collect();
+                tags.sort_unstable();
+                Some(tags.join(", "))
+            }
+            column => self.get_column(column).map(Cow::into_owned),
+        }
+    }
+
+    /// Returns each column which differs between `self` and `other`, with the value of the
+    /// column in `self` and in `other`, in the order given by `compared_columns`.
+    ///
+    /// # Arguments
+    /// * ` other ` - The book to compare against.
+    pub fn diff(&self, other: &Self) -> Vec<(ColumnIdentifier, Option<String>, Option<String>)> {
+        self.compared_columns(other)
+            .into_iter()
+            .filter_map(|column| {
+                let left = self.compared_value(&column);
+                let right = other.compared_value(&column);
+                if left == right {
+                    None
+                } else {
+                    Some((column, left, right))
+                }
+            })
+            .collect()
+    }
 }
 
 impl fmt::Display for Book {
@@ -414,6 +774,219 @@ impl fmt::Display for Book {
 mod test {
     use super::*;
     use std::convert::TryFrom;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_multimap_edits() {
+        let author =
+            |value: &str| ColumnIdentifier::MultiMapExact("author".to_string(), value.to_string());
+        let mut book = Book::default();
+        book.authors = Some(vec![
+            "Frank Herbrt".to_string(),
+            "Brian Herbert".to_string(),
+        ]);
+
+        book.edit_column(
+            &author("Frank Herbrt"),
+            Edit::Replace("Frank Herbert".to_string()),
+        )
+        .unwrap();
+        assert_eq!(
+            book.authors(),
+            Some(&["Frank Herbert".to_string(), "Brian Herbert".to_string()][..])
+        );
+
+        // Replacing an author with an existing author removes the duplicate.
+        book.edit_column(
+            &author("Brian Herbert"),
+            Edit::Replace("Frank Herbert".to_string()),
+        )
+        .unwrap();
+        assert_eq!(book.authors(), Some(&["Frank Herbert".to_string()][..]));
+
+        book.edit_column(&author("Frank Herbert"), Edit::Append(" Jr.".to_string()))
+            .unwrap();
+        assert_eq!(book.authors(), Some(&["Frank Herbert Jr.".to_string()][..]));
+        book.edit_column(&author("Frank Herbert Jr."), Edit::Delete)
+            .unwrap();
+        assert_eq!(book.authors(), None);
+
+        // Other multimap tags are not held by books.
+        book.edit_column(
+            &ColumnIdentifier::MultiMap("genre".to_string()),
+            Edit::Append("scifi".to_string()),
+        )
+        .unwrap();
+        assert_eq!(book, Book::default());
+    }
+
+    #[test]
+    fn test_display_value() {
+        let mut book = Book::default();
+        book.authors = Some(vec!["A".to_string(), "B".to_string(), "C".to_string()]);
+        let display = AuthorDisplay {
+            separator: String::from(" & "),
+            limit: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(
+            book.display_value(&ColumnIdentifier::Author, &display)
+                .as_deref(),
+            Some("A & B & C")
+        );
+
+        book.authors.as_mut().unwrap().push("D".to_string());
+        let display = AuthorDisplay {
+            limit: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(
+            book.display_value(&ColumnIdentifier::Author, &display)
+                .as_deref(),
+            Some("A, B, C et al.")
+        );
+        assert_eq!(
+            book.display_value(&ColumnIdentifier::Author, &AuthorDisplay::default())
+                .as_deref(),
+            Some("A, B, C, D")
+        );
+        assert_eq!(
+            book.get_column(&ColumnIdentifier::Author).as_deref(),
+            Some("A, B, C, D")
+        );
+    }
+
+    #[test]
+    fn test_author_order() {
+        let mut book = Book::default();
+        book.authors = Some(vec![
+            "Michael Ende".to_string(),
+            "Ralph Manheim".to_string(),
+            "anonymous".to_string(),
+        ]);
+        book.variants.push(BookVariant {
+            book_type: BookType::EPUB,
+            path: PathBuf::from("The Neverending Story.epub"),
+            file_size: 0,
+            local_title: None,
+            identifier: None,
+            language: None,
+            additional_authors: None,
+            translators: Some(vec!["Ralph Manheim".to_string()]),
+            description: None,
+            series: None,
+            id: None,
+            hash: [0; 32],
+            free_tags: HashSet::new(),
+            named_tags: HashMap::new(),
+        });
+
+        let display = |order: AuthorOrder| AuthorDisplay {
+            order,
+            ..Default::default()
+        };
+        let author = |order: AuthorOrder| {
+            book.display_value(&ColumnIdentifier::Author, &display(order))
+                .map(Cow::into_owned)
+        };
+        assert_eq!(
+            author(AuthorOrder::AsEntered).as_deref(),
+            Some("Michael Ende, Ralph Manheim, anonymous")
+        );
+        assert_eq!(
+            author(AuthorOrder::Alphabetical).as_deref(),
+            Some("anonymous, Michael Ende, Ralph Manheim")
+        );
+        assert_eq!(
+            author(AuthorOrder::RolePriority).as_deref(),
+            Some("Michael Ende, anonymous, Ralph Manheim")
+        );
+
+        // The limit applies after ordering.
+        let display = AuthorDisplay {
+            limit: Some(1),
+            order: AuthorOrder::Alphabetical,
+            ..Default::default()
+        };
+        assert_eq!(display.display(&book).as_deref(), Some("anonymous et al."));
+    }
+
+    #[test]
+    fn test_variant_by_format() {
+        let variant = |book_type: BookType, path: &str| BookVariant {
+            book_type,
+            path: PathBuf::from(path),
+            file_size: 0,
+            local_title: None,
+            identifier: None,
+            language: None,
+            additional_authors: None,
+            translators: None,
+            description: None,
+            series: None,
+            id: None,
+            hash: [0; 32],
+            free_tags: HashSet::new(),
+            named_tags: HashMap::new(),
+        };
+        let mut book = Book::default();
+        assert_eq!(book.variant_by_format(&[BookType::EPUB]), None);
+
+        book.variants.push(variant(BookType::PDF, "Dune.pdf"));
+        book.variants.push(variant(BookType::EPUB, "Dune.epub"));
+        book.variants.push(variant(BookType::EPUB, "Dune (2).epub"));
+        assert_eq!(book.variant_by_format(&[BookType::EPUB]), Some(1));
+        assert_eq!(
+            book.variant_by_format(&[BookType::MOBI, BookType::PDF, BookType::EPUB]),
+            Some(0)
+        );
+        // Without a matching format, the preferred variant is chosen.
+        assert_eq!(book.variant_by_format(&[BookType::FB2]), Some(0));
+        assert_eq!(book.variant_by_format(&[]), Some(0));
+    }
+
+    #[test]
+    fn test_author_names() {
+        let book = |authors: &[&str]| Book {
+            authors: Some(authors.iter().map(|a| a.to_string()).collect()),
+            ..Default::default()
+        };
+        let display = AuthorDisplay {
+            names: AuthorNameOrder::LastFirst,
+            separator: String::from("; "),
+            ..Default::default()
+        };
+        assert_eq!(
+            display
+                .display(&book(&["J.R.R. Tolkien", "Christopher Tolkien"]))
+                .as_deref(),
+            Some("Tolkien, J.R.R.; Tolkien, Christopher")
+        );
+
+        // Books are sorted by surname, no matter how the names are stored.
+        let tolkien = book(&["Tolkien, J.R.R."]);
+        let herbert = book(&["Frank Herbert"]);
+        let le_guin = book(&["Ursula K. Le Guin"]);
+        let collation = Collation::CaseInsensitive;
+        assert_eq!(
+            herbert.cmp_column(&le_guin, &ColumnIdentifier::Author, collation),
+            Ordering::Less
+        );
+        assert_eq!(
+            le_guin.cmp_column(&tolkien, &ColumnIdentifier::Author, collation),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn test_column_names() {
+        for name in COLUMN_NAMES.iter() {
+            assert!(!matches!(
+                ColumnIdentifier::from(name),
+                ColumnIdentifier::NamedTag(_)
+            ));
+        }
+    }
 
     #[test]
     fn test_setting_columns() {
@@ -428,6 +1001,13 @@ mod test {
             ("series", "hello world", Ok(()), "hello world"),
             ("series", "hello world [1.2]", Ok(()), "hello world [1.2]"),
             ("random_tag", "random value", Ok(()), "random value"),
+            ("read", " Reading", Ok(()), "reading"),
+            (
+                "read",
+                "finished",
+                Err(RecordError::InvalidValue("finished".to_string())),
+                "reading",
+            ),
         ];
 
         for (col, new_value, result, expected) in &test_sets {
@@ -447,4 +1027,67 @@ mod test {
             );
         }
     }
+
+    #[test]
+    fn test_read_status() {
+        let mut book = Book::default();
+        assert_eq!(book.get_column(&ColumnIdentifier::ReadStatus), None);
+
+        book.set_column(&ColumnIdentifier::ReadStatus, "YES")
+            .unwrap();
+        assert_eq!(book.tags().get(READ_STATUS_TAG), Some(&"yes".to_string()));
+        assert_eq!(
+            book.extend_column(&ColumnIdentifier::ReadStatus, "no"),
+            Err(RecordError::InextensibleColumn)
+        );
+
+        book.delete_column(&ColumnIdentifier::ReadStatus).unwrap();
+        assert!(book.tags().is_empty());
+    }
+
+    #[test]
+    fn test_diff() {
+        let mut left = Book::default();
+        left.title = Some("Dune".to_string());
+        left.authors = Some(vec!["Frank Herbert".to_string()]);
+        left.free_tags.insert("scifi".to_string());
+        left.named_tags
+            .insert("publisher".to_string(), "Chilton".to_string());
+
+        let mut right = left.clone();
+        assert!(left.diff(&right).is_empty());
+
+        right.title = Some("Dune Messiah".to_string());
+        right.free_tags.insert("classic".to_string());
+        right.named_tags.clear();
+        right
+            .named_tags
+            .insert("language".to_string(), "en".to_string());
+
+        assert_eq!(
+            left.diff(&right),
+            vec![
+                (
+                    ColumnIdentifier::Title,
+                    Some("Dune".to_string()),
+                    Some("Dune Messiah".to_string())
+                ),
+                (
+                    ColumnIdentifier::Tags,
+                    Some("scifi".to_string()),
+                    Some("classic, scifi".to_string())
+                ),
+                (
+                    ColumnIdentifier::NamedTag("language".to_string()),
+                    None,
+                    Some("en".to_string())
+                ),
+                (
+                    ColumnIdentifier::NamedTag("publisher".to_string()),
+                    Some("Chilton".to_string()),
+                    None
+                ),
+            ]
+        );
+    }
 }
@@ -1,9 +1,152 @@
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom};
 use std::str::FromStr;
 
+use flate2::read::DeflateDecoder;
 use isbn2::Isbn;
 use quick_epub::{IdentifierScheme, Metadata};
 
-use crate::variant::{unravel_author, Identifier, MetadataFiller};
+use crate::author;
+use crate::error::ParseContext;
+use crate::series::Series;
+use crate::variant::{Identifier, MetadataFiller};
+use crate::BookError;
+
+/// The metadata of an EPUB file, along with the metadata which is read from its package
+/// document, as `Metadata` only reads a single title, author and identifier, and no series.
+pub(crate) struct EpubFiller {
+    metadata: Metadata,
+    titles: Option<OpfTitles>,
+    creators: OpfCreators,
+    publication: OpfPublication,
+    modified: Option<String>,
+    identifiers: Vec<Identifier>,
+    series: Option<Series>,
+    pages: Option<usize>,
+}
+
+impl EpubFiller {
+    /// Reads the package document of the EPUB archive in `reader`, which `metadata` was read
+    /// from. Archives whose package document can not be read only use `metadata`.
+    ///
+    /// # Errors
+    /// Will return `BookError::BadMimetype` if the `mimetype` entry of the archive is not
+    /// `application/epub+zip`. Unlike `validate_mimetype`, archives whose `mimetype` entry is
+    /// compressed or missing are accepted, as most readers accept them.
+    pub(crate) fn new<R: Read + Seek>(
+        metadata: Metadata,
+        reader: &mut R,
+    ) -> Result<Self, BookError> {
+        if let Ok(Some(mimetype)) = read_entry(reader, "mimetype") {
+            let mimetype = String::from_utf8_lossy(&mimetype);
+            if mimetype.trim() != "application/epub+zip" {
+                return Err(BookError::BadMimetype(mimetype.into_owned()));
+            }
+        }
+        let (opf_path, opf) = read_package(reader).unwrap_or_default();
+        // The page count of the print edition is listed by the navigation document.
+        let pages = opf_nav(&opf, &opf_path)
+            .and_then(|nav| read_entry(reader, &nav).ok().flatten())
+            .and_then(|nav| nav_page_count(&String::from_utf8_lossy(&nav)));
+        Ok(EpubFiller {
+            metadata,
+            titles: opf_titles(&opf),
+            creators: opf_creators(&opf),
+            publication: opf_publication(&opf),
+            modified: opf_modified(&opf),
+            identifiers: opf_identifiers(&opf),
+            series: opf_series(&opf),
+            pages,
+        })
+    }
+}
+
+impl MetadataFiller for EpubFiller {
+    fn take_title(&mut self, title: &mut Option<String>) {
+        match &mut self.titles {
+            Some(titles) => *title = Some(std::mem::take(&mut titles.main)),
+            None => self.metadata.take_title(title),
+        }
+    }
+
+    fn take_description(&mut self, description: &mut Option<String>) {
+        self.metadata.take_description(description);
+    }
+
+    fn take_language(&mut self, language: &mut Option<String>) {
+        self.metadata.take_language(language);
+    }
+
+    /// Prefers the first valid ISBN of the package document, as the identifier read by
+    /// `Metadata` is often a UUID.
+    fn take_identifier(&mut self, identifier: &mut Option<Identifier>) {
+        let isbn = self
+            .identifiers
+            .iter()
+            .find(|id| matches!(id, Identifier::ISBN(_)));
+        if let Some(isbn) = isbn {
+            *identifier = Some(isbn.clone());
+            return;
+        }
+        self.metadata.take_identifier(identifier);
+        if identifier.is_none() {
+            *identifier = self.identifiers.first().cloned();
+        }
+    }
+
+    fn take_authors(&mut self, authors: &mut Option<Vec<String>>) {
+        let named = self.creators.with_role("aut");
+        if named.is_empty() {
+            self.metadata.take_authors(authors);
+        } else {
+            *authors = Some(named);
+        }
+    }
+
+    fn take_translators(&mut self, translators: &mut Option<Vec<String>>) {
+        let named = self.creators.with_role("trl");
+        if !named.is_empty() {
+            *translators = Some(named);
+        }
+    }
+
+    fn take_series(&mut self, series: &mut Option<Series>) {
+        *series = self.series.take();
+    }
+
+    /// Fills in the other titles by their title type (eg. "subtitle"), the publisher, the
+    /// publication and modification dates, each identifier by its scheme (eg. "amazon"), and
+    /// the number of pages of the print edition.
+    fn take_named_tags(&mut self, tags: &mut HashMap<String, String>) {
+        if let Some(titles) = &mut self.titles {
+            for (kind, title) in std::mem::take(&mut titles.subtitles) {
+                tags.entry(kind).or_insert(title);
+            }
+        }
+        if let Some(publisher) = self.publication.publisher.take() {
+            tags.insert("publisher".to_string(), publisher);
+        }
+        if let Some(date) = self.publication.date.take() {
+            tags.insert("published".to_string(), date);
+        }
+        for (name, value) in std::mem::take(&mut self.publication.extended_values) {
+            tags.entry(name).or_insert(value);
+        }
+        if let Some(modified) = self.modified.take() {
+            tags.insert("modified".to_string(), modified);
+        }
+        for identifier in std::mem::take(&mut self.identifiers) {
+            let (scheme, value) = match identifier {
+                Identifier::ISBN(isbn) => ("isbn".to_string(), isbn.to_string()),
+                Identifier::Unknown(scheme, value) => (scheme, value),
+            };
+            tags.entry(scheme).or_insert(value);
+        }
+        if let Some(pages) = self.pages.take() {
+            tags.insert("pages".to_string(), pages.to_string());
+        }
+    }
+}
 
 impl MetadataFiller for Metadata {
     fn take_title(&mut self, title: &mut Option<String>) {
@@ -35,7 +178,1549 @@ impl MetadataFiller for Metadata {
 
     fn take_authors(&mut self, authors: &mut Option<Vec<String>>) {
         if let Some(author) = std::mem::take(&mut self.author) {
-            *authors = Some(vec![unravel_author(&author)]);
+            *authors = Some(vec![author::normalize(&author)]);
         }
     }
 }
+
+const EOCD_SIGNATURE: u32 = 0x0605_4b50;
+const ZIP64_EOCD_LOCATOR_SIGNATURE: u32 = 0x0706_4b50;
+const ZIP64_EOCD_SIGNATURE: u32 = 0x0606_4b50;
+const CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0201_4b50;
+
+/// The compression methods EPUB readers are expected to handle - stored and deflate.
+const SUPPORTED_COMPRESSION: [u16; 2] = [0, 8];
+
+fn read_u16(buf: &[u8], i: usize) -> u16 {
+    u16::from_le_bytes([buf[i], buf[i + 1]])
+}
+
+fn read_u32(buf: &[u8], i: usize) -> u32 {
+    u32::from_le_bytes([buf[i], buf[i + 1], buf[i + 2], buf[i + 3]])
+}
+
+fn read_u64(buf: &[u8], i: usize) -> u64 {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&buf[i..i + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+fn invalid_zip() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        "invalid zip central directory",
+    )
+}
+
+/// An entry in the central directory of a zip archive.
+struct ZipEntry {
+    name: Vec<u8>,
+    method: u16,
+    compressed_size: u64,
+    local_header_offset: u64,
+}
+
+/// Reads the entries of the central directory of the zip archive in `reader`. Archives with
+/// zip64 central directories are supported.
+///
+/// # Errors
+/// Will return an error if reading fails, or if the central directory is malformed.
+fn central_directory<R: Read + Seek>(reader: &mut R) -> Result<Vec<ZipEntry>, std::io::Error> {
+    let len = reader.seek(SeekFrom::End(0))?;
+    // The end of central directory record is 22 bytes, followed by a comment of up to
+    // u16::MAX bytes.
+    let tail_len = len.min(22 + u64::from(u16::MAX));
+    reader.seek(SeekFrom::Start(len - tail_len))?;
+    let mut tail = vec![0; tail_len as usize];
+    reader.read_exact(&mut tail)?;
+
+    let eocd = (0..=tail.len().saturating_sub(22))
+        .rev()
+        .find(|&i| tail.len() >= i + 22 && read_u32(&tail, i) == EOCD_SIGNATURE)
+        .ok_or_else(invalid_zip)?;
+
+    let mut num_entries = u64::from(read_u16(&tail, eocd + 10));
+    let mut cd_offset = u64::from(read_u32(&tail, eocd + 16));
+
+    // The zip64 locator immediately precedes the end of central directory record.
+    if eocd >= 20 && read_u32(&tail, eocd - 20) == ZIP64_EOCD_LOCATOR_SIGNATURE {
+        reader.seek(SeekFrom::Start(read_u64(&tail, eocd - 12)))?;
+        let mut record = [0; 56];
+        reader.read_exact(&mut record)?;
+        if read_u32(&record, 0) != ZIP64_EOCD_SIGNATURE {
+            return Err(invalid_zip());
+        }
+        num_entries = read_u64(&record, 32);
+        cd_offset = read_u64(&record, 48);
+    }
+
+    reader.seek(SeekFrom::Start(cd_offset))?;
+    let mut header = [0; 46];
+    let mut entries = vec![];
+    for _ in 0..num_entries {
+        reader.read_exact(&mut header)?;
+        if read_u32(&header, 0) != CENTRAL_DIRECTORY_SIGNATURE {
+            return Err(invalid_zip());
+        }
+
+        let mut name = vec![0; usize::from(read_u16(&header, 28))];
+        reader.read_exact(&mut name)?;
+        let variable_len = i64::from(read_u16(&header, 30)) + i64::from(read_u16(&header, 32));
+        reader.seek(SeekFrom::Current(variable_len))?;
+
+        entries.push(ZipEntry {
+            name,
+            method: read_u16(&header, 10),
+            compressed_size: u64::from(read_u32(&header, 20)),
+            local_header_offset: u64::from(read_u32(&header, 42)),
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Reads the central directory of the zip archive in `reader`, and returns the compression
+/// method of the first entry which is neither stored nor deflate, if any such entry exists.
+/// Archives with zip64 central directories are supported.
+///
+/// # Arguments
+/// * ` reader ` - A reader over a zip archive.
+///
+/// # Errors
+/// Will return an error if reading fails, or if the central directory is malformed.
+pub(crate) fn unsupported_compression<R: Read + Seek>(
+    reader: &mut R,
+) -> Result<Option<u16>, std::io::Error> {
+    Ok(central_directory(reader)?
+        .into_iter()
+        .map(|entry| entry.method)
+        .find(|method| !SUPPORTED_COMPRESSION.contains(method)))
+}
+
+/// Moves `reader` to the start of the data of `entry`.
+fn seek_to_data<R: Read + Seek>(reader: &mut R, entry: &ZipEntry) -> Result<(), std::io::Error> {
+    // The local file header is 30 bytes, followed by the name and the extra field, whose
+    // lengths may differ from those in the central directory.
+    let mut header = [0; 30];
+    reader.seek(SeekFrom::Start(entry.local_header_offset))?;
+    reader.read_exact(&mut header)?;
+    let variable_len = i64::from(read_u16(&header, 26)) + i64::from(read_u16(&header, 28));
+    reader.seek(SeekFrom::Current(variable_len))?;
+    Ok(())
+}
+
+/// Returns the contents of the entry of the zip archive in `reader` with the given name, or
+/// None if the archive has no such entry. Entries must be stored or deflated.
+///
+/// # Errors
+/// Will return an error if reading fails, if the central directory is malformed, or if the
+/// entry uses another compression method.
+fn read_entry<R: Read + Seek>(reader: &mut R, name: &str) -> Result<Option<Vec<u8>>, BookError> {
+    read_entry_where(reader, |entry| entry == name.as_bytes())
+}
+
+/// Reads the contents of the first entry of the zip archive in `reader` whose name satisfies
+/// `matches`, as described by `read_entry`.
+///
+/// # Errors
+/// Will return an error if reading fails, if the central directory is malformed, or if the
+/// entry uses another compression method.
+pub(crate) fn read_entry_where<R: Read + Seek, F: Fn(&[u8]) -> bool>(
+    reader: &mut R,
+    matches: F,
+) -> Result<Option<Vec<u8>>, BookError> {
+    let entry = match central_directory(reader)?
+        .into_iter()
+        .find(|entry| matches(&entry.name))
+    {
+        Some(entry) => entry,
+        None => return Ok(None),
+    };
+    seek_to_data(reader, &entry)?;
+
+    let mut contents = vec![];
+    let mut data = reader.take(entry.compressed_size);
+    match entry.method {
+        0 => data.read_to_end(&mut contents)?,
+        8 => DeflateDecoder::new(data).read_to_end(&mut contents)?,
+        method => return Err(BookError::UnsupportedCompression(method)),
+    };
+    Ok(Some(contents))
+}
+
+/// Reads the OPF package document of the EPUB archive in `reader`, returning its path inside the
+/// archive and its contents.
+///
+/// # Errors
+/// Will return an error if the archive can not be read, or if it has no package document.
+fn read_package<R: Read + Seek>(reader: &mut R) -> Result<(String, String), BookError> {
+    let no_package = || {
+        BookError::NoPackage(ParseContext {
+            position: 0,
+            tag: None,
+        })
+    };
+    let container = read_entry(reader, "META-INF/container.xml")?.ok_or_else(no_package)?;
+    let opf_path = container_root_file(&String::from_utf8_lossy(&container))?;
+    let opf = read_entry(reader, &opf_path)?.ok_or_else(no_package)?;
+    Ok((opf_path, String::from_utf8_lossy(&opf).into_owned()))
+}
+
+/// Reads the cover image of the EPUB archive in `reader`, which is found as described by
+/// `opf_cover`. Returns None if the package document does not name a cover, or if the archive
+/// does not contain it.
+///
+/// # Arguments
+/// * ` reader ` - A reader over an EPUB archive.
+///
+/// # Errors
+/// Will return an error if the archive can not be read, or if it has no package document.
+pub fn read_cover<R: Read + Seek>(reader: &mut R) -> Result<Option<Vec<u8>>, BookError> {
+    let (opf_path, opf) = read_package(reader)?;
+    match opf_cover(&opf, &opf_path) {
+        Some(cover) => read_entry(reader, &cover.path),
+        None => Ok(None),
+    }
+}
+
+/// Checks that the zip archive in `reader` contains a `mimetype` entry, as required by the EPUB
+/// Open Container Format, and that its contents are `application/epub+zip`. The entry must be
+/// stored without compression - a compressed entry is treated as a mismatch.
+///
+/// # Arguments
+/// * ` reader ` - A reader over a zip archive.
+///
+/// # Errors
+/// Will return `BookError::NoMimetype` if the archive has no `mimetype` entry, and
+/// `BookError::BadMimetype` with the contents of the entry if they do not match.
+pub fn validate_mimetype<R: Read + Seek>(reader: &mut R) -> Result<(), BookError> {
+    let entry = central_directory(reader)?
+        .into_iter()
+        .find(|entry| entry.name == b"mimetype")
+        .ok_or(BookError::NoMimetype)?;
+    seek_to_data(reader, &entry)?;
+
+    // The mimetype is expected to be tiny - anything much larger is not a valid mimetype.
+    let mut contents = vec![0; entry.compressed_size.min(256) as usize];
+    reader.read_exact(&mut contents)?;
+    let mimetype = String::from_utf8_lossy(&contents);
+    if entry.method == 0 && mimetype.trim() == "application/epub+zip" {
+        Ok(())
+    } else {
+        Err(BookError::BadMimetype(mimetype.into_owned()))
+    }
+}
+
+/// Returns the path of the OPF package document named by the first `<rootfile>` element of
+/// `META-INF/container.xml` with the OPF media type.
+///
+/// # Arguments
+/// * ` container ` - The contents of `META-INF/container.xml`.
+///
+/// # Errors
+/// Will return `BookError::NoPackage` if a `<rootfile>` element is unterminated or has no
+/// `full-path`, with the byte offset of the element, or if no package document is named, with
+/// the length of the document.
+pub fn container_root_file(container: &str) -> Result<String, BookError> {
+    let open = "<rootfile";
+    for (i, _) in container.match_indices(open) {
+        let rest = &container[i + open.len()..];
+        // Skips the enclosing <rootfiles> element.
+        if !rest.starts_with(char::is_whitespace) {
+            continue;
+        }
+
+        let no_package = || {
+            BookError::NoPackage(ParseContext {
+                position: i,
+                tag: Some("rootfile".to_string()),
+            })
+        };
+        let attributes = &rest[..rest.find('>').ok_or_else(no_package)?];
+        let path = attribute(attributes, "full-path").ok_or_else(no_package)?;
+        if attribute(attributes, "media-type").map_or(true, |media_type| {
+            media_type == "application/oebps-package+xml"
+        }) {
+            return Ok(unescape(path));
+        }
+    }
+
+    Err(BookError::NoPackage(ParseContext {
+        position: container.len(),
+        tag: None,
+    }))
+}
+
+/// Returns the value of the `<meta property="dcterms:modified">` element of an EPUB 3 OPF
+/// package document, which records when the book was last modified.
+///
+/// # Arguments
+/// * ` opf ` - The contents of the OPF package document.
+pub fn opf_modified(opf: &str) -> Option<String> {
+    let mut rest = opf;
+    while let Some(start) = rest.find("<meta") {
+        rest = &rest[start + "<meta".len()..];
+        let tag_end = rest.find('>')?;
+        let attributes = &rest[..tag_end];
+        // Skip other elements starting with "meta", and empty meta elements.
+        if !attributes.starts_with(char::is_whitespace) || attributes.ends_with('/') {
+            continue;
+        }
+        if !has_attribute(attributes, "property", "dcterms:modified") {
+            continue;
+        }
+
+        let body = &rest[tag_end + 1..];
+        let value = body[..body.find("</meta")?].trim();
+        return if value.is_empty() {
+            None
+        } else {
+            Some(value.to_string())
+        };
+    }
+    None
+}
+
+/// The titles of an OPF package document.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OpfTitles {
+    /// The main title of the book.
+    pub main: String,
+    /// The other titles marked with an EPUB 3 `title-type`, as pairs of the title type
+    /// (eg. "subtitle" or "collection") and the title, in display order.
+    pub subtitles: Vec<(String, String)>,
+}
+
+/// Reads every `dc:title` element of an OPF package document, and resolves the EPUB 3
+/// `<meta refines="#id" property="title-type">` elements referring to them. The title with the
+/// `main` title type is the main title, and the other typed titles are ordered by their
+/// `display-seq`. If no title is marked as the main title, the first title is used.
+///
+/// # Arguments
+/// * ` opf ` - The contents of the OPF package document.
+pub fn opf_titles(opf: &str) -> Option<OpfTitles> {
+    let titles: Vec<_> = elements_with_text(opf, "dc:title")
+        .into_iter()
+        .map(|(attributes, text)| (attribute(attributes, "id"), unescape(text.trim())))
+        .filter(|(_, title)| !title.is_empty())
+        .collect();
+    let refines: Vec<_> = elements_with_text(opf, "meta")
+        .into_iter()
+        .filter_map(|(attributes, text)| {
+            Some((
+                attribute(attributes, "refines")?.trim_start_matches('#'),
+                attribute(attributes, "property")?,
+                text.trim(),
+            ))
+        })
+        .collect();
+    let refined = |id: Option<&str>, property: &str| {
+        let id = id?;
+        refines
+            .iter()
+            .find(|(target, p, _)| *target == id && *p == property)
+            .map(|(_, _, value)| *value)
+    };
+
+    let main = titles
+        .iter()
+        .position(|(id, _)| refined(*id, "title-type") == Some("main"))
+        .unwrap_or(0);
+    let mut subtitles: Vec<_> = titles
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i != main)
+        .filter_map(|(_, (id, title))| {
+            let seq = refined(*id, "display-seq")
+                .and_then(|seq| seq.parse::<usize>().ok())
+                .unwrap_or(usize::MAX);
+            Some((seq, refined(*id, "title-type")?, title))
+        })
+        .collect();
+    // Sorting is stable, so titles without a display-seq stay in document order.
+    subtitles.sort_by_key(|(seq, _, _)| *seq);
+
+    Some(OpfTitles {
+        main: titles.get(main)?.1.clone(),
+        subtitles: subtitles
+            .into_iter()
+            .map(|(_, kind, title)| (kind.to_string(), title.clone()))
+            .collect(),
+    })
+}
+
+/// The creators of an OPF package document.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct OpfCreators {
+    /// The first creator with the `aut` role, for readers which expect a single author.
+    pub author: Option<String>,
+    /// Each creator and its MARC relator role (eg. "aut", "edt" or "trl"), in document order.
+    pub authors: Vec<(String, Option<String>)>,
+}
+
+impl OpfCreators {
+    /// Returns the names of the creators with the given role, in document order, with names of
+    /// the form "Last, First" reordered to "First Last". Creators without a role are treated as
+    /// authors, as this is what most readers assume.
+    ///
+    /// # Arguments
+    /// * ` role ` - The MARC relator code of the role, eg. "aut" or "trl".
+    pub fn with_role(&self, role: &str) -> Vec<String> {
+        self.authors
+            .iter()
+            .filter(|(_, r)| r.as_deref().unwrap_or("aut") == role)
+            .map(|(name, _)| author::normalize(name))
+            .collect()
+    }
+}
+
+/// Reads every `dc:creator` element of an OPF package document, along with its role. The role
+/// is taken from the EPUB 2 `opf:role` attribute, or the EPUB 3
+/// `<meta refines="#id" property="role">` element referring to the creator.
+///
+/// # Arguments
+/// * ` opf ` - The contents of the OPF package document.
+pub fn opf_creators(opf: &str) -> OpfCreators {
+    let roles: Vec<_> = elements_with_text(opf, "meta")
+        .into_iter()
+        .filter(|(attributes, _)| has_attribute(attributes, "property", "role"))
+        .filter_map(|(attributes, text)| {
+            Some((
+                attribute(attributes, "refines")?.trim_start_matches('#'),
+                text.trim(),
+            ))
+        })
+        .collect();
+
+    let authors: Vec<_> = elements_with_text(opf, "dc:creator")
+        .into_iter()
+        .map(|(attributes, text)| {
+            let role = attribute(attributes, "opf:role").or_else(|| {
+                let id = attribute(attributes, "id")?;
+                roles
+                    .iter()
+                    .find(|(target, _)| *target == id)
+                    .map(|(_, role)| *role)
+            });
+            (unescape(text.trim()), role.map(str::to_string))
+        })
+        .filter(|(name, _)| !name.is_empty())
+        .collect();
+
+    OpfCreators {
+        author: authors
+            .iter()
+            .find(|(_, role)| role.as_deref().unwrap_or("aut") == "aut")
+            .map(|(name, _)| name.clone()),
+        authors,
+    }
+}
+
+/// The publication details of an OPF package document.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct OpfPublication {
+    /// The publisher of the book.
+    pub publisher: Option<String>,
+    /// The publication date, as an ISO-8601 date of the form `YYYY`, `YYYY-MM` or `YYYY-MM-DD`.
+    pub date: Option<String>,
+    /// Values which could not be parsed, keyed by the element they were read from, so that
+    /// nothing is lost - eg. a `dc:date` which is not a valid date.
+    pub extended_values: Vec<(String, String)>,
+}
+
+/// Reads the `dc:publisher` and `dc:date` elements of an OPF package document. EPUB 2 packages
+/// may have several dates, distinguished by their `opf:event` - the `publication` date is
+/// preferred, and `modification` dates are never used.
+///
+/// # Arguments
+/// * ` opf ` - The contents of the OPF package document.
+pub fn opf_publication(opf: &str) -> OpfPublication {
+    let mut publication = OpfPublication {
+        publisher: elements_with_text(opf, "dc:publisher")
+            .into_iter()
+            .map(|(_, text)| unescape(text.trim()))
+            .find(|publisher| !publisher.is_empty()),
+        ..Default::default()
+    };
+
+    let dates: Vec<_> = elements_with_text(opf, "dc:date")
+        .into_iter()
+        .map(|(attributes, text)| (attribute(attributes, "opf:event"), text.trim()))
+        .filter(|(event, text)| *event != Some("modification") && !text.is_empty())
+        .collect();
+    let date = dates
+        .iter()
+        .find(|(event, _)| *event == Some("publication"))
+        .or_else(|| dates.first());
+    if let Some((_, date)) = date {
+        match normalize_date(date) {
+            Some(date) => publication.date = Some(date),
+            None => publication
+                .extended_values
+                .push(("dc:date".to_string(), unescape(date))),
+        }
+    }
+    publication
+}
+
+/// Normalizes a date of the form `YYYY`, `YYYY-MM` or `YYYY-MM-DD` to ISO-8601, where the
+/// components may also be separated by `/` or `.`. Any time following the date, as in full
+/// timestamps, is dropped.
+fn normalize_date(date: &str) -> Option<String> {
+    let date = date.split(|c| c == 'T' || c == ' ').next()?;
+    let parts: Vec<_> = date.split(|c| c == '-' || c == '/' || c == '.').collect();
+    if parts.len() > 3 || !parts.iter().all(|p| p.chars().all(|c| c.is_ascii_digit())) {
+        return None;
+    }
+
+    let year = parts.first().filter(|year| year.len() == 4)?;
+    let mut normalized = year.to_string();
+    for (part, max) in parts[1..].iter().zip([12, 31].iter()) {
+        let value: u32 = part.parse().ok().filter(|_| part.len() <= 2)?;
+        if value == 0 || value > *max {
+            return None;
+        }
+        normalized.push_str(&format!("-{:02}", value));
+    }
+    Some(normalized)
+}
+
+/// Reads every `dc:identifier` element of an OPF package document, in document order. The
+/// scheme is taken from the EPUB 2 `opf:scheme` attribute, an EPUB 3
+/// `<meta refines="#id" property="identifier-type">` element, or a `urn:` prefix of the value.
+/// Schemes are lowercased, and ASINs are reported under the "amazon" scheme. Valid ISBNs are
+/// returned as `Identifier::ISBN` - all other identifiers, including malformed ISBNs, are
+/// returned as `Identifier::Unknown(scheme, value)`.
+///
+/// # Arguments
+/// * ` opf ` - The contents of the OPF package document.
+pub fn opf_identifiers(opf: &str) -> Vec<Identifier> {
+    let types: Vec<_> = elements_with_text(opf, "meta")
+        .into_iter()
+        .filter(|(attributes, _)| has_attribute(attributes, "property", "identifier-type"))
+        .filter_map(|(attributes, text)| {
+            Some((
+                attribute(attributes, "refines")?.trim_start_matches('#'),
+                text.trim(),
+            ))
+        })
+        .collect();
+
+    elements_with_text(opf, "dc:identifier")
+        .into_iter()
+        .filter_map(|(attributes, text)| {
+            let mut value = unescape(text.trim());
+            let mut scheme = attribute(attributes, "opf:scheme")
+                .or_else(|| {
+                    let id = attribute(attributes, "id")?;
+                    types
+                        .iter()
+                        .find(|(target, _)| *target == id)
+                        .map(|(_, scheme)| *scheme)
+                })
+                .map(str::to_lowercase);
+            if let Some(rest) = value.strip_prefix("urn:") {
+                if let Some((urn_scheme, urn_value)) = rest.split_once(':') {
+                    scheme = scheme.or_else(|| Some(urn_scheme.to_lowercase()));
+                    value = urn_value.to_string();
+                }
+            }
+            if value.is_empty() {
+                return None;
+            }
+
+            // ONIX codes 02 and 15 are ISBN-10 and ISBN-13.
+            let scheme = match scheme.as_deref() {
+                Some("02") | Some("15") | Some("isbn") => "isbn",
+                Some("asin") | Some("mobi-asin") | Some("amazon") => "amazon",
+                Some(scheme) => scheme,
+                None => "unknown",
+            };
+            Some(Identifier::new(scheme, value))
+        })
+        .collect()
+}
+
+/// Reads the series of an OPF package document. EPUB 3 packages name the series with a
+/// `<meta property="belongs-to-collection">` element, whose position is given by a
+/// `<meta refines="#id" property="group-position">` element - collections refined with the
+/// `series` collection type are preferred over other collections. Otherwise, the
+/// `calibre:series` and `calibre:series_index` meta elements written by Calibre are used.
+/// Positions which are not numbers are ignored, leaving the series without an index.
+///
+/// # Arguments
+/// * ` opf ` - The contents of the OPF package document.
+pub fn opf_series(opf: &str) -> Option<Series> {
+    let metas = elements_with_text(opf, "meta");
+    let refined = |id: Option<&str>, property: &str| {
+        let id = id?;
+        metas
+            .iter()
+            .find(|(attributes, _)| {
+                attribute(attributes, "refines").map(|target| target.trim_start_matches('#'))
+                    == Some(id)
+                    && has_attribute(attributes, "property", property)
+            })
+            .map(|(_, value)| value.trim())
+    };
+
+    let collections: Vec<_> = metas
+        .iter()
+        .filter(|(attributes, _)| has_attribute(attributes, "property", "belongs-to-collection"))
+        .map(|(attributes, text)| (attribute(attributes, "id"), unescape(text.trim())))
+        .filter(|(_, name)| !name.is_empty())
+        .collect();
+    let collection = collections
+        .iter()
+        .find(|(id, _)| refined(*id, "collection-type") == Some("series"))
+        .or_else(|| collections.first());
+    if let Some((id, name)) = collection {
+        return Some(Series {
+            name: name.clone(),
+            index: refined(*id, "group-position").and_then(|index| index.parse().ok()),
+        });
+    }
+
+    let calibre = |name: &str| {
+        elements(opf, "meta")
+            .into_iter()
+            .find(|attributes| has_attribute(attributes, "name", name))
+            .and_then(|attributes| attribute(attributes, "content"))
+            .map(|content| unescape(content.trim()))
+    };
+    Some(Series {
+        name: calibre("calibre:series").filter(|name| !name.is_empty())?,
+        index: calibre("calibre:series_index").and_then(|index| index.parse().ok()),
+    })
+}
+
+/// Returns the attributes and text of each non-empty `<name ...>text</name>` element in `xml`.
+pub(crate) fn elements_with_text<'a>(xml: &'a str, name: &str) -> Vec<(&'a str, &'a str)> {
+    let open = format!("<{}", name);
+    let close = format!("</{}", name);
+    xml.match_indices(&open)
+        .map(|(i, _)| &xml[i + open.len()..])
+        .filter(|rest| rest.starts_with(|c: char| c.is_whitespace() || c == '>'))
+        .filter_map(|rest| {
+            let tag_end = rest.find('>')?;
+            let attributes = &rest[..tag_end];
+            if attributes.ends_with('/') {
+                return None;
+            }
+            let body = &rest[tag_end + 1..];
+            Some((attributes, &body[..body.find(&close)?]))
+        })
+        .collect()
+}
+
+/// Replaces the predefined XML entities in `text`.
+pub(crate) fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// The manifest item of an OPF package document which holds the cover image.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OpfCover {
+    /// The path of the cover image, relative to the root of the archive.
+    pub path: String,
+    /// The media type of the cover image, eg. "image/jpeg".
+    pub media_type: String,
+}
+
+/// Finds the cover image of an OPF package document. The EPUB 2 `<meta name="cover">` element
+/// is followed to the manifest item it names - if there is none, the first manifest item with
+/// the EPUB 3 `cover-image` property is used instead. The href of the item is resolved against
+/// the directory containing the package document, as hrefs are relative to it.
+///
+/// # Arguments
+/// * ` opf ` - The contents of the OPF package document.
+/// * ` opf_path ` - The path of the OPF package document inside the archive.
+pub fn opf_cover(opf: &str, opf_path: &str) -> Option<OpfCover> {
+    let items = elements(opf, "item");
+    let cover_id = elements(opf, "meta")
+        .into_iter()
+        .find(|attributes| has_attribute(attributes, "name", "cover"))
+        .and_then(|attributes| attribute(attributes, "content"));
+
+    let item = cover_id
+        .and_then(|id| {
+            items
+                .iter()
+                .find(|attributes| attribute(attributes, "id") == Some(id))
+        })
+        .or_else(|| {
+            items.iter().find(|attributes| {
+                attribute(attributes, "properties")
+                    .map_or(false, |p| p.split_whitespace().any(|p| p == "cover-image"))
+            })
+        })?;
+
+    let opf_dir = opf_path.rsplit_once('/').map_or("", |(dir, _)| dir);
+    Some(OpfCover {
+        path: resolve_href(opf_dir, attribute(item, "href")?),
+        media_type: attribute(item, "media-type")
+            .unwrap_or_default()
+            .to_string(),
+    })
+}
+
+/// Returns the path of the EPUB 3 navigation document of an OPF package document - the first
+/// manifest item with the `nav` property, resolved against the directory containing the package
+/// document.
+///
+/// # Arguments
+/// * ` opf ` - The contents of the OPF package document.
+/// * ` opf_path ` - The path of the OPF package document inside the archive.
+pub fn opf_nav(opf: &str, opf_path: &str) -> Option<String> {
+    let item = elements(opf, "item").into_iter().find(|attributes| {
+        attribute(attributes, "properties")
+            .map_or(false, |p| p.split_whitespace().any(|p| p == "nav"))
+    })?;
+
+    let opf_dir = opf_path.rsplit_once('/').map_or("", |(dir, _)| dir);
+    Some(resolve_href(opf_dir, attribute(item, "href")?))
+}
+
+/// Returns the number of pages listed by the `<nav epub:type="page-list">` element of an EPUB 3
+/// navigation document, which maps the pages of a print edition to locations in the book.
+/// Returns `None` if the document has no page list.
+///
+/// # Arguments
+/// * ` nav ` - The contents of the navigation document.
+pub fn nav_page_count(nav: &str) -> Option<usize> {
+    let open = "<nav";
+    nav.match_indices(open).find_map(|(i, _)| {
+        let rest = &nav[i + open.len()..];
+        if !rest.starts_with(char::is_whitespace) {
+            return None;
+        }
+        let end = rest.find('>')?;
+        if !attribute(&rest[..end], "epub:type")
+            .map_or(false, |t| t.split_whitespace().any(|t| t == "page-list"))
+        {
+            return None;
+        }
+
+        let list = &rest[end + 1..];
+        let list = &list[..list.find("</nav>").unwrap_or_else(|| list.len())];
+        Some(elements(list, "a").len())
+    })
+}
+
+/// Joins `href` onto the archive directory `dir`, resolving `.` and `..` segments.
+fn resolve_href(dir: &str, href: &str) -> String {
+    let mut segments: Vec<_> = dir.split('/').filter(|s| !s.is_empty()).collect();
+    for segment in href.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    segments.join("/")
+}
+
+/// Returns the attributes of each `<name ...>` element in `xml`.
+pub(crate) fn elements<'a>(xml: &'a str, name: &str) -> Vec<&'a str> {
+    let open = format!("<{}", name);
+    xml.match_indices(&open)
+        .map(|(i, _)| &xml[i + open.len()..])
+        .filter(|rest| rest.starts_with(char::is_whitespace))
+        .filter_map(|rest| Some(rest[..rest.find('>')?].trim_end_matches('/')))
+        .collect()
+}
+
+/// Returns the value of the attribute `name` in `attributes`, if it is present.
+pub(crate) fn attribute<'a>(attributes: &'a str, name: &str) -> Option<&'a str> {
+    ['"', '\''].iter().find_map(|&quote| {
+        let prefix = format!("{}={}", name, quote);
+        attributes.match_indices(&prefix).find_map(|(i, _)| {
+            if !attributes[..i]
+                .chars()
+                .last()
+                .map_or(false, char::is_whitespace)
+            {
+                return None;
+            }
+            let value = &attributes[i + prefix.len()..];
+            Some(&value[..value.find(quote)?])
+        })
+    })
+}
+
+/// Returns true if `attributes` contains the attribute `name` with the given value.
+fn has_attribute(attributes: &str, name: &str, value: &str) -> bool {
+    ['"', '\''].iter().any(|quote| {
+        let attribute = format!("{}={}{}{}", name, quote, value, quote);
+        attributes.match_indices(&attribute).any(|(i, _)| {
+            attributes[..i]
+                .chars()
+                .last()
+                .map_or(false, char::is_whitespace)
+        })
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    use crate::BookVariant;
+
+    /// Creates an archive with a single empty entry, which is compressed with `method`.
+    fn archive(method: u16, zip64: bool) -> Vec<u8> {
+        let name = b"META-INF/container.xml";
+        let mut zip = vec![];
+
+        zip.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        zip.extend_from_slice(&[20, 0, 0, 0]);
+        zip.extend_from_slice(&method.to_le_bytes());
+        zip.extend_from_slice(&[0; 16]);
+        zip.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        zip.extend_from_slice(&[0, 0]);
+        zip.extend_from_slice(name);
+
+        let cd_offset = zip.len();
+        zip.extend_from_slice(&CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+        zip.extend_from_slice(&[20, 0, 20, 0, 0, 0]);
+        zip.extend_from_slice(&method.to_le_bytes());
+        zip.extend_from_slice(&[0; 16]);
+        zip.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        zip.extend_from_slice(&[0; 16]);
+        zip.extend_from_slice(name);
+        let cd_len = zip.len() - cd_offset;
+
+        if zip64 {
+            let zip64_offset = zip.len();
+            zip.extend_from_slice(&ZIP64_EOCD_SIGNATURE.to_le_bytes());
+            zip.extend_from_slice(&44u64.to_le_bytes());
+            zip.extend_from_slice(&[45, 0, 45, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+            zip.extend_from_slice(&1u64.to_le_bytes());
+            zip.extend_from_slice(&1u64.to_le_bytes());
+            zip.extend_from_slice(&(cd_len as u64).to_le_bytes());
+            zip.extend_from_slice(&(cd_offset as u64).to_le_bytes());
+
+            zip.extend_from_slice(&ZIP64_EOCD_LOCATOR_SIGNATURE.to_le_bytes());
+            zip.extend_from_slice(&0u32.to_le_bytes());
+            zip.extend_from_slice(&(zip64_offset as u64).to_le_bytes());
+            zip.extend_from_slice(&1u32.to_le_bytes());
+        }
+
+        zip.extend_from_slice(&EOCD_SIGNATURE.to_le_bytes());
+        zip.extend_from_slice(&[0; 4]);
+        if zip64 {
+            zip.extend_from_slice(&[0xFF; 4]);
+            zip.extend_from_slice(&(cd_len as u32).to_le_bytes());
+            zip.extend_from_slice(&[0xFF; 4]);
+        } else {
+            zip.extend_from_slice(&[1, 0, 1, 0]);
+            zip.extend_from_slice(&(cd_len as u32).to_le_bytes());
+            zip.extend_from_slice(&(cd_offset as u32).to_le_bytes());
+        }
+        zip.extend_from_slice(&[0, 0]);
+        zip
+    }
+
+    #[test]
+    fn test_unsupported_compression() {
+        for &zip64 in &[false, true] {
+            let mut deflate = Cursor::new(archive(8, zip64));
+            assert_eq!(unsupported_compression(&mut deflate).unwrap(), None);
+
+            let mut deflate64 = Cursor::new(archive(9, zip64));
+            assert_eq!(unsupported_compression(&mut deflate64).unwrap(), Some(9));
+        }
+
+        let mut not_zip = Cursor::new(vec![0; 64]);
+        assert!(unsupported_compression(&mut not_zip).is_err());
+    }
+
+    /// Returns the CRC-32 checksum of `data`, as recorded in zip archives.
+    fn crc32(data: &[u8]) -> u32 {
+        let mut crc = !0u32;
+        for &byte in data {
+            crc ^= u32::from(byte);
+            for _ in 0..8 {
+                crc = if crc & 1 == 1 {
+                    (crc >> 1) ^ 0xEDB8_8320
+                } else {
+                    crc >> 1
+                };
+            }
+        }
+        !crc
+    }
+
+    /// Creates an EPUB archive with the given `mimetype` entry, whose package document has the
+    /// given `metadata` elements, and a single chapter.
+    pub(crate) fn epub(mimetype: &str, metadata: &str) -> Vec<u8> {
+        epub_with_method(mimetype, 0, metadata)
+    }
+
+    /// Creates an EPUB archive like `epub`, whose `mimetype` entry is compressed with `method`.
+    fn epub_with_method(mimetype: &str, method: u16, metadata: &str) -> Vec<u8> {
+        let container = br#"<?xml version="1.0"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container"><rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+</rootfiles></container>"#;
+        let opf = format!(
+            r#"<?xml version="1.0"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="uid">
+<metadata xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:opf="http://www.idpf.org/2007/opf">
+{}
+</metadata>
+<manifest>
+    <item id="chapter" href="chapter.xhtml" media-type="application/xhtml+xml"/>
+</manifest>
+<spine><itemref idref="chapter"/></spine>
+</package>"#,
+            metadata
+        );
+        let chapter =
+            br#"<html xmlns="http://www.w3.org/1999/xhtml"><body><p>Chapter</p></body></html>"#;
+        archive_with_methods(&[
+            ("mimetype", mimetype.as_bytes(), method),
+            ("META-INF/container.xml", container, 0),
+            ("OEBPS/content.opf", opf.as_bytes(), 0),
+            ("OEBPS/chapter.xhtml", chapter, 0),
+        ])
+    }
+
+    /// Creates an archive with the given entries, which are stored without compression.
+    pub(crate) fn stored_archive(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let entries: Vec<_> = entries
+            .iter()
+            .map(|&(name, contents)| (name, contents, 0))
+            .collect();
+        archive_with_methods(&entries)
+    }
+
+    /// Creates an archive with the given entries, each of which is stored without compression
+    /// if its method is 0, or deflated if its method is 8.
+    fn archive_with_methods(entries: &[(&str, &[u8], u16)]) -> Vec<u8> {
+        let mut zip = vec![];
+        let mut central_directory = vec![];
+        for &(name, contents, method) in entries {
+            let offset = zip.len() as u32;
+            let data = match method {
+                0 => contents.to_vec(),
+                _ => {
+                    let mut encoder =
+                        flate2::write::DeflateEncoder::new(vec![], flate2::Compression::default());
+                    std::io::Write::write_all(&mut encoder, contents).unwrap();
+                    encoder.finish().unwrap()
+                }
+            };
+            let sizes = [
+                (data.len() as u32).to_le_bytes(),
+                (contents.len() as u32).to_le_bytes(),
+            ]
+            .concat();
+
+            zip.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+            zip.extend_from_slice(&[20, 0, 0, 0]);
+            zip.extend_from_slice(&method.to_le_bytes());
+            zip.extend_from_slice(&[0; 4]);
+            zip.extend_from_slice(&crc32(contents).to_le_bytes());
+            zip.extend_from_slice(&sizes);
+            zip.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            zip.extend_from_slice(&[0, 0]);
+            zip.extend_from_slice(name.as_bytes());
+            zip.extend_from_slice(&data);
+
+            central_directory.extend_from_slice(&CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+            central_directory.extend_from_slice(&[20, 0, 20, 0, 0, 0]);
+            central_directory.extend_from_slice(&method.to_le_bytes());
+            central_directory.extend_from_slice(&[0; 4]);
+            central_directory.extend_from_slice(&crc32(contents).to_le_bytes());
+            central_directory.extend_from_slice(&sizes);
+            central_directory.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            central_directory.extend_from_slice(&[0; 12]);
+            central_directory.extend_from_slice(&offset.to_le_bytes());
+            central_directory.extend_from_slice(name.as_bytes());
+        }
+
+        let cd_offset = zip.len() as u32;
+        let count = (entries.len() as u16).to_le_bytes();
+        zip.extend_from_slice(&central_directory);
+        zip.extend_from_slice(&EOCD_SIGNATURE.to_le_bytes());
+        zip.extend_from_slice(&[0; 4]);
+        zip.extend_from_slice(&count);
+        zip.extend_from_slice(&count);
+        zip.extend_from_slice(&(central_directory.len() as u32).to_le_bytes());
+        zip.extend_from_slice(&cd_offset.to_le_bytes());
+        zip.extend_from_slice(&[0, 0]);
+        zip
+    }
+
+    #[test]
+    fn test_validate_mimetype() {
+        let container: (&str, &[u8]) = ("META-INF/container.xml", b"<container/>");
+        let mut epub = Cursor::new(stored_archive(&[
+            ("mimetype", b"application/epub+zip"),
+            container,
+        ]));
+        assert_eq!(validate_mimetype(&mut epub), Ok(()));
+
+        let mut zip = Cursor::new(stored_archive(&[
+            container,
+            ("mimetype", b"application/zip"),
+        ]));
+        assert_eq!(
+            validate_mimetype(&mut zip),
+            Err(BookError::BadMimetype("application/zip".to_string()))
+        );
+
+        let mut missing = Cursor::new(stored_archive(&[container]));
+        assert_eq!(validate_mimetype(&mut missing), Err(BookError::NoMimetype));
+        assert_eq!(unsupported_compression(&mut missing).unwrap(), None);
+    }
+
+    #[test]
+    fn test_from_path_checks_mimetype() {
+        let dir = tempfile::tempdir().unwrap();
+        let metadata = r#"<dc:title>Dune</dc:title>"#;
+
+        let path = dir.path().join("valid.epub");
+        std::fs::write(&path, epub("application/epub+zip", metadata)).unwrap();
+        let book = BookVariant::from_path(&path).unwrap();
+        assert_eq!(book.local_title.as_deref(), Some("Dune"));
+
+        // The metadata of archives which claim to be something else is not trusted.
+        let path = dir.path().join("zip.epub");
+        std::fs::write(&path, epub("application/zip", metadata)).unwrap();
+        let book = BookVariant::from_path(&path).unwrap();
+        assert_eq!(book.local_title.as_deref(), Some("zip.epub"));
+
+        // Deflated mimetype entries are only rejected by the strict check.
+        let deflated = epub_with_method("application/epub+zip", 8, metadata);
+        assert!(matches!(
+            validate_mimetype(&mut Cursor::new(deflated.clone())),
+            Err(BookError::BadMimetype(_))
+        ));
+        let path = dir.path().join("deflated.epub");
+        std::fs::write(&path, deflated).unwrap();
+        let book = BookVariant::from_path(&path).unwrap();
+        assert_eq!(book.local_title.as_deref(), Some("Dune"));
+    }
+
+    /// Writes an EPUB with the given `metadata` elements to a temporary file, and reads it.
+    fn read_epub(metadata: &str) -> BookVariant {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("book.epub");
+        std::fs::write(&path, epub("application/epub+zip", metadata)).unwrap();
+        BookVariant::from_path(&path).unwrap()
+    }
+
+    #[test]
+    fn test_from_path_reads_series() {
+        let book = read_epub(
+            r#"<dc:title>Dune</dc:title>
+    <meta name="calibre:series" content="Dune Chronicles"/>
+    <meta name="calibre:series_index" content="1.0"/>"#,
+        );
+        assert_eq!(
+            book.series,
+            Some(Series {
+                name: "Dune Chronicles".to_string(),
+                index: Some(1.),
+            })
+        );
+
+        assert_eq!(read_epub("<dc:title>Dune</dc:title>").series, None);
+    }
+
+    #[test]
+    fn test_from_path_reads_creators() {
+        let book = read_epub(
+            r##"<dc:title>The Tin Drum</dc:title>
+    <dc:creator id="author">Grass, Günter</dc:creator>
+    <meta refines="#author" property="role">aut</meta>
+    <dc:creator id="translator">Ralph Manheim</dc:creator>
+    <meta refines="#translator" property="role">trl</meta>
+    <dc:creator opf:role="aut">Anonymous</dc:creator>"##,
+        );
+        assert_eq!(
+            book.additional_authors,
+            Some(vec!["Günter Grass".to_string(), "Anonymous".to_string()])
+        );
+        assert_eq!(book.translators, Some(vec!["Ralph Manheim".to_string()]));
+    }
+
+    #[test]
+    fn test_from_path_reads_titles() {
+        let book = read_epub(
+            r##"<dc:title id="sub">The Dune Chronicles</dc:title>
+    <meta refines="#sub" property="title-type">subtitle</meta>
+    <dc:title id="main">Dune</dc:title>
+    <meta refines="#main" property="title-type">main</meta>"##,
+        );
+        assert_eq!(book.local_title.as_deref(), Some("Dune"));
+        assert_eq!(
+            book.named_tags.get("subtitle").map(String::as_str),
+            Some("The Dune Chronicles")
+        );
+    }
+
+    #[test]
+    fn test_from_path_reads_publication() {
+        let book = read_epub(
+            r#"<dc:title>Dune</dc:title>
+    <dc:publisher>Chilton Books</dc:publisher>
+    <dc:date>1965-08-01</dc:date>
+    <meta property="dcterms:modified">2021-03-04T05:06:07Z</meta>"#,
+        );
+        let tag = |name: &str| book.named_tags.get(name).map(String::as_str);
+        assert_eq!(tag("publisher"), Some("Chilton Books"));
+        assert_eq!(tag("published"), Some("1965-08-01"));
+        assert_eq!(tag("modified"), Some("2021-03-04T05:06:07Z"));
+
+        // Dates which can not be parsed are kept as they are.
+        let book = read_epub(
+            r#"<dc:title>Dune</dc:title>
+    <dc:date>Summer 1965</dc:date>"#,
+        );
+        assert_eq!(book.named_tags.get("published"), None);
+        assert_eq!(
+            book.named_tags.get("dc:date").map(String::as_str),
+            Some("Summer 1965")
+        );
+    }
+
+    #[test]
+    fn test_from_path_reads_identifiers() {
+        let book = read_epub(
+            r#"<dc:title>Dune</dc:title>
+    <dc:identifier id="uid">urn:uuid:a3f2b1c4-0000-4000-8000-000000000000</dc:identifier>
+    <dc:identifier opf:scheme="ISBN">9780441013593</dc:identifier>
+    <dc:identifier opf:scheme="ASIN">B00B7NPRY8</dc:identifier>"#,
+        );
+        assert_eq!(
+            book.identifier,
+            Some(Identifier::new("isbn", "9780441013593".to_string()))
+        );
+        let tag = |name: &str| book.named_tags.get(name).map(String::as_str);
+        assert_eq!(tag("amazon"), Some("B00B7NPRY8"));
+        assert_eq!(tag("uuid"), Some("a3f2b1c4-0000-4000-8000-000000000000"));
+        assert!(tag("isbn").is_some());
+    }
+
+    #[test]
+    fn test_read_cover() {
+        let container = br#"<container><rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+</rootfiles></container>"#;
+        let opf = br#"<package version="2.0"><metadata>
+    <meta name="cover" content="cover-image"/>
+</metadata><manifest>
+    <item id="cover-image" href="images/cover.jpg" media-type="image/jpeg"/>
+</manifest></package>"#;
+        let mut epub = Cursor::new(stored_archive(&[
+            ("META-INF/container.xml", container),
+            ("OEBPS/content.opf", opf),
+            ("OEBPS/images/cover.jpg", b"\xff\xd8\xff"),
+        ]));
+        assert_eq!(
+            read_cover(&mut epub).unwrap(),
+            Some(b"\xff\xd8\xff".to_vec())
+        );
+
+        let mut missing = Cursor::new(stored_archive(&[
+            ("META-INF/container.xml", container),
+            ("OEBPS/content.opf", opf),
+        ]));
+        assert_eq!(read_cover(&mut missing).unwrap(), None);
+
+        let mut no_cover = Cursor::new(stored_archive(&[
+            ("META-INF/container.xml", container),
+            ("OEBPS/content.opf", b"<package><manifest/></package>"),
+        ]));
+        assert_eq!(read_cover(&mut no_cover).unwrap(), None);
+    }
+
+    #[test]
+    fn test_container_root_file() {
+        let container = r#"<?xml version="1.0"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>"#;
+        assert_eq!(
+            container_root_file(container),
+            Ok("OEBPS/content.opf".to_string())
+        );
+
+        let missing_path = r#"<container><rootfiles><rootfile media-type="application/oebps-package+xml"/></rootfiles></container>"#;
+        assert_eq!(
+            container_root_file(missing_path),
+            Err(BookError::NoPackage(ParseContext {
+                position: 22,
+                tag: Some("rootfile".to_string()),
+            }))
+        );
+
+        let empty = "<container><rootfiles></rootfiles></container>";
+        assert_eq!(
+            container_root_file(empty),
+            Err(BookError::NoPackage(ParseContext {
+                position: empty.len(),
+                tag: None,
+            }))
+        );
+    }
+
+    #[test]
+    fn test_opf_modified() {
+        let opf = r##"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="uid">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="uid">urn:uuid:12345</dc:identifier>
+    <dc:title>Dune</dc:title>
+    <dc:date>1965-08-01</dc:date>
+    <meta refines="#uid" property="identifier-type">uuid</meta>
+    <meta name="cover" content="cover-image"/>
+    <meta property="dcterms:modified"> 2021-03-04T05:06:07Z </meta>
+  </metadata>
+</package>"##;
+        assert_eq!(opf_modified(opf).as_deref(), Some("2021-03-04T05:06:07Z"));
+
+        let epub2 =
+            r#"<package version="2.0"><metadata><dc:title>Dune</dc:title></metadata></package>"#;
+        assert_eq!(opf_modified(epub2), None);
+    }
+
+    #[test]
+    fn test_opf_titles() {
+        let epub3 = r##"<package version="3.0">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:title id="t1">The Lord of the Rings</dc:title>
+    <dc:title id="t2">The Fellowship of the Ring</dc:title>
+    <dc:title id="t3">Being the First Part</dc:title>
+    <meta refines="#t1" property="title-type">collection</meta>
+    <meta refines="#t1" property="display-seq">1</meta>
+    <meta refines="#t2" property="title-type">main</meta>
+    <meta refines="#t3" property="title-type">subtitle</meta>
+    <meta refines="#t3" property="display-seq">2</meta>
+  </metadata>
+</package>"##;
+        assert_eq!(
+            opf_titles(epub3),
+            Some(OpfTitles {
+                main: "The Fellowship of the Ring".to_string(),
+                subtitles: vec![
+                    (
+                        "collection".to_string(),
+                        "The Lord of the Rings".to_string()
+                    ),
+                    ("subtitle".to_string(), "Being the First Part".to_string()),
+                ],
+            })
+        );
+
+        let epub2 = r#"<package version="2.0"><metadata>
+    <dc:title>Pride &amp; Prejudice</dc:title>
+    <dc:title>Another Title</dc:title>
+</metadata></package>"#;
+        assert_eq!(
+            opf_titles(epub2),
+            Some(OpfTitles {
+                main: "Pride & Prejudice".to_string(),
+                subtitles: vec![],
+            })
+        );
+
+        assert_eq!(opf_titles("<package><metadata/></package>"), None);
+    }
+
+    #[test]
+    fn test_opf_creators() {
+        let epub2 = r#"<package version="2.0"><metadata>
+    <dc:creator opf:role="edt" opf:file-as="Tolkien, Christopher">Christopher Tolkien</dc:creator>
+    <dc:creator opf:role="aut">J. R. R. Tolkien</dc:creator>
+    <dc:creator opf:role="trl">Francis Ledoux</dc:creator>
+</metadata></package>"#;
+        let creators = opf_creators(epub2);
+        assert_eq!(creators.author.as_deref(), Some("J. R. R. Tolkien"));
+        assert_eq!(
+            creators.authors,
+            vec![
+                ("Christopher Tolkien".to_string(), Some("edt".to_string())),
+                ("J. R. R. Tolkien".to_string(), Some("aut".to_string())),
+                ("Francis Ledoux".to_string(), Some("trl".to_string())),
+            ]
+        );
+        assert_eq!(creators.with_role("trl"), vec!["Francis Ledoux"]);
+
+        let epub3 = r##"<package version="3.0"><metadata>
+    <dc:creator id="c1">Terry Pratchett</dc:creator>
+    <dc:creator id="c2">Neil Gaiman</dc:creator>
+    <dc:creator id="c3">Illustrator</dc:creator>
+    <meta refines="#c1" property="role" scheme="marc:relators">aut</meta>
+    <meta refines="#c3" property="role" scheme="marc:relators">ill</meta>
+</metadata></package>"##;
+        let creators = opf_creators(epub3);
+        assert_eq!(creators.author.as_deref(), Some("Terry Pratchett"));
+        assert_eq!(
+            creators.with_role("aut"),
+            vec!["Terry Pratchett", "Neil Gaiman"]
+        );
+        assert_eq!(creators.authors[2].1.as_deref(), Some("ill"));
+
+        assert_eq!(
+            opf_creators("<package><metadata/></package>"),
+            OpfCreators::default()
+        );
+    }
+
+    #[test]
+    fn test_opf_publication() {
+        let epub2 = r#"<package version="2.0"><metadata>
+    <dc:publisher>Chilton &amp; Co.</dc:publisher>
+    <dc:date opf:event="modification">2020-01-01</dc:date>
+    <dc:date opf:event="publication">1965-8</dc:date>
+</metadata></package>"#;
+        assert_eq!(
+            opf_publication(epub2),
+            OpfPublication {
+                publisher: Some("Chilton & Co.".to_string()),
+                date: Some("1965-08".to_string()),
+                extended_values: vec![],
+            }
+        );
+
+        for (date, expected) in [
+            ("2012", Some("2012")),
+            ("2012-03", Some("2012-03")),
+            ("2012/3/4", Some("2012-03-04")),
+            ("2012-03-04T05:06:07Z", Some("2012-03-04")),
+            ("2012-13", None),
+            ("03/04/2012", None),
+            ("Spring 2012", None),
+        ] {
+            assert_eq!(normalize_date(date).as_deref(), expected, "{}", date);
+        }
+
+        let epub3 = r#"<package version="3.0"><metadata>
+    <dc:date>circa 1850</dc:date>
+</metadata></package>"#;
+        assert_eq!(
+            opf_publication(epub3),
+            OpfPublication {
+                publisher: None,
+                date: None,
+                extended_values: vec![("dc:date".to_string(), "circa 1850".to_string())],
+            }
+        );
+    }
+
+    #[test]
+    fn test_opf_identifiers() {
+        let epub2 = r#"<package version="2.0"><metadata>
+    <dc:identifier id="uid" opf:scheme="UUID">urn:uuid:0a1b2c3d</dc:identifier>
+    <dc:identifier opf:scheme="ISBN">9780441013593</dc:identifier>
+    <dc:identifier opf:scheme="MOBI-ASIN">B00B7NPRY8</dc:identifier>
+    <dc:identifier opf:scheme="GOOGLE">ydQiDQAAQBAJ</dc:identifier>
+    <dc:identifier opf:scheme="ISBN">not an isbn</dc:identifier>
+</metadata></package>"#;
+        let unknown =
+            |scheme: &str, value: &str| Identifier::Unknown(scheme.to_string(), value.to_string());
+        assert_eq!(
+            opf_identifiers(epub2),
+            vec![
+                unknown("uuid", "0a1b2c3d"),
+                Identifier::ISBN(Isbn::from_str("9780441013593").unwrap()),
+                unknown("amazon", "B00B7NPRY8"),
+                unknown("google", "ydQiDQAAQBAJ"),
+                unknown("isbn", "not an isbn"),
+            ]
+        );
+
+        let epub3 = r##"<package version="3.0"><metadata>
+    <dc:identifier id="isbn">9780441013593</dc:identifier>
+    <dc:identifier id="gr">12345</dc:identifier>
+    <dc:identifier>urn:isbn:9780441013593</dc:identifier>
+    <meta refines="#isbn" property="identifier-type" scheme="onix:codelist5">15</meta>
+    <meta refines="#gr" property="identifier-type">GOODREADS</meta>
+</metadata></package>"##;
+        let isbn = Identifier::ISBN(Isbn::from_str("9780441013593").unwrap());
+        assert_eq!(
+            opf_identifiers(epub3),
+            vec![isbn.clone(), unknown("goodreads", "12345"), isbn]
+        );
+    }
+
+    #[test]
+    fn test_opf_series() {
+        let epub2 = r#"<package version="2.0"><metadata>
+    <meta name="calibre:series" content="Dune Chronicles &amp; More"/>
+    <meta name="calibre:series_index" content="2.5"/>
+</metadata></package>"#;
+        assert_eq!(
+            opf_series(epub2),
+            Some(Series {
+                name: "Dune Chronicles & More".to_string(),
+                index: Some(2.5),
+            })
+        );
+
+        let epub3 = r##"<package version="3.0"><metadata>
+    <meta property="belongs-to-collection" id="set">Science Fiction Masterworks</meta>
+    <meta property="belongs-to-collection" id="c01">Dune Chronicles</meta>
+    <meta refines="#c01" property="collection-type">series</meta>
+    <meta refines="#c01" property="group-position">3</meta>
+    <meta refines="#set" property="group-position">12</meta>
+</metadata></package>"##;
+        assert_eq!(
+            opf_series(epub3),
+            Some(Series {
+                name: "Dune Chronicles".to_string(),
+                index: Some(3.),
+            })
+        );
+
+        // A missing or malformed index leaves the series without an index.
+        let no_index = r#"<package version="2.0"><metadata>
+    <meta name="calibre:series" content="Dune Chronicles"/>
+    <meta name="calibre:series_index" content="first"/>
+</metadata></package>"#;
+        assert_eq!(opf_series(no_index).unwrap().index, None);
+        assert_eq!(opf_series("<package><metadata/></package>"), None);
+    }
+
+    #[test]
+    fn test_opf_cover() {
+        let epub2 = r#"<package version="2.0">
+  <metadata>
+    <meta name="cover" content="cover-img"/>
+  </metadata>
+  <manifest>
+    <item id="text" href="text/chapter1.xhtml" media-type="application/xhtml+xml"/>
+    <item id="cover-img" href="images/cover.jpg" media-type="image/jpeg"/>
+  </manifest>
+</package>"#;
+        assert_eq!(
+            opf_cover(epub2, "OEBPS/content.opf"),
+            Some(OpfCover {
+                path: "OEBPS/images/cover.jpg".to_string(),
+                media_type: "image/jpeg".to_string(),
+            })
+        );
+        assert_eq!(
+            opf_cover(epub2, "content.opf").map(|cover| cover.path),
+            Some("images/cover.jpg".to_string())
+        );
+
+        let epub3 = r#"<package version="3.0">
+  <manifest>
+    <item id="nav" href="nav.xhtml" properties="nav" media-type="application/xhtml+xml"/>
+    <item id="c" href="../cover.png" properties="svg cover-image" media-type="image/png"/>
+  </manifest>
+</package>"#;
+        assert_eq!(
+            opf_cover(epub3, "OPS/package/book.opf"),
+            Some(OpfCover {
+                path: "OPS/cover.png".to_string(),
+                media_type: "image/png".to_string(),
+            })
+        );
+
+        let no_cover = r#"<package version="3.0"><manifest>
+    <item id="nav" href="nav.xhtml" properties="nav" media-type="application/xhtml+xml"/>
+</manifest></package>"#;
+        assert_eq!(opf_cover(no_cover, "OPS/book.opf"), None);
+    }
+
+    #[test]
+    fn test_opf_nav() {
+        let epub3 = r#"<package version="3.0">
+  <manifest>
+    <item id="c" href="cover.png" properties="cover-image" media-type="image/png"/>
+    <item id="toc" href="text/toc.xhtml" properties="scripted nav" media-type="application/xhtml+xml"/>
+  </manifest>
+</package>"#;
+        assert_eq!(
+            opf_nav(epub3, "OEBPS/content.opf"),
+            Some("OEBPS/text/toc.xhtml".to_string())
+        );
+
+        let epub2 = r#"<package version="2.0"><manifest>
+    <item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>
+</manifest></package>"#;
+        assert_eq!(opf_nav(epub2, "OEBPS/content.opf"), None);
+    }
+
+    #[test]
+    fn test_nav_page_count() {
+        let nav = r#"<html xmlns:epub="http://www.idpf.org/2007/ops"><body>
+  <nav epub:type="toc" id="toc">
+    <ol>
+      <li><a href="chapter1.xhtml">Chapter 1</a></li>
+      <li><a href="chapter2.xhtml">Chapter 2</a></li>
+    </ol>
+  </nav>
+  <nav epub:type="page-list" hidden="">
+    <ol>
+      <li><a href="chapter1.xhtml#page1">1</a></li>
+      <li><a href="chapter1.xhtml#page2">2</a></li>
+      <li><a href="chapter2.xhtml#page3">3</a></li>
+    </ol>
+  </nav>
+  <nav epub:type="landmarks"><ol><li><a href="cover.xhtml">Cover</a></li></ol></nav>
+</body></html>"#;
+        assert_eq!(nav_page_count(nav), Some(3));
+
+        let no_page_list = r#"<nav epub:type="toc"><ol>
+  <li><a href="chapter1.xhtml">Chapter 1</a></li>
+</ol></nav>"#;
+        assert_eq!(nav_page_count(no_page_list), None);
+    }
+
+    #[test]
+    fn test_from_path_reads_page_count() {
+        let container = br#"<container><rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+</rootfiles></container>"#;
+        let opf = br#"<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+<metadata xmlns:dc="http://purl.org/dc/elements/1.1/"><dc:title>Dune</dc:title></metadata>
+<manifest>
+    <item id="nav" href="nav.xhtml" properties="nav" media-type="application/xhtml+xml"/>
+</manifest>
+<spine><itemref idref="nav"/></spine>
+</package>"#;
+        let nav = br#"<html xmlns:epub="http://www.idpf.org/2007/ops"><body>
+  <nav epub:type="page-list"><ol>
+    <li><a href="nav.xhtml#page1">1</a></li>
+    <li><a href="nav.xhtml#page2">2</a></li>
+  </ol></nav>
+</body></html>"#;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("book.epub");
+        std::fs::write(
+            &path,
+            stored_archive(&[
+                ("mimetype", b"application/epub+zip"),
+                ("META-INF/container.xml", container),
+                ("OEBPS/content.opf", opf),
+                ("OEBPS/nav.xhtml", nav),
+            ]),
+        )
+        .unwrap();
+
+        let book = BookVariant::from_path(&path).unwrap();
+        assert_eq!(book.named_tags.get("pages").map(String::as_str), Some("2"));
+        assert_eq!(
+            read_epub("<dc:title>Dune</dc:title>")
+                .named_tags
+                .get("pages"),
+            None
+        );
+    }
+}
@@ -1,22 +1,114 @@
 use std::ffi::OsString;
+use std::fmt;
 
 use quick_epub::Error as EpubError;
 
+/// Where parsing a document failed, for triaging malformed files.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseContext {
+    /// The byte offset into the document at which parsing failed.
+    pub position: usize,
+    /// The name of the offending element, if parsing failed inside an element.
+    pub tag: Option<String>,
+}
+
+impl fmt::Display for ParseContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.tag {
+            Some(tag) => write!(f, "<{}> at byte {}", tag, self.position),
+            None => write!(f, "byte {}", self.position),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 /// Enumerates all potential errors that can occur when using a Book.
 pub enum BookError {
+    /// The path does not name a file with an extension.
     FileError,
+    /// Reading the file failed, with the reason given.
+    Io(String),
+    /// The EPUB could not be parsed, with the reason given.
+    Epub(String),
+    /// The MOBI file could not be parsed, with the reason given.
+    Mobi(String),
+    /// The PDF file could not be parsed, with the reason given.
+    Pdf(String),
+    /// The FB2 file could not be parsed, with the reason given.
+    Fb2(String),
     UnsupportedExtension(OsString), //    MetadataError,
+    /// The archive contains an entry compressed with a method other than stored or deflate
+    /// (eg. deflate64), and should be recompressed.
+    UnsupportedCompression(u16),
+    /// The archive has no `mimetype` entry.
+    NoMimetype,
+    /// The `mimetype` entry of the archive is not `application/epub+zip`.
+    BadMimetype(String),
+    /// The container document does not name a package document.
+    NoPackage(ParseContext),
+}
+
+impl std::error::Error for BookError {}
+
+impl fmt::Display for BookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BookError::FileError => f.write_str("path does not name a file with an extension"),
+            BookError::Io(reason) => write!(f, "could not read file: {}", reason),
+            BookError::Epub(reason) => write!(f, "could not parse EPUB: {}", reason),
+            BookError::Mobi(reason) => write!(f, "could not parse MOBI: {}", reason),
+            BookError::Pdf(reason) => write!(f, "could not parse PDF: {}", reason),
+            BookError::Fb2(reason) => write!(f, "could not parse FB2: {}", reason),
+            BookError::UnsupportedExtension(ext) => {
+                write!(f, "unsupported extension: {}", ext.to_string_lossy())
+            }
+            BookError::UnsupportedCompression(method) => write!(
+                f,
+                "archive uses unsupported compression method {} - recompress the file",
+                method
+            ),
+            BookError::NoMimetype => f.write_str("archive has no mimetype entry"),
+            BookError::BadMimetype(mimetype) => {
+                write!(
+                    f,
+                    "expected mimetype application/epub+zip, found {}",
+                    mimetype
+                )
+            }
+            BookError::NoPackage(context) => {
+                write!(f, "no package document found ({})", context)
+            }
+        }
+    }
 }
 
 impl From<std::io::Error> for BookError {
-    fn from(_: std::io::Error) -> Self {
-        BookError::FileError
+    fn from(e: std::io::Error) -> Self {
+        BookError::Io(e.to_string())
     }
 }
 
 impl From<EpubError> for BookError {
-    fn from(_: EpubError) -> Self {
-        BookError::FileError
+    fn from(e: EpubError) -> Self {
+        BookError::Epub(format!("{:?}", e))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_io_error_keeps_reason() {
+        let e = std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "truncated central directory",
+        );
+        let e = BookError::from(e);
+        assert_eq!(e, BookError::Io("truncated central directory".to_string()));
+        assert_eq!(
+            e.to_string(),
+            "could not read file: truncated central directory"
+        );
     }
 }
@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+
+use encoding_rs::{Encoding, UTF_8};
+
+use crate::author;
+use crate::epub::{attribute, elements, elements_with_text, read_entry_where, unescape};
+use crate::series::Series;
+use crate::variant::{Identifier, MetadataFiller};
+use crate::BookError;
+
+/// The signature at the start of a zip archive, which compressed FB2 files are stored in.
+const ZIP_SIGNATURE: &[u8] = b"PK\x03\x04";
+
+/// The metadata of a FictionBook (FB2) file, read from the `<title-info>` element of its
+/// description.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Fb2Metadata {
+    pub title: Option<String>,
+    pub authors: Vec<String>,
+    /// The genre codes of the book, such as "sf_space".
+    pub genres: Vec<String>,
+    pub series: Option<Series>,
+    pub language: Option<String>,
+    /// The annotation of the book, as plain text with one paragraph per line.
+    pub description: Option<String>,
+}
+
+/// Decodes `bytes` with the encoding named in the XML declaration, which is commonly
+/// windows-1251. Documents without a declaration, or with an unknown encoding, are read as UTF-8.
+fn decode(bytes: &[u8]) -> String {
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(256)]);
+    let encoding = head
+        .strip_prefix('\u{feff}')
+        .unwrap_or(&head)
+        .strip_prefix("<?xml")
+        .and_then(|declaration| attribute(&declaration[..declaration.find("?>")?], "encoding"))
+        .and_then(|label| Encoding::for_label(label.trim().as_bytes()))
+        .unwrap_or(UTF_8);
+    encoding.decode(bytes).0.into_owned()
+}
+
+/// Returns the unescaped text of the first `<name>` element in `xml`, if it is not empty.
+fn text(xml: &str, name: &str) -> Option<String> {
+    elements_with_text(xml, name)
+        .into_iter()
+        .map(|(_, text)| unescape(text.trim()))
+        .find(|text| !text.is_empty())
+}
+
+/// Returns the text of `xml` with its tags removed, placing each paragraph on its own line.
+fn plain_text(xml: &str) -> String {
+    let mut text = String::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('>')
+            .map_or(rest.len(), |end| start + end + 1);
+        if rest[start..end].starts_with("</p") {
+            text.push('\n');
+        }
+        rest = &rest[end..];
+    }
+    text.push_str(rest);
+
+    unescape(&text)
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+impl Fb2Metadata {
+    /// Reads the metadata of the FB2 file in `reader`. Files compressed in a zip archive, such
+    /// as `.fbz` files, are read from the first entry with the `.fb2` extension. Authors are
+    /// named by joining their first, middle and last names, or by their nickname if they
+    /// have no other name.
+    ///
+    /// # Arguments
+    /// * ` reader ` - A reader over an FB2 file, or a zip archive containing one.
+    ///
+    /// # Errors
+    /// Will return an error if reading fails, or if the file has no `<title-info>` element.
+    pub fn from_read<R: Read + Seek>(mut reader: R) -> Result<Self, BookError> {
+        reader.seek(SeekFrom::Start(0))?;
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes)?;
+        if bytes.starts_with(ZIP_SIGNATURE) {
+            bytes = read_entry_where(&mut Cursor::new(bytes), |name| {
+                name.to_ascii_lowercase().ends_with(b".fb2")
+            })?
+            .ok_or_else(|| BookError::Fb2("archive does not contain an FB2 file".to_string()))?;
+        }
+
+        let fb2 = decode(&bytes);
+        let title_info = elements_with_text(&fb2, "title-info")
+            .into_iter()
+            .next()
+            .map(|(_, title_info)| title_info)
+            .ok_or_else(|| BookError::Fb2("missing <title-info> element".to_string()))?;
+
+        let authors = elements_with_text(title_info, "author")
+            .into_iter()
+            .filter_map(|(_, author)| {
+                let names: Vec<_> = ["first-name", "middle-name", "last-name"]
+                    .iter()
+                    .filter_map(|name| text(author, name))
+                    .collect();
+                if names.is_empty() {
+                    text(author, "nickname")
+                } else {
+                    Some(names.join(" "))
+                }
+            })
+            .map(|author| author::normalize(&author))
+            .collect();
+
+        let series = elements(title_info, "sequence")
+            .into_iter()
+            .find_map(|attributes| {
+                let name = unescape(attribute(attributes, "name")?.trim());
+                if name.is_empty() {
+                    return None;
+                }
+                Some(Series {
+                    name,
+                    index: attribute(attributes, "number")
+                        .and_then(|number| number.trim().parse().ok()),
+                })
+            });
+
+        Ok(Fb2Metadata {
+            title: text(title_info, "book-title"),
+            authors,
+            genres: elements_with_text(title_info, "genre")
+                .into_iter()
+                .map(|(_, genre)| unescape(genre.trim()))
+                .filter(|genre| !genre.is_empty())
+                .collect(),
+            series,
+            language: text(title_info, "lang"),
+            description: elements_with_text(title_info, "annotation")
+                .into_iter()
+                .next()
+                .map(|(_, annotation)| plain_text(annotation))
+                .filter(|annotation| !annotation.is_empty()),
+        })
+    }
+}
+
+impl MetadataFiller for Fb2Metadata {
+    fn take_title(&mut self, title: &mut Option<String>) {
+        *title = std::mem::take(&mut self.title);
+    }
+
+    fn take_description(&mut self, description: &mut Option<String>) {
+        *description = std::mem::take(&mut self.description);
+    }
+
+    fn take_language(&mut self, language: &mut Option<String>) {
+        *language = std::mem::take(&mut self.language);
+    }
+
+    fn take_identifier(&mut self, _identifier: &mut Option<Identifier>) {}
+
+    fn take_authors(&mut self, authors: &mut Option<Vec<String>>) {
+        if !self.authors.is_empty() {
+            *authors = Some(std::mem::take(&mut self.authors));
+        }
+    }
+
+    fn take_series(&mut self, series: &mut Option<Series>) {
+        *series = self.series.take();
+    }
+
+    fn take_named_tags(&mut self, tags: &mut HashMap<String, String>) {
+        if !self.genres.is_empty() {
+            tags.insert("genre".to_string(), self.genres.join(", "));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::epub::test::stored_archive;
+
+    const DUNE: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<FictionBook xmlns="http://www.gribuser.ru/xml/fictionbook/2.0">
+<description>
+<title-info>
+    <genre>sf_space</genre>
+    <genre>sf_epic</genre>
+    <author><first-name>Frank</first-name><middle-name>Patrick</middle-name><last-name>Herbert</last-name></author>
+    <author><nickname>Anonymous</nickname></author>
+    <book-title>Dune &amp; Beyond</book-title>
+    <annotation><p>A desert   planet.</p><p>Spice &lt;must&gt; flow.</p></annotation>
+    <lang>en</lang>
+    <sequence name="Dune Chronicles" number="1"/>
+</title-info>
+<document-info><author><nickname>converter</nickname></author></document-info>
+</description>
+<body><section><p>Text</p></section></body>
+</FictionBook>"#;
+
+    #[test]
+    fn test_fb2_metadata() {
+        let mut metadata = Fb2Metadata::from_read(Cursor::new(DUNE)).unwrap();
+        assert_eq!(
+            metadata,
+            Fb2Metadata {
+                title: Some("Dune & Beyond".to_string()),
+                authors: vec!["Frank Patrick Herbert".to_string(), "Anonymous".to_string()],
+                genres: vec!["sf_space".to_string(), "sf_epic".to_string()],
+                series: Some(Series {
+                    name: "Dune Chronicles".to_string(),
+                    index: Some(1.),
+                }),
+                language: Some("en".to_string()),
+                description: Some("A desert planet.\nSpice <must> flow.".to_string()),
+            }
+        );
+
+        let mut tags = HashMap::new();
+        metadata.take_named_tags(&mut tags);
+        assert_eq!(
+            tags.get("genre").map(String::as_str),
+            Some("sf_space, sf_epic")
+        );
+
+        let zipped = stored_archive(&[("dune.fb2", DUNE.as_bytes())]);
+        let zipped = Fb2Metadata::from_read(Cursor::new(zipped)).unwrap();
+        assert_eq!(zipped.title, Some("Dune & Beyond".to_string()));
+
+        let empty = stored_archive(&[("mimetype", b"application/zip")]);
+        assert!(Fb2Metadata::from_read(Cursor::new(empty)).is_err());
+        assert!(Fb2Metadata::from_read(Cursor::new("<FictionBook/>")).is_err());
+    }
+
+    #[test]
+    fn test_fb2_encoding() {
+        let (fb2, _, _) = encoding_rs::WINDOWS_1251.encode(
+            r#"<?xml version="1.0" encoding="windows-1251"?>
+<FictionBook><description><title-info>
+    <author><first-name>Лев</first-name><last-name>Толстой</last-name></author>
+    <book-title>Война и мир</book-title>
+    <sequence name="Собрание сочинений" number="4.5"/>
+</title-info></description></FictionBook>"#,
+        );
+        let metadata = Fb2Metadata::from_read(Cursor::new(fb2.into_owned())).unwrap();
+        assert_eq!(metadata.title, Some("Война и мир".to_string()));
+        assert_eq!(metadata.authors, vec!["Лев Толстой".to_string()]);
+        assert_eq!(
+            metadata.series,
+            Some(Series {
+                name: "Собрание сочинений".to_string(),
+                index: Some(4.5),
+            })
+        );
+        assert_eq!(metadata.description, None);
+    }
+}
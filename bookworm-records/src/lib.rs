@@ -4,14 +4,24 @@
 #![deny(unused_mut)]
 
 pub use book::Book;
-pub use error::BookError;
+pub use epub::{
+    container_root_file, nav_page_count, opf_cover, opf_creators, opf_identifiers, opf_modified,
+    opf_nav, opf_publication, opf_series, opf_titles, read_cover, validate_mimetype, OpfCover,
+    OpfCreators, OpfPublication, OpfTitles,
+};
+pub use error::{BookError, ParseContext};
 pub use variant::BookVariant;
 
+pub mod author;
 pub mod book;
 mod epub;
 pub mod error;
+mod fb2;
 mod mobi;
+mod pdf;
 pub mod series;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
 pub mod variant;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -36,6 +46,48 @@ impl ColumnOrder {
     }
 }
 
+/// How text is compared when sorting books.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Collation {
+    /// ASCII letters compare equal regardless of case, matching SQLite's `NOCASE` collation.
+    CaseInsensitive,
+    /// Text is compared byte by byte, matching SQLite's `BINARY` collation.
+    CaseSensitive,
+}
+
+impl Default for Collation {
+    fn default() -> Self {
+        Collation::CaseInsensitive
+    }
+}
+
+impl Collation {
+    /// Compares `a` and `b` in the same way that SQLite does under this collation.
+    pub fn cmp_str(&self, a: &str, b: &str) -> std::cmp::Ordering {
+        match self {
+            Collation::CaseInsensitive => a
+                .bytes()
+                .map(|c| c.to_ascii_lowercase())
+                .cmp(b.bytes().map(|c| c.to_ascii_lowercase())),
+            Collation::CaseSensitive => a.cmp(b),
+        }
+    }
+
+    /// Compares `a` and `b` under this collation, placing `None` before any value.
+    pub fn cmp_opt<A: AsRef<str>, B: AsRef<str>>(
+        &self,
+        a: Option<A>,
+        b: Option<B>,
+    ) -> std::cmp::Ordering {
+        match (a, b) {
+            (None, None) => std::cmp::Ordering::Equal,
+            (None, Some(_)) => std::cmp::Ordering::Less,
+            (Some(_), None) => std::cmp::Ordering::Greater,
+            (Some(a), Some(b)) => self.cmp_str(a.as_ref(), b.as_ref()),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Edit {
     Delete,
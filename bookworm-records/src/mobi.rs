@@ -1,34 +1,294 @@
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::io::{Read, Seek, SeekFrom};
 use std::str::FromStr;
 
 use isbn2::Isbn;
-use mobi::MobiMetadata;
 
-use crate::variant::{unravel_author, Identifier, MetadataFiller};
+use crate::author;
+use crate::variant::{Identifier, MetadataFiller};
+use crate::BookError;
+
+/// The length of the Palm database header, which is followed by the record list.
+const PDB_HEADER_LEN: usize = 78;
+/// The length of the PalmDOC header at the start of record 0, which is followed by the MOBI
+/// header.
+const PALMDOC_HEADER_LEN: usize = 16;
+/// Set in the EXTH flags of the MOBI header if an EXTH header follows it.
+const EXTH_FLAG: u32 = 0x40;
+const UTF8_ENCODING: u32 = 65001;
+
+const EXTH_AUTHOR: u32 = 100;
+const EXTH_PUBLISHER: u32 = 101;
+const EXTH_DESCRIPTION: u32 = 103;
+const EXTH_ISBN: u32 = 104;
+const EXTH_ASIN: u32 = 113;
+const EXTH_COVER_OFFSET: u32 = 201;
+const EXTH_UPDATED_TITLE: u32 = 503;
+const EXTH_LANGUAGE: u32 = 524;
+
+/// The metadata of a MOBI or AZW3 file, read from the EXTH records of its MOBI header.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MobiMetadata {
+    pub title: Option<String>,
+    pub authors: Vec<String>,
+    pub publisher: Option<String>,
+    pub description: Option<String>,
+    pub language: Option<String>,
+    pub isbn: Option<String>,
+    pub asin: Option<String>,
+    /// The cover image, if the file has one.
+    pub cover: Option<Vec<u8>>,
+}
+
+fn read_u16(buf: &[u8], i: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(buf.get(i..i + 2)?.try_into().ok()?))
+}
+
+fn read_u32(buf: &[u8], i: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(buf.get(i..i + 4)?.try_into().ok()?))
+}
+
+fn invalid_mobi() -> BookError {
+    BookError::Mobi("malformed MOBI header".to_string())
+}
+
+/// Decodes `bytes` with the text encoding of the MOBI header. Files which are not UTF-8 are
+/// CP1252, which is read as Latin-1.
+fn decode(bytes: &[u8], encoding: u32) -> String {
+    if encoding == UTF8_ENCODING {
+        String::from_utf8_lossy(bytes).into_owned()
+    } else {
+        bytes.iter().map(|&b| char::from(b)).collect()
+    }
+}
+
+impl MobiMetadata {
+    /// Reads the metadata of the MOBI or AZW3 file in `reader`. The title is read from the
+    /// updated title EXTH record if present, and the full name in the MOBI header otherwise.
+    ///
+    /// # Arguments
+    /// * ` reader ` - A reader over a MOBI or AZW3 file.
+    ///
+    /// # Errors
+    /// Will return an error if reading fails, or if the file is not a MOBI file.
+    pub fn from_read<R: Read + Seek>(mut reader: R) -> Result<Self, BookError> {
+        let file_len = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(0))?;
+        let mut header = [0; PDB_HEADER_LEN];
+        reader.read_exact(&mut header)?;
+        let num_records = usize::from(read_u16(&header, 76).ok_or_else(invalid_mobi)?);
+
+        let mut record_list = vec![0; num_records * 8];
+        reader.read_exact(&mut record_list)?;
+        let offsets: Vec<u64> = (0..num_records)
+            .filter_map(|i| read_u32(&record_list, i * 8).map(u64::from))
+            .collect();
+        // Each record extends to the start of the next, and the last to the end of the file.
+        let record = |i: usize| -> Option<(u64, u64)> {
+            let start = *offsets.get(i)?;
+            let end = offsets.get(i + 1).copied().unwrap_or(file_len);
+            Some((start, end.checked_sub(start)?))
+        };
+
+        let (record0_start, record0_len) = record(0).ok_or_else(invalid_mobi)?;
+        reader.seek(SeekFrom::Start(record0_start))?;
+        let mut record0 = vec![0; record0_len as usize];
+        reader.read_exact(&mut record0)?;
+
+        let mobi = PALMDOC_HEADER_LEN;
+        if record0.get(mobi..mobi + 4) != Some(&b"MOBI"[..]) {
+            return Err(invalid_mobi());
+        }
+        let mobi_len = read_u32(&record0, mobi + 4).ok_or_else(invalid_mobi)? as usize;
+        let encoding = read_u32(&record0, mobi + 12).ok_or_else(invalid_mobi)?;
+        let first_image = read_u32(&record0, mobi + 92);
+        let exth_flags = read_u32(&record0, mobi + 112).unwrap_or(0);
+
+        let mut metadata = MobiMetadata::default();
+        let name_offset = read_u32(&record0, mobi + 68).map(|offset| offset as usize);
+        let name_len = read_u32(&record0, mobi + 72).map(|len| len as usize);
+        if let (Some(offset), Some(len)) = (name_offset, name_len) {
+            if let Some(name) = record0.get(offset..offset + len) {
+                metadata.title = Some(decode(name, encoding)).filter(|name| !name.is_empty());
+            }
+        }
+
+        let mut cover_offset = None;
+        if exth_flags & EXTH_FLAG != 0 {
+            let exth = mobi + mobi_len;
+            if record0.get(exth..exth + 4) != Some(&b"EXTH"[..]) {
+                return Err(invalid_mobi());
+            }
+            let count = read_u32(&record0, exth + 8).ok_or_else(invalid_mobi)?;
+            let mut i = exth + 12;
+            for _ in 0..count {
+                let kind = read_u32(&record0, i).ok_or_else(invalid_mobi)?;
+                let len = read_u32(&record0, i + 4).ok_or_else(invalid_mobi)? as usize;
+                let data = record0.get(i + 8..i + len).ok_or_else(invalid_mobi)?;
+                let text = || Some(decode(data, encoding)).filter(|s| !s.is_empty());
+                match kind {
+                    EXTH_AUTHOR => metadata
+                        .authors
+                        .extend(text().map(|a| author::normalize(&a))),
+                    EXTH_PUBLISHER => metadata.publisher = text(),
+                    EXTH_DESCRIPTION => metadata.description = text(),
+                    EXTH_ISBN => metadata.isbn = text(),
+                    EXTH_ASIN => metadata.asin = text(),
+                    EXTH_UPDATED_TITLE => metadata.title = text().or_else(|| metadata.title.take()),
+                    EXTH_LANGUAGE => metadata.language = text(),
+                    EXTH_COVER_OFFSET => cover_offset = read_u32(data, 0),
+                    _ => {}
+                }
+                i += len;
+            }
+        }
+
+        // The cover offset is relative to the first image record.
+        if let (Some(first_image), Some(cover_offset)) = (first_image, cover_offset) {
+            let index = first_image as usize + cover_offset as usize;
+            if let Some((start, len)) = record(index) {
+                reader.seek(SeekFrom::Start(start))?;
+                let mut cover = vec![0; len as usize];
+                reader.read_exact(&mut cover)?;
+                metadata.cover = Some(cover);
+            }
+        }
+
+        Ok(metadata)
+    }
+}
 
 impl MetadataFiller for MobiMetadata {
     fn take_title(&mut self, title: &mut Option<String>) {
-        *title = Some(self.title());
+        *title = std::mem::take(&mut self.title);
     }
 
     fn take_description(&mut self, description: &mut Option<String>) {
-        *description = self.description();
+        *description = std::mem::take(&mut self.description);
     }
 
     fn take_language(&mut self, language: &mut Option<String>) {
-        *language = Some(format!("{:?}", self.language()));
+        *language = std::mem::take(&mut self.language);
     }
 
     fn take_identifier(&mut self, identifier: &mut Option<Identifier>) {
-        if let Some(isbn) = self.isbn() {
-            if let Ok(isbn) = Isbn::from_str(&isbn) {
-                *identifier = Some(Identifier::ISBN(isbn));
-            }
-        }
+        let isbn = std::mem::take(&mut self.isbn).and_then(|isbn| Isbn::from_str(&isbn).ok());
+        *identifier = match (isbn, std::mem::take(&mut self.asin)) {
+            (Some(isbn), _) => Some(Identifier::ISBN(isbn)),
+            (None, Some(asin)) => Some(Identifier::Unknown("asin".to_string(), asin)),
+            (None, None) => None,
+        };
     }
 
     fn take_authors(&mut self, authors: &mut Option<Vec<String>>) {
-        if let Some(author) = self.author() {
-            *authors = Some(vec![unravel_author(&author)]);
+        if !self.authors.is_empty() {
+            *authors = Some(std::mem::take(&mut self.authors));
         }
     }
+
+    fn take_named_tags(&mut self, tags: &mut HashMap<String, String>) {
+        if let Some(publisher) = std::mem::take(&mut self.publisher) {
+            tags.insert("publisher".to_string(), publisher);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    /// Creates a MOBI file with the given full name and EXTH records, followed by a single
+    /// image record holding `cover`.
+    fn mobi_file(name: &str, exth: &[(u32, &[u8])], cover: &[u8]) -> Vec<u8> {
+        let mobi_len = 120;
+        let mut exth_header = b"EXTH".to_vec();
+        let records_len: usize = exth.iter().map(|(_, data)| data.len() + 8).sum();
+        exth_header.extend(&(12 + records_len as u32).to_be_bytes());
+        exth_header.extend(&(exth.len() as u32).to_be_bytes());
+        for (kind, data) in exth {
+            exth_header.extend(&kind.to_be_bytes());
+            exth_header.extend(&(data.len() as u32 + 8).to_be_bytes());
+            exth_header.extend(*data);
+        }
+
+        let mut mobi = vec![0; mobi_len];
+        mobi[..4].copy_from_slice(b"MOBI");
+        mobi[4..8].copy_from_slice(&(mobi_len as u32).to_be_bytes());
+        mobi[12..16].copy_from_slice(&UTF8_ENCODING.to_be_bytes());
+        let name_offset = PALMDOC_HEADER_LEN + mobi_len + exth_header.len();
+        mobi[68..72].copy_from_slice(&(name_offset as u32).to_be_bytes());
+        mobi[72..76].copy_from_slice(&(name.len() as u32).to_be_bytes());
+        // The image records start immediately after record 0.
+        mobi[92..96].copy_from_slice(&1u32.to_be_bytes());
+        mobi[112..116].copy_from_slice(&EXTH_FLAG.to_be_bytes());
+
+        let mut record0 = vec![0; PALMDOC_HEADER_LEN];
+        record0.extend(mobi);
+        record0.extend(exth_header);
+        record0.extend(name.as_bytes());
+
+        let mut file = vec![0; PDB_HEADER_LEN];
+        file[76..78].copy_from_slice(&2u16.to_be_bytes());
+        let record0_offset = (PDB_HEADER_LEN + 16) as u32;
+        file.extend(&record0_offset.to_be_bytes());
+        file.extend(&[0; 4]);
+        file.extend(&(record0_offset + record0.len() as u32).to_be_bytes());
+        file.extend(&[0; 4]);
+        file.extend(record0);
+        file.extend(cover);
+        file
+    }
+
+    #[test]
+    fn test_mobi_metadata() {
+        let file = mobi_file(
+            "Dune",
+            &[
+                (EXTH_AUTHOR, &b"Herbert, Frank"[..]),
+                (EXTH_AUTHOR, &b"Brian Herbert"[..]),
+                (EXTH_PUBLISHER, &b"Ace"[..]),
+                (EXTH_DESCRIPTION, &b"A desert planet."[..]),
+                (EXTH_ASIN, &b"B00B7NPRY8"[..]),
+                (EXTH_UPDATED_TITLE, &b"Dune (Deluxe Edition)"[..]),
+                (EXTH_COVER_OFFSET, &0u32.to_be_bytes()[..]),
+            ],
+            b"cover image",
+        );
+
+        let mut metadata = MobiMetadata::from_read(Cursor::new(file)).unwrap();
+        assert_eq!(
+            metadata,
+            MobiMetadata {
+                title: Some("Dune (Deluxe Edition)".to_string()),
+                authors: vec!["Frank Herbert".to_string(), "Brian Herbert".to_string()],
+                publisher: Some("Ace".to_string()),
+                description: Some("A desert planet.".to_string()),
+                language: None,
+                isbn: None,
+                asin: Some("B00B7NPRY8".to_string()),
+                cover: Some(b"cover image".to_vec()),
+            }
+        );
+
+        let mut identifier = None;
+        metadata.take_identifier(&mut identifier);
+        assert_eq!(
+            identifier,
+            Some(Identifier::Unknown(
+                "asin".to_string(),
+                "B00B7NPRY8".to_string()
+            ))
+        );
+        let mut tags = HashMap::new();
+        metadata.take_named_tags(&mut tags);
+        assert_eq!(tags.get("publisher").map(String::as_str), Some("Ace"));
+
+        let metadata = MobiMetadata::from_read(Cursor::new(mobi_file("Dune", &[], b""))).unwrap();
+        assert_eq!(metadata.title, Some("Dune".to_string()));
+        assert!(metadata.authors.is_empty());
+
+        assert!(MobiMetadata::from_read(Cursor::new(vec![0; 200])).is_err());
+    }
 }
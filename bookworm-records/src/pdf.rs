@@ -0,0 +1,348 @@
+use std::collections::HashMap;
+use std::io::Read;
+
+use crate::author;
+use crate::epub::unescape;
+use crate::variant::{Identifier, MetadataFiller};
+use crate::BookError;
+
+/// The metadata of a PDF file, read from its document information dictionary, and from its
+/// XMP metadata for fields which the dictionary does not have.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PdfMetadata {
+    pub title: Option<String>,
+    pub authors: Vec<String>,
+    pub subject: Option<String>,
+    /// The number of pages, as recorded in the root page tree node.
+    pub pages: Option<usize>,
+}
+
+/// Returns the index of the first occurrence of `needle` in `haystack` at or after `from`.
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|i| i + from)
+}
+
+/// Returns the index of the last occurrence of `needle` in `haystack`.
+fn rfind(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .rposition(|window| window == needle)
+}
+
+fn skip_whitespace(bytes: &[u8], mut i: usize) -> usize {
+    while bytes.get(i).map_or(false, u8::is_ascii_whitespace) {
+        i += 1;
+    }
+    i
+}
+
+/// Reads the unsigned integer at `i`, and returns it with the index following it.
+fn read_integer(bytes: &[u8], i: usize) -> Option<(usize, usize)> {
+    let len = bytes
+        .get(i..)?
+        .iter()
+        .take_while(|b| b.is_ascii_digit())
+        .count();
+    let value = std::str::from_utf8(&bytes[i..i + len]).ok()?.parse().ok()?;
+    Some((value, i + len))
+}
+
+/// Decodes the bytes of a PDF text string, which are UTF-16BE if they start with a byte order
+/// mark, and PDFDocEncoding otherwise. PDFDocEncoding is read as Latin-1.
+fn decode_text(bytes: &[u8]) -> String {
+    match bytes {
+        [0xFE, 0xFF, rest @ ..] => {
+            let units: Vec<u16> = rest
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => bytes.iter().map(|&b| char::from(b)).collect(),
+    }
+}
+
+/// Reads the literal `(...)` or hexadecimal `<...>` string starting at `i`.
+fn read_string(bytes: &[u8], i: usize) -> Option<String> {
+    match bytes.get(i)? {
+        b'(' => {
+            let mut value = vec![];
+            let mut depth = 0;
+            let mut j = i + 1;
+            loop {
+                match *bytes.get(j)? {
+                    b'\\' => {
+                        j += 1;
+                        match *bytes.get(j)? {
+                            b'n' => value.push(b'\n'),
+                            b'r' => value.push(b'\r'),
+                            b't' => value.push(b'\t'),
+                            b'b' => value.push(0x08),
+                            b'f' => value.push(0x0C),
+                            // A backslash at the end of a line continues the string.
+                            b'\r' | b'\n' => {}
+                            digit @ b'0'..=b'7' => {
+                                let mut code = u32::from(digit - b'0');
+                                for _ in 0..2 {
+                                    match bytes.get(j + 1) {
+                                        Some(&d @ b'0'..=b'7') => {
+                                            code = code * 8 + u32::from(d - b'0');
+                                            j += 1;
+                                        }
+                                        _ => break,
+                                    }
+                                }
+                                value.push(code as u8);
+                            }
+                            other => value.push(other),
+                        }
+                    }
+                    b'(' => {
+                        depth += 1;
+                        value.push(b'(');
+                    }
+                    b')' if depth == 0 => break,
+                    b')' => {
+                        depth -= 1;
+                        value.push(b')');
+                    }
+                    other => value.push(other),
+                }
+                j += 1;
+            }
+            Some(decode_text(&value))
+        }
+        b'<' => {
+            let end = find(bytes, b">", i)?;
+            let digits: Vec<u8> = bytes[i + 1..end]
+                .iter()
+                .filter(|b| !b.is_ascii_whitespace())
+                .copied()
+                .collect();
+            let value: Option<Vec<u8>> = digits
+                .chunks(2)
+                .map(|pair| {
+                    // A missing final digit is treated as zero.
+                    let hex = [pair[0], *pair.get(1).unwrap_or(&b'0')];
+                    u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()
+                })
+                .collect();
+            Some(decode_text(&value?))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the string value of `key` in the dictionary `dict`, if it is a direct string.
+fn dict_string(dict: &[u8], key: &[u8]) -> Option<String> {
+    let mut from = 0;
+    while let Some(i) = find(dict, key, from) {
+        from = i + key.len();
+        // Skips keys which only start with `key`, such as /Titles.
+        if dict.get(from).map_or(false, u8::is_ascii_alphanumeric) {
+            continue;
+        }
+        let value = skip_whitespace(dict, from);
+        if dict.get(value..value + 2) != Some(&b"<<"[..]) {
+            if let Some(s) = read_string(dict, value) {
+                return Some(s.trim().to_string()).filter(|s| !s.is_empty());
+            }
+        }
+    }
+    None
+}
+
+/// Returns the body of the indirect object `number generation obj`, using the last definition
+/// if the file was incrementally updated.
+fn object<'a>(bytes: &'a [u8], number: usize, generation: usize) -> Option<&'a [u8]> {
+    let header = format!("{} {} obj", number, generation);
+    let mut start = None;
+    let mut from = 0;
+    while let Some(i) = find(bytes, header.as_bytes(), from) {
+        // Skips object numbers which only end with `number`, such as 12 for 2.
+        if i == 0 || !bytes[i - 1].is_ascii_digit() {
+            start = Some(i + header.len());
+        }
+        from = i + header.len();
+    }
+    let start = start?;
+    let end = find(bytes, b"endobj", start).unwrap_or(bytes.len());
+    Some(&bytes[start..end])
+}
+
+/// Returns the document information dictionary named by the last `/Info` entry of a trailer.
+fn info_dictionary(bytes: &[u8]) -> Option<&[u8]> {
+    let i = rfind(bytes, b"/Info")? + b"/Info".len();
+    let (number, i) = read_integer(bytes, skip_whitespace(bytes, i))?;
+    let (generation, _) = read_integer(bytes, skip_whitespace(bytes, i))?;
+    object(bytes, number, generation)
+}
+
+/// Returns the text of each `<rdf:li>` element inside the XMP element `<name>`.
+fn xmp_items(xmp: &str, name: &str) -> Vec<String> {
+    let open = format!("<{}>", name);
+    let close = format!("</{}>", name);
+    let body = match xmp.find(&open) {
+        Some(start) => {
+            let body = &xmp[start + open.len()..];
+            &body[..body.find(&close).unwrap_or(body.len())]
+        }
+        None => return vec![],
+    };
+    body.match_indices("<rdf:li")
+        .filter_map(|(i, _)| {
+            let item = &body[i..];
+            let start = item.find('>')? + 1;
+            let end = item.find("</rdf:li>")?;
+            let text = unescape(item.get(start..end)?.trim());
+            Some(text).filter(|text| !text.is_empty())
+        })
+        .collect()
+}
+
+/// Returns the largest `/Count` of the page tree nodes, which is the count of the root node.
+fn page_count(bytes: &[u8]) -> Option<usize> {
+    let mut count = None;
+    for needle in &[&b"/Type/Pages"[..], &b"/Type /Pages"[..]] {
+        let mut from = 0;
+        while let Some(i) = find(bytes, needle, from) {
+            from = i + needle.len();
+            let start = rfind(&bytes[..i], b"obj").unwrap_or(0);
+            let end = find(bytes, b"endobj", i).unwrap_or(bytes.len());
+            let node = &bytes[start..end];
+            if let Some(c) = find(node, b"/Count", 0) {
+                if let Some((n, _)) = read_integer(node, skip_whitespace(node, c + 6)) {
+                    count = count.max(Some(n));
+                }
+            }
+        }
+    }
+    count
+}
+
+impl PdfMetadata {
+    /// Reads the metadata of the PDF file in `reader`. Values in the document information
+    /// dictionary are preferred, and the XMP `dc:title`, `dc:creator` and `dc:description`
+    /// fields are used for values which the dictionary does not have. Metadata inside
+    /// compressed object streams is not read.
+    ///
+    /// # Arguments
+    /// * ` reader ` - A reader over a PDF file.
+    ///
+    /// # Errors
+    /// Will return an error if reading fails, or if the file is not a PDF file.
+    pub fn from_read<R: Read>(mut reader: R) -> Result<Self, BookError> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes)?;
+        if !bytes.starts_with(b"%PDF-") {
+            return Err(BookError::Pdf("missing %PDF- header".to_string()));
+        }
+
+        let mut metadata = PdfMetadata {
+            pages: page_count(&bytes),
+            ..Default::default()
+        };
+        if let Some(info) = info_dictionary(&bytes) {
+            metadata.title = dict_string(info, b"/Title");
+            metadata.subject = dict_string(info, b"/Subject");
+            if let Some(authors) = dict_string(info, b"/Author") {
+                metadata.authors = authors
+                    .split(';')
+                    .map(str::trim)
+                    .filter(|author| !author.is_empty())
+                    .map(author::normalize)
+                    .collect();
+            }
+        }
+
+        if let Some(start) = find(&bytes, b"<x:xmpmeta", 0) {
+            let end = find(&bytes, b"</x:xmpmeta>", start).unwrap_or(bytes.len());
+            let xmp = String::from_utf8_lossy(&bytes[start..end]);
+            if metadata.title.is_none() {
+                metadata.title = xmp_items(&xmp, "dc:title").into_iter().next();
+            }
+            if metadata.authors.is_empty() {
+                metadata.authors = xmp_items(&xmp, "dc:creator")
+                    .iter()
+                    .map(|name| author::normalize(name))
+                    .collect();
+            }
+            if metadata.subject.is_none() {
+                metadata.subject = xmp_items(&xmp, "dc:description").into_iter().next();
+            }
+        }
+
+        Ok(metadata)
+    }
+}
+
+impl MetadataFiller for PdfMetadata {
+    fn take_title(&mut self, title: &mut Option<String>) {
+        *title = std::mem::take(&mut self.title);
+    }
+
+    fn take_description(&mut self, description: &mut Option<String>) {
+        *description = std::mem::take(&mut self.subject);
+    }
+
+    fn take_language(&mut self, _language: &mut Option<String>) {}
+
+    fn take_identifier(&mut self, _identifier: &mut Option<Identifier>) {}
+
+    fn take_authors(&mut self, authors: &mut Option<Vec<String>>) {
+        if !self.authors.is_empty() {
+            *authors = Some(std::mem::take(&mut self.authors));
+        }
+    }
+
+    fn take_named_tags(&mut self, tags: &mut HashMap<String, String>) {
+        if let Some(pages) = self.pages {
+            tags.insert("pages".to_string(), pages.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_pdf_metadata() {
+        let pdf = b"%PDF-1.4
+1 0 obj << /Type /Catalog /Pages 2 0 R >> endobj
+2 0 obj << /Type /Pages /Kids [3 0 R] /Count 312 >> endobj
+3 0 obj << /Type /Page /Parent 2 0 R >> endobj
+4 0 obj
+<< /Title (Dune \\(Deluxe\\)) /Author (Herbert, Frank; Brian Herbert) /Subject <FEFF0041> >>
+endobj
+trailer << /Root 1 0 R /Info 4 0 R >>
+%%EOF";
+        assert_eq!(
+            PdfMetadata::from_read(Cursor::new(&pdf[..])).unwrap(),
+            PdfMetadata {
+                title: Some("Dune (Deluxe)".to_string()),
+                authors: vec!["Frank Herbert".to_string(), "Brian Herbert".to_string()],
+                subject: Some("A".to_string()),
+                pages: Some(312),
+            }
+        );
+
+        let xmp = b"%PDF-1.7
+<x:xmpmeta xmlns:x=\"adobe:ns:meta/\"><rdf:RDF>
+<dc:title><rdf:Alt><rdf:li xml:lang=\"x-default\">Children of Dune</rdf:li></rdf:Alt></dc:title>
+<dc:creator><rdf:Seq><rdf:li>Frank Herbert</rdf:li></rdf:Seq></dc:creator>
+</rdf:RDF></x:xmpmeta>
+%%EOF";
+        let metadata = PdfMetadata::from_read(Cursor::new(&xmp[..])).unwrap();
+        assert_eq!(metadata.title, Some("Children of Dune".to_string()));
+        assert_eq!(metadata.authors, vec!["Frank Herbert".to_string()]);
+        assert_eq!(metadata.pages, None);
+
+        assert!(PdfMetadata::from_read(Cursor::new(&b"not a pdf"[..])).is_err());
+    }
+}
@@ -12,7 +12,28 @@ pub struct Series {
     pub index: Option<f32>,
 }
 
-impl Series {}
+impl Series {
+    /// Compares the indices of two series, ignoring their names. Series without an index
+    /// come first.
+    pub fn cmp_index(&self, other: &Self) -> Ordering {
+        match (self.index, other.index) {
+            (None, None) => Ordering::Equal,
+            (Some(_), None) => Ordering::Greater,
+            (None, Some(_)) => Ordering::Less,
+            (Some(si), Some(oi)) => match si.partial_cmp(&oi) {
+                Some(o) => o,
+                None => match (si.is_nan(), oi.is_nan()) {
+                    (false, false) => {
+                        unreachable!("Both can not be non-nan, otherwise partial_cmp would succeed")
+                    }
+                    (true, false) => Ordering::Less,
+                    (false, true) => Ordering::Greater,
+                    (true, true) => Ordering::Equal,
+                },
+            },
+        }
+    }
+}
 
 impl FromStr for Series {
     type Err = ();
@@ -66,25 +87,9 @@ impl std::cmp::PartialOrd for Series {
 
 impl std::cmp::Ord for Series {
     fn cmp(&self, other: &Self) -> Ordering {
-        match self.name.cmp(&other.name) {
-            Ordering::Equal => match (self.index, other.index) {
-                (None, None) => Ordering::Equal,
-                (Some(_), None) => Ordering::Greater,
-                (None, Some(_)) => Ordering::Less,
-                (Some(si), Some(oi)) => match si.partial_cmp(&oi) {
-                    Some(o) => o,
-                    None => match (si.is_nan(), oi.is_nan()) {
-                        (false, false) => unreachable!(
-                            "Both can not be non-nan, otherwise partial_cmp would succeed"
-                        ),
-                        (true, false) => Ordering::Less,
-                        (false, true) => Ordering::Greater,
-                        (true, true) => Ordering::Equal,
-                    },
-                },
-            },
-            ord => ord,
-        }
+        self.name
+            .cmp(&other.name)
+            .then_with(|| self.cmp_index(other))
     }
 }
 
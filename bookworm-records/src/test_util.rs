@@ -0,0 +1,27 @@
+//! Fixtures shared by the tests of the bookworm crates. Enabled by the `test-util` feature.
+
+use std::path::PathBuf;
+
+use crate::variant::BookType;
+use crate::BookVariant;
+
+/// Returns an EPUB variant titled `title`, whose path is derived from its title, and whose hash
+/// consists of `hash` - variants with different hashes are not duplicates of each other.
+pub fn variant(title: &str, hash: u8) -> BookVariant {
+    BookVariant {
+        book_type: BookType::EPUB,
+        path: PathBuf::from(format!("{}.epub", title)),
+        file_size: 0,
+        local_title: Some(title.to_string()),
+        identifier: None,
+        language: None,
+        additional_authors: None,
+        translators: None,
+        description: None,
+        series: None,
+        id: None,
+        hash: [hash; 32],
+        free_tags: Default::default(),
+        named_tags: Default::default(),
+    }
+}
@@ -1,29 +1,24 @@
 use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
-use std::ffi::{OsStr, OsString};
+use std::ffi::OsStr;
 use std::io::{BufReader, SeekFrom};
 use std::io::{Read, Seek};
 use std::path;
+use std::str::FromStr;
 
 use isbn2::Isbn;
-use mobi::MobiMetadata;
 use quick_epub::Metadata as EpubMetadata;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 
+use crate::epub::{unsupported_compression, EpubFiller};
+use crate::fb2::Fb2Metadata;
+use crate::mobi::MobiMetadata;
+use crate::pdf::PdfMetadata;
+use crate::series::Series;
 use crate::BookError;
 
-pub(crate) fn unravel_author(author: &str) -> String {
-    if let Some(i) = author.find(',') {
-        let (a, b) = author.split_at(i);
-        let b = b.trim_start_matches(',').trim_start_matches(' ');
-        format!("{} {}", b, a)
-    } else {
-        author.to_owned()
-    }
-}
-
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Identifier {
@@ -31,6 +26,18 @@ pub enum Identifier {
     Unknown(String, String),
 }
 
+impl Identifier {
+    /// Creates an identifier from its scheme (eg. "isbn" or "amazon") and value. Valid ISBNs
+    /// with the "isbn" scheme become `Identifier::ISBN` - all other identifiers, including
+    /// malformed ISBNs, become `Identifier::Unknown(scheme, value)`.
+    pub fn new(scheme: &str, value: String) -> Self {
+        match Isbn::from_str(&value) {
+            Ok(isbn) if scheme == "isbn" => Identifier::ISBN(isbn),
+            _ => Identifier::Unknown(scheme.to_string(), value),
+        }
+    }
+}
+
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq, Eq)]
 /// Enumerates all supported book types.
@@ -39,6 +46,8 @@ pub enum BookType {
     MOBI,
     // Look at lo-pdf and pdf-extract.
     PDF,
+    /// FictionBook, which is XML, and may be compressed in a zip archive.
+    FB2,
     // TODO: AZW3, DJVU, DOC, RTF, custom extensions?
 }
 
@@ -50,8 +59,9 @@ impl TryFrom<&OsStr> for BookType {
         match o_str.to_str() {
             Some(s) => match s.to_ascii_lowercase().as_str() {
                 "epub" => Ok(BookType::EPUB),
-                "mobi" => Ok(BookType::MOBI),
+                "mobi" | "azw3" | "azw" => Ok(BookType::MOBI),
                 "pdf" => Ok(BookType::PDF),
+                "fb2" | "fbz" => Ok(BookType::FB2),
                 _ => Err(BookError::UnsupportedExtension(o_str.to_os_string())),
             },
             None => Err(BookError::UnsupportedExtension(o_str.to_os_string())),
@@ -59,20 +69,110 @@ impl TryFrom<&OsStr> for BookType {
     }
 }
 
+/// Enumerates the metadata fields which can be read from book files.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MetadataField {
+    Title,
+    Authors,
+    Description,
+    Language,
+    Identifier,
+    Series,
+    Cover,
+}
+
+/// Describes a supported book type - the file extensions it is read from, and the metadata
+/// fields which are read from its files.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FormatInfo {
+    pub book_type: BookType,
+    pub extensions: &'static [&'static str],
+    pub fields: &'static [MetadataField],
+}
+
+impl FormatInfo {
+    /// Returns whether `field` is read from files of this format.
+    pub fn supports(&self, field: MetadataField) -> bool {
+        self.fields.contains(&field)
+    }
+}
+
+/// Returns each supported book type, with its file extensions and the metadata fields which
+/// are read from its files. Books whose files do not have a title are titled with their file name.
+pub fn supported_formats() -> Vec<FormatInfo> {
+    vec![BookType::EPUB, BookType::MOBI, BookType::PDF, BookType::FB2]
+        .into_iter()
+        .map(|book_type| FormatInfo {
+            extensions: book_type.extensions(),
+            fields: book_type.metadata_fields(),
+            book_type,
+        })
+        .collect()
+}
+
 impl BookType {
+    /// Returns the file extensions used by this book type, in lowercase.
+    pub fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            BookType::EPUB => &["epub"],
+            BookType::MOBI => &["mobi", "azw3", "azw"],
+            BookType::PDF => &["pdf"],
+            BookType::FB2 => &["fb2", "fbz"],
+        }
+    }
+
+    /// Returns the metadata fields which are read from files of this book type.
+    /// This must be kept in sync with the `MetadataFiller` used in `metadata_filler`.
+    pub fn metadata_fields(&self) -> &'static [MetadataField] {
+        match self {
+            BookType::EPUB => &[
+                MetadataField::Title,
+                MetadataField::Authors,
+                MetadataField::Description,
+                MetadataField::Language,
+                MetadataField::Identifier,
+                MetadataField::Series,
+            ],
+            BookType::MOBI => &[
+                MetadataField::Title,
+                MetadataField::Authors,
+                MetadataField::Description,
+                MetadataField::Language,
+                MetadataField::Identifier,
+            ],
+            BookType::PDF => &[
+                MetadataField::Title,
+                MetadataField::Authors,
+                MetadataField::Description,
+            ],
+            BookType::FB2 => &[
+                MetadataField::Title,
+                MetadataField::Authors,
+                MetadataField::Description,
+                MetadataField::Language,
+                MetadataField::Series,
+            ],
+        }
+    }
+
     // TODO: Implement timeout to prevent crashing if reading explodes.
     fn metadata_filler<R: std::io::Read + std::io::Seek>(
         &self,
-        reader: R,
+        mut reader: R,
     ) -> Result<Box<dyn MetadataFiller>, BookError> {
         match self {
-            BookType::EPUB => Ok(Box::new(
-                EpubMetadata::from_read(reader).map_err(|_| BookError::FileError)?,
-            )),
-            BookType::MOBI => Ok(Box::new(
-                MobiMetadata::from_read(reader).map_err(|_| BookError::FileError)?,
-            )),
-            _ => Err(BookError::UnsupportedExtension(OsString::from("PDF"))),
+            BookType::EPUB => match EpubMetadata::from_read(&mut reader) {
+                Ok(metadata) => Ok(Box::new(EpubFiller::new(metadata, &mut reader)?)),
+                // Find out if the failure is caused by an unsupported compression method,
+                // so that the user knows to recompress the file.
+                Err(e) => Err(match unsupported_compression(&mut reader) {
+                    Ok(Some(method)) => BookError::UnsupportedCompression(method),
+                    _ => BookError::from(e),
+                }),
+            },
+            BookType::MOBI => Ok(Box::new(MobiMetadata::from_read(reader)?)),
+            BookType::PDF => Ok(Box::new(PdfMetadata::from_read(reader)?)),
+            BookType::FB2 => Ok(Box::new(Fb2Metadata::from_read(reader)?)),
         }
     }
 }
@@ -87,6 +187,15 @@ pub trait MetadataFiller {
     fn take_identifier(&mut self, identifier: &mut Option<Identifier>);
 
     fn take_authors(&mut self, authors: &mut Option<Vec<String>>);
+
+    /// Moves the translators of the book into `translators`.
+    fn take_translators(&mut self, _translators: &mut Option<Vec<String>>) {}
+
+    /// Moves the series the book belongs to, and its position in the series, into `series`.
+    fn take_series(&mut self, _series: &mut Option<Series>) {}
+
+    /// Moves metadata which has no dedicated field, such as the publisher, into `tags`.
+    fn take_named_tags(&mut self, _tags: &mut HashMap<String, String>) {}
 }
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -101,6 +210,7 @@ pub struct BookVariant {
     pub additional_authors: Option<Vec<String>>,
     pub translators: Option<Vec<String>>,
     pub description: Option<String>,
+    pub series: Option<Series>,
     pub id: Option<u32>,
     pub hash: [u8; 32],
     pub free_tags: HashSet<String>,
@@ -134,19 +244,12 @@ impl BookVariant {
         let (reader, hash, file_size) = {
             let mut file = std::fs::File::open(&path)?;
             let len = file.metadata()?.len();
-            let bytes_to_read = (len as usize).min(4096);
-
-            let mut buf = [0; 4096];
-            file.read_exact(&mut buf[..bytes_to_read])?;
+            let hash = hash_prefix(&mut file, len)?;
             file.seek(SeekFrom::Start(0))?;
 
-            let mut hasher = Sha256::new();
-            hasher.update(&buf[..bytes_to_read]);
-            let res = hasher.finalize();
-
             (
-                BufReader::with_capacity(bytes_to_read, file),
-                res.into(),
+                BufReader::with_capacity((len as usize).min(HASHED_BYTES), file),
+                hash,
                 len,
             )
         };
@@ -162,6 +265,7 @@ impl BookVariant {
             additional_authors: None,
             translators: None,
             description: None,
+            series: None,
             id: None,
             free_tags: HashSet::new(),
             named_tags: HashMap::new(),
@@ -170,9 +274,12 @@ impl BookVariant {
         if let Ok(mut metadata_filler) = book.book_type.metadata_filler(reader) {
             metadata_filler.take_title(&mut book.local_title);
             metadata_filler.take_authors(&mut book.additional_authors);
+            metadata_filler.take_translators(&mut book.translators);
             metadata_filler.take_description(&mut book.description);
             metadata_filler.take_language(&mut book.language);
             metadata_filler.take_identifier(&mut book.identifier);
+            metadata_filler.take_series(&mut book.series);
+            metadata_filler.take_named_tags(&mut book.named_tags);
         }
 
         if book.local_title.is_none() {
@@ -200,4 +307,134 @@ impl BookVariant {
     pub fn book_type(&self) -> &BookType {
         &self.book_type
     }
+
+    /// Checks whether the file of this variant exists, and whether it still has the size and
+    /// hash which were recorded when the variant was added.
+    pub fn file_status(&self) -> FileStatus {
+        let mut file = match std::fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(_) => return FileStatus::Missing,
+        };
+        let len = match file.metadata() {
+            Ok(metadata) => metadata.len(),
+            Err(_) => return FileStatus::Missing,
+        };
+        if len != self.file_size {
+            return FileStatus::Changed;
+        }
+        match hash_prefix(&mut file, len) {
+            Ok(hash) if hash == self.hash => FileStatus::Unchanged,
+            Ok(_) => FileStatus::Changed,
+            Err(_) => FileStatus::Missing,
+        }
+    }
+}
+
+/// Whether the file of a variant matches what was recorded when the variant was added.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FileStatus {
+    /// The file exists, and has the recorded size and hash.
+    Unchanged,
+    /// The file exists, but its size or hash differs from the recorded values.
+    Changed,
+    /// The file does not exist, or can not be read.
+    Missing,
+}
+
+/// The number of bytes at the start of a file which are hashed to identify the file.
+const HASHED_BYTES: usize = 4096;
+
+/// Returns the size of the file at `path`, and the hash which identifies it.
+///
+/// # Errors
+/// Will return an error if the file can not be read.
+pub fn hash_file(path: &path::Path) -> Result<(u64, [u8; 32]), std::io::Error> {
+    let mut file = std::fs::File::open(path)?;
+    let len = file.metadata()?.len();
+    Ok((len, hash_prefix(&mut file, len)?))
+}
+
+/// Returns the SHA-256 hash of the first `HASHED_BYTES` bytes of `file`, which is `len` bytes
+/// long.
+fn hash_prefix(file: &mut std::fs::File, len: u64) -> Result<[u8; 32], std::io::Error> {
+    let bytes_to_read = (len as usize).min(HASHED_BYTES);
+    let mut buf = [0; HASHED_BYTES];
+    file.read_exact(&mut buf[..bytes_to_read])?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(&buf[..bytes_to_read]);
+    Ok(hasher.finalize().into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_supported_formats() {
+        let formats = supported_formats();
+        let format = |book_type: BookType| {
+            formats
+                .iter()
+                .find(|format| format.book_type == book_type)
+                .expect("Each book type should be supported")
+        };
+
+        let epub = format(BookType::EPUB);
+        assert_eq!(epub.extensions, &["epub"]);
+        assert!(epub.supports(MetadataField::Title));
+        assert!(epub.supports(MetadataField::Authors));
+        assert!(epub.supports(MetadataField::Series));
+
+        let pdf = format(BookType::PDF);
+        assert_eq!(pdf.extensions, &["pdf"]);
+        assert!(pdf.supports(MetadataField::Title));
+        assert!(pdf.supports(MetadataField::Description));
+        assert!(!pdf.supports(MetadataField::Language));
+
+        let fb2 = format(BookType::FB2);
+        assert_eq!(fb2.extensions, &["fb2", "fbz"]);
+        assert!(fb2.supports(MetadataField::Series));
+        assert!(!fb2.supports(MetadataField::Identifier));
+
+        for format in &formats {
+            for ext in format.extensions {
+                assert_eq!(
+                    BookType::try_from(OsStr::new(ext)).unwrap(),
+                    format.book_type
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_file_status() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Dune.pdf");
+        std::fs::write(&path, b"%PDF-1.4").unwrap();
+        let mut variant = BookVariant::from_path(&path).unwrap();
+        assert_eq!(variant.file_status(), FileStatus::Unchanged);
+
+        std::fs::write(&path, b"%PDF-1.7").unwrap();
+        assert_eq!(variant.file_status(), FileStatus::Changed);
+
+        variant.path = dir.path().join("Emma.pdf");
+        assert_eq!(variant.file_status(), FileStatus::Missing);
+    }
+
+    #[test]
+    fn test_identifier_new() {
+        assert_eq!(
+            Identifier::new("isbn", "9780441013593".to_string()),
+            Identifier::ISBN(Isbn::from_str("9780441013593").unwrap())
+        );
+        assert_eq!(
+            Identifier::new("isbn", "123".to_string()),
+            Identifier::Unknown("isbn".to_string(), "123".to_string())
+        );
+        assert_eq!(
+            Identifier::new("goodreads", "9780441013593".to_string()),
+            Identifier::Unknown("goodreads".to_string(), "9780441013593".to_string())
+        );
+    }
 }
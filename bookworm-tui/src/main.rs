@@ -6,9 +6,10 @@
 mod ui;
 
 use std::env;
-use std::io::stdout;
+use std::io::{stdout, Write};
 use std::path::PathBuf;
 use std::process::exit;
+use std::time::Duration;
 
 use clap::Parser;
 use crossterm::event::EventStream;
@@ -23,12 +24,15 @@ use tracing::subscriber::set_global_default;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::{fmt, EnvFilter, Registry};
 
+use bookworm_app::app::ImportEvent;
+use bookworm_app::columns::Columns;
+use bookworm_app::maintenance::prune_logs;
 use bookworm_app::{parse_args, App, Settings};
 use bookworm_database::AppDatabase;
-use bookworm_database::SQLiteDatabase;
+use bookworm_database::{FieldLimits, RepairPolicy, SQLiteDatabase};
 
 use crate::ui::terminal_ui::UIState;
-use crate::ui::utils::{run_command, AppView, ApplicationTask};
+use crate::ui::utils::{books_added, progress_message, run_command, AppView, ApplicationTask};
 use crate::ui::{AppInterface, TuiError};
 
 #[derive(Parser)]
@@ -38,6 +42,29 @@ struct Opts {
     settings: Option<PathBuf>,
     #[clap(short, long)]
     database: Option<PathBuf>,
+    /// Disables background tasks, such as prefetching books, to make behaviour reproducible.
+    #[clap(long)]
+    safe: bool,
+    /// Copies the books of a Calibre library (or its metadata.db file) into the database.
+    #[clap(long)]
+    import_calibre: Option<PathBuf>,
+    /// Deletes variants, tags and covers which belong to books which no longer exist, such as
+    /// those left behind by an interrupted deletion.
+    #[clap(long)]
+    repair: bool,
+    /// With --repair, recreates the missing books instead of deleting their variants, tags and
+    /// covers.
+    #[clap(long, requires = "repair")]
+    recreate_orphans: bool,
+    /// Rebuilds the database once, so that the space used by removed books is returned to the
+    /// file system. Databases created by older versions of bookworm do not do this otherwise.
+    #[clap(long)]
+    vacuum: bool,
+    /// Serves the library as an OPDS catalog at the given address (eg. 0.0.0.0:8080), instead of
+    /// opening the interface.
+    #[cfg(feature = "opds")]
+    #[clap(long)]
+    serve: Option<std::net::SocketAddr>,
 }
 
 #[tokio::main]
@@ -49,17 +76,16 @@ async fn main() -> Result<(), TuiError<<SQLiteDatabase as AppDatabase>::Error>>
         PathBuf::from("./bookworm/logs/log")
     };
 
-    std::fs::create_dir_all(
-        logging_dir
-            .parent()
-            .expect("Could not read the parent of logging_dir"),
-    )
-    .expect("Failed to create logging directory");
+    let logging_parent = logging_dir
+        .parent()
+        .expect("Could not read the parent of logging_dir")
+        .to_path_buf();
+    std::fs::create_dir_all(&logging_parent).expect("Failed to create logging directory");
 
     println!("Writing logs to {}", logging_dir.display());
 
     let (file_appender, _guard) = tracing_appender::non_blocking::NonBlocking::new(
-        BasicRollingFileAppender::new(logging_dir, RollingConditionBasic::new().hourly(), 24)
+        BasicRollingFileAppender::new(&logging_dir, RollingConditionBasic::new().hourly(), 24)
             .expect("Failed to initialize logging"),
     );
 
@@ -85,38 +111,151 @@ async fn main() -> Result<(), TuiError<<SQLiteDatabase as AppDatabase>::Error>>
         }
     };
 
-    let Opts { settings, database } = opts;
-    let ((interface_settings, mut app_settings), settings_path) = if let Some(path) = settings {
-        (
-            Settings::open(&path).unwrap_or_default().split(),
-            Some(path),
-        )
-    } else if let Some(mut path) = dirs::config_dir() {
-        path.push("bookworm/settings.toml");
-        (
-            Settings::open(&path).unwrap_or_default().split(),
-            Some(path),
-        )
-    } else {
-        (Settings::default().split(), None)
-    };
+    let Opts {
+        settings,
+        database,
+        safe,
+        import_calibre,
+        repair,
+        recreate_orphans,
+        vacuum,
+        #[cfg(feature = "opds")]
+        serve,
+    } = opts;
+    let settings_path = settings.or_else(|| {
+        dirs::config_dir().map(|mut path| {
+            path.push("bookworm/settings.toml");
+            path
+        })
+    });
+    let (interface_settings, mut app_settings) = match &settings_path {
+        Some(path) if !path.exists() => match Settings::first_run(path, database.clone()) {
+            Ok(settings) => {
+                println!("Welcome to bookworm! Created a new configuration:");
+                println!("  Settings: {}", path.display());
+                println!("  Database: {}", settings.database_settings.path.display());
+                settings
+            }
+            Err(e) => {
+                tracing::error!("Failed to write initial settings: {:?}", e);
+                Settings::default()
+            }
+        },
+        Some(path) => Settings::open(path).unwrap_or_default(),
+        None => Settings::default(),
+    }
+    .split();
 
     if let Some(path) = database {
         app_settings.database_settings.path = path;
     }
 
-    let db = SQLiteDatabase::open(&app_settings.database_settings.path).await?;
+    let retention = Duration::from_secs(
+        app_settings
+            .maintenance_settings
+            .log_retention_days
+            .saturating_mul(24 * 60 * 60),
+    );
+    match prune_logs(&logging_parent, &logging_dir, retention) {
+        Ok(removed) => tracing::info!("Removed {} expired log files", removed),
+        Err(e) => tracing::error!("Failed to prune log files: {:?}", e),
+    }
+
+    let mut db = SQLiteDatabase::open(&app_settings.database_settings.path).await?;
+    db.set_cache_capacity(Some(app_settings.database_settings.cache_size))
+        .await;
+    let limit = |limit: usize| Some(limit).filter(|&limit| limit > 0);
+    db.set_field_limits(FieldLimits {
+        title: limit(app_settings.database_settings.max_title_length),
+        description: limit(app_settings.database_settings.max_description_length),
+    });
+    db.set_insert_policy(app_settings.database_settings.duplicates);
+    db.set_tag_append_policy(app_settings.database_settings.tag_append.clone());
+    db.set_insert_batch_size(app_settings.database_settings.insert_batch_size);
+
+    if let Some(path) = import_calibre {
+        match db.import_calibre(&path).await {
+            Ok(ids) => println!("Imported {} books from {}", ids.len(), path.display()),
+            Err(e) => {
+                tracing::error!("Failed to import Calibre library: {:?}", e);
+                println!("Failed to import books from {}", path.display());
+            }
+        }
+    }
+
+    if repair {
+        let policy = if recreate_orphans {
+            RepairPolicy::Recreate
+        } else {
+            RepairPolicy::Delete
+        };
+        match db.repair(policy).await {
+            Ok(report) if report.rows() == 0 => println!("Found no orphaned rows"),
+            Ok(report) => {
+                println!(
+                    "Found {} orphaned variants, {} tags and {} covers",
+                    report.variants,
+                    report.named_tags + report.free_tags + report.multimap_tags,
+                    report.covers
+                );
+                match policy {
+                    RepairPolicy::Delete => println!("Deleted {} rows", report.rows()),
+                    RepairPolicy::Recreate => {
+                        println!("Recreated {} books", report.recreated.len())
+                    }
+                }
+            }
+            Err(e) => {
+                tracing::error!("Failed to repair the database: {:?}", e);
+                println!("Failed to repair the database");
+            }
+        }
+    }
+
+    if vacuum {
+        match db.enable_incremental_vacuum().await {
+            Ok(true) => println!("Rebuilt the database"),
+            Ok(false) => println!("The database does not need to be rebuilt"),
+            Err(e) => {
+                tracing::error!("Failed to rebuild the database: {:?}", e);
+                println!("Failed to rebuild the database");
+            }
+        }
+    }
+
+    #[cfg(feature = "opds")]
+    {
+        if let Some(addr) = serve {
+            println!("Serving the OPDS catalog at http://{}/opds", addr);
+            return Ok(bookworm_app::opds::OpdsServer::new(db).serve(addr).await?);
+        }
+    }
 
     let (mut app, mut receiver) = App::new(db);
+    app.set_variant_edit_policy(app_settings.database_settings.variant_edits);
+    app.set_rename_in_place(app_settings.database_settings.rename_in_place);
+    app.set_delete_empty_books(app_settings.database_settings.delete_empty_books);
+    app.set_format_order(app_settings.database_settings.format_order.clone());
+    app.set_safe_mode(safe);
     let mut placeholder_state = UIState {
         style: Default::default(),
         nav_settings: Default::default(),
         curr_command: Default::default(),
         selected_column: 0,
-        table_view: Default::default(),
+        table_view: Columns::new(vec![], interface_settings.unsearchable_columns.clone()),
+        truncation: interface_settings.truncation.clone(),
         book_view: app.new_book_view().await,
         sort_settings: Default::default(),
+        quick_add: false,
+        notification: None,
+        notifications: Default::default(),
+        covers: None,
+        placed_cover: None,
+        import: None,
     };
+    placeholder_state
+        .book_view
+        .set_normalization(interface_settings.search_normalization);
 
     tokio::spawn(async move {
         let _ = app.event_loop().await;
@@ -137,6 +276,30 @@ async fn main() -> Result<(), TuiError<<SQLiteDatabase as AppDatabase>::Error>>
                     ApplicationTask::SwitchView(AppView::Help(msg)) => println!("{}", msg),
                     _ => {}
                 }
+                // Progress is shown on a single line, which is rewritten as files are read.
+                let mut shown = false;
+                while placeholder_state.import.is_some() {
+                    match receiver.import_event().await {
+                        ImportEvent::Progress(progress) => {
+                            print!("\r{}", progress_message(&progress));
+                            stdout().flush()?;
+                            shown = true;
+                        }
+                        ImportEvent::Done(added) => {
+                            if shown {
+                                println!();
+                            }
+                            placeholder_state.import = None;
+                            books_added(&mut placeholder_state, &added).await?;
+                        }
+                    }
+                }
+                if let Some(notification) = placeholder_state.notification.take() {
+                    println!("{}", notification);
+                }
+                for notification in placeholder_state.notifications.drain() {
+                    println!("{}", notification.message);
+                }
             }
         }
     }
@@ -148,6 +311,9 @@ async fn main() -> Result<(), TuiError<<SQLiteDatabase as AppDatabase>::Error>>
         receiver,
         EventStream::new(),
         app_settings.sort_settings,
+        app_settings.database_settings,
+        app_settings.maintenance_settings,
+        app_settings.session_settings,
     )
     .await;
 
@@ -194,10 +360,7 @@ async fn main() -> Result<(), TuiError<<SQLiteDatabase as AppDatabase>::Error>>
 //  Splash screen
 //  New database button / screen
 //  Copy books to central directory: -c flag && set dir in settings.toml
-//  Add automatic date column?
 //  Convert format to media, convert book to something else
-//  Infinite undo redo (:u, :r)
-//  Pop-up notifications
 //  Documentation
 //  Testing
 //  Provide peeking access to text widgets
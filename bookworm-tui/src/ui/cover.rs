@@ -0,0 +1,217 @@
+use std::io::Write;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crossterm::cursor::{MoveTo, RestorePosition, SavePosition};
+use crossterm::style::Print;
+use crossterm::QueueableCommand;
+use image::imageops::FilterType;
+use image::RgbImage;
+use tui::buffer::Buffer;
+use tui::layout::Rect;
+use tui::style::Color;
+
+/// The largest width and height, in pixels, that covers are stored at.
+const MAX_COVER_SIZE: u32 = 512;
+/// The number of base64 characters sent in each chunk of a kitty graphics transmission.
+const KITTY_CHUNK_SIZE: usize = 4096;
+
+static NEXT_COVER_ID: AtomicU32 = AtomicU32::new(1);
+
+/// How covers are drawn in the terminal.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) enum CoverProtocol {
+    /// Covers are sent to the terminal as images, using the kitty graphics protocol.
+    Kitty,
+    /// Covers are drawn with half blocks, giving each cell two pixels. Terminals which only
+    /// support sixel graphics also use this.
+    Blocks,
+}
+
+impl CoverProtocol {
+    /// Returns the protocol which the current terminal supports.
+    pub(crate) fn detect() -> Self {
+        Self::detect_from(|name| std::env::var(name).ok())
+    }
+
+    /// Returns the protocol which the terminal described by the environment variables `var`
+    /// supports. Kitty and WezTerm both implement the kitty graphics protocol.
+    fn detect_from<F: Fn(&str) -> Option<String>>(var: F) -> Self {
+        let kitty = var("KITTY_WINDOW_ID").is_some()
+            || var("TERM").map_or(false, |term| term.contains("kitty"))
+            || var("TERM_PROGRAM").map_or(false, |program| program == "WezTerm");
+        if kitty {
+            CoverProtocol::Kitty
+        } else {
+            CoverProtocol::Blocks
+        }
+    }
+}
+
+/// A decoded cover image.
+pub(crate) struct Cover {
+    /// Identifies the image to the terminal when using the kitty graphics protocol.
+    id: u32,
+    image: RgbImage,
+}
+
+impl Cover {
+    /// Decodes the cover image in `bytes`, which is scaled down if it is larger than
+    /// `MAX_COVER_SIZE`. Returns None if the image can not be decoded.
+    pub(crate) fn decode(bytes: &[u8]) -> Option<Self> {
+        let image = image::load_from_memory(bytes).ok()?;
+        let image = if image.width().max(image.height()) > MAX_COVER_SIZE {
+            image.thumbnail(MAX_COVER_SIZE, MAX_COVER_SIZE)
+        } else {
+            image
+        };
+        Some(Cover {
+            id: NEXT_COVER_ID.fetch_add(1, Ordering::Relaxed),
+            image: image.to_rgb8(),
+        })
+    }
+
+    /// Returns the largest area in the top left corner of `area` which has the aspect ratio of
+    /// the cover. Cells are assumed to be twice as tall as they are wide.
+    pub(crate) fn fit(&self, area: Rect) -> Rect {
+        let (width, height) = self.image.dimensions();
+        if width == 0 || height == 0 {
+            return Rect::new(area.x, area.y, 0, 0);
+        }
+        let scale = f64::min(
+            f64::from(area.width) / f64::from(width),
+            f64::from(area.height) * 2. / f64::from(height),
+        );
+        Rect::new(
+            area.x,
+            area.y,
+            ((f64::from(width) * scale).round() as u16).min(area.width),
+            ((f64::from(height) * scale / 2.).round() as u16).min(area.height),
+        )
+    }
+
+    /// Returns the cover, scaled to fill `area` with two pixels per cell.
+    pub(crate) fn blocks(&self, area: Rect) -> CoverBlocks {
+        CoverBlocks(image::imageops::resize(
+            &self.image,
+            u32::from(area.width),
+            u32::from(area.height) * 2,
+            FilterType::Triangle,
+        ))
+    }
+
+    /// Sends the cover to the terminal using the kitty graphics protocol, so that it can be
+    /// placed with `place_kitty`. The image is sent as raw RGB data, in chunks.
+    pub(crate) fn transmit_kitty<W: Write>(&self, out: &mut W) -> std::io::Result<()> {
+        let (width, height) = self.image.dimensions();
+        let data = base64::encode(self.image.as_raw());
+        let chunks: Vec<_> = data.as_bytes().chunks(KITTY_CHUNK_SIZE).collect();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let more = u8::from(i + 1 < chunks.len());
+            let chunk = std::str::from_utf8(chunk).expect("base64 is always valid UTF-8");
+            let control = if i == 0 {
+                format!(
+                    "a=t,f=24,s={},v={},i={},q=2,m={}",
+                    width, height, self.id, more
+                )
+            } else {
+                format!("m={}", more)
+            };
+            out.queue(Print(format!("\x1b_G{};{}\x1b\\", control, chunk)))?;
+        }
+        Ok(())
+    }
+
+    /// Places the cover, which must have been sent with `transmit_kitty`, so that it fills
+    /// `area`.
+    pub(crate) fn place_kitty<W: Write>(&self, out: &mut W, area: Rect) -> std::io::Result<()> {
+        out.queue(SavePosition)?
+            .queue(MoveTo(area.x, area.y))?
+            .queue(Print(format!(
+                "\x1b_Ga=p,i={},c={},r={},C=1,q=2\x1b\\",
+                self.id, area.width, area.height
+            )))?
+            .queue(RestorePosition)?;
+        Ok(())
+    }
+
+    /// Returns the ID of the cover, which identifies it to the terminal.
+    pub(crate) fn id(&self) -> u32 {
+        self.id
+    }
+}
+
+/// Removes every cover placed with the kitty graphics protocol from the screen.
+pub(crate) fn clear_kitty<W: Write>(out: &mut W) -> std::io::Result<()> {
+    out.queue(Print("\x1b_Ga=d,d=a,q=2\x1b\\"))?;
+    Ok(())
+}
+
+/// Frees the image with the given ID, which was sent with the kitty graphics protocol.
+pub(crate) fn delete_kitty<W: Write>(out: &mut W, id: u32) -> std::io::Result<()> {
+    out.queue(Print(format!("\x1b_Ga=d,d=I,i={},q=2\x1b\\", id)))?;
+    Ok(())
+}
+
+/// A cover which is drawn with the upper half block character, using the foreground color for
+/// the upper pixel and the background color for the lower pixel of each cell.
+pub(crate) struct CoverBlocks(RgbImage);
+
+impl tui::widgets::Widget for &CoverBlocks {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let (width, height) = self.0.dimensions();
+        for y in 0..area.height.min((height / 2) as u16) {
+            for x in 0..area.width.min(width as u16) {
+                let [r, g, b] = self.0.get_pixel(u32::from(x), u32::from(y) * 2).0;
+                let [br, bg, bb] = self.0.get_pixel(u32::from(x), u32::from(y) * 2 + 1).0;
+                buf.get_mut(area.x + x, area.y + y)
+                    .set_symbol("▀")
+                    .set_fg(Color::Rgb(r, g, b))
+                    .set_bg(Color::Rgb(br, bg, bb));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use image::Rgb;
+
+    #[test]
+    fn test_cover_blocks() {
+        let mut image = RgbImage::new(2, 4);
+        image.put_pixel(0, 0, Rgb([255, 0, 0]));
+        image.put_pixel(0, 1, Rgb([0, 0, 255]));
+        let cover = Cover { id: 1, image };
+
+        // Cells are twice as tall as they are wide, so a 1:2 cover fills a 2x2 area.
+        assert_eq!(cover.fit(Rect::new(1, 1, 10, 2)), Rect::new(1, 1, 2, 2));
+        assert_eq!(cover.fit(Rect::new(0, 0, 1, 10)), Rect::new(0, 0, 1, 1));
+
+        let area = Rect::new(0, 0, 2, 2);
+        let mut buf = Buffer::empty(area);
+        tui::widgets::Widget::render(&cover.blocks(area), area, &mut buf);
+        let cell = buf.get(0, 0);
+        assert_eq!(cell.symbol, "▀");
+        assert_eq!(cell.fg, Color::Rgb(255, 0, 0));
+        assert_eq!(cell.bg, Color::Rgb(0, 0, 255));
+        assert_eq!(buf.get(1, 1).fg, Color::Rgb(0, 0, 0));
+    }
+
+    #[test]
+    fn test_detect_protocol() {
+        let detect = |vars: &[(&str, &str)]| {
+            CoverProtocol::detect_from(|name| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| value.to_string())
+            })
+        };
+        assert_eq!(detect(&[("TERM", "xterm-kitty")]), CoverProtocol::Kitty);
+        assert_eq!(detect(&[("KITTY_WINDOW_ID", "1")]), CoverProtocol::Kitty);
+        assert_eq!(detect(&[("TERM_PROGRAM", "WezTerm")]), CoverProtocol::Kitty);
+        assert_eq!(detect(&[("TERM", "xterm-256color")]), CoverProtocol::Blocks);
+        assert_eq!(detect(&[]), CoverProtocol::Blocks);
+    }
+}
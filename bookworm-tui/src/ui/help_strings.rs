@@ -12,6 +12,8 @@ ARGUMENTS:
 (FLAG? <string>+)+:
 
 <string>+: A string to fetch books from, according to the flag. If no flag set, treats the string as a file.
+
+When a single directory is given, the number of files read is shown until every book has been added.
 "#;
 
 pub const WRITE_FILE_HELP_STRING: &str = r#"USAGE:
@@ -31,7 +33,7 @@ pub const DELETE_HELP_STRING: &str = r#"USAGE:
 
 FLAGS:
 -a: Specifies that everything should be deleted.
--r: Uses <match> as a regular expression.
+-r / --regex: Uses <match> as a regular expression, which is matched case-sensitively.
 -e: Uses <match> as an exact substring.
 -x: Uses <match> as an exact string.
 
@@ -59,6 +61,11 @@ FLAG: A flag describing what should happen to <column>. If no flag is specified,
 <new_value>: Required if no flag, or -a, is specified.
 <new_tag_value>: If no flag, or -a is specified, and <column> is 'tag', <new_tag_value> either
 replaces, or is appended to the preexisting tag with value <new_value>.
+
+Columns starting with '#' are multimap tags, which can have several values, such as #author.
+With -r or -a, a single value replaces or is added to all values of the tag, while two values
+replace or append to the first value only (eg. :e -r #author "Frank Herbrt" "Frank Herbert").
+With -d, all values are deleted, unless a value to delete is given.
 "#;
 
 pub const MERGE_HELP_STRING: &str = r#"USAGE:
@@ -68,6 +75,17 @@ FLAGS:
 -a: Specifies that all books should be merged.
 "#;
 
+pub const DUPLICATES_HELP_STRING: &str = r#"USAGE:
+:dups <strategy>?: Lists groups of books which are duplicates of each other, without merging
+them. The first book of each group is the one which the others would be merged into.
+
+ARGUMENTS:
+<strategy>: (Optional) How duplicates are found. One of:
+    hash: Books with a variant with the same file size and hash (default).
+    author: Books with the same title, ignoring case, which share an author.
+    title: Books with the same title, ignoring case.
+"#;
+
 pub const COLUMN_HELP_STRING: &str = r#"USAGE:
 :c: Add or remove columns from the UI.
 
@@ -75,6 +93,8 @@ ARGUMENTS:
 (-?<column>)+: The column of interest.
 If in the form -<column>, column will be removed.
 If in the form <column>, column will be added.
+Removing a column only hides it - books can still be filtered by it. Columns listed under
+unsearchable in the [layout] section of the settings can not be filtered by.
 "#;
 
 pub const SORT_HELP_STRING: &str = r#"USAGE:
@@ -84,7 +104,9 @@ FLAGS:
 -d: Sort descending.
 
 ARGUMENTS:
-(<-d>? <column>)+: Sort by column. If -d specified, sort column descending
+(<-d>? <column>)+: Sort by column. If -d specified, sort column descending. Columns starting
+with # are tags which may have several values, such as #genre - books are sorted by the first
+value in alphabetical order.
 "#;
 
 pub const SEARCH_HELP_STRING: &str = r#"USAGE:
@@ -92,7 +114,36 @@ pub const SEARCH_HELP_STRING: &str = r#"USAGE:
 to leave a particular search result page, press ESC.
 
 FLAGS:
--r: Uses <match> as a regular expression.
+-r / --regex: Uses <match> as a regular expression, which is matched case-sensitively.
+-e: Uses <match> as an exact substring.
+-x: Uses <match> as an exact string.
+-empty <column>+: Matches books where each <column> is empty - eg. `-empty cover` finds
+books without a stored cover.
+-series: Matches books which belong to any series.
+
+ARGUMENTS:
+(FLAG? <column> <match>)+:
+FLAG: A flag describing how to use <match>. If none is provided, uses fuzzy search.
+<column>: The column to match. If <column> is `ext`, matches books with a variant whose file
+extension is <match> - eg. `:f ext pdf`. If <column> is `series`, matches books in the series
+named <match>. If <column> is `size`, matches books with a variant whose file size is in the
+range <match> - eg. `:f size 1MB..20MB`. Either bound of the range may be omitted. KiB, MiB and
+GiB are binary units, while KB, MB and GB are decimal unless `size_units` is set to `binary`.
+<match>: The value to match on. Text is compared exactly, unless `search_normalization` is set to
+`case-insensitive`, or to `unaccented` to also ignore accents (eg. "bronte" matches "Brontë").
+
+EXPRESSIONS:
+<column>:<match> (AND? <column>:<match>)*: Finds books matching every term, where each term is
+searched for like `<column> <match>` - eg. `:f author:Tolkien AND series:"Lord of the Rings"`.
+Values containing spaces must be quoted. OR and NOT are not supported yet.
+"#;
+
+pub const TEMP_SEARCH_HELP_STRING: &str = r#"USAGE:
+:tf: Finds all books matching the given predicates, like :f. The filter is temporary - it is never
+included in the persisted session, so a restored session starts without it.
+
+FLAGS:
+-r / --regex: Uses <match> as a regular expression, which is matched case-sensitively.
 -e: Uses <match> as an exact substring.
 -x: Uses <match> as an exact string.
 
@@ -107,7 +158,24 @@ pub const JUMP_HELP_STRING: &str = r#"USAGE:
 :j: Jumps to the first book matching the given predicate.
 
 FLAGS:
--r: Uses <match> as a regular expression.
+-r / --regex: Uses <match> as a regular expression, which is matched case-sensitively.
+-e: Uses <match> as an exact substring.
+-x: Uses <match> as an exact string.
+
+ARGUMENTS:
+(FLAG? <column> <match>)+:
+FLAG: A flag describing how to use <match>. If none is provided, uses fuzzy search.
+<column>: The column to match
+<match>: The value to match on
+"#;
+
+pub const HIGHLIGHT_HELP_STRING: &str = r#"USAGE:
+:hl: Highlights all books matching the given predicates, without hiding books which do not match,
+and selects the first highlighted book. Use :n / :N (or n / N when the book table is focused) to
+move between highlighted books. To clear the highlight, press ESC.
+
+FLAGS:
+-r / --regex: Uses <match> as a regular expression, which is matched case-sensitively.
 -e: Uses <match> as an exact substring.
 -x: Uses <match> as an exact string.
 
@@ -118,17 +186,201 @@ FLAG: A flag describing how to use <match>. If none is provided, uses fuzzy sear
 <match>: The value to match on
 "#;
 
+pub const NEXT_MATCH_HELP_STRING: &str = r#"USAGE:
+:n: Selects the next highlighted book, wrapping around to the first highlighted book.
+:N: Selects the previous highlighted book, wrapping around to the last highlighted book.
+"#;
+
+pub const IMPORT_HELP_STRING: &str = r#"USAGE:
+:import tags: Appends tags to books, using the mapping in the specified file.
+:import db: Copies every book in the specified database, along with its variants, series and
+tags, into the current database. Books which share a variant with an existing book are skipped.
+
+FLAGS (:import tags only):
+-d <delimiter>: The character separating keys and tags (eg. ";" or ","). Defaults to tabs.
+-e <encoding>: The text encoding of the file (eg. latin1 or utf-16le). Defaults to UTF-8.
+
+ARGUMENTS:
+<file>: For :import tags, a file where each line contains a key and one or more tags, separated by the delimiter.
+Keys of the form <column>=<value> select books where <column> is exactly <value>.
+Other keys select books with exactly the given title. Lines starting with # are ignored.
+For :import db, the path to a bookworm database, which is opened read-only.
+"#;
+
+pub const VARIANT_HELP_STRING: &str = r#"USAGE:
+:variant primary: Makes the specified variant the preferred variant of the book, which is used
+to represent the book.
+
+ARGUMENTS:
+<index>: The index of the variant to prefer.
+<book>: (Optional) The book to modify. If not specified, modifies the selected book.
+"#;
+
+pub const AUTHORS_HELP_STRING: &str = r#"USAGE:
+:authors dedupe [-y]?: Consolidates author names which only differ in case or whitespace
+(eg. "J.R.R. Tolkien" and "J. R. R. Tolkien") to their most common spelling, across all books.
+Names which also differ in punctuation (eg. "JRR Tolkien") may be different authors, so they are
+only reported, unless confirmed.
+
+ARGUMENTS:
+-y: Confirms that names which only differ in punctuation should also be consolidated.
+"#;
+
+pub const EXPORT_HELP_STRING: &str = r#"USAGE:
+:export view <format> <path>: Exports the books in the current view, in the current sort order.
+:export [format] <path>: Exports every book in the database. If no format is provided, the format
+is chosen by the extension of the path, defaulting to json.
+
+ARGUMENTS:
+<format>: The format to export to - either csv or json. CSV files contain the id, title, authors,
+series, description, tags and paths of each book, while JSON files also contain named tags.
+<path>: The file to write the exported books to.
+
+FLAGS (csv only):
+-d <delimiter>: The character separating fields (eg. ";" or tab). Defaults to ",".
+-e <encoding>: The text encoding of the file (eg. latin1 or utf-16le). Defaults to UTF-8.
+"#;
+
+pub const STATS_HELP_STRING: &str = r#"USAGE:
+:stats: Summarizes the library - the total size of all variants, the number of books without a
+cover, title, authors or description, and the number of books by each author, in each series and
+in each language.
+:stats <column>: Shows the number of books with each value of the column, as a bar chart.
+:stats reading [path]: Shows the number of books added and finished in each month, read from the
+date each book was added and its finished tag (eg. 2021-03-14). If a path is provided, the counts
+are written to it as CSV instead.
+
+ARGUMENTS:
+<column>: The column to group books by (eg. series, tag, variants, or a named tag).
+[path]: The CSV file to write the monthly counts to.
+
+FLAGS (:stats reading <path> only):
+-d <delimiter>: The character separating fields (eg. ";" or tab). Defaults to ",".
+-e <encoding>: The text encoding of the file (eg. latin1 or utf-16le). Defaults to UTF-8.
+"#;
+
+pub const SERIES_HELP_STRING: &str = r#"USAGE:
+:series from-filename <pattern>: Sets the series, series index and title of each selected book by
+reading the file name of its preferred variant with the pattern. Books with file names which do
+not match the pattern are not modified.
+
+ARGUMENTS:
+<pattern>: The file name without its extension, where the fields {series}, {index} and {title}
+stand for the values to read (eg. "{series} - {index} - {title}").
+"#;
+
+pub const TAGS_HELP_STRING: &str = r#"USAGE:
+:tags clear-all: Removes all tags from the selected books, including free tags, named tags and
+tags with multiple values. Authors and titles are kept. This can not be undone, so the command
+must be confirmed.
+:tag rename <old> <new>: Renames a tag in every book - free tags with the value <old>, and named
+tags and tags with multiple values called <old>. Books which already have <new> keep their value.
+:tag delete <name>: Removes a tag from every book. This can not be undone, so the command must be
+confirmed.
+
+FLAGS:
+-y: Confirms that the tags should be removed.
+
+ARGUMENTS:
+<old>: The tag to rename.
+<new>: The new name of the tag.
+<name>: The tag to remove.
+"#;
+
+pub const DIFF_HELP_STRING: &str = r#"USAGE:
+:diff <id>: Shows the selected book and the book with the given ID side by side, one column per
+row. Columns with different values are highlighted.
+
+ARGUMENTS:
+<id>: The ID of the book to compare against.
+"#;
+
+pub const FTS_HELP_STRING: &str = r#"USAGE:
+:fts rebuild: Rebuilds the full-text index of book titles, authors and descriptions, and checks
+that every book is indexed. Reports how long rebuilding took. Use this if :find misses books
+which exist.
+"#;
+
+pub const RESCAN_HELP_STRING: &str = r#"USAGE:
+:rescan: Reads the preferred variant of each selected book again, and fills in an empty title,
+authors or description with the values read from the file.
+
+FLAGS:
+--overwrite: Replaces the title, authors and description with the values read from the file,
+discarding manual edits. This can not be undone, so the command must be confirmed.
+-y: Confirms that existing values should be overwritten.
+"#;
+
+pub const REFRESH_HELP_STRING: &str = r#"USAGE:
+:refresh: Reads the files of the selected books again, and picks up changes to files which were
+modified since they were added. The title, authors and description of each book are updated from
+its modified files. Titles are only replaced if they changed in the file, and tags are kept.
+Unlike :rescan, books with unmodified files are left as they are.
+"#;
+
+pub const FETCH_HELP_STRING: &str = r#"USAGE:
+:fetch: Looks up each selected book with an ISBN on Open Library, and fills in an empty title,
+authors, description or cover with the values found. Requires bookworm to be built with the fetch
+feature.
+
+FLAGS:
+--overwrite: Replaces the title, authors, description and cover with the values found.
+"#;
+
+pub const FIND_HELP_STRING: &str = r#"USAGE:
+:find <text>: Finds all books whose title, authors or description contain a word starting with
+each word of the text, ignoring case. Uses the full-text index, which requires SQLite to be built
+with FTS5 - use :f if it is not available.
+
+ARGUMENTS:
+<text>: The words to search for.
+"#;
+
+pub const VARIANTS_HELP_STRING: &str = r#"USAGE:
+:variants: Lists each variant of the selected book, with its path, format, size and the start of
+its hash. Also shows whether the file of each variant exists, and whether it still matches the
+size and hash recorded when it was added.
+"#;
+
+pub const UNDO_HELP_STRING: &str = r#"USAGE:
+:u: Undoes the most recent edit or deletion. Deleted books are restored with their original IDs,
+but without their covers.
+:r: Redoes the most recently undone edit or deletion. Changes can no longer be redone after
+another edit or deletion.
+"#;
+
 pub const OPEN_HELP_STRING: &str = r#"USAGE:
 :o: Open the specified value.
+:o with <app>: Open the specified value with the given application.
 
 FLAGS:
 -f: Open the book in the native file explorer. Windows only.
 
 ARGUMENTS:
+<app>: The application to open the book with, and any arguments it requires.
 <book>: (Optional) The book to open. If not specified, opens the selected item.
 <index>: (Optional) The index of the variant to open.
 "#;
 
+pub const FOLDER_HELP_STRING: &str = r#"USAGE:
+:folder: Open the directory which contains the specified book in the file manager. If the book
+has several variants, the directory of the preferred variant is opened.
+
+ARGUMENTS:
+<book>: (Optional) The book to open the directory of. If not specified, uses the selected item.
+"#;
+
+pub const READ_HELP_STRING: &str = r#"USAGE:
+:read: Mark the specified book as read.
+:unread: Mark the specified book as unread.
+
+The read status is kept in the "read" column, which holds one of "yes", "no" or "reading", and can
+be edited, sorted and searched like other columns (eg. `:e read reading`, `:f read yes`).
+
+ARGUMENTS:
+<book>: (Optional) The book to mark. If not specified, marks the selected items.
+"#;
+
 pub const HELP_HELP_STRING: &str = r#"USAGE:
 :h: Show the help string for the specified command.
 
@@ -141,10 +393,33 @@ ARGUMENTS:
     :d: Delete the specified item(s).
     :e: Edit the specified item.
     :m: Merge the specified books.
+    :dups: Lists books which are duplicates of each other, without merging them.
     :s: Sort the specified column.
     :c: Add or remove columns from the UI.
     :f: Finds all books with the specified value.
+    :tf: Temporarily finds all books with the specified value.
+    :find: Finds all books containing the specified words.
+    :hl: Highlights all books with the specified value.
+    :n: Selects the next highlighted book.
+    :N: Selects the previous highlighted book.
+    :import: Appends tags to books using a mapping file, or copies books from another database.
+    :variant: Sets the preferred variant of a book.
+    :authors: Consolidates spelling variants of authors.
+    :export: Exports the books in the current view, or every book in the database.
+    :stats: Summarizes the library, or shows a breakdown of books by a column or by month.
+    :series: Sets the series of the selected books from their file names.
+    :tags: Removes all tags from the selected books, or renames or removes a tag in every book.
+    :diff: Compares the selected book with another book.
+    :fts: Rebuilds the full-text index.
+    :rescan: Reads the metadata of the selected books from their files again.
+    :refresh: Updates the selected books from their files, if the files were modified.
+    :fetch: Fills in the metadata of the selected books from Open Library, by ISBN.
+    :variants: Lists the variants of the selected book and checks their files.
+    :u: Undoes the most recent edit or deletion.
+    :r: Redoes the most recently undone edit or deletion.
     :o: Open the specified value.
+    :folder: Open the directory which contains the specified book.
+    :read / :unread: Marks the specified book as read or unread.
     :h: Find the help string for the specified command.
 "#;
 
@@ -160,10 +435,33 @@ COMMANDS:
 :d: Delete the specified item(s).
 :e: Edit the specified item.
 :m: Merge the specified books.
+:dups: Lists books which are duplicates of each other, without merging them.
 :s: Sort the specified column.
 :c: Add or remove columns from the UI.
 :f: Finds all books with the specified value.
+:tf: Temporarily finds all books with the specified value.
+:find: Finds all books containing the specified words.
+:hl: Highlights all books with the specified value.
+:n: Selects the next highlighted book.
+:N: Selects the previous highlighted book.
+:import: Appends tags to books using a mapping file, or copies books from another database.
+:variant: Sets the preferred variant of a book.
+:authors: Consolidates spelling variants of authors.
+:export: Exports the books in the current view, or every book in the database.
+:stats: Summarizes the library, or shows a breakdown of books by a column or by month.
+:series: Sets the series of the selected books from their file names.
+:tags: Removes all tags from the selected books, or renames or removes a tag in every book.
+:diff: Compares the selected book with another book.
+:fts: Rebuilds the full-text index.
+:rescan: Reads the metadata of the selected books from their files again.
+:refresh: Updates the selected books from their files, if the files were modified.
+:fetch: Fills in the metadata of the selected books from Open Library, by ISBN.
+:variants: Lists the variants of the selected book and checks their files.
+:u: Undoes the most recent edit or deletion.
+:r: Redoes the most recently undone edit or deletion.
 :o: Open the specified value.
+:folder: Open the directory which contains the specified book.
+:read / :unread: Marks the specified book as read or unread.
 :h: Find the help string for the specified command.
 "#;
 
@@ -176,11 +474,32 @@ pub fn help_strings(command: &str) -> Option<&'static str> {
         ":d" => Some(DELETE_HELP_STRING),
         ":e" => Some(EDIT_HELP_STRING),
         ":m" => Some(MERGE_HELP_STRING),
+        ":dups" => Some(DUPLICATES_HELP_STRING),
         ":s" => Some(SORT_HELP_STRING),
         ":c" => Some(COLUMN_HELP_STRING),
         ":f" => Some(SEARCH_HELP_STRING),
+        ":tf" => Some(TEMP_SEARCH_HELP_STRING),
+        ":find" => Some(FIND_HELP_STRING),
         ":j" => Some(JUMP_HELP_STRING),
-        ":o" => Some(OPEN_HELP_STRING),
+        ":hl" => Some(HIGHLIGHT_HELP_STRING),
+        ":n" | ":N" => Some(NEXT_MATCH_HELP_STRING),
+        ":import" => Some(IMPORT_HELP_STRING),
+        ":variant" => Some(VARIANT_HELP_STRING),
+        ":authors" => Some(AUTHORS_HELP_STRING),
+        ":export" => Some(EXPORT_HELP_STRING),
+        ":stats" => Some(STATS_HELP_STRING),
+        ":series" => Some(SERIES_HELP_STRING),
+        ":tags" | ":tag" => Some(TAGS_HELP_STRING),
+        ":diff" => Some(DIFF_HELP_STRING),
+        ":fts" => Some(FTS_HELP_STRING),
+        ":rescan" => Some(RESCAN_HELP_STRING),
+        ":refresh" => Some(REFRESH_HELP_STRING),
+        ":fetch" => Some(FETCH_HELP_STRING),
+        ":variants" => Some(VARIANTS_HELP_STRING),
+        ":u" | ":r" => Some(UNDO_HELP_STRING),
+        ":o" | ":open" => Some(OPEN_HELP_STRING),
+        ":folder" => Some(FOLDER_HELP_STRING),
+        ":read" | ":unread" => Some(READ_HELP_STRING),
         ":h" => Some(HELP_HELP_STRING),
         _ => None,
     }
@@ -1,7 +1,9 @@
 pub(crate) use terminal_ui::{AppInterface, TuiError};
 
+mod cover;
 pub(crate) mod help_strings;
 pub(crate) mod layouts;
+pub(crate) mod notifications;
 mod scrollable_text;
 pub(crate) mod terminal_ui;
 mod tui_widgets;
@@ -0,0 +1,250 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use tui::buffer::Buffer;
+use tui::layout::Rect;
+use tui::style::{Color, Style};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, Borders, Clear, Paragraph, Widget};
+use unicode_truncate::UnicodeTruncateStr;
+
+use bookworm_app::app::Progress;
+
+use crate::ui::utils::progress_message;
+
+/// The most notifications which are kept at once - older notifications are dropped first.
+const MAX_NOTIFICATIONS: usize = 16;
+/// The most notifications which are shown at once.
+const SHOWN_NOTIFICATIONS: usize = 3;
+/// The widest that the notification overlay may be, including its border.
+const MAX_WIDTH: u16 = 60;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    /// Returns how long notifications of this severity are shown for. Errors stay for longer,
+    /// so that they are not missed.
+    fn timeout(self) -> Duration {
+        match self {
+            Severity::Info => Duration::from_secs(4),
+            Severity::Warning => Duration::from_secs(6),
+            Severity::Error => Duration::from_secs(10),
+        }
+    }
+
+    fn style(self) -> Style {
+        match self {
+            Severity::Info => Style::default(),
+            Severity::Warning => Style::default().fg(Color::Yellow),
+            Severity::Error => Style::default().fg(Color::Red),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Notification {
+    pub(crate) message: String,
+    pub(crate) severity: Severity,
+    /// When the notification is dismissed.
+    pub(crate) expiry: Instant,
+}
+
+/// A queue of transient notifications, from the oldest to the newest.
+#[derive(Default)]
+pub(crate) struct Notifications {
+    queue: VecDeque<Notification>,
+}
+
+impl Notifications {
+    /// Adds a notification, which is dismissed once the timeout of its severity passes.
+    pub(crate) fn push<S: Into<String>>(&mut self, message: S, severity: Severity) {
+        if self.queue.len() == MAX_NOTIFICATIONS {
+            self.queue.pop_front();
+        }
+        self.queue.push_back(Notification {
+            message: message.into(),
+            severity,
+            expiry: Instant::now() + severity.timeout(),
+        });
+    }
+
+    pub(crate) fn info<S: Into<String>>(&mut self, message: S) {
+        self.push(message, Severity::Info);
+    }
+
+    pub(crate) fn warning<S: Into<String>>(&mut self, message: S) {
+        self.push(message, Severity::Warning);
+    }
+
+    pub(crate) fn error<S: Into<String>>(&mut self, message: S) {
+        self.push(message, Severity::Error);
+    }
+
+    /// Removes the notifications which expire before `now`, and returns whether any were
+    /// removed.
+    pub(crate) fn expire(&mut self, now: Instant) -> bool {
+        let len = self.queue.len();
+        self.queue.retain(|notification| notification.expiry > now);
+        self.queue.len() != len
+    }
+
+    /// Returns the newest notifications, from the newest to the oldest.
+    pub(crate) fn newest(&self, count: usize) -> impl Iterator<Item = &Notification> {
+        self.queue.iter().rev().take(count)
+    }
+
+    /// Removes and returns every notification, from the oldest to the newest.
+    pub(crate) fn drain(&mut self) -> impl Iterator<Item = Notification> + '_ {
+        self.queue.drain(..)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
+
+/// Draws the newest notifications over the top right corner of the area, one per line.
+pub(crate) struct NotificationOverlay<'a>(pub(crate) &'a Notifications);
+
+impl<'a> Widget for NotificationOverlay<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if self.0.is_empty() || area.width < 6 || area.height < 3 {
+            return;
+        }
+        let width = area.width.min(MAX_WIDTH);
+        let text_width = usize::from(width - 2);
+        let lines: Vec<_> = self
+            .0
+            .newest(SHOWN_NOTIFICATIONS)
+            .take(usize::from(area.height - 2))
+            .map(|notification| {
+                let (message, _) = notification.message.unicode_truncate(text_width);
+                Spans::from(Span::styled(
+                    message.to_string(),
+                    notification.severity.style(),
+                ))
+            })
+            .collect();
+        let overlay = Rect::new(area.right() - width, area.y, width, lines.len() as u16 + 2);
+        Clear.render(overlay, buf);
+        Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL))
+            .render(overlay, buf);
+    }
+}
+
+/// Draws the progress of adding a directory over the top right corner of the area: how many
+/// files have been read, a bar filled in proportion, and the name of the last file read.
+pub(crate) struct ProgressOverlay<'a>(pub(crate) &'a Progress);
+
+impl<'a> Widget for ProgressOverlay<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if area.width < 6 || area.height < 5 {
+            return;
+        }
+        let width = area.width.min(MAX_WIDTH);
+        let text_width = usize::from(width - 2);
+        let filled = match self.0.total {
+            0 => 0,
+            total => text_width * self.0.processed.min(total) / total,
+        };
+        let current = self
+            .0
+            .current
+            .as_ref()
+            .and_then(|path| path.file_name())
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        let lines = vec![
+            Spans::from(progress_message(self.0)),
+            Spans::from(format!(
+                "{}{}",
+                "█".repeat(filled),
+                "░".repeat(text_width - filled)
+            )),
+            Spans::from(current.unicode_truncate(text_width).0.to_string()),
+        ];
+        let overlay = Rect::new(area.right() - width, area.y, width, lines.len() as u16 + 2);
+        Clear.render(overlay, buf);
+        Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Adding books (Esc to stop)"),
+            )
+            .render(overlay, buf);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_notifications() {
+        let mut notifications = Notifications::default();
+        for i in 0..MAX_NOTIFICATIONS + 2 {
+            notifications.info(format!("Added {} books", i));
+        }
+        notifications.error("Failed to delete books");
+        let newest: Vec<_> = notifications
+            .newest(2)
+            .map(|notification| notification.message.as_str())
+            .collect();
+        assert_eq!(newest, vec!["Failed to delete books", "Added 17 books"]);
+        assert_eq!(notifications.newest(usize::MAX).count(), MAX_NOTIFICATIONS);
+
+        // Errors outlive other notifications.
+        assert!(!notifications.expire(Instant::now()));
+        assert!(notifications.expire(Instant::now() + Duration::from_secs(5)));
+        assert_eq!(notifications.newest(usize::MAX).count(), 1);
+        assert!(notifications.expire(Instant::now() + Duration::from_secs(11)));
+        assert!(notifications.is_empty());
+
+        notifications.warning("No books were added");
+        assert_eq!(notifications.drain().count(), 1);
+        assert!(notifications.is_empty());
+    }
+
+    #[test]
+    fn test_notification_overlay() {
+        let mut notifications = Notifications::default();
+        notifications.warning("No books were added");
+        let area = Rect::new(0, 0, 30, 10);
+        let mut buf = Buffer::empty(area);
+        NotificationOverlay(&notifications).render(area, &mut buf);
+
+        let line = |y: u16| -> String { (0..30).map(|x| buf.get(x, y).symbol.clone()).collect() };
+        assert_eq!(line(0), format!("┌{}┐", "─".repeat(28)));
+        assert_eq!(line(1), format!("│No books were added{}│", " ".repeat(9)));
+        assert_eq!(buf.get(1, 1).fg, Color::Yellow);
+        assert_eq!(line(3), " ".repeat(30));
+    }
+
+    #[test]
+    fn test_progress_overlay() {
+        let progress = Progress {
+            processed: 1,
+            total: 4,
+            current: Some("books/dune.epub".into()),
+        };
+        let area = Rect::new(0, 0, 30, 10);
+        let mut buf = Buffer::empty(area);
+        ProgressOverlay(&progress).render(area, &mut buf);
+
+        let line = |y: u16| -> String { (0..30).map(|x| buf.get(x, y).symbol.clone()).collect() };
+        assert_eq!(
+            line(0),
+            format!("┌Adding books (Esc to stop){}┐", "─".repeat(2))
+        );
+        assert_eq!(line(1), format!("│Read 1 of 4 files{}│", " ".repeat(11)));
+        assert_eq!(line(2), format!("│{}{}│", "█".repeat(7), "░".repeat(21)));
+        assert_eq!(line(3), format!("│dune.epub{}│", " ".repeat(19)));
+        assert_eq!(line(4), format!("└{}┘", "─".repeat(28)));
+    }
+}
@@ -1,6 +1,8 @@
+use std::io::Write;
 use std::marker::PhantomData;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Instant;
 
 use crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyModifiers};
 
@@ -8,12 +10,14 @@ use futures::{future::FutureExt, StreamExt};
 use tokio::time::{timeout, Duration};
 
 use tui::backend::Backend;
+use tui::layout::Rect;
 use tui::Terminal;
 
-use bookworm_app::app::AppChannel;
+use bookworm_app::app::{AppChannel, ImportEvent, Progress};
 use bookworm_app::columns::Columns;
 use bookworm_app::settings::{
-    DatabaseSettings, InterfaceSettings, InterfaceStyle, NavigationSettings, Settings, SortSettings,
+    DatabaseSettings, InterfaceSettings, InterfaceStyle, MaintenanceSettings, NavigationSettings,
+    SessionSettings, Settings, SortSettings,
 };
 use bookworm_app::ApplicationError;
 use bookworm_database::bookview::BookViewError;
@@ -21,11 +25,14 @@ use bookworm_database::{AppDatabase, BookView, DatabaseError};
 use bookworm_input::user_input::{CommandString, CommandStringError, InputRecorder};
 use bookworm_records::Book;
 
-use crate::ui::layouts::EditLayout;
+use crate::ui::cover::{self, Cover, CoverProtocol};
+use crate::ui::layouts::{ColumnBookLayout, EditLayout};
+use crate::ui::notifications::{NotificationOverlay, Notifications, ProgressOverlay};
 use crate::ui::scrollable_text::ScrollableText;
-use crate::ui::utils::{AppView, ApplicationTask};
+use crate::ui::utils::{books_added, restore_filters, AppView, ApplicationTask};
 use crate::ui::widgets::{
-    BorderWidget, ColumnWidget, CommandWidget, EditWidget, HelpWidget, Widget, WidgetBox,
+    BookWidget, BorderWidget, ColumnWidget, CommandWidget, DiffWidget, EditWidget, FindWidget,
+    HelpWidget, StatsWidget, Widget, WidgetBox,
 };
 #[derive(Debug)]
 pub(crate) enum TuiError<DBError> {
@@ -36,6 +43,19 @@ pub(crate) enum TuiError<DBError> {
     CommandString(CommandStringError),
 }
 
+impl<DBError: std::fmt::Debug> TuiError<DBError> {
+    /// Returns a short description of the error, to show to the user.
+    pub(crate) fn describe(&self) -> String {
+        match self {
+            TuiError::Io(e) => e.to_string(),
+            TuiError::Application(e) => format!("{:?}", e),
+            TuiError::Database(e) => format!("{:?}", e),
+            TuiError::BookView(e) => format!("{:?}", e),
+            TuiError::CommandString(e) => format!("{:?}", e),
+        }
+    }
+}
+
 impl<DBError> From<ApplicationError<DBError>> for TuiError<DBError> {
     fn from(e: ApplicationError<DBError>) -> Self {
         TuiError::Application(e)
@@ -72,8 +92,23 @@ pub(crate) struct UIState<D: AppDatabase + Send + Sync + 'static> {
     pub(crate) curr_command: CommandString,
     pub(crate) selected_column: usize,
     pub(crate) table_view: Columns,
+    /// The indicator appended to cells which are too long to fit in their column.
+    pub(crate) truncation: String,
     pub(crate) book_view: BookView<D>,
     pub(crate) sort_settings: SortSettings,
+    /// Whether the command widget is currently reading a path to quick add.
+    pub(crate) quick_add: bool,
+    /// A message to display in the command widget until the next key press.
+    pub(crate) notification: Option<String>,
+    /// Notifications which are shown over the current view until they expire.
+    pub(crate) notifications: Notifications,
+    /// How covers are drawn, or None if the selected book is not shown beside the table.
+    pub(crate) covers: Option<CoverProtocol>,
+    /// The cover to place over the frame once it is drawn, when covers are drawn as images.
+    pub(crate) placed_cover: Option<(Rect, Arc<Cover>)>,
+    /// The progress of the directory being added, if any. Commands can not be run until the
+    /// directory has been added.
+    pub(crate) import: Option<Progress>,
     // pub(crate) command_log: Vec<CommandString>,
 }
 
@@ -115,11 +150,38 @@ pub(crate) struct AppInterface<D: AppDatabase + Send + Sync + 'static, B: Backen
     ui_state: UIState<D>,
     update_tui: bool,
     settings_path: Option<PathBuf>,
+    database_settings: DatabaseSettings,
+    maintenance_settings: MaintenanceSettings,
     event_receiver: EventStream,
     app_channel: AppChannel<D>,
+    /// Whether to quit once the directory being added has been added.
+    quit_after_import: bool,
+    /// The ID of the cover which was last sent to the terminal as an image.
+    transmitted_cover: Option<u32>,
     lifetime_marker: PhantomData<B>,
 }
 
+/// Returns the view of the table, with the command line below it, and the selected book beside it
+/// if `show_book` is true.
+fn columns_view<D: AppDatabase + Send + Sync, B: 'static + Backend>(
+    show_book: bool,
+) -> WidgetBox<D, B> {
+    let mut widgets: Vec<Box<dyn Widget<D, B> + Send + Sync>> = vec![
+        Box::new(CommandWidget {
+            database: PhantomData,
+        }),
+        Box::new(ColumnWidget {
+            database: Default::default(),
+        }),
+    ];
+    if show_book {
+        widgets.push(Box::new(BookWidget::default()));
+        WidgetBox::new(widgets, Box::new(ColumnBookLayout {}))
+    } else {
+        WidgetBox::new(widgets, Box::new(EditLayout {}))
+    }
+}
+
 impl<D: AppDatabase + Send + Sync, B: 'static + Backend> AppInterface<D, B> {
     /// Returns a new interface, instantiated with the provided settings and database.
     ///
@@ -128,6 +190,9 @@ impl<D: AppDatabase + Send + Sync, B: 'static + Backend> AppInterface<D, B> {
     /// * ` name ` - The application instance name. Not to confused with the file name.
     /// * ` settings` - The interface settings.
     /// * ` settings_path ` - The settings path (used to persist settings).
+    /// * ` database_settings ` - The database settings (used to persist settings).
+    /// * ` maintenance_settings ` - The maintenance settings (used to persist settings).
+    /// * ` session_settings ` - The filters which were applied when the application was closed.
     pub(crate) async fn new<S: Into<String>>(
         name: S,
         settings: InterfaceSettings,
@@ -135,39 +200,67 @@ impl<D: AppDatabase + Send + Sync, B: 'static + Backend> AppInterface<D, B> {
         app_channel: AppChannel<D>,
         event_receiver: EventStream,
         sort_settings: SortSettings,
+        database_settings: DatabaseSettings,
+        maintenance_settings: MaintenanceSettings,
+        session_settings: SessionSettings,
     ) -> AppInterface<D, B> {
-        let book_view = app_channel.new_book_view().await;
+        let mut book_view = app_channel.new_book_view().await;
+        if settings.navigation_settings.load_more {
+            book_view.set_prefetch(false);
+        }
+        book_view.set_collation(sort_settings.collation);
+        // Restore the sort order which was last used.
+        if !sort_settings.columns.is_empty() {
+            if let Err(e) = book_view.sort_by_columns(&sort_settings.columns).await {
+                tracing::error!("Failed to restore the sort order: {:?}", e);
+            }
+        }
+        book_view.set_normalization(settings.search_normalization);
+        restore_filters(&mut book_view, &session_settings.filters).await;
+        if settings.navigation_settings.select_first {
+            if let Err(e) = book_view.select_first().await {
+                tracing::error!("Failed to select the first book: {:?}", e);
+            }
+        }
         let path = app_channel.db_path().await;
+        let mut table_view = Columns::from_display(settings.columns, settings.unsearchable_columns);
+        table_view.set_author_display(settings.author_display);
+        table_view.set_size_units(settings.size_units);
         let ui_state = UIState {
             style: settings.interface_style,
             nav_settings: settings.navigation_settings,
             curr_command: CommandString::new(),
             selected_column: 0,
-            table_view: Columns::from(settings.columns),
+            table_view,
+            truncation: settings.truncation,
             book_view,
             sort_settings,
+            quick_add: false,
+            notification: None,
+            notifications: Default::default(),
+            covers: if settings.show_covers {
+                Some(CoverProtocol::detect())
+            } else {
+                None
+            },
+            placed_cover: None,
+            import: None,
         };
         AppInterface {
             active_view: BorderWidget::new(
                 name.into(),
                 path,
-                Box::new(WidgetBox::new(
-                    vec![
-                        Box::new(CommandWidget {
-                            database: PhantomData,
-                        }),
-                        Box::new(ColumnWidget {
-                            database: Default::default(),
-                        }),
-                    ],
-                    Box::new(EditLayout {}),
-                )),
+                Box::new(columns_view(settings.show_covers)),
             ),
             update_tui: false,
             ui_state,
             settings_path,
+            database_settings,
+            maintenance_settings,
             app_channel,
+            quit_after_import: false,
             event_receiver,
+            transmitted_cover: None,
             lifetime_marker: PhantomData,
         }
     }
@@ -183,7 +276,9 @@ impl<D: AppDatabase + Send + Sync, B: 'static + Backend> AppInterface<D, B> {
         // books.
         match timeout(Duration::from_millis(20), self.event_receiver.next().fuse()).await {
             Ok(Some(Ok(event))) => {
-                match event {
+                let sort_columns = self.ui_state.sort_settings.columns.clone();
+                let filters = self.ui_state.book_view.persisted_filters();
+                let task = match event {
                     Event::Key(KeyEvent {
                         code: KeyCode::Char('q'),
                         modifiers: KeyModifiers::CONTROL,
@@ -195,29 +290,29 @@ impl<D: AppDatabase + Send + Sync, B: 'static + Backend> AppInterface<D, B> {
                         self.app_channel.save().await;
                         return Ok(true);
                     }
-                    _ => {}
-                }
-                match self
-                    .active_view
-                    .handle_input(event, &mut self.ui_state, &mut self.app_channel)
-                    .await?
-                {
+                    Event::Key(KeyEvent {
+                        code: KeyCode::Char(c),
+                        modifiers: KeyModifiers::CONTROL,
+                    }) if c == self.ui_state.nav_settings.quick_add => {
+                        self.ui_state.curr_command.clear();
+                        self.ui_state.quick_add = true;
+                        self.ui_state.notification = None;
+                        ApplicationTask::SwitchView(AppView::Columns)
+                    }
+                    event => {
+                        self.active_view
+                            .handle_input(event, &mut self.ui_state, &mut self.app_channel)
+                            .await?
+                    }
+                };
+                match task {
                     ApplicationTask::Quit => return Ok(false),
                     ApplicationTask::SwitchView(view) => {
                         self.update_tui = true;
                         match view {
                             AppView::Columns => {
-                                self.active_view.inner = Box::new(WidgetBox::new(
-                                    vec![
-                                        Box::new(CommandWidget {
-                                            database: PhantomData,
-                                        }),
-                                        Box::new(ColumnWidget {
-                                            database: Default::default(),
-                                        }),
-                                    ],
-                                    Box::new(EditLayout {}),
-                                ));
+                                self.active_view.inner =
+                                    Box::new(columns_view(self.ui_state.covers.is_some()));
                             }
                             AppView::Edit => {
                                 let _ = self.ui_state.make_selection_visible().await;
@@ -244,6 +339,19 @@ impl<D: AppDatabase + Send + Sync, B: 'static + Backend> AppInterface<D, B> {
                                     database: PhantomData,
                                 })
                             }
+                            AppView::Diff(left, right) => {
+                                self.active_view.inner = Box::new(DiffWidget::new(&left, &right))
+                            }
+                            AppView::Stats(stats) => {
+                                self.active_view.inner = Box::new(StatsWidget::new(stats))
+                            }
+                            AppView::Find => match FindWidget::new(&self.ui_state) {
+                                Some(find) => self.active_view.inner = Box::new(find),
+                                None => self
+                                    .ui_state
+                                    .notifications
+                                    .warning("The selected column can not be searched"),
+                            },
                         }
                     }
                     ApplicationTask::UpdateUI => {
@@ -251,6 +359,15 @@ impl<D: AppDatabase + Send + Sync, B: 'static + Backend> AppInterface<D, B> {
                     }
                     ApplicationTask::DoNothing => {}
                 }
+                // Persist the sort order and filters immediately, so that they are kept even if
+                // the application does not exit cleanly.
+                if self.ui_state.sort_settings.columns != sort_columns
+                    || self.ui_state.book_view.persisted_filters() != filters
+                {
+                    if let Err(e) = self.write_settings().await {
+                        tracing::error!("Failed to save the sort order and filters: {:?}", e);
+                    }
+                }
                 Ok(true)
             }
             Ok(None) => Ok(false),
@@ -266,6 +383,43 @@ impl<D: AppDatabase + Send + Sync, B: 'static + Backend> AppInterface<D, B> {
         std::mem::replace(&mut self.update_tui, false)
     }
 
+    /// Waits briefly for the next update about the directory being added, and returns whether
+    /// the interface should be redrawn. Escape stops adding the directory, and quitting stops
+    /// adding the directory before quitting. Other input is discarded until the directory has
+    /// been added, as commands can not be run in the meantime.
+    ///
+    /// # Errors
+    /// This function will return an error if reloading the books fails once they are added.
+    async fn poll_import(&mut self) -> Result<bool, TuiError<D::Error>> {
+        while let Some(Some(event)) = self.event_receiver.next().now_or_never() {
+            match event {
+                Ok(Event::Key(KeyEvent {
+                    code: KeyCode::Char('q'),
+                    modifiers: KeyModifiers::CONTROL,
+                })) => {
+                    self.quit_after_import = true;
+                    self.app_channel.cancel_import();
+                }
+                Ok(Event::Key(KeyEvent {
+                    code: KeyCode::Esc, ..
+                })) => self.app_channel.cancel_import(),
+                _ => {}
+            }
+        }
+        match timeout(Duration::from_millis(100), self.app_channel.import_event()).await {
+            Ok(ImportEvent::Progress(progress)) => {
+                self.ui_state.import = Some(progress);
+                Ok(true)
+            }
+            Ok(ImportEvent::Done(added)) => {
+                self.ui_state.import = None;
+                books_added(&mut self.ui_state, &added).await?;
+                Ok(true)
+            }
+            Err(_) => Ok(false),
+        }
+    }
+
     /// Runs the application - including handling user inputs and refreshing the output.
     ///
     /// # Arguments
@@ -279,8 +433,28 @@ impl<D: AppDatabase + Send + Sync, B: 'static + Backend> AppInterface<D, B> {
         terminal: &mut Terminal<B>,
     ) -> Result<(), TuiError<D::Error>> {
         loop {
-            if self.app_channel.take_update().await | self.take_update() {
-                self.active_view.saved = self.app_channel.saved().await;
+            if self.ui_state.notifications.expire(Instant::now()) {
+                self.update_tui = true;
+            }
+            let updated = if self.ui_state.import.is_some() {
+                match self.poll_import().await {
+                    Ok(updated) => updated,
+                    Err(e) => {
+                        tracing::info!("Error occurred while adding books: {:?}", e);
+                        self.ui_state
+                            .notifications
+                            .error(format!("Command failed: {}", e.describe()));
+                        true
+                    }
+                }
+            } else {
+                self.app_channel.take_update().await
+            };
+            if updated | self.take_update() {
+                if self.ui_state.import.is_none() {
+                    self.active_view.saved = self.app_channel.saved().await;
+                }
+                self.ui_state.placed_cover = None;
                 {
                     let frame = terminal.get_frame();
                     let size = frame.size();
@@ -296,40 +470,99 @@ impl<D: AppDatabase + Send + Sync, B: 'static + Backend> AppInterface<D, B> {
                         return;
                     }
                     self.active_view.render_into_frame(f, &self.ui_state, size);
+                    let overlay = Rect::new(1, 1, size.width - 2, size.height - 2);
+                    match &self.ui_state.import {
+                        Some(progress) => f.render_widget(ProgressOverlay(progress), overlay),
+                        None => f.render_widget(
+                            NotificationOverlay(&self.ui_state.notifications),
+                            overlay,
+                        ),
+                    }
                 })?;
+                if self.ui_state.covers == Some(CoverProtocol::Kitty) {
+                    self.place_cover()?;
+                }
+            }
+            if self.ui_state.import.is_some() {
+                continue;
             }
 
-            match self.read_user_input().await {
+            let input = if std::mem::take(&mut self.quit_after_import) {
+                Ok(false)
+            } else {
+                self.read_user_input().await
+            };
+            match input {
                 Ok(false) => {
                     self.write_settings().await?;
+                    if self.ui_state.covers == Some(CoverProtocol::Kitty) {
+                        self.ui_state.placed_cover = None;
+                        self.place_cover()?;
+                    }
                     return Ok(terminal.clear()?);
                 }
                 Ok(true) => {}
                 Err(e) => {
                     tracing::info!("Error occurred during execution: {:?}", e);
-                    // TODO: User should be notified when errors occur - but where and how?
+                    self.ui_state
+                        .notifications
+                        .error(format!("Command failed: {}", e.describe()));
+                    self.update_tui = true;
                 }
             }
         }
     }
 
+    /// Places the cover of the selected book over the frame, using the kitty graphics protocol,
+    /// and removes the cover which was previously placed. Covers are only sent to the terminal
+    /// when they are first placed.
+    ///
+    /// # Errors
+    /// This function will return an error if writing to the terminal fails.
+    fn place_cover(&mut self) -> Result<(), TuiError<D::Error>> {
+        let mut out = std::io::stdout();
+        cover::clear_kitty(&mut out)?;
+        if let Some((area, cover)) = &self.ui_state.placed_cover {
+            if self.transmitted_cover != Some(cover.id()) {
+                if let Some(id) = self.transmitted_cover {
+                    cover::delete_kitty(&mut out, id)?;
+                }
+                cover.transmit_kitty(&mut out)?;
+                self.transmitted_cover = Some(cover.id());
+            }
+            cover.place_kitty(&mut out, *area)?;
+        }
+        Ok(out.flush()?)
+    }
+
     async fn write_settings(&self) -> Result<(), TuiError<D::Error>> {
         if let Some(path) = &self.settings_path {
             // TODO: Have central settings file that lists other databases in order of recent usage.
             // TODO: Write multiple settings files to allow multiple databases.
             let s = Settings {
                 interface_style: self.ui_state.style,
-                columns: self
+                columns: self.ui_state.table_view.column_display(),
+                unsearchable_columns: self
                     .ui_state
                     .table_view
-                    .selected_cols()
+                    .unsearchable_cols()
                     .iter()
                     .map(|s| s.clone().into_inner())
                     .collect(),
+                truncation: self.ui_state.truncation.clone(),
+                author_display: self.ui_state.table_view.author_display().clone(),
+                size_units: self.ui_state.table_view.size_units(),
+                search_normalization: self.ui_state.book_view.normalization(),
+                show_covers: self.ui_state.covers.is_some(),
                 sort_settings: self.ui_state.sort_settings.clone(),
                 navigation_settings: self.ui_state.nav_settings,
                 database_settings: DatabaseSettings {
                     path: self.app_channel.db_path().await,
+                    ..self.database_settings.clone()
+                },
+                maintenance_settings: self.maintenance_settings,
+                session_settings: SessionSettings {
+                    filters: self.ui_state.book_view.persisted_filters(),
                 },
             };
             if let Some(p) = path.parent() {
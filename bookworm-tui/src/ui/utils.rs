@@ -1,16 +1,32 @@
 #[cfg(feature = "copypaste")]
 use clipboard::{ClipboardContext, ClipboardProvider};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::sync::Arc;
+
 use tui::layout::{Constraint, Direction, Layout, Rect};
 use tui::style::{Color as TColor, Modifier, Style};
 use tui::text::{Span, Spans};
 use unicode_truncate::UnicodeTruncateStr;
+use unicode_width::UnicodeWidthStr;
 
-use bookworm_app::app::AppChannel;
-use bookworm_app::settings::{Color, SortSettings};
+use bookworm_app::app::{AppChannel, Progress};
+use bookworm_app::columns::Width;
+use bookworm_app::export::{pretty_by_default, write_json, write_reading_csv};
+use bookworm_app::parser::Source;
+use bookworm_app::settings::Color;
 use bookworm_app::{BookIndex, Command};
+use bookworm_database::export::BookWriter;
 use bookworm_database::paginator::Selection;
-use bookworm_database::AppDatabase;
+use bookworm_database::search::{Search, SearchMode, SizeUnits};
+use bookworm_database::{
+    AppDatabase, BookView, ExportFormat, FtsReport, LibraryStats, MonthlyReading,
+};
 use bookworm_input::user_input::CharChunks;
+use bookworm_records::book::BookID;
+use bookworm_records::variant::FileStatus;
+use bookworm_records::Book;
 
 use crate::ui::help_strings::{help_strings, GENERAL_HELP};
 use crate::ui::tui_widgets::ListItemX;
@@ -29,6 +45,12 @@ pub enum AppView {
     Columns,
     Edit,
     Help(String),
+    /// Compares the first book against the second book.
+    Diff(Arc<Book>, Arc<Book>),
+    /// Summarizes the library.
+    Stats(LibraryStats),
+    /// Filters the table by the selected column as the user types.
+    Find,
 }
 
 pub(crate) enum ApplicationTask {
@@ -41,14 +63,70 @@ pub(crate) enum ApplicationTask {
 #[tracing::instrument(name = "Executing user command", skip(app, command, ui_state))]
 pub(crate) async fn run_command<D: AppDatabase + Send + Sync>(
     app: &mut AppChannel<D>,
-    command: Command,
+    mut command: Command,
     ui_state: &mut UIState<D>,
 ) -> Result<ApplicationTask, TuiError<D::Error>> {
+    if let Command::FilterMatches(searches)
+    | Command::TempFilterMatches(searches)
+    | Command::JumpTo(searches)
+    | Command::HighlightMatches(searches)
+    | Command::DeleteMatching(searches) = &mut command
+    {
+        // The parser does not know the configured meaning of "KB", "MB" and "GB".
+        for search in searches.iter_mut() {
+            if let SearchMode::FileSize(units) = &mut search.mode {
+                *units = ui_state.table_view.size_units();
+            }
+        }
+    }
+
+    if let Command::FilterMatches(searches)
+    | Command::TempFilterMatches(searches)
+    | Command::JumpTo(searches)
+    | Command::HighlightMatches(searches) = &command
+    {
+        let table = &ui_state.table_view;
+        if let Some(search) = searches.iter().find(|s| !table.is_searchable(&s.column)) {
+            ui_state.notification = Some(format!(
+                "The {} column can not be searched",
+                search.column.clone().into_string()
+            ));
+            return Ok(ApplicationTask::UpdateUI);
+        }
+    }
+
+    if let Command::FilterMatches(searches)
+    | Command::TempFilterMatches(searches)
+    | Command::JumpTo(searches)
+    | Command::HighlightMatches(searches)
+    | Command::DeleteMatching(searches) = &command
+    {
+        // Invalid searches would otherwise be dropped, matching more books than intended.
+        if let Some(Err(e)) = searches
+            .iter()
+            .cloned()
+            .map(Search::into_matcher)
+            .find(Result::is_err)
+        {
+            ui_state.notification = Some(e.to_string());
+            return Ok(ApplicationTask::UpdateUI);
+        }
+    }
+
     match command {
         Command::DeleteSelected => {
-            app.delete_selected(ui_state.book_view.selected_books().clone())
-                .await;
+            let selected = ui_state.book_view.selected_books().clone();
+            if selected.is_empty() {
+                ui_state.notifications.warning("No books are selected");
+            } else if app.delete_selected(selected).await {
+                ui_state.notifications.info("Deleted the selected books");
+            } else {
+                ui_state
+                    .notifications
+                    .error("Failed to delete the selected books");
+            }
             ui_state.book_view.refresh().await?;
+            return Ok(ApplicationTask::UpdateUI);
         }
         Command::DeleteMatching(matches) => {
             // TODO: This will be changed to a set of merge conflicts, which the
@@ -56,15 +134,30 @@ pub(crate) async fn run_command<D: AppDatabase + Send + Sync>(
             let matches = matches
                 .to_vec()
                 .into_iter()
-                .filter_map(|s| s.into_matcher().ok())
+                .filter_map(|s| {
+                    s.into_normalized_matcher(ui_state.book_view.normalization())
+                        .ok()
+                })
                 .collect::<Vec<_>>()
                 .into_boxed_slice();
-            let _ = app.delete_selected(Selection::All(matches)).await;
+            if app.delete_selected(Selection::All(matches)).await {
+                ui_state.notifications.info("Deleted matching books");
+            } else {
+                ui_state
+                    .notifications
+                    .error("Failed to delete matching books");
+            }
             ui_state.book_view.refresh().await?;
+            return Ok(ApplicationTask::UpdateUI);
         }
         Command::DeleteAll => {
-            app.delete_selected(Selection::All(Box::default())).await;
+            if app.delete_selected(Selection::All(Box::default())).await {
+                ui_state.notifications.info("Deleted all books");
+            } else {
+                ui_state.notifications.error("Failed to delete all books");
+            }
             ui_state.book_view.refresh().await?;
+            return Ok(ApplicationTask::UpdateUI);
         }
         Command::EditBook(book, edits) => match book {
             BookIndex::Selected => {
@@ -75,13 +168,146 @@ pub(crate) async fn run_command<D: AppDatabase + Send + Sync>(
             BookIndex::ID(id) => app.edit_books(vec![id].into_boxed_slice(), edits).await,
         },
         Command::AddBooks(sources) => {
-            app.add_books(sources).await;
-            ui_state.book_view.refresh().await?;
+            let added = app.add_books(sources).await;
+            books_added(ui_state, &added).await?;
+            return Ok(ApplicationTask::UpdateUI);
+        }
+        Command::AddDir(dir, depth) => {
+            // The progress is read by the interface, until the books have been added.
+            app.add_dir(dir, depth).await;
+            ui_state.import = Some(Progress::default());
+            return Ok(ApplicationTask::UpdateUI);
         }
         Command::UpdateBooks(sources) => {
             app.update_books(sources).await;
             ui_state.book_view.refresh().await?;
         }
+        Command::ImportTags(path, format) => {
+            app.import_tags(path, format).await;
+            ui_state.book_view.refresh().await?;
+        }
+        Command::ImportDb(path) => {
+            let ids = app.import_db(path).await;
+            ui_state.book_view.refresh().await?;
+            ui_state.notification = Some(format!("Imported {} books", ids.len()));
+            return Ok(ApplicationTask::UpdateUI);
+        }
+        Command::SetPreferredVariant(book, index) => {
+            let id = match book {
+                BookIndex::Selected => {
+                    if let Some(book) = ui_state.book_view.selected_books().front() {
+                        book.id()
+                    } else {
+                        return Ok(ApplicationTask::DoNothing);
+                    }
+                }
+                BookIndex::ID(id) => id,
+            };
+
+            app.set_preferred_variant(id, index).await;
+            ui_state.book_view.refresh().await?;
+        }
+        Command::DedupeAuthors(confirmed) => {
+            match app.dedupe_authors(confirmed).await {
+                Some(0) => {}
+                Some(near_matches) => ui_state.notifications.info(format!(
+                    "{} author spellings only differ in punctuation - run :authors dedupe -y to consolidate them",
+                    near_matches
+                )),
+                None => ui_state
+                    .notifications
+                    .error("Failed to consolidate the authors".to_string()),
+            }
+            ui_state.book_view.refresh().await?;
+            return Ok(ApplicationTask::UpdateUI);
+        }
+        Command::SeriesFromFilename(pattern) => {
+            app.series_from_filename(ui_state.book_view.selected_books().clone(), pattern)
+                .await;
+            ui_state.book_view.refresh().await?;
+        }
+        Command::ClearTags(confirmed) => {
+            if ui_state.book_view.selected_books().is_empty() {
+                return Ok(ApplicationTask::DoNothing);
+            }
+            if !confirmed {
+                ui_state.notification = Some(
+                    "This removes all tags from the selected books - run :tags clear-all -y to confirm"
+                        .to_string(),
+                );
+                return Ok(ApplicationTask::UpdateUI);
+            }
+            app.clear_tags(ui_state.book_view.selected_books().clone())
+                .await;
+            ui_state.book_view.refresh().await?;
+        }
+        Command::RenameTag(old, new) => {
+            match app.rename_tag(old.clone(), new.clone()).await {
+                Some(renamed) => ui_state
+                    .notifications
+                    .info(format!("Renamed {} tags from {} to {}", renamed, old, new)),
+                None => ui_state
+                    .notifications
+                    .error(format!("Failed to rename the tag {}", old)),
+            }
+            ui_state.book_view.refresh().await?;
+            return Ok(ApplicationTask::UpdateUI);
+        }
+        Command::DeleteTag(name, confirmed) => {
+            if !confirmed {
+                ui_state.notification = Some(format!(
+                    "This removes {} from every book - run :tag delete {} -y to confirm",
+                    name, name
+                ));
+                return Ok(ApplicationTask::UpdateUI);
+            }
+            match app.delete_tag(name.clone()).await {
+                Some(deleted) => ui_state
+                    .notifications
+                    .info(format!("Removed {} tags named {}", deleted, name)),
+                None => ui_state
+                    .notifications
+                    .error(format!("Failed to remove the tag {}", name)),
+            }
+            ui_state.book_view.refresh().await?;
+            return Ok(ApplicationTask::UpdateUI);
+        }
+        Command::Rescan(overwrite, confirmed) => {
+            if ui_state.book_view.selected_books().is_empty() {
+                return Ok(ApplicationTask::DoNothing);
+            }
+            if overwrite && !confirmed {
+                ui_state.notification = Some(
+                    "This discards manual edits to the selected books - run :rescan --overwrite -y to confirm"
+                        .to_string(),
+                );
+                return Ok(ApplicationTask::UpdateUI);
+            }
+            app.rescan(ui_state.book_view.selected_books().clone(), overwrite)
+                .await;
+            ui_state.book_view.refresh().await?;
+        }
+        Command::ExportView(format, path) => {
+            let books = ui_state.book_view.read_all().await?;
+            let writer = BufWriter::new(File::create(path)?);
+            match format {
+                ExportFormat::Json => write_json(writer, &books, pretty_by_default(books.len()))?,
+                format @ ExportFormat::Csv(_) => {
+                    let mut writer = BookWriter::new(format, writer)?;
+                    for book in &books {
+                        writer.write_book(book)?;
+                    }
+                    writer.finish()?;
+                }
+            }
+        }
+        Command::ExportDatabase(format, path) => {
+            ui_state.notification = Some(match app.export(format, path.clone()).await {
+                Some(written) => format!("Exported {} books to {}", written, path.display()),
+                None => format!("Failed to export books to {}", path.display()),
+            });
+            return Ok(ApplicationTask::UpdateUI);
+        }
         Command::ModifyColumns(columns) => {
             app.modify_columns(columns, &mut ui_state.table_view, &mut ui_state.book_view)
                 .await?;
@@ -89,7 +315,7 @@ pub(crate) async fn run_command<D: AppDatabase + Send + Sync>(
         Command::SortColumns(columns) => {
             tracing::info!("Sorting by {:?}", columns);
             ui_state.book_view.sort_by_columns(&columns).await?;
-            ui_state.sort_settings = SortSettings { columns };
+            ui_state.sort_settings.columns = columns;
         }
         #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
         Command::OpenBookIn(book, index, target) => {
@@ -106,12 +332,40 @@ pub(crate) async fn run_command<D: AppDatabase + Send + Sync>(
 
             app.open_book(id, index, target).await;
         }
+        #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
+        Command::OpenFolder(book) => {
+            let id = match book {
+                BookIndex::Selected => {
+                    if let Some(book) = ui_state.book_view.selected_books().front() {
+                        book.id()
+                    } else {
+                        return Ok(ApplicationTask::DoNothing);
+                    }
+                }
+                BookIndex::ID(id) => id,
+            };
+
+            app.open_folder(id).await;
+        }
         Command::FilterMatches(searches) => {
             ui_state.book_view.push_scope(&searches).await?;
         }
+        Command::TempFilterMatches(searches) => {
+            ui_state.book_view.push_temp_scope(&searches).await?;
+        }
         Command::JumpTo(searches) => {
             ui_state.book_view.jump_to(&searches).await?;
         }
+        Command::HighlightMatches(searches) => {
+            ui_state.book_view.highlight(&searches)?;
+            ui_state.book_view.next_match().await?;
+        }
+        Command::NextMatch => {
+            ui_state.book_view.next_match().await?;
+        }
+        Command::PrevMatch => {
+            ui_state.book_view.prev_match().await?;
+        }
         Command::Write => {
             app.save().await;
         }
@@ -123,13 +377,121 @@ pub(crate) async fn run_command<D: AppDatabase + Send + Sync>(
             return Ok(ApplicationTask::Quit);
         }
         Command::TryMergeAllBooks => {
+            match app.try_merge_all_books().await {
+                Some(merged) => ui_state
+                    .notifications
+                    .info(merge_notification(merged.len())),
+                None => ui_state.notifications.error("Failed to merge books"),
+            }
             ui_state.book_view.refresh().await?;
+            return Ok(ApplicationTask::UpdateUI);
         }
         Command::Help(target) => {
             return Ok(ApplicationTask::SwitchView(AppView::Help(
                 help_strings(&target).unwrap_or(GENERAL_HELP).to_string(),
             )));
         }
+        Command::Stats => {
+            return Ok(match app.library_stats().await {
+                Some(stats) => ApplicationTask::SwitchView(AppView::Stats(stats)),
+                None => {
+                    ui_state.notification = Some("Failed to compute library stats".to_string());
+                    ApplicationTask::UpdateUI
+                }
+            });
+        }
+        Command::ColumnStats(column) => {
+            let name = column.clone().into_string();
+            let stats = app.column_stats(column).await;
+            return Ok(ApplicationTask::SwitchView(AppView::Help(stats_chart(
+                &name, &stats, 40,
+            ))));
+        }
+        Command::ReadingStats(path) => {
+            let stats = app.reading_stats().await;
+            return Ok(match path {
+                None => ApplicationTask::SwitchView(AppView::Help(reading_report(&stats))),
+                Some((path, format)) => {
+                    write_reading_csv(BufWriter::new(File::create(&path)?), &stats, &format)?;
+                    ui_state.notification = Some(format!(
+                        "Wrote reading statistics for {} months to {}",
+                        stats.len(),
+                        path.display()
+                    ));
+                    ApplicationTask::UpdateUI
+                }
+            });
+        }
+        Command::FindDuplicates(strategy) => {
+            let groups = app.find_duplicates(strategy).await;
+            return Ok(ApplicationTask::SwitchView(AppView::Help(
+                duplicates_report(&groups),
+            )));
+        }
+        Command::DiffBook(id) => {
+            let left = match ui_state.book_view.selected_books().front() {
+                Some(book) => book.clone(),
+                None => return Ok(ApplicationTask::DoNothing),
+            };
+            match app.get_book(id).await {
+                Some(right) => return Ok(ApplicationTask::SwitchView(AppView::Diff(left, right))),
+                None => {
+                    ui_state.notification = Some(format!("No book with ID {}", id));
+                    return Ok(ApplicationTask::UpdateUI);
+                }
+            }
+        }
+        Command::RebuildFts => {
+            let report = app.rebuild_fts().await;
+            ui_state.notification = Some(fts_notification(report));
+            return Ok(ApplicationTask::UpdateUI);
+        }
+        Command::ListVariants => {
+            return Ok(match ui_state.book_view.selected_books().front() {
+                Some(book) => ApplicationTask::SwitchView(AppView::Help(variants_report(book))),
+                None => ApplicationTask::DoNothing,
+            });
+        }
+        Command::Refresh => {
+            if ui_state.book_view.selected_books().is_empty() {
+                return Ok(ApplicationTask::DoNothing);
+            }
+            let refreshed = app
+                .refresh(ui_state.book_view.selected_books().clone())
+                .await;
+            ui_state.book_view.refresh().await?;
+            ui_state.notification = Some(format!("Refreshed {} books", refreshed));
+            return Ok(ApplicationTask::UpdateUI);
+        }
+        Command::Fetch(overwrite) => {
+            if ui_state.book_view.selected_books().is_empty() {
+                return Ok(ApplicationTask::DoNothing);
+            }
+            let fetched = app
+                .fetch(ui_state.book_view.selected_books().clone(), overwrite)
+                .await;
+            ui_state.book_view.refresh().await?;
+            ui_state.notification = Some(match fetched {
+                Some(fetched) => format!("Fetched metadata for {} books", fetched),
+                None => "Failed to fetch metadata - check that bookworm was built with the fetch feature"
+                    .to_string(),
+            });
+            return Ok(ApplicationTask::UpdateUI);
+        }
+        Command::Undo => {
+            if !app.undo().await {
+                ui_state.notification = Some("Nothing to undo".to_string());
+                return Ok(ApplicationTask::UpdateUI);
+            }
+            ui_state.book_view.refresh().await?;
+        }
+        Command::Redo => {
+            if !app.redo().await {
+                ui_state.notification = Some("Nothing to redo".to_string());
+                return Ok(ApplicationTask::UpdateUI);
+            }
+            ui_state.book_view.refresh().await?;
+        }
         Command::GeneralHelp => {
             return Ok(ApplicationTask::SwitchView(AppView::Help(
                 GENERAL_HELP.to_string(),
@@ -139,6 +501,262 @@ pub(crate) async fn run_command<D: AppDatabase + Send + Sync>(
     Ok(ApplicationTask::DoNothing)
 }
 
+/// Renders `stats` as a horizontal bar chart, with one row per value. The bar of the most
+/// common value is `width` characters long, and all other bars are scaled relative to it.
+///
+/// # Arguments
+///
+/// * ` column ` - The name of the column which the stats describe.
+/// * ` stats ` - Each value of the column, and the number of books which have that value.
+/// * ` width ` - The maximum width of a bar.
+pub(crate) fn stats_chart(column: &str, stats: &[(String, usize)], width: usize) -> String {
+    let max_count = match stats.iter().map(|(_, count)| *count).max() {
+        Some(count) if count != 0 => count,
+        _ => return format!("No values found for {}.\n", column),
+    };
+    let label_width = stats
+        .iter()
+        .map(|(value, _)| value.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut chart = format!("Books by {}:\n\n", column);
+    for (value, count) in stats {
+        let bar_len = (count * width / max_count).max(1);
+        chart.push_str(&format!(
+            "{:<label_width$} {} {}\n",
+            value,
+            "█".repeat(bar_len),
+            count,
+            label_width = label_width
+        ));
+    }
+    chart
+}
+
+/// Returns `bytes` as a human readable size with one decimal place, such as "1.5 MB". Binary
+/// units are written as "KiB", "MiB" and so on.
+///
+/// # Arguments
+///
+/// * ` bytes ` - The size, in bytes.
+/// * ` units ` - Whether to use decimal or binary units.
+pub(crate) fn format_size(bytes: u64, units: SizeUnits) -> String {
+    let (base, suffixes) = match units {
+        SizeUnits::Decimal => (1000., ["KB", "MB", "GB", "TB"]),
+        SizeUnits::Binary => (1024., ["KiB", "MiB", "GiB", "TiB"]),
+    };
+    let mut size = bytes as f64;
+    if size < base {
+        return format!("{} B", bytes);
+    }
+    let mut suffix = suffixes[0];
+    for &next in suffixes.iter() {
+        if size < base {
+            break;
+        }
+        size /= base;
+        suffix = next;
+    }
+    format!("{:.1} {}", size, suffix)
+}
+
+/// Lists the number of books added and finished in each month.
+pub(crate) fn reading_report(stats: &[MonthlyReading]) -> String {
+    if stats.is_empty() {
+        return "No books have a date added or finished date.\n".to_string();
+    }
+    let mut report =
+        "Books added and finished by month:\n\nmonth      added  finished\n".to_string();
+    for month in stats {
+        report.push_str(&format!(
+            "{:<10} {:>5}  {:>8}\n",
+            month.month, month.added, month.finished
+        ));
+    }
+    report
+}
+
+/// Lists each variant of `book`, with its path, format, size and hash prefix, and whether its
+/// file still matches what was recorded when the variant was added.
+pub(crate) fn variants_report(book: &Book) -> String {
+    if book.variants().is_empty() {
+        return format!("{} has no variants.\n", book);
+    }
+    let mut report = format!("Variants of {}:\n\n", book);
+    for (index, variant) in book.variants().iter().enumerate() {
+        let hash: String = variant.hash[..4]
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        let status = match variant.file_status() {
+            FileStatus::Unchanged => "ok",
+            FileStatus::Changed => "changed since added",
+            FileStatus::Missing => "missing",
+        };
+        report.push_str(&format!(
+            "{}: {}\n   {:?}, {} bytes, hash {}..., {}\n",
+            index,
+            variant.path().display(),
+            variant.book_type(),
+            variant.file_size,
+            hash,
+            status
+        ));
+    }
+    report
+}
+
+/// Lists each group of duplicate books, with the ID, title and authors of each book. The first
+/// book of each group is the one which the others would be merged into.
+pub(crate) fn duplicates_report(groups: &[Vec<Arc<Book>>]) -> String {
+    if groups.is_empty() {
+        return "No duplicate books found.\n".to_string();
+    }
+    let mut report = "Duplicate books:\n".to_string();
+    for (index, group) in groups.iter().enumerate() {
+        report.push_str(&format!("\nGroup {}:\n", index + 1));
+        for book in group {
+            report.push_str(&format!(
+                "  {}: {}, by {}\n",
+                book.id(),
+                book.title().unwrap_or("untitled"),
+                book.authors()
+                    .map_or_else(|| "unknown".to_string(), |authors| authors.join(", "))
+            ));
+        }
+    }
+    report
+}
+
+/// A single column of two compared books.
+#[derive(Debug, PartialEq)]
+pub(crate) struct DiffRow {
+    pub(crate) column: String,
+    pub(crate) left: String,
+    pub(crate) right: String,
+    pub(crate) differs: bool,
+}
+
+/// Returns one row for each column compared between `left` and `right`, marking the
+/// columns which differ. Missing values are shown as empty strings.
+pub(crate) fn diff_rows(left: &Book, right: &Book) -> Vec<DiffRow> {
+    let differing: Vec<_> = left
+        .diff(right)
+        .into_iter()
+        .map(|(column, _, _)| column)
+        .collect();
+    left.compared_columns(right)
+        .into_iter()
+        .map(|column| DiffRow {
+            left: left.compared_value(&column).unwrap_or_default(),
+            right: right.compared_value(&column).unwrap_or_default(),
+            differs: differing.contains(&column),
+            column: column.into_string(),
+        })
+        .collect()
+}
+
+/// Adds the book at `path`, or the books directly inside of `path` if it is a directory,
+/// using the same defaults as `:a`. Returns the IDs of the added books.
+pub(crate) async fn quick_add<D: AppDatabase + Send + Sync>(
+    app: &AppChannel<D>,
+    path: PathBuf,
+) -> Vec<BookID> {
+    let source = if path.is_dir() {
+        Source::Dir(path, 1)
+    } else {
+        Source::File(path)
+    };
+    app.add_books(vec![source].into_boxed_slice()).await
+}
+
+/// Returns the notification shown after quick adding `count` books.
+pub(crate) fn quick_add_notification(count: usize) -> String {
+    match count {
+        0 => "No books added".to_string(),
+        1 => "Added 1 book".to_string(),
+        n => format!("Added {} books", n),
+    }
+}
+
+/// Applies each of the persisted `filters` to `book_view`, in order. Filters which can not be
+/// applied are logged and skipped, so that the remaining filters are still restored.
+pub(crate) async fn restore_filters<D: AppDatabase + Send + Sync>(
+    book_view: &mut BookView<D>,
+    filters: &[Box<[Search]>],
+) {
+    for searches in filters {
+        if let Some(Err(e)) = searches
+            .iter()
+            .cloned()
+            .map(Search::into_matcher)
+            .find(Result::is_err)
+        {
+            tracing::error!("Failed to restore filter {:?}: {}", searches, e);
+            continue;
+        }
+        if let Err(e) = book_view.push_scope(searches).await {
+            tracing::error!("Failed to restore filter {:?}: {:?}", searches, e);
+        }
+    }
+}
+
+/// Notifies the user of how many books were added, and reloads the books.
+///
+/// # Errors
+/// This function will return an error if reloading the books fails.
+pub(crate) async fn books_added<D: AppDatabase + Send + Sync>(
+    ui_state: &mut UIState<D>,
+    added: &[BookID],
+) -> Result<(), TuiError<D::Error>> {
+    if added.is_empty() {
+        ui_state.notifications.warning(quick_add_notification(0));
+    } else {
+        ui_state
+            .notifications
+            .info(quick_add_notification(added.len()));
+    }
+    ui_state.book_view.refresh().await?;
+    Ok(())
+}
+
+/// Returns a description of how far adding a directory has progressed.
+pub(crate) fn progress_message(progress: &Progress) -> String {
+    if progress.total == 0 {
+        "Finding books...".to_string()
+    } else {
+        format!("Read {} of {} files", progress.processed, progress.total)
+    }
+}
+
+/// Returns the notification shown after merging `count` books into similar books.
+pub(crate) fn merge_notification(count: usize) -> String {
+    match count {
+        0 => "No similar books to merge".to_string(),
+        1 => "Merged 1 book".to_string(),
+        n => format!("Merged {} books", n),
+    }
+}
+
+/// Returns the notification shown after rebuilding the full-text index.
+pub(crate) fn fts_notification(report: Option<FtsReport>) -> String {
+    match report {
+        None => "Full-text search is not available".to_string(),
+        Some(report) if report.is_consistent() => format!(
+            "Rebuilt the full-text index of {} books in {} ms",
+            report.books,
+            report.elapsed.as_millis()
+        ),
+        Some(report) => format!(
+            "Rebuilt the full-text index in {} ms, but {} of {} books are indexed",
+            report.elapsed.as_millis(),
+            report.indexed,
+            report.books
+        ),
+    }
+}
+
 pub fn to_tui(c: Color) -> tui::style::Color {
     match c {
         Color::Black => TColor::Black,
@@ -194,25 +812,39 @@ pub fn copy_from_clipboard() -> Option<String> {
 
 /// Takes `word`, and cuts excess letters to ensure that it fits within
 /// `max_width` visible characters. If `word` is too long, it will be truncated
-/// and have '...' appended to indicate that it has been truncated (if `max_width`
-/// is at least 3, otherwise, letters will simply be cut). It will then be returned as a
-/// `ListItem`.
+/// and have `indicator` appended to indicate that it has been truncated (if `max_width`
+/// is at least the width of `indicator`, otherwise, letters will simply be cut).
 ///
 /// # Arguments
 /// * ` word ` - A string reference.
 /// * ` max_width ` - The maximum width of word in visible characters.
-pub fn cut_word_to_fit(word: &str, max_width: usize) -> ListItemX {
-    // TODO: What should be done if max_width is too small?
-    ListItemX::new(Span::from(if word.len() > max_width {
-        if max_width >= 3 {
-            let possible_word = word.unicode_truncate(max_width - 3);
-            possible_word.0.to_owned() + "..."
-        } else {
-            word.unicode_truncate(max_width).0.to_owned()
-        }
+/// * ` indicator ` - The string appended to truncated words.
+pub fn truncate_to_width(word: &str, max_width: usize, indicator: &str) -> String {
+    if word.width() <= max_width {
+        return word.to_owned();
+    }
+
+    let indicator_width = indicator.width();
+    if max_width >= indicator_width {
+        word.unicode_truncate(max_width - indicator_width)
+            .0
+            .to_owned()
+            + indicator
     } else {
-        word.to_owned()
-    }))
+        word.unicode_truncate(max_width).0.to_owned()
+    }
+}
+
+/// Truncates `word` to fit within `max_width` visible characters using `truncate_to_width`,
+/// and returns it as a `ListItem`. The full value of a truncated cell can be seen by
+/// selecting the book, which displays it in the book widget.
+///
+/// # Arguments
+/// * ` word ` - A string reference.
+/// * ` max_width ` - The maximum width of word in visible characters.
+/// * ` indicator ` - The string appended to truncated words.
+pub fn cut_word_to_fit(word: &str, max_width: usize, indicator: &str) -> ListItemX {
+    ListItemX::new(Span::from(truncate_to_width(word, max_width, indicator)))
 }
 
 /// Splits `chunk` into `num_cols` columns with widths differing by no more than
@@ -248,6 +880,52 @@ pub fn split_chunk_into_columns(chunk: Rect, num_cols: u16) -> Vec<Rect> {
         .split(chunk)
 }
 
+/// Splits `chunk` into columns with the given widths. Columns with a fixed or percentage width
+/// are given their width, in order, for as long as `chunk` has space left. The remaining space
+/// is split between the automatic columns as described by `split_chunk_into_columns`, and is
+/// left empty if there are none.
+///
+/// # Arguments
+/// * ` chunk ` - A chunk which the columns will be placed into.
+/// * ` widths ` - The width of each column.
+pub fn split_chunk_by_widths(chunk: Rect, widths: &[Width]) -> Vec<Rect> {
+    let mut remaining = chunk.width;
+    let mut sizes: Vec<_> = widths
+        .iter()
+        .map(|&width| {
+            let size = match width {
+                Width::Auto => return None,
+                Width::Fixed(size) => size,
+                Width::Percent(percent) => {
+                    (u32::from(chunk.width) * u32::from(percent.min(100)) / 100) as u16
+                }
+            }
+            .min(remaining);
+            remaining -= size;
+            Some(size)
+        })
+        .collect();
+
+    let num_auto = sizes.iter().filter(|size| size.is_none()).count() as u16;
+    let mut auto_sizes = split_chunk_into_columns(Rect::new(0, 0, remaining, 0), num_auto)
+        .into_iter()
+        .map(|column| column.width);
+    for size in sizes.iter_mut().filter(|size| size.is_none()) {
+        *size = auto_sizes.next();
+    }
+
+    let mut x = chunk.x;
+    sizes
+        .into_iter()
+        .map(|size| {
+            let size = size.unwrap_or_default();
+            let column = Rect::new(x, chunk.y, size, chunk.height);
+            x += size;
+            column
+        })
+        .collect()
+}
+
 /// Takes the CharChunk and styles it with the provided styling rules.
 pub fn char_chunks_to_styled_text(c: CharChunks, styles: StyleRules) -> Spans {
     let mut text = vec![];
@@ -378,6 +1056,20 @@ impl StyleRules {
 mod test {
     use super::*;
 
+    use std::convert::TryFrom;
+
+    use bookworm_app::app::ImportEvent;
+    use bookworm_app::columns::Columns;
+    use bookworm_app::settings::Settings;
+    use bookworm_app::App;
+    use bookworm_database::search::{Search, SearchMode};
+    use bookworm_database::SQLiteDatabase;
+    use bookworm_records::book::ColumnIdentifier;
+    use bookworm_records::test_util::variant;
+    use bookworm_records::{BookVariant, ColumnOrder};
+
+    use crate::ui::notifications::Severity;
+
     #[test]
     fn test_chunk_split() {
         let width = 50;
@@ -395,4 +1087,466 @@ mod test {
             );
         }
     }
+
+    #[test]
+    fn test_chunk_split_by_widths() {
+        let c = Rect::new(2, 1, 100, 10);
+        let widths = |columns: &[Width]| {
+            split_chunk_by_widths(c, columns)
+                .iter()
+                .map(|r| r.width)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(widths(&[Width::Auto; 3]), vec![34, 33, 33]);
+        assert_eq!(
+            widths(&[
+                Width::Auto,
+                Width::Fixed(20),
+                Width::Percent(30),
+                Width::Auto
+            ]),
+            vec![25, 20, 30, 25]
+        );
+        assert_eq!(
+            widths(&[Width::Fixed(80), Width::Percent(50)]),
+            vec![80, 20]
+        );
+        assert_eq!(widths(&[Width::Fixed(120), Width::Auto]), vec![100, 0]);
+
+        let columns = split_chunk_by_widths(c, &[Width::Fixed(10), Width::Auto]);
+        assert_eq!(columns[0], Rect::new(2, 1, 10, 10));
+        assert_eq!(columns[1], Rect::new(12, 1, 90, 10));
+    }
+
+    #[test]
+    fn test_truncate_to_width() {
+        assert_eq!(truncate_to_width("Dune", 10, "..."), "Dune");
+        assert_eq!(truncate_to_width("Dune Messiah", 12, "..."), "Dune Messiah");
+        assert_eq!(truncate_to_width("Dune Messiah", 8, "..."), "Dune ...");
+        assert_eq!(truncate_to_width("Dune Messiah", 8, "…"), "Dune Me…");
+        assert_eq!(truncate_to_width("Dune Messiah", 2, "..."), "Du");
+        assert_eq!(truncate_to_width("Dune Messiah", 8, "…").width(), 8);
+        assert_eq!(truncate_to_width("デューン砂の惑星", 7, "…"), "デュー…");
+    }
+
+    #[tokio::test]
+    async fn test_quick_add() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        let (mut app, channel) = App::new(db);
+        tokio::spawn(async move {
+            let _ = app.event_loop().await;
+        });
+
+        let path = dir.path().join("book.epub");
+        std::fs::write(&path, b"").unwrap();
+        let ids = quick_add(&channel, path).await;
+        assert_eq!(ids.len(), 1);
+        assert_eq!(quick_add_notification(ids.len()), "Added 1 book");
+    }
+
+    #[test]
+    fn test_variants_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let present = dir.path().join("Dune.pdf");
+        std::fs::write(&present, b"%PDF-1.4").unwrap();
+        let mut book = Book::from_variant(
+            BookID::try_from(1).unwrap(),
+            BookVariant::from_path(&present).unwrap(),
+        );
+        book.push_variant(BookVariant {
+            path: dir.path().join("Dune.epub"),
+            file_size: 4096,
+            ..variant("Dune", 0xab)
+        });
+
+        let report = variants_report(&book);
+        let lines: Vec<_> = report.lines().collect();
+        assert_eq!(lines[0], "Variants of Dune.pdf:");
+        assert_eq!(lines[2], format!("0: {}", present.display()));
+        assert!(lines[3].starts_with("   PDF, 8 bytes, hash "));
+        assert!(lines[3].ends_with(", ok"));
+        assert_eq!(lines[5], "   EPUB, 4096 bytes, hash abababab..., missing");
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0, SizeUnits::Decimal), "0 B");
+        assert_eq!(format_size(999, SizeUnits::Decimal), "999 B");
+        assert_eq!(format_size(1500, SizeUnits::Decimal), "1.5 KB");
+        assert_eq!(format_size(1500, SizeUnits::Binary), "1.5 KiB");
+        assert_eq!(format_size(2_500_000_000, SizeUnits::Decimal), "2.5 GB");
+        assert_eq!(format_size(3 << 20, SizeUnits::Binary), "3.0 MiB");
+    }
+
+    #[test]
+    fn test_duplicates_report() {
+        let book = |id: u64, title: &str, authors: Option<&str>| {
+            let mut book = Book::from_variant(BookID::try_from(id).unwrap(), variant(title, 0));
+            book.authors = authors.map(|author| vec![author.to_string()]);
+            Arc::new(book)
+        };
+        assert_eq!(duplicates_report(&[]), "No duplicate books found.\n");
+
+        let groups = vec![vec![
+            book(1, "Dune", Some("Frank Herbert")),
+            book(4, "dune", None),
+        ]];
+        assert_eq!(
+            duplicates_report(&groups),
+            "Duplicate books:\n\nGroup 1:\n  1: Dune, by Frank Herbert\n  4: dune, by unknown\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_export_view() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        let titles = [
+            "alpha match",
+            "beta",
+            "gamma match",
+            "delta match",
+            "epsilon",
+        ];
+        db.insert_books(
+            titles
+                .iter()
+                .enumerate()
+                .map(|(i, title)| variant(title, i as u8)),
+        )
+        .await
+        .unwrap();
+        let (mut app, mut channel) = App::new(db);
+        tokio::spawn(async move {
+            let _ = app.event_loop().await;
+        });
+
+        let mut ui_state = UIState {
+            style: Default::default(),
+            nav_settings: Default::default(),
+            curr_command: Default::default(),
+            selected_column: 0,
+            table_view: Default::default(),
+            truncation: String::from("..."),
+            book_view: channel.new_book_view().await,
+            sort_settings: Default::default(),
+            quick_add: false,
+            notification: None,
+            notifications: Default::default(),
+            covers: None,
+            placed_cover: None,
+            import: None,
+        };
+
+        let path = dir.path().join("view.json");
+        let commands = vec![
+            Command::FilterMatches(
+                vec![Search {
+                    mode: SearchMode::Default,
+                    column: ColumnIdentifier::Title,
+                    search: "match".to_string(),
+                }]
+                .into_boxed_slice(),
+            ),
+            Command::SortColumns(
+                vec![(ColumnIdentifier::Title, ColumnOrder::Descending)].into_boxed_slice(),
+            ),
+            Command::ExportView(ExportFormat::Json, path.clone()),
+        ];
+        for command in commands {
+            run_command(&mut channel, command, &mut ui_state)
+                .await
+                .unwrap();
+        }
+
+        let exported: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        let exported: Vec<_> = exported
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|book| book["title"].as_str().unwrap())
+            .collect();
+        assert_eq!(exported, vec!["gamma match", "delta match", "alpha match"]);
+    }
+
+    #[tokio::test]
+    async fn test_restore_filters() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        let titles = ["Dune", "Dune Messiah", "Emma"];
+        db.insert_books(
+            titles
+                .iter()
+                .enumerate()
+                .map(|(i, title)| variant(title, i as u8)),
+        )
+        .await
+        .unwrap();
+        let (mut app, mut channel) = App::new(db);
+        tokio::spawn(async move {
+            let _ = app.event_loop().await;
+        });
+
+        let search = |title: &str| Search {
+            mode: SearchMode::Default,
+            column: ColumnIdentifier::Title,
+            search: title.to_string(),
+        };
+        let titles = |books: Vec<Arc<Book>>| -> Vec<String> {
+            books.iter().filter_map(|book| book.title.clone()).collect()
+        };
+
+        // A persisted filter is applied when the settings are next opened, and a temporary
+        // filter applied on top of it is not persisted.
+        let path = dir.path().join("settings.toml");
+        let mut settings = Settings::default();
+        settings.session_settings.filters = vec![vec![search("dune")].into_boxed_slice()];
+        settings.write(&path).unwrap();
+        let (_, app_settings) = Settings::open(&path).unwrap().split();
+
+        let mut book_view = channel.new_book_view().await;
+        restore_filters(&mut book_view, &app_settings.session_settings.filters).await;
+        let mut ui_state = UIState {
+            style: Default::default(),
+            nav_settings: Default::default(),
+            curr_command: Default::default(),
+            selected_column: 0,
+            table_view: Default::default(),
+            truncation: String::from("..."),
+            book_view,
+            sort_settings: Default::default(),
+            quick_add: false,
+            notification: None,
+            notifications: Default::default(),
+            covers: None,
+            placed_cover: None,
+            import: None,
+        };
+        let books = ui_state.book_view.read_all().await.unwrap();
+        assert_eq!(titles(books), vec!["Dune", "Dune Messiah"]);
+
+        run_command(
+            &mut channel,
+            Command::TempFilterMatches(vec![search("messiah")].into_boxed_slice()),
+            &mut ui_state,
+        )
+        .await
+        .unwrap();
+        let books = ui_state.book_view.read_all().await.unwrap();
+        assert_eq!(titles(books), vec!["Dune Messiah"]);
+        assert_eq!(
+            ui_state.book_view.persisted_filters(),
+            app_settings.session_settings.filters
+        );
+
+        // Filters which are no longer valid are skipped.
+        let invalid = Search {
+            mode: SearchMode::Regex,
+            column: ColumnIdentifier::Title,
+            search: "(".to_string(),
+        };
+        let mut book_view = channel.new_book_view().await;
+        restore_filters(&mut book_view, &[vec![invalid].into_boxed_slice()]).await;
+        assert!(book_view.persisted_filters().is_empty());
+        assert_eq!(book_view.read_all().await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_hidden_columns_are_searchable() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = SQLiteDatabase::open(dir.path().join("test.db"))
+            .await
+            .unwrap();
+        let books = [("Dune", "Frank Herbert"), ("Emma", "Jane Austen")];
+        db.insert_books(books.iter().enumerate().map(|(i, (title, author))| {
+            let mut variant = variant(title, i as u8);
+            variant.additional_authors = Some(vec![author.to_string()]);
+            variant
+        }))
+        .await
+        .unwrap();
+        let (mut app, mut channel) = App::new(db);
+        tokio::spawn(async move {
+            let _ = app.event_loop().await;
+        });
+
+        let mut ui_state = UIState {
+            style: Default::default(),
+            nav_settings: Default::default(),
+            curr_command: Default::default(),
+            selected_column: 0,
+            table_view: Columns::new(vec!["Title".to_string()], vec!["Title".to_string()]),
+            truncation: String::from("..."),
+            book_view: channel.new_book_view().await,
+            sort_settings: Default::default(),
+            quick_add: false,
+            notification: None,
+            notifications: Default::default(),
+            covers: None,
+            placed_cover: None,
+            import: None,
+        };
+        let filter = |column: ColumnIdentifier, search: &str| {
+            Command::FilterMatches(
+                vec![Search {
+                    mode: SearchMode::Default,
+                    column,
+                    search: search.to_string(),
+                }]
+                .into_boxed_slice(),
+            )
+        };
+        let titles = |books: Vec<Arc<Book>>| -> Vec<String> {
+            books.iter().filter_map(|book| book.title.clone()).collect()
+        };
+
+        // Authors are not displayed, but can still be used to filter books.
+        run_command(
+            &mut channel,
+            filter(ColumnIdentifier::Author, "Austen"),
+            &mut ui_state,
+        )
+        .await
+        .unwrap();
+        let books = ui_state.book_view.read_all().await.unwrap();
+        assert_eq!(titles(books.clone()), vec!["Emma"]);
+        let columns: Vec<_> = ui_state
+            .table_view
+            .read_columns(&books)
+            .map(|(column, _)| column.clone().into_inner())
+            .collect();
+        assert_eq!(columns, vec!["Title"]);
+
+        // Titles are displayed, but can not be used to filter books.
+        run_command(
+            &mut channel,
+            filter(ColumnIdentifier::Title, "Dune"),
+            &mut ui_state,
+        )
+        .await
+        .unwrap();
+        assert!(ui_state.notification.is_some());
+        let books = ui_state.book_view.read_all().await.unwrap();
+        assert_eq!(titles(books), vec!["Emma"]);
+
+        // Invalid patterns are reported, rather than being ignored.
+        ui_state.notification = None;
+        let invalid = Search {
+            mode: SearchMode::Regex,
+            column: ColumnIdentifier::Author,
+            search: "(".to_string(),
+        };
+        run_command(
+            &mut channel,
+            Command::FilterMatches(vec![invalid].into_boxed_slice()),
+            &mut ui_state,
+        )
+        .await
+        .unwrap();
+        assert!(ui_state
+            .notification
+            .take()
+            .unwrap()
+            .starts_with("Invalid regular expression"));
+        let books = ui_state.book_view.read_all().await.unwrap();
+        assert_eq!(titles(books), vec!["Emma"]);
+
+        // Commands which change the library report their results.
+        let newest = |ui_state: &UIState<SQLiteDatabase>| {
+            let notification = ui_state.notifications.newest(1).next().unwrap();
+            (notification.message.clone(), notification.severity)
+        };
+        let task = run_command(&mut channel, Command::TryMergeAllBooks, &mut ui_state)
+            .await
+            .unwrap();
+        assert!(matches!(task, ApplicationTask::UpdateUI));
+        assert_eq!(
+            newest(&ui_state),
+            ("No similar books to merge".to_string(), Severity::Info)
+        );
+        run_command(&mut channel, Command::DeleteAll, &mut ui_state)
+            .await
+            .unwrap();
+        assert_eq!(
+            newest(&ui_state),
+            ("Deleted all books".to_string(), Severity::Info)
+        );
+        run_command(&mut channel, Command::DeleteSelected, &mut ui_state)
+            .await
+            .unwrap();
+        assert_eq!(
+            newest(&ui_state),
+            ("No books are selected".to_string(), Severity::Warning)
+        );
+
+        // Directories are added while the interface reads their progress.
+        let shelf = dir.path().join("shelf");
+        std::fs::create_dir(&shelf).unwrap();
+        for i in 0..2 {
+            std::fs::write(shelf.join(format!("{}.epub", i)), i.to_string()).unwrap();
+        }
+        let task = run_command(&mut channel, Command::AddDir(shelf, 1), &mut ui_state)
+            .await
+            .unwrap();
+        assert!(matches!(task, ApplicationTask::UpdateUI));
+        assert_eq!(ui_state.import, Some(Progress::default()));
+        let added = loop {
+            if let ImportEvent::Done(added) = channel.import_event().await {
+                break added;
+            }
+        };
+        ui_state.import = None;
+        books_added(&mut ui_state, &added).await.unwrap();
+        assert_eq!(
+            newest(&ui_state),
+            ("Added 2 books".to_string(), Severity::Info)
+        );
+    }
+
+    #[test]
+    fn test_diff_rows() {
+        let mut left = Book::default();
+        left.title = Some("Dune".to_string());
+        left.authors = Some(vec!["Frank Herbert".to_string()]);
+        let mut right = left.clone();
+        right.title = Some("Dune Messiah".to_string());
+        right
+            .named_tags
+            .insert("language".to_string(), "en".to_string());
+
+        let rows = diff_rows(&left, &right);
+        let differing: Vec<_> = rows.iter().filter(|row| row.differs).collect();
+        assert_eq!(
+            differing,
+            vec![
+                &DiffRow {
+                    column: "Title".to_string(),
+                    left: "Dune".to_string(),
+                    right: "Dune Messiah".to_string(),
+                    differs: true,
+                },
+                &DiffRow {
+                    column: "language".to_string(),
+                    left: String::new(),
+                    right: "en".to_string(),
+                    differs: true,
+                },
+            ]
+        );
+        assert!(rows.contains(&DiffRow {
+            column: "Author".to_string(),
+            left: "Frank Herbert".to_string(),
+            right: "Frank Herbert".to_string(),
+            differs: false,
+        }));
+    }
 }
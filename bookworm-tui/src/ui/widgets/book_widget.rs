@@ -1,44 +1,87 @@
 use std::marker::PhantomData;
 use std::sync::Arc;
 
+use std::fs::File;
+
 use crossterm::event::{Event, MouseEventKind};
 use tui::backend::Backend;
-use tui::layout::Rect;
+use tui::layout::{Constraint, Direction, Layout, Rect};
 use tui::style::{Modifier, Style};
 use tui::text::Text;
 use tui::widgets::Paragraph;
 use tui::Frame;
 
 use bookworm_app::app::AppChannel;
-use bookworm_database::AppDatabase;
+use bookworm_database::{AppDatabase, BookView};
+use bookworm_records::book::AuthorDisplay;
+use bookworm_records::variant::BookType;
 use bookworm_records::Book;
 
+use crate::ui::cover::{Cover, CoverBlocks, CoverProtocol};
 use crate::ui::scrollable_text::BlindOffset;
 use crate::ui::widgets::Widget;
 use crate::{ApplicationTask, TuiError, UIState};
 
 use async_trait::async_trait;
 
+/// Reads the cover of `book` - the cover stored in the database if it has one, or otherwise, the
+/// cover of its first EPUB variant.
+async fn read_cover<D: AppDatabase + Send + Sync>(
+    book_view: &BookView<D>,
+    book: &Book,
+) -> Option<Vec<u8>> {
+    if book.has_cover {
+        return book_view.cover(book.id()).await.ok().flatten();
+    }
+    book.variants()
+        .iter()
+        .filter(|variant| variant.book_type() == &BookType::EPUB)
+        .find_map(|variant| {
+            let mut file = File::open(variant.path()).ok()?;
+            bookworm_records::read_cover(&mut file).ok().flatten()
+        })
+}
+
 /// Contains information needed to render a book.
 /// Only guaranteed to reflect the current state of the book if no
 /// EditCommand occurs - should be regenerated during the prepare_render call
 pub struct BookWidget<D> {
     chunk: Rect,
     offset: BlindOffset,
-    book: Arc<Book>,
+    book: Option<Arc<Book>>,
+    author_display: AuthorDisplay,
+    /// The cover of `book`, if covers are shown and it has one.
+    cover: Option<Arc<Cover>>,
+    /// The area which the cover is drawn in, and the cover scaled to fill it, if it is drawn
+    /// with blocks.
+    cover_area: Rect,
+    blocks: Option<CoverBlocks>,
     pub(crate) database: PhantomData<fn(D)>,
 }
 
-impl<D> BookWidget<D> {
-    pub fn new(chunk: Rect, book: Arc<Book>) -> Self {
-        let mut book_widget = BookWidget {
-            chunk,
+impl<D> Default for BookWidget<D> {
+    fn default() -> Self {
+        BookWidget {
+            chunk: Rect::default(),
             offset: BlindOffset::new(),
-            book,
+            book: None,
+            author_display: AuthorDisplay::default(),
+            cover: None,
+            cover_area: Rect::default(),
+            blocks: None,
             database: PhantomData,
+        }
+    }
+}
+
+impl<D> BookWidget<D> {
+    pub fn new(chunk: Rect, book: Arc<Book>, author_display: AuthorDisplay) -> Self {
+        let mut book_widget = BookWidget {
+            book: Some(book),
+            author_display,
+            ..Self::default()
         };
-        let height = chunk.height as usize;
-        book_widget.offset.refresh_window_height(height as usize);
+        book_widget.set_chunk(chunk);
         book_widget
     }
 
@@ -51,6 +94,10 @@ impl<D> BookWidget<D> {
     }
 
     pub fn to_widget_text(&self) -> Text {
+        let book = match &self.book {
+            Some(book) => book,
+            None => return Text::default(),
+        };
         let width = self.chunk.width as usize;
         let field_exists = Style::default().add_modifier(Modifier::BOLD);
         let field_not_provided = Style::default();
@@ -59,27 +106,27 @@ impl<D> BookWidget<D> {
             Err(_) => None,
         };
 
-        let mut data = if let Some(t) = self.book.title() {
+        let mut data = if let Some(t) = book.title() {
             Text::styled(t.to_string(), field_exists)
         } else {
             Text::styled("No title provided", field_not_provided)
         };
 
-        if let Some(a) = self.book.authors() {
+        if let Some(a) = self.author_display.display(book) {
             let mut s = String::from("By: ");
-            s.push_str(&a.join(", "));
+            s.push_str(&a);
             data.extend(Text::styled(s, field_exists));
         } else {
             data.extend(Text::styled("No author provided", field_not_provided));
         }
 
-        if let Some(d) = self.book.description() {
+        if let Some(d) = book.description() {
             data.extend(Text::styled("\n", field_exists));
             // TODO: Make this look nice in the TUI.
             data.extend(Text::raw(html2text::from_read(d.as_bytes(), width)));
         }
 
-        let columns = self.book.tags();
+        let columns = book.tags();
         if !columns.is_empty() {
             data.extend(Text::raw("\nNamed tags provided:"));
             for (key, value) in columns.iter() {
@@ -90,7 +137,7 @@ impl<D> BookWidget<D> {
             }
         }
 
-        let free_tags = self.book.free_tags();
+        let free_tags = book.free_tags();
         if !free_tags.is_empty() {
             data.extend(Text::raw("\nTags provided:"));
             for value in free_tags.iter() {
@@ -98,7 +145,7 @@ impl<D> BookWidget<D> {
             }
         }
 
-        let variants = self.book.variants();
+        let variants = book.variants();
         if !variants.is_empty() {
             data.extend(Text::raw("\nVariant paths:"));
             for variant in variants {
@@ -130,21 +177,60 @@ impl<D> BookWidget<D> {
 #[async_trait]
 impl<'b, D: AppDatabase + Send + Sync, B: Backend> Widget<D, B> for BookWidget<D> {
     async fn prepare_render(&mut self, state: &mut UIState<D>, chunk: Rect) {
-        // need to push to parent
-        let books = state.book_view.selected_books();
-        *self = books
-            .front()
-            .map(|book| BookWidget::new(Rect::default(), book.clone()))
-            .unwrap();
-
-        self.set_chunk(chunk);
+        let book = state.book_view.selected_books().front().cloned();
+        let changed = match (&self.book, &book) {
+            (Some(current), Some(book)) => current.id != book.id,
+            (None, None) => false,
+            _ => true,
+        };
+        if changed {
+            self.offset = BlindOffset::new();
+            self.cover = None;
+            self.blocks = None;
+            if let (Some(book), Some(_)) = (&book, state.covers) {
+                self.cover = read_cover(&state.book_view, book)
+                    .await
+                    .and_then(|bytes| Cover::decode(&bytes))
+                    .map(Arc::new);
+            }
+        }
+        self.book = book;
+        self.author_display = state.table_view.author_display().clone();
+
+        // The cover takes up to half of the widget, and the text fills the rest.
+        let text_chunk = match (&self.cover, state.covers) {
+            (Some(cover), Some(protocol)) => {
+                let vchunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Percentage(50), Constraint::Min(0)])
+                    .split(chunk);
+                let cover_area = cover.fit(vchunks[0]);
+                if changed || cover_area != self.cover_area {
+                    self.blocks = match protocol {
+                        CoverProtocol::Blocks => Some(cover.blocks(cover_area)),
+                        CoverProtocol::Kitty => None,
+                    };
+                }
+                if protocol == CoverProtocol::Kitty {
+                    state.placed_cover = Some((cover_area, cover.clone()));
+                }
+                self.cover_area = cover_area;
+                let height = chunk.height.saturating_sub(cover_area.height + 1);
+                Rect::new(chunk.x, chunk.bottom() - height, chunk.width, height)
+            }
+            _ => chunk,
+        };
+        self.set_chunk(text_chunk);
     }
 
-    fn render_into_frame(&self, f: &mut Frame<B>, _state: &UIState<D>, chunk: Rect) {
+    fn render_into_frame(&self, f: &mut Frame<B>, _state: &UIState<D>, _chunk: Rect) {
+        if let Some(blocks) = &self.blocks {
+            f.render_widget(blocks, self.cover_area);
+        }
         let book_text = self.to_widget_text();
         let offset = self.offset.offset();
         let p = Paragraph::new(book_text).scroll((offset as u16, 0));
-        f.render_widget(p, chunk);
+        f.render_widget(p, self.chunk);
     }
 
     async fn handle_input(
@@ -177,9 +263,9 @@ impl<'b, D: AppDatabase + Send + Sync, B: Backend> Widget<D, B> for BookWidget<D
                     return Ok(ApplicationTask::DoNothing);
                 }
             },
-            _ => {
-                return Ok(ApplicationTask::UpdateUI);
-            }
+            Event::Resize(_, _) => return Ok(ApplicationTask::UpdateUI),
+            // Keys are left to the table, so that focus can be tabbed back to it.
+            Event::Key(_) => return Ok(ApplicationTask::DoNothing),
         }
         Ok(ApplicationTask::UpdateUI)
     }
@@ -3,6 +3,7 @@ use std::marker::PhantomData;
 use crossterm::event::{Event, KeyCode, KeyModifiers, MouseEventKind};
 use tui::backend::Backend;
 use tui::layout::Rect;
+use tui::style::{Modifier, Style};
 use tui::text::Span;
 use tui::widgets::Block;
 use tui::Frame;
@@ -12,7 +13,7 @@ use bookworm_app::Command;
 use bookworm_database::{AppDatabase, DatabaseError};
 
 use crate::ui::tui_widgets::{MultiSelectList, MultiSelectListState};
-use crate::ui::utils::{cut_word_to_fit, split_chunk_into_columns, TuiStyle};
+use crate::ui::utils::{cut_word_to_fit, split_chunk_by_widths, TuiStyle};
 use crate::ui::widgets::Widget;
 use crate::{run_command, AppView, ApplicationTask, TuiError, UIState};
 
@@ -194,7 +195,7 @@ impl<'b, D: AppDatabase + Send + Sync, B: Backend> Widget<D, B> for ColumnWidget
     }
 
     fn render_into_frame(&self, f: &mut Frame<B>, state: &UIState<D>, chunk: Rect) {
-        let hchunks = split_chunk_into_columns(chunk, state.num_cols() as u16);
+        let hchunks = split_chunk_by_widths(chunk, &state.table_view.selected_widths());
 
         // let edit_style = state.style.edit_style();
         let select_style = state.style.select_style();
@@ -231,13 +232,26 @@ impl<'b, D: AppDatabase + Send + Sync, B: Backend> Widget<D, B> for ColumnWidget
         // }
 
         let books = state.book_view.window();
+        let highlight_style = Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+        let highlighted: Vec<_> = books
+            .iter()
+            .map(|book| state.book_view.is_highlighted(book))
+            .collect();
         for ((title, data), &chunk) in state.table_view.read_columns(books).zip(hchunks.iter()) {
             let width = usize::from(chunk.width).saturating_sub(1);
             let column: Vec<_> = data.collect();
             let list = MultiSelectList::new(
                 column
                     .iter()
-                    .map(|word| cut_word_to_fit(word, width))
+                    .zip(highlighted.iter())
+                    .map(|(word, &highlighted)| {
+                        let item = cut_word_to_fit(word, width, &state.truncation);
+                        if highlighted {
+                            item.style(highlight_style)
+                        } else {
+                            item
+                        }
+                    })
                     .collect::<Vec<_>>(),
             )
             .block(Block::default().title(Span::from(title.to_string())))
@@ -287,8 +301,19 @@ impl<'b, D: AppDatabase + Send + Sync, B: Backend> Widget<D, B> for ColumnWidget
                     }
                     // if active widget, deactivates
                     KeyCode::Esc => {
-                        state.book_view.deselect_all();
-                        state.book_view.pop_scope();
+                        if !state.book_view.clear_highlight() {
+                            state.book_view.deselect_all();
+                            state.book_view.pop_scope();
+                        }
+                    }
+                    KeyCode::Char('f') if event.modifiers == KeyModifiers::CONTROL => {
+                        return Ok(ApplicationTask::SwitchView(AppView::Find));
+                    }
+                    KeyCode::Char('n') => {
+                        run_command(app, Command::NextMatch, state).await?;
+                    }
+                    KeyCode::Char('N') => {
+                        run_command(app, Command::PrevMatch, state).await?;
                     }
                     KeyCode::Delete => {
                         return if !state.book_view.selected_books().is_empty() {
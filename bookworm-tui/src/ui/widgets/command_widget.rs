@@ -1,4 +1,5 @@
 use std::marker::PhantomData;
+use std::path::PathBuf;
 
 use crossterm::event::{Event, KeyCode, KeyModifiers};
 use tui::backend::Backend;
@@ -9,15 +10,17 @@ use tui::widgets::Paragraph;
 use tui::Frame;
 
 use bookworm_app::app::AppChannel;
-use bookworm_app::parser::Source;
+use bookworm_app::parser::{CommandError, Source, COLUMN_COMMANDS, COMMAND_NAMES};
 use bookworm_app::{parse_args, Command};
 use bookworm_database::{AppDatabase, DatabaseError};
+use bookworm_records::book::COLUMN_NAMES;
 
 use crate::ui::utils::{
-    char_chunks_to_styled_text, copy_from_clipboard, paste_into_clipboard, StyleRules,
+    char_chunks_to_styled_text, copy_from_clipboard, paste_into_clipboard, quick_add,
+    quick_add_notification, StyleRules,
 };
 use crate::ui::widgets::Widget;
-use crate::{run_command, ApplicationTask, TuiError, UIState};
+use crate::{run_command, AppView, ApplicationTask, TuiError, UIState};
 
 use async_trait::async_trait;
 
@@ -31,8 +34,15 @@ impl<'b, D: AppDatabase + Send + Sync, B: Backend> Widget<D, B> for CommandWidge
 
     fn render_into_frame(&self, f: &mut Frame<B>, state: &UIState<D>, chunk: Rect) {
         let command_widget = if state.curr_command.is_empty() {
+            let placeholder = if state.quick_add {
+                "Enter path to add"
+            } else if let Some(notification) = &state.notification {
+                notification.as_str()
+            } else {
+                "Enter command or search"
+            };
             Paragraph::new(Text::styled(
-                "Enter command or search",
+                placeholder,
                 Style::default().add_modifier(Modifier::BOLD),
             ))
         } else {
@@ -60,6 +70,7 @@ impl<'b, D: AppDatabase + Send + Sync, B: Backend> Widget<D, B> for CommandWidge
     ) -> Result<ApplicationTask, TuiError<D::Error>> {
         match event {
             Event::Key(event) => {
+                state.notification = None;
                 // Text input
                 match event.code {
                     KeyCode::Backspace => {
@@ -101,6 +112,9 @@ impl<'b, D: AppDatabase + Send + Sync, B: Backend> Widget<D, B> for CommandWidge
                                     state.curr_command.deselect();
                                 }
                                 ('a', _) => state.curr_command.select_all(),
+                                ('f', _) if state.curr_command.is_empty() => {
+                                    return Ok(ApplicationTask::SwitchView(AppView::Find));
+                                }
                                 _ => state.curr_command.push(x),
                             }
                         } else {
@@ -118,6 +132,16 @@ impl<'b, D: AppDatabase + Send + Sync, B: Backend> Widget<D, B> for CommandWidge
                         //     };
                         // }
 
+                        if state.quick_add {
+                            state.quick_add = false;
+                            let path = PathBuf::from(state.curr_command.to_string());
+                            state.curr_command.clear();
+                            let added = quick_add(app, path).await;
+                            state.notification = Some(quick_add_notification(added.len()));
+                            state.book_view.refresh().await?;
+                            return Ok(ApplicationTask::UpdateUI);
+                        }
+
                         let args: Vec<_> = state
                             .curr_command
                             .autofilled_values()
@@ -132,6 +156,10 @@ impl<'b, D: AppDatabase + Send + Sync, B: Backend> Widget<D, B> for CommandWidge
                                 ApplicationTask::DoNothing => Ok(ApplicationTask::UpdateUI),
                                 other => Ok(other),
                             },
+                            Err(CommandError::InvalidSearch(e)) => {
+                                state.notification = Some(e.to_string());
+                                Ok(ApplicationTask::UpdateUI)
+                            }
                             Err(_) => {
                                 // TODO: How should invalid commands be handled?
                                 Ok(ApplicationTask::UpdateUI)
@@ -145,6 +173,31 @@ impl<'b, D: AppDatabase + Send + Sync, B: Backend> Widget<D, B> for CommandWidge
                         if curr_command.is_empty() {
                             return Ok(ApplicationTask::DoNothing);
                         }
+                        let values: Vec<_> = curr_command.get_values().map(|(_, s)| s).collect();
+                        let new_word = curr_command.to_string().ends_with(' ');
+                        match values.as_slice() {
+                            [command] if !new_word && command.starts_with(':') => {
+                                curr_command
+                                    .auto_fill_words(COMMAND_NAMES.iter().map(|s| s.to_string()));
+                                return Ok(ApplicationTask::UpdateUI);
+                            }
+                            [command, ..] if COLUMN_COMMANDS.contains(&command.as_str()) => {
+                                let mut columns: Vec<_> = COLUMN_NAMES
+                                    .iter()
+                                    .map(|s| s.to_string())
+                                    .chain(state.table_view.known_cols().map(|c| c.to_lowercase()))
+                                    .collect();
+                                // Columns are removed with :c -column.
+                                if command == ":c" {
+                                    let removed: Vec<_> =
+                                        columns.iter().map(|c| format!("-{}", c)).collect();
+                                    columns.extend(removed);
+                                }
+                                curr_command.auto_fill_words(columns);
+                                return Ok(ApplicationTask::UpdateUI);
+                            }
+                            _ => {}
+                        }
                         curr_command.refresh_autofill()?;
                         match parse_args(curr_command.get_values().map(|(_, s)| s).collect()) {
                             Ok(command) => match command {
@@ -159,13 +212,19 @@ impl<'b, D: AppDatabase + Send + Sync, B: Backend> Widget<D, B> for CommandWidge
                                         _ => {}
                                     }
                                 }
+                                Command::AddDir(_, _) => {
+                                    curr_command.auto_fill(true);
+                                }
                                 _ => {}
                             },
                             Err(_) => {}
                         }
                     }
                     // if active widget, deactivates
-                    KeyCode::Esc => state.curr_command.clear(),
+                    KeyCode::Esc => {
+                        state.quick_add = false;
+                        state.curr_command.clear();
+                    }
                     KeyCode::Delete => state.curr_command.del(),
                     // Scrolling
                     KeyCode::Up => self.select_up(state, event.modifiers).await?,
@@ -0,0 +1,145 @@
+use std::marker::PhantomData;
+
+use crossterm::event::{Event, KeyCode, MouseEventKind};
+use tui::backend::Backend;
+use tui::layout::{Constraint, Direction, Layout, Rect};
+use tui::style::{Modifier, Style};
+use tui::text::Text;
+use tui::widgets::{Cell, Paragraph, Row, Table};
+use tui::Frame;
+
+use bookworm_app::app::AppChannel;
+use bookworm_database::AppDatabase;
+use bookworm_records::Book;
+
+use crate::ui::utils::{diff_rows, DiffRow, TuiStyle};
+use crate::ui::widgets::Widget;
+use crate::{AppView, ApplicationTask, TuiError, UIState};
+
+/// Shows two books side by side, one column per row, highlighting the columns which differ.
+pub struct DiffWidget<D> {
+    header: [String; 3],
+    rows: Vec<DiffRow>,
+    offset: usize,
+    height: usize,
+    database: PhantomData<fn(D)>,
+}
+
+impl<D> DiffWidget<D> {
+    pub(crate) fn new(left: &Book, right: &Book) -> Self {
+        let label = |book: &Book| match book.id {
+            Some(id) => format!("{} ({})", book, id),
+            None => book.to_string(),
+        };
+        DiffWidget {
+            header: ["Column".to_string(), label(left), label(right)],
+            rows: diff_rows(left, right),
+            offset: 0,
+            height: 0,
+            database: PhantomData,
+        }
+    }
+
+    fn scroll_up(&mut self, scroll: usize) {
+        self.offset = self.offset.saturating_sub(scroll);
+    }
+
+    fn scroll_down(&mut self, scroll: usize) {
+        let max_offset = self.rows.len().saturating_sub(self.height);
+        self.offset = (self.offset + scroll).min(max_offset);
+    }
+}
+
+use async_trait::async_trait;
+
+#[async_trait]
+impl<'b, D: AppDatabase + Send + Sync, B: Backend> Widget<D, B> for DiffWidget<D> {
+    async fn prepare_render(&mut self, _state: &mut UIState<D>, chunk: Rect) {
+        // One line for the header, and one for the footer.
+        self.height = usize::from(chunk.height.saturating_sub(2));
+        self.scroll_down(0);
+    }
+
+    fn render_into_frame(&self, f: &mut Frame<B>, state: &UIState<D>, chunk: Rect) {
+        let vchunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(chunk.height.saturating_sub(1)),
+                Constraint::Length(1),
+            ])
+            .split(chunk);
+
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let header = Row::new(self.header.iter().map(|h| Cell::from(h.as_str()))).style(bold);
+        let rows = self.rows.iter().skip(self.offset).map(|row| {
+            let style = if row.differs {
+                state.style.select_style()
+            } else {
+                Style::default()
+            };
+            Row::new(vec![
+                Cell::from(row.column.as_str()),
+                Cell::from(row.left.as_str()),
+                Cell::from(row.right.as_str()),
+            ])
+            .style(style)
+        });
+
+        let widths = [
+            Constraint::Percentage(20),
+            Constraint::Percentage(40),
+            Constraint::Percentage(40),
+        ];
+        let table = Table::new(rows)
+            .header(header)
+            .widths(&widths)
+            .column_spacing(1);
+        f.render_widget(table, vchunks[0]);
+
+        let text = Text::styled("Press ESC to return", bold);
+        f.render_widget(Paragraph::new(text), vchunks[1])
+    }
+
+    async fn handle_input(
+        &mut self,
+        event: Event,
+        state: &mut UIState<D>,
+        _app: &mut AppChannel<D>,
+    ) -> Result<ApplicationTask, TuiError<D::Error>> {
+        match event {
+            Event::Resize(_, _) => return Ok(ApplicationTask::UpdateUI),
+            Event::Mouse(m) => match m.kind {
+                MouseEventKind::ScrollDown => {
+                    let scroll = state.nav_settings.scroll;
+                    if state.nav_settings.inverted {
+                        self.scroll_up(scroll)
+                    } else {
+                        self.scroll_down(scroll)
+                    };
+                }
+                MouseEventKind::ScrollUp => {
+                    let scroll = state.nav_settings.scroll;
+                    if state.nav_settings.inverted {
+                        self.scroll_down(scroll)
+                    } else {
+                        self.scroll_up(scroll)
+                    };
+                }
+                _ => {
+                    return Ok(ApplicationTask::DoNothing);
+                }
+            },
+            Event::Key(event) => match event.code {
+                KeyCode::Esc => return Ok(ApplicationTask::SwitchView(AppView::Columns)),
+                KeyCode::Up => self.scroll_up(1),
+                KeyCode::Down => self.scroll_down(1),
+                KeyCode::PageUp => self.scroll_up(self.height),
+                KeyCode::PageDown => self.scroll_down(self.height),
+                KeyCode::Home => self.offset = 0,
+                KeyCode::End => self.scroll_down(self.rows.len()),
+                _ => return Ok(ApplicationTask::DoNothing),
+            },
+        }
+        Ok(ApplicationTask::UpdateUI)
+    }
+}
@@ -18,7 +18,7 @@ use bookworm_records::book::{BookID, ColumnIdentifier, RecordError};
 
 use crate::ui::tui_widgets::{ListItemX, MultiSelectList, MultiSelectListState};
 use crate::ui::utils::{
-    char_chunks_to_styled_text, copy_from_clipboard, split_chunk_into_columns, StyleRules, TuiStyle,
+    char_chunks_to_styled_text, copy_from_clipboard, split_chunk_by_widths, StyleRules, TuiStyle,
 };
 use crate::ui::widgets::Widget;
 use crate::{run_command, AppView, ApplicationTask, TuiError, UIState};
@@ -83,7 +83,7 @@ impl<'b, D: AppDatabase + Send + Sync, B: Backend> Widget<D, B> for EditWidget<D
     }
 
     fn render_into_frame(&self, f: &mut Frame<B>, state: &UIState<D>, chunk: Rect) {
-        let hchunks = split_chunk_into_columns(chunk, state.num_cols() as u16);
+        let hchunks = split_chunk_by_widths(chunk, &state.table_view.selected_widths());
 
         let edit_style = state.style.edit_style();
         let select_style = state.style.select_style();
@@ -0,0 +1,186 @@
+use std::marker::PhantomData;
+
+use crossterm::event::{Event, KeyCode, KeyModifiers};
+use tui::backend::Backend;
+use tui::layout::Rect;
+use tui::style::{Color, Modifier, Style};
+use tui::text::Span;
+use tui::widgets::Paragraph;
+use tui::Frame;
+
+use bookworm_app::app::AppChannel;
+use bookworm_database::search::{Search, SearchMode};
+use bookworm_database::AppDatabase;
+use bookworm_input::user_input::CommandString;
+use bookworm_records::book::ColumnIdentifier;
+
+use crate::ui::layouts::{EditLayout, LayoutGenerator};
+use crate::ui::utils::{char_chunks_to_styled_text, StyleRules};
+use crate::ui::widgets::{ColumnWidget, Widget};
+use crate::{AppView, ApplicationTask, TuiError, UIState};
+
+use async_trait::async_trait;
+
+/// Filters the table to the books whose selected column contains the text typed so far,
+/// updating the filter as the user types. Enter keeps the filter, and Esc removes it.
+pub struct FindWidget<D> {
+    input: CommandString,
+    column: ColumnIdentifier,
+    /// The text which the current filter searches for.
+    applied: String,
+    /// Whether a filter has been pushed onto the book view by this widget.
+    scoped: bool,
+    table: ColumnWidget<D>,
+}
+
+impl<D: AppDatabase + Send + Sync> FindWidget<D> {
+    /// Returns a widget which searches the selected column, or None if the selected column
+    /// can not be searched.
+    pub(crate) fn new(state: &UIState<D>) -> Option<Self> {
+        let column = ColumnIdentifier::from(
+            state
+                .table_view
+                .selected_cols()
+                .get(state.selected_column)?,
+        );
+        if !state.table_view.is_searchable(&column) {
+            return None;
+        }
+        Some(FindWidget {
+            input: CommandString::new(),
+            column,
+            applied: String::new(),
+            scoped: false,
+            table: ColumnWidget {
+                database: PhantomData,
+            },
+        })
+    }
+
+    fn search(&self) -> Search {
+        Search {
+            mode: SearchMode::ExactSubstring,
+            column: self.column.clone(),
+            search: self.applied.clone(),
+        }
+    }
+
+    /// Replaces the filter with one for the current input. Nothing is done if the input has not
+    /// changed since the filter was last replaced, so that moving the cursor does not query the
+    /// database again.
+    async fn rebind(&mut self, state: &mut UIState<D>) -> Result<(), TuiError<D::Error>> {
+        let query = self.input.to_string();
+        if query == self.applied {
+            return Ok(());
+        }
+        if self.scoped {
+            state.book_view.pop_scope();
+        }
+        self.applied = query;
+        self.scoped = !self.applied.is_empty();
+        if self.scoped {
+            state.book_view.push_temp_scope(&[self.search()]).await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<'b, D: AppDatabase + Send + Sync, B: Backend> Widget<D, B> for FindWidget<D> {
+    async fn prepare_render(&mut self, state: &mut UIState<D>, chunk: Rect) {
+        let chunks = EditLayout {}.layout(chunk);
+        Widget::<D, B>::prepare_render(&mut self.table, state, chunks[1]).await;
+    }
+
+    fn render_into_frame(&self, f: &mut Frame<B>, state: &UIState<D>, chunk: Rect) {
+        let chunks = EditLayout {}.layout(chunk);
+        let styles = StyleRules::default()
+            .cursor_fg(Color::Black)
+            .cursor_bg(Color::White)
+            .add_cursor_modifier(Modifier::SLOW_BLINK)
+            .selected_fg(Color::White)
+            .selected_bg(Color::Blue);
+        let mut text = char_chunks_to_styled_text(self.input.char_chunks(), styles);
+        text.0.insert(
+            0,
+            Span::styled(
+                format!("Find in {}: ", self.column.clone().into_string()),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+        );
+        f.render_widget(Paragraph::new(text), chunks[0]);
+        Widget::<D, B>::render_into_frame(&self.table, f, state, chunks[1]);
+    }
+
+    async fn handle_input(
+        &mut self,
+        event: Event,
+        state: &mut UIState<D>,
+        app: &mut AppChannel<D>,
+    ) -> Result<ApplicationTask, TuiError<D::Error>> {
+        let event = match event {
+            Event::Key(event) => event,
+            event => {
+                return Widget::<D, B>::handle_input(&mut self.table, event, state, app).await;
+            }
+        };
+        match event.code {
+            KeyCode::Enter => {
+                // The filter is kept as though it was entered with :f.
+                if self.scoped {
+                    state.book_view.pop_scope();
+                    state.book_view.push_scope(&[self.search()]).await?;
+                }
+                return Ok(ApplicationTask::SwitchView(AppView::Columns));
+            }
+            KeyCode::Esc => {
+                if self.scoped {
+                    state.book_view.pop_scope();
+                }
+                return Ok(ApplicationTask::SwitchView(AppView::Columns));
+            }
+            KeyCode::Char(c) => {
+                if event.modifiers == KeyModifiers::CONTROL {
+                    match c {
+                        'a' => self.input.select_all(),
+                        'd' => self.input.deselect(),
+                        _ => return Ok(ApplicationTask::DoNothing),
+                    }
+                } else {
+                    self.input.push(c);
+                }
+            }
+            KeyCode::Backspace => self.input.backspace(),
+            KeyCode::Delete => self.input.del(),
+            KeyCode::Left => {
+                if event.modifiers.intersects(KeyModifiers::SHIFT) {
+                    self.input.key_shift_left();
+                } else {
+                    self.input.key_left();
+                }
+            }
+            KeyCode::Right => {
+                if event.modifiers.intersects(KeyModifiers::SHIFT) {
+                    self.input.key_shift_right();
+                } else {
+                    self.input.key_right();
+                }
+            }
+            KeyCode::Home => self.input.key_up(),
+            KeyCode::End => self.input.key_down(),
+            // The table can still be navigated while typing.
+            KeyCode::Up | KeyCode::Down | KeyCode::PageUp | KeyCode::PageDown => {
+                return Widget::<D, B>::handle_input(
+                    &mut self.table,
+                    Event::Key(event),
+                    state,
+                    app,
+                )
+                .await;
+            }
+            _ => return Ok(ApplicationTask::DoNothing),
+        }
+        self.rebind(state).await?;
+        Ok(ApplicationTask::UpdateUI)
+    }
+}
@@ -3,8 +3,11 @@ mod border_widget;
 mod box_widget;
 mod column_widget;
 mod command_widget;
+mod diff_widget;
 mod edit_widget;
+mod find_widget;
 mod help_widget;
+mod stats_widget;
 
 use crossterm::event::Event;
 
@@ -25,12 +28,14 @@ pub use border_widget::BorderWidget;
 pub use box_widget::WidgetBox;
 pub use column_widget::ColumnWidget;
 pub use command_widget::CommandWidget;
+pub use diff_widget::DiffWidget;
 pub use edit_widget::EditWidget;
+pub use find_widget::FindWidget;
 pub use help_widget::HelpWidget;
+pub use stats_widget::StatsWidget;
 
 use async_trait::async_trait;
 
-// TODO: Add Find widget that does live searching as user types (but doesn't update if match isn't being changed).
 impl TuiStyle for InterfaceStyle {
     fn edit_style(&self) -> Style {
         Style::default()
@@ -0,0 +1,212 @@
+use std::marker::PhantomData;
+
+use crossterm::event::{Event, KeyCode, MouseEventKind};
+use tui::backend::Backend;
+use tui::layout::{Constraint, Direction, Layout, Rect};
+use tui::style::{Modifier, Style};
+use tui::text::Text;
+use tui::widgets::{Cell, Paragraph, Row, Table};
+use tui::Frame;
+
+use bookworm_app::app::AppChannel;
+use bookworm_database::{AppDatabase, LibraryStats};
+
+use crate::ui::utils::{format_size, TuiStyle};
+use crate::ui::widgets::Widget;
+use crate::{AppView, ApplicationTask, TuiError, UIState};
+
+/// The number of lines used by the summary, including its header.
+const SUMMARY_HEIGHT: u16 = 9;
+
+/// Shows a summary of the library - the totals and missing metadata first, followed by the
+/// number of books by each author, in each series and in each language.
+pub struct StatsWidget<D> {
+    stats: LibraryStats,
+    offset: usize,
+    height: usize,
+    database: PhantomData<fn(D)>,
+}
+
+impl<D> StatsWidget<D> {
+    pub(crate) fn new(stats: LibraryStats) -> Self {
+        StatsWidget {
+            stats,
+            offset: 0,
+            height: 0,
+            database: PhantomData,
+        }
+    }
+
+    /// Returns the length of the longest of the author, series and language lists.
+    fn len(&self) -> usize {
+        self.stats
+            .authors
+            .len()
+            .max(self.stats.series.len())
+            .max(self.stats.languages.len())
+    }
+
+    fn scroll_up(&mut self, scroll: usize) {
+        self.offset = self.offset.saturating_sub(scroll);
+    }
+
+    fn scroll_down(&mut self, scroll: usize) {
+        let max_offset = self.len().saturating_sub(self.height);
+        self.offset = (self.offset + scroll).min(max_offset);
+    }
+
+    fn counts_table<'a>(&self, name: &'a str, counts: &'a [(String, usize)]) -> Table<'a> {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let rows = counts.iter().skip(self.offset).map(|(value, count)| {
+            Row::new(vec![
+                Cell::from(value.as_str()),
+                Cell::from(count.to_string()),
+            ])
+        });
+        Table::new(rows)
+            .header(Row::new(vec![name, "Books"]).style(bold))
+            .column_spacing(1)
+    }
+}
+
+use async_trait::async_trait;
+
+#[async_trait]
+impl<'b, D: AppDatabase + Send + Sync, B: Backend> Widget<D, B> for StatsWidget<D> {
+    async fn prepare_render(&mut self, _state: &mut UIState<D>, chunk: Rect) {
+        // The summary, a blank line, the header of each list, and the footer.
+        self.height = usize::from(chunk.height.saturating_sub(SUMMARY_HEIGHT + 3));
+        self.scroll_down(0);
+    }
+
+    fn render_into_frame(&self, f: &mut Frame<B>, state: &UIState<D>, chunk: Rect) {
+        let vchunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(SUMMARY_HEIGHT + 1),
+                Constraint::Length(chunk.height.saturating_sub(SUMMARY_HEIGHT + 2)),
+                Constraint::Length(1),
+            ])
+            .split(chunk);
+
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let stats = &self.stats;
+        let missing = |count: usize| {
+            let style = if count == 0 {
+                Style::default()
+            } else {
+                state.style.select_style()
+            };
+            Cell::from(count.to_string()).style(style)
+        };
+        let summary = vec![
+            Row::new(vec![
+                Cell::from("Total size"),
+                Cell::from(format_size(stats.total_size, state.table_view.size_units())),
+            ]),
+            Row::new(vec![
+                Cell::from("Books"),
+                Cell::from(stats.books.to_string()),
+            ]),
+            Row::new(vec![
+                Cell::from("Variants"),
+                Cell::from(stats.variants.to_string()),
+            ]),
+            Row::new(vec![
+                Cell::from("Without a cover"),
+                missing(stats.missing_cover),
+            ]),
+            Row::new(vec![
+                Cell::from("Without a title"),
+                missing(stats.missing_title),
+            ]),
+            Row::new(vec![
+                Cell::from("Without authors"),
+                missing(stats.missing_authors),
+            ]),
+            Row::new(vec![
+                Cell::from("Without a description"),
+                missing(stats.missing_description),
+            ]),
+            Row::new(vec![
+                Cell::from("Authors / series / languages"),
+                Cell::from(format!(
+                    "{} / {} / {}",
+                    stats.authors.len(),
+                    stats.series.len(),
+                    stats.languages.len()
+                )),
+            ]),
+        ];
+        let summary_widths = [Constraint::Length(30), Constraint::Min(10)];
+        let summary = Table::new(summary)
+            .header(Row::new(vec!["Library", ""]).style(bold))
+            .widths(&summary_widths)
+            .column_spacing(1);
+        f.render_widget(summary, vchunks[0]);
+
+        let hchunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage(40),
+                Constraint::Percentage(40),
+                Constraint::Percentage(20),
+            ])
+            .split(vchunks[1]);
+        let widths = [Constraint::Percentage(80), Constraint::Percentage(20)];
+        let lists = [
+            ("Author", &stats.authors),
+            ("Series", &stats.series),
+            ("Language", &stats.languages),
+        ];
+        for ((name, counts), &chunk) in lists.iter().zip(hchunks.iter()) {
+            f.render_widget(self.counts_table(name, counts).widths(&widths), chunk);
+        }
+
+        let text = Text::styled("Press ESC to return", bold);
+        f.render_widget(Paragraph::new(text), vchunks[2])
+    }
+
+    async fn handle_input(
+        &mut self,
+        event: Event,
+        state: &mut UIState<D>,
+        _app: &mut AppChannel<D>,
+    ) -> Result<ApplicationTask, TuiError<D::Error>> {
+        match event {
+            Event::Resize(_, _) => return Ok(ApplicationTask::UpdateUI),
+            Event::Mouse(m) => match m.kind {
+                MouseEventKind::ScrollDown => {
+                    let scroll = state.nav_settings.scroll;
+                    if state.nav_settings.inverted {
+                        self.scroll_up(scroll)
+                    } else {
+                        self.scroll_down(scroll)
+                    };
+                }
+                MouseEventKind::ScrollUp => {
+                    let scroll = state.nav_settings.scroll;
+                    if state.nav_settings.inverted {
+                        self.scroll_down(scroll)
+                    } else {
+                        self.scroll_up(scroll)
+                    };
+                }
+                _ => {
+                    return Ok(ApplicationTask::DoNothing);
+                }
+            },
+            Event::Key(event) => match event.code {
+                KeyCode::Esc => return Ok(ApplicationTask::SwitchView(AppView::Columns)),
+                KeyCode::Up => self.scroll_up(1),
+                KeyCode::Down => self.scroll_down(1),
+                KeyCode::PageUp => self.scroll_up(self.height),
+                KeyCode::PageDown => self.scroll_down(self.height),
+                KeyCode::Home => self.offset = 0,
+                KeyCode::End => self.scroll_down(self.len()),
+                _ => return Ok(ApplicationTask::DoNothing),
+            },
+        }
+        Ok(ApplicationTask::UpdateUI)
+    }
+}